@@ -0,0 +1,38 @@
+//! Point lookups by id on a 5k-point `DataBatch`.
+//!
+//! Run with `cargo bench --bench data_batch`.
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use igw::{DataBatch, DataPoint};
+
+const POINTS: u32 = 5_000;
+
+fn bench_lookup(c: &mut Criterion) {
+    let batch: DataBatch = (0..POINTS)
+        .map(|id| DataPoint::new(id, f64::from(id)))
+        .collect();
+
+    let mut group = c.benchmark_group("data_batch_lookup_5k");
+    group.throughput(Throughput::Elements(u64::from(POINTS)));
+
+    group.bench_function("get", |b| {
+        b.iter(|| {
+            for id in 0..POINTS {
+                black_box(batch.get(black_box(id)));
+            }
+        })
+    });
+    group.bench_function("linear_find", |b| {
+        b.iter(|| {
+            for id in 0..POINTS {
+                black_box(batch.iter().find(|p| p.id == black_box(id)));
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_lookup);
+criterion_main!(benches);
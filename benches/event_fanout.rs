@@ -0,0 +1,71 @@
+//! Fan-out of data updates to several subscribers: 10 channels publishing
+//! 1k-point batches to 3 subscribers each.
+//!
+//! `owned_batch` broadcasts plain `DataBatch`es, so every receiver gets a
+//! deep copy (how `DataEvent::DataUpdate` used to behave); `shared_batch`
+//! goes through the `EventBus` with `Arc<DataBatch>`.
+//!
+//! Run with `cargo bench --bench event_fanout`.
+
+use std::hint::black_box;
+use std::sync::Arc;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use igw::core::event::EventBus;
+use igw::core::traits::DataEvent;
+use igw::{DataBatch, DataPoint};
+use tokio::sync::broadcast;
+
+const CHANNELS: u32 = 10;
+const POINTS: u32 = 1_000;
+const SUBSCRIBERS: usize = 3;
+
+fn batch(channel: u32) -> DataBatch {
+    (0..POINTS)
+        .map(|id| DataPoint::new(id, f64::from(channel * POINTS + id)))
+        .collect()
+}
+
+fn bench_fanout(c: &mut Criterion) {
+    let batches: Vec<DataBatch> = (0..CHANNELS).map(batch).collect();
+
+    let mut group = c.benchmark_group("event_fanout_10x1k");
+    group.throughput(Throughput::Elements(u64::from(CHANNELS * POINTS)));
+
+    group.bench_function("owned_batch", |b| {
+        let (tx, _) = broadcast::channel::<DataBatch>(64);
+        let mut receivers: Vec<_> = (0..SUBSCRIBERS).map(|_| tx.subscribe()).collect();
+        b.iter(|| {
+            for batch in &batches {
+                tx.send(batch.clone()).unwrap();
+            }
+            for rx in &mut receivers {
+                while let Ok(batch) = rx.try_recv() {
+                    black_box(batch.len());
+                }
+            }
+        })
+    });
+
+    group.bench_function("shared_batch", |b| {
+        let bus = EventBus::new(64);
+        let mut receivers: Vec<_> = (0..SUBSCRIBERS).map(|_| bus.subscribe()).collect();
+        b.iter(|| {
+            for batch in &batches {
+                bus.publish(DataEvent::DataUpdate(Arc::new(batch.clone())));
+            }
+            for rx in &mut receivers {
+                while let Some(event) = rx.try_recv() {
+                    if let DataEvent::DataUpdate(batch) = event {
+                        black_box(batch.len());
+                    }
+                }
+            }
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_fanout);
+criterion_main!(benches);
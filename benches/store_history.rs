@@ -0,0 +1,55 @@
+//! Write throughput of `MemoryStore` with and without point history.
+//!
+//! Run with `cargo bench --bench store_history`.
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use igw::store::{DataStore, HistoryConfig, MemoryStore};
+use igw::{DataBatch, DataPoint};
+
+const POINTS: u32 = 10_000;
+
+fn batch(round: u32) -> DataBatch {
+    (0..POINTS)
+        .map(|id| DataPoint::new(id, f64::from(round + id)))
+        .collect()
+}
+
+fn bench_write(c: &mut Criterion) {
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+
+    let mut group = c.benchmark_group("memory_store_write_10k");
+    group.throughput(Throughput::Elements(u64::from(POINTS)));
+
+    for (name, history) in [
+        ("latest_only", None),
+        ("history_100", Some(HistoryConfig::with_capacity(100))),
+    ] {
+        let store = match history {
+            Some(config) => MemoryStore::new().with_history(config),
+            None => MemoryStore::new(),
+        };
+        // Fill the ring buffers so the measurement includes eviction.
+        rt.block_on(async {
+            for round in 0..100 {
+                store.write_batch(1, &batch(round)).await.unwrap();
+            }
+        });
+
+        group.bench_function(name, |b| {
+            b.iter_batched(
+                || batch(7),
+                |batch| rt.block_on(store.write_batch(1, black_box(&batch))),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_write);
+criterion_main!(benches);
@@ -0,0 +1,77 @@
+//! Reads of a 20k-point channel by 4 concurrent readers polling at 10 Hz,
+//! while a device updates a 500-point block every tick.
+//!
+//! One iteration is one 100 ms tick: a write, then one read per reader.
+//! `read_all` copies every point for every reader; `snapshot` shares the
+//! stored values, and the write copies only the buckets it touched that a
+//! reader still holds.
+//!
+//! Run with `cargo bench --bench store_snapshot`.
+
+use std::hint::black_box;
+use std::sync::Arc;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use igw::store::{DataStore, MemoryStore};
+use igw::{DataBatch, DataPoint};
+
+const POINTS: u32 = 20_000;
+const BLOCK: u32 = 500;
+const READERS: usize = 4;
+
+fn block(tick: u32) -> DataBatch {
+    let start = (tick * BLOCK) % POINTS;
+    (start..start + BLOCK)
+        .map(|id| DataPoint::new(id, f64::from(tick)))
+        .collect()
+}
+
+fn bench_readers(c: &mut Criterion) {
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(READERS)
+        .build()
+        .unwrap();
+
+    let mut group = c.benchmark_group("store_read_20k_4_readers");
+    group.throughput(Throughput::Elements(u64::from(POINTS) * READERS as u64));
+
+    for name in ["read_all", "snapshot"] {
+        let store = Arc::new(MemoryStore::new());
+        let all: DataBatch = (0..POINTS).map(|id| DataPoint::new(id, 0.0)).collect();
+        rt.block_on(store.write_batch(1, &all)).unwrap();
+
+        let mut tick = 0;
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                tick += 1;
+                let batch = block(tick);
+                rt.block_on(async {
+                    // A reader still holding the previous tick's values
+                    let previous = store.snapshot(1).await.unwrap();
+                    store.write_batch(1, &batch).await.unwrap();
+                    drop(previous);
+                    let readers: Vec<_> = (0..READERS)
+                        .map(|_| {
+                            let store = Arc::clone(&store);
+                            tokio::spawn(async move {
+                                if name == "read_all" {
+                                    black_box(store.read_all(1).await.unwrap().len())
+                                } else {
+                                    black_box(store.snapshot(1).await.unwrap().len())
+                                }
+                            })
+                        })
+                        .collect();
+                    for reader in readers {
+                        reader.await.unwrap();
+                    }
+                })
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_readers);
+criterion_main!(benches);
@@ -72,7 +72,7 @@ pub enum GatewayEvent {
     },
     DataUpdate {
         channel_id: u32,
-        batch: DataBatch,
+        batch: Arc<DataBatch>,
     },
     PollResult {
         channel_id: u32,
@@ -300,7 +300,7 @@ impl Gateway {
                         if !result.data.is_empty() {
                             let _ = event_tx.send(GatewayEvent::DataUpdate {
                                 channel_id,
-                                batch: result.data.clone(),
+                                batch: Arc::new(result.data.clone()),
                             });
                         }
 
@@ -352,23 +352,23 @@ impl Gateway {
                     _ = shutdown_rx.changed() => break,
                     event = data_rx.recv() => {
                         match event {
-                            Ok(DataEvent::DataUpdate(batch)) => {
+                            Some(DataEvent::DataUpdate(batch)) => {
                                 let _ = event_tx.send(GatewayEvent::DataUpdate {
                                     channel_id,
                                     batch,
                                 });
                             }
-                            Ok(DataEvent::Error(e)) => {
+                            // Includes lag notifications from the channel's event bus
+                            Some(DataEvent::Error(e)) => {
                                 let _ = event_tx.send(GatewayEvent::Error {
                                     channel_id,
                                     error: e,
                                 });
                             }
-                            Ok(DataEvent::ConnectionChanged(_)) | Ok(DataEvent::Heartbeat) => {}
-                            Err(broadcast::error::RecvError::Lagged(n)) => {
-                                eprintln!("Warning: Channel {} event receiver lagged by {}", channel_id, n);
-                            }
-                            Err(broadcast::error::RecvError::Closed) => break,
+                            Some(DataEvent::ConnectionChanged(_))
+                            | Some(DataEvent::Heartbeat)
+                            | Some(DataEvent::CommandUpdate(_)) => {}
+                            None => break,
                         }
                     }
                 }
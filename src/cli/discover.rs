@@ -0,0 +1,403 @@
+//! `igw discover`: draft a channel configuration from a live device.
+//!
+//! A discovery finds the points a device answers on and writes them as a
+//! `[[channels]]` block with `[[channels.points]]` entries, to be renamed
+//! and trimmed by the engineer:
+//!
+//! ```text
+//! igw discover modbus 192.168.1.10:502 --slave 1 --range 0-500 > device.toml
+//! ```
+//!
+//! Every discovery path fills a [`DiscoveredChannel`] and leaves the TOML to
+//! [`DiscoveredChannel::to_toml()`], so all of them emit the same layout.
+//!
+//! For Modbus, the holding and input registers of the range are read in
+//! blocks through the pacing [`Prober`]; a block the device refuses is
+//! split in halves until the answering registers are found. The formats
+//! are guesses from the values read: a run of printable characters becomes
+//! a `string` point, a register pair holding a plausible Float32 (in ABCD
+//! or CDAB order) a `float32` point, anything else a `uint16` point. Each
+//! point carries the value read as a comment so the guesses can be checked
+//! against the device's display.
+
+use std::fmt::Write as _;
+use std::ops::RangeInclusive;
+
+use igw::core::data::PointId;
+
+use crate::probe::{Prober, Reply, ILLEGAL_FUNCTION};
+use crate::scan::GATEWAY_EXCEPTIONS;
+
+/// Most registers read by one request (FC03/FC04 limit).
+const MAX_BLOCK: u16 = 125;
+
+/// Registers of a `string` point (16 characters).
+const STRING_REGISTERS: usize = 8;
+
+/// A point found on a device, written as one `[[channels.points]]` entry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiscoveredPoint {
+    /// Placeholder name.
+    pub name: String,
+
+    /// Address in the protocol's shorthand.
+    pub address: String,
+
+    /// Remark written after the address, e.g. the value read.
+    pub comment: Option<String>,
+}
+
+/// A device found by a discovery, written as one `[[channels]]` block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiscoveredChannel {
+    /// Placeholder channel name.
+    pub name: String,
+
+    /// Protocol name, as in `ChannelConfig::protocol`.
+    pub protocol: String,
+
+    /// Channel parameters, in output order.
+    pub parameters: Vec<(String, toml::Value)>,
+
+    /// Points, in output order.
+    pub points: Vec<DiscoveredPoint>,
+}
+
+impl DiscoveredChannel {
+    /// The channel as a `[[channels]]` block with id `id`, its points
+    /// numbered from `first_point_id`.
+    pub fn to_toml(&self, id: u32, first_point_id: PointId) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "[[channels]]");
+        let _ = writeln!(out, "id = {}", id);
+        let _ = writeln!(out, "name = {}", quote(&self.name));
+        let _ = writeln!(out, "protocol = {}", quote(&self.protocol));
+        let _ = writeln!(out, "enabled = true");
+
+        if !self.parameters.is_empty() {
+            let _ = writeln!(out, "\n[channels.parameters]");
+            for (key, value) in &self.parameters {
+                let _ = writeln!(out, "{} = {}", key, value);
+            }
+        }
+
+        for (point_id, point) in (first_point_id..).zip(&self.points) {
+            let _ = writeln!(out, "\n[[channels.points]]");
+            let _ = writeln!(out, "id = {}", point_id);
+            let _ = writeln!(out, "name = {}", quote(&point.name));
+            let _ = write!(out, "address = {}", quote(&point.address));
+            match &point.comment {
+                Some(comment) => {
+                    let _ = writeln!(out, "  # {}", comment);
+                }
+                None => out.push('\n'),
+            }
+        }
+        out
+    }
+}
+
+fn quote(s: &str) -> String {
+    toml::Value::String(s.to_string()).to_string()
+}
+
+/// Parse a register range like `0-500` or a single register.
+pub fn parse_range(range: &str) -> Result<RangeInclusive<u16>, String> {
+    let parse = |s: &str| {
+        s.trim()
+            .parse::<u16>()
+            .map_err(|_| format!("invalid register '{}'", s.trim()))
+    };
+    let (from, to) = match range.split_once('-') {
+        Some((from, to)) => (parse(from)?, parse(to)?),
+        None => {
+            let register = parse(range)?;
+            (register, register)
+        }
+    };
+    if from > to {
+        return Err(format!("invalid register range '{}'", range));
+    }
+    Ok(from..=to)
+}
+
+/// Registers of `range` answering on `function_code` (3 or 4) at `unit`,
+/// with the values read, in register order.
+///
+/// An area the device refuses with "illegal function" on the first block
+/// is reported empty. Fails if the first request gets no answer or the
+/// device sits behind a dead gateway path.
+pub async fn sweep_modbus(
+    prober: &mut Prober,
+    unit: u8,
+    function_code: u8,
+    range: RangeInclusive<u16>,
+) -> Result<Vec<(u16, u16)>, String> {
+    // Pending blocks as (first register, count), next block last
+    let mut blocks: Vec<(u16, u16)> = Vec::new();
+    let (start, end) = (u32::from(*range.start()), u32::from(*range.end()));
+    let mut first = start;
+    while first <= end {
+        let count = (end - first + 1).min(u32::from(MAX_BLOCK));
+        blocks.push((first as u16, count as u16));
+        first += count;
+    }
+    blocks.reverse();
+
+    let mut found = Vec::new();
+    let mut first_request = true;
+    while let Some((register, count)) = blocks.pop() {
+        let reply = prober.read(unit, function_code, register, count).await;
+        let refused = match reply {
+            Ok(Reply::Data(data)) if data.len() == usize::from(count) * 2 => {
+                found.extend(
+                    (register..)
+                        .zip(data.chunks_exact(2))
+                        .map(|(r, word)| (r, u16::from_be_bytes([word[0], word[1]]))),
+                );
+                false
+            }
+            Ok(Reply::Exception(code)) if GATEWAY_EXCEPTIONS.contains(&code) => {
+                return Err(format!("gateway exception 0x{:02X}", code));
+            }
+            Ok(Reply::Exception(ILLEGAL_FUNCTION)) if first_request => return Ok(found),
+            Err(e) if first_request => return Err(e),
+            // Exceptions, short replies and lost requests
+            _ => true,
+        };
+        first_request = false;
+        if refused && count > 1 {
+            let half = count / 2;
+            blocks.push((register + half, count - half));
+            blocks.push((register, half));
+        }
+    }
+    Ok(found)
+}
+
+/// Points guessed from the `registers` read with `function_code` (3 or 4)
+/// at `unit`, in register order.
+pub fn guess_modbus_points(
+    unit: u8,
+    function_code: u8,
+    registers: &[(u16, u16)],
+) -> Vec<DiscoveredPoint> {
+    let (area, prefix) = match function_code {
+        4 => ("input", 'i'),
+        _ => ("holding", 'h'),
+    };
+    let point = |register: u16, format: &str, comment: String| DiscoveredPoint {
+        name: format!("{}_{}", area, register),
+        address: format!("{}:{}{}{}", unit, prefix, register, format),
+        comment: Some(comment),
+    };
+
+    let mut points = Vec::new();
+    let mut i = 0;
+    while i < registers.len() {
+        let (register, word) = registers[i];
+        // Values of the registers following without a gap
+        let run: Vec<u16> = registers[i..]
+            .iter()
+            .zip(register..)
+            .take_while(|((r, _), expected)| r == expected)
+            .map(|((_, value), _)| *value)
+            .collect();
+
+        if run.len() >= STRING_REGISTERS && is_text(&run[..STRING_REGISTERS]) {
+            let text = decode_text(&run[..STRING_REGISTERS]);
+            points.push(point(register, ":string", format!("read {:?}", text)));
+            i += STRING_REGISTERS;
+            continue;
+        }
+        if let [high, low, ..] = run[..] {
+            let abcd = f32::from_bits(u32::from(high) << 16 | u32::from(low));
+            let cdab = f32::from_bits(u32::from(low) << 16 | u32::from(high));
+            let guess = if plausible_float(abcd) {
+                Some((":float32", abcd))
+            } else if plausible_float(cdab) {
+                Some((":float32:cdab", cdab))
+            } else {
+                None
+            };
+            if let Some((format, value)) = guess {
+                points.push(point(register, format, format!("read {}", value)));
+                i += 2;
+                continue;
+            }
+        }
+        // Small negative numbers are more likely than large counters
+        if word >= 0xFF00 {
+            points.push(point(register, ":int16", format!("read {}", word as i16)));
+        } else {
+            points.push(point(register, "", format!("read {}", word)));
+        }
+        i += 1;
+    }
+    points
+}
+
+/// Registers holding printable ASCII characters, at least half of them
+/// before the NUL padding.
+fn is_text(words: &[u16]) -> bool {
+    let bytes: Vec<u8> = words.iter().flat_map(|w| w.to_be_bytes()).collect();
+    let text = bytes.iter().take_while(|b| **b != 0).count();
+    text >= bytes.len() / 2
+        && bytes[..text].iter().all(|b| (0x20..=0x7E).contains(b))
+        && bytes[text..].iter().all(|b| *b == 0)
+}
+
+fn decode_text(words: &[u16]) -> String {
+    let bytes: Vec<u8> = words.iter().flat_map(|w| w.to_be_bytes()).collect();
+    String::from_utf8_lossy(&bytes)
+        .trim_end_matches(['\0', ' '])
+        .to_string()
+}
+
+/// A float the size of a typical measurement; register pairs holding two
+/// small integers read as denormals or tiny values and are rejected.
+fn plausible_float(value: f32) -> bool {
+    value.is_normal() && (1e-3..=1e7).contains(&value.abs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    use crate::probe::ILLEGAL_DATA_ADDRESS;
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("0-500").unwrap(), 0..=500);
+        assert_eq!(parse_range(" 40 ").unwrap(), 40..=40);
+        assert!(parse_range("500-0").is_err());
+        assert!(parse_range("0-70000").is_err());
+    }
+
+    #[test]
+    fn test_guess_modbus_points() {
+        let mut registers = vec![(0, 230), (1, 0xFFFE)];
+        // 12.5 as ABCD and as CDAB
+        registers.extend([(10, 0x4148), (11, 0), (20, 0), (21, 0x4148)]);
+        // Two counters that happen to sit side by side
+        registers.extend([(30, 100), (31, 200)]);
+        // "SN-2024-0042" padded with NULs
+        let text = b"SN-2024-0042\0\0\0\0";
+        registers.extend(
+            (40..)
+                .zip(text.chunks_exact(2))
+                .map(|(r, c)| (r, u16::from_be_bytes([c[0], c[1]]))),
+        );
+
+        let points = guess_modbus_points(1, 3, &registers);
+        let addresses: Vec<&str> = points.iter().map(|p| p.address.as_str()).collect();
+        assert_eq!(
+            addresses,
+            vec![
+                "1:h0",
+                "1:h1:int16",
+                "1:h10:float32",
+                "1:h20:float32:cdab",
+                "1:h30",
+                "1:h31",
+                "1:h40:string",
+            ]
+        );
+        assert_eq!(points[1].comment.as_deref(), Some("read -2"));
+        assert_eq!(points[3].comment.as_deref(), Some("read 12.5"));
+        assert_eq!(points[6].comment.as_deref(), Some("read \"SN-2024-0042\""));
+        assert_eq!(points[6].name, "holding_40");
+
+        // Every guess is an address the gateway accepts
+        for address in addresses {
+            igw::gateway::parse_address("modbus", address).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_to_toml() {
+        let channel = DiscoveredChannel {
+            name: "device".into(),
+            protocol: "modbus".into(),
+            parameters: vec![
+                ("host".into(), "10.0.0.7".into()),
+                ("port".into(), toml::Value::Integer(502)),
+            ],
+            points: guess_modbus_points(1, 4, &[(100, 0x4148), (101, 0), (102, 7)]),
+        };
+        let text = channel.to_toml(3, 301);
+        assert!(text.contains("address = \"1:i100:float32\"  # read 12.5\n"));
+
+        let table: toml::Table = toml::from_str(&text).unwrap();
+        let channels: Vec<igw::gateway::ChannelConfig> =
+            table["channels"].clone().try_into().unwrap();
+        let channel = &channels[0];
+        assert_eq!((channel.id, channel.protocol.as_str()), (3, "modbus"));
+        assert_eq!(channel.parameters["port"], 502);
+        let ids: Vec<PointId> = channel.points.iter().map(|p| p.id).collect();
+        assert_eq!(ids, vec![301, 302]);
+        assert_eq!(channel.points[1].name, "input_102");
+    }
+
+    /// Device with holding registers 5-9 and 200-203, no input registers.
+    async fn fake_device() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = [0u8; 12];
+            while stream.read_exact(&mut request).await.is_ok() {
+                let (unit, fc) = (request[6], request[7]);
+                let register = u16::from_be_bytes([request[8], request[9]]);
+                let count = u16::from_be_bytes([request[10], request[11]]);
+                let last = register + count - 1;
+                let answers =
+                    (5..=9).contains(&register) && last <= 9 || register >= 200 && last <= 203;
+                let pdu: Vec<u8> = match fc {
+                    3 if answers => {
+                        let mut pdu = vec![fc, count as u8 * 2];
+                        for r in register..=last {
+                            pdu.extend_from_slice(&r.to_be_bytes());
+                        }
+                        pdu
+                    }
+                    3 => vec![fc | 0x80, ILLEGAL_DATA_ADDRESS],
+                    _ => vec![fc | 0x80, ILLEGAL_FUNCTION],
+                };
+                let mut reply = vec![request[0], request[1], 0, 0, 0, pdu.len() as u8 + 1, unit];
+                reply.extend_from_slice(&pdu);
+                stream.write_all(&reply).await.unwrap();
+            }
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn test_sweep_modbus() {
+        let addr = fake_device().await;
+        let mut prober = Prober::new(addr.to_string(), Duration::from_millis(500), Duration::ZERO);
+
+        let holding = sweep_modbus(&mut prober, 1, 3, 0..=300).await.unwrap();
+        let registers: Vec<u16> = holding.iter().map(|(r, _)| *r).collect();
+        assert_eq!(registers, vec![5, 6, 7, 8, 9, 200, 201, 202, 203]);
+        assert_eq!(holding[0], (5, 5));
+
+        assert!(sweep_modbus(&mut prober, 1, 4, 0..=300)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sweep_modbus_unreachable() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        let mut prober = Prober::new(addr.to_string(), Duration::from_millis(200), Duration::ZERO);
+        assert!(sweep_modbus(&mut prober, 1, 3, 0..=10).await.is_err());
+    }
+}
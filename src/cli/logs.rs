@@ -0,0 +1,33 @@
+//! Log output of the CLI.
+//!
+//! Events go to stderr. `RUST_LOG` selects them when set (e.g.
+//! `RUST_LOG=warn,igw::protocols::modbus=debug`); otherwise the number of
+//! `-v` flags does. Gateways running with `jsonl_output` log one JSON object
+//! per line instead, so a log collector can take both streams.
+
+use tracing_subscriber::EnvFilter;
+
+/// Filter used when `RUST_LOG` is not set.
+fn default_filter(verbose: u8) -> &'static str {
+    match verbose {
+        0 => "warn",
+        1 => "info",
+        2 => "info,igw=debug",
+        _ => "debug,igw=trace",
+    }
+}
+
+/// Install the global subscriber.
+pub fn init(verbose: u8, json: bool) {
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new(default_filter(verbose)));
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr);
+    // Only fails if a subscriber is already installed
+    let _ = if json {
+        builder.json().try_init()
+    } else {
+        builder.try_init()
+    };
+}
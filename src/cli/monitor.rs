@@ -0,0 +1,494 @@
+//! `igw monitor`: live terminal dashboard.
+//!
+//! Runs the gateway like `igw run` and shows a table of channels (state,
+//! read/error counters, last error) above a scrollable view of point values
+//! colored by quality, titled with their counts per value kind and quality. Values come from the store's change notifications
+//! ([`DataStore::watch()`]), so only changed points are redrawn from new data;
+//! channel diagnostics are refreshed once per second.
+//!
+//! | Key | Action |
+//! |-----|--------|
+//! | `↑`/`↓` | Select channel |
+//! | `f` | Show only the selected channel's points (again: all) |
+//! | `r` | Poll the selected channel now |
+//! | `p`/`space` | Pause/resume the display |
+//! | `PgUp`/`PgDn` | Scroll points |
+//! | `q`/`Esc` | Quit |
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::Arc;
+use std::time::Duration;
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::widgets::{Block, Paragraph, Row, Table, TableState};
+use ratatui::Frame;
+use tokio::sync::mpsc;
+
+use igw::core::data::{BatchSummary, DataBatch, DataPoint, PointId};
+use igw::core::quality::Quality;
+use igw::core::traits::DataEvent;
+use igw::gateway::{ChannelDiagnostics, GatewayConfig, GatewayRuntime};
+use igw::store::{DataStore, MemoryStore};
+
+use super::{format_value, CliResult};
+
+/// Run the gateway with the dashboard until the user quits.
+pub async fn run(config: GatewayConfig) -> CliResult {
+    let store = Arc::new(MemoryStore::new());
+    let mut gateway = GatewayRuntime::from_config(config, store.clone())?;
+    gateway.start().await?;
+
+    let mut app = App::new(gateway.config());
+    let updates = watch_channels(store, &gateway.channel_ids()).await?;
+    app.channels = gateway.diagnostics_snapshot().await;
+
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, &mut app, &gateway, updates).await;
+    ratatui::restore();
+
+    gateway.stop().await?;
+    result
+}
+
+/// Forward store changes of every channel to the UI.
+async fn watch_channels(
+    store: Arc<MemoryStore>,
+    channel_ids: &[u32],
+) -> CliResult<mpsc::Receiver<(u32, DataBatch)>> {
+    let (tx, rx) = mpsc::channel(1024);
+    for &channel_id in channel_ids {
+        let mut watch = store.watch(channel_id, &[])?;
+        let (store, tx) = (Arc::clone(&store), tx.clone());
+        tokio::spawn(async move {
+            while let Some(event) = watch.recv().await {
+                let batch = match event {
+                    DataEvent::DataUpdate(batch) => Arc::unwrap_or_clone(batch),
+                    // Lagged behind: resynchronize from the store
+                    DataEvent::Error(_) => match store.read_all(channel_id).await {
+                        Ok(batch) => batch,
+                        Err(_) => continue,
+                    },
+                    _ => continue,
+                };
+                if tx.send((channel_id, batch)).await.is_err() {
+                    break;
+                }
+            }
+        });
+    }
+    Ok(rx)
+}
+
+async fn event_loop(
+    terminal: &mut ratatui::DefaultTerminal,
+    app: &mut App,
+    gateway: &GatewayRuntime,
+    mut updates: mpsc::Receiver<(u32, DataBatch)>,
+) -> CliResult {
+    let mut redraw = tokio::time::interval(Duration::from_millis(100));
+    let mut refresh = tokio::time::interval(Duration::from_secs(1));
+
+    loop {
+        tokio::select! {
+            Some((channel_id, batch)) = updates.recv() => app.apply(channel_id, batch),
+            _ = refresh.tick() => app.channels = gateway.diagnostics_snapshot().await,
+            _ = redraw.tick() => {
+                while event::poll(Duration::ZERO)? {
+                    let Event::Key(key) = event::read()? else {
+                        continue;
+                    };
+                    match app.on_key(key) {
+                        Action::None => {}
+                        Action::Quit => return Ok(()),
+                        Action::Poll(channel_id) => {
+                            app.status = match gateway.poll_now(channel_id).await {
+                                Ok(result) => format!(
+                                    "Polled channel {}: {} value(s), {} failure(s)",
+                                    channel_id,
+                                    result.data.len(),
+                                    result.failures.len()
+                                ),
+                                Err(e) => format!("Poll of channel {} failed: {}", channel_id, e),
+                            };
+                        }
+                    }
+                }
+                terminal.draw(|frame| app.draw(frame))?;
+            }
+        }
+    }
+}
+
+/// Result of a key press.
+#[derive(Debug, PartialEq)]
+enum Action {
+    None,
+    Quit,
+    Poll(u32),
+}
+
+/// A configured point and its latest value.
+#[derive(Debug, Clone)]
+struct PointRow {
+    name: String,
+    unit: String,
+    value: Option<DataPoint>,
+}
+
+/// Dashboard state.
+struct App {
+    channels: Vec<ChannelDiagnostics>,
+    points: BTreeMap<(u32, PointId), PointRow>,
+    /// Points as they were when the display was paused.
+    frozen: Option<BTreeMap<(u32, PointId), PointRow>>,
+    selected: TableState,
+    filter: Option<u32>,
+    scroll: usize,
+    status: String,
+}
+
+impl App {
+    fn new(config: &GatewayConfig) -> Self {
+        let points = config
+            .enabled_channels()
+            .flat_map(|channel| {
+                channel.points.iter().filter(|p| p.enabled).map(|point| {
+                    let row = PointRow {
+                        name: point.display_name.clone().unwrap_or(point.name.clone()),
+                        unit: point.unit.clone().unwrap_or_default(),
+                        value: None,
+                    };
+                    ((channel.id, point.id), row)
+                })
+            })
+            .collect();
+
+        Self {
+            channels: Vec::new(),
+            points,
+            frozen: None,
+            selected: TableState::default().with_selected(Some(0)),
+            filter: None,
+            scroll: 0,
+            status: String::new(),
+        }
+    }
+
+    fn apply(&mut self, channel_id: u32, batch: DataBatch) {
+        for point in batch {
+            let row = self
+                .points
+                .entry((channel_id, point.id))
+                .or_insert_with(|| PointRow {
+                    name: String::new(),
+                    unit: String::new(),
+                    value: None,
+                });
+            row.value = Some(point);
+        }
+    }
+
+    fn selected_channel(&self) -> Option<u32> {
+        self.selected
+            .selected()
+            .and_then(|i| self.channels.get(i))
+            .map(|c| c.channel_id)
+    }
+
+    fn on_key(&mut self, key: KeyEvent) -> Action {
+        if key.kind != KeyEventKind::Press {
+            return Action::None;
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Action::Quit,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return Action::Quit
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.selected.select_previous(),
+            KeyCode::Down | KeyCode::Char('j') => {
+                let last = self.channels.len().saturating_sub(1);
+                let next = self.selected.selected().map_or(0, |i| (i + 1).min(last));
+                self.selected.select(Some(next));
+            }
+            KeyCode::Char('f') => {
+                self.filter = match self.filter {
+                    Some(_) => None,
+                    None => self.selected_channel(),
+                };
+                self.scroll = 0;
+            }
+            KeyCode::Char('p') | KeyCode::Char(' ') => {
+                self.frozen = match self.frozen {
+                    Some(_) => None,
+                    None => Some(self.points.clone()),
+                };
+            }
+            KeyCode::PageDown => self.scroll += 10,
+            KeyCode::PageUp => self.scroll = self.scroll.saturating_sub(10),
+            KeyCode::Char('r') => {
+                if let Some(channel_id) = self.selected_channel() {
+                    return Action::Poll(channel_id);
+                }
+            }
+            _ => {}
+        }
+        Action::None
+    }
+
+    /// Point rows to display, honouring pause and channel filter.
+    fn visible_points(&self) -> impl Iterator<Item = (&(u32, PointId), &PointRow)> {
+        let filter = self.filter;
+        self.frozen
+            .as_ref()
+            .unwrap_or(&self.points)
+            .iter()
+            .filter(move |((channel_id, _), _)| filter.is_none_or(|f| f == *channel_id))
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [channels_area, points_area, help_area] = Layout::vertical([
+            Constraint::Length(self.channels.len() as u16 + 3),
+            Constraint::Min(5),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let channel_rows = self.channels.iter().map(|c| {
+            let (state, reads, errors, last_error) = match &c.diagnostics {
+                Some(d) => (
+                    d.connection_state.to_string(),
+                    d.read_count.to_string(),
+                    d.error_count.to_string(),
+                    d.last_error.clone().unwrap_or_default(),
+                ),
+                None => (
+                    "-".into(),
+                    "-".into(),
+                    "-".into(),
+                    c.error.clone().unwrap_or_default(),
+                ),
+            };
+            let state = if c.enabled { state } else { "disabled".into() };
+            Row::new(vec![
+                c.channel_id.to_string(),
+                c.name.clone(),
+                state,
+                reads,
+                errors,
+                last_error,
+            ])
+        });
+        let channels = Table::new(
+            channel_rows,
+            [
+                Constraint::Length(6),
+                Constraint::Length(20),
+                Constraint::Length(14),
+                Constraint::Length(10),
+                Constraint::Length(8),
+                Constraint::Min(20),
+            ],
+        )
+        .header(header([
+            "ID",
+            "Name",
+            "State",
+            "Reads",
+            "Errors",
+            "Last error",
+        ]))
+        .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+        .block(Block::bordered().title(" Channels "));
+        frame.render_stateful_widget(channels, channels_area, &mut self.selected);
+
+        let visible = points_area.height.saturating_sub(3) as usize;
+        let total = self.visible_points().count();
+        self.scroll = self.scroll.min(total.saturating_sub(visible));
+        let point_rows = self.visible_points().skip(self.scroll).take(visible).map(
+            |(&(channel_id, point_id), row)| {
+                let (value, quality, time, style) = match &row.value {
+                    Some(dp) => (
+                        format_value(&dp.value),
+                        dp.quality.to_string(),
+                        dp.timestamp
+                            .with_timezone(&chrono::Local)
+                            .format("%H:%M:%S%.3f")
+                            .to_string(),
+                        Style::new().fg(quality_color(dp.quality)),
+                    ),
+                    None => ("-".into(), "-".into(), String::new(), Style::new()),
+                };
+                Row::new(vec![
+                    channel_id.to_string(),
+                    point_id.to_string(),
+                    row.name.clone(),
+                    value,
+                    row.unit.clone(),
+                    quality,
+                    time,
+                ])
+                .style(style)
+            },
+        );
+
+        let summary: BatchSummary = self
+            .visible_points()
+            .filter_map(|(_, row)| row.value.as_ref())
+            .collect();
+        let mut title = format!(" Points ({}) ", total);
+        if let Some(channel_id) = self.filter {
+            title = format!(" Points of channel {} ({}) ", channel_id, total);
+        }
+        if summary.total > 0 {
+            let _ = write!(title, "· {} ", summary);
+        }
+        if self.frozen.is_some() {
+            title.push_str("[PAUSED] ");
+        }
+        let points = Table::new(
+            point_rows,
+            [
+                Constraint::Length(8),
+                Constraint::Length(8),
+                Constraint::Min(16),
+                Constraint::Length(16),
+                Constraint::Length(8),
+                Constraint::Length(14),
+                Constraint::Length(12),
+            ],
+        )
+        .header(header([
+            "Channel", "Point", "Name", "Value", "Unit", "Quality", "Time",
+        ]))
+        .block(Block::bordered().title(title));
+        frame.render_widget(points, points_area);
+
+        let help = if self.status.is_empty() {
+            "↑/↓ select  f filter  r poll now  p pause  PgUp/PgDn scroll  q quit".to_string()
+        } else {
+            self.status.clone()
+        };
+        frame.render_widget(Paragraph::new(help), help_area);
+    }
+}
+
+fn header<const N: usize>(titles: [&'static str; N]) -> Row<'static> {
+    Row::new(titles).style(Style::new().add_modifier(Modifier::BOLD))
+}
+
+fn quality_color(quality: Quality) -> Color {
+    match quality {
+        Quality::Good => Color::Green,
+        Quality::Uncertain | Quality::Substituted | Quality::LastKnown => Color::Yellow,
+        Quality::OutOfService => Color::DarkGray,
+        _ => Color::Red,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use igw::core::traits::CommunicationMode;
+    use igw::gateway::ChannelCapabilities;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    fn app() -> App {
+        let config: GatewayConfig = serde_json::from_value(serde_json::json!({
+            "gateway": { "name": "monitor" },
+            "channels": [
+                {
+                    "id": 1,
+                    "name": "plc",
+                    "protocol": "virtual",
+                    "points": [{ "id": 10, "name": "temp", "unit": "°C", "address": "t" }]
+                },
+                {
+                    "id": 2,
+                    "name": "meter",
+                    "protocol": "virtual",
+                    "points": [{ "id": 20, "name": "power", "address": "p" }]
+                }
+            ]
+        }))
+        .unwrap();
+        let mut app = App::new(&config);
+        app.channels = [(1, "plc"), (2, "meter")]
+            .into_iter()
+            .map(|(channel_id, name)| ChannelDiagnostics {
+                channel_id,
+                name: name.into(),
+                enabled: true,
+                capabilities: ChannelCapabilities::new(
+                    "Virtual",
+                    &[CommunicationMode::EventDriven],
+                ),
+                diagnostics: None,
+                error: None,
+                overrides: Vec::new(),
+            })
+            .collect();
+        app
+    }
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn visible_ids(app: &App) -> Vec<(u32, PointId)> {
+        app.visible_points().map(|(id, _)| *id).collect()
+    }
+
+    #[test]
+    fn test_keys() {
+        let mut app = app();
+        app.apply(1, DataBatch::from_points(vec![DataPoint::new(10, 21.5)]));
+
+        // Pause freezes the view but keeps collecting updates
+        assert_eq!(app.on_key(key(KeyCode::Char('p'))), Action::None);
+        app.apply(1, DataBatch::from_points(vec![DataPoint::new(10, 22.0)]));
+        let shown = app.visible_points().next().unwrap().1.value.clone();
+        assert_eq!(shown.unwrap().value.as_f64(), Some(21.5));
+        app.on_key(key(KeyCode::Char('p')));
+        let shown = app.visible_points().next().unwrap().1.value.clone();
+        assert_eq!(shown.unwrap().value.as_f64(), Some(22.0));
+
+        // Filter by the selected channel
+        app.on_key(key(KeyCode::Down));
+        app.on_key(key(KeyCode::Char('f')));
+        assert_eq!(visible_ids(&app), vec![(2, 20)]);
+        app.on_key(key(KeyCode::Char('f')));
+        assert_eq!(visible_ids(&app), vec![(1, 10), (2, 20)]);
+
+        assert_eq!(app.on_key(key(KeyCode::Char('r'))), Action::Poll(2));
+        assert_eq!(app.on_key(key(KeyCode::Char('q'))), Action::Quit);
+    }
+
+    #[test]
+    fn test_draw() {
+        let mut app = app();
+        app.apply(
+            1,
+            DataBatch::from_points(vec![
+                DataPoint::new(10, 21.5).with_quality(Quality::CommFailure)
+            ]),
+        );
+
+        let mut terminal = Terminal::new(TestBackend::new(100, 20)).unwrap();
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let text: String = buffer.content().iter().map(|c| c.symbol()).collect();
+
+        assert!(text.contains("meter"));
+        assert!(text.contains("· 1 point: 1 float; 1 bad"));
+        let value_x = buffer
+            .content()
+            .windows(4)
+            .position(|w| w.iter().map(|c| c.symbol()).collect::<String>() == "21.5")
+            .unwrap();
+        assert_eq!(buffer.content()[value_x].fg, Color::Red);
+    }
+}
@@ -0,0 +1,496 @@
+//! `igw probe`: check the function codes of a Modbus channel's points.
+//!
+//! Input registers configured as holding registers (FC03 instead of FC04),
+//! or discrete inputs as coils, are a frequent mistake: the device answers
+//! every read with an illegal function or illegal data address exception
+//! and the point never gets a value. The probe reads each point of a
+//! channel once with its configured function code and, on either of these
+//! exceptions, once more with the sibling code (FC03 ↔ FC04, FC01 ↔ FC02),
+//! then reports the code the device answers on:
+//!
+//! ```text
+//! point 1001 (unit 1, register 100): responds on FC04, not FC03
+//! ```
+//!
+//! The probe only reads: points written through FC05/06/0F/10 and
+//! broadcast points are skipped. Requests go out one at a time, at least
+//! `interval` apart, so a device in service is not flooded. Nothing runs
+//! the probe on its own; it only talks to a device when `igw probe` is
+//! called. Like `igw scan`, it speaks Modbus TCP only.
+
+use std::fmt;
+use std::time::Duration;
+
+use tokio::net::TcpStream;
+use tokio::time::{timeout, Instant};
+
+use igw::core::data::PointId;
+use igw::core::point::{ModbusAddress, ProtocolAddress};
+use igw::gateway::{parse_address, ChannelConfig};
+
+use crate::scan::{read_frame, GATEWAY_EXCEPTIONS};
+
+/// Exception: the function code is not supported by the device.
+pub const ILLEGAL_FUNCTION: u8 = 0x01;
+
+/// Exception: the register range is not valid for the function code.
+pub const ILLEGAL_DATA_ADDRESS: u8 = 0x02;
+
+/// One read of a point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProbeTarget {
+    /// Probed point.
+    pub point_id: PointId,
+
+    /// Unit id.
+    pub unit: u8,
+
+    /// Configured read function code (1-4).
+    pub function_code: u8,
+
+    /// First register or bit.
+    pub register: u16,
+
+    /// Registers or bits read.
+    pub quantity: u16,
+}
+
+impl ProbeTarget {
+    /// Read function code answering for the same kind of object in the
+    /// other area: FC03 ↔ FC04, FC01 ↔ FC02.
+    pub fn sibling_function_code(&self) -> u8 {
+        match self.function_code {
+            1 => 2,
+            2 => 1,
+            3 => 4,
+            _ => 3,
+        }
+    }
+}
+
+/// What the probe learned about one point.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Finding {
+    /// The device answers on the configured function code.
+    Ok,
+
+    /// The device refuses the configured function code but answers on
+    /// `suggested`.
+    WrongFunctionCode { suggested: u8 },
+
+    /// The device refuses the point with exception `exception`, and with
+    /// `sibling_exception` on the sibling code if it was tried.
+    Refused {
+        exception: u8,
+        sibling_exception: Option<u8>,
+    },
+
+    /// No usable answer (timeout, connection lost, gateway exception).
+    Failed(String),
+
+    /// Not probed.
+    Skipped(&'static str),
+}
+
+/// Finding for one point, printable as a report line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PointProbe {
+    /// Point the finding is about.
+    pub point_id: PointId,
+
+    /// Read that was tried, unless skipped.
+    pub target: Option<ProbeTarget>,
+
+    /// Result.
+    pub finding: Finding,
+}
+
+impl fmt::Display for PointProbe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(target) = &self.target else {
+            return match &self.finding {
+                Finding::Skipped(reason) => {
+                    write!(f, "point {}: skipped ({})", self.point_id, reason)
+                }
+                finding => write!(f, "point {}: {:?}", self.point_id, finding),
+            };
+        };
+        let area = if target.function_code <= 2 {
+            "bit"
+        } else {
+            "register"
+        };
+        write!(
+            f,
+            "point {} (unit {}, {} {}): ",
+            self.point_id, target.unit, area, target.register
+        )?;
+        match &self.finding {
+            Finding::Ok => write!(f, "OK on FC{:02}", target.function_code),
+            Finding::WrongFunctionCode { suggested } => write!(
+                f,
+                "responds on FC{:02}, not FC{:02}",
+                suggested, target.function_code
+            ),
+            Finding::Refused {
+                exception,
+                sibling_exception,
+            } => {
+                write!(
+                    f,
+                    "FC{:02} refused: {}",
+                    target.function_code,
+                    exception_name(*exception)
+                )?;
+                match sibling_exception {
+                    Some(code) => write!(
+                        f,
+                        ", FC{:02} refused: {}",
+                        target.sibling_function_code(),
+                        exception_name(*code)
+                    ),
+                    None => Ok(()),
+                }
+            }
+            Finding::Failed(error) => write!(f, "no answer: {}", error),
+            Finding::Skipped(reason) => write!(f, "skipped ({})", reason),
+        }
+    }
+}
+
+fn exception_name(code: u8) -> String {
+    match code {
+        ILLEGAL_FUNCTION => "illegal function".into(),
+        ILLEGAL_DATA_ADDRESS => "illegal data address".into(),
+        0x03 => "illegal data value".into(),
+        0x04 => "server device failure".into(),
+        0x06 => "server device busy".into(),
+        code => format!("exception 0x{:02X}", code),
+    }
+}
+
+/// The read to probe for every point of a Modbus `channel`, in point
+/// order; points that are not read are skipped.
+pub fn plan(channel: &ChannelConfig) -> Result<Vec<PointProbe>, String> {
+    let mut probes = Vec::with_capacity(channel.points.len());
+    for point in &channel.points {
+        let address = parse_address(&channel.protocol, &point.address)
+            .map_err(|e| format!("point {}: {}", point.id, e))?;
+        let ProtocolAddress::Modbus(address) = address else {
+            return Err(format!("point {} is not a Modbus point", point.id));
+        };
+        let (target, finding) = match target(point.id, &address) {
+            Ok(target) => (Some(target), Finding::Ok),
+            Err(reason) => (None, Finding::Skipped(reason)),
+        };
+        probes.push(PointProbe {
+            point_id: point.id,
+            target,
+            finding,
+        });
+    }
+    Ok(probes)
+}
+
+fn target(point_id: PointId, address: &ModbusAddress) -> Result<ProbeTarget, &'static str> {
+    if address.broadcast {
+        return Err("broadcast point");
+    }
+    let quantity = match address.function_code {
+        1 | 2 => address.count.unwrap_or(1),
+        3 | 4 => address.register_count(),
+        _ => return Err("write-only point"),
+    };
+    Ok(ProbeTarget {
+        point_id,
+        unit: address.slave_id,
+        function_code: address.function_code,
+        register: address.register,
+        quantity,
+    })
+}
+
+/// Answer of the device to one read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reply {
+    /// The data bytes, after the byte count.
+    Data(Vec<u8>),
+
+    /// The exception code.
+    Exception(u8),
+}
+
+/// Read-only Modbus TCP client sending at most one request per
+/// `interval`.
+pub struct Prober {
+    addr: String,
+    stream: Option<TcpStream>,
+    transaction: u16,
+    io_timeout: Duration,
+    interval: Duration,
+    last_request: Option<Instant>,
+}
+
+impl Prober {
+    /// Prober for the device at `addr` (`host:port`).
+    pub fn new(addr: impl Into<String>, io_timeout: Duration, interval: Duration) -> Self {
+        Self {
+            addr: addr.into(),
+            stream: None,
+            transaction: 0,
+            io_timeout,
+            interval,
+            last_request: None,
+        }
+    }
+
+    /// Probe every planned point.
+    pub async fn run(&mut self, probes: &mut [PointProbe]) {
+        for probe in probes {
+            if let Some(target) = probe.target {
+                probe.finding = self.probe(&target).await;
+            }
+        }
+    }
+
+    /// Read `target` with its function code and, if the device refuses
+    /// it, with the sibling code.
+    async fn probe(&mut self, target: &ProbeTarget) -> Finding {
+        let exception = match self.read_target(target, target.function_code).await {
+            Ok(Reply::Data(_)) => return Finding::Ok,
+            Ok(Reply::Exception(code)) => code,
+            Err(e) => return Finding::Failed(e),
+        };
+        if !matches!(exception, ILLEGAL_FUNCTION | ILLEGAL_DATA_ADDRESS) {
+            return Finding::Refused {
+                exception,
+                sibling_exception: None,
+            };
+        }
+        let sibling = target.sibling_function_code();
+        match self.read_target(target, sibling).await {
+            Ok(Reply::Data(_)) => Finding::WrongFunctionCode { suggested: sibling },
+            Ok(Reply::Exception(code)) => Finding::Refused {
+                exception,
+                sibling_exception: Some(code),
+            },
+            Err(_) => Finding::Refused {
+                exception,
+                sibling_exception: None,
+            },
+        }
+    }
+
+    /// Read `target` with `function_code`.
+    async fn read_target(
+        &mut self,
+        target: &ProbeTarget,
+        function_code: u8,
+    ) -> Result<Reply, String> {
+        self.read(target.unit, function_code, target.register, target.quantity)
+            .await
+    }
+
+    /// Read `quantity` registers or bits from `register` of `unit` with
+    /// `function_code` (1-4), waiting out the interval first.
+    pub async fn read(
+        &mut self,
+        unit: u8,
+        function_code: u8,
+        register: u16,
+        quantity: u16,
+    ) -> Result<Reply, String> {
+        if let Some(last) = self.last_request {
+            tokio::time::sleep_until(last + self.interval).await;
+        }
+        self.last_request = Some(Instant::now());
+        self.transaction = self.transaction.wrapping_add(1);
+
+        let result = self.exchange(unit, function_code, register, quantity).await;
+        if result.is_err() {
+            // Start over with a fresh connection for the next request
+            self.stream = None;
+        }
+        result
+    }
+
+    async fn exchange(
+        &mut self,
+        unit: u8,
+        function_code: u8,
+        register: u16,
+        quantity: u16,
+    ) -> Result<Reply, String> {
+        let stream = match &mut self.stream {
+            Some(stream) => stream,
+            None => {
+                let stream = timeout(self.io_timeout, TcpStream::connect(&self.addr))
+                    .await
+                    .map_err(|_| format!("connecting to {} timed out", self.addr))?
+                    .map_err(|e| format!("{}: {}", self.addr, e))?;
+                self.stream.insert(stream)
+            }
+        };
+
+        let [tid_hi, tid_lo] = self.transaction.to_be_bytes();
+        let [reg_hi, reg_lo] = register.to_be_bytes();
+        let [qty_hi, qty_lo] = quantity.to_be_bytes();
+        let request = [
+            tid_hi,
+            tid_lo,
+            0,
+            0,
+            0,
+            6,
+            unit,
+            function_code,
+            reg_hi,
+            reg_lo,
+            qty_hi,
+            qty_lo,
+        ];
+        tokio::io::AsyncWriteExt::write_all(stream, &request)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let deadline = Instant::now() + self.io_timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let (tid, reply_unit, pdu) = timeout(remaining, read_frame(stream))
+                .await
+                .map_err(|_| format!("FC{:02} timed out", function_code))?
+                .map_err(|e| e.to_string())?;
+            // Skip late replies to earlier requests
+            if tid != self.transaction || reply_unit != unit {
+                continue;
+            }
+            return match pdu.as_slice() {
+                [fc, code, ..] if *fc == function_code | 0x80 => {
+                    if GATEWAY_EXCEPTIONS.contains(code) {
+                        Err(format!("gateway exception 0x{:02X}", code))
+                    } else {
+                        Ok(Reply::Exception(*code))
+                    }
+                }
+                [fc, _byte_count, data @ ..] if *fc == function_code => {
+                    Ok(Reply::Data(data.to_vec()))
+                }
+                _ => Err(format!("unexpected reply to FC{:02}", function_code)),
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    fn channel(points: serde_json::Value) -> ChannelConfig {
+        serde_json::from_value(serde_json::json!({
+            "id": 1,
+            "name": "meter",
+            "protocol": "modbus",
+            "parameters": { "host": "127.0.0.1" },
+            "points": points
+        }))
+        .unwrap()
+    }
+
+    /// Device with holding registers 0-99, input registers 100-199, coils
+    /// 0-9 and no discrete inputs; unit 2 is behind a dead gateway path.
+    async fn fake_device() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = [0u8; 12];
+            while stream.read_exact(&mut request).await.is_ok() {
+                let (unit, fc) = (request[6], request[7]);
+                let register = u16::from_be_bytes([request[8], request[9]]);
+                let pdu: Vec<u8> = match (unit, fc) {
+                    (2, _) => vec![fc | 0x80, 0x0B],
+                    (_, 3) if register < 100 => vec![fc, 2, 0, 1],
+                    (_, 4) if (100..200).contains(&register) => vec![fc, 2, 0, 1],
+                    (_, 1) if register < 10 => vec![fc, 1, 1],
+                    (_, 2) => vec![fc | 0x80, ILLEGAL_FUNCTION],
+                    _ => vec![fc | 0x80, ILLEGAL_DATA_ADDRESS],
+                };
+                let mut reply = vec![request[0], request[1], 0, 0, 0, pdu.len() as u8 + 1, unit];
+                reply.extend_from_slice(&pdu);
+                stream.write_all(&reply).await.unwrap();
+            }
+        });
+        addr
+    }
+
+    #[test]
+    fn test_plan() {
+        let probes = plan(&channel(serde_json::json!([
+            { "id": 1, "name": "a", "address": "1:100:4:float32" },
+            { "id": 2, "name": "b", "address": "1:5:6" },
+            { "id": 3, "name": "c", "address": "*:7:6" },
+            { "id": 4, "name": "d", "address": "3:12:1" }
+        ])))
+        .unwrap();
+        assert_eq!(
+            probes[0].target,
+            Some(ProbeTarget {
+                point_id: 1,
+                unit: 1,
+                function_code: 4,
+                register: 100,
+                quantity: 2,
+            })
+        );
+        assert_eq!(probes[1].finding, Finding::Skipped("write-only point"));
+        assert_eq!(probes[2].finding, Finding::Skipped("broadcast point"));
+        assert_eq!(probes[3].target.map(|t| t.sibling_function_code()), Some(2));
+        assert_eq!(probes[1].to_string(), "point 2: skipped (write-only point)");
+    }
+
+    #[tokio::test]
+    async fn test_probe_suggests_sibling() {
+        let addr = fake_device().await;
+        let mut probes = plan(&channel(serde_json::json!([
+            { "id": 1, "name": "ok", "address": "1:10:3" },
+            { "id": 2, "name": "input", "address": "1:100:3" },
+            { "id": 3, "name": "missing", "address": "1:300:4" },
+            { "id": 4, "name": "coil", "address": "1:1:2" },
+            { "id": 5, "name": "gateway", "address": "2:1:3" }
+        ])))
+        .unwrap();
+        let interval = Duration::from_millis(20);
+        let mut prober = Prober::new(addr.to_string(), Duration::from_millis(500), interval);
+        let start = Instant::now();
+        prober.run(&mut probes).await;
+
+        let findings: Vec<&Finding> = probes.iter().map(|p| &p.finding).collect();
+        assert_eq!(
+            findings,
+            vec![
+                &Finding::Ok,
+                &Finding::WrongFunctionCode { suggested: 4 },
+                &Finding::Refused {
+                    exception: ILLEGAL_DATA_ADDRESS,
+                    sibling_exception: Some(ILLEGAL_DATA_ADDRESS),
+                },
+                &Finding::WrongFunctionCode { suggested: 1 },
+                &Finding::Failed("gateway exception 0x0B".into()),
+            ]
+        );
+        assert_eq!(
+            probes[1].to_string(),
+            "point 2 (unit 1, register 100): responds on FC04, not FC03"
+        );
+        assert_eq!(
+            probes[2].to_string(),
+            "point 3 (unit 1, register 300): FC04 refused: illegal data address, \
+             FC03 refused: illegal data address"
+        );
+        // Eight requests, spaced by the interval
+        assert!(start.elapsed() >= interval * 7);
+    }
+}
@@ -0,0 +1,255 @@
+//! `igw scan modbus`: probe a network range for Modbus TCP devices.
+//!
+//! Every host of the target range is probed in parallel. On each host that
+//! accepts a TCP connection, the unit ids are tried one after another with a
+//! minimal "read holding register 0" request. Any reply counts as a device,
+//! including exception replies (the register may simply not exist), except
+//! the gateway exceptions 0x0A/0x0B which mean nothing answered behind a
+//! gateway.
+
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::task::JoinSet;
+use tokio::time::{timeout, Instant};
+
+/// Default Modbus TCP port.
+const MODBUS_PORT: u16 = 502;
+
+/// Largest range we agree to scan (a /16).
+const MAX_HOSTS: u64 = 65536;
+
+/// Gateway path unavailable / gateway target device failed to respond.
+pub const GATEWAY_EXCEPTIONS: [u8; 2] = [0x0A, 0x0B];
+
+/// Parse `host[:port]`, `network/prefix[:port]` (IPv4) into socket addresses.
+///
+/// Network and broadcast addresses are skipped for prefixes up to /30.
+pub fn parse_target(target: &str) -> Result<Vec<SocketAddr>, String> {
+    let (range, port) = match target.rsplit_once(':') {
+        Some((range, port)) => (
+            range,
+            port.parse::<u16>()
+                .map_err(|_| format!("invalid port '{}'", port))?,
+        ),
+        None => (target, MODBUS_PORT),
+    };
+
+    let (addr, prefix) = match range.split_once('/') {
+        Some((addr, prefix)) => (
+            addr,
+            prefix
+                .parse::<u32>()
+                .ok()
+                .filter(|p| *p <= 32)
+                .ok_or_else(|| format!("invalid prefix length '{}'", prefix))?,
+        ),
+        None => (range, 32),
+    };
+    let addr: Ipv4Addr = addr
+        .parse()
+        .map_err(|_| format!("invalid IPv4 address '{}'", addr))?;
+
+    let hosts = 1u64 << (32 - prefix);
+    if hosts > MAX_HOSTS {
+        return Err(format!("/{} is too large to scan (at most /16)", prefix));
+    }
+
+    let network = u32::from(addr) & u32::MAX.checked_shl(32 - prefix).unwrap_or(0);
+    let (first, last) = if prefix <= 30 {
+        (network + 1, network + hosts as u32 - 2)
+    } else {
+        (network, network + (hosts - 1) as u32)
+    };
+
+    Ok((first..=last)
+        .map(|ip| SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::from(ip), port)))
+        .collect())
+}
+
+/// Parse unit ids like `1-247` or `1,2,10-20`.
+pub fn parse_units(units: &str) -> Result<Vec<u8>, String> {
+    let mut ids = Vec::new();
+    for part in units.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let parse = |s: &str| {
+            s.trim()
+                .parse::<u8>()
+                .map_err(|_| format!("invalid unit id '{}'", s))
+        };
+        match part.split_once('-') {
+            Some((from, to)) => {
+                let (from, to) = (parse(from)?, parse(to)?);
+                if from > to {
+                    return Err(format!("invalid unit range '{}'", part));
+                }
+                ids.extend(from..=to);
+            }
+            None => ids.push(parse(part)?),
+        }
+    }
+    ids.sort_unstable();
+    ids.dedup();
+    if ids.is_empty() {
+        return Err("no unit ids to probe".into());
+    }
+    Ok(ids)
+}
+
+/// Probe every target and return the responding unit ids per host.
+pub async fn scan_modbus(
+    targets: Vec<SocketAddr>,
+    units: Vec<u8>,
+    probe_timeout: Duration,
+) -> Vec<(SocketAddr, Vec<u8>)> {
+    let mut tasks = JoinSet::new();
+    for addr in targets {
+        let units = units.clone();
+        tasks.spawn(async move { (addr, scan_host(addr, &units, probe_timeout).await) });
+    }
+
+    let mut found = Vec::new();
+    while let Some(result) = tasks.join_next().await {
+        if let Ok((addr, responding)) = result {
+            if !responding.is_empty() {
+                found.push((addr, responding));
+            }
+        }
+    }
+    found.sort();
+    found
+}
+
+/// Responding unit ids of one host (empty if nothing listens).
+async fn scan_host(addr: SocketAddr, units: &[u8], probe_timeout: Duration) -> Vec<u8> {
+    let mut stream = None;
+    let mut responding = Vec::new();
+
+    for (transaction, &unit) in (1u16..).zip(units) {
+        if stream.is_none() {
+            match timeout(probe_timeout, TcpStream::connect(addr)).await {
+                Ok(Ok(s)) => stream = Some(s),
+                // Nothing listening (or the device dropped us for good)
+                _ => break,
+            }
+        }
+        let Some(s) = stream.as_mut() else {
+            break;
+        };
+
+        match probe(s, transaction, unit, probe_timeout).await {
+            Ok(true) => responding.push(unit),
+            Ok(false) => {}
+            // Devices often close the connection on unknown unit ids
+            Err(_) => stream = None,
+        }
+    }
+    responding
+}
+
+/// Send "read holding register 0" to `unit`; `Ok(true)` if a device answered.
+async fn probe(
+    stream: &mut TcpStream,
+    transaction: u16,
+    unit: u8,
+    probe_timeout: Duration,
+) -> std::io::Result<bool> {
+    let [tid_hi, tid_lo] = transaction.to_be_bytes();
+    let request = [tid_hi, tid_lo, 0, 0, 0, 6, unit, 0x03, 0, 0, 0, 1];
+    stream.write_all(&request).await?;
+
+    let deadline = Instant::now() + probe_timeout;
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let Ok(frame) = timeout(remaining, read_frame(stream)).await else {
+            return Ok(false);
+        };
+        let (tid, frame_unit, pdu) = frame?;
+        // Skip late replies to earlier probes
+        if tid != transaction || frame_unit != unit {
+            continue;
+        }
+        let exception = pdu.first().is_some_and(|fc| fc & 0x80 != 0);
+        return Ok(!(exception && pdu.get(1).is_some_and(|c| GATEWAY_EXCEPTIONS.contains(c))));
+    }
+}
+
+/// Read one Modbus TCP frame: (transaction id, unit id, PDU).
+pub async fn read_frame(stream: &mut TcpStream) -> std::io::Result<(u16, u8, Vec<u8>)> {
+    let mut header = [0u8; 7];
+    stream.read_exact(&mut header).await?;
+    let length = u16::from_be_bytes([header[4], header[5]]) as usize;
+    if !(2..=254).contains(&length) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "invalid MBAP length",
+        ));
+    }
+    let mut pdu = vec![0u8; length - 1];
+    stream.read_exact(&mut pdu).await?;
+    Ok((u16::from_be_bytes([header[0], header[1]]), header[6], pdu))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_parse_target() {
+        let hosts = parse_target("192.168.1.0/24:1502").unwrap();
+        assert_eq!(hosts.len(), 254);
+        assert_eq!(hosts[0], "192.168.1.1:1502".parse().unwrap());
+        assert_eq!(hosts[253], "192.168.1.254:1502".parse().unwrap());
+
+        assert_eq!(
+            parse_target("10.0.0.7").unwrap(),
+            vec!["10.0.0.7:502".parse().unwrap()]
+        );
+        assert_eq!(parse_target("10.0.0.6/31").unwrap().len(), 2);
+        assert!(parse_target("10.0.0.0/8").is_err());
+        assert!(parse_target("10.0.0.0/24:x").is_err());
+        assert!(parse_target("host/24").is_err());
+    }
+
+    #[test]
+    fn test_parse_units() {
+        assert_eq!(parse_units("1-3,7, 2").unwrap(), vec![1, 2, 3, 7]);
+        assert_eq!(parse_units("1-247").unwrap().len(), 247);
+        assert!(parse_units("5-1").is_err());
+        assert!(parse_units("300").is_err());
+        assert!(parse_units("").is_err());
+    }
+
+    /// Answers unit 1 normally, unit 2 with an illegal address exception,
+    /// unit 3 with a gateway exception and ignores the rest.
+    async fn fake_device() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = [0u8; 12];
+            while stream.read_exact(&mut request).await.is_ok() {
+                let (tid, unit) = ([request[0], request[1]], request[6]);
+                let pdu: &[u8] = match unit {
+                    1 => &[0x03, 2, 0, 42],
+                    2 => &[0x83, 0x02],
+                    3 => &[0x83, 0x0B],
+                    _ => continue,
+                };
+                let mut reply = vec![tid[0], tid[1], 0, 0, 0, pdu.len() as u8 + 1, unit];
+                reply.extend_from_slice(pdu);
+                stream.write_all(&reply).await.unwrap();
+            }
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn test_scan_host() {
+        let addr = fake_device().await;
+        let found = scan_modbus(vec![addr], vec![1, 2, 3, 4], Duration::from_millis(50)).await;
+        assert_eq!(found, vec![(addr, vec![1, 2])]);
+    }
+}
@@ -0,0 +1,162 @@
+//! `igw snapshot save/load`: store snapshots of a running gateway.
+//!
+//! Both commands talk to the gateway's HTTP API (`GET`/`POST /snapshot`) at
+//! the address and with the token of `[gateway.http_api]`, so they need a
+//! gateway running with the `http-api` feature. A wildcard bind address is
+//! reached over loopback.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+
+use igw::gateway::HttpApiConfig;
+
+/// Upper bound for one request, snapshot transfer included.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Address to reach an API bound to `bind` from this host.
+pub fn api_address(bind: &str) -> Result<SocketAddr, String> {
+    let mut addr: SocketAddr = bind
+        .parse()
+        .map_err(|_| format!("http_api.bind '{}' is not a host:port address", bind))?;
+    if addr.ip().is_unspecified() {
+        addr.set_ip(match addr.ip() {
+            IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::LOCALHOST),
+            IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::LOCALHOST),
+        });
+    }
+    Ok(addr)
+}
+
+/// Send one request to the API and return the response body.
+///
+/// Responses other than 200 are turned into an error carrying the API's
+/// error message.
+pub async fn request(
+    config: &HttpApiConfig,
+    method: &str,
+    path: &str,
+    body: Option<&str>,
+) -> Result<String, String> {
+    let addr = api_address(&config.bind)?;
+    let exchange = async {
+        let mut stream = TcpStream::connect(addr).await?;
+        let mut head = format!(
+            "{} {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n",
+            method, path, addr
+        );
+        if let Some(token) = &config.token {
+            head.push_str(&format!("Authorization: Bearer {}\r\n", token));
+        }
+        let body = body.unwrap_or_default();
+        if !body.is_empty() {
+            head.push_str("Content-Type: application/json\r\n");
+        }
+        head.push_str(&format!("Content-Length: {}\r\n\r\n", body.len()));
+        stream.write_all(head.as_bytes()).await?;
+        stream.write_all(body.as_bytes()).await?;
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).await?;
+        Ok::<_, std::io::Error>(response)
+    };
+    let response = timeout(REQUEST_TIMEOUT, exchange)
+        .await
+        .map_err(|_| format!("{} {} timed out", method, path))?
+        .map_err(|e| format!("gateway API at {}: {}", addr, e))?;
+
+    let (status, body) = parse_response(&response)?;
+    if status != 200 {
+        let message = serde_json::from_str::<serde_json::Value>(&body)
+            .ok()
+            .and_then(|v| v["error"].as_str().map(str::to_string))
+            .unwrap_or(body);
+        return Err(format!("{} {}: HTTP {}: {}", method, path, status, message));
+    }
+    Ok(body)
+}
+
+/// Split an HTTP/1.1 response into status code and (de-chunked) body.
+pub fn parse_response(response: &[u8]) -> Result<(u16, String), String> {
+    let malformed = || "malformed HTTP response".to_string();
+    let split = response
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .ok_or_else(malformed)?;
+    let head = std::str::from_utf8(&response[..split]).map_err(|_| malformed())?;
+    let mut body = &response[split + 4..];
+
+    let status = head
+        .split_whitespace()
+        .nth(1)
+        .and_then(|s| s.parse::<u16>().ok())
+        .ok_or_else(malformed)?;
+    let chunked = head.lines().any(|line| {
+        line.split_once(':').is_some_and(|(name, value)| {
+            name.eq_ignore_ascii_case("transfer-encoding")
+                && value.trim().eq_ignore_ascii_case("chunked")
+        })
+    });
+
+    let body = if chunked {
+        let mut data = Vec::new();
+        loop {
+            let line_end = body
+                .windows(2)
+                .position(|w| w == b"\r\n")
+                .ok_or_else(malformed)?;
+            let size = std::str::from_utf8(&body[..line_end])
+                .ok()
+                .and_then(|s| usize::from_str_radix(s.split(';').next()?.trim(), 16).ok())
+                .ok_or_else(malformed)?;
+            body = &body[line_end + 2..];
+            if size == 0 {
+                break;
+            }
+            data.extend_from_slice(body.get(..size).ok_or_else(malformed)?);
+            body = body.get(size + 2..).ok_or_else(malformed)?;
+        }
+        data
+    } else {
+        body.to_vec()
+    };
+    let body = String::from_utf8(body).map_err(|_| "response is not UTF-8".to_string())?;
+    Ok((status, body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_api_address() {
+        assert_eq!(
+            api_address("0.0.0.0:8080").unwrap(),
+            "127.0.0.1:8080".parse().unwrap()
+        );
+        assert_eq!(api_address("[::]:80").unwrap(), "[::1]:80".parse().unwrap());
+        assert_eq!(
+            api_address("10.0.0.5:8080").unwrap(),
+            "10.0.0.5:8080".parse().unwrap()
+        );
+        assert!(api_address("localhost").is_err());
+    }
+
+    #[test]
+    fn test_parse_response() {
+        let response = b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\n{}";
+        assert_eq!(parse_response(response).unwrap(), (200, "{}".to_string()));
+
+        let response = b"HTTP/1.1 400 Bad Request\r\nTransfer-Encoding: chunked\r\n\r\n\
+                         4\r\n{\"a\"\r\n3\r\n:1}\r\n0\r\n\r\n";
+        assert_eq!(
+            parse_response(response).unwrap(),
+            (400, "{\"a\":1}".to_string())
+        );
+
+        assert!(parse_response(b"HTTP/1.1 200 OK").is_err());
+        assert!(parse_response(b"garbage\r\n\r\n").is_err());
+    }
+}
@@ -3,6 +3,18 @@
 //! This module provides tools for converting between protocol raw bytes
 //! and application-level values.
 
+pub mod bcd;
 pub mod byte_order;
+pub mod checksum;
+pub mod float16;
+pub mod iec_time;
 
+#[cfg(feature = "sparkplug")]
+#[cfg_attr(docsrs, doc(cfg(feature = "sparkplug")))]
+pub mod sparkplug;
+
+pub use bcd::*;
 pub use byte_order::*;
+pub use checksum::*;
+pub use float16::*;
+pub use iec_time::*;
@@ -0,0 +1,126 @@
+//! Binary-coded decimal (BCD) conversion.
+//!
+//! Each nibble of a BCD value holds one decimal digit, most significant
+//! digit first: the register `0x1234` reads as `1234`. Energy meters often
+//! expose counters as 8 digits across two registers; those follow the
+//! channel's [`ByteOrder`] like any other 32-bit value.
+//!
+//! A nibble above 9 is not a digit. Decoding reports it as
+//! [`GatewayError::DataConversion`] instead of returning a bogus number,
+//! so the point is marked `Quality::Invalid`.
+
+use crate::codec::byte_order::{decode_u32, encode_u32};
+use crate::core::error::{GatewayError, Result};
+use crate::core::point::ByteOrder;
+
+/// Largest value that fits in 4 BCD digits.
+pub const BCD16_MAX: u16 = 9_999;
+
+/// Largest value that fits in 8 BCD digits.
+pub const BCD32_MAX: u32 = 99_999_999;
+
+/// Decode a 4-digit BCD value from the first register.
+pub fn decode_bcd_u16(registers: &[u16]) -> Result<u16> {
+    let Some(&register) = registers.first() else {
+        return Err(GatewayError::invalid_data("No registers for bcd16"));
+    };
+    unpack(register as u32, 4).map(|v| v as u16)
+}
+
+/// Encode a value (0-9999) as 4-digit BCD.
+pub fn encode_bcd_u16(value: u16) -> Result<u16> {
+    if value > BCD16_MAX {
+        return Err(GatewayError::DataConversion(format!(
+            "{} does not fit in 4 BCD digits",
+            value
+        )));
+    }
+    Ok(pack(value as u32) as u16)
+}
+
+/// Decode an 8-digit BCD value from the first 2 registers.
+pub fn decode_bcd_u32(registers: &[u16], order: ByteOrder) -> Result<u32> {
+    unpack(decode_u32(registers, order)?, 8)
+}
+
+/// Encode a value (0-99999999) as 8-digit BCD in 2 registers.
+pub fn encode_bcd_u32(value: u32, order: ByteOrder) -> Result<[u16; 2]> {
+    if value > BCD32_MAX {
+        return Err(GatewayError::DataConversion(format!(
+            "{} does not fit in 8 BCD digits",
+            value
+        )));
+    }
+    Ok(encode_u32(pack(value), order))
+}
+
+/// Decimal value of the low `digits` nibbles of `raw`.
+fn unpack(raw: u32, digits: u32) -> Result<u32> {
+    let mut value = 0;
+    for i in (0..digits).rev() {
+        let nibble = (raw >> (i * 4)) & 0xF;
+        if nibble > 9 {
+            return Err(GatewayError::DataConversion(format!(
+                "Invalid BCD digit 0x{:X} in 0x{:0width$X}",
+                nibble,
+                raw,
+                width = digits as usize
+            )));
+        }
+        value = value * 10 + nibble;
+    }
+    Ok(value)
+}
+
+/// BCD nibbles of `value`, least significant digit in the low nibble.
+fn pack(mut value: u32) -> u32 {
+    let mut raw = 0;
+    let mut shift = 0;
+    while value > 0 {
+        raw |= (value % 10) << shift;
+        value /= 10;
+        shift += 4;
+    }
+    raw
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bcd16() {
+        assert_eq!(decode_bcd_u16(&[0x1234]).unwrap(), 1234);
+        assert_eq!(decode_bcd_u16(&[0x9999]).unwrap(), 9999);
+        assert_eq!(encode_bcd_u16(907).unwrap(), 0x0907);
+        assert!(encode_bcd_u16(10_000).is_err());
+        assert!(decode_bcd_u16(&[]).is_err());
+    }
+
+    #[test]
+    fn test_bcd32_byte_orders() {
+        assert_eq!(
+            decode_bcd_u32(&[0x1234, 0x5678], ByteOrder::Abcd).unwrap(),
+            12_345_678
+        );
+        assert_eq!(
+            decode_bcd_u32(&[0x5678, 0x1234], ByteOrder::Cdab).unwrap(),
+            12_345_678
+        );
+        assert_eq!(
+            encode_bcd_u32(12_345_678, ByteOrder::Dcba).unwrap(),
+            [0x7856, 0x3412]
+        );
+        assert!(encode_bcd_u32(BCD32_MAX + 1, ByteOrder::Abcd).is_err());
+    }
+
+    #[test]
+    fn test_invalid_nibble() {
+        let err = decode_bcd_u16(&[0x12A4]).unwrap_err();
+        assert!(matches!(err, GatewayError::DataConversion(_)));
+        assert!(err.to_string().contains("0xA"));
+
+        let err = decode_bcd_u32(&[0x0000, 0xF001], ByteOrder::Abcd).unwrap_err();
+        assert!(matches!(err, GatewayError::DataConversion(_)));
+    }
+}
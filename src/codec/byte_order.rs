@@ -2,7 +2,34 @@
 //!
 //! Provides functions for reading and writing multi-byte values
 //! with configurable byte order.
-
+//!
+//! # Byte orders
+//!
+//! A value's bytes are named in big-endian order: `ABCD` for 32-bit,
+//! `ABCDEFGH` for 64-bit values (`A` most significant). Registers are
+//! listed in address order. A [`ByteOrder`] combines two independent
+//! swaps: the bytes within each register, and the order of the registers.
+//!
+//! | `ByteOrder` | Byte swap | Register order | 32-bit | 64-bit |
+//! |-------------|-----------|----------------|--------|--------|
+//! | `Abcd` | no | high first | `AB CD` | `AB CD EF GH` |
+//! | `Badc` | yes | high first | `BA DC` | `BA DC FE HG` |
+//! | `Cdab` | no | low first | `CD AB` | `GH EF CD AB` |
+//! | `Dcba` | yes | low first | `DC BA` | `HG FE DC BA` |
+//!
+//! Of the eight orderings a 64-bit value can be found in, these are the
+//! four where both 32-bit halves follow the same order as the registers
+//! within them. The other four (e.g. `CD AB GH EF`, swapping registers
+//! within each half only) cannot be expressed with a [`ByteOrder`].
+//!
+//! Protocols decoding multi-register values should go through
+//! [`decode_registers()`]/[`encode_registers()`] or the typed
+//! `decode_*`/`encode_*` functions instead of reordering bytes themselves.
+
+use crate::codec::bcd::{
+    decode_bcd_u16, decode_bcd_u32, encode_bcd_u16, encode_bcd_u32, BCD16_MAX, BCD32_MAX,
+};
+use crate::codec::float16::{decode_f16, encode_f16};
 use crate::core::data::Value;
 use crate::core::error::{GatewayError, Result};
 use crate::core::point::{ByteOrder, DataFormat};
@@ -51,57 +78,25 @@ pub fn decode_registers(
             Ok(Value::Integer(registers[0] as i16 as i64))
         }
 
-        DataFormat::UInt32 => {
-            if registers.len() < 2 {
-                return Err(GatewayError::invalid_data("Need 2 registers for uint32"));
-            }
-            let bytes = reorder_bytes_32(registers[0], registers[1], byte_order);
-            Ok(Value::Integer(u32::from_be_bytes(bytes) as i64))
-        }
+        DataFormat::UInt32 => Ok(Value::Integer(decode_u32(registers, byte_order)? as i64)),
 
-        DataFormat::Int32 => {
-            if registers.len() < 2 {
-                return Err(GatewayError::invalid_data("Need 2 registers for int32"));
-            }
-            let bytes = reorder_bytes_32(registers[0], registers[1], byte_order);
-            Ok(Value::Integer(i32::from_be_bytes(bytes) as i64))
-        }
+        DataFormat::Int32 => Ok(Value::Integer(decode_i32(registers, byte_order)? as i64)),
 
         DataFormat::Float32 => {
-            if registers.len() < 2 {
-                return Err(GatewayError::invalid_data("Need 2 registers for float32"));
-            }
-            let bytes = reorder_bytes_32(registers[0], registers[1], byte_order);
-            let value = f32::from_be_bytes(bytes);
-            if value.is_nan() || value.is_infinite() {
+            let value = decode_f32(registers, byte_order)?;
+            if !value.is_finite() {
                 return Err(GatewayError::invalid_data("Invalid float32 value"));
             }
             Ok(Value::Float(value as f64))
         }
 
-        DataFormat::UInt64 => {
-            if registers.len() < 4 {
-                return Err(GatewayError::invalid_data("Need 4 registers for uint64"));
-            }
-            let bytes = reorder_bytes_64(&registers[0..4], byte_order);
-            Ok(Value::Integer(u64::from_be_bytes(bytes) as i64))
-        }
+        DataFormat::UInt64 => Ok(Value::Integer(decode_u64(registers, byte_order)? as i64)),
 
-        DataFormat::Int64 => {
-            if registers.len() < 4 {
-                return Err(GatewayError::invalid_data("Need 4 registers for int64"));
-            }
-            let bytes = reorder_bytes_64(&registers[0..4], byte_order);
-            Ok(Value::Integer(i64::from_be_bytes(bytes)))
-        }
+        DataFormat::Int64 => Ok(Value::Integer(decode_i64(registers, byte_order)?)),
 
         DataFormat::Float64 => {
-            if registers.len() < 4 {
-                return Err(GatewayError::invalid_data("Need 4 registers for float64"));
-            }
-            let bytes = reorder_bytes_64(&registers[0..4], byte_order);
-            let value = f64::from_be_bytes(bytes);
-            if value.is_nan() || value.is_infinite() {
+            let value = decode_f64(registers, byte_order)?;
+            if !value.is_finite() {
                 return Err(GatewayError::invalid_data("Invalid float64 value"));
             }
             Ok(Value::Float(value))
@@ -121,7 +116,47 @@ pub fn decode_registers(
             }
             Ok(Value::String(s.trim_end_matches('\0').to_string()))
         }
+
+        DataFormat::Float16 => {
+            if registers.is_empty() {
+                return Err(GatewayError::invalid_data("No registers for float16"));
+            }
+            Ok(Value::Float(decode_f16(registers[0])?))
+        }
+
+        DataFormat::Bcd16 => Ok(Value::Integer(decode_bcd_u16(registers)? as i64)),
+
+        DataFormat::Bcd32 => Ok(Value::Integer(decode_bcd_u32(registers, byte_order)? as i64)),
+    }
+}
+
+/// Decode `count` consecutive `format` values from 16-bit registers into a
+/// `Value::Array`.
+///
+/// Each element occupies `format.register_count()` registers. Fails if any
+/// element fails to decode or the registers run short.
+pub fn decode_register_array(
+    registers: &[u16],
+    format: DataFormat,
+    byte_order: ByteOrder,
+    count: u16,
+) -> Result<Value> {
+    let size = format.register_count() as usize;
+    let needed = size * count as usize;
+    if registers.len() < needed {
+        return Err(GatewayError::invalid_data(format!(
+            "Array of {} {:?} values needs {} registers, got {}",
+            count,
+            format,
+            needed,
+            registers.len()
+        )));
     }
+    registers[..needed]
+        .chunks_exact(size)
+        .map(|chunk| decode_registers(chunk, format, byte_order, None))
+        .collect::<Result<Vec<_>>>()
+        .map(Value::Array)
 }
 
 /// Encode a value to 16-bit registers.
@@ -135,6 +170,11 @@ pub fn decode_registers(
 /// # Returns
 ///
 /// Vec of 16-bit register values
+///
+/// Integer formats only take whole numbers within their range; fractions,
+/// NaN, infinities and values out of range fail with
+/// `GatewayError::DataConversion` instead of being truncated or wrapped.
+/// `Float64` refuses integers that have no exact f64.
 pub fn encode_registers(
     value: &Value,
     format: DataFormat,
@@ -148,55 +188,27 @@ pub fn encode_registers(
             Ok(vec![if b { 1 } else { 0 }])
         }
 
-        DataFormat::UInt16 => {
-            let v = value
-                .as_i64()
-                .ok_or_else(|| GatewayError::invalid_data("Cannot convert to integer"))?;
-            Ok(vec![v as u16])
-        }
+        DataFormat::UInt16 => Ok(vec![fit::<u16>(value, format)?]),
 
-        DataFormat::Int16 => {
-            let v = value
-                .as_i64()
-                .ok_or_else(|| GatewayError::invalid_data("Cannot convert to integer"))?;
-            Ok(vec![v as i16 as u16])
-        }
+        DataFormat::Int16 => Ok(vec![fit::<i16>(value, format)? as u16]),
 
-        DataFormat::UInt32 | DataFormat::Int32 => {
-            let v = value
-                .as_i64()
-                .ok_or_else(|| GatewayError::invalid_data("Cannot convert to integer"))?;
-            let bytes = (v as u32).to_be_bytes();
-            let (r0, r1) = reorder_to_registers_32(bytes, byte_order);
-            Ok(vec![r0, r1])
-        }
+        DataFormat::UInt32 => Ok(encode_u32(value.try_as_u32()?, byte_order).to_vec()),
+
+        DataFormat::Int32 => Ok(encode_u32(fit::<i32>(value, format)? as u32, byte_order).to_vec()),
 
         DataFormat::Float32 => {
             let v = value
                 .as_f64()
                 .ok_or_else(|| GatewayError::invalid_data("Cannot convert to float"))?;
-            let bytes = (v as f32).to_be_bytes();
-            let (r0, r1) = reorder_to_registers_32(bytes, byte_order);
-            Ok(vec![r0, r1])
+            Ok(encode_f32(v as f32, byte_order).to_vec())
         }
 
+        // UInt64 values beyond i64::MAX are carried wrapped, as decoded
         DataFormat::UInt64 | DataFormat::Int64 => {
-            let v = value
-                .as_i64()
-                .ok_or_else(|| GatewayError::invalid_data("Cannot convert to integer"))?;
-            let bytes = v.to_be_bytes();
-            let regs = reorder_to_registers_64(bytes, byte_order);
-            Ok(regs.to_vec())
+            Ok(encode_i64(value.try_as_i64()?, byte_order).to_vec())
         }
 
-        DataFormat::Float64 => {
-            let v = value
-                .as_f64()
-                .ok_or_else(|| GatewayError::invalid_data("Cannot convert to float"))?;
-            let bytes = v.to_be_bytes();
-            let regs = reorder_to_registers_64(bytes, byte_order);
-            Ok(regs.to_vec())
-        }
+        DataFormat::Float64 => Ok(encode_f64(value.try_as_f64_exact()?, byte_order).to_vec()),
 
         DataFormat::String => {
             let s = value
@@ -211,92 +223,155 @@ pub fn encode_registers(
             }
             Ok(regs)
         }
+
+        DataFormat::Float16 => {
+            let v = value
+                .as_f64()
+                .ok_or_else(|| GatewayError::invalid_data("Cannot convert to float"))?;
+            Ok(vec![encode_f16(v)?])
+        }
+
+        DataFormat::Bcd16 | DataFormat::Bcd32 => {
+            let v = value.try_as_i64()?;
+            let max = if format == DataFormat::Bcd16 {
+                BCD16_MAX as i64
+            } else {
+                BCD32_MAX as i64
+            };
+            if !(0..=max).contains(&v) {
+                return Err(GatewayError::DataConversion(format!(
+                    "{} is outside the BCD range 0-{}",
+                    v, max
+                )));
+            }
+            if format == DataFormat::Bcd16 {
+                Ok(vec![encode_bcd_u16(v as u16)?])
+            } else {
+                Ok(encode_bcd_u32(v as u32, byte_order)?.to_vec())
+            }
+        }
     }
 }
 
-/// Reorder 2 registers (4 bytes) according to byte order.
-fn reorder_bytes_32(r0: u16, r1: u16, order: ByteOrder) -> [u8; 4] {
-    let a = (r0 >> 8) as u8;
-    let b = (r0 & 0xFF) as u8;
-    let c = (r1 >> 8) as u8;
-    let d = (r1 & 0xFF) as u8;
-
-    match order {
-        ByteOrder::Abcd => [a, b, c, d],
-        ByteOrder::Dcba => [d, c, b, a],
-        ByteOrder::Badc => [b, a, d, c],
-        ByteOrder::Cdab => [c, d, a, b],
-    }
+/// `value` as an integer of type `T`, refusing fractions, NaN, infinities
+/// and values out of `T`'s range (see [`Value::try_as_i64()`]).
+fn fit<T: TryFrom<i64>>(value: &Value, format: DataFormat) -> Result<T> {
+    let v = value.try_as_i64()?;
+    T::try_from(v).map_err(|_| {
+        GatewayError::DataConversion(format!("{} is outside the {:?} range", v, format))
+    })
 }
 
-/// Reorder 4 registers (8 bytes) according to byte order.
-fn reorder_bytes_64(regs: &[u16], order: ByteOrder) -> [u8; 8] {
-    let mut bytes = [0u8; 8];
-    for (i, &reg) in regs.iter().take(4).enumerate() {
-        bytes[i * 2] = (reg >> 8) as u8;
-        bytes[i * 2 + 1] = (reg & 0xFF) as u8;
-    }
+/// Decode a `u32` from the first 2 registers.
+pub fn decode_u32(registers: &[u16], order: ByteOrder) -> Result<u32> {
+    read_bytes(registers, order, "uint32").map(u32::from_be_bytes)
+}
 
-    match order {
-        ByteOrder::Abcd => bytes,
-        ByteOrder::Dcba => {
-            bytes.reverse();
-            bytes
-        }
-        ByteOrder::Badc => {
-            // Swap within each word
-            [
-                bytes[1], bytes[0], bytes[3], bytes[2], bytes[5], bytes[4], bytes[7], bytes[6],
-            ]
-        }
-        ByteOrder::Cdab => {
-            // Swap word pairs
-            [
-                bytes[2], bytes[3], bytes[0], bytes[1], bytes[6], bytes[7], bytes[4], bytes[5],
-            ]
-        }
-    }
+/// Decode an `i32` from the first 2 registers.
+pub fn decode_i32(registers: &[u16], order: ByteOrder) -> Result<i32> {
+    read_bytes(registers, order, "int32").map(i32::from_be_bytes)
 }
 
-/// Convert 4 bytes to 2 registers with byte order.
-fn reorder_to_registers_32(bytes: [u8; 4], order: ByteOrder) -> (u16, u16) {
-    let [a, b, c, d] = bytes;
+/// Decode an `f32` from the first 2 registers (NaN and infinities included).
+pub fn decode_f32(registers: &[u16], order: ByteOrder) -> Result<f32> {
+    read_bytes(registers, order, "float32").map(f32::from_be_bytes)
+}
 
-    let (b0, b1, b2, b3) = match order {
-        ByteOrder::Abcd => (a, b, c, d),
-        ByteOrder::Dcba => (d, c, b, a),
-        ByteOrder::Badc => (b, a, d, c),
-        ByteOrder::Cdab => (c, d, a, b),
-    };
+/// Decode a `u64` from the first 4 registers.
+pub fn decode_u64(registers: &[u16], order: ByteOrder) -> Result<u64> {
+    read_bytes(registers, order, "uint64").map(u64::from_be_bytes)
+}
 
-    let r0 = ((b0 as u16) << 8) | (b1 as u16);
-    let r1 = ((b2 as u16) << 8) | (b3 as u16);
-    (r0, r1)
+/// Decode an `i64` from the first 4 registers.
+pub fn decode_i64(registers: &[u16], order: ByteOrder) -> Result<i64> {
+    read_bytes(registers, order, "int64").map(i64::from_be_bytes)
 }
 
-/// Convert 8 bytes to 4 registers with byte order.
-fn reorder_to_registers_64(bytes: [u8; 8], order: ByteOrder) -> [u16; 4] {
-    let ordered = match order {
-        ByteOrder::Abcd => bytes,
-        ByteOrder::Dcba => {
-            let mut b = bytes;
-            b.reverse();
-            b
-        }
-        ByteOrder::Badc => [
-            bytes[1], bytes[0], bytes[3], bytes[2], bytes[5], bytes[4], bytes[7], bytes[6],
-        ],
-        ByteOrder::Cdab => [
-            bytes[2], bytes[3], bytes[0], bytes[1], bytes[6], bytes[7], bytes[4], bytes[5],
-        ],
-    };
+/// Decode an `f64` from the first 4 registers (NaN and infinities included).
+pub fn decode_f64(registers: &[u16], order: ByteOrder) -> Result<f64> {
+    read_bytes(registers, order, "float64").map(f64::from_be_bytes)
+}
+
+/// Encode a `u32` into 2 registers.
+pub fn encode_u32(value: u32, order: ByteOrder) -> [u16; 2] {
+    to_registers(value.to_be_bytes(), order)
+}
+
+/// Encode an `i32` into 2 registers.
+pub fn encode_i32(value: i32, order: ByteOrder) -> [u16; 2] {
+    to_registers(value.to_be_bytes(), order)
+}
+
+/// Encode an `f32` into 2 registers.
+pub fn encode_f32(value: f32, order: ByteOrder) -> [u16; 2] {
+    to_registers(value.to_be_bytes(), order)
+}
+
+/// Encode a `u64` into 4 registers.
+pub fn encode_u64(value: u64, order: ByteOrder) -> [u16; 4] {
+    to_registers(value.to_be_bytes(), order)
+}
+
+/// Encode an `i64` into 4 registers.
+pub fn encode_i64(value: i64, order: ByteOrder) -> [u16; 4] {
+    to_registers(value.to_be_bytes(), order)
+}
+
+/// Encode an `f64` into 4 registers.
+pub fn encode_f64(value: f64, order: ByteOrder) -> [u16; 4] {
+    to_registers(value.to_be_bytes(), order)
+}
+
+/// Big-endian value bytes from the first `N / 2` registers.
+fn read_bytes<const N: usize>(
+    registers: &[u16],
+    order: ByteOrder,
+    format: &str,
+) -> Result<[u8; N]> {
+    let count = N / 2;
+    if registers.len() < count {
+        return Err(GatewayError::invalid_data(format!(
+            "Need {} registers for {}",
+            count, format
+        )));
+    }
+    let mut bytes = [0u8; N];
+    for (pair, register) in bytes.chunks_exact_mut(2).zip(registers) {
+        pair.copy_from_slice(&register.to_be_bytes());
+    }
+    permute(&mut bytes, order);
+    Ok(bytes)
+}
+
+/// Registers holding big-endian value `bytes` (`R` = `N / 2`).
+fn to_registers<const N: usize, const R: usize>(mut bytes: [u8; N], order: ByteOrder) -> [u16; R] {
+    permute(&mut bytes, order);
+    let mut registers = [0u16; R];
+    for (register, pair) in registers.iter_mut().zip(bytes.chunks_exact(2)) {
+        *register = u16::from_be_bytes([pair[0], pair[1]]);
+    }
+    registers
+}
 
-    [
-        ((ordered[0] as u16) << 8) | (ordered[1] as u16),
-        ((ordered[2] as u16) << 8) | (ordered[3] as u16),
-        ((ordered[4] as u16) << 8) | (ordered[5] as u16),
-        ((ordered[6] as u16) << 8) | (ordered[7] as u16),
-    ]
+/// Convert between big-endian value bytes and register bytes in `order`.
+///
+/// Both swaps are their own inverse, so this works in either direction.
+fn permute(bytes: &mut [u8], order: ByteOrder) {
+    let (swap_bytes, low_first) = match order {
+        ByteOrder::Abcd => (false, false),
+        ByteOrder::Badc => (true, false),
+        ByteOrder::Cdab => (false, true),
+        ByteOrder::Dcba => (true, true),
+    };
+    // Reversing all bytes reverses the registers and swaps within them
+    if low_first {
+        bytes.reverse();
+    }
+    if swap_bytes != low_first {
+        for pair in bytes.chunks_exact_mut(2) {
+            pair.swap(0, 1);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -343,6 +418,166 @@ mod tests {
         assert_eq!(v.as_bool(), Some(false));
     }
 
+    const ORDERS: [ByteOrder; 4] = [
+        ByteOrder::Abcd,
+        ByteOrder::Badc,
+        ByteOrder::Cdab,
+        ByteOrder::Dcba,
+    ];
+
+    #[test]
+    fn test_byte_order_table() {
+        let expected_32 = [
+            [0x0A0B, 0x0C0D],
+            [0x0B0A, 0x0D0C],
+            [0x0C0D, 0x0A0B],
+            [0x0D0C, 0x0B0A],
+        ];
+        let expected_64 = [
+            [0x0A0B, 0x0C0D, 0x0E0F, 0x1011],
+            [0x0B0A, 0x0D0C, 0x0F0E, 0x1110],
+            [0x1011, 0x0E0F, 0x0C0D, 0x0A0B],
+            [0x1110, 0x0F0E, 0x0D0C, 0x0B0A],
+        ];
+        for (i, order) in ORDERS.into_iter().enumerate() {
+            assert_eq!(encode_u32(0x0A0B0C0D, order), expected_32[i], "{:?}", order);
+            assert_eq!(
+                encode_u64(0x0A0B0C0D0E0F1011, order),
+                expected_64[i],
+                "{:?}",
+                order
+            );
+            assert_eq!(decode_u32(&expected_32[i], order).unwrap(), 0x0A0B0C0D);
+            assert_eq!(
+                decode_u64(&expected_64[i], order).unwrap(),
+                0x0A0B0C0D0E0F1011
+            );
+        }
+        assert!(decode_u64(&[0, 0, 0], ByteOrder::Abcd).is_err());
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn prop_typed_roundtrip(
+            order in proptest::sample::select(&ORDERS[..]),
+            a in proptest::num::u32::ANY,
+            b in proptest::num::u64::ANY,
+        ) {
+            proptest::prop_assert_eq!(decode_u32(&encode_u32(a, order), order).unwrap(), a);
+            proptest::prop_assert_eq!(decode_i32(&encode_i32(a as i32, order), order).unwrap(), a as i32);
+            let f = f32::from_bits(a);
+            proptest::prop_assert_eq!(decode_f32(&encode_f32(f, order), order).unwrap().to_bits(), a);
+            proptest::prop_assert_eq!(decode_u64(&encode_u64(b, order), order).unwrap(), b);
+            proptest::prop_assert_eq!(decode_i64(&encode_i64(b as i64, order), order).unwrap(), b as i64);
+            let d = f64::from_bits(b);
+            proptest::prop_assert_eq!(decode_f64(&encode_f64(d, order), order).unwrap().to_bits(), b);
+        }
+
+        /// encode(decode(registers)) gives back the registers for every
+        /// numeric format and order (non-finite floats are rejected).
+        #[test]
+        fn prop_registers_roundtrip(
+            order in proptest::sample::select(&ORDERS[..]),
+            registers in proptest::array::uniform4(proptest::num::u16::ANY),
+        ) {
+            let formats = [
+                DataFormat::UInt16,
+                DataFormat::Int16,
+                DataFormat::UInt32,
+                DataFormat::Int32,
+                DataFormat::Float32,
+                DataFormat::UInt64,
+                DataFormat::Int64,
+                DataFormat::Float64,
+                DataFormat::Float16,
+                DataFormat::Bcd16,
+                DataFormat::Bcd32,
+            ];
+            for format in formats {
+                let used = &registers[..format.register_count() as usize];
+                let Ok(value) = decode_registers(used, format, order, None) else {
+                    continue;
+                };
+                let encoded = encode_registers(&value, format, order).unwrap();
+                proptest::prop_assert_eq!(&encoded[..], used, "{:?} {:?}", format, order);
+            }
+        }
+    }
+
+    #[test]
+    fn test_bcd_formats() {
+        let v = decode_registers(&[0x1234, 0x5678], DataFormat::Bcd32, ByteOrder::Abcd, None);
+        assert_eq!(v.unwrap().as_i64(), Some(12_345_678));
+        let v = decode_registers(&[0x0042], DataFormat::Bcd16, ByteOrder::Abcd, None);
+        assert_eq!(v.unwrap().as_i64(), Some(42));
+
+        let err = decode_registers(&[0x1234, 0x56F8], DataFormat::Bcd32, ByteOrder::Abcd, None);
+        assert!(matches!(err, Err(GatewayError::DataConversion(_))));
+
+        let regs = encode_registers(&Value::Float(9999.0), DataFormat::Bcd16, ByteOrder::Abcd);
+        assert_eq!(regs.unwrap(), vec![0x9999]);
+        for value in [-1, 10_000] {
+            let err = encode_registers(&Value::Integer(value), DataFormat::Bcd16, ByteOrder::Abcd);
+            assert!(matches!(err, Err(GatewayError::DataConversion(_))));
+        }
+    }
+
+    #[test]
+    fn test_encode_refuses_lossy_values() {
+        let encode = |value: Value, format| encode_registers(&value, format, ByteOrder::Abcd);
+
+        assert_eq!(
+            encode(Value::Integer(-5), DataFormat::Int16).unwrap(),
+            vec![0xFFFB]
+        );
+        assert_eq!(
+            encode(Value::Float(65535.0), DataFormat::UInt16).unwrap(),
+            vec![0xFFFF]
+        );
+        for (value, format) in [
+            (Value::Integer(65536), DataFormat::UInt16),
+            (Value::Integer(-1), DataFormat::UInt16),
+            (Value::Integer(40_000), DataFormat::Int16),
+            (Value::Float(1.5), DataFormat::UInt16),
+            (Value::Float(f64::NAN), DataFormat::Int32),
+            (Value::Integer(1 << 32), DataFormat::UInt32),
+            (Value::Float(f64::INFINITY), DataFormat::Int64),
+            (Value::Integer((1 << 53) + 1), DataFormat::Float64),
+        ] {
+            let err = encode(value.clone(), format);
+            assert!(
+                matches!(err, Err(GatewayError::DataConversion(_))),
+                "{:?} as {:?}: {:?}",
+                value,
+                format,
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn test_decode_register_array() {
+        let mut registers = Vec::new();
+        for v in [3.25f32, 3.5, -1.0] {
+            registers.extend(encode_f32(v, ByteOrder::Cdab));
+        }
+        assert_eq!(
+            decode_register_array(&registers, DataFormat::Float32, ByteOrder::Cdab, 3).unwrap(),
+            Value::Array(vec![
+                Value::Float(3.25),
+                Value::Float(3.5),
+                Value::Float(-1.0)
+            ])
+        );
+        assert_eq!(
+            decode_register_array(&[7, 8, 9], DataFormat::UInt16, ByteOrder::Abcd, 2).unwrap(),
+            Value::Array(vec![Value::Integer(7), Value::Integer(8)])
+        );
+        assert!(
+            decode_register_array(&registers, DataFormat::Float32, ByteOrder::Cdab, 4).is_err()
+        );
+    }
+
     #[test]
     fn test_encode_decode_roundtrip() {
         let original = Value::Float(123.456);
@@ -0,0 +1,94 @@
+//! Frame checksums for serial links.
+//!
+//! - [`crc16_modbus()`]: CRC-16/MODBUS (reflected poly `0xA001`, init
+//!   `0xFFFF`), as used by Modbus RTU and RTU-over-TCP. The CRC is sent
+//!   low byte first.
+//! - [`checksum8()`]: arithmetic sum modulo 256, as used by IEC 60870-5
+//!   FT1.2 frames (IEC 101).
+//!
+//! Everything here works on borrowed slices without allocating, so it can
+//! be reused outside the gateway.
+
+/// CRC-16/MODBUS lookup table, generated at compile time.
+const CRC16_MODBUS_TABLE: [u16; 256] = crc16_table(0xA001);
+
+const fn crc16_table(poly: u16) -> [u16; 256] {
+    let mut table = [0u16; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u16;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ poly
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// CRC-16/MODBUS of `data`.
+pub fn crc16_modbus(data: &[u8]) -> u16 {
+    data.iter().fold(0xFFFF, |crc, &byte| {
+        (crc >> 8) ^ CRC16_MODBUS_TABLE[((crc ^ byte as u16) & 0xFF) as usize]
+    })
+}
+
+/// Check a frame whose last two bytes are its CRC-16/MODBUS (low byte first).
+///
+/// Frames shorter than three bytes are rejected.
+pub fn verify_crc16_modbus(frame: &[u8]) -> bool {
+    if frame.len() < 3 {
+        return false;
+    }
+    let (body, crc) = frame.split_at(frame.len() - 2);
+    crc16_modbus(body) == u16::from_le_bytes([crc[0], crc[1]])
+}
+
+/// Arithmetic sum of `data` modulo 256.
+pub fn checksum8(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crc16_modbus_reference_vectors() {
+        // CRC catalogue check value
+        assert_eq!(crc16_modbus(b"123456789"), 0x4B37);
+        assert_eq!(crc16_modbus(&[]), 0xFFFF);
+        // Modbus over serial line guide, read holding registers example
+        assert_eq!(crc16_modbus(&[0x11, 0x03, 0x00, 0x6B, 0x00, 0x03]), 0x8776);
+        assert_eq!(crc16_modbus(&[0x01, 0x03, 0x00, 0x00, 0x00, 0x0A]), 0xCDC5);
+    }
+
+    #[test]
+    fn test_verify_crc16_modbus() {
+        let frame = [0x11, 0x03, 0x00, 0x6B, 0x00, 0x03, 0x76, 0x87];
+        assert!(verify_crc16_modbus(&frame));
+
+        let mut corrupted = frame;
+        corrupted[3] ^= 0x01;
+        assert!(!verify_crc16_modbus(&corrupted));
+        // Byte-swapped CRC
+        assert!(!verify_crc16_modbus(&[
+            0x11, 0x03, 0x00, 0x6B, 0x00, 0x03, 0x87, 0x76
+        ]));
+        assert!(!verify_crc16_modbus(&[0xFF, 0xFF]));
+    }
+
+    #[test]
+    fn test_checksum8() {
+        // FT1.2 fixed frame 10 53 01 54 16: checksum over control + address
+        assert_eq!(checksum8(&[0x53, 0x01]), 0x54);
+        assert_eq!(checksum8(&[0xFF, 0x02]), 0x01);
+        assert_eq!(checksum8(&[]), 0);
+    }
+}
@@ -0,0 +1,117 @@
+//! IEEE 754 half-precision (binary16) conversion.
+//!
+//! A half float has 1 sign bit, 5 exponent bits (bias 15) and 10 mantissa
+//! bits, for a range of ±65504 with about 3 significant digits.
+//!
+//! Meters only report normal numbers and zero. Infinities, NaN and
+//! subnormals are rejected on decode with [`GatewayError::DataConversion`],
+//! which marks the point `Quality::Invalid` instead of passing on garbage.
+
+use crate::core::error::{GatewayError, Result};
+
+/// Largest finite half float.
+pub const F16_MAX: f64 = 65504.0;
+
+/// Smallest positive normal half float (2^-14).
+pub const F16_MIN_POSITIVE: f64 = 6.103_515_625e-5;
+
+/// Decode a half float from its bit pattern.
+pub fn decode_f16(bits: u16) -> Result<f64> {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((bits >> 10) & 0x1F) as i32;
+    let mantissa = (bits & 0x3FF) as f64;
+    match exponent {
+        0 if mantissa == 0.0 => Ok(sign * 0.0),
+        0 => Err(GatewayError::DataConversion(format!(
+            "Subnormal float16 0x{:04X}",
+            bits
+        ))),
+        0x1F => Err(GatewayError::DataConversion(format!(
+            "Non-finite float16 0x{:04X}",
+            bits
+        ))),
+        _ => Ok(sign * (1.0 + mantissa / 1024.0) * 2f64.powi(exponent - 15)),
+    }
+}
+
+/// Encode a value as a half float, rounding to nearest (ties to even).
+///
+/// Values too small for a normal half float become zero; values beyond
+/// [`F16_MAX`] and non-finite values fail.
+pub fn encode_f16(value: f64) -> Result<u16> {
+    if !value.is_finite() {
+        return Err(GatewayError::DataConversion(format!(
+            "{} cannot be encoded as float16",
+            value
+        )));
+    }
+    let sign: u16 = if value.is_sign_negative() { 0x8000 } else { 0 };
+    let magnitude = value.abs();
+    if magnitude < F16_MIN_POSITIVE {
+        return Ok(sign);
+    }
+
+    let mut exponent = ((magnitude.to_bits() >> 52) & 0x7FF) as i32 - 1023;
+    let mut mantissa = ((magnitude / 2f64.powi(exponent) - 1.0) * 1024.0).round_ties_even();
+    if mantissa >= 1024.0 {
+        exponent += 1;
+        mantissa = 0.0;
+    }
+    if exponent > 15 {
+        return Err(GatewayError::DataConversion(format!(
+            "{} is outside the float16 range ±{}",
+            value, F16_MAX
+        )));
+    }
+    Ok(sign | (((exponent + 15) as u16) << 10) | mantissa as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_f16() {
+        assert_eq!(decode_f16(0x3C00).unwrap(), 1.0);
+        assert_eq!(decode_f16(0xC000).unwrap(), -2.0);
+        assert_eq!(decode_f16(0x3555).unwrap(), 0.333_251_953_125);
+        assert_eq!(decode_f16(0x7BFF).unwrap(), F16_MAX);
+        assert_eq!(decode_f16(0x0400).unwrap(), F16_MIN_POSITIVE);
+        assert_eq!(decode_f16(0x0000).unwrap(), 0.0);
+        assert!(decode_f16(0x8000).unwrap().is_sign_negative());
+    }
+
+    #[test]
+    fn test_decode_f16_rejects_special_values() {
+        for bits in [0x7C00, 0xFC00, 0x7E00, 0x7C01, 0x0001, 0x83FF] {
+            let err = decode_f16(bits).unwrap_err();
+            assert!(
+                matches!(err, GatewayError::DataConversion(_)),
+                "{:04X}",
+                bits
+            );
+        }
+    }
+
+    #[test]
+    fn test_encode_f16() {
+        assert_eq!(encode_f16(1.0).unwrap(), 0x3C00);
+        assert_eq!(encode_f16(-2.0).unwrap(), 0xC000);
+        assert_eq!(encode_f16(F16_MAX).unwrap(), 0x7BFF);
+        assert_eq!(encode_f16(230.4).unwrap(), 0x5B33);
+        // 1 + 2^-11 is halfway between 1.0 and the next half float: ties to even
+        assert_eq!(encode_f16(1.0 + 2f64.powi(-11)).unwrap(), 0x3C00);
+        // Rounds up into the next exponent
+        assert_eq!(encode_f16(2.0 - 2f64.powi(-12)).unwrap(), 0x4000);
+        assert_eq!(encode_f16(1e-6).unwrap(), 0x0000);
+        assert!(encode_f16(65520.0).is_err());
+        assert!(encode_f16(f64::NAN).is_err());
+    }
+
+    #[test]
+    fn test_f16_roundtrip() {
+        for bits in (0..=u16::MAX).filter(|b| (b >> 10) & 0x1F != 0 && (b >> 10) & 0x1F != 0x1F) {
+            assert_eq!(encode_f16(decode_f16(bits).unwrap()).unwrap(), bits);
+        }
+    }
+}
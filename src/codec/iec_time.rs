@@ -0,0 +1,333 @@
+//! IEC 60870-5 time tags (CP56Time2a and CP24Time2a).
+//!
+//! The IEC 104 channel uses these for the source time of received points
+//! and for clock synchronization.
+//!
+//! # CP56Time2a (7 bytes)
+//!
+//! | Byte | Bits | Field |
+//! |------|------|-------|
+//! | 0-1 | 16 | milliseconds within the minute (0-59999), little-endian |
+//! | 2 | 0-5 | minute, bit 7 = IV (invalid) |
+//! | 3 | 0-4 | hour, bit 7 = SU (summer time) |
+//! | 4 | 0-4 / 5-7 | day of month (1-31) / day of week (1 = Monday, 0 = unused) |
+//! | 5 | 0-3 | month (1-12) |
+//! | 6 | 0-6 | year within the century (0-99, i.e. 2000-2099) |
+//!
+//! CP24Time2a is the first three bytes: milliseconds and minute. It only
+//! identifies an instant relative to a reference time, see
+//! [`Cp24Time2a::resolve()`].
+//!
+//! Times are treated as UTC. The summer-time bit is carried through but
+//! does not shift the time. A time tag with the IV bit set is rejected
+//! with [`GatewayError::DataConversion`], as is any out-of-range field, so
+//! a bad tag never turns into a wrong timestamp. The day of week is written
+//! on encode but not checked on decode, as many devices leave it at 0.
+
+use chrono::{DateTime, Datelike, NaiveDate, TimeZone, Timelike, Utc};
+
+use crate::core::error::{GatewayError, Result};
+
+/// Invalid flag (byte 2, bit 7).
+const IV: u8 = 0x80;
+
+/// Summer time flag (byte 3, bit 7).
+const SU: u8 = 0x80;
+
+/// A decoded CP56Time2a time tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cp56Time2a {
+    /// The time, to millisecond precision.
+    pub time: DateTime<Utc>,
+    /// Summer time (SU) bit.
+    pub summer_time: bool,
+}
+
+impl Cp56Time2a {
+    /// Time tag for `time` with the summer-time bit clear.
+    pub fn new(time: DateTime<Utc>) -> Self {
+        Self {
+            time,
+            summer_time: false,
+        }
+    }
+
+    /// Set the summer-time bit.
+    pub fn with_summer_time(mut self, summer_time: bool) -> Self {
+        self.summer_time = summer_time;
+        self
+    }
+
+    /// Encode into 7 bytes.
+    ///
+    /// Fails for years outside 2000-2099, which the format cannot hold.
+    /// Sub-millisecond precision is truncated.
+    pub fn encode(&self) -> Result<[u8; 7]> {
+        let time = self.time;
+        if !(2000..=2099).contains(&time.year()) {
+            return Err(GatewayError::DataConversion(format!(
+                "CP56Time2a cannot hold year {}",
+                time.year()
+            )));
+        }
+        let ms = millis_in_minute(&time);
+        let weekday = time.weekday().number_from_monday() as u8;
+        Ok([
+            ms as u8,
+            (ms >> 8) as u8,
+            time.minute() as u8,
+            time.hour() as u8 | if self.summer_time { SU } else { 0 },
+            time.day() as u8 | (weekday << 5),
+            time.month() as u8,
+            (time.year() - 2000) as u8,
+        ])
+    }
+
+    /// Decode the first 7 bytes of `bytes`.
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        let Some(bytes) = bytes.first_chunk::<7>() else {
+            return Err(GatewayError::DataConversion(format!(
+                "CP56Time2a needs 7 bytes, got {}",
+                bytes.len()
+            )));
+        };
+        let (ms, minute) = decode_ms_minute(bytes[0], bytes[1], bytes[2])?;
+        let hour = bytes[3] & 0x1F;
+        let day = bytes[4] & 0x1F;
+        let month = bytes[5] & 0x0F;
+        let year = 2000 + (bytes[6] & 0x7F) as i32;
+
+        let time = NaiveDate::from_ymd_opt(year, month as u32, day as u32)
+            .and_then(|date| {
+                date.and_hms_milli_opt(
+                    hour as u32,
+                    minute as u32,
+                    (ms / 1000) as u32,
+                    (ms % 1000) as u32,
+                )
+            })
+            .ok_or_else(|| {
+                GatewayError::DataConversion(format!(
+                    "Invalid CP56Time2a date {:04}-{:02}-{:02} {:02}:{:02}",
+                    year, month, day, hour, minute
+                ))
+            })?;
+
+        Ok(Self {
+            time: Utc.from_utc_datetime(&time),
+            summer_time: bytes[3] & SU != 0,
+        })
+    }
+}
+
+/// A decoded CP24Time2a time tag: a position within an hour.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cp24Time2a {
+    /// Minute (0-59).
+    pub minute: u8,
+    /// Milliseconds within the minute (0-59999).
+    pub millisecond: u16,
+}
+
+impl Cp24Time2a {
+    /// Time tag for the minute and millisecond of `time`.
+    pub fn from_time(time: &DateTime<Utc>) -> Self {
+        Self {
+            minute: time.minute() as u8,
+            millisecond: millis_in_minute(time),
+        }
+    }
+
+    /// Encode into 3 bytes.
+    pub fn encode(&self) -> [u8; 3] {
+        [
+            self.millisecond as u8,
+            (self.millisecond >> 8) as u8,
+            self.minute & 0x3F,
+        ]
+    }
+
+    /// Decode the first 3 bytes of `bytes`.
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        let Some(bytes) = bytes.first_chunk::<3>() else {
+            return Err(GatewayError::DataConversion(format!(
+                "CP24Time2a needs 3 bytes, got {}",
+                bytes.len()
+            )));
+        };
+        let (millisecond, minute) = decode_ms_minute(bytes[0], bytes[1], bytes[2])?;
+        Ok(Self {
+            minute,
+            millisecond,
+        })
+    }
+
+    /// The latest instant at or before `reference` with this minute and
+    /// millisecond, i.e. within the hour before `reference`.
+    ///
+    /// `reference` is usually the receive time, since a CP24Time2a event
+    /// has already happened when it arrives.
+    pub fn resolve(&self, reference: DateTime<Utc>) -> DateTime<Utc> {
+        let hour_start = reference
+            .with_minute(0)
+            .and_then(|t| t.with_second(0))
+            .and_then(|t| t.with_nanosecond(0))
+            .unwrap_or(reference);
+        let time = hour_start
+            + chrono::Duration::minutes(self.minute as i64)
+            + chrono::Duration::milliseconds(self.millisecond as i64);
+        if time > reference {
+            time - chrono::Duration::hours(1)
+        } else {
+            time
+        }
+    }
+}
+
+/// Milliseconds within the minute; a leap second counts as second 59.
+fn millis_in_minute(time: &DateTime<Utc>) -> u16 {
+    (time.second() * 1000 + (time.nanosecond() % 1_000_000_000) / 1_000_000) as u16
+}
+
+/// Shared first three bytes of both formats.
+fn decode_ms_minute(ms_lo: u8, ms_hi: u8, minute: u8) -> Result<(u16, u8)> {
+    if minute & IV != 0 {
+        return Err(GatewayError::DataConversion(
+            "Time tag is flagged invalid (IV)".into(),
+        ));
+    }
+    let ms = u16::from_le_bytes([ms_lo, ms_hi]);
+    let minute = minute & 0x3F;
+    if ms > 59_999 || minute > 59 {
+        return Err(GatewayError::DataConversion(format!(
+            "Invalid time tag minute {} / millisecond {}",
+            minute, ms
+        )));
+    }
+    Ok((ms, minute))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(y: i32, mo: u32, d: u32, h: u32, mi: u32, s: u32, ms: u32) -> DateTime<Utc> {
+        Utc.from_utc_datetime(
+            &NaiveDate::from_ymd_opt(y, mo, d)
+                .unwrap()
+                .and_hms_milli_opt(h, mi, s, ms)
+                .unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_cp56_field_packing() {
+        // Tuesday 2024-03-12 13:45:56.789
+        let bytes = Cp56Time2a::new(utc(2024, 3, 12, 13, 45, 56, 789))
+            .with_summer_time(true)
+            .encode()
+            .unwrap();
+        // 56789 ms = 0xDDD5, little-endian
+        assert_eq!(bytes, [0xD5, 0xDD, 45, 13 | 0x80, 12 | (2 << 5), 3, 24]);
+
+        let decoded = Cp56Time2a::decode(&bytes).unwrap();
+        assert_eq!(decoded.time, utc(2024, 3, 12, 13, 45, 56, 789));
+        assert!(decoded.summer_time);
+    }
+
+    #[test]
+    fn test_cp56_leap_days() {
+        let leap = utc(2024, 2, 29, 23, 59, 59, 999);
+        let bytes = Cp56Time2a::new(leap).encode().unwrap();
+        assert_eq!(Cp56Time2a::decode(&bytes).unwrap().time, leap);
+        // Thursday
+        assert_eq!(bytes[4] >> 5, 4);
+
+        // 2023-02-29 does not exist
+        let bytes = [0, 0, 0, 0, 29, 2, 23];
+        assert!(Cp56Time2a::decode(&bytes).is_err());
+        // 2000 was a leap year (divisible by 400)
+        let bytes = [0, 0, 0, 0, 29, 2, 0];
+        assert_eq!(
+            Cp56Time2a::decode(&bytes).unwrap().time,
+            utc(2000, 2, 29, 0, 0, 0, 0)
+        );
+    }
+
+    #[test]
+    fn test_cp56_rejects_invalid() {
+        let mut bytes = Cp56Time2a::new(utc(2025, 1, 1, 0, 0, 0, 0))
+            .encode()
+            .unwrap();
+        assert!(Cp56Time2a::decode(&bytes).is_ok());
+
+        bytes[2] |= IV;
+        let err = Cp56Time2a::decode(&bytes).unwrap_err();
+        assert!(matches!(err, GatewayError::DataConversion(_)));
+
+        for bad in [
+            [0x60, 0xEA, 0, 0, 1, 1, 25], // 60000 ms
+            [0, 0, 60, 0, 1, 1, 25],      // minute 60
+            [0, 0, 0, 24, 1, 1, 25],      // hour 24
+            [0, 0, 0, 0, 0, 1, 25],       // day 0
+            [0, 0, 0, 0, 1, 13, 25],      // month 13
+            [0, 0, 0, 0, 31, 4, 25],      // 31 April
+        ] {
+            assert!(Cp56Time2a::decode(&bad).is_err(), "{:?}", bad);
+        }
+        assert!(Cp56Time2a::decode(&bytes[..6]).is_err());
+        assert!(Cp56Time2a::new(utc(1999, 12, 31, 0, 0, 0, 0))
+            .encode()
+            .is_err());
+    }
+
+    #[test]
+    fn test_cp56_ignores_reserved_bits_and_weekday() {
+        let bytes = [0x10, 0x27, 5 | 0x40, 6 | 0x60, 7, 8 | 0xF0, 26 | 0x80];
+        let decoded = Cp56Time2a::decode(&bytes).unwrap();
+        assert_eq!(decoded.time, utc(2026, 8, 7, 6, 5, 10, 0));
+        assert!(!decoded.summer_time);
+    }
+
+    #[test]
+    fn test_cp24() {
+        let time = utc(2025, 6, 1, 10, 20, 30, 400);
+        let tag = Cp24Time2a::from_time(&time);
+        let bytes = tag.encode();
+        assert_eq!(bytes, [0xC0, 0x76, 20]);
+        assert_eq!(Cp24Time2a::decode(&bytes).unwrap(), tag);
+
+        // Same hour
+        assert_eq!(tag.resolve(utc(2025, 6, 1, 10, 25, 0, 0)), time);
+        assert_eq!(tag.resolve(time), time);
+        // Received after the hour rolled over
+        assert_eq!(tag.resolve(utc(2025, 6, 1, 11, 0, 1, 0)), time);
+        // Across midnight
+        let tag = Cp24Time2a::from_time(&utc(2025, 6, 1, 23, 59, 59, 0));
+        assert_eq!(
+            tag.resolve(utc(2025, 6, 2, 0, 0, 2, 0)),
+            utc(2025, 6, 1, 23, 59, 59, 0)
+        );
+
+        assert!(Cp24Time2a::decode(&[0, 0, IV]).is_err());
+        assert!(Cp24Time2a::decode(&[0x60, 0xEA, 0]).is_err());
+        assert!(Cp24Time2a::decode(&[0, 0]).is_err());
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn prop_cp56_roundtrip(
+            millis in 946_684_800_000i64..4_102_444_800_000i64,
+            summer_time in proptest::bool::ANY,
+        ) {
+            let time = Utc.timestamp_millis_opt(millis).unwrap();
+            let tag = Cp56Time2a::new(time).with_summer_time(summer_time);
+            let bytes = tag.encode().unwrap();
+            proptest::prop_assert_eq!(Cp56Time2a::decode(&bytes).unwrap(), tag);
+            proptest::prop_assert_eq!(
+                (bytes[4] >> 5) as u32,
+                time.weekday().number_from_monday()
+            );
+        }
+    }
+}
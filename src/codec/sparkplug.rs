@@ -0,0 +1,715 @@
+//! Sparkplug B payloads (feature `sparkplug`).
+//!
+//! [`SparkplugNode`] turns igw data into the protobuf payloads an edge node
+//! publishes to a Sparkplug B host such as Ignition, and decodes the
+//! commands the host sends back. It does not talk MQTT itself; the caller
+//! publishes the returned bytes on [`SparkplugNode::topic()`]:
+//!
+//! 1. Before connecting, register [`death()`](SparkplugNode::death) as the
+//!    MQTT last will on the `NDEATH` topic.
+//! 2. After connecting, publish [`birth()`](SparkplugNode::birth) on
+//!    `NBIRTH`: every registered point with its name, alias, data type,
+//!    engineering unit and current value.
+//! 3. Publish [`data()`](SparkplugNode::data) on `NDATA` for every batch of
+//!    changes; metrics are sent by alias only.
+//! 4. Decode `NCMD` payloads with
+//!    [`decode_command()`](SparkplugNode::decode_command). A rebirth request
+//!    means: publish `birth()` again.
+//! 5. On reconnect, call [`next_session()`](SparkplugNode::next_session)
+//!    and start over at 1.
+//!
+//! Metric names are `<channel name>/<point name>` and aliases are derived
+//! from `(channel id, point id)`, so they stay stable across restarts.
+//! Points with a bad or uncertain quality carry a `Quality` property with
+//! the OPC DA code (0 = bad, 64 = uncertain).
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! let mut node = SparkplugNode::new("plant", "igw-1");
+//! node.add_points(1, "PLC1", &point_configs);
+//! let will = (node.topic(MessageType::NDeath), node.death());
+//! // connect with `will` ...
+//! client.publish(node.topic(MessageType::NBirth), node.birth(&current)).await?;
+//! if let Some(payload) = node.data(1, &batch) {
+//!     client.publish(node.topic(MessageType::NData), payload).await?;
+//! }
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+
+use prost::Message;
+
+use crate::core::data::{DataBatch, DataPoint, PointId, Value};
+use crate::core::error::{GatewayError, Result};
+use crate::core::point::{DataFormat, PointConfig, ProtocolAddress};
+use crate::core::traits::{AdjustmentCommand, ControlCommand};
+
+/// Sparkplug B protobuf messages (the subset igw uses).
+pub mod proto {
+    /// Top-level payload.
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Payload {
+        /// Milliseconds since the Unix epoch.
+        #[prost(uint64, optional, tag = "1")]
+        pub timestamp: Option<u64>,
+        /// Metrics.
+        #[prost(message, repeated, tag = "2")]
+        pub metrics: Vec<Metric>,
+        /// Sequence number (0-255), absent in `NDEATH`.
+        #[prost(uint64, optional, tag = "3")]
+        pub seq: Option<u64>,
+    }
+
+    /// One metric.
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Metric {
+        /// Name (birth certificates and commands).
+        #[prost(string, optional, tag = "1")]
+        pub name: Option<String>,
+        /// Alias replacing the name after the birth certificate.
+        #[prost(uint64, optional, tag = "2")]
+        pub alias: Option<u64>,
+        /// Milliseconds since the Unix epoch.
+        #[prost(uint64, optional, tag = "3")]
+        pub timestamp: Option<u64>,
+        /// [`DataType`](super::DataType) code.
+        #[prost(uint32, optional, tag = "4")]
+        pub datatype: Option<u32>,
+        /// Set when the metric has no value.
+        #[prost(bool, optional, tag = "7")]
+        pub is_null: Option<bool>,
+        /// Properties such as the engineering unit.
+        #[prost(message, optional, tag = "9")]
+        pub properties: Option<PropertySet>,
+        /// Value.
+        #[prost(oneof = "MetricValue", tags = "10, 11, 12, 13, 14, 15")]
+        pub value: Option<MetricValue>,
+    }
+
+    /// Metric value.
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum MetricValue {
+        /// 8 to 32-bit integers.
+        #[prost(uint32, tag = "10")]
+        IntValue(u32),
+        /// 64-bit integers (two's complement for signed types).
+        #[prost(uint64, tag = "11")]
+        LongValue(u64),
+        /// Float.
+        #[prost(float, tag = "12")]
+        FloatValue(f32),
+        /// Double.
+        #[prost(double, tag = "13")]
+        DoubleValue(f64),
+        /// Boolean.
+        #[prost(bool, tag = "14")]
+        BooleanValue(bool),
+        /// String.
+        #[prost(string, tag = "15")]
+        StringValue(String),
+    }
+
+    /// Metric properties as parallel key and value lists.
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct PropertySet {
+        /// Keys.
+        #[prost(string, repeated, tag = "1")]
+        pub keys: Vec<String>,
+        /// Values, one per key.
+        #[prost(message, repeated, tag = "2")]
+        pub values: Vec<PropertyValue>,
+    }
+
+    /// Property value.
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct PropertyValue {
+        /// [`DataType`](super::DataType) code.
+        #[prost(uint32, optional, tag = "1")]
+        pub r#type: Option<u32>,
+        /// Value.
+        #[prost(oneof = "PropertyValueKind", tags = "3, 8")]
+        pub value: Option<PropertyValueKind>,
+    }
+
+    /// Property value kinds igw uses.
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum PropertyValueKind {
+        /// 8 to 32-bit integers.
+        #[prost(uint32, tag = "3")]
+        IntValue(u32),
+        /// String.
+        #[prost(string, tag = "8")]
+        StringValue(String),
+    }
+}
+
+use proto::{Metric, MetricValue, Payload, PropertySet, PropertyValue, PropertyValueKind};
+
+/// Sparkplug B namespace prefix of every topic.
+pub const NAMESPACE: &str = "spBv1.0";
+
+/// Metric a host writes `true` to when it wants a new birth certificate.
+pub const REBIRTH_METRIC: &str = "Node Control/Rebirth";
+
+/// Metric carrying the birth/death sequence number.
+const BD_SEQ_METRIC: &str = "bdSeq";
+
+/// Sparkplug B metric data types igw produces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum DataType {
+    /// Signed 32-bit integer.
+    Int32 = 3,
+    /// Signed 64-bit integer.
+    Int64 = 4,
+    /// 64-bit float.
+    Double = 10,
+    /// Boolean.
+    Boolean = 11,
+    /// UTF-8 string.
+    String = 12,
+}
+
+impl DataType {
+    /// Type of a point, from its address format and transform.
+    ///
+    /// `None` if the configuration does not tell (non-Modbus addresses).
+    fn of_point(point: &PointConfig) -> Option<Self> {
+        if point.transform.enum_map.is_some() {
+            return Some(Self::String);
+        }
+        let ProtocolAddress::Modbus(address) = &point.address else {
+            return None;
+        };
+        let scaled = point.transform.scale != 1.0
+            || point.transform.offset != 0.0
+            || !point.transform.kind.is_linear();
+        Some(match address.format {
+            DataFormat::Bool => Self::Boolean,
+            DataFormat::String => Self::String,
+            DataFormat::Float16 | DataFormat::Float32 | DataFormat::Float64 => Self::Double,
+            _ if scaled => Self::Double,
+            _ => Self::Int64,
+        })
+    }
+
+    /// Type of a value; `Double` for values without one.
+    fn of_value(value: &Value) -> Self {
+        match value {
+            Value::Bool(_) => Self::Boolean,
+            Value::Integer(_) => Self::Int64,
+            Value::String(_) => Self::String,
+            _ => Self::Double,
+        }
+    }
+}
+
+/// Sparkplug B message types of an edge node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageType {
+    /// Birth certificate.
+    NBirth,
+    /// Death certificate (MQTT last will).
+    NDeath,
+    /// Data changes.
+    NData,
+    /// Command from the host.
+    NCmd,
+}
+
+impl fmt::Display for MessageType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::NBirth => "NBIRTH",
+            Self::NDeath => "NDEATH",
+            Self::NData => "NDATA",
+            Self::NCmd => "NCMD",
+        })
+    }
+}
+
+/// A decoded host command.
+#[derive(Debug, Clone)]
+pub enum NodeCommand {
+    /// Publish the birth certificate again.
+    Rebirth,
+    /// Control command for a point of a channel.
+    Control {
+        /// Channel id.
+        channel_id: u32,
+        /// Command.
+        command: ControlCommand,
+    },
+    /// Adjustment command for a point of a channel.
+    Adjustment {
+        /// Channel id.
+        channel_id: u32,
+        /// Command.
+        command: AdjustmentCommand,
+    },
+}
+
+/// A point published as a metric.
+#[derive(Debug, Clone)]
+struct MetricDef {
+    channel_id: u32,
+    point_id: PointId,
+    name: String,
+    datatype: Option<DataType>,
+    unit: Option<String>,
+}
+
+impl MetricDef {
+    fn alias(&self) -> u64 {
+        alias(self.channel_id, self.point_id)
+    }
+}
+
+fn alias(channel_id: u32, point_id: PointId) -> u64 {
+    (u64::from(channel_id) << 32) | u64::from(point_id)
+}
+
+/// Sparkplug B edge node state: metric definitions and sequence numbers.
+#[derive(Debug, Clone)]
+pub struct SparkplugNode {
+    group_id: String,
+    edge_node_id: String,
+    metrics: Vec<MetricDef>,
+    by_alias: HashMap<u64, usize>,
+    by_name: HashMap<String, usize>,
+    bd_seq: u8,
+    seq: u8,
+}
+
+impl SparkplugNode {
+    /// Create an edge node `edge_node_id` in group `group_id`.
+    pub fn new(group_id: impl Into<String>, edge_node_id: impl Into<String>) -> Self {
+        Self {
+            group_id: group_id.into(),
+            edge_node_id: edge_node_id.into(),
+            metrics: Vec::new(),
+            by_alias: HashMap::new(),
+            by_name: HashMap::new(),
+            bd_seq: 0,
+            seq: 0,
+        }
+    }
+
+    /// Topic of `message` for this node.
+    pub fn topic(&self, message: MessageType) -> String {
+        format!(
+            "{}/{}/{}/{}",
+            NAMESPACE, self.group_id, message, self.edge_node_id
+        )
+    }
+
+    /// Publish the enabled points of a channel as metrics.
+    ///
+    /// Points registered before are updated. Takes effect with the next
+    /// birth certificate.
+    pub fn add_points(&mut self, channel_id: u32, channel_name: &str, points: &[PointConfig]) {
+        for point in points.iter().filter(|p| p.enabled) {
+            let point_name = point.name.clone().unwrap_or_else(|| point.id.to_string());
+            let metric = MetricDef {
+                channel_id,
+                point_id: point.id,
+                name: format!("{}/{}", channel_name, point_name),
+                datatype: DataType::of_point(point),
+                unit: point.unit.clone(),
+            };
+            let alias = metric.alias();
+            match self.by_alias.get(&alias) {
+                Some(&index) => {
+                    self.by_name.remove(&self.metrics[index].name);
+                    self.by_name.insert(metric.name.clone(), index);
+                    self.metrics[index] = metric;
+                }
+                None => {
+                    self.by_alias.insert(alias, self.metrics.len());
+                    self.by_name.insert(metric.name.clone(), self.metrics.len());
+                    self.metrics.push(metric);
+                }
+            }
+        }
+    }
+
+    /// `NDEATH` payload to register as the MQTT last will.
+    pub fn death(&self) -> Vec<u8> {
+        Payload {
+            timestamp: Some(now_ms()),
+            metrics: vec![self.bd_seq_metric()],
+            seq: None,
+        }
+        .encode_to_vec()
+    }
+
+    /// `NBIRTH` payload with every metric and its current value from
+    /// `current` (`(channel id, batch)` pairs). Restarts the sequence.
+    ///
+    /// Data types that the point configuration does not tell are taken from
+    /// the current value and kept for later `NDATA` messages.
+    pub fn birth(&mut self, current: &[(u32, DataBatch)]) -> Vec<u8> {
+        let values: HashMap<u64, &DataPoint> = current
+            .iter()
+            .flat_map(|(channel_id, batch)| {
+                batch.iter().map(move |p| (alias(*channel_id, p.id), p))
+            })
+            .collect();
+
+        let mut metrics = vec![
+            self.bd_seq_metric(),
+            Metric {
+                name: Some(REBIRTH_METRIC.into()),
+                datatype: Some(DataType::Boolean as u32),
+                value: Some(MetricValue::BooleanValue(false)),
+                ..Default::default()
+            },
+        ];
+        for def in &mut self.metrics {
+            let point = values.get(&def.alias()).copied();
+            let datatype = *def.datatype.get_or_insert_with(|| {
+                point.map_or(DataType::Double, |p| DataType::of_value(&p.value))
+            });
+
+            let mut metric = match point {
+                Some(point) => metric(datatype, point),
+                None => Metric {
+                    is_null: Some(true),
+                    ..Default::default()
+                },
+            };
+            metric.name = Some(def.name.clone());
+            metric.alias = Some(def.alias());
+            metric.datatype = Some(datatype as u32);
+            if let Some(unit) = &def.unit {
+                let properties = metric.properties.get_or_insert_with(Default::default);
+                properties.keys.push("engUnit".into());
+                properties.values.push(PropertyValue {
+                    r#type: Some(DataType::String as u32),
+                    value: Some(PropertyValueKind::StringValue(unit.clone())),
+                });
+            }
+            metrics.push(metric);
+        }
+
+        self.seq = 0;
+        self.payload(metrics)
+    }
+
+    /// `NDATA` payload for a batch of changes of a channel; `None` if the
+    /// batch has no registered point.
+    pub fn data(&mut self, channel_id: u32, batch: &DataBatch) -> Option<Vec<u8>> {
+        let metrics: Vec<Metric> = batch
+            .iter()
+            .filter_map(|point| {
+                let alias = alias(channel_id, point.id);
+                let def = &self.metrics[*self.by_alias.get(&alias)?];
+                let datatype = def.datatype.unwrap_or(DataType::Double);
+                Some(Metric {
+                    alias: Some(alias),
+                    ..metric(datatype, point)
+                })
+            })
+            .collect();
+        if metrics.is_empty() {
+            return None;
+        }
+        Some(self.payload(metrics))
+    }
+
+    /// Start a new MQTT session: the next death and birth certificates get
+    /// the next `bdSeq`.
+    pub fn next_session(&mut self) {
+        self.bd_seq = self.bd_seq.wrapping_add(1);
+    }
+
+    /// Decode an `NCMD` payload.
+    ///
+    /// Metrics are matched by alias or name. Boolean metrics become control
+    /// commands, numeric ones adjustment commands (control commands for
+    /// `Boolean` points).
+    pub fn decode_command(&self, payload: &[u8]) -> Result<Vec<NodeCommand>> {
+        let payload = Payload::decode(payload)
+            .map_err(|e| GatewayError::InvalidData(format!("Sparkplug payload: {}", e)))?;
+
+        let mut commands = Vec::new();
+        for metric in payload.metrics {
+            if metric.name.as_deref() == Some(REBIRTH_METRIC) {
+                if matches!(metric.value, Some(MetricValue::BooleanValue(true))) {
+                    commands.push(NodeCommand::Rebirth);
+                }
+                continue;
+            }
+
+            let index = match (metric.alias, &metric.name) {
+                (Some(alias), _) => self.by_alias.get(&alias),
+                (None, Some(name)) => self.by_name.get(name),
+                (None, None) => None,
+            };
+            let label = || {
+                metric
+                    .name
+                    .clone()
+                    .or_else(|| metric.alias.map(|a| format!("alias {}", a)))
+                    .unwrap_or_else(|| "unnamed metric".into())
+            };
+            let def = &self.metrics[*index.ok_or_else(|| GatewayError::PointNotFound(label()))?];
+
+            let (channel_id, id) = (def.channel_id, def.point_id);
+            let value = match metric.value {
+                Some(MetricValue::BooleanValue(value)) => {
+                    let command = ControlCommand::latching(id, value);
+                    commands.push(NodeCommand::Control {
+                        channel_id,
+                        command,
+                    });
+                    continue;
+                }
+                Some(MetricValue::IntValue(v)) => f64::from(v as i32),
+                Some(MetricValue::LongValue(v)) => v as i64 as f64,
+                Some(MetricValue::FloatValue(v)) => f64::from(v),
+                Some(MetricValue::DoubleValue(v)) => v,
+                _ => {
+                    return Err(GatewayError::InvalidData(format!(
+                        "{}: command needs a boolean or numeric value",
+                        label()
+                    )))
+                }
+            };
+            commands.push(if def.datatype == Some(DataType::Boolean) {
+                NodeCommand::Control {
+                    channel_id,
+                    command: ControlCommand::latching(id, value != 0.0),
+                }
+            } else {
+                NodeCommand::Adjustment {
+                    channel_id,
+                    command: AdjustmentCommand::new(id, value),
+                }
+            });
+        }
+        Ok(commands)
+    }
+
+    fn bd_seq_metric(&self) -> Metric {
+        Metric {
+            name: Some(BD_SEQ_METRIC.into()),
+            datatype: Some(DataType::Int64 as u32),
+            value: Some(MetricValue::LongValue(u64::from(self.bd_seq))),
+            ..Default::default()
+        }
+    }
+
+    /// Payload with the next sequence number.
+    fn payload(&mut self, metrics: Vec<Metric>) -> Vec<u8> {
+        let seq = self.seq;
+        self.seq = self.seq.wrapping_add(1);
+        Payload {
+            timestamp: Some(now_ms()),
+            metrics,
+            seq: Some(u64::from(seq)),
+        }
+        .encode_to_vec()
+    }
+}
+
+/// Metric (without name or alias) carrying `point`'s value as `datatype`.
+fn metric(datatype: DataType, point: &DataPoint) -> Metric {
+    let value = &point.value;
+    let value = match datatype {
+        DataType::Boolean => value.as_bool().map(MetricValue::BooleanValue),
+        DataType::Int32 => value
+            .as_i64()
+            .map(|v| MetricValue::IntValue(v as i32 as u32)),
+        DataType::Int64 => value.as_i64().map(|v| MetricValue::LongValue(v as u64)),
+        DataType::Double => value.as_f64().map(MetricValue::DoubleValue),
+        DataType::String => match value {
+            Value::String(s) => Some(s.clone()),
+            Value::Float(v) => Some(v.to_string()),
+            Value::Integer(v) => Some(v.to_string()),
+            Value::Bool(v) => Some(v.to_string()),
+            Value::Bytes(_) | Value::Array(_) | Value::Null => None,
+        }
+        .map(MetricValue::StringValue),
+    };
+
+    let severity = point.quality.severity();
+    let properties = (severity > 0).then(|| PropertySet {
+        keys: vec!["Quality".into()],
+        values: vec![PropertyValue {
+            r#type: Some(DataType::Int32 as u32),
+            value: Some(PropertyValueKind::IntValue(if severity == 1 {
+                64
+            } else {
+                0
+            })),
+        }],
+    });
+
+    Metric {
+        timestamp: Some(point.timestamp.timestamp_millis().max(0) as u64),
+        is_null: value.is_none().then_some(true),
+        properties,
+        value,
+        ..Default::default()
+    }
+}
+
+fn now_ms() -> u64 {
+    chrono::Utc::now().timestamp_millis().max(0) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::point::{ModbusAddress, VirtualAddress};
+    use crate::core::quality::Quality;
+
+    fn node() -> SparkplugNode {
+        let points = vec![
+            PointConfig::new(
+                10,
+                ProtocolAddress::Modbus(ModbusAddress::holding_register(1, 0, DataFormat::Bool)),
+            )
+            .with_name("Pump"),
+            PointConfig::new(
+                11,
+                ProtocolAddress::Modbus(ModbusAddress::holding_register(1, 1, DataFormat::Int16)),
+            )
+            .with_name("Setpoint")
+            .with_unit("kPa"),
+            PointConfig::new(12, ProtocolAddress::Virtual(VirtualAddress::new("t"))),
+        ];
+        let mut node = SparkplugNode::new("plant", "igw");
+        node.add_points(1, "PLC1", &points);
+        node
+    }
+
+    fn decode(bytes: &[u8]) -> Payload {
+        Payload::decode(bytes).unwrap()
+    }
+
+    #[test]
+    fn test_birth_and_data() {
+        let mut node = node();
+        assert_eq!(node.topic(MessageType::NBirth), "spBv1.0/plant/NBIRTH/igw");
+
+        let death = decode(&node.death());
+        assert_eq!(death.seq, None);
+        assert_eq!(death.metrics[0].name.as_deref(), Some("bdSeq"));
+
+        let current = DataBatch::from_points(vec![
+            DataPoint::new(10, true),
+            DataPoint::new(12, 21.5).with_quality(Quality::LastKnown),
+        ]);
+        let birth = decode(&node.birth(&[(1, current)]));
+        assert_eq!(birth.seq, Some(0));
+        let names: Vec<&str> = birth
+            .metrics
+            .iter()
+            .map(|m| m.name.as_deref().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "bdSeq",
+                REBIRTH_METRIC,
+                "PLC1/Pump",
+                "PLC1/Setpoint",
+                "PLC1/12"
+            ]
+        );
+
+        let pump = &birth.metrics[2];
+        assert_eq!(pump.alias, Some((1 << 32) | 10));
+        assert_eq!(pump.datatype, Some(DataType::Boolean as u32));
+        assert_eq!(pump.value, Some(MetricValue::BooleanValue(true)));
+
+        let setpoint = &birth.metrics[3];
+        assert_eq!(setpoint.datatype, Some(DataType::Int64 as u32));
+        assert_eq!(setpoint.is_null, Some(true));
+        let properties = setpoint.properties.as_ref().unwrap();
+        assert_eq!(properties.keys, ["engUnit"]);
+
+        // Type taken from the value, quality flagged as uncertain
+        let temp = &birth.metrics[4];
+        assert_eq!(temp.datatype, Some(DataType::Double as u32));
+        assert_eq!(
+            temp.properties.as_ref().unwrap().values[0].value,
+            Some(PropertyValueKind::IntValue(64))
+        );
+
+        let changes = DataBatch::from_points(vec![
+            DataPoint::new(11, Value::Integer(-3)),
+            DataPoint::new(99, 1.0),
+        ]);
+        let data = decode(&node.data(1, &changes).unwrap());
+        assert_eq!(data.seq, Some(1));
+        assert_eq!(data.metrics.len(), 1);
+        assert_eq!(data.metrics[0].name, None);
+        assert_eq!(data.metrics[0].alias, Some((1 << 32) | 11));
+        assert_eq!(
+            data.metrics[0].value,
+            Some(MetricValue::LongValue(-3i64 as u64))
+        );
+        assert!(node.data(2, &changes).is_none());
+
+        node.next_session();
+        let death = decode(&node.death());
+        assert_eq!(death.metrics[0].value, Some(MetricValue::LongValue(1)));
+        assert_eq!(decode(&node.birth(&[])).seq, Some(0));
+    }
+
+    #[test]
+    fn test_decode_command() {
+        let node = node();
+        let payload = Payload {
+            timestamp: Some(0),
+            metrics: vec![
+                Metric {
+                    name: Some(REBIRTH_METRIC.into()),
+                    value: Some(MetricValue::BooleanValue(true)),
+                    ..Default::default()
+                },
+                Metric {
+                    alias: Some((1 << 32) | 10),
+                    value: Some(MetricValue::LongValue(1)),
+                    ..Default::default()
+                },
+                Metric {
+                    name: Some("PLC1/Setpoint".into()),
+                    value: Some(MetricValue::DoubleValue(4.5)),
+                    ..Default::default()
+                },
+            ],
+            seq: None,
+        };
+        let commands = node.decode_command(&payload.encode_to_vec()).unwrap();
+        assert_eq!(commands.len(), 3);
+        assert!(matches!(commands[0], NodeCommand::Rebirth));
+        assert!(matches!(
+            &commands[1],
+            NodeCommand::Control { channel_id: 1, command } if command.id == 10 && command.value
+        ));
+        assert!(matches!(
+            &commands[2],
+            NodeCommand::Adjustment { channel_id: 1, command } if command.id == 11 && command.value == 4.5
+        ));
+
+        let unknown = Payload {
+            metrics: vec![Metric {
+                name: Some("PLC1/Missing".into()),
+                value: Some(MetricValue::BooleanValue(true)),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        assert!(matches!(
+            node.decode_command(&unknown.encode_to_vec()),
+            Err(GatewayError::PointNotFound(name)) if name == "PLC1/Missing"
+        ));
+        assert!(node.decode_command(&[0xFF]).is_err());
+    }
+}
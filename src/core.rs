@@ -2,16 +2,28 @@
 //!
 //! This module provides the foundational types and traits that all protocols implement.
 
+pub mod alarm;
+pub mod clock;
 pub mod data;
+pub mod deadband;
+pub mod diagnostics;
 pub mod error;
+pub mod event;
 pub mod logging;
 pub mod metadata;
 pub mod point;
 pub mod quality;
 pub mod traits;
+pub mod transport;
+pub mod units;
 
+pub use alarm::{AlarmConfig, AlarmLimit};
+pub use clock::{Clock, SharedClock, SimulatedClock, SystemClock};
 pub use data::*;
+pub use deadband::DeadbandFilter;
+pub use diagnostics::{ConnectionHistory, ConnectionSummary, DiagnosticsRecorder, StateTransition};
 pub use error::{GatewayError, Result};
+pub use event::{DataEventReceiver, EventBus, OverflowCounters, OverflowPolicy};
 pub use metadata::{
     get_protocol_registry, DriverMetadata, HasMetadata, ParameterMetadata, ParameterType,
     ProtocolMetadata, ProtocolRegistry,
@@ -19,3 +31,5 @@ pub use metadata::{
 pub use point::*;
 pub use quality::*;
 pub use traits::*;
+pub use transport::{SharedTransport, TransportRegistry, TransportStats};
+pub use units::UnitConversion;
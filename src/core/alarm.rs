@@ -0,0 +1,404 @@
+//! Limit alarms on analog points.
+//!
+//! A point with an `alarms` block gets up to four boolean signal points,
+//! one per configured limit:
+//!
+//! ```toml
+//! [[channels.points]]
+//! id = 1
+//! name = "Coolant temperature"
+//! address = "1:100"
+//!
+//! [channels.points.alarms.high]
+//! id = 101
+//! setpoint = 80.0
+//! hysteresis = 2.0
+//! on_delay_ms = 5000
+//!
+//! [channels.points.alarms.high_high]
+//! id = 102
+//! setpoint = 95.0
+//! ```
+//!
+//! - A high limit becomes active once the value is above `setpoint` and
+//!   clears once it is below `setpoint - hysteresis`; in between it keeps its
+//!   state. Low limits are the mirror image (active below `setpoint`, clear
+//!   above `setpoint + hysteresis`).
+//! - `on_delay_ms` / `off_delay_ms` require the condition to hold for that
+//!   long before the signal changes. Delays are measured on the samples'
+//!   timestamps, so the signal changes with the first sample after the
+//!   delay has passed.
+//! - Signals carry the quality of the sample they were evaluated from.
+//!   Samples that are not `Good` do not change the alarm state; the signal
+//!   keeps its state with the sample's quality.
+//!
+//! The gateway evaluates alarms as data is written to the store and stores
+//! the signals with the channel's other points. The alarm state belongs to
+//! the gateway, not the channel, so it survives reconnects and restarts of
+//! the channel.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::data::{DataBatch, DataPoint, PointId, Value};
+use super::point::PointConfig;
+
+/// Limit alarms of a point.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AlarmConfig {
+    /// Active above the setpoint.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub high: Option<AlarmLimit>,
+
+    /// Second, higher "high" limit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub high_high: Option<AlarmLimit>,
+
+    /// Active below the setpoint.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub low: Option<AlarmLimit>,
+
+    /// Second, lower "low" limit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub low_low: Option<AlarmLimit>,
+}
+
+/// One limit of an [`AlarmConfig`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlarmLimit {
+    /// Id of the signal point reporting the limit.
+    pub id: PointId,
+
+    /// Value at which the limit becomes active.
+    pub setpoint: f64,
+
+    /// Distance back past the setpoint before the limit clears.
+    #[serde(default)]
+    pub hysteresis: f64,
+
+    /// Time the limit must be exceeded before the signal turns on.
+    #[serde(default)]
+    pub on_delay_ms: u64,
+
+    /// Time the value must be back before the signal turns off.
+    #[serde(default)]
+    pub off_delay_ms: u64,
+}
+
+impl AlarmLimit {
+    /// Signal `id` for a limit at `setpoint`, without hysteresis or delays.
+    pub fn new(id: PointId, setpoint: f64) -> Self {
+        Self {
+            id,
+            setpoint,
+            hysteresis: 0.0,
+            on_delay_ms: 0,
+            off_delay_ms: 0,
+        }
+    }
+
+    /// Set the hysteresis.
+    #[must_use]
+    pub fn with_hysteresis(mut self, hysteresis: f64) -> Self {
+        self.hysteresis = hysteresis;
+        self
+    }
+
+    /// Set the on and off delays.
+    #[must_use]
+    pub fn with_delays(mut self, on: std::time::Duration, off: std::time::Duration) -> Self {
+        self.on_delay_ms = on.as_millis() as u64;
+        self.off_delay_ms = off.as_millis() as u64;
+        self
+    }
+}
+
+/// Direction of a limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitKind {
+    /// Active above the setpoint (`high`, `high_high`).
+    Upper,
+    /// Active below the setpoint (`low`, `low_low`).
+    Lower,
+}
+
+impl AlarmConfig {
+    /// Configured limits with their direction and name.
+    pub fn limits(&self) -> impl Iterator<Item = (&'static str, LimitKind, &AlarmLimit)> {
+        [
+            ("high", LimitKind::Upper, &self.high),
+            ("high_high", LimitKind::Upper, &self.high_high),
+            ("low", LimitKind::Lower, &self.low),
+            ("low_low", LimitKind::Lower, &self.low_low),
+        ]
+        .into_iter()
+        .filter_map(|(name, kind, limit)| Some((name, kind, limit.as_ref()?)))
+    }
+
+    /// Whether no limit is configured.
+    pub fn is_empty(&self) -> bool {
+        self.limits().next().is_none()
+    }
+}
+
+/// Running state of one limit.
+#[derive(Debug)]
+struct LimitState {
+    kind: LimitKind,
+    limit: AlarmLimit,
+    active: bool,
+    /// Since when the value asks for the opposite state.
+    pending_since: Option<DateTime<Utc>>,
+}
+
+impl LimitState {
+    fn new(kind: LimitKind, limit: AlarmLimit) -> Self {
+        Self {
+            kind,
+            limit,
+            active: false,
+            pending_since: None,
+        }
+    }
+
+    /// Take a good sample.
+    fn sample(&mut self, value: f64, at: DateTime<Utc>) {
+        let AlarmLimit {
+            setpoint,
+            hysteresis,
+            ..
+        } = self.limit;
+        let (exceeded, back) = match self.kind {
+            LimitKind::Upper => (value > setpoint, value < setpoint - hysteresis),
+            LimitKind::Lower => (value < setpoint, value > setpoint + hysteresis),
+        };
+        let (wants_change, delay_ms) = if self.active {
+            (back, self.limit.off_delay_ms)
+        } else {
+            (exceeded, self.limit.on_delay_ms)
+        };
+        if !wants_change {
+            self.pending_since = None;
+            return;
+        }
+        let since = *self.pending_since.get_or_insert(at);
+        if at - since >= Duration::milliseconds(delay_ms as i64) {
+            self.active = !self.active;
+            self.pending_since = None;
+        }
+    }
+
+    fn signal(&self, source: &DataPoint) -> DataPoint {
+        let mut point =
+            DataPoint::new(self.limit.id, Value::Bool(self.active)).with_quality(source.quality);
+        point.timestamp = source.timestamp;
+        point
+    }
+}
+
+/// Alarm state of every channel's points.
+#[derive(Debug, Default)]
+pub(crate) struct AlarmEvaluator {
+    /// Limits by `(channel_id, source point id)`.
+    limits: std::sync::Mutex<HashMap<(u32, PointId), Vec<LimitState>>>,
+}
+
+impl AlarmEvaluator {
+    /// Take the alarm configuration of a channel's points.
+    ///
+    /// Limits whose signal id and setpoints are unchanged keep their state.
+    pub(crate) fn configure(&self, channel_id: u32, points: &[PointConfig]) {
+        let mut limits = self.limits.lock().unwrap_or_else(|e| e.into_inner());
+        let mut old: HashMap<PointId, Vec<LimitState>> = HashMap::new();
+        limits.retain(|(channel, point_id), states| {
+            if *channel != channel_id {
+                return true;
+            }
+            old.insert(*point_id, std::mem::take(states));
+            false
+        });
+
+        for point in points {
+            let Some(alarms) = &point.alarms else {
+                continue;
+            };
+            let mut previous = old.remove(&point.id).unwrap_or_default();
+            let states: Vec<LimitState> = alarms
+                .limits()
+                .map(|(_, kind, limit)| {
+                    match previous
+                        .iter()
+                        .position(|s| s.kind == kind && s.limit == *limit)
+                    {
+                        Some(index) => previous.swap_remove(index),
+                        None => LimitState::new(kind, limit.clone()),
+                    }
+                })
+                .collect();
+            if !states.is_empty() {
+                limits.insert((channel_id, point.id), states);
+            }
+        }
+    }
+
+    /// Evaluate a batch about to be stored; returns the signal points of
+    /// the alarmed points it contains.
+    pub(crate) fn evaluate(&self, channel_id: u32, batch: &DataBatch) -> DataBatch {
+        let mut limits = self.limits.lock().unwrap_or_else(|e| e.into_inner());
+        let mut signals = DataBatch::new();
+        if limits.is_empty() {
+            return signals;
+        }
+        for point in batch.iter() {
+            let Some(states) = limits.get_mut(&(channel_id, point.id)) else {
+                continue;
+            };
+            let good = point.quality.is_good();
+            let value = point.value.as_f64().filter(|v| v.is_finite());
+            if good && value.is_none() {
+                continue;
+            }
+            for state in states.iter_mut() {
+                if let Some(value) = value.filter(|_| good) {
+                    state.sample(value, point.timestamp);
+                }
+                signals.add(state.signal(point));
+            }
+        }
+        signals
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::point::ProtocolAddress;
+    use crate::core::quality::Quality;
+
+    fn at(ms: i64, value: f64) -> DataPoint {
+        let mut point = DataPoint::new(1, value);
+        point.timestamp = DateTime::<Utc>::UNIX_EPOCH + Duration::milliseconds(ms);
+        point
+    }
+
+    fn evaluator(alarms: AlarmConfig) -> AlarmEvaluator {
+        let evaluator = AlarmEvaluator::default();
+        let point = PointConfig::new(1, ProtocolAddress::Generic("t".into())).with_alarms(alarms);
+        evaluator.configure(7, &[point]);
+        evaluator
+    }
+
+    /// Signal values for a sequence of samples.
+    fn run(evaluator: &AlarmEvaluator, samples: &[DataPoint]) -> Vec<bool> {
+        samples
+            .iter()
+            .map(|sample| {
+                let signals = evaluator.evaluate(7, &DataBatch::from_points(vec![sample.clone()]));
+                signals.into_vec()[0].value.as_bool().unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_hysteresis_suppresses_chattering() {
+        let high = AlarmConfig {
+            high: Some(AlarmLimit::new(101, 80.0).with_hysteresis(2.0)),
+            ..Default::default()
+        };
+        let noisy = [79.0, 80.5, 79.5, 80.5, 78.5, 79.9, 77.9, 80.1];
+        let samples: Vec<DataPoint> = noisy
+            .iter()
+            .enumerate()
+            .map(|(i, v)| at(i as i64 * 100, *v))
+            .collect();
+        assert_eq!(
+            run(&evaluator(high), &samples),
+            [false, true, true, true, true, true, false, true]
+        );
+
+        let low = AlarmConfig {
+            low_low: Some(AlarmLimit::new(101, 10.0).with_hysteresis(1.0)),
+            ..Default::default()
+        };
+        let samples: Vec<DataPoint> = [9.9, 10.5, 9.0, 11.1]
+            .iter()
+            .enumerate()
+            .map(|(i, v)| at(i as i64 * 100, *v))
+            .collect();
+        assert_eq!(run(&evaluator(low), &samples), [true, true, true, false]);
+    }
+
+    #[test]
+    fn test_delays_suppress_short_excursions() {
+        let config = AlarmConfig {
+            high: Some(AlarmLimit::new(101, 80.0).with_delays(
+                std::time::Duration::from_millis(1000),
+                std::time::Duration::from_millis(500),
+            )),
+            ..Default::default()
+        };
+        let samples = [
+            // A spike shorter than the on delay
+            at(0, 85.0),
+            at(500, 85.0),
+            at(600, 70.0),
+            // Held for the on delay
+            at(1000, 85.0),
+            at(1500, 85.0),
+            at(2000, 85.0),
+            // A dip shorter than the off delay
+            at(2100, 70.0),
+            at(2300, 85.0),
+            // Back for the off delay
+            at(3000, 70.0),
+            at(3500, 70.0),
+        ];
+        assert_eq!(
+            run(&evaluator(config), &samples),
+            [false, false, false, false, false, true, true, true, true, false]
+        );
+    }
+
+    #[test]
+    fn test_quality_and_state_across_reconfigure() {
+        let config = AlarmConfig {
+            high: Some(AlarmLimit::new(101, 80.0)),
+            low: Some(AlarmLimit::new(102, 20.0)),
+            ..Default::default()
+        };
+        let evaluator = evaluator(config.clone());
+        let signals = evaluator.evaluate(7, &DataBatch::from_points(vec![at(0, 90.0)]));
+        assert_eq!(signals.len(), 2);
+        assert_eq!(signals.get(101).unwrap().value, Value::Bool(true));
+        assert_eq!(signals.get(102).unwrap().value, Value::Bool(false));
+
+        // A bad sample keeps the state but passes on its quality
+        let bad = at(100, 0.0).with_quality(Quality::CommFailure);
+        let signals = evaluator.evaluate(7, &DataBatch::from_points(vec![bad]));
+        let high = signals.get(101).unwrap();
+        assert_eq!(
+            (&high.value, high.quality),
+            (&Value::Bool(true), Quality::CommFailure)
+        );
+
+        // Re-registering the same points (a channel restart) keeps the state
+        let point = PointConfig::new(1, ProtocolAddress::Generic("t".into())).with_alarms(config);
+        evaluator.configure(7, &[point]);
+        let signals = evaluator.evaluate(7, &DataBatch::from_points(vec![at(200, 80.0)]));
+        assert_eq!(signals.get(101).unwrap().value, Value::Bool(true));
+
+        // Other channels and non-numeric values produce nothing
+        assert!(evaluator
+            .evaluate(8, &DataBatch::from_points(vec![at(300, 90.0)]))
+            .is_empty());
+        let text = DataBatch::from_points(vec![DataPoint::new(1, Value::String("x".into()))]);
+        assert!(evaluator.evaluate(7, &text).is_empty());
+
+        evaluator.configure(7, &[]);
+        assert!(evaluator
+            .evaluate(7, &DataBatch::from_points(vec![at(400, 90.0)]))
+            .is_empty());
+    }
+}
@@ -0,0 +1,165 @@
+//! Time source.
+//!
+//! Checks that compare sample timestamps with the current time (staleness,
+//! interlock `max_age_ms`) ask a [`Clock`] instead of calling `Utc::now()`
+//! themselves. The gateway uses the [`SystemClock`]; tests give it a
+//! [`SimulatedClock`] and move time forward by hand, so a point exactly at
+//! its age limit can be checked without sleeping:
+//!
+//! ```no_run
+//! # async fn example(store: std::sync::Arc<dyn igw::store::DataStore>, config: igw::GatewayConfig) -> igw::Result<()> {
+//! use std::sync::Arc;
+//! use std::time::Duration;
+//! use igw::core::clock::SimulatedClock;
+//! use igw::gateway::GatewayRuntime;
+//!
+//! let clock = Arc::new(SimulatedClock::new());
+//! let mut runtime = GatewayRuntime::from_config(config, store)?.with_clock(clock.clone());
+//! runtime.start().await?;
+//! clock.advance(Duration::from_secs(60));
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Poll scheduling runs on tokio time, which tests control with
+//! `tokio::time::pause()`.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use tokio::sync::watch;
+use tokio::time::Instant;
+
+/// Source of the current time.
+#[async_trait]
+pub trait Clock: Send + Sync + std::fmt::Debug {
+    /// Current wall-clock time.
+    fn now_utc(&self) -> DateTime<Utc>;
+
+    /// Current monotonic time.
+    fn now_instant(&self) -> Instant;
+
+    /// Wait until `duration` has passed on this clock.
+    async fn sleep(&self, duration: Duration);
+}
+
+/// Clock shared by the gateway's tasks.
+pub type SharedClock = Arc<dyn Clock>;
+
+/// The real time: chrono's wall clock and tokio's timers.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl SystemClock {
+    /// The system clock, ready to share.
+    pub fn shared() -> SharedClock {
+        Arc::new(Self)
+    }
+}
+
+#[async_trait]
+impl Clock for SystemClock {
+    fn now_utc(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    fn now_instant(&self) -> Instant {
+        Instant::now()
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        tokio::time::sleep(duration).await;
+    }
+}
+
+/// Clock that only moves when [`advance()`](Self::advance)d.
+///
+/// Sleepers wake once the clock has been advanced past their deadline.
+#[derive(Debug)]
+pub struct SimulatedClock {
+    start_utc: DateTime<Utc>,
+    start_instant: Instant,
+    elapsed: watch::Sender<Duration>,
+}
+
+impl Default for SimulatedClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SimulatedClock {
+    /// Clock standing at the current time.
+    pub fn new() -> Self {
+        Self::starting_at(Utc::now())
+    }
+
+    /// Clock standing at `start`.
+    pub fn starting_at(start: DateTime<Utc>) -> Self {
+        Self {
+            start_utc: start,
+            start_instant: Instant::now(),
+            elapsed: watch::Sender::new(Duration::ZERO),
+        }
+    }
+
+    /// Move the clock forward by `duration`, waking the sleepers it passes.
+    pub fn advance(&self, duration: Duration) {
+        self.elapsed.send_modify(|elapsed| *elapsed += duration);
+    }
+
+    /// Time advanced since the start.
+    pub fn elapsed(&self) -> Duration {
+        *self.elapsed.borrow()
+    }
+}
+
+#[async_trait]
+impl Clock for SimulatedClock {
+    fn now_utc(&self) -> DateTime<Utc> {
+        self.start_utc + self.elapsed()
+    }
+
+    fn now_instant(&self) -> Instant {
+        self.start_instant + self.elapsed()
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        let deadline = self.elapsed() + duration;
+        let mut elapsed = self.elapsed.subscribe();
+        // The sender lives as long as `self`, so this only returns once due
+        let _ = elapsed.wait_for(|elapsed| *elapsed >= deadline).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_simulated_clock() {
+        let start = Utc::now();
+        let clock = Arc::new(SimulatedClock::starting_at(start));
+        let instant = clock.now_instant();
+
+        let sleeper = tokio::spawn({
+            let clock = Arc::clone(&clock);
+            async move { clock.sleep(Duration::from_secs(10)).await }
+        });
+        // Let the sleeper start waiting
+        tokio::task::yield_now().await;
+        clock.advance(Duration::from_secs(4));
+        tokio::task::yield_now().await;
+        assert!(!sleeper.is_finished());
+
+        clock.advance(Duration::from_secs(6));
+        sleeper.await.unwrap();
+        assert_eq!(clock.now_utc(), start + Duration::from_secs(10));
+        assert_eq!(clock.now_instant() - instant, Duration::from_secs(10));
+
+        // Zero-length sleeps return at once
+        clock.sleep(Duration::ZERO).await;
+    }
+}
@@ -5,15 +5,75 @@
 //! "Four Remotes" (四遥: Telemetry/Signal/Control/Adjustment). The application
 //! layer (e.g., comsrv) is responsible for categorizing data points.
 
-use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::fmt::{self, Write as _};
+use std::sync::OnceLock;
+
+use chrono::{DateTime, Timelike, Utc};
 use serde::{Deserialize, Serialize};
 
+use crate::core::error::{GatewayError, Result};
+use crate::core::point::{AnnotatedBatch, PointMetaMap};
 use crate::core::quality::Quality;
 
+/// Canonical point identifier used throughout the crate.
+///
+/// Point ids are numeric, application-level ids. Human-readable names belong in
+/// [`PointConfig::name`](crate::core::point::PointConfig::name); protocol-native
+/// identifiers (J1939 SPN, IEC 104 IOA, ...) are mapped onto this id space by
+/// the adapters.
+pub type PointId = u32;
+
+/// Deserialize a [`PointId`] from either a number or a numeric string.
+///
+/// Older configurations wrote point ids as strings (`id = "101"`). This keeps
+/// them loading while still rejecting non-numeric ids.
+pub fn deserialize_point_id<'de, D>(deserializer: D) -> std::result::Result<PointId, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum RawId {
+        Num(PointId),
+        Str(String),
+    }
+
+    match RawId::deserialize(deserializer)? {
+        RawId::Num(id) => Ok(id),
+        RawId::Str(s) => s.trim().parse().map_err(|_| {
+            serde::de::Error::custom(format!("invalid point id '{}': expected a u32", s))
+        }),
+    }
+}
+
 /// A protocol-agnostic value representation.
 ///
 /// This enum provides a unified way to represent values from different protocols.
-#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+///
+/// # Wire format
+///
+/// Values serialize untagged, as the plain JSON value:
+///
+/// | Variant | JSON | Reads back as |
+/// |---|---|---|
+/// | `Float(25.0)` | `25.0` | `Float` |
+/// | `Integer(25)` | `25` | `Integer` |
+/// | `Bool`, `String` | `true`, `"on"` | the same |
+/// | `Bytes([1, 255])` | `[1,255]` | `Bytes` |
+/// | `Array([..])` | `[..]` | `Array`, see below |
+/// | `Null` | `null` | `Null` |
+///
+/// The number literal is the tag: a number without fraction or exponent
+/// reads back as [`Integer`](Self::Integer), any other as
+/// [`Float`](Self::Float). serde_json always writes a float with a
+/// fraction or exponent (`25.0`), so both round-trip, including files
+/// written by earlier releases. Integers beyond the `i64` range read back
+/// as floats. The remaining coercions are lossy by design:
+/// - non-finite floats are written as `null` and read back as `Null`;
+/// - arrays consisting only of integers 0-255, and empty arrays, read back
+///   as [`Bytes`](Self::Bytes).
+#[derive(Debug, Clone, PartialEq, Default, Serialize)]
 #[serde(untagged)]
 pub enum Value {
     /// Floating-point number (most common for analog values)
@@ -31,13 +91,103 @@ pub enum Value {
     /// Raw bytes
     Bytes(Vec<u8>),
 
+    /// Ordered values read as one object, e.g. a block of cell voltages,
+    /// an OPC UA array node or a list of fault codes
+    Array(Vec<Value>),
+
     /// Null/missing value
     #[default]
     Null,
 }
 
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+/// Reads the [wire format](Value#wire-format) of a [`Value`].
+struct ValueVisitor;
+
+impl<'de> serde::de::Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a number, bool, string, array or null")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> std::result::Result<Value, E> {
+        Ok(Value::Bool(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> std::result::Result<Value, E> {
+        Ok(Value::Integer(v))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> std::result::Result<Value, E> {
+        Ok(i64::try_from(v).map_or(Value::Float(v as f64), Value::Integer))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> std::result::Result<Value, E> {
+        Ok(Value::Float(v))
+    }
+
+    fn visit_str<E>(self, v: &str) -> std::result::Result<Value, E> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn visit_string<E>(self, v: String) -> std::result::Result<Value, E> {
+        Ok(Value::String(v))
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> std::result::Result<Value, E> {
+        Ok(Value::Bytes(v.to_vec()))
+    }
+
+    fn visit_unit<E>(self) -> std::result::Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_none<E>(self) -> std::result::Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> std::result::Result<Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Value::deserialize(deserializer)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> std::result::Result<Value, A::Error>
+    where
+        A: serde::de::SeqAccess<'de>,
+    {
+        let mut items = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+        while let Some(item) = seq.next_element::<Value>()? {
+            items.push(item);
+        }
+        let bytes: Option<Vec<u8>> = items
+            .iter()
+            .map(|item| match item {
+                Value::Integer(v) => u8::try_from(*v).ok(),
+                _ => None,
+            })
+            .collect();
+        Ok(bytes.map_or(Value::Array(items), Value::Bytes))
+    }
+}
+
 impl Value {
     /// Try to get the value as f64.
+    ///
+    /// Lossy: integers beyond ±2^53 are rounded to the nearest
+    /// representable float, without notice. Bools are 1.0 and 0.0; strings,
+    /// bytes, arrays and null give `None`. Use
+    /// [`try_as_f64_exact()`](Self::try_as_f64_exact) where the loss matters.
     pub fn as_f64(&self) -> Option<f64> {
         match self {
             Self::Float(v) => Some(*v),
@@ -48,6 +198,11 @@ impl Value {
     }
 
     /// Try to get the value as i64.
+    ///
+    /// Lossy: floats are truncated toward zero (`2.9` gives 2, `-2.9` gives
+    /// -2) and saturate at `i64::MIN`/`i64::MAX`, infinities included; NaN
+    /// gives 0. Bools are 1 and 0; strings, bytes, arrays and null give
+    /// `None`. Use [`try_as_i64()`](Self::try_as_i64) to refuse such values.
     pub fn as_i64(&self) -> Option<i64> {
         match self {
             Self::Integer(v) => Some(*v),
@@ -57,6 +212,77 @@ impl Value {
         }
     }
 
+    /// The value as i64, if it is one exactly.
+    ///
+    /// Floats must be finite whole numbers within the i64 range. Fails with
+    /// `GatewayError::DataConversion` naming the loss otherwise, and for
+    /// non-numeric values. Bools are 1 and 0.
+    pub fn try_as_i64(&self) -> Result<i64> {
+        match self {
+            Self::Integer(v) => Ok(*v),
+            Self::Bool(v) => Ok(i64::from(*v)),
+            Self::Float(v) => {
+                // i64::MAX is not representable; 2^63 is the first float
+                // out of range
+                const LIMIT: f64 = 9_223_372_036_854_775_808.0;
+                if v.is_nan() {
+                    Err(GatewayError::DataConversion(
+                        "NaN is not an integer".to_string(),
+                    ))
+                } else if !(-LIMIT..LIMIT).contains(v) {
+                    Err(GatewayError::DataConversion(format!(
+                        "{} is outside the i64 range",
+                        v
+                    )))
+                } else if v.fract() != 0.0 {
+                    Err(GatewayError::DataConversion(format!(
+                        "{} is not a whole number",
+                        v
+                    )))
+                } else {
+                    Ok(*v as i64)
+                }
+            }
+            other => Err(not_numeric(other, "i64")),
+        }
+    }
+
+    /// The value as u32, if it is one exactly.
+    ///
+    /// Like [`try_as_i64()`](Self::try_as_i64), and the result must also
+    /// fit in a u32.
+    pub fn try_as_u32(&self) -> Result<u32> {
+        let v = self.try_as_i64()?;
+        u32::try_from(v)
+            .map_err(|_| GatewayError::DataConversion(format!("{} is outside the u32 range", v)))
+    }
+
+    /// The value as f64, if that loses nothing.
+    ///
+    /// Integers beyond ±2^53 that have no exact float fail with
+    /// `GatewayError::DataConversion`, as do non-numeric values. Floats,
+    /// NaN and infinities included, are returned as they are; bools are 1.0
+    /// and 0.0.
+    pub fn try_as_f64_exact(&self) -> Result<f64> {
+        match self {
+            Self::Float(v) => Ok(*v),
+            Self::Bool(v) => Ok(if *v { 1.0 } else { 0.0 }),
+            Self::Integer(v) => {
+                let f = *v as f64;
+                // Compare wider than i64, as `f as i64` would saturate
+                if f as i128 == i128::from(*v) {
+                    Ok(f)
+                } else {
+                    Err(GatewayError::DataConversion(format!(
+                        "{} has no exact f64 (nearest is {})",
+                        v, f
+                    )))
+                }
+            }
+            other => Err(not_numeric(other, "f64")),
+        }
+    }
+
     /// Try to get the value as bool.
     pub fn as_bool(&self) -> Option<bool> {
         match self {
@@ -75,11 +301,125 @@ impl Value {
         }
     }
 
+    /// Try to get the elements of an array value.
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Self::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// Number of elements of an array value.
+    pub fn array_len(&self) -> Option<usize> {
+        self.as_array().map(<[Value]>::len)
+    }
+
     /// Check if this is a null value.
     #[inline]
     pub fn is_null(&self) -> bool {
         matches!(self, Self::Null)
     }
+
+    /// Kind of the value.
+    pub fn kind(&self) -> ValueKind {
+        match self {
+            Self::Float(_) => ValueKind::Float,
+            Self::Integer(_) => ValueKind::Integer,
+            Self::Bool(_) => ValueKind::Bool,
+            Self::String(_) => ValueKind::String,
+            Self::Bytes(_) => ValueKind::Bytes,
+            Self::Array(_) => ValueKind::Array,
+            Self::Null => ValueKind::Null,
+        }
+    }
+}
+
+/// Error for converting a non-numeric value to a number type.
+fn not_numeric(value: &Value, to: &str) -> GatewayError {
+    GatewayError::DataConversion(format!(
+        "{} value cannot be converted to {}",
+        value.kind().name(),
+        to
+    ))
+}
+
+/// Compact text form: numbers as is (floats honour a precision, e.g.
+/// `{:.2}`), strings quoted, bytes as hex (`0x01ff`), arrays in brackets.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Float(v) => match f.precision() {
+                Some(precision) => write!(f, "{:.*}", precision, v),
+                None => write!(f, "{}", v),
+            },
+            Self::Integer(v) => write!(f, "{}", v),
+            Self::Bool(v) => write!(f, "{}", v),
+            Self::String(v) => write!(f, "{:?}", v),
+            Self::Bytes(bytes) => {
+                f.write_str("0x")?;
+                bytes.iter().try_for_each(|b| write!(f, "{:02x}", b))
+            }
+            Self::Array(items) => {
+                f.write_char('[')?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    item.fmt(f)?;
+                }
+                f.write_char(']')
+            }
+            Self::Null => f.write_str("null"),
+        }
+    }
+}
+
+/// Kind of a [`Value`], without its content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValueKind {
+    /// [`Value::Float`]
+    Float,
+    /// [`Value::Integer`]
+    Integer,
+    /// [`Value::Bool`]
+    Bool,
+    /// [`Value::String`]
+    String,
+    /// [`Value::Bytes`]
+    Bytes,
+    /// [`Value::Array`]
+    Array,
+    /// [`Value::Null`]
+    Null,
+}
+
+impl ValueKind {
+    /// One-letter tag used by the [`DataPoint`] display.
+    pub fn tag(self) -> char {
+        match self {
+            Self::Float => 'F',
+            Self::Integer => 'I',
+            Self::Bool => 'B',
+            Self::String => 'S',
+            Self::Bytes => 'X',
+            Self::Array => 'A',
+            Self::Null => 'N',
+        }
+    }
+
+    /// Lowercase name, as serialized.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Float => "float",
+            Self::Integer => "integer",
+            Self::Bool => "bool",
+            Self::String => "string",
+            Self::Bytes => "bytes",
+            Self::Array => "array",
+            Self::Null => "null",
+        }
+    }
 }
 
 // Convenient From implementations
@@ -143,6 +483,12 @@ impl From<&str> for Value {
     }
 }
 
+impl From<Vec<Value>> for Value {
+    fn from(v: Vec<Value>) -> Self {
+        Self::Array(v)
+    }
+}
+
 /// A single data point with timestamp and quality.
 ///
 /// This is a protocol-layer data structure. It does NOT contain SCADA-level
@@ -151,7 +497,8 @@ impl From<&str> for Value {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DataPoint {
     /// Point identifier (numeric, application-level ID)
-    pub id: u32,
+    #[serde(deserialize_with = "deserialize_point_id")]
+    pub id: PointId,
 
     /// The value
     pub value: Value,
@@ -170,7 +517,7 @@ pub struct DataPoint {
 
 impl DataPoint {
     /// Create a new data point with current timestamp.
-    pub fn new(id: u32, value: impl Into<Value>) -> Self {
+    pub fn new(id: PointId, value: impl Into<Value>) -> Self {
         Self {
             id,
             value: value.into(),
@@ -195,17 +542,76 @@ impl DataPoint {
     }
 }
 
+/// One line: kind tag, id, value, quality and the time of day (UTC) it
+/// was received, e.g. `[F] 1001=25.50 Good @12:30:01.123`.
+///
+/// Floats are shown with two decimals unless a precision is given
+/// (`{:.4}`).
+impl fmt::Display for DataPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let precision = f.precision().unwrap_or(2);
+        write!(
+            f,
+            "[{}] {}={:.*} {:?} @{:02}:{:02}:{:02}.{:03}",
+            self.value.kind().tag(),
+            self.id,
+            precision,
+            self.value,
+            self.quality,
+            self.timestamp.hour(),
+            self.timestamp.minute(),
+            self.timestamp.second(),
+            self.timestamp.timestamp_subsec_millis().min(999),
+        )
+    }
+}
+
 /// A batch of data points.
 ///
 /// Simple collection without SCADA-level categorization.
 /// The application layer is responsible for routing/storing
 /// points based on their type (determined by id lookup).
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+///
+/// Lookups by id ([`get()`](Self::get), [`get_mut()`](Self::get_mut)) scan
+/// small batches and build an id index on first use for large ones, so
+/// repeated lookups stay O(1). If an id occurs more than once, lookups and
+/// [`remove()`](Self::remove) find the first occurrence.
+///
+/// `Display` writes a count header and one [`DataPoint`] line per point,
+/// sorted by id; `Debug` lists the points in the same compact form.
+///
+/// # Wire format
+///
+/// A batch serializes as `{"points": [...]}`, each point as
+/// `{"id", "value", "quality", "timestamp", "source_timestamp"?}`
+/// with values in the [`Value` wire format](Value#wire-format). Files
+/// that outlive a release should go through [`to_json()`](Self::to_json)
+/// and [`from_json()`](Self::from_json), which add and check a
+/// `schema_version`:
+///
+/// ```text
+/// {"schema_version":1,"igw_version":"0.2.14","points":[{"id":1001,"value":21.5,...}]}
+/// ```
+///
+/// A batch without `schema_version` is read as version 1, the layout every
+/// release so far has written.
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct DataBatch {
     /// All data points in this batch
     points: Vec<DataPoint>,
+
+    /// Position of the first point per id, built lazily for large batches.
+    #[serde(skip)]
+    index: OnceLock<HashMap<PointId, usize>>,
 }
 
+/// Batches up to this size are searched linearly.
+const INDEX_THRESHOLD: usize = 32;
+
+/// Batch wire format written by this version of igw, see
+/// [`DataBatch::to_json()`].
+pub const BATCH_SCHEMA_VERSION: u32 = 1;
+
 impl DataBatch {
     /// Create an empty batch.
     pub fn new() -> Self {
@@ -214,14 +620,73 @@ impl DataBatch {
 
     /// Create a batch from a vector of points.
     pub fn from_points(points: Vec<DataPoint>) -> Self {
-        Self { points }
+        Self {
+            points,
+            index: OnceLock::new(),
+        }
     }
 
     /// Add a data point.
     pub fn add(&mut self, point: DataPoint) {
+        if let Some(index) = self.index.get_mut() {
+            index.entry(point.id).or_insert(self.points.len());
+        }
         self.points.push(point);
     }
 
+    /// Get the point with `id`.
+    pub fn get(&self, id: PointId) -> Option<&DataPoint> {
+        self.position(id).map(|i| &self.points[i])
+    }
+
+    /// Get the point with `id` mutably.
+    ///
+    /// Do not change the point's `id` through the returned reference.
+    pub fn get_mut(&mut self, id: PointId) -> Option<&mut DataPoint> {
+        self.position(id).map(|i| &mut self.points[i])
+    }
+
+    /// Remove and return the point with `id`, keeping the order of the rest.
+    pub fn remove(&mut self, id: PointId) -> Option<DataPoint> {
+        let i = self.position(id)?;
+        self.index.take();
+        Some(self.points.remove(i))
+    }
+
+    /// Keep only the points for which `f` returns `true`.
+    pub fn retain(&mut self, f: impl FnMut(&DataPoint) -> bool) {
+        self.index.take();
+        self.points.retain(f);
+    }
+
+    /// Sort points by timestamp, oldest first (stable).
+    pub fn sort_by_timestamp(&mut self) {
+        self.index.take();
+        self.points.sort_by_key(|p| p.timestamp);
+    }
+
+    /// Consume the batch into a map by id; later duplicates win.
+    pub fn to_map(self) -> HashMap<PointId, DataPoint> {
+        self.points.into_iter().map(|p| (p.id, p)).collect()
+    }
+
+    /// Position of the first point with `id`.
+    fn position(&self, id: PointId) -> Option<usize> {
+        if self.points.len() <= INDEX_THRESHOLD {
+            return self.points.iter().position(|p| p.id == id);
+        }
+        let index = self.index.get_or_init(|| {
+            let mut index = HashMap::with_capacity(self.points.len());
+            for (i, point) in self.points.iter().enumerate() {
+                index.entry(point.id).or_insert(i);
+            }
+            index
+        });
+        let i = *index.get(&id)?;
+        debug_assert_eq!(self.points[i].id, id, "point id changed behind the index");
+        Some(i)
+    }
+
     /// Get total number of points.
     pub fn len(&self) -> usize {
         self.points.len()
@@ -234,6 +699,7 @@ impl DataBatch {
 
     /// Merge another batch into this one.
     pub fn merge(&mut self, other: DataBatch) {
+        self.index.take();
         self.points.extend(other.points);
     }
 
@@ -244,6 +710,7 @@ impl DataBatch {
 
     /// Get mutable iterator over all points.
     pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut DataPoint> {
+        self.index.take();
         self.points.iter_mut()
     }
 
@@ -251,6 +718,235 @@ impl DataBatch {
     pub fn into_vec(self) -> Vec<DataPoint> {
         self.points
     }
+
+    /// View this batch with point metadata joined in for serialization.
+    ///
+    /// Points without an entry in `meta` are serialized unchanged.
+    pub fn with_meta<'a>(&'a self, meta: &'a PointMetaMap) -> AnnotatedBatch<'a> {
+        AnnotatedBatch::new(self, meta)
+    }
+
+    /// Point counts per value kind and quality.
+    pub fn summary(&self) -> BatchSummary {
+        self.iter().collect()
+    }
+
+    /// Serialize with a `schema_version` envelope, see the
+    /// [wire format](Self#wire-format).
+    pub fn to_json(&self) -> Result<String> {
+        #[derive(Serialize)]
+        struct Envelope<'a> {
+            schema_version: u32,
+            igw_version: &'static str,
+            #[serde(flatten)]
+            batch: &'a DataBatch,
+        }
+
+        serde_json::to_string(&Envelope {
+            schema_version: BATCH_SCHEMA_VERSION,
+            igw_version: env!("CARGO_PKG_VERSION"),
+            batch: self,
+        })
+        .map_err(|e| GatewayError::InvalidData(format!("batch: {}", e)))
+    }
+
+    /// Parse a batch written by [`to_json()`](Self::to_json), or a bare
+    /// serialized batch.
+    ///
+    /// The schema version is checked before the points, so a batch of a
+    /// newer layout is reported as such rather than as a parse error.
+    pub fn from_json(json: &str) -> Result<Self> {
+        /// Envelope fields, absent on bare batches.
+        #[derive(Deserialize)]
+        struct Header {
+            #[serde(default = "first_schema_version")]
+            schema_version: u32,
+            #[serde(default)]
+            igw_version: String,
+        }
+
+        fn first_schema_version() -> u32 {
+            1
+        }
+
+        let invalid = |e: serde_json::Error| GatewayError::InvalidData(format!("batch: {}", e));
+        let header: Header = serde_json::from_str(json).map_err(invalid)?;
+        if !(1..=BATCH_SCHEMA_VERSION).contains(&header.schema_version) {
+            return Err(GatewayError::Unsupported(format!(
+                "batch schema version {} (written by igw {}) is not supported, expected 1..={}",
+                header.schema_version,
+                if header.igw_version.is_empty() {
+                    "unknown"
+                } else {
+                    &header.igw_version
+                },
+                BATCH_SCHEMA_VERSION
+            )));
+        }
+        serde_json::from_str(json).map_err(invalid)
+    }
+}
+
+impl fmt::Display for DataBatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut sorted: Vec<&DataPoint> = self.points.iter().collect();
+        sorted.sort_by_key(|p| p.id);
+        write!(f, "{} point", sorted.len())?;
+        if sorted.len() != 1 {
+            f.write_char('s')?;
+        }
+        for point in sorted {
+            f.write_str("\n  ")?;
+            point.fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for DataBatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        /// Debug as the point's display line.
+        struct Line<'a>(&'a DataPoint);
+
+        impl fmt::Debug for Line<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::Display::fmt(self.0, f)
+            }
+        }
+
+        f.write_str("DataBatch ")?;
+        f.debug_list()
+            .entries(self.points.iter().map(Line))
+            .finish()
+    }
+}
+
+/// Point counts of a batch, see [`DataBatch::summary()`].
+///
+/// Displayed as e.g. `12 points: 10 float, 2 bool; 11 good, 1 bad`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BatchSummary {
+    /// Number of points.
+    pub total: usize,
+    /// Points per value kind.
+    pub kinds: KindCounts,
+    /// Points with `Quality::Good`.
+    pub good: usize,
+    /// Points of uncertain quality (severity 1, e.g. `LastKnown`).
+    pub uncertain: usize,
+    /// Points of bad quality (severity 2, e.g. `CommFailure`).
+    pub bad: usize,
+}
+
+/// Points per [`ValueKind`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KindCounts {
+    /// Float points.
+    pub float: usize,
+    /// Integer points.
+    pub integer: usize,
+    /// Boolean points.
+    pub bool: usize,
+    /// String points.
+    pub string: usize,
+    /// Byte string points.
+    pub bytes: usize,
+    /// Array points.
+    pub array: usize,
+    /// Null points.
+    pub null: usize,
+}
+
+impl KindCounts {
+    /// Number of points of `kind`.
+    pub fn get(&self, kind: ValueKind) -> usize {
+        match kind {
+            ValueKind::Float => self.float,
+            ValueKind::Integer => self.integer,
+            ValueKind::Bool => self.bool,
+            ValueKind::String => self.string,
+            ValueKind::Bytes => self.bytes,
+            ValueKind::Array => self.array,
+            ValueKind::Null => self.null,
+        }
+    }
+
+    fn count(&mut self, kind: ValueKind) {
+        *match kind {
+            ValueKind::Float => &mut self.float,
+            ValueKind::Integer => &mut self.integer,
+            ValueKind::Bool => &mut self.bool,
+            ValueKind::String => &mut self.string,
+            ValueKind::Bytes => &mut self.bytes,
+            ValueKind::Array => &mut self.array,
+            ValueKind::Null => &mut self.null,
+        } += 1;
+    }
+}
+
+impl BatchSummary {
+    /// Count `point`.
+    pub fn add(&mut self, point: &DataPoint) {
+        self.total += 1;
+        self.kinds.count(point.value.kind());
+        match point.quality.severity() {
+            0 => self.good += 1,
+            1 => self.uncertain += 1,
+            _ => self.bad += 1,
+        }
+    }
+}
+
+impl<'a> FromIterator<&'a DataPoint> for BatchSummary {
+    fn from_iter<I: IntoIterator<Item = &'a DataPoint>>(iter: I) -> Self {
+        let mut summary = Self::default();
+        iter.into_iter().for_each(|point| summary.add(point));
+        summary
+    }
+}
+
+impl fmt::Display for BatchSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} point", self.total)?;
+        if self.total != 1 {
+            f.write_char('s')?;
+        }
+        if self.total == 0 {
+            return Ok(());
+        }
+
+        let kinds = [
+            ValueKind::Float,
+            ValueKind::Integer,
+            ValueKind::Bool,
+            ValueKind::String,
+            ValueKind::Bytes,
+            ValueKind::Array,
+            ValueKind::Null,
+        ]
+        .into_iter()
+        .map(|kind| (self.kinds.get(kind), kind.name()));
+        let qualities = [
+            (self.good, "good"),
+            (self.uncertain, "uncertain"),
+            (self.bad, "bad"),
+        ];
+        let mut separator = ": ";
+        for (count, name) in kinds {
+            if count > 0 {
+                write!(f, "{}{} {}", separator, count, name)?;
+                separator = ", ";
+            }
+        }
+        separator = "; ";
+        for (count, name) in qualities {
+            if count > 0 {
+                write!(f, "{}{} {}", separator, count, name)?;
+                separator = ", ";
+            }
+        }
+        Ok(())
+    }
 }
 
 impl IntoIterator for DataBatch {
@@ -273,9 +969,7 @@ impl<'a> IntoIterator for &'a DataBatch {
 
 impl FromIterator<DataPoint> for DataBatch {
     fn from_iter<I: IntoIterator<Item = DataPoint>>(iter: I) -> Self {
-        Self {
-            points: iter.into_iter().collect(),
-        }
+        Self::from_points(iter.into_iter().collect())
     }
 }
 
@@ -292,6 +986,193 @@ mod tests {
         let v = Value::from(true);
         assert_eq!(v.as_bool(), Some(true));
         assert_eq!(v.as_f64(), Some(1.0));
+
+        // The lossy conversions
+        assert_eq!(Value::Float(-2.9).as_i64(), Some(-2));
+        assert_eq!(Value::Float(f64::NAN).as_i64(), Some(0));
+        assert_eq!(Value::Float(f64::INFINITY).as_i64(), Some(i64::MAX));
+        assert_eq!(Value::Float(1e30).as_i64(), Some(i64::MAX));
+        assert_eq!(
+            Value::Integer(i64::MAX).as_f64(),
+            Some(9_223_372_036_854_775_808.0)
+        );
+    }
+
+    #[test]
+    fn test_checked_value_conversions() {
+        fn conversion_error<T: fmt::Debug>(result: Result<T>) -> String {
+            match result {
+                Err(GatewayError::DataConversion(message)) => message,
+                other => panic!("expected a conversion error, got {:?}", other),
+            }
+        }
+
+        assert_eq!(Value::Float(-7.0).try_as_i64().unwrap(), -7);
+        assert_eq!(Value::Integer(i64::MIN).try_as_i64().unwrap(), i64::MIN);
+        assert_eq!(Value::Bool(true).try_as_i64().unwrap(), 1);
+        assert_eq!(
+            conversion_error(Value::Float(42.5).try_as_i64()),
+            "42.5 is not a whole number"
+        );
+        assert_eq!(
+            conversion_error(Value::Float(f64::NAN).try_as_i64()),
+            "NaN is not an integer"
+        );
+        assert!(conversion_error(Value::Float(f64::NEG_INFINITY).try_as_i64()).contains("range"));
+        assert!(conversion_error(Value::Float(9.3e18).try_as_i64()).contains("range"));
+        assert_eq!(
+            Value::Float(-9_223_372_036_854_775_808.0)
+                .try_as_i64()
+                .unwrap(),
+            i64::MIN
+        );
+        assert_eq!(
+            conversion_error(Value::String("5".into()).try_as_i64()),
+            "string value cannot be converted to i64"
+        );
+
+        assert_eq!(
+            Value::Integer(4_000_000_000).try_as_u32().unwrap(),
+            4e9 as u32
+        );
+        assert_eq!(
+            conversion_error(Value::Integer(-1).try_as_u32()),
+            "-1 is outside the u32 range"
+        );
+        assert!(Value::Integer(1 << 32).try_as_u32().is_err());
+
+        assert_eq!(
+            Value::Integer(1 << 53).try_as_f64_exact().unwrap(),
+            2f64.powi(53)
+        );
+        assert_eq!(
+            Value::Integer(i64::MIN).try_as_f64_exact().unwrap(),
+            -(2f64.powi(63))
+        );
+        assert_eq!(
+            conversion_error(Value::Integer((1 << 53) + 1).try_as_f64_exact()),
+            "9007199254740993 has no exact f64 (nearest is 9007199254740992)"
+        );
+        assert!(Value::Integer(i64::MAX).try_as_f64_exact().is_err());
+        assert!(Value::Float(f64::NAN).try_as_f64_exact().unwrap().is_nan());
+        assert!(Value::Null.try_as_f64_exact().is_err());
+    }
+
+    #[test]
+    fn test_array_value() {
+        let cells = Value::from(vec![Value::Float(3.31), Value::Float(3.29)]);
+        assert_eq!(cells.array_len(), Some(2));
+        assert_eq!(cells.as_array().unwrap()[1], Value::Float(3.29));
+        assert_eq!(cells.as_f64(), None);
+        assert_eq!(cells.as_bool(), None);
+        assert_eq!(Value::Float(1.0).array_len(), None);
+
+        let json = serde_json::to_string(&cells).unwrap();
+        assert_eq!(json, "[3.31,3.29]");
+        assert_eq!(serde_json::from_str::<Value>(&json).unwrap(), cells);
+
+        let mixed = Value::Array(vec![Value::Bool(true), Value::String("x".into())]);
+        let json = serde_json::to_string(&mixed).unwrap();
+        assert_eq!(serde_json::from_str::<Value>(&json).unwrap(), mixed);
+        // Small integers are indistinguishable from bytes
+        assert_eq!(
+            serde_json::from_str::<Value>("[1,2]").unwrap(),
+            Value::Bytes(vec![1, 2])
+        );
+        let codes = Value::Array(vec![Value::Integer(1), Value::Integer(300)]);
+        let json = serde_json::to_string(&codes).unwrap();
+        assert_eq!(serde_json::from_str::<Value>(&json).unwrap(), codes);
+    }
+
+    #[test]
+    fn test_value_round_trip() {
+        for value in [
+            Value::Float(1.0),
+            Value::Float(-0.5),
+            Value::Float(1e300),
+            Value::Integer(1),
+            Value::Integer(i64::MIN),
+            Value::Bool(false),
+            Value::String("1.0".into()),
+            Value::Bytes(vec![0, 255]),
+            Value::Array(vec![Value::Float(2.0), Value::Integer(2)]),
+            Value::Null,
+        ] {
+            let json = serde_json::to_string(&value).unwrap();
+            assert_eq!(
+                serde_json::from_str::<Value>(&json).unwrap(),
+                value,
+                "{}",
+                json
+            );
+        }
+        assert_eq!(serde_json::to_string(&Value::Float(1.0)).unwrap(), "1.0");
+
+        // Documented coercions
+        let json = serde_json::to_string(&Value::Float(f64::NAN)).unwrap();
+        assert_eq!(serde_json::from_str::<Value>(&json).unwrap(), Value::Null);
+        assert_eq!(
+            serde_json::from_str::<Value>("18446744073709551615").unwrap(),
+            Value::Float(u64::MAX as f64)
+        );
+        assert_eq!(
+            serde_json::from_str::<Value>("[]").unwrap(),
+            Value::Bytes(vec![])
+        );
+        assert!(serde_json::from_str::<Value>(r#"{"a":1}"#).is_err());
+    }
+
+    /// A batch as written by igw 0.2.14, before the schema envelope.
+    const BATCH_0_2_14: &str = r#"{"points":[
+        {"id":1,"value":21.0,"quality":"good","timestamp":"2024-05-01T03:00:00Z"},
+        {"id":2,"value":7,"quality":"good","timestamp":"2024-05-01T03:00:00Z"},
+        {"id":3,"value":true,"quality":"last_known","timestamp":"2024-05-01T03:00:00Z"},
+        {"id":4,"value":"auto","quality":"good","timestamp":"2024-05-01T03:00:00Z",
+         "source_timestamp":"2024-05-01T02:59:59.500Z"},
+        {"id":5,"value":[3.31,3.29],"quality":"good","timestamp":"2024-05-01T03:00:00Z"},
+        {"id":6,"value":[1,2],"quality":"good","timestamp":"2024-05-01T03:00:00Z"},
+        {"id":"7","value":null,"quality":"comm_failure","timestamp":"2024-05-01T03:00:00Z"}
+    ]}"#;
+
+    #[test]
+    fn test_batch_compatibility() {
+        let batch = DataBatch::from_json(BATCH_0_2_14).unwrap();
+        let values: Vec<_> = batch.iter().map(|p| p.value.clone()).collect();
+        assert_eq!(
+            values,
+            vec![
+                Value::Float(21.0),
+                Value::Integer(7),
+                Value::Bool(true),
+                Value::String("auto".into()),
+                Value::Array(vec![Value::Float(3.31), Value::Float(3.29)]),
+                Value::Bytes(vec![1, 2]),
+                Value::Null,
+            ]
+        );
+        assert!(batch.get(4).unwrap().source_timestamp.is_some());
+        assert_eq!(batch.get(3).unwrap().quality, Quality::LastKnown);
+        assert_eq!(batch.get(7).unwrap().quality, Quality::CommFailure);
+        // Plain serde reads the same
+        let plain: DataBatch = serde_json::from_str(BATCH_0_2_14).unwrap();
+        assert_eq!(plain.len(), 7);
+
+        // Through the envelope and back
+        let json = batch.to_json().unwrap();
+        assert!(json.starts_with(r#"{"schema_version":1,"igw_version":""#));
+        let again = DataBatch::from_json(&json).unwrap();
+        assert_eq!(format!("{:?}", again), format!("{:?}", batch));
+        // Readers without envelope support still see the batch
+        assert_eq!(serde_json::from_str::<DataBatch>(&json).unwrap().len(), 7);
+
+        let newer = r#"{"schema_version":2,"igw_version":"9.0.0","points":[]}"#;
+        let error = DataBatch::from_json(newer).unwrap_err().to_string();
+        assert!(
+            error.contains("batch schema version 2 (written by igw 9.0.0) is not supported"),
+            "{}",
+            error
+        );
+        assert!(DataBatch::from_json("[]").is_err());
     }
 
     #[test]
@@ -322,4 +1203,117 @@ mod tests {
         let batch: DataBatch = points.into_iter().collect();
         assert_eq!(batch.len(), 2);
     }
+
+    #[test]
+    fn test_data_batch_lookup() {
+        // Below and above the index threshold
+        for size in [10, 1000] {
+            let mut batch: DataBatch = (0..size).map(|id| DataPoint::new(id, id as i64)).collect();
+            assert_eq!(batch.get(7).unwrap().value, Value::Integer(7));
+            assert!(batch.get(size).is_none());
+
+            batch.get_mut(7).unwrap().quality = Quality::Invalid;
+            assert_eq!(batch.get(7).unwrap().quality, Quality::Invalid);
+
+            // Appending keeps an already built index usable
+            batch.add(DataPoint::new(size, -1i64));
+            batch.add(DataPoint::new(3, -3i64));
+            assert_eq!(batch.get(size).unwrap().value, Value::Integer(-1));
+            assert_eq!(batch.get(3).unwrap().value, Value::Integer(3));
+
+            let removed = batch.remove(3).unwrap();
+            assert_eq!(removed.value, Value::Integer(3));
+            assert_eq!(batch.get(3).unwrap().value, Value::Integer(-3));
+            assert_eq!(batch.get(4).unwrap().value, Value::Integer(4));
+
+            batch.retain(|p| p.id % 2 == 0);
+            assert!(batch.get(5).is_none());
+            assert_eq!(batch.get(6).unwrap().value, Value::Integer(6));
+        }
+    }
+
+    #[test]
+    fn test_data_batch_sort_and_map() {
+        let now = Utc::now();
+        let mut batch = DataBatch::new();
+        for (id, age) in [(1, 3), (2, 1), (3, 2), (1, 0)] {
+            let mut point = DataPoint::new(id, age);
+            point.timestamp = now - chrono::Duration::seconds(age);
+            batch.add(point);
+        }
+        batch.sort_by_timestamp();
+        let ids: Vec<_> = batch.iter().map(|p| p.id).collect();
+        assert_eq!(ids, vec![1, 3, 2, 1]);
+
+        let map = batch.to_map();
+        assert_eq!(map.len(), 3);
+        assert_eq!(map[&1].value, Value::Integer(0));
+    }
+
+    #[test]
+    fn test_display() {
+        let at = |ms| {
+            DateTime::parse_from_rfc3339(ms)
+                .unwrap()
+                .with_timezone(&Utc)
+        };
+        let mut point = DataPoint::new(1001, 25.5);
+        point.timestamp = at("2024-05-01T12:30:01.123456Z");
+        assert_eq!(point.to_string(), "[F] 1001=25.50 Good @12:30:01.123");
+        assert_eq!(
+            format!("{:.3}", point),
+            "[F] 1001=25.500 Good @12:30:01.123"
+        );
+
+        let mut flag = DataPoint::new(7, true).with_quality(Quality::CommFailure);
+        flag.timestamp = at("2024-05-01T08:00:00Z");
+        let mut raw = DataPoint::new(3, Value::Bytes(vec![0x01, 0xff]));
+        raw.timestamp = flag.timestamp;
+        let batch = DataBatch::from_points(vec![point, flag, raw]);
+        assert_eq!(
+            batch.to_string(),
+            "3 points\n  \
+             [X] 3=0x01ff Good @08:00:00.000\n  \
+             [B] 7=true CommFailure @08:00:00.000\n  \
+             [F] 1001=25.50 Good @12:30:01.123"
+        );
+        assert!(format!("{:?}", batch).starts_with("DataBatch [[F] 1001=25.50 Good"));
+
+        let cells = Value::Array(vec![Value::Float(3.3), Value::String("ok".into())]);
+        assert_eq!(cells.to_string(), "[3.3, \"ok\"]");
+        assert_eq!(DataBatch::new().to_string(), "0 points");
+    }
+
+    #[test]
+    fn test_summary() {
+        let batch = DataBatch::from_points(vec![
+            DataPoint::new(1, 1.5),
+            DataPoint::new(2, 2.5).with_quality(Quality::LastKnown),
+            DataPoint::new(3, true).with_quality(Quality::CommFailure),
+            DataPoint::new(4, 7i64),
+        ]);
+        let summary = batch.summary();
+        assert_eq!(summary.total, 4);
+        assert_eq!(summary.kinds.get(ValueKind::Float), 2);
+        assert_eq!((summary.good, summary.uncertain, summary.bad), (2, 1, 1));
+        assert_eq!(
+            summary.to_string(),
+            "4 points: 2 float, 1 integer, 1 bool; 2 good, 1 uncertain, 1 bad"
+        );
+        assert_eq!(DataBatch::new().summary().to_string(), "0 points");
+    }
+
+    #[test]
+    fn test_point_id_accepts_numeric_strings() {
+        let json = r#"{"id":"42","value":1.5,"timestamp":"2024-01-01T00:00:00Z"}"#;
+        let point: DataPoint = serde_json::from_str(json).unwrap();
+        assert_eq!(point.id, 42);
+
+        let json = r#"{"id":7,"value":true,"timestamp":"2024-01-01T00:00:00Z"}"#;
+        let point: DataPoint = serde_json::from_str(json).unwrap();
+        assert_eq!(point.id, 7);
+
+        let json = r#"{"id":"temperature","value":1.0,"timestamp":"2024-01-01T00:00:00Z"}"#;
+        assert!(serde_json::from_str::<DataPoint>(json).is_err());
+    }
 }
@@ -0,0 +1,193 @@
+//! Deadband filtering at acquisition time.
+//!
+//! Channels decoding numeric values keep a [`DeadbandFilter`] built from
+//! their points' [`TransformConfig::deadband`](crate::core::point::TransformConfig::deadband).
+//! It remembers the engineering value each point was last published with
+//! and suppresses samples that stay within the deadband of it, so a noisy
+//! signal only reaches the caller when it actually moves.
+//!
+//! - The deadband is in engineering units: it is compared against values
+//!   after scaling and offset.
+//! - A sample whose quality differs from the last published one is always
+//!   published, whatever its value.
+//! - Points without a (positive) deadband, and non-numeric values, are
+//!   never filtered.
+//!
+//! Channel-level filtering happens first. Whatever is published then goes
+//! through the gateway's own change detection and the store's watchers,
+//! which compare against the last *published* value with the same
+//! deadband, so they never see the jitter suppressed here.
+
+use std::collections::HashMap;
+
+use crate::core::data::{DataBatch, DataPoint, PointId, Value};
+use crate::core::point::PointConfig;
+use crate::core::quality::Quality;
+
+/// Suppresses numeric samples within their point's deadband.
+#[derive(Debug, Clone, Default)]
+pub struct DeadbandFilter {
+    deadbands: HashMap<PointId, f64>,
+    /// Last published value and quality of each filtered point.
+    published: HashMap<PointId, (f64, Quality)>,
+}
+
+impl DeadbandFilter {
+    /// Filter for the deadbands configured on `points`.
+    pub fn new(points: &[PointConfig]) -> Self {
+        Self::from_deadbands(
+            points
+                .iter()
+                .filter_map(|p| Some((p.id, p.transform.deadband?))),
+        )
+    }
+
+    /// Filter for explicit `(point, deadband)` pairs.
+    pub fn from_deadbands(deadbands: impl IntoIterator<Item = (PointId, f64)>) -> Self {
+        Self {
+            deadbands: deadbands
+                .into_iter()
+                .filter(|(_, deadband)| *deadband > 0.0)
+                .collect(),
+            published: HashMap::new(),
+        }
+    }
+
+    /// Whether no point has a deadband.
+    pub fn is_empty(&self) -> bool {
+        self.deadbands.is_empty()
+    }
+
+    /// Take the deadbands of a new point list.
+    ///
+    /// Points whose deadband is unchanged keep their last published value.
+    pub fn set_points(&mut self, points: &[PointConfig]) {
+        let next = Self::new(points);
+        self.published
+            .retain(|id, _| next.deadbands.get(id) == self.deadbands.get(id));
+        self.deadbands = next.deadbands;
+    }
+
+    /// Whether `point` is to be published, remembering it if so.
+    pub fn admit(&mut self, point: &DataPoint) -> bool {
+        let Some(&deadband) = self.deadbands.get(&point.id) else {
+            return true;
+        };
+        let value = match point.value {
+            Value::Float(v) => v,
+            Value::Integer(v) => v as f64,
+            _ => return true,
+        };
+        if let Some(&(last, quality)) = self.published.get(&point.id) {
+            if quality == point.quality && (value - last).abs() < deadband {
+                return false;
+            }
+        }
+        self.published.insert(point.id, (value, point.quality));
+        true
+    }
+
+    /// Drop the samples of `batch` within their deadband, returning how many
+    /// were dropped.
+    pub fn retain(&mut self, batch: &mut DataBatch) -> usize {
+        if self.deadbands.is_empty() {
+            return 0;
+        }
+        let before = batch.len();
+        let kept: Vec<DataPoint> = batch.iter().filter(|p| self.admit(p)).cloned().collect();
+        let dropped = before - kept.len();
+        if dropped > 0 {
+            *batch = DataBatch::from_points(kept);
+        }
+        dropped
+    }
+
+    /// Forget the last published value of `id`, so its next sample is
+    /// published (e.g. after a failed read).
+    pub fn forget(&mut self, id: PointId) {
+        self.published.remove(&id);
+    }
+
+    /// Forget all published values, e.g. after a reconnect.
+    pub fn reset(&mut self) {
+        self.published.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic noisy ramp: 0.1 per sample plus ±0.3 of jitter.
+    fn noisy_signal(samples: usize) -> Vec<f64> {
+        let mut state = 0x2545_f491_u32;
+        (0..samples)
+            .map(|i| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                let jitter = (state % 601) as f64 / 1000.0 - 0.3;
+                50.0 + i as f64 * 0.1 + jitter
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_noisy_signal_volume() {
+        let samples = noisy_signal(1000);
+        let mut filter = DeadbandFilter::from_deadbands([(1, 2.0)]);
+
+        let mut published = Vec::new();
+        for &v in &samples {
+            if filter.admit(&DataPoint::new(1, v)) {
+                published.push(v);
+            } else {
+                // Suppressed samples stay within the deadband of the last event
+                assert!((v - published.last().unwrap()).abs() < 2.0);
+            }
+        }
+
+        // The ramp moves 100 units: about one event per 2 units instead of 1000
+        assert!(published.len() < 100, "{} events", published.len());
+        assert!(published.len() >= 40, "{} events", published.len());
+        assert_eq!(published[0], samples[0]);
+        for pair in published.windows(2) {
+            assert!((pair[1] - pair[0]).abs() >= 2.0);
+        }
+    }
+
+    #[test]
+    fn test_quality_change_always_published() {
+        let mut filter = DeadbandFilter::from_deadbands([(1, 1.0)]);
+        assert!(filter.admit(&DataPoint::new(1, 10.0)));
+        assert!(!filter.admit(&DataPoint::new(1, 10.5)));
+        assert!(filter.admit(&DataPoint::new(1, 10.5).with_quality(Quality::Uncertain)));
+        assert!(!filter.admit(&DataPoint::new(1, 10.6).with_quality(Quality::Uncertain)));
+        assert!(filter.admit(&DataPoint::new(1, 10.6)));
+        assert!(filter.admit(&DataPoint::new(1, 11.6)));
+
+        filter.forget(1);
+        assert!(filter.admit(&DataPoint::new(1, 11.6)));
+        filter.reset();
+        assert!(filter.admit(&DataPoint::new(1, 11.6)));
+    }
+
+    #[test]
+    fn test_unfiltered_points() {
+        let mut filter = DeadbandFilter::from_deadbands([(1, 1.0), (2, 0.0)]);
+        let mut batch: DataBatch = [
+            DataPoint::new(1, 5.0),
+            DataPoint::new(1, 5.2),
+            DataPoint::new(2, 5.0),
+            DataPoint::new(2, 5.0),
+            DataPoint::new(3, 5.0),
+            DataPoint::new(3, 5.0),
+            DataPoint::new(1, Value::String("on".into())),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(filter.retain(&mut batch), 1);
+        assert_eq!(batch.len(), 6);
+    }
+}
@@ -0,0 +1,434 @@
+//! Shared diagnostics bookkeeping for protocol adapters.
+//!
+//! [`DiagnosticsRecorder`] holds the counters every adapter needs (reads,
+//! writes, errors, bytes) plus timing information: the duration of the last
+//! poll cycle, a rolling window of request latencies and the time of the last
+//! successful read. Adapters embed one (usually behind an `Arc` so background
+//! tasks can record into it) and build their [`Diagnostics`] from it.
+//!
+//! All recording methods take `&self` and never block on async locks, so they
+//! are safe to call from hot paths.
+//!
+//! [`ConnectionHistory`] keeps the last connection state transitions of a
+//! channel and derives the numbers operators ask for: since when the
+//! channel is connected, how often it lost its connection in the last 24
+//! hours and the share of time it was connected.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::core::traits::{ConnectionState, Diagnostics};
+
+/// Default number of latency samples kept for percentile calculation.
+pub const DEFAULT_LATENCY_WINDOW: usize = 256;
+
+/// Default number of state transitions kept by a [`ConnectionHistory`].
+pub const DEFAULT_TRANSITION_HISTORY: usize = 64;
+
+/// Thread-safe recorder for adapter statistics and timing.
+#[derive(Debug)]
+pub struct DiagnosticsRecorder {
+    read_count: AtomicU64,
+    write_count: AtomicU64,
+    error_count: AtomicU64,
+    bytes_sent: AtomicU64,
+    bytes_received: AtomicU64,
+    window: usize,
+    state: Mutex<RecorderState>,
+}
+
+#[derive(Debug, Default)]
+struct RecorderState {
+    last_error: Option<String>,
+    last_success: Option<DateTime<Utc>>,
+    last_poll_duration_ms: Option<u64>,
+    /// Recent request latencies in milliseconds (oldest first).
+    latencies: VecDeque<f64>,
+}
+
+impl Default for DiagnosticsRecorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DiagnosticsRecorder {
+    /// Create a recorder with the default latency window.
+    pub fn new() -> Self {
+        Self::with_latency_window(DEFAULT_LATENCY_WINDOW)
+    }
+
+    /// Create a recorder keeping the last `window` latency samples.
+    pub fn with_latency_window(window: usize) -> Self {
+        Self {
+            read_count: AtomicU64::new(0),
+            write_count: AtomicU64::new(0),
+            error_count: AtomicU64::new(0),
+            bytes_sent: AtomicU64::new(0),
+            bytes_received: AtomicU64::new(0),
+            window: window.max(1),
+            state: Mutex::new(RecorderState::default()),
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, RecorderState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Record `count` successful reads and stamp the last-success time.
+    pub fn record_read(&self, count: u64) {
+        self.read_count.fetch_add(count, Ordering::Relaxed);
+        if count > 0 {
+            self.state().last_success = Some(Utc::now());
+        }
+    }
+
+    /// Record `count` successful writes.
+    pub fn record_write(&self, count: u64) {
+        self.write_count.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// Record an error and remember its message.
+    pub fn record_error(&self, error: impl Into<String>) {
+        self.error_count.fetch_add(1, Ordering::Relaxed);
+        self.state().last_error = Some(error.into());
+    }
+
+    /// Record bytes sent on the wire.
+    pub fn record_bytes_sent(&self, bytes: u64) {
+        self.bytes_sent.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Record bytes received from the wire.
+    pub fn record_bytes_received(&self, bytes: u64) {
+        self.bytes_received.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Record the latency of a single request/response exchange.
+    pub fn record_latency(&self, latency: Duration) {
+        let mut state = self.state();
+        if state.latencies.len() == self.window {
+            state.latencies.pop_front();
+        }
+        state.latencies.push_back(latency.as_secs_f64() * 1000.0);
+    }
+
+    /// Record the duration of a complete poll cycle.
+    pub fn record_poll(&self, duration: Duration) {
+        self.state().last_poll_duration_ms = Some(duration.as_millis() as u64);
+    }
+
+    /// Number of successful reads.
+    pub fn read_count(&self) -> u64 {
+        self.read_count.load(Ordering::Relaxed)
+    }
+
+    /// Number of successful writes.
+    pub fn write_count(&self) -> u64 {
+        self.write_count.load(Ordering::Relaxed)
+    }
+
+    /// Number of errors.
+    pub fn error_count(&self) -> u64 {
+        self.error_count.load(Ordering::Relaxed)
+    }
+
+    /// Last recorded error message.
+    pub fn last_error(&self) -> Option<String> {
+        self.state().last_error.clone()
+    }
+
+    /// Build a [`Diagnostics`] snapshot.
+    ///
+    /// `extra` is left as `Null`; adapters fill in protocol-specific details.
+    pub fn to_diagnostics(
+        &self,
+        protocol: impl Into<String>,
+        connection_state: ConnectionState,
+    ) -> Diagnostics {
+        let state = self.state();
+        let (p50, p95, max) = latency_stats(&state.latencies);
+
+        Diagnostics {
+            protocol: protocol.into(),
+            connection_state,
+            read_count: self.read_count(),
+            write_count: self.write_count(),
+            error_count: self.error_count(),
+            last_error: state.last_error.clone(),
+            last_poll_duration_ms: state.last_poll_duration_ms,
+            latency_p50_ms: p50,
+            latency_p95_ms: p95,
+            latency_max_ms: max,
+            bytes_sent: self.bytes_sent.load(Ordering::Relaxed),
+            bytes_received: self.bytes_received.load(Ordering::Relaxed),
+            last_success: state.last_success,
+            ..Diagnostics::default()
+        }
+    }
+}
+
+/// A connection state change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct StateTransition {
+    /// When the state changed.
+    pub timestamp: DateTime<Utc>,
+
+    /// State entered.
+    pub state: ConnectionState,
+}
+
+/// Connection figures derived from a [`ConnectionHistory`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ConnectionSummary {
+    /// Start of the current connection, `None` while not connected.
+    pub connected_since: Option<DateTime<Utc>>,
+
+    /// Last state change.
+    pub state_changed_at: Option<DateTime<Utc>>,
+
+    /// Connections lost in the last 24 hours.
+    pub disconnect_count_24h: u64,
+
+    /// Share of the tracked time spent connected, in percent.
+    pub availability_percent: f64,
+}
+
+/// Connection state transitions of a channel, with uptime accounting.
+///
+/// The last `capacity` transitions are kept for inspection; uptime and the
+/// disconnect count cover the whole tracked time regardless.
+#[derive(Debug, Clone)]
+pub struct ConnectionHistory {
+    capacity: usize,
+    transitions: VecDeque<StateTransition>,
+    tracked_since: DateTime<Utc>,
+    /// Time connected before the current connection.
+    uptime: chrono::Duration,
+    connected_since: Option<DateTime<Utc>>,
+    /// Times the connection was lost, within the last 24 hours.
+    disconnects: VecDeque<DateTime<Utc>>,
+}
+
+impl Default for ConnectionHistory {
+    fn default() -> Self {
+        Self::new(DEFAULT_TRANSITION_HISTORY)
+    }
+}
+
+impl ConnectionHistory {
+    /// History keeping the last `capacity` transitions, tracking from now.
+    pub fn new(capacity: usize) -> Self {
+        Self::starting_at(capacity, Utc::now())
+    }
+
+    /// History keeping the last `capacity` transitions, tracking from
+    /// `start`.
+    pub fn starting_at(capacity: usize, start: DateTime<Utc>) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            transitions: VecDeque::new(),
+            tracked_since: start,
+            uptime: chrono::Duration::zero(),
+            connected_since: None,
+            disconnects: VecDeque::new(),
+        }
+    }
+
+    /// Record that the channel entered `state` now.
+    pub fn record(&mut self, state: ConnectionState) {
+        self.record_at(state, Utc::now());
+    }
+
+    /// Record that the channel entered `state` at `timestamp`.
+    ///
+    /// Repeats of the current state are ignored.
+    pub fn record_at(&mut self, state: ConnectionState, timestamp: DateTime<Utc>) {
+        if self.transitions.back().map(|t| t.state) == Some(state) {
+            return;
+        }
+        match (self.connected_since, state.is_connected()) {
+            (None, true) => self.connected_since = Some(timestamp),
+            (Some(since), false) => {
+                self.uptime += (timestamp - since).max(chrono::Duration::zero());
+                self.connected_since = None;
+                self.disconnects.push_back(timestamp);
+                prune_day(&mut self.disconnects, timestamp);
+            }
+            _ => {}
+        }
+        if self.transitions.len() == self.capacity {
+            self.transitions.pop_front();
+        }
+        self.transitions
+            .push_back(StateTransition { timestamp, state });
+    }
+
+    /// Kept transitions, oldest first.
+    pub fn transitions(&self) -> impl Iterator<Item = &StateTransition> {
+        self.transitions.iter()
+    }
+
+    /// Figures as of now.
+    pub fn summary(&mut self) -> ConnectionSummary {
+        self.summary_at(Utc::now())
+    }
+
+    /// Figures as of `now`.
+    pub fn summary_at(&mut self, now: DateTime<Utc>) -> ConnectionSummary {
+        prune_day(&mut self.disconnects, now);
+        let current = self
+            .connected_since
+            .map_or(chrono::Duration::zero(), |since| {
+                (now - since).max(chrono::Duration::zero())
+            });
+        let tracked = (now - self.tracked_since).num_milliseconds();
+        let availability_percent = if tracked > 0 {
+            ((self.uptime + current).num_milliseconds() as f64 * 100.0 / tracked as f64)
+                .clamp(0.0, 100.0)
+        } else if self.connected_since.is_some() {
+            100.0
+        } else {
+            0.0
+        };
+        ConnectionSummary {
+            connected_since: self.connected_since,
+            state_changed_at: self.transitions.back().map(|t| t.timestamp),
+            disconnect_count_24h: self.disconnects.len() as u64,
+            availability_percent,
+        }
+    }
+}
+
+/// Drop the timestamps older than 24 hours before `now`.
+fn prune_day(timestamps: &mut VecDeque<DateTime<Utc>>, now: DateTime<Utc>) {
+    let cutoff = now - chrono::Duration::hours(24);
+    while timestamps.front().is_some_and(|&t| t <= cutoff) {
+        timestamps.pop_front();
+    }
+}
+
+/// Nearest-rank p50/p95 and max of the latency window.
+fn latency_stats(samples: &VecDeque<f64>) -> (Option<f64>, Option<f64>, Option<f64>) {
+    if samples.is_empty() {
+        return (None, None, None);
+    }
+
+    let mut sorted: Vec<f64> = samples.iter().copied().collect();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+
+    let rank = |p: f64| {
+        let idx = ((p * sorted.len() as f64).ceil() as usize).saturating_sub(1);
+        sorted[idx.min(sorted.len() - 1)]
+    };
+
+    (Some(rank(0.50)), Some(rank(0.95)), sorted.last().copied())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counters_and_errors() {
+        let recorder = DiagnosticsRecorder::new();
+        recorder.record_read(3);
+        recorder.record_write(1);
+        recorder.record_error("timeout");
+        recorder.record_bytes_sent(12);
+        recorder.record_bytes_received(25);
+
+        let diag = recorder.to_diagnostics("Test", ConnectionState::Connected);
+        assert_eq!(diag.read_count, 3);
+        assert_eq!(diag.write_count, 1);
+        assert_eq!(diag.error_count, 1);
+        assert_eq!(diag.last_error.as_deref(), Some("timeout"));
+        assert_eq!(diag.bytes_sent, 12);
+        assert_eq!(diag.bytes_received, 25);
+        assert!(diag.last_success.is_some());
+        assert_eq!(diag.latency_p50_ms, None);
+    }
+
+    #[test]
+    fn test_latency_percentiles() {
+        let recorder = DiagnosticsRecorder::new();
+        for ms in 1..=100 {
+            recorder.record_latency(Duration::from_millis(ms));
+        }
+        recorder.record_poll(Duration::from_millis(42));
+
+        let diag = recorder.to_diagnostics("Test", ConnectionState::Connected);
+        assert_eq!(diag.latency_p50_ms, Some(50.0));
+        assert_eq!(diag.latency_p95_ms, Some(95.0));
+        assert_eq!(diag.latency_max_ms, Some(100.0));
+        assert_eq!(diag.last_poll_duration_ms, Some(42));
+    }
+
+    #[test]
+    fn test_latency_window_rolls() {
+        let recorder = DiagnosticsRecorder::with_latency_window(2);
+        recorder.record_latency(Duration::from_millis(500));
+        recorder.record_latency(Duration::from_millis(10));
+        recorder.record_latency(Duration::from_millis(20));
+
+        let diag = recorder.to_diagnostics("Test", ConnectionState::Connected);
+        assert_eq!(diag.latency_max_ms, Some(20.0));
+    }
+
+    #[test]
+    fn test_connection_history() {
+        let start = Utc::now();
+        let at = |minutes: i64| start + chrono::Duration::minutes(minutes);
+        let mut history = ConnectionHistory::starting_at(3, start);
+        assert_eq!(history.summary_at(at(10)).availability_percent, 0.0);
+
+        history.record_at(ConnectionState::Connecting, at(10));
+        history.record_at(ConnectionState::Connected, at(20));
+        history.record_at(ConnectionState::Connected, at(30));
+        history.record_at(ConnectionState::Reconnecting, at(60));
+        history.record_at(ConnectionState::Connected, at(70));
+
+        let summary = history.summary_at(at(100));
+        assert_eq!(summary.connected_since, Some(at(70)));
+        assert_eq!(summary.state_changed_at, Some(at(70)));
+        assert_eq!(summary.disconnect_count_24h, 1);
+        // Connected 20..60 and 70..100
+        assert_eq!(summary.availability_percent, 70.0);
+        // Only the last 3 transitions are kept
+        let states: Vec<_> = history.transitions().map(|t| t.state).collect();
+        assert_eq!(
+            states,
+            [
+                ConnectionState::Connected,
+                ConnectionState::Reconnecting,
+                ConnectionState::Connected
+            ]
+        );
+
+        // The disconnect ages out after a day
+        history.record_at(ConnectionState::Error, at(200));
+        let summary = history.summary_at(at(60 * 24 + 61));
+        assert_eq!(summary.connected_since, None);
+        assert_eq!(summary.disconnect_count_24h, 1);
+    }
+
+    #[test]
+    fn test_old_json_still_deserializes() {
+        let json = r#"{
+            "protocol": "Modbus",
+            "connection_state": "connected",
+            "read_count": 1,
+            "write_count": 0,
+            "error_count": 0,
+            "last_error": null
+        }"#;
+        let diag: Diagnostics = serde_json::from_str(json).unwrap();
+        assert_eq!(diag.bytes_sent, 0);
+        assert!(diag.last_poll_duration_ms.is_none());
+    }
+}
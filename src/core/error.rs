@@ -1,17 +1,51 @@
 //! Error types for the Industrial Gateway.
-
+//!
+//! Every [`GatewayError`] has a stable numeric [`code()`](GatewayError::code)
+//! and a coarse [`category()`](GatewayError::category), reported next to
+//! the message by the HTTP API and the JSON Lines output (see
+//! [`ErrorReport`]). Codes are grouped by the section of the variant:
+//!
+//! | Codes | Variants |
+//! |---|---|
+//! | 1001-1003 | connection: `Connection`, `NotConnected`, `ConnectionTimeout` |
+//! | 2001-2003 | protocol: `Protocol`, `InvalidResponse`, `Unsupported` |
+//! | 3001-3003 | data: `InvalidData`, `DataConversion`, `PointNotFound` |
+//! | 4001-4002 | configuration: `Config`, `InvalidAddress` |
+//! | 5001-5003 | I/O: `Io`, `ReadTimeout`, `WriteTimeout` |
+//! | 6001-6004 | protocol-specific: `Modbus`, `Iec104`, `Dnp3`, `OpcUa` |
+//! | 7001-7003 | commands: `Interlock`, `QueueFull`, `PermissionDenied` |
+//! | 8001 | storage: `Storage` |
+//! | 9001-9002 | internal: `Internal`, `ChannelClosed` |
+//!
+//! Codes are never reused; a new variant gets the next code of its section.
+//!
+//! Errors raised from a lower-level error (a socket, a serial port, a
+//! protocol library) keep it as their [`source()`](std::error::Error::source),
+//! so callers can tell e.g. a refused connection from a failed name lookup
+//! with [`io_kind()`](GatewayError::io_kind) or by downcasting.
+
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 /// A specialized Result type for gateway operations.
 pub type Result<T> = std::result::Result<T, GatewayError>;
 
+/// Lower-level error kept as the source of a [`GatewayError`].
+pub type BoxError = Box<dyn std::error::Error + Send + Sync + 'static>;
+
 /// The main error type for all gateway operations.
 #[derive(Debug, Error)]
 pub enum GatewayError {
     // === Connection Errors ===
     /// Connection failed
-    #[error("Connection error: {0}")]
-    Connection(String),
+    #[error("Connection error: {message}")]
+    Connection {
+        /// What failed.
+        message: String,
+        /// Underlying error, if any.
+        #[source]
+        source: Option<BoxError>,
+    },
 
     /// Not connected to the target
     #[error("Not connected")]
@@ -71,20 +105,68 @@ pub enum GatewayError {
 
     // === Protocol-Specific Errors ===
     /// Modbus protocol error
-    #[error("Modbus error: {0}")]
-    Modbus(String),
+    #[error("Modbus error: {message}")]
+    Modbus {
+        /// What failed.
+        message: String,
+        /// Underlying error, if any.
+        #[source]
+        source: Option<BoxError>,
+    },
 
     /// IEC 104 protocol error
-    #[error("IEC 104 error: {0}")]
-    Iec104(String),
+    #[error("IEC 104 error: {message}")]
+    Iec104 {
+        /// What failed.
+        message: String,
+        /// Underlying error, if any.
+        #[source]
+        source: Option<BoxError>,
+    },
 
     /// DNP3 protocol error
-    #[error("DNP3 error: {0}")]
-    Dnp3(String),
+    #[error("DNP3 error: {message}")]
+    Dnp3 {
+        /// What failed.
+        message: String,
+        /// Underlying error, if any.
+        #[source]
+        source: Option<BoxError>,
+    },
 
     /// OPC UA protocol error
-    #[error("OPC UA error: {0}")]
-    OpcUa(String),
+    #[error("OPC UA error: {message}")]
+    OpcUa {
+        /// What failed.
+        message: String,
+        /// Underlying error, if any.
+        #[source]
+        source: Option<BoxError>,
+    },
+
+    // === Command Errors ===
+    /// Command blocked by an interlock
+    #[error("Interlock violated: {0}")]
+    Interlock(String),
+
+    /// Command queue of a channel cannot take more commands
+    #[error("Command queue full: {0}")]
+    QueueFull(String),
+
+    /// Caller's permission level is too low for a command
+    #[error("Permission denied: {0}")]
+    PermissionDenied(String),
+
+    // === Storage Errors ===
+    /// Data store operation failed
+    #[error("Storage error: {message}")]
+    Storage {
+        /// What failed.
+        message: String,
+        /// Underlying error, if any.
+        #[source]
+        source: Option<BoxError>,
+    },
 
     // === Internal Errors ===
     /// Internal error (bug)
@@ -96,24 +178,137 @@ pub enum GatewayError {
     ChannelClosed,
 }
 
+/// Coarse classification of a [`GatewayError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCategory {
+    /// The link to the device is down or unusable: reconnect.
+    Connectivity,
+    /// The device answered, but not as expected: flag the points concerned.
+    Protocol,
+    /// A value or point is wrong or missing.
+    Data,
+    /// The configuration or the caller's request is wrong.
+    Config,
+    /// A fault of the gateway itself, e.g. its store.
+    Internal,
+}
+
+/// Code, category and message of an error, as reported by the HTTP API and
+/// the JSON Lines output.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorReport {
+    /// Stable numeric code, see [`GatewayError::code()`].
+    pub code: u16,
+    /// Coarse classification.
+    pub category: ErrorCategory,
+    /// Display message.
+    pub message: String,
+}
+
+impl From<&GatewayError> for ErrorReport {
+    fn from(error: &GatewayError) -> Self {
+        Self {
+            code: error.code(),
+            category: error.category(),
+            message: error.to_string(),
+        }
+    }
+}
+
 impl GatewayError {
+    /// Stable numeric code of the variant (see the [module docs](self)).
+    pub fn code(&self) -> u16 {
+        match self {
+            Self::Connection { .. } => 1001,
+            Self::NotConnected => 1002,
+            Self::ConnectionTimeout(_) => 1003,
+            Self::Protocol(_) => 2001,
+            Self::InvalidResponse(_) => 2002,
+            Self::Unsupported(_) => 2003,
+            Self::InvalidData(_) => 3001,
+            Self::DataConversion(_) => 3002,
+            Self::PointNotFound(_) => 3003,
+            Self::Config(_) => 4001,
+            Self::InvalidAddress(_) => 4002,
+            Self::Io(_) => 5001,
+            Self::ReadTimeout => 5002,
+            Self::WriteTimeout => 5003,
+            Self::Modbus { .. } => 6001,
+            Self::Iec104 { .. } => 6002,
+            Self::Dnp3 { .. } => 6003,
+            Self::OpcUa { .. } => 6004,
+            Self::Interlock(_) => 7001,
+            Self::QueueFull(_) => 7002,
+            Self::PermissionDenied(_) => 7003,
+            Self::Storage { .. } => 8001,
+            Self::Internal(_) => 9001,
+            Self::ChannelClosed => 9002,
+        }
+    }
+
+    /// Coarse classification.
+    ///
+    /// The runtime reconnects a channel on [`ErrorCategory::Connectivity`]
+    /// errors; the others concern single points or requests and are
+    /// reflected in point quality or returned to the caller.
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            Self::Connection { .. }
+            | Self::NotConnected
+            | Self::ConnectionTimeout(_)
+            | Self::Io(_)
+            | Self::ChannelClosed => ErrorCategory::Connectivity,
+            Self::Protocol(_)
+            | Self::InvalidResponse(_)
+            | Self::Unsupported(_)
+            | Self::ReadTimeout
+            | Self::WriteTimeout
+            | Self::Modbus { .. }
+            | Self::Iec104 { .. }
+            | Self::Dnp3 { .. }
+            | Self::OpcUa { .. } => ErrorCategory::Protocol,
+            Self::InvalidData(_)
+            | Self::DataConversion(_)
+            | Self::PointNotFound(_)
+            | Self::Interlock(_) => ErrorCategory::Data,
+            Self::Config(_) | Self::InvalidAddress(_) | Self::PermissionDenied(_) => {
+                ErrorCategory::Config
+            }
+            Self::QueueFull(_) | Self::Storage { .. } | Self::Internal(_) => {
+                ErrorCategory::Internal
+            }
+        }
+    }
+
+    /// Code, category and message.
+    pub fn report(&self) -> ErrorReport {
+        self.into()
+    }
+
+    /// Kind of the first I/O error in this error's source chain, e.g.
+    /// `ConnectionRefused`.
+    pub fn io_kind(&self) -> Option<std::io::ErrorKind> {
+        let mut current: Option<&(dyn std::error::Error + 'static)> = Some(self);
+        while let Some(error) = current {
+            if let Some(io) = error.downcast_ref::<std::io::Error>() {
+                return Some(io.kind());
+            }
+            current = error.source();
+        }
+        None
+    }
+
     /// Check if this error indicates that reconnection is needed.
     pub fn needs_reconnect(&self) -> bool {
-        matches!(
-            self,
-            Self::Connection(_)
-                | Self::NotConnected
-                | Self::ConnectionTimeout(_)
-                | Self::Io(_)
-                | Self::ChannelClosed
-        )
+        self.category() == ErrorCategory::Connectivity
     }
 
     /// Check if this error is retryable.
     pub fn is_retryable(&self) -> bool {
         matches!(
             self,
-            Self::ReadTimeout | Self::WriteTimeout | Self::Connection(_)
+            Self::ReadTimeout | Self::WriteTimeout | Self::Connection { .. }
         )
     }
 
@@ -124,7 +319,18 @@ impl GatewayError {
 
     /// Create a connection error.
     pub fn connection(msg: impl Into<String>) -> Self {
-        Self::Connection(msg.into())
+        Self::Connection {
+            message: msg.into(),
+            source: None,
+        }
+    }
+
+    /// Create a connection error caused by `source`.
+    pub fn connection_with(msg: impl Into<String>, source: impl Into<BoxError>) -> Self {
+        Self::Connection {
+            message: msg.into(),
+            source: Some(source.into()),
+        }
     }
 
     /// Create an IO error from a message.
@@ -144,7 +350,50 @@ impl GatewayError {
 
     /// Create a Modbus error.
     pub fn modbus(msg: impl Into<String>) -> Self {
-        Self::Modbus(msg.into())
+        Self::Modbus {
+            message: msg.into(),
+            source: None,
+        }
+    }
+
+    /// Create a Modbus error caused by `source`.
+    pub fn modbus_with(msg: impl Into<String>, source: impl Into<BoxError>) -> Self {
+        Self::Modbus {
+            message: msg.into(),
+            source: Some(source.into()),
+        }
+    }
+
+    /// Create an IEC 104 error.
+    pub fn iec104(msg: impl Into<String>) -> Self {
+        Self::Iec104 {
+            message: msg.into(),
+            source: None,
+        }
+    }
+
+    /// Create an OPC UA error.
+    pub fn opcua(msg: impl Into<String>) -> Self {
+        Self::OpcUa {
+            message: msg.into(),
+            source: None,
+        }
+    }
+
+    /// Create a storage error.
+    pub fn storage(msg: impl Into<String>) -> Self {
+        Self::Storage {
+            message: msg.into(),
+            source: None,
+        }
+    }
+
+    /// Create a storage error caused by `source`.
+    pub fn storage_with(msg: impl Into<String>, source: impl Into<BoxError>) -> Self {
+        Self::Storage {
+            message: msg.into(),
+            source: Some(source.into()),
+        }
     }
 
     /// Create an internal error.
@@ -164,6 +413,52 @@ mod tests {
         assert!(!GatewayError::protocol("test").needs_reconnect());
     }
 
+    #[test]
+    fn test_codes_and_categories() {
+        assert_eq!(GatewayError::NotConnected.code(), 1002);
+        assert_eq!(GatewayError::storage("x").code(), 8001);
+        assert_eq!(
+            GatewayError::Config("x".into()).category(),
+            ErrorCategory::Config
+        );
+        assert_eq!(
+            GatewayError::ReadTimeout.category(),
+            ErrorCategory::Protocol
+        );
+        assert!(GatewayError::io("x").needs_reconnect());
+
+        let report = GatewayError::Interlock("pump 2 running".into()).report();
+        assert_eq!(
+            serde_json::to_value(&report).unwrap(),
+            serde_json::json!({
+                "code": 7001,
+                "category": "data",
+                "message": "Interlock violated: pump 2 running",
+            })
+        );
+    }
+
+    #[test]
+    fn test_source_preserved() {
+        use std::error::Error as _;
+        use std::io;
+
+        let refused = io::Error::from(io::ErrorKind::ConnectionRefused);
+        let error = GatewayError::connection_with("10.0.0.5:502: connection refused", refused);
+        // Display unchanged by the source
+        assert_eq!(
+            error.to_string(),
+            "Connection error: 10.0.0.5:502: connection refused"
+        );
+        assert_eq!(error.io_kind(), Some(io::ErrorKind::ConnectionRefused));
+        assert!(error.source().is_some());
+
+        let nested = GatewayError::storage_with("archive", GatewayError::io("disk gone"));
+        assert_eq!(nested.io_kind(), Some(io::ErrorKind::Other));
+        assert_eq!(GatewayError::connection("x").io_kind(), None);
+        assert!(GatewayError::modbus("x").source().is_none());
+    }
+
     #[test]
     fn test_is_retryable() {
         assert!(GatewayError::ReadTimeout.is_retryable());
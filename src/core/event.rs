@@ -0,0 +1,588 @@
+//! Event bus for event-driven protocols.
+//!
+//! [`EventBus`] is what every event-driven channel uses to fan out
+//! [`DataEvent`]s. Each call to [`EventBus::subscribe()`] returns an
+//! independent [`DataEventReceiver`]; every receiver sees every event
+//! published after it subscribed.
+//!
+//! # Overflow
+//!
+//! Publishing never blocks. Every receiver has its own bounded buffer
+//! (`capacity` events); a receiver that falls behind, e.g. a store writer
+//! during a store outage, loses events according to its
+//! [`OverflowPolicy`]. Fast receivers are never affected by a slow one.
+//!
+//! - [`DropOldest`](OverflowPolicy::DropOldest) (the default): the oldest
+//!   buffered event makes room for the new one.
+//! - [`DropNewest`](OverflowPolicy::DropNewest): the new event is dropped.
+//! - [`CoalesceLatestPerPoint`](OverflowPolicy::CoalesceLatestPerPoint):
+//!   buffered data updates are merged into one, keeping only the newest
+//!   value of each point, so no point loses its latest value. The merged
+//!   update is delivered after the other events still buffered. Other
+//!   events are only dropped (oldest first) if the buffer is full of them.
+//!
+//! When a receiver first loses events, its next `recv()` yields a
+//! `DataEvent::Error` saying how many were dropped or coalesced so far; once
+//! it has caught up (its buffer is empty) it gets another `DataEvent::Error`
+//! with the totals of the episode. The counts since subscribing are
+//! available from [`DataEventReceiver::counters()`].
+//!
+//! Consumers that must not miss updates should either use
+//! `CoalesceLatestPerPoint` or follow a lag error with a `poll_once()` to
+//! resynchronize.
+
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+
+use crate::core::data::{DataBatch, PointId};
+use crate::core::traits::DataEvent;
+
+/// What a receiver's full buffer does with new events.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    /// Drop the oldest buffered event.
+    #[default]
+    DropOldest,
+    /// Drop the new event.
+    DropNewest,
+    /// Merge buffered data updates, keeping the newest value per point.
+    CoalesceLatestPerPoint,
+}
+
+/// Events a receiver lost to its overflow policy.
+#[derive(Debug, Default)]
+pub struct OverflowCounters {
+    dropped: AtomicU64,
+    coalesced: AtomicU64,
+}
+
+impl OverflowCounters {
+    /// Events dropped.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Data updates merged into another one.
+    pub fn coalesced(&self) -> u64 {
+        self.coalesced.load(Ordering::Relaxed)
+    }
+}
+
+/// Broadcast bus for [`DataEvent`]s.
+///
+/// Cloning the bus is cheap and yields a handle to the same channel, so it can
+/// be moved into protocol background tasks. Receivers see the end of the
+/// stream once every handle is dropped.
+#[derive(Debug, Clone)]
+pub struct EventBus {
+    sender: Arc<Sender>,
+}
+
+impl EventBus {
+    /// Default per-receiver buffer capacity.
+    pub const DEFAULT_CAPACITY: usize = 1024;
+
+    /// Create a bus that buffers up to `capacity` events per receiver,
+    /// dropping the oldest on overflow.
+    ///
+    /// A capacity of 0 is raised to 1.
+    pub fn new(capacity: usize) -> Self {
+        Self::with_policy(capacity, OverflowPolicy::default())
+    }
+
+    /// Create a bus whose receivers use `policy` on overflow.
+    ///
+    /// A capacity of 0 is raised to 1.
+    pub fn with_policy(capacity: usize, policy: OverflowPolicy) -> Self {
+        Self {
+            sender: Arc::new(Sender {
+                shared: Arc::new(Shared {
+                    capacity: capacity.max(1),
+                    policy,
+                    closed: AtomicBool::new(false),
+                    queues: Mutex::new(Vec::new()),
+                }),
+            }),
+        }
+    }
+
+    /// Publish an event to all current subscribers.
+    ///
+    /// Returns the number of receivers the event was delivered to. Having no
+    /// subscribers is not an error; the event is simply dropped.
+    pub fn publish(&self, event: DataEvent) -> usize {
+        let mut queues = lock(&self.sender.shared.queues);
+        queues.retain(|queue| queue.strong_count() > 0);
+        let mut delivered = 0;
+        for queue in queues.iter().filter_map(Weak::upgrade) {
+            queue.push(event.clone());
+            delivered += 1;
+        }
+        delivered
+    }
+
+    /// Create a new independent receiver.
+    pub fn subscribe(&self) -> DataEventReceiver {
+        DataEventReceiver::new(&self.sender.shared)
+    }
+
+    /// Number of active receivers.
+    pub fn subscriber_count(&self) -> usize {
+        lock(&self.sender.shared.queues)
+            .iter()
+            .filter(|queue| queue.strong_count() > 0)
+            .count()
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_CAPACITY)
+    }
+}
+
+/// Receiving half of an [`EventBus`] subscription.
+#[derive(Debug)]
+pub struct DataEventReceiver {
+    queue: Arc<Queue>,
+    shared: Arc<Shared>,
+}
+
+impl DataEventReceiver {
+    fn new(shared: &Arc<Shared>) -> Self {
+        // The sender closes the queues under this lock, so a receiver
+        // created while the last sender drops is either closed here or
+        // found and closed by the sender
+        let mut queues = lock(&shared.queues);
+        let queue = Arc::new(Queue {
+            state: Mutex::new(QueueState {
+                events: VecDeque::new(),
+                capacity: shared.capacity,
+                policy: shared.policy,
+                merged_tail: false,
+                episode: None,
+                closed: shared.closed.load(Ordering::Acquire),
+            }),
+            notify: Notify::new(),
+            counters: Arc::default(),
+        });
+        queues.push(Arc::downgrade(&queue));
+        drop(queues);
+        Self {
+            queue,
+            shared: Arc::clone(shared),
+        }
+    }
+
+    /// Receive the next event.
+    ///
+    /// Returns `None` once the bus has been dropped and every buffered event
+    /// was received. Lost events are reported as `DataEvent::Error` (see the
+    /// [module docs](self)).
+    pub async fn recv(&mut self) -> Option<DataEvent> {
+        loop {
+            match self.queue.next() {
+                Next::Event(event) => return Some(event),
+                Next::Closed => return None,
+                Next::Empty => self.queue.notify.notified().await,
+            }
+        }
+    }
+
+    /// Receive an event without waiting.
+    ///
+    /// Returns `None` if no event is pending or the bus has been dropped.
+    pub fn try_recv(&mut self) -> Option<DataEvent> {
+        match self.queue.next() {
+            Next::Event(event) => Some(event),
+            Next::Empty | Next::Closed => None,
+        }
+    }
+
+    /// Change this receiver's buffer capacity and overflow policy.
+    ///
+    /// A capacity of 0 is raised to 1. Events already buffered are kept.
+    pub fn set_buffer(&self, capacity: usize, policy: OverflowPolicy) {
+        let mut state = lock(&self.queue.state);
+        state.capacity = capacity.max(1);
+        state.policy = policy;
+    }
+
+    /// Events this receiver lost since it subscribed.
+    ///
+    /// The counters stay readable (and keep counting) while the receiver
+    /// is busy, e.g. from a diagnostics task.
+    pub fn counters(&self) -> Arc<OverflowCounters> {
+        Arc::clone(&self.queue.counters)
+    }
+
+    /// Create another receiver on the same bus, starting from now.
+    ///
+    /// The new receiver uses the bus's buffer settings.
+    pub fn resubscribe(&self) -> Self {
+        Self::new(&self.shared)
+    }
+}
+
+/// State shared by the bus handles and their receivers.
+#[derive(Debug)]
+struct Shared {
+    capacity: usize,
+    policy: OverflowPolicy,
+    closed: AtomicBool,
+    queues: Mutex<Vec<Weak<Queue>>>,
+}
+
+/// Closes the receivers when the last bus handle is dropped.
+#[derive(Debug)]
+struct Sender {
+    shared: Arc<Shared>,
+}
+
+impl Drop for Sender {
+    fn drop(&mut self) {
+        let queues = lock(&self.shared.queues);
+        self.shared.closed.store(true, Ordering::Release);
+        for queue in queues.iter().filter_map(Weak::upgrade) {
+            lock(&queue.state).closed = true;
+            queue.notify.notify_one();
+        }
+    }
+}
+
+/// Buffer of one receiver.
+#[derive(Debug)]
+struct Queue {
+    state: Mutex<QueueState>,
+    notify: Notify,
+    counters: Arc<OverflowCounters>,
+}
+
+#[derive(Debug)]
+struct QueueState {
+    events: VecDeque<DataEvent>,
+    capacity: usize,
+    policy: OverflowPolicy,
+    /// The last buffered event is a merge of coalesced updates.
+    merged_tail: bool,
+    /// Losses since the buffer last overflowed, until it is drained.
+    episode: Option<Episode>,
+    closed: bool,
+}
+
+#[derive(Debug, Default)]
+struct Episode {
+    dropped: u64,
+    coalesced: u64,
+    /// The start was reported to the receiver.
+    reported: bool,
+}
+
+enum Next {
+    Event(DataEvent),
+    Empty,
+    Closed,
+}
+
+impl Queue {
+    fn push(&self, event: DataEvent) {
+        let mut state = lock(&self.state);
+        let (dropped, coalesced) = state.push(event);
+        if dropped + coalesced > 0 {
+            self.counters.dropped.fetch_add(dropped, Ordering::Relaxed);
+            self.counters
+                .coalesced
+                .fetch_add(coalesced, Ordering::Relaxed);
+            #[cfg(feature = "tracing-support")]
+            if state.episode.is_none() {
+                tracing::warn!(policy = ?state.policy, "Event receiver overflowing");
+            }
+            let episode = state.episode.get_or_insert_with(Episode::default);
+            episode.dropped += dropped;
+            episode.coalesced += coalesced;
+        }
+        drop(state);
+        self.notify.notify_one();
+    }
+
+    fn next(&self) -> Next {
+        let mut state = lock(&self.state);
+        if let Some(episode) = state.episode.as_mut().filter(|e| !e.reported) {
+            episode.reported = true;
+            return Next::Event(DataEvent::Error(format!(
+                "event receiver lagged: {}",
+                losses(episode)
+            )));
+        }
+        if let Some(event) = state.events.pop_front() {
+            if state.events.is_empty() {
+                state.merged_tail = false;
+            }
+            return Next::Event(event);
+        }
+        if let Some(episode) = state.episode.take() {
+            return Next::Event(DataEvent::Error(format!(
+                "event receiver caught up: {}",
+                losses(&episode)
+            )));
+        }
+        if state.closed {
+            Next::Closed
+        } else {
+            Next::Empty
+        }
+    }
+}
+
+impl QueueState {
+    /// Buffer an event; returns how many events were `(dropped, coalesced)`.
+    fn push(&mut self, event: DataEvent) -> (u64, u64) {
+        if self.events.len() < self.capacity {
+            self.push_back(event);
+            return (0, 0);
+        }
+        match self.policy {
+            OverflowPolicy::DropOldest => {
+                self.pop_oldest();
+                self.push_back(event);
+                (1, 0)
+            }
+            OverflowPolicy::DropNewest => (1, 0),
+            OverflowPolicy::CoalesceLatestPerPoint => self.coalesce(event),
+        }
+    }
+
+    fn coalesce(&mut self, event: DataEvent) -> (u64, u64) {
+        let mut coalesced = 0;
+        if let DataEvent::DataUpdate(batch) = event {
+            if self.merged_tail {
+                if let Some(DataEvent::DataUpdate(tail)) = self.events.back_mut() {
+                    *tail = Arc::new(merge([&**tail, &*batch]));
+                    return (0, 1);
+                }
+            }
+            coalesced = self.merge_updates(Some(batch));
+        } else {
+            if !self.merged_tail {
+                coalesced = self.merge_updates(None);
+            }
+            self.push_back(event);
+        }
+
+        let mut dropped = 0;
+        while self.events.len() > self.capacity {
+            self.pop_oldest();
+            dropped += 1;
+        }
+        (dropped, coalesced)
+    }
+
+    /// Replace the buffered data updates (and `extra`) by one merged update
+    /// at the back; returns how many updates were merged away.
+    fn merge_updates(&mut self, extra: Option<Arc<DataBatch>>) -> u64 {
+        let mut batches = Vec::new();
+        self.events.retain(|event| match event {
+            DataEvent::DataUpdate(batch) => {
+                batches.push(Arc::clone(batch));
+                false
+            }
+            _ => true,
+        });
+        batches.extend(extra);
+        let Some(merged) = batches.len().checked_sub(1) else {
+            return 0;
+        };
+        self.events.push_back(DataEvent::DataUpdate(Arc::new(merge(
+            batches.iter().map(|b| &**b),
+        ))));
+        self.merged_tail = true;
+        merged as u64
+    }
+
+    fn push_back(&mut self, event: DataEvent) {
+        self.events.push_back(event);
+        self.merged_tail = false;
+    }
+
+    fn pop_oldest(&mut self) {
+        self.events.pop_front();
+        if self.events.is_empty() {
+            self.merged_tail = false;
+        }
+    }
+}
+
+/// Points of `batches`, keeping the last value of each point.
+fn merge<'a>(batches: impl IntoIterator<Item = &'a DataBatch>) -> DataBatch {
+    let mut index: HashMap<PointId, usize> = HashMap::new();
+    let mut points = Vec::new();
+    for point in batches.into_iter().flat_map(DataBatch::iter) {
+        match index.entry(point.id) {
+            Entry::Occupied(entry) => points[*entry.get()] = point.clone(),
+            Entry::Vacant(entry) => {
+                entry.insert(points.len());
+                points.push(point.clone());
+            }
+        }
+    }
+    DataBatch::from_points(points)
+}
+
+fn losses(episode: &Episode) -> String {
+    match (episode.dropped, episode.coalesced) {
+        (dropped, 0) => format!("{} event(s) dropped", dropped),
+        (0, coalesced) => format!("{} update(s) coalesced", coalesced),
+        (dropped, coalesced) => format!(
+            "{} event(s) dropped, {} update(s) coalesced",
+            dropped, coalesced
+        ),
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::data::DataPoint;
+    use crate::core::traits::ConnectionState;
+
+    fn update(points: &[(PointId, f64)]) -> DataEvent {
+        DataEvent::DataUpdate(Arc::new(
+            points
+                .iter()
+                .map(|&(id, value)| DataPoint::new(id, value))
+                .collect(),
+        ))
+    }
+
+    fn values(event: Option<DataEvent>) -> Vec<(PointId, f64)> {
+        match event {
+            Some(DataEvent::DataUpdate(batch)) => batch
+                .iter()
+                .map(|p| (p.id, p.value.as_f64().unwrap()))
+                .collect(),
+            other => panic!("expected data update, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_multiple_subscribers() {
+        let bus = EventBus::new(8);
+        let mut rx1 = bus.subscribe();
+        let mut rx2 = bus.subscribe();
+        assert_eq!(bus.subscriber_count(), 2);
+
+        assert_eq!(bus.publish(DataEvent::Heartbeat), 2);
+        assert!(matches!(rx1.recv().await, Some(DataEvent::Heartbeat)));
+        assert!(matches!(rx2.recv().await, Some(DataEvent::Heartbeat)));
+
+        drop(rx2);
+        assert_eq!(bus.subscriber_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_publish_without_subscribers() {
+        let bus = EventBus::default();
+        assert_eq!(bus.publish(DataEvent::Heartbeat), 0);
+    }
+
+    #[tokio::test]
+    async fn test_lag_reported_as_error() {
+        let bus = EventBus::new(2);
+        let mut rx = bus.subscribe();
+
+        for _ in 0..5 {
+            bus.publish(DataEvent::Heartbeat);
+        }
+        bus.publish(DataEvent::ConnectionChanged(ConnectionState::Connected));
+
+        match rx.recv().await {
+            Some(DataEvent::Error(msg)) => assert!(msg.contains("4 event(s) dropped")),
+            other => panic!("expected lag error, got {:?}", other),
+        }
+        assert!(matches!(rx.recv().await, Some(DataEvent::Heartbeat)));
+        assert!(matches!(
+            rx.recv().await,
+            Some(DataEvent::ConnectionChanged(ConnectionState::Connected))
+        ));
+        match rx.recv().await {
+            Some(DataEvent::Error(msg)) => assert!(msg.contains("caught up")),
+            other => panic!("expected recovery error, got {:?}", other),
+        }
+        assert!(rx.try_recv().is_none());
+        assert_eq!(rx.counters().dropped(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_drop_newest() {
+        let bus = EventBus::with_policy(2, OverflowPolicy::DropNewest);
+        let mut rx = bus.subscribe();
+        for value in 1..=4 {
+            bus.publish(update(&[(1, f64::from(value))]));
+        }
+
+        assert!(matches!(rx.recv().await, Some(DataEvent::Error(_))));
+        assert_eq!(values(rx.recv().await), vec![(1, 1.0)]);
+        assert_eq!(values(rx.recv().await), vec![(1, 2.0)]);
+        assert_eq!(rx.counters().dropped(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_coalesce_keeps_latest_per_point() {
+        let bus = EventBus::new(2);
+        let mut rx = bus.subscribe();
+        rx.set_buffer(2, OverflowPolicy::CoalesceLatestPerPoint);
+
+        bus.publish(update(&[(1, 1.0), (2, 1.0)]));
+        bus.publish(DataEvent::ConnectionChanged(ConnectionState::Connected));
+        bus.publish(update(&[(1, 2.0)]));
+        bus.publish(update(&[(3, 3.0), (1, 4.0)]));
+
+        match rx.recv().await {
+            Some(DataEvent::Error(msg)) => assert!(msg.contains("2 update(s) coalesced")),
+            other => panic!("expected lag error, got {:?}", other),
+        }
+        assert!(matches!(
+            rx.recv().await,
+            Some(DataEvent::ConnectionChanged(ConnectionState::Connected))
+        ));
+        assert_eq!(values(rx.recv().await), vec![(1, 4.0), (2, 1.0), (3, 3.0)]);
+        assert!(matches!(rx.recv().await, Some(DataEvent::Error(_))));
+
+        let counters = rx.counters();
+        assert_eq!((counters.dropped(), counters.coalesced()), (0, 2));
+    }
+
+    #[tokio::test]
+    async fn test_closed_bus() {
+        let bus = EventBus::new(4);
+        let mut rx = bus.subscribe();
+        bus.publish(DataEvent::Heartbeat);
+        drop(bus);
+        assert!(matches!(rx.recv().await, Some(DataEvent::Heartbeat)));
+        assert!(rx.recv().await.is_none());
+        assert!(rx.resubscribe().try_recv().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_resubscribe_while_bus_drops() {
+        for _ in 0..200 {
+            let bus = EventBus::new(4);
+            let rx = bus.subscribe();
+            let subscriber = std::thread::spawn(move || rx.resubscribe());
+            drop(bus);
+            let mut late = subscriber.join().unwrap();
+            let closed = tokio::time::timeout(std::time::Duration::from_secs(1), late.recv()).await;
+            assert!(matches!(closed, Ok(None)));
+        }
+    }
+}
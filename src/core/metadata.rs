@@ -234,6 +234,21 @@ fn build_registry() -> ProtocolRegistry {
         });
     }
 
+    // Register MQTT protocol
+    #[cfg(feature = "mqtt")]
+    {
+        use crate::protocols::mqtt::MqttChannel;
+        let mqtt_meta = MqttChannel::metadata();
+        registry.register(ProtocolMetadata {
+            name: "mqtt",
+            display_name: "MQTT",
+            description: "MQTT subscriber for values published to a broker",
+            protocol_type: "mqtt",
+            drivers: vec![mqtt_meta],
+            supports_points: true,
+        });
+    }
+
     // Register CAN protocol (Linux only)
     #[cfg(all(feature = "can", target_os = "linux"))]
     {
@@ -3,9 +3,15 @@
 //! This module defines protocol-agnostic point configuration,
 //! with protocol-specific address types for each supported protocol.
 
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
 
+use crate::core::alarm::AlarmConfig;
+use crate::core::data::{deserialize_point_id, DataBatch, DataPoint, PointId, Value, ValueKind};
 use crate::core::error::GatewayError;
+use crate::core::quality::Quality;
+use crate::core::units::UnitConversion;
 
 /// Protocol-agnostic point configuration.
 ///
@@ -15,12 +21,21 @@ use crate::core::error::GatewayError;
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PointConfig {
     /// Unique point identifier (numeric).
-    pub id: u32,
+    #[serde(deserialize_with = "deserialize_point_id")]
+    pub id: PointId,
 
     /// Human-readable name (optional).
     #[serde(skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
 
+    /// Display name for operator-facing output (optional).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+
+    /// Engineering unit of the transformed value, e.g. `"kPa"` (optional).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+
     /// Protocol-specific address.
     pub address: ProtocolAddress,
 
@@ -32,6 +47,30 @@ pub struct PointConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub poll_group: Option<String>,
 
+    /// Maximum time the value may stay unchanged before the gateway marks
+    /// it `Quality::Uncertain` (optional).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age_ms: Option<u64>,
+
+    /// Kind of value a strict virtual channel accepts for this point
+    /// (optional; integers are accepted for `float`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data_type: Option<ValueKind>,
+
+    /// Time after the last write before a virtual channel marks the value
+    /// `Quality::LastKnown` (optional).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl_ms: Option<u64>,
+
+    /// Time after the last write before a virtual channel marks the value
+    /// `Quality::OutOfService`; later than `ttl_ms` (optional).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub out_of_service_ms: Option<u64>,
+
+    /// Limit alarms reported as signal points (optional).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alarms: Option<AlarmConfig>,
+
     /// Whether this point is enabled.
     #[serde(default = "default_true")]
     pub enabled: bool,
@@ -43,13 +82,20 @@ fn default_true() -> bool {
 
 impl PointConfig {
     /// Create a new point configuration.
-    pub fn new(id: u32, address: ProtocolAddress) -> Self {
+    pub fn new(id: PointId, address: ProtocolAddress) -> Self {
         Self {
             id,
             name: None,
+            display_name: None,
+            unit: None,
             address,
             transform: TransformConfig::default(),
             poll_group: None,
+            max_age_ms: None,
+            data_type: None,
+            ttl_ms: None,
+            out_of_service_ms: None,
+            alarms: None,
             enabled: true,
         }
     }
@@ -61,6 +107,29 @@ impl PointConfig {
         self
     }
 
+    /// Set the display name.
+    #[must_use]
+    pub fn with_display_name(mut self, display_name: impl Into<String>) -> Self {
+        self.display_name = Some(display_name.into());
+        self
+    }
+
+    /// Set the engineering unit.
+    #[must_use]
+    pub fn with_unit(mut self, unit: impl Into<String>) -> Self {
+        self.unit = Some(unit.into());
+        self
+    }
+
+    /// Descriptive metadata for this point.
+    pub fn meta(&self) -> PointMeta {
+        PointMeta {
+            name: self.name.clone(),
+            display_name: self.display_name.clone(),
+            unit: self.unit.clone(),
+        }
+    }
+
     /// Set the transform configuration.
     #[must_use]
     pub fn with_transform(mut self, transform: TransformConfig) -> Self {
@@ -68,12 +137,176 @@ impl PointConfig {
         self
     }
 
+    /// Convert values from the point's unit to `unit`, which becomes the
+    /// point's unit.
+    pub fn with_unit_conversion(mut self, unit: impl Into<String>) -> Self {
+        let unit = unit.into();
+        self.transform.unit_conversion = Some(UnitConversion {
+            from: self.unit.replace(unit.clone()),
+            to: unit,
+        });
+        self
+    }
+
     /// Set the poll group.
     #[must_use]
     pub fn with_poll_group(mut self, group: impl Into<String>) -> Self {
         self.poll_group = Some(group.into());
         self
     }
+
+    /// Set the maximum age of an unchanged value.
+    #[must_use]
+    pub fn with_max_age(mut self, max_age: std::time::Duration) -> Self {
+        self.max_age_ms = Some(max_age.as_millis() as u64);
+        self
+    }
+
+    /// Set the kind of value a strict virtual channel accepts.
+    #[must_use]
+    pub fn with_data_type(mut self, data_type: ValueKind) -> Self {
+        self.data_type = Some(data_type);
+        self
+    }
+
+    /// Set the time after the last write before a virtual channel marks
+    /// the value `LastKnown`.
+    #[must_use]
+    pub fn with_ttl(mut self, ttl: std::time::Duration) -> Self {
+        self.ttl_ms = Some(ttl.as_millis() as u64);
+        self
+    }
+
+    /// Set the time after the last write before a virtual channel marks
+    /// the value `OutOfService`.
+    #[must_use]
+    pub fn with_out_of_service_after(mut self, after: std::time::Duration) -> Self {
+        self.out_of_service_ms = Some(after.as_millis() as u64);
+        self
+    }
+
+    /// Maximum age of an unchanged value, if configured.
+    pub fn max_age(&self) -> Option<std::time::Duration> {
+        self.max_age_ms.map(std::time::Duration::from_millis)
+    }
+
+    /// Set the limit alarms.
+    #[must_use]
+    pub fn with_alarms(mut self, alarms: AlarmConfig) -> Self {
+        self.alarms = Some(alarms);
+        self
+    }
+}
+
+/// Descriptive point metadata (name and engineering unit).
+///
+/// Kept separate from [`DataPoint`] so values stay small on the hot path;
+/// consumers that need units join it back in by point id, e.g. with
+/// [`DataBatch::with_meta()`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PointMeta {
+    /// Point name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// Display name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+
+    /// Engineering unit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+}
+
+impl PointMeta {
+    /// Check whether no metadata is set.
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none() && self.display_name.is_none() && self.unit.is_none()
+    }
+}
+
+/// Point metadata keyed by point id.
+pub type PointMetaMap = HashMap<PointId, PointMeta>;
+
+/// Build a metadata map from point configurations.
+///
+/// Points without any metadata are left out.
+pub fn point_meta_map<'a>(points: impl IntoIterator<Item = &'a PointConfig>) -> PointMetaMap {
+    points
+        .into_iter()
+        .map(|p| (p.id, p.meta()))
+        .filter(|(_, meta)| !meta.is_empty())
+        .collect()
+}
+
+/// Poll group of every point that has one, keyed by point id.
+pub fn poll_group_map<'a>(
+    points: impl IntoIterator<Item = &'a PointConfig>,
+) -> HashMap<PointId, &'a str> {
+    points
+        .into_iter()
+        .filter_map(|p| Some((p.id, p.poll_group.as_deref()?)))
+        .collect()
+}
+
+/// A [`DataPoint`] serialized together with its metadata.
+#[derive(Debug, Serialize)]
+pub struct AnnotatedPoint<'a> {
+    #[serde(flatten)]
+    point: &'a DataPoint,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    meta: Option<&'a PointMeta>,
+}
+
+impl<'a> AnnotatedPoint<'a> {
+    pub(crate) fn new(point: &'a DataPoint, meta: &'a PointMetaMap) -> Self {
+        Self {
+            point,
+            meta: meta.get(&point.id),
+        }
+    }
+}
+
+/// A [`DataBatch`] view that serializes each point with its metadata.
+///
+/// Produced by [`DataBatch::with_meta()`]. Serializes as
+/// `{"points": [{"id": .., "value": .., "unit": "kPa", ..}]}`, matching the
+/// plain batch layout plus the metadata fields.
+#[derive(Debug)]
+pub struct AnnotatedBatch<'a> {
+    batch: &'a DataBatch,
+    meta: &'a PointMetaMap,
+}
+
+impl<'a> AnnotatedBatch<'a> {
+    pub(crate) fn new(batch: &'a DataBatch, meta: &'a PointMetaMap) -> Self {
+        Self { batch, meta }
+    }
+
+    /// Iterate over the annotated points.
+    pub fn iter(&self) -> impl Iterator<Item = AnnotatedPoint<'a>> + '_ {
+        self.batch
+            .iter()
+            .map(|point| AnnotatedPoint::new(point, self.meta))
+    }
+}
+
+impl Serialize for AnnotatedBatch<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        struct Points<'b, 'a>(&'b AnnotatedBatch<'a>);
+
+        impl Serialize for Points<'_, '_> {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_seq(self.0.iter())
+            }
+        }
+
+        let mut state = serializer.serialize_struct("DataBatch", 1)?;
+        state.serialize_field("points", &Points(self))?;
+        state.end()
+    }
 }
 
 /// Protocol-specific address configuration.
@@ -89,6 +322,9 @@ pub enum ProtocolAddress {
     /// OPC UA address.
     OpcUa(OpcUaAddress),
 
+    /// MQTT topic address.
+    Mqtt(MqttAddress),
+
     /// DNP3 address.
     Dnp3(Dnp3Address),
 
@@ -103,6 +339,20 @@ pub enum ProtocolAddress {
     Generic(String),
 }
 
+impl ProtocolAddress {
+    /// Check protocol-specific constraints a parser cannot express.
+    ///
+    /// Returns `GatewayError::Config` describing the first problem.
+    pub fn validate(&self) -> Result<(), GatewayError> {
+        match self {
+            Self::Modbus(addr) => addr.validate(),
+            Self::Iec104(addr) => addr.validate(),
+            Self::Mqtt(addr) => addr.validate(),
+            _ => Ok(()),
+        }
+    }
+}
+
 /// Virtual channel address.
 ///
 /// Used for points that don't connect to a physical device.
@@ -215,8 +465,24 @@ pub struct ModbusAddress {
     pub byte_order: ByteOrder,
 
     /// Bit position for boolean values (0-15).
+    ///
+    /// Writing such a point sets the whole register to 0 or 1. A Modbus
+    /// channel with `preserve_register_bits` reads the register and writes
+    /// it back with the bit changed instead. The device does not see this
+    /// as one operation, so a change another master makes to the register
+    /// in between is lost.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub bit_position: Option<u8>,
+
+    /// Number of consecutive `format` values read as one `Value::Array`
+    /// (register areas only).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count: Option<u16>,
+
+    /// Write-only broadcast point: written to unit 0 without waiting for a
+    /// reply, and never read.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub broadcast: bool,
 }
 
 impl ModbusAddress {
@@ -229,6 +495,8 @@ impl ModbusAddress {
             format,
             byte_order: ByteOrder::default(),
             bit_position: None,
+            count: None,
+            broadcast: false,
         }
     }
 
@@ -241,6 +509,8 @@ impl ModbusAddress {
             format,
             byte_order: ByteOrder::default(),
             bit_position: None,
+            count: None,
+            broadcast: false,
         }
     }
 
@@ -253,6 +523,8 @@ impl ModbusAddress {
             format: DataFormat::Bool,
             byte_order: ByteOrder::default(),
             bit_position: None,
+            count: None,
+            broadcast: false,
         }
     }
 
@@ -265,15 +537,79 @@ impl ModbusAddress {
             format: DataFormat::Bool,
             byte_order: ByteOrder::default(),
             bit_position: None,
+            count: None,
+            broadcast: false,
         }
     }
 
-    /// Get the number of registers to read based on format.
+    /// Read `count` consecutive values as one array.
+    #[must_use]
+    pub fn with_count(mut self, count: u16) -> Self {
+        self.count = Some(count);
+        self
+    }
+
+    /// Make this a broadcast point: written to unit 0 of every device on the
+    /// bus at once.
+    #[must_use]
+    pub fn broadcast(mut self) -> Self {
+        self.slave_id = 0;
+        self.broadcast = true;
+        self
+    }
+
+    /// Get the number of registers to read based on format (and count).
     pub fn register_count(&self) -> u16 {
-        self.format.register_count()
+        self.format
+            .register_count()
+            .saturating_mul(self.count.unwrap_or(1))
+    }
+
+    /// Check the function code, bit position and register range.
+    pub fn validate(&self) -> Result<(), GatewayError> {
+        if !matches!(self.function_code, 1..=6 | 15 | 16) {
+            return Err(GatewayError::Config(format!(
+                "unsupported Modbus function code {}",
+                self.function_code
+            )));
+        }
+        if let Some(bit) = self.bit_position.filter(|b| *b > 15) {
+            return Err(GatewayError::Config(format!(
+                "bit position {} out of range 0-15",
+                bit
+            )));
+        }
+        if let Some(count) = self.count {
+            if !matches!(self.function_code, 3 | 4) {
+                return Err(GatewayError::Config(
+                    "arrays need a register area (function code 3 or 4)".into(),
+                ));
+            }
+            let registers = u32::from(count) * u32::from(self.format.register_count());
+            if count == 0 || registers > MAX_READ_REGISTERS {
+                return Err(GatewayError::Config(format!(
+                    "array of {} {:?} values must span 1-{} registers",
+                    count, self.format, MAX_READ_REGISTERS
+                )));
+            }
+        }
+
+        let last = u32::from(self.register) + u32::from(self.register_count()) - 1;
+        if last > 0xFFFF {
+            return Err(GatewayError::Config(format!(
+                "register {} with format {:?} ({} registers) runs past 0xFFFF",
+                self.register,
+                self.format,
+                self.register_count()
+            )));
+        }
+        Ok(())
     }
 }
 
+/// Most registers a single Modbus read request may return.
+const MAX_READ_REGISTERS: u32 = 125;
+
 /// IEC 60870-5-104 address.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Iec104Address {
@@ -296,6 +632,17 @@ impl Iec104Address {
             common_address,
         }
     }
+
+    /// Check the IOA fits its 3-byte field.
+    pub fn validate(&self) -> Result<(), GatewayError> {
+        if self.ioa > 0xFF_FFFF {
+            return Err(GatewayError::Config(format!(
+                "IOA {} exceeds the 3-byte maximum 16777215",
+                self.ioa
+            )));
+        }
+        Ok(())
+    }
 }
 
 /// OPC UA address.
@@ -307,6 +654,11 @@ pub struct OpcUaAddress {
     /// Namespace index.
     #[serde(default)]
     pub namespace_index: u16,
+
+    /// OPC UA index range of an array node to read, e.g. `"0:23"`
+    /// (optional; the whole array otherwise).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index_range: Option<String>,
 }
 
 impl OpcUaAddress {
@@ -315,7 +667,76 @@ impl OpcUaAddress {
         Self {
             node_id: node_id.into(),
             namespace_index,
+            index_range: None,
+        }
+    }
+
+    /// Read only `range` of an array node.
+    #[must_use]
+    pub fn with_index_range(mut self, range: impl Into<String>) -> Self {
+        self.index_range = Some(range.into());
+        self
+    }
+}
+
+/// MQTT address: where a point's value is published.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MqttAddress {
+    /// Topic the value is published on.
+    pub topic: String,
+
+    /// Location of the value in a JSON payload: a JSON pointer
+    /// (`/sensors/0/value`) or a dotted path (`sensors.0.value`). Without
+    /// it the whole payload is the value.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub json_path: Option<String>,
+
+    /// Topic commands for the point are published on (optional; the point
+    /// cannot be commanded without one).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command_topic: Option<String>,
+}
+
+impl MqttAddress {
+    /// Create an address for the whole payload of `topic`.
+    pub fn new(topic: impl Into<String>) -> Self {
+        Self {
+            topic: topic.into(),
+            json_path: None,
+            command_topic: None,
+        }
+    }
+
+    /// Read the value at `path` of a JSON payload.
+    #[must_use]
+    pub fn with_json_path(mut self, path: impl Into<String>) -> Self {
+        self.json_path = Some(path.into());
+        self
+    }
+
+    /// Publish commands for the point on `topic`.
+    #[must_use]
+    pub fn with_command_topic(mut self, topic: impl Into<String>) -> Self {
+        self.command_topic = Some(topic.into());
+        self
+    }
+
+    /// Check the topics are non-empty and free of wildcards.
+    pub fn validate(&self) -> Result<(), GatewayError> {
+        let topics = std::iter::once(("topic", &self.topic))
+            .chain(self.command_topic.iter().map(|t| ("command topic", t)));
+        for (what, topic) in topics {
+            if topic.is_empty() {
+                return Err(GatewayError::Config(format!("MQTT {} is empty", what)));
+            }
+            if topic.contains(['+', '#']) {
+                return Err(GatewayError::Config(format!(
+                    "MQTT {} '{}' must not contain wildcards",
+                    what, topic
+                )));
+            }
         }
+        Ok(())
     }
 }
 
@@ -350,6 +771,11 @@ pub enum Dnp3PointType {
 /// Supports multiple serde aliases for flexibility in JSON configs:
 /// - `uint16` / `u16`, `int16` / `i16`, etc.
 /// - `float32` / `f32` / `float`, `float64` / `f64` / `double`
+/// - `bcd16`, `bcd32` for binary-coded decimal counters
+/// - `float16` / `f16` / `half` for IEEE 754 half-precision floats
+///
+/// Scaled integers ("value ×10 as Int16") use `int16` with
+/// `TransformConfig::scale` set to `0.1`.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum DataFormat {
@@ -381,16 +807,23 @@ pub enum DataFormat {
     /// 64-bit floating point.
     #[serde(alias = "f64", alias = "double")]
     Float64,
+    /// 16-bit (half-precision) floating point.
+    #[serde(alias = "f16", alias = "half")]
+    Float16,
     /// String (fixed length).
     String,
+    /// 4-digit BCD in one register (0-9999).
+    Bcd16,
+    /// 8-digit BCD across two registers (0-99999999).
+    Bcd32,
 }
 
 impl DataFormat {
     /// Get the number of 16-bit registers needed for this format.
     pub fn register_count(&self) -> u16 {
         match self {
-            Self::Bool | Self::UInt16 | Self::Int16 => 1,
-            Self::UInt32 | Self::Int32 | Self::Float32 => 2,
+            Self::Bool | Self::UInt16 | Self::Int16 | Self::Float16 | Self::Bcd16 => 1,
+            Self::UInt32 | Self::Int32 | Self::Float32 | Self::Bcd32 => 2,
             Self::UInt64 | Self::Int64 | Self::Float64 => 4,
             Self::String => 8, // Default 16 characters
         }
@@ -400,8 +833,8 @@ impl DataFormat {
     pub fn byte_size(&self) -> usize {
         match self {
             Self::Bool => 1,
-            Self::UInt16 | Self::Int16 => 2,
-            Self::UInt32 | Self::Int32 | Self::Float32 => 4,
+            Self::UInt16 | Self::Int16 | Self::Float16 | Self::Bcd16 => 2,
+            Self::UInt32 | Self::Int32 | Self::Float32 | Self::Bcd32 => 4,
             Self::UInt64 | Self::Int64 | Self::Float64 => 8,
             Self::String => 16, // Default
         }
@@ -448,21 +881,34 @@ impl ByteOrder {
 }
 
 /// Data transformation configuration.
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+///
+/// Numeric values become `f(raw) * scale + offset`, where `f` is chosen by
+/// [`kind`](Self::kind) (the raw value itself by default).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TransformConfig {
-    /// Scale factor: result = raw * scale + offset.
+    /// Scale factor: result = f(raw) * scale + offset.
     #[serde(default = "default_scale")]
     pub scale: f64,
 
-    /// Offset: result = raw * scale + offset.
+    /// Offset: result = f(raw) * scale + offset.
     #[serde(default)]
     pub offset: f64,
 
+    /// Curve applied to the raw value before scaling.
+    #[serde(default, skip_serializing_if = "TransformKind::is_linear")]
+    pub kind: TransformKind,
+
     /// Reverse boolean value (for signals/controls).
     #[serde(default)]
     pub reverse: bool,
 
-    /// Deadband for change detection.
+    /// Deadband for change detection, in engineering units (after scaling).
+    ///
+    /// Applied first by the Modbus, IEC 104 and J1939 channels, which drop
+    /// samples within the deadband of the last value they returned (see
+    /// [`DeadbandFilter`](crate::core::deadband::DeadbandFilter)); quality
+    /// changes always pass. Store watchers then apply it again to what was
+    /// stored, so they report changes of more than the deadband.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub deadband: Option<f64>,
 
@@ -473,12 +919,193 @@ pub struct TransformConfig {
     /// Maximum valid value.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub max_value: Option<f64>,
+
+    /// What to do with values outside `[min_value, max_value]`.
+    #[serde(default)]
+    pub range_policy: RangePolicy,
+
+    /// Mapping from coded values to state names (e.g. `0 = "Stopped"`).
+    ///
+    /// Applied after scaling; mapped values are emitted as `Value::String`.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_enum_map"
+    )]
+    pub enum_map: Option<HashMap<i64, String>>,
+
+    /// What to do with codes missing from `enum_map`.
+    #[serde(default)]
+    pub invalid_code: InvalidCodePolicy,
+
+    /// Conversion to another engineering unit, applied after scaling (see
+    /// [`units`](crate::core::units)).
+    ///
+    /// `min_value`, `max_value` and `deadband` are in the converted unit.
+    /// A conversion that cannot be resolved makes values `Invalid`;
+    /// configuration validation reports it up front.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit_conversion: Option<UnitConversion>,
 }
 
 fn default_scale() -> f64 {
     1.0
 }
 
+impl Default for TransformConfig {
+    /// Identity transform (scale 1, offset 0), matching the serde defaults.
+    fn default() -> Self {
+        Self {
+            scale: default_scale(),
+            offset: 0.0,
+            kind: TransformKind::default(),
+            reverse: false,
+            deadband: None,
+            min_value: None,
+            max_value: None,
+            range_policy: RangePolicy::default(),
+            enum_map: None,
+            invalid_code: InvalidCodePolicy::default(),
+            unit_conversion: None,
+        }
+    }
+}
+
+/// Deserialize an enum map whose keys may be integers or numeric strings.
+///
+/// TOML and JSON table keys are always strings, so `0 = "Stopped"` arrives as
+/// the key `"0"`.
+fn deserialize_enum_map<'de, D>(
+    deserializer: D,
+) -> std::result::Result<Option<HashMap<i64, String>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize, PartialEq, Eq, Hash)]
+    #[serde(untagged)]
+    enum RawCode {
+        Num(i64),
+        Str(String),
+    }
+
+    let raw: Option<HashMap<RawCode, String>> = Option::deserialize(deserializer)?;
+    raw.map(|raw| {
+        raw.into_iter()
+            .map(|(code, label)| {
+                let code = match code {
+                    RawCode::Num(code) => code,
+                    RawCode::Str(s) => s.trim().parse().map_err(|_| {
+                        serde::de::Error::custom(format!(
+                            "invalid enum code '{}': expected an integer",
+                            s
+                        ))
+                    })?,
+                };
+                Ok((code, label))
+            })
+            .collect()
+    })
+    .transpose()
+}
+
+/// Curve of a [`TransformConfig`], applied to the raw value before
+/// `scale` and `offset`.
+///
+/// Configured as a table tagged with `type`; points without one stay
+/// linear:
+///
+/// ```toml
+/// [points.transform]
+/// scale = 12.5
+/// kind = { type = "square_root", cutoff = 0.02 }
+/// ```
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TransformKind {
+    /// The raw value itself.
+    #[default]
+    Linear,
+
+    /// `sqrt(raw)` for differential-pressure flow meters. Raw values below
+    /// `cutoff` (low-flow cutoff, at least 0) count as 0.
+    SquareRoot {
+        /// Raw values below this are treated as 0.
+        #[serde(default)]
+        cutoff: f64,
+    },
+
+    /// `c0 + c1 * raw + c2 * raw^2 + ...`, e.g. for thermocouples.
+    Polynomial {
+        /// Coefficients, constant term first.
+        coefficients: Vec<f64>,
+    },
+}
+
+impl TransformKind {
+    /// Whether this is the default linear curve.
+    pub fn is_linear(&self) -> bool {
+        *self == Self::Linear
+    }
+
+    fn apply(&self, raw: f64) -> f64 {
+        match self {
+            Self::Linear => raw,
+            Self::SquareRoot { cutoff } if raw < cutoff.max(0.0) => 0.0,
+            Self::SquareRoot { .. } => raw.sqrt(),
+            // Horner's scheme, highest order first
+            Self::Polynomial { coefficients } => {
+                coefficients.iter().rev().fold(0.0, |acc, c| acc * raw + c)
+            }
+        }
+    }
+
+    fn reverse(&self, value: f64) -> Result<f64, GatewayError> {
+        match self {
+            Self::Linear => Ok(value),
+            Self::SquareRoot { .. } if value < 0.0 => Err(GatewayError::DataConversion(format!(
+                "Cannot reverse square-root transform: {} is negative",
+                value
+            ))),
+            Self::SquareRoot { .. } => Ok(value * value),
+            Self::Polynomial { coefficients } => match coefficients.as_slice() {
+                [c0, c1, rest @ ..] if *c1 != 0.0 && rest.iter().all(|c| *c == 0.0) => {
+                    Ok((value - c0) / c1)
+                }
+                _ => Err(GatewayError::DataConversion(
+                    "Cannot reverse polynomial transform: not of first order".into(),
+                )),
+            },
+        }
+    }
+}
+
+/// Handling of codes that are not listed in [`TransformConfig::enum_map`].
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InvalidCodePolicy {
+    /// Keep the numeric value with its quality unchanged.
+    PassThrough,
+
+    /// Keep the numeric value and flag it `Quality::Invalid`.
+    #[default]
+    MarkInvalid,
+
+    /// Replace the value with the given label.
+    Substitute(String),
+}
+
+/// Handling of transformed values outside `[min_value, max_value]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RangePolicy {
+    /// Keep the value and flag it `Quality::Overflow` / `Quality::Underflow`.
+    #[default]
+    MarkBad,
+
+    /// Clamp the value to the violated bound and keep `Quality::Good`.
+    Clamp,
+}
+
 impl TransformConfig {
     /// Create a simple linear transform.
     pub fn linear(scale: f64, offset: f64) -> Self {
@@ -490,20 +1117,141 @@ impl TransformConfig {
     }
 
     /// Apply the transform to a raw value.
+    ///
+    /// Returns NaN if the [`unit_conversion`](Self::unit_conversion) cannot
+    /// be resolved.
     pub fn apply(&self, raw: f64) -> f64 {
-        raw * self.scale + self.offset
+        let value = self.kind.apply(raw) * self.scale + self.offset;
+        match &self.unit_conversion {
+            Some(conversion) => conversion
+                .resolve()
+                .map_or(f64::NAN, |conversion| conversion.apply(value)),
+            None => value,
+        }
+    }
+
+    /// Apply the transform and enforce the configured range.
+    ///
+    /// Bounds are inclusive. Out-of-range values are handled per
+    /// [`range_policy`](Self::range_policy). Non-finite results (NaN, ±inf)
+    /// are always reported as `Quality::Invalid`, never `Good`.
+    pub fn apply_checked(&self, raw: f64) -> (f64, Quality) {
+        let value = self.apply(raw);
+        if !value.is_finite() {
+            return (value, Quality::Invalid);
+        }
+
+        if let Some(min) = self.min_value.filter(|min| value < *min) {
+            return match self.range_policy {
+                RangePolicy::Clamp => (min, Quality::Good),
+                RangePolicy::MarkBad => (value, Quality::Underflow),
+            };
+        }
+        if let Some(max) = self.max_value.filter(|max| value > *max) {
+            return match self.range_policy {
+                RangePolicy::Clamp => (max, Quality::Good),
+                RangePolicy::MarkBad => (value, Quality::Overflow),
+            };
+        }
+
+        (value, Quality::Good)
+    }
+
+    /// Apply the transform to a decoded protocol value.
+    ///
+    /// Numeric values are scaled and range-checked via [`apply_checked`](Self::apply_checked)
+    /// (integers become floats); booleans honour `reverse`; anything else
+    /// passes through unchanged. If an [`enum_map`](Self::enum_map) is set,
+    /// in-range numeric and boolean results are then looked up in it. Arrays
+    /// are transformed element by element and take the quality of their
+    /// first element that is not `Good`. This is the single entry point
+    /// protocol decode paths should use.
+    pub fn apply_value(&self, value: Value) -> (Value, Quality) {
+        if let Value::Array(items) = value {
+            let mut quality = Quality::Good;
+            let items = items
+                .into_iter()
+                .map(|item| {
+                    let (item, item_quality) = self.apply_value(item);
+                    if quality.is_good() {
+                        quality = item_quality;
+                    }
+                    item
+                })
+                .collect();
+            return (Value::Array(items), quality);
+        }
+        let (value, quality) = self.apply_scalar(value);
+        match &self.enum_map {
+            Some(map) if quality.is_good() => self.apply_enum(map, value),
+            _ => (value, quality),
+        }
+    }
+
+    fn apply_scalar(&self, value: Value) -> (Value, Quality) {
+        match value {
+            Value::Float(v) => {
+                let (v, quality) = self.apply_checked(v);
+                (Value::Float(v), quality)
+            }
+            Value::Integer(v) => {
+                let (v, quality) = self.apply_checked(v as f64);
+                (Value::Float(v), quality)
+            }
+            Value::Bool(v) => (Value::Bool(self.apply_bool(v)), Quality::Good),
+            other => (other, Quality::Good),
+        }
+    }
+
+    fn apply_enum(&self, map: &HashMap<i64, String>, value: Value) -> (Value, Quality) {
+        let code = match &value {
+            Value::Float(v) if v.fract() == 0.0 => Some(*v as i64),
+            Value::Float(_) => None,
+            Value::Integer(v) => Some(*v),
+            Value::Bool(v) => Some(i64::from(*v)),
+            _ => return (value, Quality::Good),
+        };
+
+        if let Some(label) = code.and_then(|code| map.get(&code)) {
+            return (Value::String(label.clone()), Quality::Good);
+        }
+
+        match &self.invalid_code {
+            InvalidCodePolicy::PassThrough => (value, Quality::Good),
+            InvalidCodePolicy::MarkInvalid => (value, Quality::Invalid),
+            InvalidCodePolicy::Substitute(label) => (Value::String(label.clone()), Quality::Good),
+        }
+    }
+
+    /// Look up the code for a state name in [`enum_map`](Self::enum_map).
+    ///
+    /// Used on the write path to turn `"Running"` back into its raw code.
+    pub fn enum_code(&self, label: &str) -> Option<i64> {
+        self.enum_map
+            .as_ref()?
+            .iter()
+            .find(|(_, l)| l.as_str() == label)
+            .map(|(code, _)| *code)
     }
 
     /// Apply reverse transform to get raw value.
     ///
-    /// Returns an error if `scale` is zero (division by zero).
+    /// Returns an error if the unit conversion cannot be resolved, `scale`
+    /// is zero (division by zero) or the
+    /// [`kind`](Self::kind) cannot be inverted for `value` (negative square
+    /// roots, polynomials of second order or higher), so write paths never
+    /// send a misconverted raw value.
     pub fn reverse_apply(&self, value: f64) -> Result<f64, GatewayError> {
+        let value = match &self.unit_conversion {
+            Some(conversion) => conversion.resolve()?.reverse(value),
+            None => value,
+        };
         if self.scale == 0.0 {
             return Err(GatewayError::DataConversion(
                 "Cannot reverse transform: scale is zero".into(),
             ));
         }
-        Ok((value - self.offset) / self.scale)
+        self.kind.reverse((value - self.offset) / self.scale)
     }
 
     /// Apply boolean reverse if configured.
@@ -529,6 +1277,35 @@ mod tests {
         assert_eq!(addr.register_count(), 2);
     }
 
+    #[test]
+    fn test_modbus_address_validate() {
+        assert!(
+            ModbusAddress::holding_register(1, 0xFFFE, DataFormat::Float32)
+                .validate()
+                .is_ok()
+        );
+        let overrun = ModbusAddress::holding_register(1, 0xFFFF, DataFormat::Float32);
+        assert!(overrun
+            .validate()
+            .unwrap_err()
+            .to_string()
+            .contains("0xFFFF"));
+
+        let mut addr = ModbusAddress::holding_register(1, 0, DataFormat::UInt16);
+        addr.function_code = 7;
+        assert!(addr.validate().is_err());
+        addr.function_code = 3;
+        addr.bit_position = Some(16);
+        assert!(ProtocolAddress::Modbus(addr).validate().is_err());
+
+        let cells = ModbusAddress::input_register(1, 100, DataFormat::Float32).with_count(24);
+        assert_eq!(cells.register_count(), 48);
+        assert!(cells.validate().is_ok());
+        assert!(cells.clone().with_count(0).validate().is_err());
+        assert!(cells.with_count(63).validate().is_err());
+        assert!(ModbusAddress::coil(1, 0).with_count(8).validate().is_err());
+    }
+
     #[test]
     fn test_transform() {
         let t = TransformConfig::linear(0.1, 10.0);
@@ -536,16 +1313,238 @@ mod tests {
         assert_eq!(t.reverse_apply(20.0).unwrap(), 100.0);
     }
 
+    #[test]
+    fn test_transform_default_is_identity() {
+        let t = TransformConfig::default();
+        assert_eq!(t.apply(42.0), 42.0);
+        assert_eq!(t.apply_checked(42.0), (42.0, Quality::Good));
+    }
+
+    #[test]
+    fn test_square_root_transform() {
+        let t: TransformConfig = serde_json::from_value(serde_json::json!({
+            "scale": 10.0,
+            "kind": { "type": "square_root", "cutoff": 0.5 }
+        }))
+        .unwrap();
+        assert_eq!(t.apply(4.0), 20.0);
+        assert_eq!(t.apply(0.25), 0.0);
+        assert_eq!(t.apply(-1.0), 0.0);
+        assert_eq!(t.reverse_apply(20.0).unwrap(), 4.0);
+        assert!(t.reverse_apply(-20.0).is_err());
+    }
+
+    #[test]
+    fn test_polynomial_transform() {
+        let t = TransformConfig {
+            kind: TransformKind::Polynomial {
+                coefficients: vec![1.0, 2.0, 3.0],
+            },
+            ..Default::default()
+        };
+        assert_eq!(t.apply(2.0), 17.0); // 1 + 2*2 + 3*4
+        assert!(t.reverse_apply(17.0).is_err());
+
+        let first_order = TransformConfig {
+            kind: TransformKind::Polynomial {
+                coefficients: vec![1.0, 2.0, 0.0],
+            },
+            ..TransformConfig::linear(2.0, 0.0)
+        };
+        assert_eq!(first_order.apply(3.0), 14.0);
+        assert_eq!(first_order.reverse_apply(14.0).unwrap(), 3.0);
+    }
+
+    #[test]
+    fn test_linear_kind_is_the_serde_default() {
+        let t: TransformConfig =
+            serde_json::from_value(serde_json::json!({ "scale": 0.1 })).unwrap();
+        assert!(t.kind.is_linear());
+        let json = serde_json::to_value(&t).unwrap();
+        assert!(json.get("kind").is_none());
+    }
+
     #[test]
     fn test_transform_zero_scale() {
         let t = TransformConfig::linear(0.0, 10.0);
         assert!(t.reverse_apply(20.0).is_err());
     }
 
+    #[test]
+    fn test_apply_checked_range() {
+        let t = TransformConfig {
+            min_value: Some(0.0),
+            max_value: Some(100.0),
+            ..TransformConfig::linear(1.0, 0.0)
+        };
+
+        // Exact bounds are in range
+        assert_eq!(t.apply_checked(0.0), (0.0, Quality::Good));
+        assert_eq!(t.apply_checked(100.0), (100.0, Quality::Good));
+
+        assert_eq!(t.apply_checked(100.5), (100.5, Quality::Overflow));
+        assert_eq!(t.apply_checked(-0.5), (-0.5, Quality::Underflow));
+
+        let t = TransformConfig {
+            range_policy: RangePolicy::Clamp,
+            ..t
+        };
+        assert_eq!(t.apply_checked(100.5), (100.0, Quality::Good));
+        assert_eq!(t.apply_checked(-0.5), (0.0, Quality::Good));
+    }
+
+    #[test]
+    fn test_apply_checked_non_finite() {
+        let t = TransformConfig::linear(1.0, 0.0);
+        let (v, q) = t.apply_checked(f64::NAN);
+        assert!(v.is_nan());
+        assert_eq!(q, Quality::Invalid);
+
+        // Clamping must not turn NaN into a good value
+        let t = TransformConfig {
+            min_value: Some(0.0),
+            max_value: Some(1.0),
+            range_policy: RangePolicy::Clamp,
+            ..t
+        };
+        assert_eq!(t.apply_checked(f64::NAN).1, Quality::Invalid);
+        assert_eq!(t.apply_checked(f64::INFINITY).1, Quality::Invalid);
+    }
+
+    #[test]
+    fn test_apply_value() {
+        let t = TransformConfig {
+            max_value: Some(10.0),
+            ..TransformConfig::linear(0.1, 0.0)
+        };
+        assert_eq!(
+            t.apply_value(Value::Integer(50)),
+            (Value::Float(5.0), Quality::Good)
+        );
+        assert_eq!(t.apply_value(Value::Integer(200)).1, Quality::Overflow);
+        assert_eq!(
+            t.apply_value(Value::String("x".into())),
+            (Value::String("x".into()), Quality::Good)
+        );
+        assert_eq!(
+            t.apply_value(Value::Array(vec![Value::Integer(30), Value::Integer(200)])),
+            (
+                Value::Array(vec![Value::Float(3.0), Value::Float(20.0)]),
+                Quality::Overflow
+            )
+        );
+    }
+
     #[test]
     fn test_data_format_register_count() {
         assert_eq!(DataFormat::UInt16.register_count(), 1);
         assert_eq!(DataFormat::Float32.register_count(), 2);
         assert_eq!(DataFormat::Float64.register_count(), 4);
     }
+
+    #[test]
+    fn test_point_meta_map() {
+        let points = vec![
+            PointConfig::new(1, ProtocolAddress::Virtual(VirtualAddress::new("p")))
+                .with_name("boost")
+                .with_unit("kPa"),
+            PointConfig::new(2, ProtocolAddress::Virtual(VirtualAddress::new("p"))),
+        ];
+        let meta = point_meta_map(&points);
+        assert_eq!(meta.len(), 1);
+        assert_eq!(meta[&1].unit.as_deref(), Some("kPa"));
+        assert_eq!(meta[&1].name.as_deref(), Some("boost"));
+    }
+
+    #[test]
+    fn test_annotated_batch_serialization() {
+        let mut meta = PointMetaMap::new();
+        meta.insert(
+            1,
+            PointMeta {
+                unit: Some("°C".into()),
+                ..Default::default()
+            },
+        );
+        let batch = DataBatch::from_points(vec![DataPoint::new(1, 21.5), DataPoint::new(2, 7i64)]);
+
+        let json = serde_json::to_value(batch.with_meta(&meta)).unwrap();
+        let points = json["points"].as_array().unwrap();
+        assert_eq!(points[0]["unit"], "°C");
+        assert_eq!(points[0]["value"], 21.5);
+        assert!(points[1].get("unit").is_none());
+    }
+
+    fn state_transform(policy: InvalidCodePolicy) -> TransformConfig {
+        TransformConfig {
+            enum_map: Some(HashMap::from([
+                (0, "Stopped".to_string()),
+                (2, "Running".to_string()),
+            ])),
+            invalid_code: policy,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_enum_map() {
+        let t = state_transform(InvalidCodePolicy::MarkInvalid);
+        assert_eq!(
+            t.apply_value(Value::Integer(2)),
+            (Value::String("Running".into()), Quality::Good)
+        );
+        assert_eq!(
+            t.apply_value(Value::Integer(7)),
+            (Value::Float(7.0), Quality::Invalid)
+        );
+        assert_eq!(t.apply_value(Value::Float(0.5)).1, Quality::Invalid);
+        assert_eq!(t.enum_code("Running"), Some(2));
+
+        let t = state_transform(InvalidCodePolicy::PassThrough);
+        assert_eq!(
+            t.apply_value(Value::Integer(7)),
+            (Value::Float(7.0), Quality::Good)
+        );
+
+        let t = state_transform(InvalidCodePolicy::Substitute("Unknown".into()));
+        assert_eq!(
+            t.apply_value(Value::Integer(7)),
+            (Value::String("Unknown".into()), Quality::Good)
+        );
+    }
+
+    #[test]
+    fn test_enum_map_after_scaling() {
+        let t = TransformConfig {
+            scale: 0.5,
+            ..state_transform(InvalidCodePolicy::MarkInvalid)
+        };
+        assert_eq!(
+            t.apply_value(Value::Integer(4)).0,
+            Value::String("Running".into())
+        );
+    }
+
+    #[test]
+    fn test_enum_map_toml() {
+        let t: TransformConfig = toml::from_str(
+            r#"
+invalid_code = { substitute = "Unknown" }
+
+[enum_map]
+0 = "Stopped"
+3 = "Fault"
+"#,
+        )
+        .unwrap();
+        assert_eq!(t.enum_map.as_ref().unwrap()[&3], "Fault");
+        assert_eq!(
+            t.invalid_code,
+            InvalidCodePolicy::Substitute("Unknown".into())
+        );
+
+        let t: TransformConfig = toml::from_str("scale = 2.0").unwrap();
+        assert!(t.enum_map.is_none());
+        assert_eq!(t.invalid_code, InvalidCodePolicy::MarkInvalid);
+    }
 }
@@ -84,6 +84,25 @@ impl Quality {
         matches!(self, Self::DeviceFailure | Self::SensorFailure)
     }
 
+    /// Severity class: 0 = good, 1 = uncertain, 2 = bad.
+    ///
+    /// Derived from the OPC UA severity bits of [`to_opc_status`](Self::to_opc_status).
+    #[inline]
+    pub fn severity(&self) -> u8 {
+        (self.to_opc_status() >> 30) as u8
+    }
+
+    /// Return the worse of two qualities (by severity; `self` wins ties).
+    #[inline]
+    #[must_use]
+    pub fn worst(self, other: Self) -> Self {
+        if other.severity() > self.severity() {
+            other
+        } else {
+            self
+        }
+    }
+
     /// Convert to OPC UA status code (subset).
     pub fn to_opc_status(&self) -> u32 {
         match self {
@@ -165,4 +184,18 @@ mod tests {
         assert_eq!(Quality::from_opc_status(0x40000000), Quality::Uncertain);
         assert_eq!(Quality::from_opc_status(0x80000000), Quality::Bad);
     }
+
+    #[test]
+    fn test_quality_worst() {
+        assert_eq!(Quality::Good.worst(Quality::LastKnown), Quality::LastKnown);
+        assert_eq!(
+            Quality::LastKnown.worst(Quality::CommFailure),
+            Quality::CommFailure
+        );
+        assert_eq!(
+            Quality::Overflow.worst(Quality::Uncertain),
+            Quality::Overflow
+        );
+        assert_eq!(Quality::Good.severity(), 0);
+    }
 }
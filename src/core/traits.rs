@@ -11,7 +11,7 @@
 //!
 //! Layer 2: Core Operations (single responsibility)
 //! ├── ProtocolClient        // connect, disconnect, poll_once, write_*
-//! └── EventDrivenProtocol   // event_stream (broadcast)
+//! └── EventDrivenProtocol   // subscribe (EventBus)
 //!
 //! Layer 3: Optional Extensions
 //! └── ProtocolServer        // listen, stop, connected_clients
@@ -21,10 +21,10 @@ use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::future::Future;
 use std::sync::Arc;
-use tokio::sync::broadcast;
 
-use crate::core::data::DataBatch;
+use crate::core::data::{DataBatch, DataPoint, PointId};
 use crate::core::error::Result;
+use crate::core::quality::Quality;
 
 /// Communication mode supported by a protocol.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -98,23 +98,102 @@ impl std::fmt::Display for ConnectionState {
 ///
 /// Simple request type for protocol-layer reads. The application layer
 /// is responsible for any SCADA-level filtering (by type, etc.).
+///
+/// All filters are optional and combine: a point is returned only if it
+/// passes every filter that is set.
+///
+/// ```
+/// use igw::core::traits::{QualityFilter, ReadRequest};
+///
+/// // Points of the "fast" poll group that are currently not good
+/// let request = ReadRequest::by_group("fast").with_quality_filter(QualityFilter::Bad);
+/// assert_eq!(request.poll_group.as_deref(), Some("fast"));
+/// ```
 #[derive(Debug, Clone, Default)]
 pub struct ReadRequest {
     /// Point IDs to read (None = all configured points)
-    pub point_ids: Option<Vec<u32>>,
+    pub point_ids: Option<Vec<PointId>>,
+
+    /// Only points of this poll group (None = any group)
+    pub poll_group: Option<String>,
+
+    /// Only points of this quality (None = any quality)
+    pub quality_filter: Option<QualityFilter>,
 }
 
 impl ReadRequest {
     /// Create a request for specific points.
-    pub fn by_ids(ids: Vec<u32>) -> Self {
+    pub fn by_ids(ids: Vec<PointId>) -> Self {
         Self {
             point_ids: Some(ids),
+            ..Self::default()
         }
     }
 
     /// Create a request for all configured points.
     pub fn all() -> Self {
-        Self { point_ids: None }
+        Self::default()
+    }
+
+    /// Create a request for all points of a poll group.
+    pub fn by_group(group: impl Into<String>) -> Self {
+        Self::all().with_poll_group(group)
+    }
+
+    /// Create a request for all points whose quality is not good.
+    pub fn bad_quality() -> Self {
+        Self::all().with_quality_filter(QualityFilter::Bad)
+    }
+
+    /// Restrict the request to a poll group.
+    #[must_use]
+    pub fn with_poll_group(mut self, group: impl Into<String>) -> Self {
+        self.poll_group = Some(group.into());
+        self
+    }
+
+    /// Restrict the request to a quality.
+    #[must_use]
+    pub fn with_quality_filter(mut self, filter: QualityFilter) -> Self {
+        self.quality_filter = Some(filter);
+        self
+    }
+
+    /// Whether a point passes every filter of the request.
+    ///
+    /// `poll_group` is the point's configured poll group; a point without
+    /// one never matches a group filter.
+    pub fn matches(&self, point: &DataPoint, poll_group: Option<&str>) -> bool {
+        self.point_ids
+            .as_ref()
+            .is_none_or(|ids| ids.contains(&point.id))
+            && self
+                .poll_group
+                .as_deref()
+                .is_none_or(|group| poll_group == Some(group))
+            && self
+                .quality_filter
+                .is_none_or(|filter| filter.matches(point.quality))
+    }
+}
+
+/// Quality filter of a [`ReadRequest`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QualityFilter {
+    /// Only points with `Quality::Good`.
+    Good,
+    /// Only points whose quality is anything but `Quality::Good`.
+    Bad,
+}
+
+impl QualityFilter {
+    /// Whether `quality` passes the filter.
+    pub fn matches(self, quality: Quality) -> bool {
+        match self {
+            Self::Good => quality.is_good(),
+            Self::Bad => quality.is_bad(),
+        }
     }
 }
 
@@ -131,7 +210,7 @@ pub struct ReadResponse {
     ///
     /// This allows callers to know exactly which points failed and why,
     /// rather than just a count.
-    pub partial_errors: Vec<(u32, String)>,
+    pub partial_errors: Vec<(PointId, String)>,
 }
 
 impl ReadResponse {
@@ -154,7 +233,7 @@ impl ReadResponse {
     }
 
     /// Create a response with detailed error information.
-    pub fn with_errors(data: DataBatch, errors: Vec<(u32, String)>) -> Self {
+    pub fn with_errors(data: DataBatch, errors: Vec<(PointId, String)>) -> Self {
         let failed_count = errors.len();
         Self {
             data,
@@ -163,6 +242,17 @@ impl ReadResponse {
         }
     }
 
+    /// Create a response from per-point failures.
+    pub fn with_failures(data: DataBatch, failures: Vec<PointFailure>) -> Self {
+        Self::with_errors(
+            data,
+            failures
+                .into_iter()
+                .map(|f| (f.point_id, f.error))
+                .collect(),
+        )
+    }
+
     /// Check if any reads failed.
     ///
     /// Returns true if either:
@@ -194,44 +284,96 @@ impl ReadResponse {
     }
 }
 
+impl From<PollResult> for ReadResponse {
+    fn from(result: PollResult) -> Self {
+        Self::with_failures(result.data, result.failures)
+    }
+}
+
+/// How a control command is executed on the device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OperateMode {
+    /// Execute immediately in a single step.
+    #[default]
+    DirectExecute,
+
+    /// Select-before-operate: select the point, wait for the device to
+    /// confirm the selection, then execute (IEC 104, DNP3).
+    ///
+    /// Protocols without a select step treat this as `DirectExecute`.
+    SelectThenExecute,
+}
+
 /// A control command to write.
-#[derive(Debug, Clone)]
+///
+/// In JSON only `id` and `value` are required; the other fields default to
+/// a latching, direct-execute command.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ControlCommand {
     /// Point ID
-    pub id: u32,
+    pub id: PointId,
 
     /// Command value (true = ON/CLOSE, false = OFF/OPEN)
     pub value: bool,
 
     /// Pulse duration in milliseconds (None = latching)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub pulse_duration_ms: Option<u32>,
+
+    /// Direct execute or select-before-operate.
+    #[serde(default)]
+    pub operate_mode: OperateMode,
+
+    /// How long to wait for each confirmation step in milliseconds
+    /// (None = protocol default).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u32>,
 }
 
 impl ControlCommand {
     /// Create a latching control command.
-    pub fn latching(id: u32, value: bool) -> Self {
+    pub fn latching(id: PointId, value: bool) -> Self {
         Self {
             id,
             value,
             pulse_duration_ms: None,
+            operate_mode: OperateMode::DirectExecute,
+            timeout_ms: None,
         }
     }
 
     /// Create a pulse control command.
-    pub fn pulse(id: u32, value: bool, duration_ms: u32) -> Self {
+    pub fn pulse(id: PointId, value: bool, duration_ms: u32) -> Self {
         Self {
             id,
             value,
             pulse_duration_ms: Some(duration_ms),
+            operate_mode: OperateMode::DirectExecute,
+            timeout_ms: None,
         }
     }
+
+    /// Set the operate mode.
+    #[must_use]
+    pub fn with_operate_mode(mut self, mode: OperateMode) -> Self {
+        self.operate_mode = mode;
+        self
+    }
+
+    /// Set the confirmation timeout.
+    #[must_use]
+    pub fn with_timeout_ms(mut self, timeout_ms: u32) -> Self {
+        self.timeout_ms = Some(timeout_ms);
+        self
+    }
 }
 
 /// An adjustment command to write.
 #[derive(Debug, Clone)]
 pub struct AdjustmentCommand {
     /// Point ID
-    pub id: u32,
+    pub id: PointId,
 
     /// Setpoint value
     pub value: f64,
@@ -239,11 +381,71 @@ pub struct AdjustmentCommand {
 
 impl AdjustmentCommand {
     /// Create an adjustment command.
-    pub fn new(id: u32, value: f64) -> Self {
+    pub fn new(id: PointId, value: f64) -> Self {
         Self { id, value }
     }
 }
 
+/// Stage a command has reached in the protocol's confirmation sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommandStage {
+    /// Sent and accepted by the local stack; the device has not confirmed yet.
+    Accepted,
+
+    /// Positively confirmed by the device (e.g. IEC 104 ACTCON, Modbus response)
+    /// or by its feedback point.
+    Confirmed,
+
+    /// Execution completed on the device (e.g. IEC 104 ACTTERM).
+    Terminated,
+
+    /// Rejected by the device or no confirmation within the timeout.
+    Failed,
+
+    /// Written, but the feedback point did not reach the expected value
+    /// within the timeout.
+    FeedbackTimeout,
+}
+
+/// Late confirmation of a previously written command.
+///
+/// Event-driven protocols publish this as `DataEvent::CommandUpdate` when a
+/// confirmation arrives after `write_control()`/`write_adjustment()` returned.
+/// The gateway runtime publishes it for commands with a feedback check.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommandOutcome {
+    /// The point the command was written to.
+    pub point_id: PointId,
+
+    /// Stage reached.
+    pub stage: CommandStage,
+
+    /// Error detail when `stage` is `Failed` or `FeedbackTimeout`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl CommandOutcome {
+    /// Create an outcome for the given stage.
+    pub fn new(point_id: PointId, stage: CommandStage) -> Self {
+        Self {
+            point_id,
+            stage,
+            error: None,
+        }
+    }
+
+    /// Create a failed outcome.
+    pub fn failed(point_id: PointId, error: impl Into<String>) -> Self {
+        Self {
+            point_id,
+            stage: CommandStage::Failed,
+            error: Some(error.into()),
+        }
+    }
+}
+
 /// Result of write operations.
 #[derive(Debug, Clone)]
 pub struct WriteResult {
@@ -251,15 +453,32 @@ pub struct WriteResult {
     pub success_count: usize,
 
     /// IDs of failed writes with error messages.
-    pub failures: Vec<(u32, String)>,
+    pub failures: Vec<(PointId, String)>,
+
+    /// Stage the successful writes had reached when the call returned.
+    ///
+    /// `Confirmed` for request/response protocols (Modbus, OPC UA, GPIO).
+    /// `Accepted` when the device confirms asynchronously; the confirmation
+    /// then arrives as `DataEvent::CommandUpdate`.
+    pub stage: CommandStage,
 }
 
 impl WriteResult {
-    /// Create a fully successful result.
+    /// Create a fully successful, confirmed result.
     pub fn success(count: usize) -> Self {
         Self {
             success_count: count,
             failures: vec![],
+            stage: CommandStage::Confirmed,
+        }
+    }
+
+    /// Create a fully successful result still awaiting device confirmation.
+    pub fn accepted(count: usize) -> Self {
+        Self {
+            success_count: count,
+            failures: vec![],
+            stage: CommandStage::Accepted,
         }
     }
 
@@ -330,10 +549,10 @@ impl PollResult {
 }
 
 /// Information about a point that failed to read.
-#[derive(Debug, Clone, serde::Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PointFailure {
     /// The point ID that failed.
-    pub point_id: u32,
+    pub point_id: PointId,
 
     /// Error message describing the failure.
     pub error: String,
@@ -341,7 +560,7 @@ pub struct PointFailure {
 
 impl PointFailure {
     /// Create a new point failure.
-    pub fn new(point_id: u32, error: impl Into<String>) -> Self {
+    pub fn new(point_id: PointId, error: impl Into<String>) -> Self {
         Self {
             point_id,
             error: error.into(),
@@ -356,7 +575,7 @@ pub struct PollingConfig {
     pub interval_ms: u64,
 
     /// Point IDs to poll (None = all configured points).
-    pub point_ids: Option<Vec<u32>>,
+    pub point_ids: Option<Vec<PointId>>,
 
     /// Whether to continue on individual point errors.
     pub continue_on_error: bool,
@@ -373,7 +592,11 @@ impl Default for PollingConfig {
 }
 
 /// Protocol diagnostics information.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// Timing and byte counters are optional for serde compatibility; adapters
+/// that do not measure them leave the defaults. See
+/// [`DiagnosticsRecorder`](crate::core::diagnostics::DiagnosticsRecorder).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Diagnostics {
     /// Protocol name.
     pub protocol: String,
@@ -393,6 +616,41 @@ pub struct Diagnostics {
     /// Last error message.
     pub last_error: Option<String>,
 
+    /// Duration of the most recent poll cycle in milliseconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_poll_duration_ms: Option<u64>,
+
+    /// Poll cycles skipped because a poll took longer than the interval.
+    ///
+    /// Filled in by the scheduler driving `poll_once()`, e.g.
+    /// [`GatewayRuntime`](crate::gateway::GatewayRuntime).
+    #[serde(default)]
+    pub poll_overruns: u64,
+
+    /// Median request latency over the recent window, in milliseconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_p50_ms: Option<f64>,
+
+    /// 95th percentile request latency over the recent window, in milliseconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_p95_ms: Option<f64>,
+
+    /// Maximum request latency over the recent window, in milliseconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_max_ms: Option<f64>,
+
+    /// Bytes sent on the wire (0 if not measured).
+    #[serde(default)]
+    pub bytes_sent: u64,
+
+    /// Bytes received from the wire (0 if not measured).
+    #[serde(default)]
+    pub bytes_received: u64,
+
+    /// Time of the last successful read.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_success: Option<chrono::DateTime<chrono::Utc>>,
+
     /// Protocol-specific information.
     #[serde(default)]
     pub extra: serde_json::Value,
@@ -403,12 +661,7 @@ impl Diagnostics {
     pub fn new(protocol: impl Into<String>) -> Self {
         Self {
             protocol: protocol.into(),
-            connection_state: ConnectionState::Disconnected,
-            read_count: 0,
-            write_count: 0,
-            error_count: 0,
-            last_error: None,
-            extra: serde_json::Value::Null,
+            ..Self::default()
         }
     }
 }
@@ -476,6 +729,11 @@ pub trait ProtocolClient: Protocol {
     /// A `PollResult` containing:
     /// - `data`: Successfully read data points
     /// - `failures`: Points that failed to read (partial success supported)
+    ///
+    /// Points that could not be read belong in `failures`, not in `data` with a
+    /// bad quality. Event-driven adapters that answer from a cache (e.g. J1939)
+    /// return their latest values and mark ones that are no longer refreshed
+    /// as `Quality::LastKnown`.
     fn poll_once(&mut self) -> impl Future<Output = PollResult> + Send;
 
     /// Write control commands (遥控).
@@ -509,10 +767,12 @@ pub trait ProtocolServer: Protocol {
 }
 
 /// Data event for event-driven protocols.
+///
+/// Events are cloned once per subscriber, so data updates share their batch.
 #[derive(Debug, Clone)]
 pub enum DataEvent {
     /// Data update received.
-    DataUpdate(DataBatch),
+    DataUpdate(Arc<DataBatch>),
 
     /// Connection state changed.
     ConnectionChanged(ConnectionState),
@@ -522,13 +782,12 @@ pub enum DataEvent {
 
     /// Heartbeat/keep-alive.
     Heartbeat,
-}
 
-/// Event receiver type (broadcast supports multiple subscribers).
-pub type DataEventReceiver = broadcast::Receiver<DataEvent>;
+    /// Late command confirmation or termination.
+    CommandUpdate(CommandOutcome),
+}
 
-/// Event sender type (broadcast supports multiple subscribers).
-pub type DataEventSender = broadcast::Sender<DataEvent>;
+pub use crate::core::event::{DataEventReceiver, EventBus};
 
 /// Event handler trait.
 ///
@@ -536,7 +795,7 @@ pub type DataEventSender = broadcast::Sender<DataEvent>;
 #[async_trait]
 pub trait DataEventHandler: Send + Sync {
     /// Handle data update event.
-    async fn on_data_update(&self, batch: DataBatch);
+    async fn on_data_update(&self, batch: Arc<DataBatch>);
 
     /// Handle connection state change.
     async fn on_connection_changed(&self, state: ConnectionState);
@@ -552,15 +811,16 @@ pub trait DataEventHandler: Send + Sync {
 pub trait EventDrivenProtocol: Protocol {
     /// Subscribe to data events.
     ///
-    /// Returns a broadcast receiver that will receive all events from this protocol.
-    /// Multiple subscribers can call this method and each will receive all events.
+    /// Returns an [`EventBus`] receiver that will receive all events from this protocol.
+    /// Multiple subscribers can call this method and each will receive all events;
+    /// a subscriber that falls behind gets a `DataEvent::Error` reporting the lag.
     ///
     /// # Example
     ///
     /// ```ignore
     /// let mut rx = protocol.subscribe();
     /// tokio::spawn(async move {
-    ///     while let Ok(event) = rx.recv().await {
+    ///     while let Some(event) = rx.recv().await {
     ///         match event {
     ///             DataEvent::DataUpdate(batch) => { /* handle data */ }
     ///             DataEvent::ConnectionChanged(state) => { /* handle state change */ }
@@ -643,6 +903,47 @@ mod tests {
         assert_eq!(result.failure_count(), 1);
     }
 
+    #[test]
+    fn test_read_response_from_poll_result() {
+        let mut batch = DataBatch::new();
+        batch.add(crate::core::data::DataPoint::new(1, 1.0));
+        let result = PollResult::partial(batch, vec![PointFailure::new(2, "decode error")]);
+
+        let response = ReadResponse::from(result);
+        assert_eq!(response.data.len(), 1);
+        assert_eq!(response.failed_count, 1);
+        assert_eq!(
+            response.partial_errors,
+            vec![(2, "decode error".to_string())]
+        );
+        assert!(response.has_errors());
+    }
+
+    #[test]
+    fn test_read_request_filters() {
+        use crate::core::data::DataPoint;
+
+        let good = DataPoint::new(1, 1.0);
+        let bad = DataPoint::new(2, 2.0).with_quality(Quality::CommFailure);
+
+        let all = ReadRequest::all();
+        assert!(all.matches(&good, None) && all.matches(&bad, Some("slow")));
+
+        let fast = ReadRequest::by_group("fast");
+        assert!(fast.matches(&good, Some("fast")));
+        assert!(!fast.matches(&good, Some("slow")));
+        assert!(!fast.matches(&good, None));
+
+        let alarms = ReadRequest::bad_quality();
+        assert!(!alarms.matches(&good, None));
+        assert!(alarms.matches(&bad, None));
+
+        let combined = ReadRequest::by_ids(vec![1, 2]).with_quality_filter(QualityFilter::Good);
+        assert!(combined.matches(&good, None));
+        assert!(!combined.matches(&bad, None));
+        assert!(!combined.matches(&DataPoint::new(3, 3.0), None));
+    }
+
     #[test]
     fn test_point_failure() {
         let failure = PointFailure::new(42, "read timeout");
@@ -656,5 +957,26 @@ mod tests {
         assert!(result.is_success());
         assert_eq!(result.success_count, 5);
         assert!(result.failures.is_empty());
+        assert_eq!(result.stage, CommandStage::Confirmed);
+
+        let result = WriteResult::accepted(1);
+        assert_eq!(result.stage, CommandStage::Accepted);
+    }
+
+    #[test]
+    fn test_control_command_operate_mode() {
+        let cmd = ControlCommand::latching(1, true);
+        assert_eq!(cmd.operate_mode, OperateMode::DirectExecute);
+        assert_eq!(cmd.timeout_ms, None);
+
+        let cmd = ControlCommand::pulse(2, true, 500)
+            .with_operate_mode(OperateMode::SelectThenExecute)
+            .with_timeout_ms(3000);
+        assert_eq!(cmd.operate_mode, OperateMode::SelectThenExecute);
+        assert_eq!(cmd.timeout_ms, Some(3000));
+
+        let outcome = CommandOutcome::failed(2, "select rejected");
+        assert_eq!(outcome.stage, CommandStage::Failed);
+        assert_eq!(serde_json::to_value(&outcome).unwrap()["stage"], "failed");
     }
 }
@@ -0,0 +1,293 @@
+//! Transports shared by several channels.
+//!
+//! Devices on one RS-485 trunk (or behind one Modbus TCP gateway) are
+//! reached through a single port. Two channels opening that port each on
+//! their own corrupt each other's frames, so a [`SharedTransport`] holds
+//! the port's client for every channel using it instead:
+//!
+//! - the first channel to [`attach()`](SharedTransport::attach) opens the
+//!   client, the last to [`detach()`](SharedTransport::detach) gets it back
+//!   to close it;
+//! - every request [`acquire()`](SharedTransport::acquire)s the client for
+//!   just its own round trip, so there is one outstanding request per
+//!   transport;
+//! - channels waiting for the client are served in arrival order, so a
+//!   channel with many requests per poll cannot starve the others;
+//! - a request can keep the transport
+//!   [quiet](TransportGuard::quiet_until) for a while after it, e.g. the
+//!   turn-around delay after a Modbus broadcast, which every next request
+//!   waits out.
+//!
+//! [`TransportRegistry`] hands out one transport per key (a device path or
+//! address), so channels built independently of each other still share it.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Weak};
+
+use serde::Serialize;
+use tokio::sync::{Mutex, MutexGuard};
+use tokio::time::Instant;
+
+use crate::core::error::Result;
+
+/// A client used by several channels, one request at a time.
+#[derive(Debug)]
+pub struct SharedTransport<T> {
+    key: String,
+    slot: Mutex<Slot<T>>,
+    users: AtomicUsize,
+    requests: AtomicU64,
+    contended: AtomicU64,
+}
+
+/// Usage of a [`SharedTransport`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct TransportStats {
+    /// Attached channels.
+    pub users: usize,
+
+    /// Requests made.
+    pub requests: u64,
+
+    /// Requests that had to wait for another one to finish.
+    pub contended: u64,
+}
+
+#[derive(Debug)]
+struct Slot<T> {
+    client: Option<T>,
+    /// No request before this time.
+    quiet_until: Option<Instant>,
+}
+
+/// Exclusive use of a transport's client for one request.
+pub struct TransportGuard<'a, T>(MutexGuard<'a, Slot<T>>);
+
+impl<T> TransportGuard<'_, T> {
+    /// The client, unless no channel has it open.
+    pub fn client(&mut self) -> Option<&mut T> {
+        self.0.client.as_mut()
+    }
+
+    /// Keep the transport idle until `deadline`: the next request waits
+    /// for it.
+    pub fn quiet_until(&mut self, deadline: Instant) {
+        self.0.quiet_until = self.0.quiet_until.max(Some(deadline));
+    }
+}
+
+impl<T> SharedTransport<T> {
+    /// Transport identified by `key`, not yet open.
+    pub fn new(key: impl Into<String>) -> Self {
+        Self {
+            key: key.into(),
+            slot: Mutex::new(Slot {
+                client: None,
+                quiet_until: None,
+            }),
+            users: AtomicUsize::new(0),
+            requests: AtomicU64::new(0),
+            contended: AtomicU64::new(0),
+        }
+    }
+
+    /// Device path or address this transport stands for.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Start using the transport, opening the client with `open` unless
+    /// another channel already did.
+    ///
+    /// Every successful `attach()` must be paired with a `detach()`.
+    pub async fn attach<F, Fut>(&self, open: F) -> Result<()>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut slot = self.slot.lock().await;
+        if slot.client.is_none() {
+            slot.client = Some(open().await?);
+        }
+        self.users.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Stop using the transport.
+    ///
+    /// The last channel to detach gets the client back to close it.
+    pub async fn detach(&self) -> Option<T> {
+        let mut slot = self.slot.lock().await;
+        let users = self.users.load(Ordering::Relaxed).saturating_sub(1);
+        self.users.store(users, Ordering::Relaxed);
+        if users == 0 {
+            slot.client.take()
+        } else {
+            None
+        }
+    }
+
+    /// Wait for the client (and the end of a quiet period) and keep it
+    /// until the guard is dropped.
+    pub async fn acquire(&self) -> TransportGuard<'_, T> {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        let mut slot = match self.slot.try_lock() {
+            Ok(slot) => slot,
+            Err(_) => {
+                self.contended.fetch_add(1, Ordering::Relaxed);
+                self.slot.lock().await
+            }
+        };
+        if let Some(deadline) = slot.quiet_until.take() {
+            tokio::time::sleep_until(deadline).await;
+        }
+        TransportGuard(slot)
+    }
+
+    /// Current usage.
+    pub fn stats(&self) -> TransportStats {
+        TransportStats {
+            users: self.users.load(Ordering::Relaxed),
+            requests: self.requests.load(Ordering::Relaxed),
+            contended: self.contended.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Shared transports by key, kept while any channel holds them.
+#[derive(Debug)]
+pub struct TransportRegistry<T> {
+    transports: std::sync::Mutex<HashMap<String, Weak<SharedTransport<T>>>>,
+}
+
+impl<T> Default for TransportRegistry<T> {
+    fn default() -> Self {
+        Self {
+            transports: std::sync::Mutex::default(),
+        }
+    }
+}
+
+impl<T> TransportRegistry<T> {
+    /// Empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The transport for `key`, created if no channel holds it.
+    pub fn get(&self, key: &str) -> Arc<SharedTransport<T>> {
+        let mut transports = self.transports.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(transport) = transports.get(key).and_then(Weak::upgrade) {
+            return transport;
+        }
+        transports.retain(|_, t| t.strong_count() > 0);
+        let transport = Arc::new(SharedTransport::new(key));
+        transports.insert(key.to_string(), Arc::downgrade(&transport));
+        transport
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::error::GatewayError;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_open_once_close_last() {
+        let transport = SharedTransport::new("/dev/ttyUSB0");
+        let opened = AtomicUsize::new(0);
+        let open = || async {
+            opened.fetch_add(1, Ordering::Relaxed);
+            Ok(String::from("port"))
+        };
+        transport.attach(open).await.unwrap();
+        transport.attach(open).await.unwrap();
+        assert_eq!(opened.load(Ordering::Relaxed), 1);
+        assert_eq!(transport.stats().users, 2);
+        assert_eq!(
+            transport.acquire().await.client().map(|c| c.as_str()),
+            Some("port")
+        );
+
+        assert_eq!(transport.detach().await, None);
+        assert_eq!(transport.detach().await.as_deref(), Some("port"));
+        assert!(transport.acquire().await.client().is_none());
+
+        // A failed open leaves the transport closed and unused
+        let failed = transport
+            .attach(|| async { Err(GatewayError::connection("busy")) })
+            .await;
+        assert!(failed.is_err());
+        assert_eq!(transport.stats().users, 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_one_request_at_a_time_in_turn() {
+        let transport = Arc::new(SharedTransport::new("trunk"));
+        transport.attach(|| async { Ok(Vec::new()) }).await.unwrap();
+
+        // Three channels, each sending requests back to back
+        let tasks: Vec<_> = (0..3)
+            .map(|channel| {
+                let transport = Arc::clone(&transport);
+                tokio::spawn(async move {
+                    for _ in 0..4 {
+                        let mut guard = transport.acquire().await;
+                        let log: &mut Vec<u32> = guard.client().unwrap();
+                        log.push(channel);
+                        tokio::time::sleep(Duration::from_millis(10)).await;
+                        log.push(channel);
+                    }
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        let log = transport.detach().await.unwrap();
+        // Requests never interleave ...
+        assert!(log.chunks(2).all(|request| request[0] == request[1]));
+        // ... and the channels take turns
+        let order: Vec<u32> = log.chunks(2).map(|request| request[0]).collect();
+        assert_eq!(order, [0, 1, 2].repeat(4));
+
+        let stats = transport.stats();
+        assert_eq!(stats.requests, 12);
+        assert!(stats.contended >= 8);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_quiet_period() {
+        let transport = SharedTransport::new("/dev/ttyUSB0");
+        transport.attach(|| async { Ok(()) }).await.unwrap();
+
+        let start = Instant::now();
+        let mut guard = transport.acquire().await;
+        guard.quiet_until(start + Duration::from_millis(100));
+        guard.quiet_until(start + Duration::from_millis(50));
+        drop(guard);
+
+        drop(transport.acquire().await);
+        assert_eq!(start.elapsed(), Duration::from_millis(100));
+        // Waited out once
+        drop(transport.acquire().await);
+        assert_eq!(start.elapsed(), Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_registry() {
+        let registry: TransportRegistry<()> = TransportRegistry::new();
+        let first = registry.get("/dev/ttyUSB0");
+        let second = registry.get("/dev/ttyUSB0");
+        assert!(Arc::ptr_eq(&first, &second));
+        assert!(!Arc::ptr_eq(&first, &registry.get("/dev/ttyUSB1")));
+
+        drop((first, second));
+        let fresh = registry.get("/dev/ttyUSB0");
+        assert_eq!(fresh.stats(), TransportStats::default());
+    }
+}
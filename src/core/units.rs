@@ -0,0 +1,252 @@
+//! Engineering unit conversion.
+//!
+//! A point's [`TransformConfig::unit_conversion`](crate::core::point::TransformConfig::unit_conversion)
+//! converts its value from the unit the device reports to the unit
+//! consumers want, after scaling:
+//!
+//! ```toml
+//! [[channels.points]]
+//! id = 1
+//! name = "oil_temp"
+//! unit = "degC"
+//! address = "1:100:3"
+//! transform = { scale = 0.1, unit_conversion = { to = "degF" } }
+//! ```
+//!
+//! The source unit defaults to the point's `unit`, which then becomes the
+//! target unit. Units are resolved through a built-in table of four
+//! families; converting between families, or to or from an unknown unit,
+//! is a configuration error:
+//!
+//! | Family | Units |
+//! |---|---|
+//! | temperature | `K`, `degC` (`°C`, `C`), `degF` (`°F`, `F`) |
+//! | pressure | `Pa`, `hPa`, `kPa`, `MPa`, `mbar`, `bar`, `psi`, `atm`, `mmHg` |
+//! | flow | `m3/s`, `m3/h`, `L/s`, `L/min`, `L/h`, `gal/min` (`gpm`), `gal/h` |
+//! | energy | `J`, `kJ`, `MJ`, `Wh`, `kWh`, `MWh`, `BTU` |
+//!
+//! Gallons are US gallons. `m3` may also be written `m³` and `L` as `l`.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::error::{GatewayError, Result};
+
+/// Family of units that convert into each other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dimension {
+    /// Base unit K.
+    Temperature,
+    /// Base unit Pa.
+    Pressure,
+    /// Volumetric flow, base unit m³/s.
+    Flow,
+    /// Base unit J.
+    Energy,
+}
+
+impl fmt::Display for Dimension {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Temperature => "temperature",
+            Self::Pressure => "pressure",
+            Self::Flow => "flow",
+            Self::Energy => "energy",
+        })
+    }
+}
+
+const US_GALLON_M3: f64 = 3.785_411_784e-3;
+
+/// Known units: names, family, and `base = value * factor + offset`.
+const UNITS: &[(&[&str], Dimension, f64, f64)] = &[
+    (&["K"], Dimension::Temperature, 1.0, 0.0),
+    (
+        &["degC", "°C", "℃", "C"],
+        Dimension::Temperature,
+        1.0,
+        273.15,
+    ),
+    (
+        &["degF", "°F", "℉", "F"],
+        Dimension::Temperature,
+        5.0 / 9.0,
+        273.15 - 32.0 * 5.0 / 9.0,
+    ),
+    (&["Pa"], Dimension::Pressure, 1.0, 0.0),
+    (&["hPa"], Dimension::Pressure, 100.0, 0.0),
+    (&["kPa"], Dimension::Pressure, 1e3, 0.0),
+    (&["MPa"], Dimension::Pressure, 1e6, 0.0),
+    (&["mbar"], Dimension::Pressure, 100.0, 0.0),
+    (&["bar"], Dimension::Pressure, 1e5, 0.0),
+    (&["psi"], Dimension::Pressure, 6_894.757_293_168, 0.0),
+    (&["atm"], Dimension::Pressure, 101_325.0, 0.0),
+    (&["mmHg"], Dimension::Pressure, 133.322_387_415, 0.0),
+    (&["m3/s", "m³/s"], Dimension::Flow, 1.0, 0.0),
+    (&["m3/h", "m³/h"], Dimension::Flow, 1.0 / 3600.0, 0.0),
+    (&["L/s", "l/s"], Dimension::Flow, 1e-3, 0.0),
+    (&["L/min", "l/min"], Dimension::Flow, 1e-3 / 60.0, 0.0),
+    (&["L/h", "l/h"], Dimension::Flow, 1e-3 / 3600.0, 0.0),
+    (
+        &["gal/min", "gpm"],
+        Dimension::Flow,
+        US_GALLON_M3 / 60.0,
+        0.0,
+    ),
+    (&["gal/h"], Dimension::Flow, US_GALLON_M3 / 3600.0, 0.0),
+    (&["J"], Dimension::Energy, 1.0, 0.0),
+    (&["kJ"], Dimension::Energy, 1e3, 0.0),
+    (&["MJ"], Dimension::Energy, 1e6, 0.0),
+    (&["Wh"], Dimension::Energy, 3600.0, 0.0),
+    (&["kWh"], Dimension::Energy, 3.6e6, 0.0),
+    (&["MWh"], Dimension::Energy, 3.6e9, 0.0),
+    (&["BTU", "Btu"], Dimension::Energy, 1_055.055_852_62, 0.0),
+];
+
+/// Family of `unit`, if it is a known unit.
+pub fn dimension(unit: &str) -> Option<Dimension> {
+    lookup(unit).map(|(dimension, ..)| dimension)
+}
+
+fn lookup(unit: &str) -> Option<(Dimension, f64, f64)> {
+    let unit = unit.trim();
+    UNITS
+        .iter()
+        .find(|(names, ..)| names.contains(&unit))
+        .map(|&(_, dimension, factor, offset)| (dimension, factor, offset))
+}
+
+/// A resolved conversion: `to = from * scale + offset`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Conversion {
+    scale: f64,
+    offset: f64,
+}
+
+impl Conversion {
+    /// Conversion from unit `from` to unit `to`.
+    ///
+    /// Fails for unknown units and units of different families.
+    pub fn between(from: &str, to: &str) -> Result<Self> {
+        let unknown = |unit: &str| GatewayError::Config(format!("unknown unit '{}'", unit));
+        let (from_dim, from_factor, from_offset) = lookup(from).ok_or_else(|| unknown(from))?;
+        let (to_dim, to_factor, to_offset) = lookup(to).ok_or_else(|| unknown(to))?;
+        if from_dim != to_dim {
+            return Err(GatewayError::Config(format!(
+                "cannot convert {} ({}) to {} ({})",
+                from.trim(),
+                from_dim,
+                to.trim(),
+                to_dim
+            )));
+        }
+        Ok(Self {
+            scale: from_factor / to_factor,
+            offset: (from_offset - to_offset) / to_factor,
+        })
+    }
+
+    /// Convert a value.
+    pub fn apply(&self, value: f64) -> f64 {
+        value * self.scale + self.offset
+    }
+
+    /// Convert a value back.
+    pub fn reverse(&self, value: f64) -> f64 {
+        (value - self.offset) / self.scale
+    }
+}
+
+/// Unit conversion of a point, see the [module docs](self).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnitConversion {
+    /// Unit the value is in after scaling (default: the point's `unit`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+
+    /// Unit to convert to.
+    pub to: String,
+}
+
+impl UnitConversion {
+    /// Conversion from `from` to `to`.
+    pub fn new(from: impl Into<String>, to: impl Into<String>) -> Self {
+        Self {
+            from: Some(from.into()),
+            to: to.into(),
+        }
+    }
+
+    /// Conversion to `to` from the point's unit.
+    pub fn to(to: impl Into<String>) -> Self {
+        Self {
+            from: None,
+            to: to.into(),
+        }
+    }
+
+    /// Resolve against the built-in unit table.
+    ///
+    /// Fails if no source unit is set, or the units are unknown or of
+    /// different families.
+    pub fn resolve(&self) -> Result<Conversion> {
+        let from = self.from.as_deref().ok_or_else(|| {
+            GatewayError::Config(format!(
+                "no unit to convert to {} from (set the point's unit or `from`)",
+                self.to
+            ))
+        })?;
+        Conversion::between(from, &self.to)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert(value: f64, from: &str, to: &str) -> f64 {
+        Conversion::between(from, to).unwrap().apply(value)
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9 * expected.abs().max(1.0),
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_conversions() {
+        assert_close(convert(100.0, "degC", "degF"), 212.0);
+        assert_close(convert(-40.0, "°F", "C"), -40.0);
+        assert_close(convert(0.0, "degC", "K"), 273.15);
+        assert_close(convert(101.325, "kPa", "atm"), 1.0);
+        assert_close(convert(1.0, "bar", "psi"), 14.503_773_773_0);
+        assert_close(convert(3600.0, "L/h", "L/s"), 1.0);
+        assert_close(convert(1.0, "gal/min", "L/h"), 227.124_707_04);
+        assert_close(convert(1.0, "kWh", "MJ"), 3.6);
+        assert_close(convert(42.0, "kPa", "kPa"), 42.0);
+
+        let conversion = Conversion::between("degF", "degC").unwrap();
+        assert_close(conversion.reverse(conversion.apply(71.3)), 71.3);
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |from, to| Conversion::between(from, to).unwrap_err().to_string();
+        assert!(
+            error("degC", "psi").contains("cannot convert degC (temperature) to psi (pressure)")
+        );
+        assert!(error("furlong", "m").contains("unknown unit 'furlong'"));
+        assert!(UnitConversion::to("degF")
+            .resolve()
+            .unwrap_err()
+            .to_string()
+            .contains("no unit to convert to degF from"));
+        assert_eq!(dimension(" L/min "), Some(Dimension::Flow));
+        assert_eq!(dimension("RPM"), None);
+    }
+}
@@ -38,18 +38,81 @@
 // Submodules in gateway/ directory
 #[path = "gateway/address.rs"]
 mod address;
+#[path = "gateway/audit.rs"]
+pub mod audit;
+#[path = "gateway/authorization.rs"]
+mod authorization;
+#[path = "gateway/command.rs"]
+mod command;
 #[path = "gateway/config.rs"]
 mod config;
+#[path = "gateway/delta.rs"]
+mod delta;
+#[cfg(feature = "export")]
+#[path = "gateway/export.rs"]
+pub mod export;
 #[path = "gateway/factory.rs"]
 pub mod factory;
+#[path = "gateway/feedback.rs"]
+mod feedback;
+#[path = "gateway/heartbeat.rs"]
+pub mod heartbeat;
+#[cfg(feature = "http-api")]
+#[path = "gateway/http_api.rs"]
+pub mod http_api;
+#[path = "gateway/interlock.rs"]
+mod interlock;
+#[path = "gateway/jsonl.rs"]
+pub mod jsonl;
+#[cfg(feature = "cli")]
+#[path = "gateway/loader.rs"]
+pub mod loader;
+#[path = "gateway/orchestrator.rs"]
+mod orchestrator;
+#[path = "gateway/overrides.rs"]
+pub mod overrides;
+#[path = "gateway/params.rs"]
+pub mod params;
+#[path = "gateway/recording.rs"]
+pub mod recording;
+#[cfg(feature = "replication")]
+#[path = "gateway/replication.rs"]
+pub mod replication;
 #[path = "gateway/runtime.rs"]
 mod runtime;
+#[path = "gateway/schedule.rs"]
+mod schedule;
+#[path = "gateway/startup.rs"]
+mod startup;
+#[path = "gateway/template.rs"]
+pub mod template;
+#[path = "gateway/transport.rs"]
+pub mod transport;
+#[path = "gateway/validate.rs"]
+mod validate;
 #[path = "gateway/wrappers.rs"]
 pub mod wrappers;
 
 // Public exports
-pub use address::parse_address;
+pub use address::{format_modbus_address, parse_address};
+pub use authorization::Caller;
+pub use command::CommandKind;
 pub use config::{
-    ChannelConfig, ChannelModeConfig, ConfigError, GatewayConfig, GatewayGlobalConfig, PointDef,
+    ArchiveConfig, ArchiveFormat, AuditConfig, AuthorizationConfig, BackfillConfig, CallerDef,
+    ChangeDetectionConfig, ChannelConfig, ChannelModeConfig, CommandQueueConfig, ConfigError,
+    EventBufferConfig, FeedbackDef, FeedbackMapping, GatewayConfig, GatewayGlobalConfig,
+    HeartbeatConfig, HeartbeatPattern, HttpApiConfig, HttpApiUser, InterlockDef, JsonlConfig,
+    ModbusServerConfig, OpcUaServerConfig, OpcUaUser, OpcUaWritable, PermissionLevel, PointDef,
+    QueuePolicy, RegisterArea, RegisterMapping, ReplicationConfig, ReplicationRole, SafeStateDef,
+    SafeStateKind, StandbyMode, WatchdogConfig,
 };
-pub use runtime::{ChannelMode, ChannelRuntime};
+pub use factory::{register_protocol, ChannelBuilder, ChannelFactoryRegistry};
+pub use orchestrator::{
+    ChannelDiagnostics, GatewayRuntime, ReloadReport, ShutdownReport, WatchdogReport,
+    DEFAULT_RECONNECT_MAX, DEFAULT_RECONNECT_MIN,
+};
+pub use overrides::PointOverride;
+pub use runtime::{ChannelCapabilities, ChannelMode, ChannelRuntime};
+pub use template::{DeviceTemplate, TemplateOverrides};
+pub use transport::TransportConfig;
+pub use validate::ValidationError;
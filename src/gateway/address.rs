@@ -4,7 +4,8 @@
 
 use crate::core::error::{GatewayError, Result};
 use crate::core::point::{
-    Iec104Address, ModbusAddress, OpcUaAddress, ProtocolAddress, VirtualAddress,
+    ByteOrder, DataFormat, Iec104Address, ModbusAddress, MqttAddress, OpcUaAddress,
+    ProtocolAddress, VirtualAddress,
 };
 
 #[cfg(feature = "gpio")]
@@ -14,9 +15,24 @@ use crate::core::point::GpioAddress;
 ///
 /// # Address Formats
 ///
-/// - **Modbus**: `"slave_id:register"` or `"slave_id:register:function_code"`
+/// - **Modbus**: `"slave_id:register[:function_code][:format][:byte_order]"`
 ///   - Example: `"1:100"` → slave_id=1, register=100, function_code=3 (default)
 ///   - Example: `"1:100:4"` → slave_id=1, register=100, function_code=4
+///   - Example: `"1:100:3:float32:cdab"` → Float32 in CDAB byte order
+///   - Example: `"1:100:float32"` → function_code=3, Float32, ABCD (default)
+///   - Example: `"1:h100"` → register area prefix instead of a function code:
+///     `c` coils (1), `d` discrete inputs (2), `h` holding (3), `i` input (4)
+///   - Format: `bool`, `uint16` (default), `int16`, `uint32`, `int32`,
+///     `uint64`, `int64`, `float32`, `float64`, `string` (and the usual
+///     aliases such as `u16`, `f32`, `double`); coil and discrete input
+///     function codes default to `bool`
+///   - Example: `"1:i100:float32[24]"` → 24 consecutive Float32 values (48
+///     registers) read as one array point; a `[count]` suffix on the format
+///     makes an array
+///   - Byte order: `abcd` (default), `dcba`, `badc`, `cdab` (or `be`, `le`,
+///     `word_swap`, `byte_swap`)
+///   - Example: `"*:h100"` → broadcast point: written to unit 0 of all
+///     devices without a reply, never read (coils and holding registers only)
 ///
 /// - **IEC104**: `"ioa"` or `"ioa:type_id"`
 ///   - Example: `"1001"` → ioa=1001
@@ -26,6 +42,15 @@ use crate::core::point::GpioAddress;
 ///   - Example: `"ns=2;i=1234"` → namespace=2, node_id="i=1234"
 ///   - Example: `"ns=2;s=Temperature"` → namespace=2, node_id="s=Temperature"
 ///   - Example: `"i=1234"` → namespace=0, node_id="i=1234"
+///   - Example: `"ns=2;s=CellVoltages;range=0:23"` → elements 0-23 of an
+///     array node, read as one array point
+///
+/// - **MQTT**: `"topic[:json_path[:command_topic]]"`
+///   - Example: `"sensors/room1"` → the whole payload of `sensors/room1`
+///   - Example: `"sensors/room1:/env/temp"` → JSON pointer into the payload
+///   - Example: `"sensors/room1:env.temp"` → dotted path into the payload
+///   - Example: `"relay/1/state::relay/1/set"` → whole payload, commands
+///     published on `relay/1/set`
 ///
 /// - **CAN**: `"can_id:byte_offset:bit_pos:bit_len"`
 ///   - Example: `"0x100:0:0:16"` → can_id=0x100, byte_offset=0, bit_pos=0, bit_len=16
@@ -41,6 +66,7 @@ pub fn parse_address(protocol: &str, address: &str) -> Result<ProtocolAddress> {
         "modbus" => parse_modbus_address(address),
         "iec104" => parse_iec104_address(address),
         "opcua" => parse_opcua_address(address),
+        "mqtt" => parse_mqtt_address(address),
         "can" => parse_can_address(address),
         #[cfg(feature = "gpio")]
         "gpio" => parse_gpio_address(address),
@@ -52,50 +78,219 @@ pub fn parse_address(protocol: &str, address: &str) -> Result<ProtocolAddress> {
     }
 }
 
-/// Parse Modbus address: "slave_id:register" or "slave_id:register:function_code"
+/// Parse Modbus address: `"slave_id:register[:function_code][:format][:byte_order]"`.
+///
+/// The register may carry an area prefix instead of a function code. Format
+/// and byte order tokens are case-insensitive; bit areas default to `bool`,
+/// register areas to `uint16`, and the byte order to `abcd`. A `*` slave id
+/// makes a write-only broadcast point (unit 0), e.g. `"*:h100"`.
 fn parse_modbus_address(address: &str) -> Result<ProtocolAddress> {
-    let parts: Vec<&str> = address.split(':').collect();
+    let invalid = |reason: String| {
+        GatewayError::Config(format!(
+            "Invalid Modbus address '{}': {}. Expected 'slave_id:register[:function_code][:format][:byte_order]'",
+            address, reason
+        ))
+    };
 
-    match parts.len() {
-        2 => {
-            let slave_id = parts[0]
-                .parse::<u8>()
-                .map_err(|_| GatewayError::Config(format!("Invalid slave_id: {}", parts[0])))?;
-            let register = parts[1]
-                .parse::<u16>()
-                .map_err(|_| GatewayError::Config(format!("Invalid register: {}", parts[1])))?;
-
-            Ok(ProtocolAddress::Modbus(ModbusAddress::holding_register(
-                slave_id,
-                register,
-                crate::core::point::DataFormat::default(),
-            )))
+    let parts: Vec<&str> = address.split(':').map(str::trim).collect();
+    if !(2..=5).contains(&parts.len()) {
+        return Err(invalid(format!(
+            "expected 2 to 5 fields, got {}",
+            parts.len()
+        )));
+    }
+
+    let broadcast = parts[0] == "*";
+    let slave_id = if broadcast {
+        0
+    } else {
+        parts[0]
+            .parse::<u8>()
+            .map_err(|_| invalid(format!("invalid slave_id '{}'", parts[0])))?
+    };
+
+    // Optional register area prefix: c/d/h/i
+    let (area_code, register) = match parts[1].as_bytes().first() {
+        Some(c) if c.is_ascii_alphabetic() => {
+            let code = match c.to_ascii_lowercase() {
+                b'c' => 1,
+                b'd' => 2,
+                b'h' => 3,
+                b'i' => 4,
+                _ => {
+                    return Err(invalid(format!(
+                        "unknown register area '{}' (expected c, d, h or i)",
+                        &parts[1][..1]
+                    )))
+                }
+            };
+            (Some(code), &parts[1][1..])
         }
-        3 => {
-            let slave_id = parts[0]
-                .parse::<u8>()
-                .map_err(|_| GatewayError::Config(format!("Invalid slave_id: {}", parts[0])))?;
-            let register = parts[1]
-                .parse::<u16>()
-                .map_err(|_| GatewayError::Config(format!("Invalid register: {}", parts[1])))?;
-            let function_code = parts[2]
-                .parse::<u8>()
-                .map_err(|_| GatewayError::Config(format!("Invalid function_code: {}", parts[2])))?;
-
-            Ok(ProtocolAddress::Modbus(ModbusAddress {
-                slave_id,
-                register,
-                function_code,
-                format: crate::core::point::DataFormat::default(),
-                byte_order: crate::core::point::ByteOrder::default(),
-                bit_position: None,
-            }))
+        _ => (None, parts[1]),
+    };
+    let register = register
+        .parse::<u16>()
+        .map_err(|_| invalid(format!("invalid register '{}'", parts[1])))?;
+
+    let mut rest = parts[2..].iter().peekable();
+
+    let function_code = match rest.next_if(|t| t.parse::<u8>().is_ok()) {
+        Some(_) if area_code.is_some() => {
+            return Err(invalid(
+                "give either a register area prefix or a function code, not both".into(),
+            ))
         }
-        _ => Err(GatewayError::Config(format!(
-            "Invalid Modbus address format: {}. Expected 'slave_id:register' or 'slave_id:register:function_code'",
-            address
-        ))),
+        Some(token) => token.parse::<u8>().unwrap_or_default(),
+        None => area_code.unwrap_or(3),
+    };
+
+    let (format, count) = match rest.next_if(|t| format_token(t).is_some()) {
+        Some(token) => format_token(token).unwrap_or_default(),
+        None if matches!(function_code, 1 | 2 | 5 | 15) => (DataFormat::Bool, None),
+        None => (DataFormat::default(), None),
+    };
+
+    let byte_order = match rest.next() {
+        Some(token) => byte_order(token).ok_or_else(|| {
+            invalid(format!(
+                "unknown data format or byte order '{}' (formats: {}; byte orders: {})",
+                token,
+                DATA_FORMATS
+                    .iter()
+                    .map(|(t, _)| *t)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                BYTE_ORDERS
+                    .iter()
+                    .map(|(t, _)| *t)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))
+        })?,
+        None => ByteOrder::default(),
+    };
+
+    if let Some(token) = rest.next() {
+        return Err(invalid(format!("unexpected field '{}'", token)));
     }
+    if broadcast && matches!(function_code, 2 | 4) {
+        return Err(invalid(
+            "broadcast points must be coils or holding registers".into(),
+        ));
+    }
+
+    Ok(ProtocolAddress::Modbus(ModbusAddress {
+        slave_id,
+        register,
+        function_code,
+        format,
+        byte_order,
+        bit_position: None,
+        count,
+        broadcast,
+    }))
+}
+
+/// Format a Modbus address in the full shorthand accepted by
+/// [`parse_address()`], e.g. `"1:100:3:float32:cdab"`.
+///
+/// The bit position has no shorthand and is not included.
+pub fn format_modbus_address(address: &ModbusAddress) -> String {
+    let format = DATA_FORMATS
+        .iter()
+        .find(|(_, f)| *f == address.format)
+        .map_or("uint16", |(t, _)| *t);
+    let count = address
+        .count
+        .map(|count| format!("[{}]", count))
+        .unwrap_or_default();
+    let slave_id = if address.broadcast {
+        "*".to_string()
+    } else {
+        address.slave_id.to_string()
+    };
+    format!(
+        "{}:{}:{}:{}{}:{}",
+        slave_id,
+        address.register,
+        address.function_code,
+        format,
+        count,
+        address.byte_order.as_str().to_lowercase()
+    )
+}
+
+/// Data format tokens; the first one per format is canonical.
+const DATA_FORMATS: &[(&str, DataFormat)] = &[
+    ("bool", DataFormat::Bool),
+    ("uint16", DataFormat::UInt16),
+    ("int16", DataFormat::Int16),
+    ("uint32", DataFormat::UInt32),
+    ("int32", DataFormat::Int32),
+    ("uint64", DataFormat::UInt64),
+    ("int64", DataFormat::Int64),
+    ("float32", DataFormat::Float32),
+    ("float64", DataFormat::Float64),
+    ("float16", DataFormat::Float16),
+    ("string", DataFormat::String),
+    ("bcd16", DataFormat::Bcd16),
+    ("bcd32", DataFormat::Bcd32),
+];
+
+/// Aliases matching the serde aliases of `DataFormat`.
+const DATA_FORMAT_ALIASES: &[(&str, DataFormat)] = &[
+    ("boolean", DataFormat::Bool),
+    ("u16", DataFormat::UInt16),
+    ("i16", DataFormat::Int16),
+    ("u32", DataFormat::UInt32),
+    ("i32", DataFormat::Int32),
+    ("u64", DataFormat::UInt64),
+    ("i64", DataFormat::Int64),
+    ("f32", DataFormat::Float32),
+    ("float", DataFormat::Float32),
+    ("f64", DataFormat::Float64),
+    ("double", DataFormat::Float64),
+    ("f16", DataFormat::Float16),
+    ("half", DataFormat::Float16),
+];
+
+/// Byte order tokens, including the serde aliases of `ByteOrder`.
+const BYTE_ORDERS: &[(&str, ByteOrder)] = &[
+    ("abcd", ByteOrder::Abcd),
+    ("dcba", ByteOrder::Dcba),
+    ("badc", ByteOrder::Badc),
+    ("cdab", ByteOrder::Cdab),
+    ("be", ByteOrder::Abcd),
+    ("big_endian", ByteOrder::Abcd),
+    ("le", ByteOrder::Dcba),
+    ("little_endian", ByteOrder::Dcba),
+    ("word_swap", ByteOrder::Badc),
+    ("byte_swap", ByteOrder::Cdab),
+];
+
+fn data_format(token: &str) -> Option<DataFormat> {
+    let token = token.to_lowercase();
+    DATA_FORMATS
+        .iter()
+        .chain(DATA_FORMAT_ALIASES)
+        .find(|(t, _)| *t == token)
+        .map(|(_, f)| *f)
+}
+
+/// A data format token with an optional array count, e.g. `float32[24]`.
+fn format_token(token: &str) -> Option<(DataFormat, Option<u16>)> {
+    match token.strip_suffix(']').and_then(|t| t.split_once('[')) {
+        Some((format, count)) => Some((data_format(format)?, Some(count.trim().parse().ok()?))),
+        None => Some((data_format(token)?, None)),
+    }
+}
+
+fn byte_order(token: &str) -> Option<ByteOrder> {
+    let token = token.to_lowercase();
+    BYTE_ORDERS
+        .iter()
+        .find(|(t, _)| *t == token)
+        .map(|(_, o)| *o)
 }
 
 /// Parse IEC104 address: "ioa" or "ioa:type_id"
@@ -137,6 +332,22 @@ fn parse_iec104_address(address: &str) -> Result<ProtocolAddress> {
 
 /// Parse OPC UA address: "ns=N;i=ID" or "ns=N;s=Name" or "i=ID"
 fn parse_opcua_address(address: &str) -> Result<ProtocolAddress> {
+    let (address, index_range) = match address.rsplit_once(";range=") {
+        Some((node, range)) => {
+            let valid = !range.is_empty()
+                && range
+                    .chars()
+                    .all(|c| c.is_ascii_digit() || c == ':' || c == ',');
+            if !valid {
+                return Err(GatewayError::Config(format!(
+                    "Invalid OPC UA index range '{}': expected e.g. '0:23' or '2'",
+                    range
+                )));
+            }
+            (node, Some(range.to_string()))
+        }
+        None => (address, None),
+    };
     let mut namespace_index = 0u16;
     let mut node_id = address.to_string();
 
@@ -171,6 +382,22 @@ fn parse_opcua_address(address: &str) -> Result<ProtocolAddress> {
     Ok(ProtocolAddress::OpcUa(OpcUaAddress {
         node_id,
         namespace_index,
+        index_range,
+    }))
+}
+
+/// Parse MQTT address: `"topic[:json_path[:command_topic]]"`.
+///
+/// Empty fields after the topic are left unset.
+fn parse_mqtt_address(address: &str) -> Result<ProtocolAddress> {
+    let mut parts = address.splitn(3, ':').map(str::trim);
+    let topic = parts.next().unwrap_or_default();
+    let mut next = || parts.next().filter(|p| !p.is_empty()).map(str::to_string);
+    let (json_path, command_topic) = (next(), next());
+    Ok(ProtocolAddress::Mqtt(MqttAddress {
+        topic: topic.to_string(),
+        json_path,
+        command_topic,
     }))
 }
 
@@ -258,6 +485,117 @@ mod tests {
         }
     }
 
+    fn modbus(address: &str) -> ModbusAddress {
+        match parse_modbus_address(address).unwrap() {
+            ProtocolAddress::Modbus(m) => m,
+            other => panic!("Expected Modbus address, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_modbus_address_extended() {
+        let m = modbus("1:100:3:float32:cdab");
+        assert_eq!((m.slave_id, m.register, m.function_code), (1, 100, 3));
+        assert_eq!(m.format, DataFormat::Float32);
+        assert_eq!(m.byte_order, ByteOrder::Cdab);
+
+        // Function code, byte order are optional; tokens are case-insensitive
+        let m = modbus("1:100:Float64");
+        assert_eq!(m.function_code, 3);
+        assert_eq!(m.format, DataFormat::Float64);
+        assert_eq!(m.byte_order, ByteOrder::Abcd);
+        assert_eq!(modbus("1:100:4:I32:LE").byte_order, ByteOrder::Dcba);
+        assert_eq!(modbus("1:100:4:badc").format, DataFormat::UInt16);
+        assert_eq!(modbus("1:100:4:badc").byte_order, ByteOrder::Badc);
+        assert_eq!(modbus("1:100:4:bcd32:cdab").format, DataFormat::Bcd32);
+        assert_eq!(modbus("1:100:4:half").format, DataFormat::Float16);
+
+        // Arrays
+        let m = modbus("1:i100:f32[24]:cdab");
+        assert_eq!((m.format, m.count), (DataFormat::Float32, Some(24)));
+        assert_eq!(m.register_count(), 48);
+        assert_eq!(format_modbus_address(&m), "1:100:4:float32[24]:cdab");
+        assert_eq!(modbus("1:100").count, None);
+
+        // Broadcast points
+        let m = modbus("*:h100:f32");
+        assert!(m.broadcast);
+        assert_eq!((m.slave_id, m.function_code), (0, 3));
+        assert_eq!(format_modbus_address(&m), "*:100:3:float32:abcd");
+        assert!(!modbus("0:100").broadcast);
+
+        // Register area prefixes
+        for (address, code, format) in [
+            ("1:c5", 1, DataFormat::Bool),
+            ("1:D5", 2, DataFormat::Bool),
+            ("1:h5", 3, DataFormat::UInt16),
+            ("1:i5:f32", 4, DataFormat::Float32),
+            ("1:5:1", 1, DataFormat::Bool),
+        ] {
+            let m = modbus(address);
+            assert_eq!((m.register, m.function_code, m.format), (5, code, format));
+        }
+    }
+
+    #[test]
+    fn test_parse_modbus_address_errors() {
+        for (address, message) in [
+            ("1", "expected 2 to 5 fields"),
+            ("1:2:3:float32:abcd:x", "expected 2 to 5 fields"),
+            ("300:1", "invalid slave_id '300'"),
+            ("1:x100", "unknown register area 'x'"),
+            ("1:h", "invalid register 'h'"),
+            ("1:h100:3", "not both"),
+            (
+                "*:i100",
+                "broadcast points must be coils or holding registers",
+            ),
+            (
+                "1:100:3:float33",
+                "unknown data format or byte order 'float33'",
+            ),
+            ("1:100:cdab:float32", "unexpected field 'float32'"),
+            (
+                "1:100:float32[x]",
+                "unknown data format or byte order 'float32[x]'",
+            ),
+        ] {
+            let error = parse_modbus_address(address).unwrap_err().to_string();
+            assert!(error.contains(message), "{}: {}", address, error);
+        }
+    }
+
+    #[test]
+    fn test_modbus_address_round_trip() {
+        let formats = DATA_FORMATS.iter().map(|(_, f)| *f);
+        let orders = [
+            ByteOrder::Abcd,
+            ByteOrder::Dcba,
+            ByteOrder::Badc,
+            ByteOrder::Cdab,
+        ];
+        for format in formats {
+            for byte_order in orders {
+                let address = ModbusAddress {
+                    slave_id: 7,
+                    function_code: 4,
+                    register: 40,
+                    format,
+                    byte_order,
+                    bit_position: None,
+                    count: None,
+                    broadcast: false,
+                };
+                let shorthand = format_modbus_address(&address);
+                let parsed = modbus(&shorthand);
+                assert_eq!(parsed.format, format, "{}", shorthand);
+                assert_eq!(parsed.byte_order, byte_order, "{}", shorthand);
+                assert_eq!(format_modbus_address(&parsed), shorthand);
+                assert_eq!(modbus(&shorthand.to_uppercase()).format, format);
+            }
+        }
+    }
+
     #[test]
     fn test_parse_iec104_address() {
         let addr = parse_iec104_address("1001").unwrap();
@@ -274,11 +612,24 @@ mod tests {
         if let ProtocolAddress::OpcUa(o) = addr {
             assert_eq!(o.namespace_index, 2);
             assert_eq!(o.node_id, "i=1234");
+            assert_eq!(o.index_range, None);
         } else {
             panic!("Expected OPC UA address");
         }
     }
 
+    #[test]
+    fn test_parse_opcua_array_range() {
+        match parse_opcua_address("ns=2;s=Cells;range=0:23").unwrap() {
+            ProtocolAddress::OpcUa(o) => {
+                assert_eq!((o.namespace_index, o.node_id.as_str()), (2, "s=Cells"));
+                assert_eq!(o.index_range.as_deref(), Some("0:23"));
+            }
+            other => panic!("Expected OPC UA address, got {:?}", other),
+        }
+        assert!(parse_opcua_address("ns=2;s=Cells;range=a-b").is_err());
+    }
+
     #[test]
     fn test_parse_opcua_address_no_namespace() {
         let addr = parse_opcua_address("i=1234").unwrap();
@@ -290,6 +641,32 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_mqtt_address() {
+        let mqtt = |address: &str| match parse_address("mqtt", address).unwrap() {
+            ProtocolAddress::Mqtt(m) => m,
+            other => panic!("Expected MQTT address, got {:?}", other),
+        };
+        assert_eq!(mqtt("sensors/room1"), MqttAddress::new("sensors/room1"));
+        assert_eq!(
+            mqtt("sensors/room1:/env/temp"),
+            MqttAddress::new("sensors/room1").with_json_path("/env/temp")
+        );
+        assert_eq!(
+            mqtt("relay/1/state::relay/1/set"),
+            MqttAddress::new("relay/1/state").with_command_topic("relay/1/set")
+        );
+
+        let invalid = |address: &str| {
+            parse_address("mqtt", address)
+                .and_then(|a| a.validate())
+                .is_err()
+        };
+        assert!(invalid(""));
+        assert!(invalid("sensors/+/temp"));
+        assert!(invalid("relay/1/state::relay/#"));
+    }
+
     #[test]
     fn test_parse_virtual_address() {
         let addr = parse_address("virtual", "temperature").unwrap();
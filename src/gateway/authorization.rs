@@ -0,0 +1,224 @@
+//! Authorization of control and adjustment commands.
+//!
+//! Every command carries a [`Caller`], whose level comes from
+//! [`AuthorizationConfig`]. Before [`GatewayRuntime`](super::GatewayRuntime)
+//! checks interlocks, each commanded point's required [`PermissionLevel`] is
+//! compared with the caller's. If any command exceeds it, none of the
+//! commands is sent and the error lists each refused point, e.g.
+//!
+//! ```text
+//! Permission denied: scada (operate): point 3001 requires tune
+//! ```
+
+use serde::Serialize;
+
+use crate::core::data::PointId;
+use crate::core::error::{GatewayError, Result};
+
+use super::command::CommandKind;
+use super::config::{AuthorizationConfig, ChannelConfig, GatewayConfig, PermissionLevel};
+
+/// Who sends a command, with the level granted to them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Caller {
+    /// Caller identity, e.g. `http_api` or a configured user.
+    pub identity: String,
+
+    /// Granted level.
+    pub level: PermissionLevel,
+}
+
+impl Caller {
+    /// Identity of commands sent through the library API.
+    pub const LOCAL: &'static str = "local";
+
+    /// Caller with an explicit level.
+    pub fn new(identity: impl Into<String>, level: PermissionLevel) -> Self {
+        Self {
+            identity: identity.into(),
+            level,
+        }
+    }
+
+    /// Caller `identity` with the level `authorization` grants it
+    /// (`tune` without an authorization config).
+    pub fn resolve(
+        identity: impl Into<String>,
+        authorization: Option<&AuthorizationConfig>,
+    ) -> Self {
+        let identity = identity.into();
+        let level = authorization.map_or(PermissionLevel::Tune, |a| a.level_of(&identity));
+        Self { identity, level }
+    }
+
+    /// Check that this caller may send `kind` commands `(point_id, value)`
+    /// to `channel`.
+    ///
+    /// Returns `GatewayError::PermissionDenied` listing every refused point.
+    pub fn authorize(
+        &self,
+        channel: &ChannelConfig,
+        kind: CommandKind,
+        commands: &[(PointId, f64)],
+    ) -> Result<()> {
+        let denied: Vec<String> = commands
+            .iter()
+            .filter_map(|&(point_id, _)| {
+                let required = channel.required_level(point_id, kind);
+                (self.level < required).then(|| format!("point {} requires {}", point_id, required))
+            })
+            .collect();
+        if denied.is_empty() {
+            return Ok(());
+        }
+        #[cfg(feature = "tracing-support")]
+        tracing::warn!(
+            channel_id = channel.id,
+            caller = %self.identity,
+            level = %self.level,
+            denied = ?denied,
+            "Commands refused"
+        );
+        Err(GatewayError::PermissionDenied(format!(
+            "{} ({}): {}",
+            self.identity,
+            self.level,
+            denied.join("; ")
+        )))
+    }
+}
+
+impl ChannelConfig {
+    /// Level required to send a `kind` command to `point_id`: the point's
+    /// `permission`, else the channel's, else `operate` for controls and
+    /// `tune` for adjustments.
+    pub fn required_level(&self, point_id: PointId, kind: CommandKind) -> PermissionLevel {
+        self.points
+            .iter()
+            .find(|p| p.id == point_id)
+            .and_then(|p| p.permission)
+            .or(self.permission)
+            .unwrap_or(match kind {
+                CommandKind::Control => PermissionLevel::Operate,
+                CommandKind::Adjustment => PermissionLevel::Tune,
+            })
+    }
+}
+
+impl GatewayConfig {
+    /// Caller `identity` with the level this configuration grants it.
+    pub fn caller(&self, identity: impl Into<String>) -> Caller {
+        Caller::resolve(identity, self.gateway.authorization.as_ref())
+    }
+}
+
+/// Compare `given` with `expected` in a time that does not depend on where
+/// they differ.
+#[cfg(any(feature = "http-api", feature = "replication"))]
+pub(super) fn token_matches(given: &str, expected: &str) -> bool {
+    let (given, expected) = (given.as_bytes(), expected.as_bytes());
+    given.len() == expected.len()
+        && given
+            .iter()
+            .zip(expected)
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> GatewayConfig {
+        serde_json::from_value(serde_json::json!({
+            "gateway": {
+                "name": "auth",
+                "authorization": {
+                    "callers": [
+                        { "identity": "scada", "level": "operate" },
+                        { "identity": "ops", "level": "tune" }
+                    ]
+                }
+            },
+            "channels": [{
+                "id": 1,
+                "name": "feeder",
+                "protocol": "virtual",
+                "points": [
+                    { "id": 1, "name": "breaker", "address": "b" },
+                    { "id": 2, "name": "tap", "address": "t", "permission": "operate" },
+                    { "id": 3, "name": "trip", "address": "r", "permission": "tune" }
+                ]
+            }]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_levels() {
+        let config = config();
+        assert_eq!(config.caller("scada").level, PermissionLevel::Operate);
+        assert_eq!(config.caller("anyone").level, PermissionLevel::ReadOnly);
+        // Without an authorization section everyone may command.
+        let open = Caller::resolve("anyone", None);
+        assert_eq!(open.level, PermissionLevel::Tune);
+
+        let channel = &config.channels[0];
+        assert_eq!(
+            channel.required_level(1, CommandKind::Control),
+            PermissionLevel::Operate
+        );
+        assert_eq!(
+            channel.required_level(1, CommandKind::Adjustment),
+            PermissionLevel::Tune
+        );
+        assert_eq!(
+            channel.required_level(2, CommandKind::Adjustment),
+            PermissionLevel::Operate
+        );
+        assert_eq!(
+            channel.required_level(3, CommandKind::Control),
+            PermissionLevel::Tune
+        );
+    }
+
+    #[test]
+    fn test_authorize() {
+        let config = config();
+        let channel = &config.channels[0];
+        let scada = config.caller("scada");
+        assert!(scada
+            .authorize(channel, CommandKind::Control, &[(1, 1.0), (2, 0.0)])
+            .is_ok());
+        assert!(scada
+            .authorize(channel, CommandKind::Adjustment, &[(2, 5.0)])
+            .is_ok());
+
+        let error = scada
+            .authorize(channel, CommandKind::Control, &[(1, 1.0), (3, 1.0)])
+            .unwrap_err();
+        assert!(matches!(error, GatewayError::PermissionDenied(_)));
+        assert_eq!(
+            error.to_string(),
+            "Permission denied: scada (operate): point 3 requires tune"
+        );
+
+        let guest = config.caller("guest");
+        assert!(guest
+            .authorize(channel, CommandKind::Control, &[(1, 1.0)])
+            .is_err());
+        assert!(config
+            .caller("ops")
+            .authorize(channel, CommandKind::Control, &[(3, 1.0)])
+            .is_ok());
+    }
+
+    #[cfg(any(feature = "http-api", feature = "replication"))]
+    #[test]
+    fn test_token_matches() {
+        assert!(token_matches("s3cret", "s3cret"));
+        assert!(!token_matches("s3cret", "s3creT"));
+        assert!(!token_matches("s3cre", "s3cret"));
+        assert!(!token_matches("", "s3cret"));
+    }
+}
@@ -0,0 +1,462 @@
+//! Sending commands to a channel, directly or through its command queue.
+//!
+//! [`GatewayRuntime`](super::GatewayRuntime) hands control and adjustment
+//! commands to a [`CommandTarget`], which checks the channel's interlocks,
+//! writes the commands and starts the feedback checks of the written points
+//! (see [`feedback`](super::feedback)).
+//!
+//! # Limits
+//!
+//! Adjustments are checked against the `transform.min_value` /
+//! `max_value` of their point (bounds inclusive) before anything else, so
+//! a setpoint of 5000 instead of 50.00 never reaches the device. An
+//! out-of-range adjustment fails on its own, e.g.
+//!
+//! ```text
+//! point 2001: 5000 is outside the allowed range 0 to 100
+//! ```
+//!
+//! while the other commands of the call are sent. The failure is returned
+//! in the call's `WriteResult::failures` and written to the audit trail.
+//! With `clamp_adjustments` set on the channel, the adjustment is sent with
+//! the violated bound instead. Control commands are not checked.
+//!
+//! # Queueing
+//!
+//! With a `command_queue` (see [`CommandQueueConfig`]), the call returns as
+//! soon as the commands are queued, with the number accepted. A dispatcher
+//! task, running while the queue is not empty, sends them one at a time and
+//! at most `max_rate` per second, checking interlocks again just before each
+//! write. Each command's outcome is published as `DataEvent::CommandUpdate`:
+//! `CommandStage::Confirmed` once the channel wrote it, otherwise
+//! `CommandStage::Failed` with the error. Commands still queued when the
+//! channel stops are failed as well.
+//!
+//! A call that does not fit into `max_depth` is rejected as a whole with
+//! `GatewayError::QueueFull`. With the `coalesce` policy, a command for a
+//! point that is already queued only replaces the queued value.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+
+use crate::core::clock::SharedClock;
+use crate::core::data::PointId;
+use crate::core::error::{GatewayError, Result};
+use crate::core::point::TransformConfig;
+use crate::core::traits::{CommandOutcome, CommandStage, DataEvent, EventBus, WriteResult};
+use crate::store::DataStore;
+
+use super::config::{CommandQueueConfig, FeedbackDef, InterlockDef, QueuePolicy};
+use super::jsonl::{JsonlEvent, JsonlSink};
+use super::orchestrator::SharedChannel;
+use super::{feedback, interlock};
+
+/// Control or adjustment command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommandKind {
+    /// Control (switching) command, see `write_control()`.
+    Control,
+    /// Adjustment (setpoint) command, see `write_adjustment()`.
+    Adjustment,
+}
+
+/// What sending commands to one channel needs, cloned out of the runtime.
+#[derive(Clone)]
+pub(crate) struct CommandTarget {
+    pub(crate) channel_id: u32,
+    /// Channel id the points are stored under (the primary's for backups).
+    pub(crate) store_id: u32,
+    pub(crate) runtime: SharedChannel,
+    pub(crate) store: Arc<dyn DataStore>,
+    pub(crate) clock: SharedClock,
+    pub(crate) events: EventBus,
+    pub(crate) jsonl: Option<JsonlSink>,
+    pub(crate) interlocks: Vec<InterlockDef>,
+    /// Feedback checks by command point.
+    pub(crate) feedback: HashMap<PointId, FeedbackDef>,
+    /// Allowed adjustment ranges by point.
+    pub(crate) limits: HashMap<PointId, WriteLimits>,
+    /// Clamp out-of-range adjustments instead of failing them.
+    pub(crate) clamp: bool,
+}
+
+/// Inclusive range an adjustment of a point must fall into.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct WriteLimits {
+    pub(crate) min: Option<f64>,
+    pub(crate) max: Option<f64>,
+}
+
+impl WriteLimits {
+    /// Limits of a point with `transform`, if it sets any.
+    pub(crate) fn of(transform: &TransformConfig) -> Option<Self> {
+        let limits = Self {
+            min: transform.min_value,
+            max: transform.max_value,
+        };
+        (limits.min.is_some() || limits.max.is_some()).then_some(limits)
+    }
+
+    /// `value`, or with `clamp` the violated bound, if it may be sent.
+    ///
+    /// NaN is never within range and cannot be clamped.
+    fn check(&self, value: f64, clamp: bool) -> std::result::Result<f64, String> {
+        let below = self.min.filter(|min| value < *min);
+        let above = self.max.filter(|max| value > *max);
+        match below.or(above) {
+            None if !value.is_nan() => Ok(value),
+            Some(bound) if clamp => Ok(bound),
+            _ => Err(format!("{} is outside the allowed range {}", value, self)),
+        }
+    }
+}
+
+impl fmt::Display for WriteLimits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.min, self.max) {
+            (Some(min), Some(max)) => write!(f, "{} to {}", min, max),
+            (Some(min), None) => write!(f, "of at least {}", min),
+            (None, Some(max)) => write!(f, "of at most {}", max),
+            (None, None) => f.write_str("of any value"),
+        }
+    }
+}
+
+impl CommandTarget {
+    /// The `commands` that may be sent, clamped if configured. Adjustments
+    /// outside their point's limits are added to `rejected` instead.
+    pub(crate) fn check_limits(
+        &self,
+        kind: CommandKind,
+        commands: &[(u32, f64)],
+        rejected: &mut Vec<(PointId, String)>,
+    ) -> Vec<(u32, f64)> {
+        if kind == CommandKind::Control || self.limits.is_empty() {
+            return commands.to_vec();
+        }
+
+        let mut allowed = Vec::with_capacity(commands.len());
+        for &(point_id, value) in commands {
+            let Some(limits) = self.limits.get(&point_id) else {
+                allowed.push((point_id, value));
+                continue;
+            };
+            match limits.check(value, self.clamp) {
+                Ok(sent) => {
+                    #[cfg(feature = "tracing-support")]
+                    if sent != value {
+                        tracing::warn!(
+                            channel_id = self.channel_id,
+                            point_id,
+                            value,
+                            sent,
+                            "Adjustment clamped to its limits"
+                        );
+                    }
+                    allowed.push((point_id, sent));
+                }
+                Err(error) => {
+                    #[cfg(feature = "tracing-support")]
+                    tracing::warn!(
+                        channel_id = self.channel_id,
+                        point_id,
+                        error = %error,
+                        "Adjustment rejected"
+                    );
+                    rejected.push((point_id, error));
+                }
+            }
+        }
+        allowed
+    }
+
+    /// Check interlocks, write `commands` and start their feedback checks.
+    pub(crate) async fn send(
+        &self,
+        kind: CommandKind,
+        commands: &[(u32, f64)],
+    ) -> Result<WriteResult> {
+        self.check_interlocks(commands).await?;
+        let result = {
+            let mut runtime = self.runtime.lock().await;
+            match kind {
+                CommandKind::Control => runtime.write_control(commands).await?,
+                CommandKind::Adjustment => runtime.write_adjustment(commands).await?,
+            }
+        };
+        if result.success_count > 0 {
+            self.check_feedback(commands);
+        }
+        Ok(result)
+    }
+
+    /// Fail with `GatewayError::Interlock` if an interlock blocks a command.
+    pub(crate) async fn check_interlocks(&self, commands: &[(u32, f64)]) -> Result<()> {
+        interlock::check(
+            self.store.as_ref(),
+            self.channel_id,
+            &self.interlocks,
+            commands,
+            self.clock.now_utc(),
+        )
+        .await
+    }
+
+    /// Publish a command outcome to subscribers and the JSON Lines output.
+    fn publish(&self, outcome: CommandOutcome) {
+        if let Some(sink) = &self.jsonl {
+            sink.emit(JsonlEvent::command(self.channel_id, outcome.clone()));
+        }
+        self.events.publish(DataEvent::CommandUpdate(outcome));
+    }
+
+    /// Start the feedback checks of written commands.
+    fn check_feedback(&self, commands: &[(u32, f64)]) {
+        for &(point_id, value) in commands {
+            let Some(feedback) = self.feedback.get(&point_id).cloned() else {
+                continue;
+            };
+            let target = self.clone();
+            tokio::spawn(async move {
+                let outcome = feedback::verify(
+                    target.store.as_ref(),
+                    target.store_id,
+                    point_id,
+                    &feedback,
+                    value,
+                )
+                .await;
+                #[cfg(feature = "tracing-support")]
+                match &outcome.error {
+                    Some(error) => tracing::warn!(
+                        channel_id = target.channel_id,
+                        point_id,
+                        error = %error,
+                        "Command feedback timed out"
+                    ),
+                    None => tracing::debug!(
+                        channel_id = target.channel_id,
+                        point_id,
+                        "Command confirmed by feedback"
+                    ),
+                }
+                target.publish(outcome);
+            });
+        }
+    }
+}
+
+/// Commands waiting to be sent to a channel.
+#[derive(Default)]
+pub(crate) struct CommandQueue {
+    state: Mutex<QueueState>,
+}
+
+#[derive(Default)]
+struct QueueState {
+    pending: VecDeque<Queued>,
+    /// Target of the latest [`push()`](CommandQueue::push).
+    target: Option<CommandTarget>,
+    /// Minimum time between two writes.
+    interval: Duration,
+    /// A dispatcher task is draining the queue.
+    dispatching: bool,
+    /// When the last command was sent.
+    last_sent: Option<Instant>,
+    rejected: u64,
+    coalesced: u64,
+}
+
+struct Queued {
+    kind: CommandKind,
+    point_id: u32,
+    value: f64,
+}
+
+/// Queue depth and rejected and coalesced commands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct QueueCounters {
+    pub(crate) depth: usize,
+    pub(crate) rejected: u64,
+    pub(crate) coalesced: u64,
+}
+
+impl CommandQueue {
+    fn lock(&self) -> MutexGuard<'_, QueueState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Queue `commands` for `target` and make sure a dispatcher sends
+    /// them. Returns the number of commands accepted.
+    pub(crate) fn push(
+        self: &Arc<Self>,
+        config: &CommandQueueConfig,
+        target: CommandTarget,
+        kind: CommandKind,
+        commands: &[(u32, f64)],
+    ) -> Result<usize> {
+        let coalesce = config.policy == QueuePolicy::Coalesce;
+        let mut state = self.lock();
+        let queued = |state: &QueueState, point_id: u32| {
+            state
+                .pending
+                .iter()
+                .any(|q| q.kind == kind && q.point_id == point_id)
+        };
+
+        let mut seen = HashSet::new();
+        let added = commands
+            .iter()
+            .filter(|(point_id, _)| {
+                !coalesce || (!queued(&state, *point_id) && seen.insert(*point_id))
+            })
+            .count();
+        if state.pending.len() + added > config.max_depth {
+            state.rejected += commands.len() as u64;
+            #[cfg(feature = "tracing-support")]
+            tracing::warn!(
+                channel_id = target.channel_id,
+                depth = state.pending.len(),
+                rejected = commands.len(),
+                "Command queue full"
+            );
+            return Err(GatewayError::QueueFull(format!(
+                "channel {}: {} commands queued, {} more exceed the limit of {}",
+                target.channel_id,
+                state.pending.len(),
+                added,
+                config.max_depth
+            )));
+        }
+
+        for &(point_id, value) in commands {
+            let waiting = state
+                .pending
+                .iter_mut()
+                .find(|q| q.kind == kind && q.point_id == point_id);
+            match waiting {
+                Some(waiting) if coalesce => {
+                    waiting.value = value;
+                    state.coalesced += 1;
+                }
+                _ => state.pending.push_back(Queued {
+                    kind,
+                    point_id,
+                    value,
+                }),
+            }
+        }
+
+        state.target = Some(target);
+        state.interval = if config.max_rate > 0.0 {
+            Duration::from_secs_f64(1.0 / config.max_rate)
+        } else {
+            Duration::ZERO
+        };
+        if !state.dispatching {
+            state.dispatching = true;
+            tokio::spawn(Arc::clone(self).dispatch());
+        }
+        Ok(commands.len())
+    }
+
+    /// Send queued commands until the queue is empty.
+    async fn dispatch(self: Arc<Self>) {
+        loop {
+            let next_slot = {
+                let state = self.lock();
+                state.last_sent.map(|sent| sent + state.interval)
+            };
+            if let Some(slot) = next_slot {
+                tokio::time::sleep_until(slot).await;
+            }
+
+            let (command, target) = {
+                let mut state = self.lock();
+                let target = state.target.clone();
+                let (Some(command), Some(target)) = (state.pending.pop_front(), target) else {
+                    state.dispatching = false;
+                    return;
+                };
+                state.last_sent = Some(Instant::now());
+                (command, target)
+            };
+
+            let point_id = command.point_id;
+            let outcome = match target
+                .send(command.kind, &[(point_id, command.value)])
+                .await
+            {
+                Ok(result) => match result.failures.into_iter().next() {
+                    Some((_, error)) => failed(point_id, error),
+                    None if result.success_count > 0 => {
+                        CommandOutcome::new(point_id, CommandStage::Confirmed)
+                    }
+                    None => failed(point_id, "not written by the channel".into()),
+                },
+                Err(e) => failed(point_id, e.to_string()),
+            };
+            #[cfg(feature = "tracing-support")]
+            if let Some(error) = &outcome.error {
+                tracing::warn!(
+                    channel_id = target.channel_id,
+                    point_id,
+                    error = %error,
+                    "Queued command failed"
+                );
+            }
+            target.publish(outcome);
+        }
+    }
+
+    /// Fail every queued command, e.g. because the channel stops.
+    pub(crate) fn discard(&self, reason: &str) {
+        let (pending, target) = {
+            let mut state = self.lock();
+            (std::mem::take(&mut state.pending), state.target.clone())
+        };
+        let Some(target) = target else {
+            return;
+        };
+        for command in pending {
+            target.publish(failed(command.point_id, format!("discarded: {}", reason)));
+        }
+    }
+
+    /// Counters, `None` if no command was ever queued.
+    pub(crate) fn counters(&self) -> Option<QueueCounters> {
+        let state = self.lock();
+        if state.target.is_none() && state.rejected == 0 {
+            return None;
+        }
+        Some(QueueCounters {
+            depth: state.pending.len(),
+            rejected: state.rejected,
+            coalesced: state.coalesced,
+        })
+    }
+}
+
+impl std::fmt::Debug for CommandQueue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = self.lock();
+        f.debug_struct("CommandQueue")
+            .field("depth", &state.pending.len())
+            .field("dispatching", &state.dispatching)
+            .finish_non_exhaustive()
+    }
+}
+
+fn failed(point_id: u32, error: String) -> CommandOutcome {
+    CommandOutcome {
+        point_id,
+        stage: CommandStage::Failed,
+        error: Some(error),
+    }
+}
@@ -2,9 +2,18 @@
 //!
 //! Defines the TOML-friendly configuration format for the gateway.
 
+use std::collections::HashMap;
+use std::path::PathBuf;
+
 use serde::{Deserialize, Serialize};
 
-use crate::core::point::TransformConfig;
+use crate::core::alarm::AlarmConfig;
+use crate::core::data::{deserialize_point_id, PointId, ValueKind};
+use crate::core::event::OverflowPolicy;
+use crate::core::point::{ByteOrder, DataFormat, TransformConfig};
+
+use super::template::{DeviceTemplate, TemplateOverrides};
+use super::transport::TransportConfig;
 
 /// Gateway configuration (top-level).
 ///
@@ -30,18 +39,27 @@ use crate::core::point::TransformConfig;
 /// name = "Temperature"
 /// address = "1:100"
 /// ```
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct GatewayConfig {
     /// Gateway global settings.
     pub gateway: GatewayGlobalConfig,
 
+    /// Device templates by name (see [`template`](super::template)).
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub templates: HashMap<String, DeviceTemplate>,
+
+    /// Ports shared by several channels, by name (see
+    /// [`transport`](super::transport)).
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub transports: HashMap<String, TransportConfig>,
+
     /// Channel configurations.
     #[serde(default)]
     pub channels: Vec<ChannelConfig>,
 }
 
 /// Gateway global settings.
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct GatewayGlobalConfig {
     /// Gateway name for identification.
     pub name: String,
@@ -54,9 +72,751 @@ pub struct GatewayGlobalConfig {
     #[serde(default = "default_diagnostics_interval")]
     pub diagnostics_interval_ms: u64,
 
+    /// Most connect attempts and polls in flight at once across all
+    /// channels (unlimited if unset).
+    ///
+    /// Channels still run concurrently, each in a task of its own; beyond
+    /// the limit they wait their turn. Useful with hundreds of TCP
+    /// channels, whose simultaneous connects would overwhelm the network
+    /// or device firewalls.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_concurrent_polls: Option<usize>,
+
     /// Enable JSON Lines output for events.
     #[serde(default)]
     pub jsonl_output: bool,
+
+    /// Where and how JSON Lines output is written.
+    #[serde(default)]
+    pub jsonl: JsonlConfig,
+
+    /// Embedded HTTP API (requires the `http-api` feature).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http_api: Option<HttpApiConfig>,
+
+    /// Northbound Modbus TCP server (requires the `modbus-server` feature).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modbus_server: Option<ModbusServerConfig>,
+
+    /// Northbound OPC UA server (requires the `opcua-server` feature).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub opcua_server: Option<OpcUaServerConfig>,
+
+    /// Permission levels of command callers (every caller may send any
+    /// command if unset).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub authorization: Option<AuthorizationConfig>,
+
+    /// Persistent command audit history in the data store (disabled if
+    /// unset).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audit: Option<AuditConfig>,
+
+    /// Rolling CSV/Parquet archive of point changes (requires the `export`
+    /// feature).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archive: Option<ArchiveConfig>,
+
+    /// Store replication between a primary and a standby gateway (requires
+    /// the `replication` feature).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replication: Option<ReplicationConfig>,
+
+    /// Upper bound in milliseconds for each shutdown step of a channel
+    /// (stopping its task, writing its safe state, disconnecting).
+    #[serde(default = "default_shutdown_timeout")]
+    pub shutdown_timeout_ms: u64,
+
+    /// Channel watchdog (disabled if unset).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub watchdog: Option<WatchdogConfig>,
+
+    /// Heartbeat output for an external safety relay (disabled if unset).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub heartbeat: Option<HeartbeatConfig>,
+}
+
+/// Channel watchdog settings.
+///
+/// A channel that has not completed a successful poll, received an event or
+/// finished a connect attempt for `stall_timeout_ms` is stopped and rebuilt
+/// from its configuration. After `max_restarts` restarts within
+/// `restart_window_ms` it is parked in the `Error` state instead.
+///
+/// The stall timeout must be longer than the slowest poll interval, the
+/// maximum reconnect delay and the longest quiet period of event-driven
+/// channels.
+///
+/// ```toml
+/// [gateway.watchdog]
+/// stall_timeout_ms = 60000
+/// max_restarts = 3
+/// restart_window_ms = 600000
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct WatchdogConfig {
+    /// Inactivity after which a channel counts as stalled.
+    #[serde(default = "default_stall_timeout")]
+    pub stall_timeout_ms: u64,
+
+    /// Restarts allowed within `restart_window_ms` before parking.
+    #[serde(default = "default_max_restarts")]
+    pub max_restarts: u32,
+
+    /// Sliding window for `max_restarts`.
+    #[serde(default = "default_restart_window")]
+    pub restart_window_ms: u64,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            stall_timeout_ms: default_stall_timeout(),
+            max_restarts: default_max_restarts(),
+            restart_window_ms: default_restart_window(),
+        }
+    }
+}
+
+fn default_stall_timeout() -> u64 {
+    60_000
+}
+
+fn default_max_restarts() -> u32 {
+    3
+}
+
+fn default_restart_window() -> u64 {
+    600_000
+}
+
+/// Heartbeat output settings (see [`heartbeat`](super::heartbeat)).
+///
+/// The runtime drives control point `point_id` of channel `channel_id`
+/// (a GPIO output or a Modbus coil) every `period_ms`, so a safety relay
+/// watching it drops the plant to a safe state when the gateway hangs.
+/// The heartbeat stops while any of the `monitored_channels` has not
+/// returned data for `stall_timeout_ms`, so a gateway that still runs but
+/// no longer polls does not keep it alive either.
+///
+/// ```toml
+/// [gateway.heartbeat]
+/// channel_id = 9
+/// point_id = 1
+/// period_ms = 500
+/// pattern = "toggle"
+/// monitored_channels = [1, 2]
+/// stall_timeout_ms = 5000
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct HeartbeatConfig {
+    /// Channel of the heartbeat output.
+    pub channel_id: u32,
+
+    /// Control point of the heartbeat output.
+    #[serde(deserialize_with = "deserialize_point_id")]
+    pub point_id: PointId,
+
+    /// Interval between toggles or pulses.
+    #[serde(default = "default_heartbeat_period")]
+    pub period_ms: u64,
+
+    /// How the output is driven.
+    #[serde(default)]
+    pub pattern: HeartbeatPattern,
+
+    /// Length of a pulse (`pattern = "pulse"` only).
+    #[serde(default = "default_heartbeat_pulse")]
+    pub pulse_ms: u64,
+
+    /// Channels whose polling keeps the heartbeat alive (none: only a hung
+    /// runtime stops it).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub monitored_channels: Vec<u32>,
+
+    /// Time without data after which a monitored channel counts as stalled.
+    #[serde(default = "default_heartbeat_stall_timeout")]
+    pub stall_timeout_ms: u64,
+}
+
+impl HeartbeatConfig {
+    /// Toggled heartbeat on a point with default timing.
+    pub fn new(channel_id: u32, point_id: PointId) -> Self {
+        Self {
+            channel_id,
+            point_id,
+            period_ms: default_heartbeat_period(),
+            pattern: HeartbeatPattern::default(),
+            pulse_ms: default_heartbeat_pulse(),
+            monitored_channels: Vec::new(),
+            stall_timeout_ms: default_heartbeat_stall_timeout(),
+        }
+    }
+}
+
+/// How a heartbeat output is driven.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HeartbeatPattern {
+    /// Inverted every period.
+    #[default]
+    Toggle,
+    /// Set for `pulse_ms` at the start of every period.
+    Pulse,
+}
+
+fn default_heartbeat_period() -> u64 {
+    1000
+}
+
+fn default_heartbeat_pulse() -> u64 {
+    200
+}
+
+fn default_heartbeat_stall_timeout() -> u64 {
+    5000
+}
+
+/// Embedded HTTP API settings.
+///
+/// Requests with `token` act as caller `http_api`, requests with the token
+/// of one of the `users` as that user (see [`AuthorizationConfig`]).
+///
+/// ```toml
+/// [gateway.http_api]
+/// bind = "0.0.0.0:8080"
+/// token = "secret"
+/// users = [{ identity = "ops", token = "ops-secret" }]
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct HttpApiConfig {
+    /// Listen address (`host:port`).
+    pub bind: String,
+
+    /// Static bearer token (no auth if neither this nor `users` is set).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+
+    /// Callers with their own bearer token.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub users: Vec<HttpApiUser>,
+}
+
+/// Caller of the HTTP API with its own token.
+#[derive(Clone, PartialEq, Deserialize, Serialize)]
+pub struct HttpApiUser {
+    /// Caller identity, looked up in [`AuthorizationConfig::callers`].
+    pub identity: String,
+
+    /// Bearer token.
+    pub token: String,
+}
+
+impl std::fmt::Debug for HttpApiUser {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HttpApiUser")
+            .field("identity", &self.identity)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Permission levels of command callers.
+///
+/// Every command entry point names its caller: `http_api` (or the
+/// [`HttpApiUser`] identity), `modbus_server`, `opcua_server` (or the
+/// [`OpcUaUser`] username). A caller listed in `callers` gets its level,
+/// any other caller `default_level`. Commands sent through the library API
+/// (`local`) or with `igw write` (`cli`) come from whoever runs the
+/// gateway or holds its configuration, and always have `tune`. A command is
+/// refused with `GatewayError::PermissionDenied` if the caller's level is
+/// below the level its point requires (see [`PermissionLevel`]).
+///
+/// ```toml
+/// [gateway.authorization]
+/// default_level = "read_only"
+/// callers = [
+///     { identity = "scada", level = "operate" },
+///     { identity = "ops", level = "tune" },
+/// ]
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize, Serialize)]
+pub struct AuthorizationConfig {
+    /// Level of callers not listed in `callers`.
+    #[serde(default)]
+    pub default_level: PermissionLevel,
+
+    /// Levels by caller identity.
+    #[serde(default)]
+    pub callers: Vec<CallerDef>,
+}
+
+impl AuthorizationConfig {
+    /// Level granted to `identity`.
+    pub fn level_of(&self, identity: &str) -> PermissionLevel {
+        self.callers
+            .iter()
+            .find(|c| c.identity == identity)
+            .map_or(self.default_level, |c| c.level)
+    }
+}
+
+/// Level granted to one caller.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct CallerDef {
+    /// Caller identity.
+    pub identity: String,
+
+    /// Granted level.
+    pub level: PermissionLevel,
+}
+
+/// Permission level, from least to most privileged.
+///
+/// Controls require `operate` and adjustments `tune` unless the point (or
+/// else its channel) declares its own `permission`:
+///
+/// ```toml
+/// [[channels]]
+/// id = 1
+/// permission = "tune"       # every command to this channel needs tune
+///
+/// [[channels.points]]
+/// id = 2001
+/// permission = "operate"    # except for this point
+/// ```
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Deserialize, Serialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum PermissionLevel {
+    /// May read only.
+    #[default]
+    ReadOnly,
+    /// May send controls (e.g. operate breakers).
+    Operate,
+    /// May send controls and adjustments (setpoints).
+    Tune,
+}
+
+impl std::fmt::Display for PermissionLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::ReadOnly => "read_only",
+            Self::Operate => "operate",
+            Self::Tune => "tune",
+        })
+    }
+}
+
+/// Northbound Modbus TCP server settings.
+///
+/// Each register entry exposes the current value of one point. Entries must
+/// not overlap within an area; `coil` entries take one bit, register entries
+/// as many registers as their `format` needs.
+///
+/// ```toml
+/// [gateway.modbus_server]
+/// bind = "0.0.0.0:502"
+/// unit_id = 1
+///
+/// [[gateway.modbus_server.registers]]
+/// area = "holding"
+/// address = 0
+/// channel_id = 1
+/// point_id = 1001
+/// format = "float32"
+/// byte_order = "CDAB"
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ModbusServerConfig {
+    /// Listen address (`host:port`).
+    pub bind: String,
+
+    /// Unit id to answer (any unit id if unset).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit_id: Option<u8>,
+
+    /// Register layout.
+    #[serde(default)]
+    pub registers: Vec<RegisterMapping>,
+}
+
+/// Northbound OPC UA server settings.
+///
+/// Every enabled channel becomes a folder of the gateway's namespace with
+/// one variable per point. Only points listed in `writable` accept writes,
+/// which are sent to the channel as commands. Sessions are unencrypted
+/// (security policy `None`); clients log in anonymously (unless
+/// `anonymous = false`) or with one of the `users`, whose username is the
+/// caller of their writes (see [`AuthorizationConfig`]).
+///
+/// ```toml
+/// [gateway.opcua_server]
+/// bind = "0.0.0.0:4840"
+/// anonymous = false
+/// users = [{ username = "scada", password = "secret" }]
+///
+/// [[gateway.opcua_server.writable]]
+/// channel_id = 1
+/// point_id = 2001
+/// kind = "control"
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct OpcUaServerConfig {
+    /// Listen address (`host:port`).
+    pub bind: String,
+
+    /// Namespace of the point nodes.
+    #[serde(default = "default_opcua_namespace")]
+    pub namespace_uri: String,
+
+    /// Accept anonymous sessions.
+    #[serde(default = "default_true")]
+    pub anonymous: bool,
+
+    /// Username/password accounts.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub users: Vec<OpcUaUser>,
+
+    /// Points clients may write.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub writable: Vec<OpcUaWritable>,
+
+    /// Directory holding the server certificate (created on first start).
+    #[serde(default = "default_opcua_pki_dir")]
+    pub pki_dir: String,
+}
+
+fn default_opcua_namespace() -> String {
+    "urn:igw:gateway".into()
+}
+
+fn default_opcua_pki_dir() -> String {
+    "pki".into()
+}
+
+/// Account of the OPC UA server.
+#[derive(Clone, PartialEq, Deserialize, Serialize)]
+pub struct OpcUaUser {
+    /// Login name.
+    pub username: String,
+
+    /// Password.
+    pub password: String,
+}
+
+impl std::fmt::Debug for OpcUaUser {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OpcUaUser")
+            .field("username", &self.username)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Point the OPC UA server accepts writes for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct OpcUaWritable {
+    /// Channel the point belongs to.
+    pub channel_id: u32,
+
+    /// Point written to.
+    #[serde(deserialize_with = "deserialize_point_id")]
+    pub point_id: PointId,
+
+    /// Whether a write is a control or an adjustment command.
+    pub kind: SafeStateKind,
+}
+
+/// Modbus data area of a [`RegisterMapping`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RegisterArea {
+    /// Coils: read with FC01, written with FC05 as control commands.
+    Coil,
+    /// Holding registers: read with FC03, written with FC06/FC16.
+    #[default]
+    Holding,
+    /// Input registers: read-only, FC04.
+    Input,
+}
+
+/// One point exposed by the Modbus TCP server.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct RegisterMapping {
+    /// Data area.
+    #[serde(default)]
+    pub area: RegisterArea,
+
+    /// First register (or coil) address, zero-based.
+    pub address: u16,
+
+    /// Channel the point belongs to.
+    pub channel_id: u32,
+
+    /// Point whose value is exposed.
+    #[serde(deserialize_with = "deserialize_point_id")]
+    pub point_id: PointId,
+
+    /// Register encoding (ignored for coils).
+    #[serde(default)]
+    pub format: DataFormat,
+
+    /// Byte order of multi-register formats.
+    #[serde(default)]
+    pub byte_order: ByteOrder,
+}
+
+impl RegisterMapping {
+    /// Number of coils or registers the entry occupies.
+    pub fn register_count(&self) -> u16 {
+        match self.area {
+            RegisterArea::Coil => 1,
+            _ => self.format.register_count(),
+        }
+    }
+
+    /// Whether writes to the entry are control (rather than adjustment)
+    /// commands.
+    pub fn is_control(&self) -> bool {
+        self.area == RegisterArea::Coil || self.format == DataFormat::Bool
+    }
+}
+
+/// JSON Lines output settings (used when `jsonl_output` is enabled).
+///
+/// ```toml
+/// [gateway.jsonl]
+/// path = "/var/log/igw/events.jsonl"
+/// max_file_bytes = 10485760
+/// max_files = 5
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct JsonlConfig {
+    /// Output file; events go to stdout if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+
+    /// Rotate the file once it would grow beyond this size.
+    #[serde(default = "default_jsonl_max_file_bytes")]
+    pub max_file_bytes: u64,
+
+    /// Rotated files to keep (`events.jsonl.1` is the newest).
+    #[serde(default = "default_jsonl_max_files")]
+    pub max_files: usize,
+
+    /// Events buffered for the writer; the oldest are dropped beyond this.
+    #[serde(default = "default_jsonl_queue_capacity")]
+    pub queue_capacity: usize,
+}
+
+impl Default for JsonlConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            max_file_bytes: default_jsonl_max_file_bytes(),
+            max_files: default_jsonl_max_files(),
+            queue_capacity: default_jsonl_queue_capacity(),
+        }
+    }
+}
+
+fn default_jsonl_max_file_bytes() -> u64 {
+    10 * 1024 * 1024
+}
+
+fn default_jsonl_max_files() -> usize {
+    5
+}
+
+fn default_jsonl_queue_capacity() -> usize {
+    4096
+}
+
+/// Rolling archive settings (see [`export`](super::export)).
+///
+/// Every change of a point of the archived channels is appended to an
+/// hourly file per channel, `<dir>/<channel_id>/<YYYY-MM-DD_HH>.csv` (or
+/// `.parquet`), named after the UTC hour of the values it holds.
+///
+/// ```toml
+/// [gateway.archive]
+/// dir = "/var/lib/igw/archive"
+/// format = "parquet"
+/// channels = [1, 2]   # all channels if empty
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ArchiveConfig {
+    /// Output directory, created if missing.
+    pub dir: PathBuf,
+
+    /// File format.
+    #[serde(default)]
+    pub format: ArchiveFormat,
+
+    /// Channels to archive; all channels of the store if empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub channels: Vec<u32>,
+
+    /// Batches buffered for the writer; newer batches are dropped beyond this.
+    #[serde(default = "default_archive_queue_capacity")]
+    pub queue_capacity: usize,
+}
+
+impl ArchiveConfig {
+    /// Archive all channels as CSV into `dir`.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            format: ArchiveFormat::default(),
+            channels: Vec::new(),
+            queue_capacity: default_archive_queue_capacity(),
+        }
+    }
+}
+
+/// File format of the archive.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArchiveFormat {
+    /// Comma-separated values with a header line.
+    #[default]
+    Csv,
+    /// Apache Parquet, one row group per 8192 rows.
+    Parquet,
+}
+
+fn default_archive_queue_capacity() -> usize {
+    1024
+}
+
+/// Store replication settings (see [`replication`](super::replication)).
+///
+/// The primary streams every write to its data store to the standby, which
+/// keeps a copy of the primary's values so it can take over without
+/// waiting for its devices.
+///
+/// ```toml
+/// # On the primary, listening on the interface facing the standby
+/// [gateway.replication]
+/// role = "primary"
+/// address = "10.0.0.1:7900"
+/// token = "replication-secret"
+///
+/// # On the standby
+/// [gateway.replication]
+/// role = "standby"
+/// address = "10.0.0.1:7900"
+/// token = "replication-secret"
+/// ```
+#[derive(Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ReplicationConfig {
+    /// Which end of the link this gateway is.
+    pub role: ReplicationRole,
+
+    /// Address the primary listens on, or the primary's address on the
+    /// standby.
+    pub address: String,
+
+    /// Shared secret of the two gateways. Each end of a new connection
+    /// checks the other's before the snapshot is sent; the secret travels
+    /// unencrypted, so keep the link on a private network.
+    pub token: String,
+
+    /// Interval of the primary's heartbeats. The standby reconnects after
+    /// three intervals without a message, so use the same value on both
+    /// gateways.
+    #[serde(default = "default_replication_heartbeat_interval")]
+    pub heartbeat_interval_ms: u64,
+
+    /// Store writes queued per standby on the primary; a standby falling
+    /// further behind is resynchronized.
+    #[serde(default = "default_replication_queue_capacity")]
+    pub queue_capacity: usize,
+}
+
+impl ReplicationConfig {
+    /// Replication link with default settings.
+    pub fn new(
+        role: ReplicationRole,
+        address: impl Into<String>,
+        token: impl Into<String>,
+    ) -> Self {
+        Self {
+            role,
+            address: address.into(),
+            token: token.into(),
+            heartbeat_interval_ms: default_replication_heartbeat_interval(),
+            queue_capacity: default_replication_queue_capacity(),
+        }
+    }
+}
+
+impl std::fmt::Debug for ReplicationConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReplicationConfig")
+            .field("role", &self.role)
+            .field("address", &self.address)
+            .field("heartbeat_interval_ms", &self.heartbeat_interval_ms)
+            .field("queue_capacity", &self.queue_capacity)
+            .finish_non_exhaustive()
+    }
+}
+
+/// End of a replication link.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReplicationRole {
+    /// Streams its store to the standby.
+    Primary,
+    /// Keeps a copy of the primary's store.
+    Standby,
+}
+
+fn default_replication_heartbeat_interval() -> u64 {
+    1000
+}
+
+fn default_replication_queue_capacity() -> usize {
+    4096
+}
+
+/// Command audit history settings (see [`audit`](super::audit)).
+///
+/// Every command call is appended to the data store, which must support
+/// audit records (`MemoryStore`, `SqliteStore`), and can be queried by time
+/// range and channel.
+///
+/// ```toml
+/// [gateway.audit]
+/// retention_days = 365
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct AuditConfig {
+    /// Records older than this many days are deleted (kept forever if 0).
+    #[serde(default = "default_audit_retention_days")]
+    pub retention_days: u32,
+
+    /// Records buffered for the store; newer records are dropped (and
+    /// counted) beyond this.
+    #[serde(default = "default_audit_queue_capacity")]
+    pub queue_capacity: usize,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            retention_days: default_audit_retention_days(),
+            queue_capacity: default_audit_queue_capacity(),
+        }
+    }
+}
+
+fn default_audit_retention_days() -> u32 {
+    365
+}
+
+fn default_audit_queue_capacity() -> usize {
+    1024
 }
 
 fn default_poll_interval() -> u64 {
@@ -67,19 +827,35 @@ fn default_diagnostics_interval() -> u64 {
     5000
 }
 
+fn default_shutdown_timeout() -> u64 {
+    5000
+}
+
 impl Default for GatewayGlobalConfig {
     fn default() -> Self {
         Self {
             name: "IGW".to_string(),
             default_poll_interval_ms: default_poll_interval(),
             diagnostics_interval_ms: default_diagnostics_interval(),
+            max_concurrent_polls: None,
             jsonl_output: false,
+            jsonl: JsonlConfig::default(),
+            http_api: None,
+            modbus_server: None,
+            opcua_server: None,
+            authorization: None,
+            audit: None,
+            archive: None,
+            replication: None,
+            shutdown_timeout_ms: default_shutdown_timeout(),
+            watchdog: None,
+            heartbeat: None,
         }
     }
 }
 
 /// Channel configuration.
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct ChannelConfig {
     /// Channel unique identifier.
     pub id: u32,
@@ -87,7 +863,8 @@ pub struct ChannelConfig {
     /// Channel display name.
     pub name: String,
 
-    /// Protocol type: "modbus", "iec104", "opcua", "can", "gpio", "virtual".
+    /// Protocol type: "modbus", "iec104", "opcua", "mqtt", "can", "gpio", "virtual",
+    /// "replay".
     pub protocol: String,
 
     /// Whether this channel is enabled.
@@ -101,13 +878,310 @@ pub struct ChannelConfig {
     /// Polling interval override (uses gateway default if not set).
     pub poll_interval_ms: Option<u64>,
 
+    /// Shared medium this channel's device sits on, e.g. an RS-485 trunk.
+    ///
+    /// Polling channels naming the same bus are staggered across their
+    /// interval instead of all polling at once (see
+    /// [`GatewayRuntime`](super::GatewayRuntime)).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bus: Option<String>,
+
+    /// Fixed phase of the polls within the interval, in milliseconds,
+    /// instead of the automatic staggering of a `bus`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub poll_offset_ms: Option<u64>,
+
+    /// Shared transport this channel's device is reached through (see
+    /// [`transport`](super::transport)).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transport: Option<String>,
+
     /// Protocol-specific parameters (JSON object).
     #[serde(default)]
     pub parameters: serde_json::Value,
 
-    /// Point definitions.
+    /// Point definitions (after the template's, if any).
     #[serde(default)]
     pub points: Vec<PointDef>,
+
+    /// Device template providing points and default parameters (see
+    /// [`template`](super::template)).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+
+    /// Adjustments of the template's points for this channel.
+    #[serde(default, skip_serializing_if = "TemplateOverrides::is_empty")]
+    pub template_overrides: TemplateOverrides,
+
+    /// Values written to control/adjustment points when the gateway shuts
+    /// down, before the channel is disconnected.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub safe_state: Vec<SafeStateDef>,
+
+    /// Conditions checked before commands are sent to this channel's
+    /// points.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub interlocks: Vec<InterlockDef>,
+
+    /// Make this channel the backup of a redundant primary device.
+    ///
+    /// The backup takes over the primary's point ids while the primary is
+    /// down for `failover_after_ms` and hands them back once the primary
+    /// reconnects. A backup without points uses the primary's points.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backup_of: Option<u32>,
+
+    /// How a backup waits while the primary is healthy.
+    #[serde(default)]
+    pub standby: StandbyMode,
+
+    /// How long the primary must be down before its backup takes over.
+    #[serde(default = "default_failover_after")]
+    pub failover_after_ms: u64,
+
+    /// Channels that must be connected before this channel connects, e.g.
+    /// the GPIO channel powering its devices (see
+    /// [`GatewayRuntime`](super::GatewayRuntime)).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<u32>,
+
+    /// Time to wait after the `depends_on` channels connected before
+    /// connecting.
+    #[serde(default)]
+    pub startup_delay_ms: u64,
+
+    /// How long to wait for the `depends_on` channels before connecting
+    /// without them.
+    #[serde(default = "default_dependency_timeout")]
+    pub dependency_timeout_ms: u64,
+
+    /// Buffering of an event-driven channel's updates while the store
+    /// falls behind.
+    #[serde(default)]
+    pub event_buffer: EventBufferConfig,
+
+    /// Queue and rate-limit commands to this channel instead of writing
+    /// them directly.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command_queue: Option<CommandQueueConfig>,
+
+    /// Clamp adjustments outside their point's `transform.min_value` /
+    /// `max_value` to the violated bound instead of rejecting them (see
+    /// [`GatewayRuntime`](super::GatewayRuntime)).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub clamp_adjustments: bool,
+
+    /// Level required to command the points of this channel (see
+    /// [`PermissionLevel`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub permission: Option<PermissionLevel>,
+
+    /// Fill the point history from the device's own history after each
+    /// reconnect (see [`BackfillConfig`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backfill: Option<BackfillConfig>,
+
+    /// Store only the points of a poll that changed since they were last
+    /// stored (see [`ChangeDetectionConfig`]; event-driven channels ignore
+    /// it).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub change_detection: Option<ChangeDetectionConfig>,
+}
+
+fn default_failover_after() -> u64 {
+    5000
+}
+
+fn default_dependency_timeout() -> u64 {
+    60_000
+}
+
+/// Buffer between an event-driven channel and the store.
+///
+/// If the store stalls, up to `capacity` events are kept; beyond that the
+/// `overflow` policy applies (see [`EventBus`](crate::core::event)). The
+/// default keeps the newest value of every point.
+///
+/// ```toml
+/// [channels.event_buffer]
+/// capacity = 4096
+/// overflow = "drop_oldest"   # or "drop_newest", "coalesce_latest_per_point"
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct EventBufferConfig {
+    /// Events buffered before the overflow policy applies.
+    #[serde(default = "default_event_capacity")]
+    pub capacity: usize,
+
+    /// What to do with events beyond `capacity`.
+    #[serde(default = "default_event_overflow")]
+    pub overflow: OverflowPolicy,
+}
+
+impl Default for EventBufferConfig {
+    fn default() -> Self {
+        Self {
+            capacity: default_event_capacity(),
+            overflow: default_event_overflow(),
+        }
+    }
+}
+
+fn default_event_capacity() -> usize {
+    1024
+}
+
+fn default_event_overflow() -> OverflowPolicy {
+    OverflowPolicy::CoalesceLatestPerPoint
+}
+
+/// Command queue of a channel.
+///
+/// Commands are sent one at a time, at most `max_rate` per second (`0`:
+/// unlimited). Calls that would grow the queue beyond `max_depth` are
+/// rejected. With `policy = "coalesce"` a new command replaces the queued
+/// command for the same point (last writer wins); `"fifo"` sends every
+/// command in order.
+///
+/// ```toml
+/// [channels.command_queue]
+/// max_rate = 5.0
+/// max_depth = 32
+/// policy = "coalesce"
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct CommandQueueConfig {
+    /// Commands sent per second (`0` for no limit).
+    #[serde(default)]
+    pub max_rate: f64,
+
+    /// Commands waiting to be sent.
+    #[serde(default = "default_queue_depth")]
+    pub max_depth: usize,
+
+    /// How commands for an already queued point are handled.
+    #[serde(default)]
+    pub policy: QueuePolicy,
+}
+
+impl Default for CommandQueueConfig {
+    fn default() -> Self {
+        Self {
+            max_rate: 0.0,
+            max_depth: default_queue_depth(),
+            policy: QueuePolicy::default(),
+        }
+    }
+}
+
+fn default_queue_depth() -> usize {
+    64
+}
+
+/// Backfill of a channel's point history after a reconnect.
+///
+/// Once connected, the gateway asks the device for the samples of `points`
+/// (all points of the channel if empty) recorded since the last stored
+/// sample of each point, at most `max_gap_ms` back, and adds them to the
+/// store's point history with the quality the device reported (see
+/// [`DataStore::write_history()`](crate::store::DataStore::write_history)).
+/// Only channels with historical access (OPC UA HistoryRead) support it.
+///
+/// Backfilled samples do not replace the latest values, evaluate alarms or
+/// notify watchers. They are written to the JSON Lines output only with
+/// `forward = true`.
+///
+/// ```toml
+/// [channels.backfill]
+/// points = [1, 2]
+/// max_gap_ms = 86400000
+/// forward = false
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct BackfillConfig {
+    /// Points to backfill (empty for all points of the channel).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub points: Vec<PointId>,
+
+    /// How far back to backfill at most.
+    #[serde(default = "default_backfill_max_gap")]
+    pub max_gap_ms: u64,
+
+    /// Also write backfilled samples to the JSON Lines output.
+    #[serde(default)]
+    pub forward: bool,
+}
+
+impl Default for BackfillConfig {
+    fn default() -> Self {
+        Self {
+            points: Vec::new(),
+            max_gap_ms: default_backfill_max_gap(),
+            forward: false,
+        }
+    }
+}
+
+fn default_backfill_max_gap() -> u64 {
+    24 * 60 * 60 * 1000
+}
+
+/// Change detection on a polling channel's results (see
+/// [`delta`](super::delta)).
+///
+/// Points whose value and quality are the same as when they were last
+/// stored are left out of the batch written to the store, so the store, its
+/// watchers, alarms and the JSON Lines output only see what changed. The
+/// whole poll result is stored on the first poll after each connect and
+/// after every `full_refresh_ms` (never if 0), so consumers that only read
+/// what is written converge and timestamps do not grow stale forever.
+/// Dropped points are counted in `Diagnostics::extra`
+/// (`points_suppressed`).
+///
+/// ```toml
+/// [channels.change_detection]
+/// full_refresh_ms = 60000
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ChangeDetectionConfig {
+    /// Interval of full refreshes.
+    #[serde(default = "default_full_refresh")]
+    pub full_refresh_ms: u64,
+}
+
+impl Default for ChangeDetectionConfig {
+    fn default() -> Self {
+        Self {
+            full_refresh_ms: default_full_refresh(),
+        }
+    }
+}
+
+fn default_full_refresh() -> u64 {
+    60_000
+}
+
+/// How a command queue treats a new command for a point that already has a
+/// command waiting.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueuePolicy {
+    /// Queue it behind the waiting command.
+    #[default]
+    Fifo,
+    /// Replace the waiting command's value, keeping its place.
+    Coalesce,
+}
+
+/// How a backup channel waits while its primary is healthy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StandbyMode {
+    /// Stay connected without polling (fast takeover).
+    #[default]
+    Warm,
+    /// Stay disconnected until taking over.
+    Cold,
 }
 
 fn default_true() -> bool {
@@ -127,6 +1201,89 @@ pub enum ChannelModeConfig {
     Hybrid,
 }
 
+/// Value driven to an output point on shutdown.
+///
+/// ```toml
+/// [[channels.safe_state]]
+/// point_id = 2001
+/// kind = "control"
+/// value = 0
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct SafeStateDef {
+    /// Point to write (must be defined in the channel's `points`).
+    #[serde(deserialize_with = "deserialize_point_id")]
+    pub point_id: PointId,
+
+    /// Whether the point takes a control or an adjustment command.
+    pub kind: SafeStateKind,
+
+    /// Value to write (controls: `0` off, anything else on).
+    pub value: f64,
+}
+
+/// Command type of a [`SafeStateDef`] or an [`OpcUaWritable`] point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SafeStateKind {
+    /// Control (remote control / digital output).
+    Control,
+    /// Adjustment (setpoint / analog output).
+    Adjustment,
+}
+
+/// Condition that must hold before a command is sent to a point.
+///
+/// Checked against the store whenever a control or adjustment command for
+/// `point_id` is executed through the [`GatewayRuntime`](super::GatewayRuntime).
+/// The command is rejected, and nothing is sent to the device, if the
+/// checked point has no value, a quality other than `Good`, a value older
+/// than `max_age_ms` or a value failing the condition. All interlocks of a
+/// point must hold.
+///
+/// ```toml
+/// [[channels.interlocks]]
+/// point_id = 2001        # guarded control/adjustment point
+/// check_channel = 3      # channel of the checked point (default: this one)
+/// check_point = 1005     # point whose stored value is checked
+/// equals = 0             # or a range: min = 380.0, max = 420.0
+/// max_age_ms = 5000      # 0 = no age limit
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct InterlockDef {
+    /// Point whose commands are guarded.
+    #[serde(deserialize_with = "deserialize_point_id")]
+    pub point_id: PointId,
+
+    /// Channel of the checked point (`None` for this channel).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub check_channel: Option<u32>,
+
+    /// Point whose stored value is checked.
+    #[serde(deserialize_with = "deserialize_point_id")]
+    pub check_point: PointId,
+
+    /// Value the checked point must have.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub equals: Option<f64>,
+
+    /// Lowest allowed value (inclusive).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+
+    /// Highest allowed value (inclusive).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+
+    /// Maximum age of the checked value (0 = no limit).
+    #[serde(default = "default_interlock_max_age")]
+    pub max_age_ms: u64,
+}
+
+fn default_interlock_max_age() -> u64 {
+    10_000
+}
+
 /// Point definition with simplified address format.
 ///
 /// The `address` field uses a protocol-specific shorthand format:
@@ -136,14 +1293,28 @@ pub enum ChannelModeConfig {
 /// - CAN: "can_id:byte_offset:bit_pos:bit_len" (e.g., "0x100:0:0:16")
 /// - GPIO: "pin_number" (e.g., "17")
 /// - Virtual: "key" (e.g., "temperature")
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct PointDef {
     /// Point unique identifier.
-    pub id: u32,
+    ///
+    /// Numeric strings (`id = "101"`) are accepted for older configs.
+    #[serde(deserialize_with = "deserialize_point_id")]
+    pub id: PointId,
 
     /// Point display name.
     pub name: String,
 
+    /// Operator-facing label, if different from `name`.
+    #[serde(default)]
+    pub display_name: Option<String>,
+
+    /// Engineering unit of the transformed value.
+    ///
+    /// With a `transform.unit_conversion`, the unit converted from; the
+    /// point then reports the converted unit.
+    #[serde(default)]
+    pub unit: Option<String>,
+
     /// Protocol-specific address (shorthand format).
     pub address: String,
 
@@ -151,27 +1322,128 @@ pub struct PointDef {
     #[serde(default)]
     pub transform: TransformConfig,
 
+    /// Mark the point `Uncertain` once its value has not changed for this
+    /// long (flatlined sensor).
+    #[serde(default)]
+    pub max_age_ms: Option<u64>,
+
+    /// On a strict virtual channel, the kind of value accepted for the
+    /// point, e.g. `"float"` (integers are accepted for `float`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data_type: Option<ValueKind>,
+
+    /// On a virtual channel, mark the point `LastKnown` once it has not
+    /// been written for this long.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl_ms: Option<u64>,
+
+    /// On a virtual channel, mark the point `OutOfService` once it has not
+    /// been written for this long (later than `ttl_ms`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub out_of_service_ms: Option<u64>,
+
+    /// Limit alarms reported as signal points (see
+    /// [`alarm`](crate::core::alarm)).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alarms: Option<AlarmConfig>,
+
+    /// Check that commands to this point take effect on the device.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub feedback: Option<FeedbackDef>,
+
+    /// Level required to command this point, overriding the channel's
+    /// (see [`PermissionLevel`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub permission: Option<PermissionLevel>,
+
     /// Whether this point is enabled.
     #[serde(default = "default_true")]
     pub enabled: bool,
 }
 
+/// Feedback check of a command point.
+///
+/// After a successful write to the point through the
+/// [`GatewayRuntime`](super::GatewayRuntime), the runtime waits up to
+/// `timeout_ms` for the feedback point to be stored with `Good` quality and
+/// the expected value: the commanded value, or the `feedback` value of the
+/// matching `map` entry. The outcome is published as
+/// `DataEvent::CommandUpdate` with `CommandStage::Confirmed` or
+/// `CommandStage::FeedbackTimeout`.
+///
+/// ```toml
+/// [[channels.points]]
+/// id = 2001
+/// name = "breaker_close"
+/// address = "1:2001"
+/// # Position indication: 2 = closed, 1 = open
+/// feedback = { point_id = 1001, timeout_ms = 3000, map = [
+///     { command = 1, feedback = 2 },
+///     { command = 0, feedback = 1 },
+/// ] }
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct FeedbackDef {
+    /// Point reporting the device state.
+    #[serde(deserialize_with = "deserialize_point_id")]
+    pub point_id: PointId,
+
+    /// Channel of the feedback point (`None` for this channel).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<u32>,
+
+    /// Expected feedback per command value; unmapped commands expect
+    /// their own value.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub map: Vec<FeedbackMapping>,
+
+    /// How long to wait for the expected value.
+    #[serde(default = "default_feedback_timeout")]
+    pub timeout_ms: u64,
+}
+
+fn default_feedback_timeout() -> u64 {
+    5000
+}
+
+impl FeedbackDef {
+    /// Feedback value expected after commanding `value`.
+    pub fn expected(&self, value: f64) -> f64 {
+        self.map
+            .iter()
+            .find(|m| m.command == value)
+            .map_or(value, |m| m.feedback)
+    }
+}
+
+/// Entry of [`FeedbackDef::map`].
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct FeedbackMapping {
+    /// Commanded value.
+    pub command: f64,
+
+    /// Feedback value the command should lead to.
+    pub feedback: f64,
+}
+
 impl GatewayConfig {
-    /// Load configuration from a TOML file.
+    /// Load configuration from a TOML file, substituting `${VAR}`
+    /// references and merging the channels of `include`d files (see
+    /// [`loader`](super::loader)).
     ///
     /// Requires the `cli` feature.
     #[cfg(feature = "cli")]
     pub fn from_file(path: &std::path::Path) -> Result<Self, ConfigError> {
-        let content = std::fs::read_to_string(path).map_err(|e| ConfigError::Io(e.to_string()))?;
-        Self::parse(&content)
+        super::loader::load_file(path)
     }
 
-    /// Parse configuration from a TOML string.
+    /// Parse configuration from a TOML string, substituting `${VAR}`
+    /// references.
     ///
     /// Requires the `cli` feature.
     #[cfg(feature = "cli")]
     pub fn parse(s: &str) -> Result<Self, ConfigError> {
-        toml::from_str(s).map_err(|e| ConfigError::Parse(e.to_string()))
+        super::loader::parse_str(s)
     }
 
     /// Get enabled channels only.
@@ -191,6 +1463,12 @@ pub enum ConfigError {
 
     #[error("Validation error: {0}")]
     Validation(String),
+
+    #[error("{file}: environment variable '{variable}' is not set")]
+    MissingEnvVar { file: String, variable: String },
+
+    #[error("{file}: {message}")]
+    Include { file: String, message: String },
 }
 
 #[cfg(test)]
@@ -233,6 +1511,28 @@ scale = 0.1
         assert_eq!(config.channels[0].points[0].address, "1:100");
     }
 
+    #[test]
+    #[cfg(feature = "cli")]
+    fn test_parse_string_point_id() {
+        let toml_str = r#"
+[gateway]
+name = "Legacy"
+
+[[channels]]
+id = 1
+name = "Virtual"
+protocol = "virtual"
+
+[[channels.points]]
+id = "2001"
+name = "Setpoint"
+address = "setpoint"
+"#;
+
+        let config: GatewayConfig = toml::from_str(toml_str).unwrap();
+        assert_eq!(config.channels[0].points[0].id, 2001);
+    }
+
     #[test]
     fn test_channel_mode_default() {
         let mode = ChannelModeConfig::default();
@@ -0,0 +1,220 @@
+//! Change detection on poll results.
+//!
+//! A channel with `change_detection` configured (see
+//! [`ChangeDetectionConfig`]) passes every poll result through a
+//! [`DeltaFilter`] before it is stored. The filter remembers the value and
+//! quality each point was last stored with and drops the points that still
+//! have them, so a poll of mostly static registers writes only the few that
+//! moved.
+//!
+//! Some points are always written:
+//! - every point, on the first poll after a (re)connect or takeover and on
+//!   the first poll after each `full_refresh_ms`;
+//! - points with limit alarms, whose on/off delays need every sample;
+//! - points with an override in effect, and on the poll after it ended;
+//! - points whose previous read failed (they were stored as
+//!   `CommFailure` in between).
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use crate::core::data::{DataBatch, PointId, Value};
+use crate::core::point::PointConfig;
+use crate::core::quality::Quality;
+
+use super::config::ChangeDetectionConfig;
+
+/// What a point was last stored with.
+#[derive(Debug)]
+struct Written {
+    value: Value,
+    quality: Quality,
+    /// Written while overridden (the store holds the override's value).
+    overridden: bool,
+}
+
+/// Drops unchanged points from the poll results of one channel.
+#[derive(Debug)]
+pub(crate) struct DeltaFilter {
+    /// Interval of full refreshes (never if `None`).
+    full_refresh: Option<Duration>,
+    /// Store the next poll result in full.
+    refresh_due: bool,
+    /// When the next periodic full refresh is due.
+    next_refresh: Option<Instant>,
+    /// Points that are never dropped.
+    exempt: HashSet<PointId>,
+    written: HashMap<PointId, Written>,
+}
+
+impl DeltaFilter {
+    /// Filter for a channel with `points`.
+    pub(crate) fn new(config: &ChangeDetectionConfig, points: &[PointConfig]) -> Self {
+        Self {
+            full_refresh: (config.full_refresh_ms > 0)
+                .then(|| Duration::from_millis(config.full_refresh_ms)),
+            refresh_due: true,
+            next_refresh: None,
+            exempt: points
+                .iter()
+                .filter(|p| p.alarms.is_some())
+                .map(|p| p.id)
+                .collect(),
+            written: HashMap::new(),
+        }
+    }
+
+    /// The points of `batch` to store at `now`, and how many were dropped.
+    ///
+    /// `overridden` tells which points have an override in effect.
+    pub(crate) fn filter(
+        &mut self,
+        batch: Arc<DataBatch>,
+        now: Instant,
+        overridden: impl Fn(PointId) -> bool,
+    ) -> (Arc<DataBatch>, usize) {
+        let refresh = self.refresh_due || self.next_refresh.is_some_and(|next| now >= next);
+        if refresh {
+            self.refresh_due = false;
+            self.next_refresh = self.full_refresh.map(|interval| now + interval);
+        }
+
+        let mut changed = Vec::with_capacity(batch.len());
+        for point in batch.iter() {
+            let overridden = overridden(point.id);
+            let unchanged = !refresh
+                && !overridden
+                && !self.exempt.contains(&point.id)
+                && self.written.get(&point.id).is_some_and(|w| {
+                    !w.overridden && w.quality == point.quality && w.value == point.value
+                });
+            if unchanged {
+                continue;
+            }
+            self.written.insert(
+                point.id,
+                Written {
+                    value: point.value.clone(),
+                    quality: point.quality,
+                    overridden,
+                },
+            );
+            changed.push(point.clone());
+        }
+
+        let dropped = batch.len() - changed.len();
+        if dropped == 0 {
+            return (batch, 0);
+        }
+        (Arc::new(DataBatch::from_points(changed)), dropped)
+    }
+
+    /// Forget what `point_ids` were stored with, e.g. after they were
+    /// stored with another quality, so their next sample is written.
+    pub(crate) fn forget(&mut self, point_ids: impl IntoIterator<Item = PointId>) {
+        for id in point_ids {
+            self.written.remove(&id);
+        }
+    }
+
+    /// Forget everything, so the next poll is written in full.
+    pub(crate) fn reset(&mut self) {
+        self.written.clear();
+        self.refresh_due = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::alarm::{AlarmConfig, AlarmLimit};
+    use crate::core::data::DataPoint;
+    use crate::core::point::{ProtocolAddress, VirtualAddress};
+
+    fn filter(full_refresh_ms: u64) -> DeltaFilter {
+        let point = |id| PointConfig::new(id, ProtocolAddress::Virtual(VirtualAddress::new("t")));
+        let points = [
+            point(1),
+            point(2),
+            point(3).with_alarms(AlarmConfig {
+                high: Some(AlarmLimit::new(103, 80.0)),
+                ..AlarmConfig::default()
+            }),
+        ];
+        DeltaFilter::new(&ChangeDetectionConfig { full_refresh_ms }, &points)
+    }
+
+    fn poll(values: &[(PointId, f64)]) -> Arc<DataBatch> {
+        Arc::new(
+            values
+                .iter()
+                .map(|&(id, value)| DataPoint::new(id, value))
+                .collect(),
+        )
+    }
+
+    fn ids(batch: &DataBatch) -> Vec<PointId> {
+        batch.iter().map(|p| p.id).collect()
+    }
+
+    #[test]
+    fn test_only_changes_pass() {
+        let mut filter = filter(0);
+        let now = Instant::now();
+        let none = |_| false;
+
+        let (batch, dropped) = filter.filter(poll(&[(1, 1.0), (2, 2.0), (3, 3.0)]), now, none);
+        assert_eq!((ids(&batch), dropped), (vec![1, 2, 3], 0));
+
+        // Unchanged points are dropped, the alarm point never is
+        let (batch, dropped) = filter.filter(poll(&[(1, 1.0), (2, 2.5), (3, 3.0)]), now, none);
+        assert_eq!((ids(&batch), dropped), (vec![2, 3], 1));
+
+        // A quality change is a change
+        let bad = Arc::new(DataBatch::from_points(vec![
+            DataPoint::new(1, 1.0).with_quality(Quality::Uncertain)
+        ]));
+        let (batch, _) = filter.filter(bad, now, none);
+        assert_eq!(ids(&batch), vec![1]);
+
+        // Forgotten points are written again
+        filter.forget([2]);
+        let (batch, dropped) = filter.filter(poll(&[(2, 2.5)]), now, none);
+        assert_eq!((ids(&batch), dropped), (vec![2], 0));
+
+        filter.reset();
+        let (batch, _) = filter.filter(poll(&[(1, 1.0), (2, 2.5)]), now, none);
+        assert_eq!(ids(&batch), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_full_refresh() {
+        let mut filter = filter(1000);
+        let start = Instant::now();
+        let none = |_| false;
+        let values = [(1, 1.0), (2, 2.0)];
+
+        assert_eq!(filter.filter(poll(&values), start, none).1, 0);
+        let at = |ms| start + Duration::from_millis(ms);
+        assert_eq!(filter.filter(poll(&values), at(999), none).1, 2);
+        assert_eq!(filter.filter(poll(&values), at(1000), none).1, 0);
+        assert_eq!(filter.filter(poll(&values), at(1500), none).1, 2);
+        assert_eq!(filter.filter(poll(&values), at(2000), none).1, 0);
+    }
+
+    #[test]
+    fn test_overridden_points_pass() {
+        let mut filter = filter(0);
+        let now = Instant::now();
+        filter.filter(poll(&[(1, 1.0)]), now, |_| false);
+
+        // While overridden, and once more after the override ended
+        assert_eq!(filter.filter(poll(&[(1, 1.0)]), now, |_| true).1, 0);
+        assert_eq!(filter.filter(poll(&[(1, 1.0)]), now, |_| true).1, 0);
+        assert_eq!(filter.filter(poll(&[(1, 1.0)]), now, |_| false).1, 0);
+        assert_eq!(filter.filter(poll(&[(1, 1.0)]), now, |_| false).1, 1);
+    }
+}
@@ -0,0 +1,798 @@
+//! CSV and Parquet export of point values.
+//!
+//! [`DataBatch::to_csv()`] writes a batch as CSV that spreadsheets and
+//! pandas open directly:
+//!
+//! ```text
+//! timestamp,point_id,value,quality
+//! 2024-05-01T03:00:00.000Z,1001,21.5,good
+//! 2024-05-01T03:00:00.000Z,1002,true,good
+//! ```
+//!
+//! Timestamps are UTC with milliseconds. Null values are empty fields,
+//! bytes and arrays are written as JSON.
+//!
+//! # Archive
+//!
+//! An [`Archiver`] watches channels of a [`DataStore`] and appends every
+//! change of their points to one file per channel and UTC hour,
+//! `<dir>/<channel_id>/2024-05-01_03.csv` (or `.parquet`), with the columns
+//! `timestamp, point_id, name, value, quality`. Configure it with
+//! `[gateway.archive]` (see [`ArchiveConfig`]).
+//!
+//! Only changes are archived, with the rules of
+//! [`DataStore::watch()`]: a point that keeps reporting the same value is
+//! written once. Like JSON Lines output, archiving never blocks the store:
+//! batches are queued and written by a dedicated thread, and batches that do
+//! not fit the queue are dropped and counted in [`Archiver::dropped()`].
+//!
+//! In Parquet files `value` is a `Float64` column (booleans as 0 and 1);
+//! strings, bytes and arrays are archived as null, so use CSV for channels
+//! with such points.
+//!
+//! # Durability
+//!
+//! The file of an hour is closed once the hour is over: it is flushed and
+//! fsynced, and its directory entry synced, before the next file is opened,
+//! so power loss can only affect the file of the current hour.
+//!
+//! - CSV is flushed to the OS after every batch. After a crash, at most the
+//!   last line of the current file is cut short; a restart within the same
+//!   hour appends to it.
+//! - A Parquet file is only readable once its footer is written, so the
+//!   current hour is written to `<name>.parquet.partial` and renamed to
+//!   `<name>.parquet` once closed. A restart within the same hour starts
+//!   `<name>.1.parquet`.
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use arrow_array::{
+    ArrayRef, Float64Array, RecordBatch, StringArray, TimestampMillisecondArray, UInt32Array,
+};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use chrono::{DateTime, DurationRound, SecondsFormat, TimeDelta, Utc};
+use futures::stream::{self, BoxStream, SelectAll, StreamExt};
+use parquet::arrow::ArrowWriter;
+use parquet::file::properties::WriterProperties;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::task::JoinHandle;
+
+use crate::core::data::{DataBatch, DataPoint, PointId, Value};
+use crate::core::error::{GatewayError, Result};
+use crate::core::quality::Quality;
+use crate::core::traits::DataEvent;
+use crate::store::DataStore;
+
+use super::config::{ArchiveConfig, ArchiveFormat};
+
+/// How often the archiver checks whether the current hour is over.
+const TICK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Rows per Parquet row group.
+const ROW_GROUP_ROWS: usize = 8192;
+
+/// Header of archive CSV files.
+const ARCHIVE_COLUMNS: [&str; 5] = ["timestamp", "point_id", "name", "value", "quality"];
+
+impl DataBatch {
+    /// Write the batch as CSV, with a header line.
+    ///
+    /// Columns are `timestamp, point_id, value` and, if `include_quality`,
+    /// `quality`. See the [`export`](crate::gateway::export) module for how
+    /// values are formatted.
+    pub fn to_csv<W: Write>(&self, writer: W, include_quality: bool) -> Result<()> {
+        let mut csv = csv::Writer::from_writer(writer);
+        let columns = if include_quality { 4 } else { 3 };
+        csv.write_record(&["timestamp", "point_id", "value", "quality"][..columns])
+            .map_err(csv_error)?;
+        for point in self.iter() {
+            let record = [
+                timestamp_text(&point.timestamp),
+                point.id.to_string(),
+                value_text(&point.value),
+                quality_name(point.quality),
+            ];
+            csv.write_record(&record[..columns]).map_err(csv_error)?;
+        }
+        csv.flush()?;
+        Ok(())
+    }
+}
+
+/// Rolling archive of the point changes of a store.
+///
+/// Dropping the archiver stops watching the store; call
+/// [`stop()`](Self::stop) to also wait until the open files are closed.
+#[derive(Debug)]
+pub struct Archiver {
+    task: JoinHandle<()>,
+    writer: JoinHandle<()>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl Archiver {
+    /// Watch the configured channels of `store` and start the writer
+    /// thread.
+    ///
+    /// Fails if the output directory cannot be created or the store does
+    /// not support watching. Must be called from within a Tokio runtime.
+    pub async fn start(config: &ArchiveConfig, store: Arc<dyn DataStore>) -> Result<Self> {
+        fs::create_dir_all(&config.dir)?;
+        let channels = if config.channels.is_empty() {
+            store.channels().await?
+        } else {
+            config.channels.clone()
+        };
+        let mut events = SelectAll::new();
+        for &channel_id in &channels {
+            let watch = store.watch(channel_id, &[])?;
+            events.push(
+                stream::unfold(watch, move |mut watch| async move {
+                    let event = watch.recv().await?;
+                    Some(((channel_id, event), watch))
+                })
+                .boxed(),
+            );
+        }
+
+        let (tx, rx) = mpsc::channel(config.queue_capacity.max(1));
+        let dropped = Arc::new(AtomicU64::new(0));
+        let writer = FileWriter::new(&config.dir, config.format);
+        Ok(Self {
+            task: tokio::spawn(watch_channels(
+                store,
+                channels,
+                events,
+                tx,
+                Arc::clone(&dropped),
+            )),
+            writer: tokio::task::spawn_blocking(move || writer.run(rx)),
+            dropped,
+        })
+    }
+
+    /// Number of batches dropped because the writer fell behind.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Stop watching and wait until every open file is closed and synced.
+    pub async fn stop(mut self) {
+        self.task.abort();
+        let _ = (&mut self.writer).await;
+    }
+}
+
+impl Drop for Archiver {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// What the writer thread is asked to do.
+#[derive(Debug)]
+enum Entry {
+    /// Append a batch of changes received at `at`.
+    Batch {
+        channel_id: u32,
+        at: DateTime<Utc>,
+        batch: Arc<DataBatch>,
+        names: Arc<HashMap<PointId, String>>,
+    },
+    /// Close the files of hours before `at`.
+    Tick(DateTime<Utc>),
+}
+
+type ChannelEvents = SelectAll<BoxStream<'static, (u32, DataEvent)>>;
+
+/// Forward store changes to the writer, reloading point names every hour.
+async fn watch_channels(
+    store: Arc<dyn DataStore>,
+    channels: Vec<u32>,
+    mut events: ChannelEvents,
+    tx: mpsc::Sender<Entry>,
+    dropped: Arc<AtomicU64>,
+) {
+    let mut names = point_names(store.as_ref(), &channels).await;
+    let mut hour = hour_of(Utc::now());
+    let mut tick = tokio::time::interval(TICK_INTERVAL);
+    loop {
+        let entry = tokio::select! {
+            event = events.next() => match event {
+                Some((channel_id, DataEvent::DataUpdate(batch))) => Entry::Batch {
+                    channel_id,
+                    at: Utc::now(),
+                    batch,
+                    names: names.get(&channel_id).cloned().unwrap_or_default(),
+                },
+                Some((_channel_id, DataEvent::Error(_e))) => {
+                    #[cfg(feature = "tracing-support")]
+                    tracing::warn!(channel_id = _channel_id, "Archive missed changes: {}", _e);
+                    continue;
+                }
+                Some(_) => continue,
+                None => return,
+            },
+            _ = tick.tick() => {
+                let now = Utc::now();
+                if hour_of(now) != hour {
+                    hour = hour_of(now);
+                    names = point_names(store.as_ref(), &channels).await;
+                }
+                Entry::Tick(now)
+            }
+        };
+        if let Err(TrySendError::Full(_)) = tx.try_send(entry) {
+            dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+async fn point_names(
+    store: &dyn DataStore,
+    channels: &[u32],
+) -> HashMap<u32, Arc<HashMap<PointId, String>>> {
+    let mut names = HashMap::new();
+    for &channel_id in channels {
+        let configs = store.point_configs(channel_id).await.unwrap_or_default();
+        let channel: HashMap<_, _> = configs
+            .into_iter()
+            .filter_map(|p| Some((p.id, p.name?)))
+            .collect();
+        names.insert(channel_id, Arc::new(channel));
+    }
+    names
+}
+
+/// Writer thread state: the open file of each channel.
+struct FileWriter {
+    dir: PathBuf,
+    format: ArchiveFormat,
+    files: HashMap<u32, HourFile>,
+}
+
+impl FileWriter {
+    fn new(dir: &Path, format: ArchiveFormat) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            format,
+            files: HashMap::new(),
+        }
+    }
+
+    /// Write entries until the archiver is stopped, flushing whenever the
+    /// queue is drained, then close every file.
+    fn run(mut self, mut rx: mpsc::Receiver<Entry>) {
+        while let Some(entry) = rx.blocking_recv() {
+            let mut next = Some(entry);
+            while let Some(entry) = next {
+                self.handle(entry);
+                next = rx.try_recv().ok();
+            }
+            self.flush();
+        }
+        self.close_before(None);
+    }
+
+    fn handle(&mut self, entry: Entry) {
+        match entry {
+            Entry::Batch {
+                channel_id,
+                at,
+                batch,
+                names,
+            } => {
+                if let Err(_e) = self.write(channel_id, at, &batch, &names) {
+                    #[cfg(feature = "tracing-support")]
+                    tracing::error!(channel_id, "Archive write failed: {}", _e);
+                }
+            }
+            Entry::Tick(at) => self.close_before(Some(hour_of(at))),
+        }
+    }
+
+    fn write(
+        &mut self,
+        channel_id: u32,
+        at: DateTime<Utc>,
+        batch: &DataBatch,
+        names: &HashMap<PointId, String>,
+    ) -> Result<()> {
+        let hour = hour_of(at);
+        if self.files.get(&channel_id).is_some_and(|f| f.hour < hour) {
+            self.close(channel_id);
+        }
+        let file = match self.files.entry(channel_id) {
+            std::collections::hash_map::Entry::Occupied(entry) => entry.into_mut(),
+            std::collections::hash_map::Entry::Vacant(entry) => {
+                entry.insert(HourFile::open(&self.dir, channel_id, hour, self.format)?)
+            }
+        };
+        for point in batch.iter() {
+            file.write(point, names.get(&point.id).map(String::as_str))?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) {
+        for (_channel_id, file) in self.files.iter_mut() {
+            if let Err(_e) = file.flush() {
+                #[cfg(feature = "tracing-support")]
+                tracing::error!(channel_id = *_channel_id, "Archive write failed: {}", _e);
+            }
+        }
+    }
+
+    /// Close the files of hours before `hour` (all files if `None`).
+    fn close_before(&mut self, hour: Option<DateTime<Utc>>) {
+        let expired: Vec<u32> = self
+            .files
+            .iter()
+            .filter(|(_, f)| hour.is_none_or(|hour| f.hour < hour))
+            .map(|(&channel_id, _)| channel_id)
+            .collect();
+        for channel_id in expired {
+            self.close(channel_id);
+        }
+    }
+
+    fn close(&mut self, channel_id: u32) {
+        if let Some(file) = self.files.remove(&channel_id) {
+            if let Err(_e) = file.close() {
+                #[cfg(feature = "tracing-support")]
+                tracing::error!(channel_id, "Archive file not closed cleanly: {}", _e);
+            }
+        }
+    }
+}
+
+/// The file of one channel and hour.
+struct HourFile {
+    hour: DateTime<Utc>,
+    sink: Sink,
+}
+
+enum Sink {
+    Csv(csv::Writer<File>),
+    Parquet {
+        writer: ArrowWriter<File>,
+        rows: Rows,
+        partial: PathBuf,
+        path: PathBuf,
+    },
+}
+
+impl HourFile {
+    fn open(
+        dir: &Path,
+        channel_id: u32,
+        hour: DateTime<Utc>,
+        format: ArchiveFormat,
+    ) -> Result<Self> {
+        let dir = dir.join(channel_id.to_string());
+        fs::create_dir_all(&dir)?;
+        let stem = hour.format("%Y-%m-%d_%H").to_string();
+        let sink = match format {
+            ArchiveFormat::Csv => {
+                let path = dir.join(format!("{}.csv", stem));
+                let file = File::options().create(true).append(true).open(&path)?;
+                let empty = file.metadata()?.len() == 0;
+                let mut writer = csv::Writer::from_writer(file);
+                if empty {
+                    writer.write_record(ARCHIVE_COLUMNS).map_err(csv_error)?;
+                }
+                Sink::Csv(writer)
+            }
+            ArchiveFormat::Parquet => {
+                // Parquet files cannot be appended to: after a restart within
+                // the hour, continue in a new file.
+                let (path, partial) = (0..)
+                    .map(|n| {
+                        let name = match n {
+                            0 => stem.clone(),
+                            n => format!("{}.{}", stem, n),
+                        };
+                        (
+                            dir.join(format!("{}.parquet", name)),
+                            dir.join(format!("{}.parquet.partial", name)),
+                        )
+                    })
+                    .find(|(path, partial)| !path.exists() && !partial.exists())
+                    .expect("unbounded file names");
+                let properties = WriterProperties::builder()
+                    .set_max_row_group_size(ROW_GROUP_ROWS)
+                    .build();
+                let writer = ArrowWriter::try_new(
+                    File::create(&partial)?,
+                    archive_schema(),
+                    Some(properties),
+                )
+                .map_err(parquet_error)?;
+                Sink::Parquet {
+                    writer,
+                    rows: Rows::default(),
+                    partial,
+                    path,
+                }
+            }
+        };
+        Ok(Self { hour, sink })
+    }
+
+    fn write(&mut self, point: &DataPoint, name: Option<&str>) -> Result<()> {
+        match &mut self.sink {
+            Sink::Csv(writer) => writer
+                .write_record([
+                    timestamp_text(&point.timestamp),
+                    point.id.to_string(),
+                    name.unwrap_or_default().to_string(),
+                    value_text(&point.value),
+                    quality_name(point.quality),
+                ])
+                .map_err(csv_error),
+            Sink::Parquet { rows, .. } => {
+                rows.push(point, name);
+                Ok(())
+            }
+        }
+    }
+
+    /// Hand buffered rows to the OS (CSV) or the Parquet encoder.
+    fn flush(&mut self) -> Result<()> {
+        match &mut self.sink {
+            Sink::Csv(writer) => Ok(writer.flush()?),
+            Sink::Parquet { writer, rows, .. } => {
+                if !rows.is_empty() {
+                    writer.write(&rows.take()?).map_err(parquet_error)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Flush, fsync and (for Parquet) publish the file.
+    fn close(mut self) -> Result<()> {
+        self.flush()?;
+        match self.sink {
+            Sink::Csv(writer) => {
+                let file = writer.into_inner().map_err(|e| e.into_error())?;
+                file.sync_all()?;
+            }
+            Sink::Parquet {
+                writer,
+                partial,
+                path,
+                ..
+            } => {
+                let file = writer.into_inner().map_err(parquet_error)?;
+                file.sync_all()?;
+                fs::rename(&partial, &path)?;
+                sync_dir(path.parent().unwrap_or(Path::new(".")))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Pending Parquet rows, by column.
+#[derive(Default)]
+struct Rows {
+    timestamps: Vec<i64>,
+    point_ids: Vec<PointId>,
+    names: Vec<Option<String>>,
+    values: Vec<Option<f64>>,
+    qualities: Vec<String>,
+}
+
+impl Rows {
+    fn push(&mut self, point: &DataPoint, name: Option<&str>) {
+        self.timestamps.push(point.timestamp.timestamp_millis());
+        self.point_ids.push(point.id);
+        self.names.push(name.map(str::to_string));
+        self.values.push(point.value.as_f64());
+        self.qualities.push(quality_name(point.quality));
+    }
+
+    fn is_empty(&self) -> bool {
+        self.timestamps.is_empty()
+    }
+
+    fn take(&mut self) -> Result<RecordBatch> {
+        let rows = std::mem::take(self);
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(TimestampMillisecondArray::from(rows.timestamps).with_timezone("UTC")),
+            Arc::new(UInt32Array::from(rows.point_ids)),
+            Arc::new(StringArray::from(rows.names)),
+            Arc::new(Float64Array::from(rows.values)),
+            Arc::new(StringArray::from(rows.qualities)),
+        ];
+        RecordBatch::try_new(archive_schema(), columns)
+            .map_err(|e| GatewayError::storage_with(format!("parquet: {}", e), e))
+    }
+}
+
+fn archive_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new(
+            ARCHIVE_COLUMNS[0],
+            DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into())),
+            false,
+        ),
+        Field::new(ARCHIVE_COLUMNS[1], DataType::UInt32, false),
+        Field::new(ARCHIVE_COLUMNS[2], DataType::Utf8, true),
+        Field::new(ARCHIVE_COLUMNS[3], DataType::Float64, true),
+        Field::new(ARCHIVE_COLUMNS[4], DataType::Utf8, false),
+    ]))
+}
+
+/// Make a rename in `dir` durable.
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> io::Result<()> {
+    File::open(dir)?.sync_all()
+}
+
+/// Directories cannot be opened for syncing on this platform.
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+fn hour_of(at: DateTime<Utc>) -> DateTime<Utc> {
+    at.duration_trunc(TimeDelta::hours(1)).unwrap_or(at)
+}
+
+fn timestamp_text(timestamp: &DateTime<Utc>) -> String {
+    timestamp.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+fn value_text(value: &Value) -> String {
+    match value {
+        Value::Float(v) => v.to_string(),
+        Value::Integer(v) => v.to_string(),
+        Value::Bool(v) => v.to_string(),
+        Value::String(v) => v.clone(),
+        Value::Null => String::new(),
+        other => serde_json::to_string(other).unwrap_or_default(),
+    }
+}
+
+/// Serialized (snake_case) name of a quality, e.g. `last_known`.
+fn quality_name(quality: Quality) -> String {
+    match serde_json::to_value(quality) {
+        Ok(serde_json::Value::String(name)) => name,
+        _ => quality.to_string(),
+    }
+}
+
+fn csv_error(e: csv::Error) -> GatewayError {
+    GatewayError::Io(e.into())
+}
+
+fn parquet_error(e: parquet::errors::ParquetError) -> GatewayError {
+    GatewayError::storage_with(format!("parquet: {}", e), e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::point::{PointConfig, ProtocolAddress, VirtualAddress};
+    use crate::store::MemoryStore;
+    use arrow_array::Array;
+    use chrono::TimeZone;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    /// Unique directory in the system temp dir, removed on drop.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let nanos = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos();
+            Self(std::env::temp_dir().join(format!(
+                "igw-{}-{}-{}",
+                name,
+                std::process::id(),
+                nanos
+            )))
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 5, 1, hour, minute, 0).unwrap()
+    }
+
+    fn batch(points: &[(PointId, Value, DateTime<Utc>)]) -> Arc<DataBatch> {
+        Arc::new(
+            points
+                .iter()
+                .map(|(id, value, timestamp)| {
+                    let mut point = DataPoint::new(*id, value.clone());
+                    point.timestamp = *timestamp;
+                    point
+                })
+                .collect(),
+        )
+    }
+
+    fn names() -> Arc<HashMap<PointId, String>> {
+        Arc::new(HashMap::from([(1, "voltage".to_string())]))
+    }
+
+    #[test]
+    fn test_to_csv() {
+        let mut batch = (*batch(&[
+            (1, Value::Float(21.5), at(3, 0)),
+            (2, Value::Bool(true), at(3, 0)),
+            (3, Value::String("a,b".into()), at(3, 0)),
+            (4, Value::Null, at(3, 0)),
+        ]))
+        .clone();
+        batch.iter_mut().nth(1).unwrap().quality = Quality::LastKnown;
+
+        let mut out = Vec::new();
+        batch.to_csv(&mut out, true).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "timestamp,point_id,value,quality\n\
+             2024-05-01T03:00:00.000Z,1,21.5,good\n\
+             2024-05-01T03:00:00.000Z,2,true,last_known\n\
+             2024-05-01T03:00:00.000Z,3,\"a,b\",good\n\
+             2024-05-01T03:00:00.000Z,4,,good\n"
+        );
+
+        let mut out = Vec::new();
+        batch.to_csv(&mut out, false).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.starts_with("timestamp,point_id,value\n2024-05-01T03:00:00.000Z,1,21.5\n"));
+    }
+
+    #[test]
+    fn test_csv_rotation() {
+        let dir = TempDir::new("archive-csv");
+        let mut writer = FileWriter::new(&dir.0, ArchiveFormat::Csv);
+        let entry = |minute_hour: (u32, u32), id: PointId, value: f64| Entry::Batch {
+            channel_id: 7,
+            at: at(minute_hour.0, minute_hour.1),
+            batch: batch(&[(id, Value::Float(value), at(minute_hour.0, minute_hour.1))]),
+            names: names(),
+        };
+        writer.handle(entry((3, 10), 1, 1.0));
+        writer.handle(entry((3, 50), 2, 2.0));
+        writer.flush();
+        // The hour is over: the file is closed before any new value arrives.
+        writer.handle(Entry::Tick(at(4, 0)));
+        assert!(writer.files.is_empty());
+        writer.handle(entry((4, 5), 1, 3.0));
+        writer.close_before(None);
+
+        let read = |name: &str| fs::read_to_string(dir.0.join("7").join(name)).unwrap();
+        assert_eq!(
+            read("2024-05-01_03.csv"),
+            "timestamp,point_id,name,value,quality\n\
+             2024-05-01T03:10:00.000Z,1,voltage,1,good\n\
+             2024-05-01T03:50:00.000Z,2,,2,good\n"
+        );
+        assert_eq!(
+            read("2024-05-01_04.csv"),
+            "timestamp,point_id,name,value,quality\n\
+             2024-05-01T04:05:00.000Z,1,voltage,3,good\n"
+        );
+
+        // A restart within the hour appends without a second header.
+        let mut writer = FileWriter::new(&dir.0, ArchiveFormat::Csv);
+        writer.handle(entry((4, 30), 1, 4.0));
+        writer.close_before(None);
+        assert_eq!(read("2024-05-01_04.csv").lines().count(), 3);
+    }
+
+    #[test]
+    fn test_parquet() {
+        let dir = TempDir::new("archive-parquet");
+        let mut writer = FileWriter::new(&dir.0, ArchiveFormat::Parquet);
+        writer.handle(Entry::Batch {
+            channel_id: 1,
+            at: at(3, 10),
+            batch: batch(&[
+                (1, Value::Float(230.5), at(3, 10)),
+                (2, Value::String("text".into()), at(3, 10)),
+            ]),
+            names: names(),
+        });
+        writer.flush();
+        let channel_dir = dir.0.join("1");
+        assert!(channel_dir.join("2024-05-01_03.parquet.partial").exists());
+        assert!(!channel_dir.join("2024-05-01_03.parquet").exists());
+
+        writer.handle(Entry::Tick(at(4, 0)));
+        assert!(!channel_dir.join("2024-05-01_03.parquet.partial").exists());
+        let file = File::open(channel_dir.join("2024-05-01_03.parquet")).unwrap();
+        let batches: Vec<RecordBatch> = ParquetRecordBatchReaderBuilder::try_new(file)
+            .unwrap()
+            .build()
+            .unwrap()
+            .collect::<std::result::Result<_, _>>()
+            .unwrap();
+        assert_eq!(batches.len(), 1);
+        let record = &batches[0];
+        assert_eq!(record.schema(), archive_schema());
+        assert_eq!(record.num_rows(), 2);
+        let column = |name: &str| record.column_by_name(name).unwrap().clone();
+        let timestamps = column("timestamp");
+        let timestamps = timestamps
+            .as_any()
+            .downcast_ref::<TimestampMillisecondArray>()
+            .unwrap();
+        assert_eq!(timestamps.value(0), at(3, 10).timestamp_millis());
+        let point_names = column("name");
+        let point_names = point_names.as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(point_names.value(0), "voltage");
+        assert!(point_names.is_null(1));
+        let values = column("value");
+        let values = values.as_any().downcast_ref::<Float64Array>().unwrap();
+        assert_eq!(values.value(0), 230.5);
+        assert!(values.is_null(1));
+
+        // The published file is never reused.
+        writer.handle(Entry::Batch {
+            channel_id: 1,
+            at: at(3, 59),
+            batch: batch(&[(1, Value::Float(1.0), at(3, 59))]),
+            names: names(),
+        });
+        writer.close_before(None);
+        assert!(channel_dir.join("2024-05-01_03.1.parquet").exists());
+    }
+
+    #[tokio::test]
+    async fn test_archiver() {
+        let dir = TempDir::new("archiver");
+        let store = Arc::new(MemoryStore::new());
+        let point = PointConfig::new(1, ProtocolAddress::Virtual(VirtualAddress::new("v")))
+            .with_name("voltage");
+        store.set_point_configs(3, &[point]).await.unwrap();
+
+        let archiver = Archiver::start(&ArchiveConfig::new(&dir.0), store.clone())
+            .await
+            .unwrap();
+        let now = Utc::now();
+        store
+            .write_batch(3, &batch(&[(1, Value::Float(1.5), now)]))
+            .await
+            .unwrap();
+        // Unchanged values are not archived again.
+        store
+            .write_batch(3, &batch(&[(1, Value::Float(1.5), now)]))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        archiver.stop().await;
+
+        let files: Vec<PathBuf> = fs::read_dir(dir.0.join("3"))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(files.len(), 1);
+        let text = fs::read_to_string(&files[0]).unwrap();
+        assert_eq!(
+            text,
+            format!(
+                "timestamp,point_id,name,value,quality\n{},1,voltage,1.5,good\n",
+                timestamp_text(&now)
+            )
+        );
+    }
+}
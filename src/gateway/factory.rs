@@ -1,44 +1,208 @@
 //! Channel factory.
 //!
 //! Creates `ChannelRuntime` instances from configuration.
+//!
+//! Protocols are looked up in the [`ChannelFactoryRegistry`]. The built-in
+//! protocols of the enabled features are registered from the start;
+//! applications add their own with [`register_protocol()`] and then use
+//! them in [`GatewayConfig`](super::GatewayConfig) like any other protocol:
+//!
+//! ```rust,ignore
+//! fn build_udp(config: &ChannelConfig) -> Result<Box<dyn ChannelRuntime>> {
+//!     Ok(Box::new(MyUdpRuntime::from_config(config)?))
+//! }
+//!
+//! igw::gateway::factory::register_protocol("my-udp", build_udp)?;
+//! ```
+//!
+//! Point addresses of custom protocols are not parsed by igw; they reach
+//! the builder unchanged in [`ChannelConfig::points`] and become
+//! `ProtocolAddress::Generic` in the point configurations the runtime
+//! stores.
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use once_cell::sync::Lazy;
 
 use crate::core::error::{GatewayError, Result};
-use crate::core::point::PointConfig;
+use crate::core::point::{PointConfig, ProtocolAddress};
 
 use super::config::ChannelConfig;
+use super::params;
 use super::parse_address;
+use super::recording::ReplayChannel;
 use super::runtime::ChannelRuntime;
+use super::validate::{ensure_valid, validate_channel};
 use super::wrappers::VirtualRuntime;
 
-/// Create a channel from configuration.
-pub fn create_channel(config: &ChannelConfig) -> Result<Box<dyn ChannelRuntime>> {
-    match config.protocol.to_lowercase().as_str() {
-        #[cfg(feature = "modbus")]
-        "modbus" => create_modbus_channel(config),
+/// Built-in protocols [`create_channel()`] can build with the enabled
+/// features (see [`ChannelFactoryRegistry::protocols()`] for all protocols,
+/// including registered ones).
+pub const SUPPORTED_PROTOCOLS: &[&str] = &[
+    #[cfg(feature = "modbus")]
+    "modbus",
+    #[cfg(feature = "iec104")]
+    "iec104",
+    #[cfg(feature = "opcua")]
+    "opcua",
+    #[cfg(feature = "mqtt")]
+    "mqtt",
+    #[cfg(all(feature = "can", target_os = "linux"))]
+    "can",
+    #[cfg(all(feature = "gpio", target_os = "linux"))]
+    "gpio",
+    "virtual",
+    "replay",
+];
+
+/// Protocols built into igw, whether or not their feature is enabled.
+///
+/// `"replay"` is not listed: like registered protocols it takes any point
+/// address.
+pub(crate) const BUILTIN_PROTOCOLS: &[&str] = &[
+    "modbus", "iec104", "opcua", "mqtt", "can", "gpio", "virtual",
+];
+
+/// Builds a channel runtime from its configuration.
+pub type ChannelBuilder = fn(&ChannelConfig) -> Result<Box<dyn ChannelRuntime>>;
+
+/// Protocol name to channel builder mapping used by [`create_channel()`].
+///
+/// Protocol names are case-insensitive.
+#[derive(Debug)]
+pub struct ChannelFactoryRegistry {
+    builders: RwLock<HashMap<String, ChannelBuilder>>,
+}
 
-        #[cfg(feature = "iec104")]
-        "iec104" => create_iec104_channel(config),
+impl ChannelFactoryRegistry {
+    /// Create a registry with no protocols.
+    pub fn new() -> Self {
+        Self {
+            builders: RwLock::new(HashMap::new()),
+        }
+    }
 
-        #[cfg(feature = "opcua")]
-        "opcua" => create_opcua_channel(config),
+    /// Create a registry with the built-in protocols of the enabled features.
+    pub fn with_builtins() -> Self {
+        let builtins: &[(&str, ChannelBuilder)] = &[
+            #[cfg(feature = "modbus")]
+            ("modbus", create_modbus_channel),
+            #[cfg(feature = "iec104")]
+            ("iec104", create_iec104_channel),
+            #[cfg(feature = "opcua")]
+            ("opcua", create_opcua_channel),
+            #[cfg(feature = "mqtt")]
+            ("mqtt", create_mqtt_channel),
+            #[cfg(all(feature = "can", target_os = "linux"))]
+            ("can", create_can_channel),
+            #[cfg(all(feature = "gpio", target_os = "linux"))]
+            ("gpio", create_gpio_channel),
+            ("virtual", create_virtual_channel),
+            ("replay", create_replay_channel),
+        ];
+
+        let registry = Self::new();
+        for (protocol, builder) in builtins {
+            // Names are distinct, registration cannot fail
+            let _ = registry.register(protocol, *builder);
+        }
+        registry
+    }
 
-        #[cfg(all(feature = "can", target_os = "linux"))]
-        "can" => create_can_channel(config),
+    /// Register a protocol.
+    ///
+    /// Fails with `GatewayError::Config` if the name is empty or already
+    /// registered (built-in protocols cannot be replaced).
+    pub fn register(&self, protocol: &str, builder: ChannelBuilder) -> Result<()> {
+        let protocol = protocol.trim().to_lowercase();
+        if protocol.is_empty() {
+            return Err(GatewayError::Config("protocol name is empty".into()));
+        }
 
-        #[cfg(all(feature = "gpio", target_os = "linux"))]
-        "gpio" => create_gpio_channel(config),
+        let mut builders = self.builders.write().unwrap_or_else(|e| e.into_inner());
+        if builders.contains_key(&protocol) {
+            return Err(GatewayError::Config(format!(
+                "protocol '{}' is already registered",
+                protocol
+            )));
+        }
+        builders.insert(protocol, builder);
+        Ok(())
+    }
 
-        "virtual" => create_virtual_channel(config),
+    /// Whether a protocol is registered.
+    pub fn contains(&self, protocol: &str) -> bool {
+        self.builder(protocol).is_some()
+    }
 
-        protocol => Err(GatewayError::Config(format!(
-            "Unsupported protocol: {}. Check if the required feature is enabled.",
-            protocol
-        ))),
+    /// Registered protocol names, sorted.
+    pub fn protocols(&self) -> Vec<String> {
+        let builders = self.builders.read().unwrap_or_else(|e| e.into_inner());
+        let mut protocols: Vec<String> = builders.keys().cloned().collect();
+        protocols.sort();
+        protocols
     }
+
+    /// Build a channel with the builder registered for `config.protocol`.
+    ///
+    /// Unlike [`create_channel()`], the configuration is not validated.
+    pub fn create(&self, config: &ChannelConfig) -> Result<Box<dyn ChannelRuntime>> {
+        let builder = self.builder(&config.protocol).ok_or_else(|| {
+            GatewayError::Config(format!(
+                "Unsupported protocol: {}. Check if the required feature is enabled.",
+                config.protocol
+            ))
+        })?;
+        builder(config)
+    }
+
+    fn builder(&self, protocol: &str) -> Option<ChannelBuilder> {
+        let builders = self.builders.read().unwrap_or_else(|e| e.into_inner());
+        builders.get(&protocol.to_lowercase()).copied()
+    }
+}
+
+impl Default for ChannelFactoryRegistry {
+    fn default() -> Self {
+        Self::with_builtins()
+    }
+}
+
+/// Global channel factory registry used by [`create_channel()`].
+static CHANNEL_FACTORY_REGISTRY: Lazy<ChannelFactoryRegistry> =
+    Lazy::new(ChannelFactoryRegistry::with_builtins);
+
+/// Get the global channel factory registry.
+pub fn get_channel_factory_registry() -> &'static ChannelFactoryRegistry {
+    &CHANNEL_FACTORY_REGISTRY
+}
+
+/// Register a custom protocol with the global registry so that
+/// [`create_channel()`] and the gateway runtime can build it from
+/// configuration.
+pub fn register_protocol(protocol: &str, builder: ChannelBuilder) -> Result<()> {
+    get_channel_factory_registry().register(protocol, builder)
+}
+
+/// Create a channel from configuration.
+///
+/// The channel configuration is validated first (see
+/// [`GatewayConfig::validate()`](super::GatewayConfig::validate)); all
+/// problems are reported in one `GatewayError::Config`. The protocol is then
+/// built by the builder registered in the global
+/// [`ChannelFactoryRegistry`].
+pub fn create_channel(config: &ChannelConfig) -> Result<Box<dyn ChannelRuntime>> {
+    ensure_valid(validate_channel(config))?;
+    get_channel_factory_registry().create(config)
 }
 
 /// Convert PointDef list to PointConfig list.
-fn build_point_configs(config: &ChannelConfig) -> Result<Vec<PointConfig>> {
+///
+/// Addresses of custom (registered) protocols become
+/// `ProtocolAddress::Generic`.
+pub(crate) fn build_point_configs(config: &ChannelConfig) -> Result<Vec<PointConfig>> {
+    let builtin = BUILTIN_PROTOCOLS.contains(&config.protocol.to_lowercase().as_str());
     let mut points = Vec::new();
 
     for point_def in &config.points {
@@ -46,14 +210,36 @@ fn build_point_configs(config: &ChannelConfig) -> Result<Vec<PointConfig>> {
             continue;
         }
 
-        let address = parse_address(&config.protocol, &point_def.address)?;
+        let address = if builtin {
+            parse_address(&config.protocol, &point_def.address)?
+        } else {
+            ProtocolAddress::Generic(point_def.address.clone())
+        };
+
+        // A unit conversion converts from the point's unit by default, and
+        // the point then reports the converted unit
+        let mut transform = point_def.transform.clone();
+        let mut unit = point_def.unit.clone();
+        if let Some(conversion) = &mut transform.unit_conversion {
+            if conversion.from.is_none() {
+                conversion.from = unit.clone();
+            }
+            unit = Some(conversion.to.clone());
+        }
 
         points.push(PointConfig {
             id: point_def.id,
             name: Some(point_def.name.clone()),
+            display_name: point_def.display_name.clone(),
+            unit,
             address,
-            transform: point_def.transform.clone(),
+            transform,
             poll_group: None,
+            max_age_ms: point_def.max_age_ms,
+            data_type: point_def.data_type,
+            ttl_ms: point_def.ttl_ms,
+            out_of_service_ms: point_def.out_of_service_ms,
+            alarms: point_def.alarms.clone(),
             enabled: true,
         });
     }
@@ -71,8 +257,7 @@ fn create_modbus_channel(config: &ChannelConfig) -> Result<Box<dyn ChannelRuntim
     use crate::protocols::modbus::ModbusChannelParamsConfig;
 
     // Parse parameters
-    let params: ModbusChannelParamsConfig = serde_json::from_value(config.parameters.clone())
-        .map_err(|e| GatewayError::Config(format!("Invalid Modbus parameters: {}", e)))?;
+    let params: ModbusChannelParamsConfig = params::parse(config)?;
 
     // Build channel config
     let channel_config = params.to_channel_config();
@@ -82,7 +267,11 @@ fn create_modbus_channel(config: &ChannelConfig) -> Result<Box<dyn ChannelRuntim
     let channel_config = channel_config.with_points(points);
 
     // Create channel
-    let channel = crate::protocols::modbus::ModbusChannel::new(channel_config, config.id);
+    let mut channel = crate::protocols::modbus::ModbusChannel::new(channel_config, config.id);
+    if config.transport.is_some() {
+        // All channels of a shared transport use one client
+        channel = channel.with_shared_transport();
+    }
 
     Ok(Box::new(ModbusRuntime::new(
         config.id,
@@ -97,8 +286,7 @@ fn create_iec104_channel(config: &ChannelConfig) -> Result<Box<dyn ChannelRuntim
     use crate::protocols::iec104::Iec104ParamsConfig;
 
     // Parse parameters
-    let params: Iec104ParamsConfig = serde_json::from_value(config.parameters.clone())
-        .map_err(|e| GatewayError::Config(format!("Invalid IEC104 parameters: {}", e)))?;
+    let params: Iec104ParamsConfig = params::parse(config)?;
 
     // Build point configs
     let points = build_point_configs(config)?;
@@ -122,8 +310,7 @@ fn create_opcua_channel(config: &ChannelConfig) -> Result<Box<dyn ChannelRuntime
     use crate::protocols::opcua::OpcUaParamsConfig;
 
     // Parse parameters
-    let params: OpcUaParamsConfig = serde_json::from_value(config.parameters.clone())
-        .map_err(|e| GatewayError::Config(format!("Invalid OPC UA parameters: {}", e)))?;
+    let params: OpcUaParamsConfig = params::parse(config)?;
 
     // Build point configs
     let points = build_point_configs(config)?;
@@ -141,14 +328,37 @@ fn create_opcua_channel(config: &ChannelConfig) -> Result<Box<dyn ChannelRuntime
     )))
 }
 
+#[cfg(feature = "mqtt")]
+fn create_mqtt_channel(config: &ChannelConfig) -> Result<Box<dyn ChannelRuntime>> {
+    use super::wrappers::MqttRuntime;
+    use crate::protocols::mqtt::MqttParamsConfig;
+
+    // Parse parameters
+    let params: MqttParamsConfig = params::parse(config)?;
+
+    // Build point configs
+    let points = build_point_configs(config)?;
+
+    // Build channel config
+    let channel_config = params.to_config(config.id).with_points(points);
+
+    // Create channel
+    let channel = crate::protocols::mqtt::MqttChannel::new(channel_config);
+
+    Ok(Box::new(MqttRuntime::new(
+        config.id,
+        config.name.clone(),
+        channel,
+    )))
+}
+
 #[cfg(all(feature = "can", target_os = "linux"))]
 fn create_can_channel(config: &ChannelConfig) -> Result<Box<dyn ChannelRuntime>> {
     use super::wrappers::CanRuntime;
     use crate::protocols::can::CanChannelParamsConfig;
 
     // Parse parameters
-    let params: CanChannelParamsConfig = serde_json::from_value(config.parameters.clone())
-        .map_err(|e| GatewayError::Config(format!("Invalid CAN parameters: {}", e)))?;
+    let params: CanChannelParamsConfig = params::parse(config)?;
 
     // Build channel config
     let channel_config = params.to_config();
@@ -169,8 +379,7 @@ fn create_gpio_channel(config: &ChannelConfig) -> Result<Box<dyn ChannelRuntime>
     use crate::protocols::gpio::GpioChannelParamsConfig;
 
     // Parse parameters
-    let params: GpioChannelParamsConfig = serde_json::from_value(config.parameters.clone())
-        .map_err(|e| GatewayError::Config(format!("Invalid GPIO parameters: {}", e)))?;
+    let params: GpioChannelParamsConfig = params::parse(config)?;
 
     // Build channel config
     let channel_config = params.to_config();
@@ -189,8 +398,7 @@ fn create_virtual_channel(config: &ChannelConfig) -> Result<Box<dyn ChannelRunti
     use crate::protocols::virtual_channel::{VirtualChannel, VirtualChannelParamsConfig};
 
     // Parse parameters (optional for virtual)
-    let params: VirtualChannelParamsConfig =
-        serde_json::from_value(config.parameters.clone()).unwrap_or_default();
+    let params: VirtualChannelParamsConfig = params::parse(config)?;
 
     // Build point configs
     let points = build_point_configs(config)?;
@@ -199,7 +407,7 @@ fn create_virtual_channel(config: &ChannelConfig) -> Result<Box<dyn ChannelRunti
     let channel_config = params.to_config().with_points(points);
 
     // Create channel
-    let channel = VirtualChannel::new(channel_config);
+    let channel = VirtualChannel::try_new(channel_config)?;
 
     Ok(Box::new(VirtualRuntime::new(
         config.id,
@@ -207,3 +415,72 @@ fn create_virtual_channel(config: &ChannelConfig) -> Result<Box<dyn ChannelRunti
         channel,
     )))
 }
+
+fn create_replay_channel(config: &ChannelConfig) -> Result<Box<dyn ChannelRuntime>> {
+    Ok(Box::new(ReplayChannel::from_config(config)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channel(protocol: &str) -> ChannelConfig {
+        serde_json::from_value(serde_json::json!({
+            "id": 7,
+            "name": "custom",
+            "protocol": protocol,
+            "points": [{ "id": 1, "name": "a", "address": "reg-1@dev" }]
+        }))
+        .unwrap()
+    }
+
+    /// Custom protocol built on a virtual channel.
+    fn build_custom(config: &ChannelConfig) -> Result<Box<dyn ChannelRuntime>> {
+        use crate::protocols::virtual_channel::{VirtualChannel, VirtualChannelConfig};
+
+        let channel = VirtualChannel::try_new(VirtualChannelConfig::new(&config.name))?;
+        Ok(Box::new(VirtualRuntime::new(
+            config.id,
+            config.name.clone(),
+            channel,
+        )))
+    }
+
+    #[test]
+    fn test_registry() {
+        let registry = ChannelFactoryRegistry::with_builtins();
+        assert!(registry.contains("virtual"));
+        assert!(registry.contains("VIRTUAL"));
+        assert!(!registry.contains("acme-udp"));
+
+        registry.register("Acme-UDP", build_custom).unwrap();
+        assert!(registry.contains("acme-udp"));
+        let runtime = registry.create(&channel("acme-udp")).unwrap();
+        assert_eq!(runtime.id(), 7);
+
+        // Names are unique, built-ins included
+        assert!(registry.register("acme-udp", build_custom).is_err());
+        assert!(registry.register("virtual", build_custom).is_err());
+        assert!(registry.register(" ", build_custom).is_err());
+
+        assert!(ChannelFactoryRegistry::new()
+            .create(&channel("virtual"))
+            .is_err());
+    }
+
+    #[test]
+    fn test_create_registered_channel() {
+        let config = channel("factory-test-udp");
+        assert!(create_channel(&config).is_err());
+
+        register_protocol("factory-test-udp", build_custom).unwrap();
+        let runtime = create_channel(&config).unwrap();
+        assert_eq!(runtime.name(), "custom");
+
+        // Addresses are passed through untouched
+        let points = build_point_configs(&config).unwrap();
+        assert!(
+            matches!(&points[0].address, ProtocolAddress::Generic(address) if address == "reg-1@dev")
+        );
+    }
+}
@@ -0,0 +1,75 @@
+//! Feedback checks of written commands.
+//!
+//! A command point with a [`FeedbackDef`] is only considered done once the
+//! device reports the new state: after a successful write,
+//! [`GatewayRuntime`](super::GatewayRuntime) checks the store every
+//! [`CHECK_INTERVAL`] until the feedback point holds the expected value with
+//! `Good` quality, or the feedback timeout expires. Checking the store makes
+//! this independent of the protocol and of whether the feedback point is
+//! polled or event-driven.
+
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use crate::core::data::{DataPoint, PointId};
+use crate::core::traits::{CommandOutcome, CommandStage};
+use crate::store::DataStore;
+
+use super::config::FeedbackDef;
+
+/// How often the store is checked for the feedback value.
+const CHECK_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Wait for the feedback of `value` written to `point_id` of `channel_id`.
+///
+/// Returns `CommandStage::Confirmed`, or `CommandStage::FeedbackTimeout`
+/// with the last mismatch as error.
+pub(crate) async fn verify(
+    store: &dyn DataStore,
+    channel_id: u32,
+    point_id: PointId,
+    feedback: &FeedbackDef,
+    value: f64,
+) -> CommandOutcome {
+    let feedback_channel = feedback.channel.unwrap_or(channel_id);
+    let expected = feedback.expected(value);
+    let deadline = Instant::now() + Duration::from_millis(feedback.timeout_ms);
+    loop {
+        let mismatch = match store.read(feedback_channel, feedback.point_id).await {
+            Ok(point) => mismatch(point.as_ref(), expected),
+            Err(e) => Some(format!("cannot be read: {}", e)),
+        };
+        let Some(mismatch) = mismatch else {
+            return CommandOutcome::new(point_id, CommandStage::Confirmed);
+        };
+
+        let now = Instant::now();
+        if now >= deadline {
+            return CommandOutcome {
+                point_id,
+                stage: CommandStage::FeedbackTimeout,
+                error: Some(format!(
+                    "feedback channel {} point {} {} after {} ms",
+                    feedback_channel, feedback.point_id, mismatch, feedback.timeout_ms
+                )),
+            };
+        }
+        tokio::time::sleep(CHECK_INTERVAL.min(deadline - now)).await;
+    }
+}
+
+/// Why `point` does not confirm the command, if it does not.
+fn mismatch(point: Option<&DataPoint>, expected: f64) -> Option<String> {
+    let Some(point) = point else {
+        return Some("has no value".into());
+    };
+    if !point.quality.is_good() {
+        return Some(format!("has quality '{}'", point.quality));
+    }
+    match point.value.as_f64() {
+        Some(value) if value == expected => None,
+        Some(value) => Some(format!("is {} (expected {})", value, expected)),
+        None => Some(format!("has non-numeric value {:?}", point.value)),
+    }
+}
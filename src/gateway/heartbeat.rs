@@ -0,0 +1,230 @@
+//! Heartbeat output for an external safety relay.
+//!
+//! With `[gateway.heartbeat]` configured (see [`HeartbeatConfig`]), the
+//! runtime spawns a task of its own that writes a control point every
+//! period: inverted (`toggle`) or set for `pulse_ms` (`pulse`). The relay
+//! expects that change within its timeout and drops the plant to a safe
+//! state when it is missing.
+//!
+//! The task only drives the output while the gateway does its job. It
+//! stops, without writing anything, while
+//! - any monitored channel has not returned data for `stall_timeout_ms`
+//!   (a successful poll or a received event);
+//! - the heartbeat channel is not running (disabled, parked or being
+//!   restarted);
+//! - a write does not complete within the period, e.g. because the
+//!   heartbeat channel is stuck in a poll.
+//!
+//! It resumes once all of them are healthy again. Either transition is
+//! logged, and the current status is returned by
+//! [`GatewayRuntime::heartbeat_status()`](super::GatewayRuntime::heartbeat_status)
+//! and shown in the heartbeat channel's `Diagnostics::extra` (`heartbeat`).
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio::time::{timeout, MissedTickBehavior};
+
+use crate::core::error::{GatewayError, Result};
+
+use super::config::{HeartbeatConfig, HeartbeatPattern};
+use super::orchestrator::{ChannelStats, SharedChannel};
+
+/// State of the heartbeat output.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct HeartbeatStatus {
+    /// Whether the output is currently driven.
+    pub driving: bool,
+
+    /// Successful toggles or pulses since the runtime started.
+    pub beats: u64,
+
+    /// Time of the last successful toggle or pulse.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_beat: Option<DateTime<Utc>>,
+
+    /// Monitored channels that stopped the heartbeat.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub stalled_channels: Vec<u32>,
+
+    /// Failed or timed-out writes of the output.
+    pub write_failures: u64,
+
+    /// Why the output is not driven, if it is not.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// What the heartbeat task drives and watches; updated by the runtime as
+/// channels are restarted.
+#[derive(Default)]
+pub(super) struct Wiring {
+    /// The heartbeat channel, if running.
+    pub(super) target: Option<SharedChannel>,
+    /// Counters of the monitored channels, by channel id.
+    pub(super) monitors: Vec<(u32, Arc<ChannelStats>)>,
+}
+
+/// Running heartbeat task.
+pub(super) struct Heartbeat {
+    config: HeartbeatConfig,
+    wiring: watch::Sender<Wiring>,
+    status: Arc<std::sync::Mutex<HeartbeatStatus>>,
+    handle: JoinHandle<()>,
+}
+
+impl Heartbeat {
+    /// Spawn the heartbeat task.
+    pub(super) fn spawn(config: HeartbeatConfig, wiring: Wiring) -> Self {
+        let (wiring, rx) = watch::channel(wiring);
+        let status = Arc::default();
+        let handle = tokio::spawn(run(config.clone(), rx, Arc::clone(&status)));
+        Self {
+            config,
+            wiring,
+            status,
+            handle,
+        }
+    }
+
+    pub(super) fn config(&self) -> &HeartbeatConfig {
+        &self.config
+    }
+
+    /// Point the task at restarted channels.
+    pub(super) fn rewire(&self, wiring: Wiring) {
+        self.wiring.send_replace(wiring);
+    }
+
+    pub(super) fn status(&self) -> HeartbeatStatus {
+        self.status
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Stop driving the output.
+    pub(super) fn stop(self) {
+        self.handle.abort();
+    }
+}
+
+async fn run(
+    config: HeartbeatConfig,
+    wiring: watch::Receiver<Wiring>,
+    status: Arc<std::sync::Mutex<HeartbeatStatus>>,
+) {
+    let period = Duration::from_millis(config.period_ms.max(1));
+    let stall = Duration::from_millis(config.stall_timeout_ms);
+    let mut ticker = tokio::time::interval(period);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut level = false;
+
+    loop {
+        ticker.tick().await;
+        let (target, stalled) = {
+            let wiring = wiring.borrow();
+            let stalled: Vec<u32> = wiring
+                .monitors
+                .iter()
+                .filter(|(_, stats)| stats.since_data() >= stall)
+                .map(|(id, _)| *id)
+                .collect();
+            (wiring.target.clone(), stalled)
+        };
+
+        let outcome = match target {
+            _ if !stalled.is_empty() => Err(Halt::Stalled),
+            None => Err(Halt::NotRunning),
+            Some(target) => {
+                let beat = async {
+                    match config.pattern {
+                        HeartbeatPattern::Toggle => {
+                            write(&target, config.point_id, !level).await?;
+                            level = !level;
+                            Ok(())
+                        }
+                        HeartbeatPattern::Pulse => {
+                            write(&target, config.point_id, true).await?;
+                            tokio::time::sleep(Duration::from_millis(config.pulse_ms)).await;
+                            write(&target, config.point_id, false).await
+                        }
+                    }
+                };
+                match timeout(period, beat).await {
+                    Ok(Ok(())) => Ok(()),
+                    Ok(Err(e)) => Err(Halt::Write(format!("write failed: {}", e))),
+                    Err(_) => Err(Halt::Write(format!(
+                        "write timed out after {} ms",
+                        period.as_millis()
+                    ))),
+                }
+            }
+        };
+
+        let mut status = status.lock().unwrap_or_else(|e| e.into_inner());
+        let was_driving = status.driving;
+        match outcome {
+            Ok(()) => {
+                status.driving = true;
+                status.beats += 1;
+                status.last_beat = Some(Utc::now());
+                status.stalled_channels.clear();
+                status.reason = None;
+                if !was_driving {
+                    #[cfg(feature = "tracing-support")]
+                    tracing::info!("Heartbeat on channel {} driven", config.channel_id);
+                }
+            }
+            Err(halt) => {
+                let reason = match halt {
+                    Halt::Stalled => format!("channels {:?} stalled", stalled),
+                    Halt::NotRunning => format!("channel {} is not running", config.channel_id),
+                    Halt::Write(reason) => {
+                        status.write_failures += 1;
+                        reason
+                    }
+                };
+                if was_driving || status.reason.as_ref() != Some(&reason) {
+                    #[cfg(feature = "tracing-support")]
+                    tracing::warn!(
+                        "Heartbeat on channel {} stopped: {}",
+                        config.channel_id,
+                        reason
+                    );
+                }
+                status.driving = false;
+                status.stalled_channels = stalled;
+                status.reason = Some(reason);
+            }
+        }
+    }
+}
+
+/// Why a period passed without a beat.
+enum Halt {
+    /// Monitored channels stopped returning data.
+    Stalled,
+    /// The heartbeat channel is not running.
+    NotRunning,
+    /// Writing the output failed or timed out.
+    Write(String),
+}
+
+/// Write the heartbeat output.
+async fn write(target: &SharedChannel, point_id: u32, on: bool) -> Result<()> {
+    let value = if on { 1.0 } else { 0.0 };
+    let result = target
+        .lock()
+        .await
+        .write_control(&[(point_id, value)])
+        .await?;
+    match result.failures.first() {
+        Some((_, error)) => Err(GatewayError::Protocol(error.clone())),
+        None => Ok(()),
+    }
+}
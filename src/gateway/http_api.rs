@@ -0,0 +1,952 @@
+//! Embedded HTTP API (feature `http-api`).
+//!
+//! A small JSON API over a running [`GatewayRuntime`] for field diagnostics:
+//!
+//! | Method | Path | Response |
+//! |--------|------|----------|
+//! | `GET` | `/channels` | Channels with their connection state and [`ChannelCapabilities`] |
+//! | `GET` | `/channels/{id}/diagnostics` | The channel's [`Diagnostics`] |
+//! | `GET` | `/channels/{id}/points` | Latest values from the store, with names, units and age |
+//! | `POST` | `/channels/{id}/control` | Sends a [`ControlCommand`] array via `write_control` |
+//! | `POST` | `/channels/{id}/refresh` | Has the channel report all points now, see below |
+//! | `GET` | `/snapshot` | [`StoreSnapshot`] of the whole store |
+//! | `POST` | `/snapshot` | Imports a [`StoreSnapshot`], values marked `LastKnown` |
+//! | `GET` | `/audit` | Command audit history with the recorder's counters |
+//! | `GET` | `/overrides` | [`PointOverride`]s in effect |
+//! | `PUT` | `/channels/{id}/points/{point}/override` | Forces a point to a value, see below |
+//! | `DELETE` | `/channels/{id}/points/{point}/override` | Clears a point's override |
+//!
+//! Each point of `/channels/{id}/points` carries `last_change` (timestamp
+//! of the sample that last changed its value) and `age_ms` (time since
+//! then) when the store tracks point age, so flatlined sensors stand out.
+//!
+//! If a token or users are configured, every request must carry
+//! `Authorization: Bearer <token>`. Errors are returned as
+//! `{"error": "..."}`, with the [`code` and `category`](crate::core::error)
+//! of the gateway error if one caused them:
+//!
+//! ```text
+//! {"error": "Not connected", "code": 1002, "category": "connectivity"}
+//! ```
+//!
+//! `POST .../control` answers with the number of commands sent and the
+//! commands that were refused or failed:
+//!
+//! ```text
+//! {"accepted": 1, "failures": [{"id": 12, "error": "..."}]}
+//! ```
+//!
+//! Commands are sent as caller `http_api`, or as the identity of the
+//! [`HttpApiUser`] whose token the request carries, and are subject to the
+//! gateway's [`AuthorizationConfig`]; a refused command answers
+//! `403 Forbidden`.
+//!
+//! `/audit` takes the optional query parameters `from` and `to` (RFC 3339
+//! timestamps, `to` exclusive), `channel` and `limit` (default
+//! 1000, at most 10000) and returns the oldest matching records:
+//!
+//! ```text
+//! GET /audit?from=2024-05-01T00:00:00Z&channel=1
+//! {"records": [{"timestamp": "...", "caller": "scada", ...}], "stats": {"recorded": 12, "dropped": 0, "failed": 0}}
+//! ```
+//!
+//! `POST .../refresh` runs [`GatewayRuntime::refresh()`] and requires the
+//! `operate` level, as it makes the channel query its device. Polling
+//! channels answer with the number of points read and failed by the poll
+//! that follows; event-driven channels answer `null` for both, their
+//! values arrive as events:
+//!
+//! ```text
+//! POST /channels/3/refresh
+//! {"points": 24, "failed": 0}
+//! ```
+//!
+//! `PUT .../override` takes the value and an optional lifetime in seconds
+//! and requires the `tune` level (see [`overrides`](super::overrides)):
+//!
+//! ```text
+//! PUT /channels/1/points/1001/override
+//! {"value": 42.0, "expires_in_s": 3600}
+//! ```
+//!
+//! `POST /snapshot` overwrites stored values, so it requires the `tune`
+//! level as well.
+//!
+//! Handlers only take the runtime's read lock and never touch the channel
+//! tasks directly, so serving requests does not hold up polling.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! let gateway = Arc::new(RwLock::new(GatewayRuntime::from_config(config, store)?));
+//! let api = HttpApi::bind(&http_config, Arc::clone(&gateway)).await?;
+//! tokio::spawn(api.serve(std::future::pending()));
+//! ```
+//!
+//! [`Diagnostics`]: crate::core::traits::Diagnostics
+//! [`StoreSnapshot`]: crate::store::StoreSnapshot
+//! [`AuthorizationConfig`]: super::config::AuthorizationConfig
+
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use axum::extract::{DefaultBodyLimit, Path, RawQuery, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post, put};
+use axum::{Extension, Json, Router};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::net::TcpListener;
+use tokio::sync::RwLock;
+
+use crate::core::data::{DataPoint, PointId, Value};
+use crate::core::error::{ErrorReport, GatewayError, Result};
+use crate::core::point::{point_meta_map, AnnotatedPoint};
+use crate::core::traits::{ConnectionState, ControlCommand, OperateMode};
+use crate::store::{AuditQuery, StoreSnapshot};
+
+use super::audit::{AuditStats, CommandAudit};
+use super::authorization::{token_matches, Caller};
+
+use super::config::{HttpApiConfig, HttpApiUser, PermissionLevel};
+use super::orchestrator::GatewayRuntime;
+use super::overrides::PointOverride;
+use super::runtime::ChannelCapabilities;
+
+/// Largest snapshot accepted by `POST /snapshot`.
+const SNAPSHOT_BODY_LIMIT: usize = 64 * 1024 * 1024;
+
+/// Records returned by `GET /audit` without a `limit`.
+const DEFAULT_AUDIT_LIMIT: usize = 1000;
+
+/// Most records returned by one `GET /audit`.
+const MAX_AUDIT_LIMIT: usize = 10_000;
+
+/// Gateway runtime shared between the HTTP API and its owner.
+pub type SharedGateway = Arc<RwLock<GatewayRuntime>>;
+
+/// A bound HTTP API server.
+pub struct HttpApi {
+    listener: TcpListener,
+    router: Router,
+}
+
+impl HttpApi {
+    /// Bind to `config.bind`.
+    pub async fn bind(config: &HttpApiConfig, gateway: SharedGateway) -> Result<Self> {
+        let listener = TcpListener::bind(&config.bind).await.map_err(|e| {
+            GatewayError::Config(format!("http_api: cannot bind {}: {}", config.bind, e))
+        })?;
+        Ok(Self {
+            listener,
+            router: router_with_users(gateway, config.token.clone(), config.users.clone()),
+        })
+    }
+
+    /// The address the server listens on.
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Serve requests until `shutdown` completes.
+    pub async fn serve(self, shutdown: impl Future<Output = ()> + Send + 'static) -> Result<()> {
+        axum::serve(self.listener, self.router)
+            .with_graceful_shutdown(shutdown)
+            .await?;
+        Ok(())
+    }
+}
+
+/// Build the API router (useful for embedding into a larger application).
+pub fn router(gateway: SharedGateway, token: Option<String>) -> Router {
+    router_with_users(gateway, token, Vec::new())
+}
+
+/// Build the API router, also accepting the tokens of `users`.
+pub fn router_with_users(
+    gateway: SharedGateway,
+    token: Option<String>,
+    users: Vec<HttpApiUser>,
+) -> Router {
+    let state = ApiState {
+        gateway,
+        open: token.is_none() && users.is_empty(),
+        tokens: token
+            .map(|token| HttpApiUser {
+                identity: HTTP_API_CALLER.into(),
+                token,
+            })
+            .into_iter()
+            .chain(users)
+            .collect(),
+    };
+
+    Router::new()
+        .route("/channels", get(list_channels))
+        .route("/channels/{id}/diagnostics", get(channel_diagnostics))
+        .route("/channels/{id}/points", get(channel_points))
+        .route("/channels/{id}/control", post(channel_control))
+        .route("/channels/{id}/refresh", post(channel_refresh))
+        .route(
+            "/snapshot",
+            get(export_snapshot)
+                .post(import_snapshot)
+                .layer(DefaultBodyLimit::max(SNAPSHOT_BODY_LIMIT)),
+        )
+        .route("/audit", get(audit_history))
+        .route("/overrides", get(list_overrides))
+        .route(
+            "/channels/{id}/points/{point}/override",
+            put(set_override).delete(clear_override),
+        )
+        .layer(middleware::from_fn_with_state(state.clone(), require_token))
+        .with_state(state)
+}
+
+#[derive(Clone)]
+struct ApiState {
+    gateway: SharedGateway,
+    /// No token configured: every request is accepted.
+    open: bool,
+    /// Accepted tokens with the caller identity each stands for.
+    tokens: Arc<[HttpApiUser]>,
+}
+
+/// Caller identity of requests with the main token, or without a token.
+const HTTP_API_CALLER: &str = "http_api";
+
+/// Identity of the request's caller, set by `require_token`.
+#[derive(Clone)]
+struct ApiCaller(Arc<str>);
+
+/// Entry of `GET /channels`.
+#[derive(Debug, Serialize)]
+struct ChannelSummary {
+    id: u32,
+    name: String,
+    enabled: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    connection_state: Option<ConnectionState>,
+    capabilities: ChannelCapabilities,
+}
+
+/// Response of `POST /channels/{id}/control`.
+#[derive(Debug, Serialize)]
+struct ControlResponse {
+    accepted: usize,
+    /// Commands that were refused or failed, with the reason.
+    failures: Vec<CommandFailure>,
+}
+
+/// Entry of [`ControlResponse::failures`].
+#[derive(Debug, Serialize)]
+struct CommandFailure {
+    id: PointId,
+    error: String,
+}
+
+/// Response of `POST /channels/{id}/refresh`, `null`s for event-driven
+/// channels.
+#[derive(Debug, Serialize)]
+struct RefreshResponse {
+    points: Option<usize>,
+    failed: Option<usize>,
+}
+
+/// Response of `POST /snapshot`.
+#[derive(Debug, Serialize)]
+struct ImportResponse {
+    imported: usize,
+}
+
+/// Body of `PUT /channels/{id}/points/{point}/override`.
+#[derive(Debug, Deserialize)]
+struct OverrideRequest {
+    value: Value,
+    /// Lifetime of the override, unlimited if absent.
+    #[serde(default)]
+    expires_in_s: Option<u64>,
+}
+
+/// Response of `DELETE /channels/{id}/points/{point}/override`.
+#[derive(Debug, Serialize)]
+struct ClearOverrideResponse {
+    cleared: bool,
+}
+
+/// Response of `GET /audit`.
+#[derive(Debug, Serialize)]
+struct AuditResponse {
+    records: Vec<CommandAudit>,
+    /// Counters of the gateway's recorder, `null` if it does not record.
+    stats: Option<AuditStats>,
+}
+
+/// JSON error response.
+struct ApiError {
+    status: StatusCode,
+    message: String,
+    /// Code and category of the gateway error behind it.
+    report: Option<ErrorReport>,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+            report: None,
+        }
+    }
+}
+
+impl From<GatewayError> for ApiError {
+    fn from(error: GatewayError) -> Self {
+        let status = match &error {
+            GatewayError::Config(_)
+            | GatewayError::Unsupported(_)
+            | GatewayError::PointNotFound(_) => StatusCode::BAD_REQUEST,
+            GatewayError::PermissionDenied(_) => StatusCode::FORBIDDEN,
+            GatewayError::Interlock(_) => StatusCode::CONFLICT,
+            GatewayError::QueueFull(_) => StatusCode::TOO_MANY_REQUESTS,
+            e if e.needs_reconnect() => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::BAD_GATEWAY,
+        };
+        let report = error.report();
+        Self {
+            status,
+            message: report.message.clone(),
+            report: Some(report),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let mut body = serde_json::json!({ "error": self.message });
+        if let Some(report) = self.report {
+            body["code"] = report.code.into();
+            body["category"] = serde_json::json!(report.category);
+        }
+        (self.status, Json(body)).into_response()
+    }
+}
+
+type ApiResult<T> = std::result::Result<Json<T>, ApiError>;
+
+async fn require_token(
+    State(state): State<ApiState>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let identity = if state.open {
+        HTTP_API_CALLER
+    } else {
+        let user = request
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .and_then(|given| {
+                // Check every user so the time taken does not reveal which
+                // token matched
+                state.tokens.iter().fold(None, |found, user| {
+                    let matches = token_matches(given, &user.token);
+                    found.or(matches.then_some(user))
+                })
+            });
+        match user {
+            Some(user) => &user.identity,
+            None => {
+                return ApiError::new(StatusCode::UNAUTHORIZED, "missing or invalid bearer token")
+                    .into_response()
+            }
+        }
+    };
+    let caller = ApiCaller(Arc::from(identity));
+    request.extensions_mut().insert(caller);
+    next.run(request).await
+}
+
+/// Fail with `PermissionDenied` unless `caller` has at least `required`.
+fn require_level(
+    caller: &Caller,
+    required: PermissionLevel,
+    action: &str,
+) -> std::result::Result<(), ApiError> {
+    if caller.level < required {
+        return Err(GatewayError::PermissionDenied(format!(
+            "{} ({}): {} requires {}",
+            caller.identity, caller.level, action, required
+        ))
+        .into());
+    }
+    Ok(())
+}
+
+fn ensure_channel(gateway: &GatewayRuntime, id: u32) -> std::result::Result<(), ApiError> {
+    if gateway.channel_ids().contains(&id) {
+        Ok(())
+    } else {
+        Err(ApiError::new(
+            StatusCode::NOT_FOUND,
+            format!("unknown channel {}", id),
+        ))
+    }
+}
+
+async fn list_channels(State(state): State<ApiState>) -> ApiResult<Vec<ChannelSummary>> {
+    let snapshot = state.gateway.read().await.diagnostics_snapshot().await;
+    Ok(Json(
+        snapshot
+            .into_iter()
+            .map(|c| ChannelSummary {
+                id: c.channel_id,
+                name: c.name,
+                enabled: c.enabled,
+                connection_state: c.diagnostics.map(|d| d.connection_state),
+                capabilities: c.capabilities,
+            })
+            .collect(),
+    ))
+}
+
+async fn channel_diagnostics(
+    State(state): State<ApiState>,
+    Path(id): Path<u32>,
+) -> ApiResult<serde_json::Value> {
+    let gateway = state.gateway.read().await;
+    ensure_channel(&gateway, id)?;
+    let channel = gateway.channel_diagnostics(id).await?;
+    match (channel.diagnostics, channel.error) {
+        (Some(diagnostics), _) => {
+            Ok(Json(serde_json::to_value(diagnostics).map_err(|e| {
+                ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+            })?))
+        }
+        (None, error) => Err(ApiError::new(
+            StatusCode::BAD_GATEWAY,
+            error.unwrap_or_default(),
+        )),
+    }
+}
+
+async fn channel_points(
+    State(state): State<ApiState>,
+    Path(id): Path<u32>,
+) -> ApiResult<serde_json::Value> {
+    let store = {
+        let gateway = state.gateway.read().await;
+        ensure_channel(&gateway, id)?;
+        Arc::clone(gateway.store())
+    };
+
+    // Serialized straight from the shared snapshot, without copying points
+    let snapshot = store.snapshot(id).await?;
+    let mut points: Vec<&DataPoint> = snapshot.iter().collect();
+    points.sort_unstable_by_key(|p| p.id);
+    let configs = store.point_configs(id).await?;
+    let meta = point_meta_map(&configs);
+
+    let annotated: Vec<_> = points
+        .iter()
+        .map(|point| AnnotatedPoint::new(point, &meta))
+        .collect();
+    let mut body = serde_json::json!({
+        "points": serde_json::to_value(annotated)
+            .map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?,
+    });
+    if let Some(json_points) = body["points"].as_array_mut() {
+        let now = Utc::now();
+        for (json, point) in json_points.iter_mut().zip(points) {
+            let (Some(json), Ok(Some(age))) = (json.as_object_mut(), store.point_age(id, point.id))
+            else {
+                continue;
+            };
+            json.insert("last_change".into(), serde_json::json!(age.last_change));
+            json.insert(
+                "age_ms".into(),
+                serde_json::json!(age.age_at(now).as_millis() as u64),
+            );
+        }
+    }
+    Ok(Json(body))
+}
+
+async fn channel_control(
+    State(state): State<ApiState>,
+    Extension(ApiCaller(identity)): Extension<ApiCaller>,
+    Path(id): Path<u32>,
+    Json(commands): Json<Vec<ControlCommand>>,
+) -> ApiResult<ControlResponse> {
+    // write_control() carries latching, direct-execute commands only
+    if let Some(cmd) = commands
+        .iter()
+        .find(|c| c.pulse_duration_ms.is_some() || c.operate_mode != OperateMode::DirectExecute)
+    {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            format!(
+                "point {}: pulse and select-before-operate commands are not supported over HTTP",
+                cmd.id
+            ),
+        ));
+    }
+
+    let commands: Vec<(u32, f64)> = commands
+        .iter()
+        .map(|c| (c.id, if c.value { 1.0 } else { 0.0 }))
+        .collect();
+
+    let gateway = state.gateway.read().await;
+    ensure_channel(&gateway, id)?;
+    let caller = gateway.caller(&*identity);
+    let result = gateway.write_control_as(&caller, id, &commands).await?;
+    Ok(Json(ControlResponse {
+        accepted: result.success_count,
+        failures: result
+            .failures
+            .into_iter()
+            .map(|(id, error)| CommandFailure { id, error })
+            .collect(),
+    }))
+}
+
+async fn channel_refresh(
+    State(state): State<ApiState>,
+    Extension(ApiCaller(identity)): Extension<ApiCaller>,
+    Path(id): Path<u32>,
+) -> ApiResult<RefreshResponse> {
+    let gateway = state.gateway.read().await;
+    ensure_channel(&gateway, id)?;
+    // A refresh makes the channel talk to its device
+    require_level(
+        &gateway.caller(&*identity),
+        PermissionLevel::Operate,
+        "refresh",
+    )?;
+    let result = gateway.refresh(id).await?;
+    Ok(Json(RefreshResponse {
+        points: result.as_ref().map(|r| r.data.len()),
+        failed: result.as_ref().map(|r| r.failures.len()),
+    }))
+}
+
+async fn export_snapshot(State(state): State<ApiState>) -> ApiResult<StoreSnapshot> {
+    let store = Arc::clone(state.gateway.read().await.store());
+    Ok(Json(store.export_snapshot().await?))
+}
+
+async fn import_snapshot(
+    State(state): State<ApiState>,
+    Extension(ApiCaller(identity)): Extension<ApiCaller>,
+    body: String,
+) -> ApiResult<ImportResponse> {
+    let caller = state.gateway.read().await.caller(&*identity);
+    require_level(&caller, PermissionLevel::Tune, "snapshot import")?;
+    let snapshot = StoreSnapshot::from_json(&body).map_err(|e| match e {
+        GatewayError::InvalidData(message) => ApiError::new(StatusCode::BAD_REQUEST, message),
+        e => e.into(),
+    })?;
+    let store = Arc::clone(state.gateway.read().await.store());
+    let imported = store.import_snapshot(&snapshot).await?;
+    Ok(Json(ImportResponse { imported }))
+}
+
+async fn audit_history(
+    State(state): State<ApiState>,
+    RawQuery(query): RawQuery,
+) -> ApiResult<AuditResponse> {
+    let query = audit_query(query.as_deref().unwrap_or_default())
+        .map_err(|message| ApiError::new(StatusCode::BAD_REQUEST, message))?;
+    let (store, stats) = {
+        let gateway = state.gateway.read().await;
+        (Arc::clone(gateway.store()), gateway.audit_stats())
+    };
+    let records = store.query_audit(&query).await?;
+    Ok(Json(AuditResponse { records, stats }))
+}
+
+async fn list_overrides(State(state): State<ApiState>) -> ApiResult<Vec<PointOverride>> {
+    Ok(Json(state.gateway.read().await.overrides()))
+}
+
+async fn set_override(
+    State(state): State<ApiState>,
+    Extension(ApiCaller(identity)): Extension<ApiCaller>,
+    Path((id, point)): Path<(u32, PointId)>,
+    Json(request): Json<OverrideRequest>,
+) -> ApiResult<PointOverride> {
+    let gateway = state.gateway.read().await;
+    ensure_channel(&gateway, id)?;
+    let caller = gateway.caller(&*identity);
+    let expires_after = request.expires_in_s.map(std::time::Duration::from_secs);
+    let point_override = gateway
+        .set_override_as(&caller, id, point, request.value, expires_after)
+        .await?;
+    Ok(Json(point_override))
+}
+
+async fn clear_override(
+    State(state): State<ApiState>,
+    Extension(ApiCaller(identity)): Extension<ApiCaller>,
+    Path((id, point)): Path<(u32, PointId)>,
+) -> ApiResult<ClearOverrideResponse> {
+    let gateway = state.gateway.read().await;
+    ensure_channel(&gateway, id)?;
+    let caller = gateway.caller(&*identity);
+    let cleared = gateway.clear_override_as(&caller, id, point).await?;
+    Ok(Json(ClearOverrideResponse {
+        cleared: cleared.is_some(),
+    }))
+}
+
+/// Parse the query string of `GET /audit`.
+fn audit_query(raw: &str) -> std::result::Result<AuditQuery, String> {
+    let mut query = AuditQuery::new().with_limit(DEFAULT_AUDIT_LIMIT);
+    for pair in raw.split('&').filter(|p| !p.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let value = percent_decode(value).ok_or_else(|| format!("{}: invalid encoding", key))?;
+        let time = |value: &str| {
+            DateTime::parse_from_rfc3339(value)
+                .map(|t| t.with_timezone(&Utc))
+                .map_err(|e| format!("{}: {}", key, e))
+        };
+        let number = |value: &str| {
+            value
+                .parse::<u32>()
+                .map_err(|_| format!("{}: '{}' is not a number", key, value))
+        };
+        match key {
+            "from" => query.from = Some(time(&value)?),
+            "to" => query.to = Some(time(&value)?),
+            "channel" => query.channel_id = Some(number(&value)?),
+            "limit" => query.limit = Some((number(&value)? as usize).min(MAX_AUDIT_LIMIT)),
+            _ => return Err(format!("unknown parameter '{}'", key)),
+        }
+    }
+    Ok(query)
+}
+
+/// Decode `%XX` escapes. `+` is kept as is, as in timestamp offsets.
+fn percent_decode(value: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut rest = value.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::data::DataBatch;
+    use crate::gateway::GatewayConfig;
+    use crate::store::{DataStore, MemoryStore};
+    use axum::body::Body;
+    use tower::ServiceExt;
+
+    async fn gateway(store: Arc<MemoryStore>) -> SharedGateway {
+        let config: GatewayConfig = serde_json::from_value(serde_json::json!({
+            "gateway": { "name": "api" },
+            "channels": [{
+                "id": 1,
+                "name": "hub",
+                "protocol": "virtual",
+                "points": [{ "id": 10, "name": "temp", "unit": "°C", "address": "t" }]
+            }]
+        }))
+        .unwrap();
+        let mut runtime = GatewayRuntime::from_config(config, store).unwrap();
+        runtime.start().await.unwrap();
+        Arc::new(RwLock::new(runtime))
+    }
+
+    async fn call(
+        router: &Router,
+        method: &str,
+        uri: &str,
+        token: Option<&str>,
+        body: Option<serde_json::Value>,
+    ) -> (StatusCode, serde_json::Value) {
+        let mut request = Request::builder().method(method).uri(uri);
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        let request = match body {
+            Some(body) => request
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string())),
+            None => request.body(Body::empty()),
+        }
+        .unwrap();
+
+        let response = router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap_or_default())
+    }
+
+    #[tokio::test]
+    async fn test_endpoints() {
+        let store = Arc::new(MemoryStore::new());
+        store
+            .write_batch(1, &DataBatch::from_points(vec![DataPoint::new(10, 21.5)]))
+            .await
+            .unwrap();
+        let gateway = gateway(store).await;
+        let router = router(Arc::clone(&gateway), None);
+
+        let (status, body) = call(&router, "GET", "/channels", None, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body[0]["id"], 1);
+        assert_eq!(body[0]["enabled"], true);
+        assert_eq!(body[0]["capabilities"]["protocol"], "Virtual");
+        assert_eq!(
+            body[0]["capabilities"]["modes"],
+            serde_json::json!(["event_driven"])
+        );
+        assert_eq!(body[0]["capabilities"]["writable"], true);
+
+        let (status, body) = call(&router, "GET", "/channels/1/diagnostics", None, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["protocol"], "Virtual");
+
+        let (status, body) = call(&router, "GET", "/channels/1/points", None, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["points"][0]["id"], 10);
+        assert_eq!(body["points"][0]["unit"], "°C");
+        assert!(body["points"][0]["age_ms"].is_u64());
+        assert!(body["points"][0]["last_change"].is_string());
+
+        let (status, body) = call(
+            &router,
+            "POST",
+            "/channels/1/control",
+            None,
+            Some(serde_json::json!([{ "id": 10, "value": true }])),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["accepted"], 1);
+        assert_eq!(body["failures"], serde_json::json!([]));
+
+        let (status, _) = call(
+            &router,
+            "POST",
+            "/channels/1/control",
+            None,
+            Some(serde_json::json!([{ "id": 10, "value": true, "pulse_duration_ms": 500 }])),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        // Virtual channels have nothing to ask for
+        let (status, body) = call(&router, "POST", "/channels/1/refresh", None, None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["code"], 2003);
+
+        let (status, body) = call(&router, "GET", "/channels/9/points", None, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["error"], "unknown channel 9");
+
+        let (status, mut snapshot) = call(&router, "GET", "/snapshot", None, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(snapshot["channels"][0]["values"][0]["value"], 21.5);
+        snapshot["channels"][0]["values"][0]["timestamp"] = serde_json::json!(Utc::now());
+        snapshot["channels"][0]["values"][0]["value"] = serde_json::json!(19.0);
+        let (status, body) = call(&router, "POST", "/snapshot", None, Some(snapshot.clone())).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["imported"], 1);
+        let (_, body) = call(&router, "GET", "/channels/1/points", None, None).await;
+        assert_eq!(body["points"][0]["value"], 19.0);
+        assert_eq!(body["points"][0]["quality"], "last_known");
+
+        snapshot["format_version"] = serde_json::json!(99);
+        let (status, _) = call(&router, "POST", "/snapshot", None, Some(snapshot)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        // No audit history configured: the memory store still answers
+        let (status, body) = call(&router, "GET", "/audit?channel=1", None, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body["stats"].is_null());
+        let (status, _) = call(&router, "GET", "/audit?since=1", None, None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let uri = "/channels/1/points/10/override";
+        let body = serde_json::json!({ "value": 99.0, "expires_in_s": 3600 });
+        let (status, body) = call(&router, "PUT", uri, None, Some(body)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["set_by"], "http_api");
+        assert!(body["expires_at"].is_string());
+        let (_, body) = call(&router, "GET", "/channels/1/points", None, None).await;
+        assert_eq!(body["points"][0]["value"], 99.0);
+        assert_eq!(body["points"][0]["quality"], "substituted");
+        let (status, body) = call(&router, "GET", "/overrides", None, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body[0]["point_id"], 10);
+        let (status, body) = call(&router, "DELETE", uri, None, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["cleared"], true);
+        let (_, body) = call(&router, "DELETE", uri, None, None).await;
+        assert_eq!(body["cleared"], false);
+        let body = serde_json::json!({ "value": 1 });
+        let (status, _) = call(
+            &router,
+            "PUT",
+            "/channels/1/points/11/override",
+            None,
+            Some(body),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        gateway.write().await.stop().await.unwrap();
+    }
+
+    #[test]
+    fn test_audit_query() {
+        let query =
+            audit_query("from=2024-05-01T00%3A00%3A00%2B02:00&channel=3&limit=50000").unwrap();
+        assert_eq!(
+            query.from.unwrap().to_rfc3339(),
+            "2024-04-30T22:00:00+00:00"
+        );
+        assert_eq!(query.channel_id, Some(3));
+        assert_eq!(query.limit, Some(MAX_AUDIT_LIMIT));
+        assert_eq!(audit_query("").unwrap().limit, Some(DEFAULT_AUDIT_LIMIT));
+        assert!(audit_query("to=yesterday").is_err());
+        assert!(audit_query("channel=%zz").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_bearer_token() {
+        let gateway = gateway(Arc::new(MemoryStore::new())).await;
+        let router = router(Arc::clone(&gateway), Some("secret".into()));
+
+        let (status, _) = call(&router, "GET", "/channels", None, None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = call(&router, "GET", "/channels", Some("wrong"), None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = call(&router, "GET", "/channels", Some("secret"), None).await;
+        assert_eq!(status, StatusCode::OK);
+
+        gateway.write().await.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_user_authorization() {
+        let config: GatewayConfig = serde_json::from_value(serde_json::json!({
+            "gateway": {
+                "name": "api",
+                "authorization": { "callers": [{ "identity": "scada", "level": "operate" }] },
+                "audit": {}
+            },
+            "channels": [{
+                "id": 1,
+                "name": "hub",
+                "protocol": "virtual",
+                "points": [{ "id": 10, "name": "breaker", "address": "b" }]
+            }]
+        }))
+        .unwrap();
+        let mut runtime =
+            GatewayRuntime::from_config(config, Arc::new(MemoryStore::new())).unwrap();
+        runtime.start().await.unwrap();
+        let gateway = Arc::new(RwLock::new(runtime));
+        let users = vec![HttpApiUser {
+            identity: "scada".into(),
+            token: "scada-token".into(),
+        }];
+        let router = router_with_users(Arc::clone(&gateway), Some("secret".into()), users);
+        let command = || Some(serde_json::json!([{ "id": 10, "value": true }]));
+
+        // The main token stands for `http_api`, which is read-only here.
+        let (status, body) = call(
+            &router,
+            "POST",
+            "/channels/1/control",
+            Some("secret"),
+            command(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(
+            body["error"],
+            "Permission denied: http_api (read_only): point 10 requires operate"
+        );
+        assert_eq!(body["code"], 7003);
+        assert_eq!(body["category"], "config");
+        let (status, body) = call(
+            &router,
+            "POST",
+            "/channels/1/control",
+            Some("scada-token"),
+            command(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["accepted"], 1);
+        // Overrides need tune
+        let (status, _) = call(
+            &router,
+            "PUT",
+            "/channels/1/points/10/override",
+            Some("scada-token"),
+            Some(serde_json::json!({ "value": true })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        // So does importing a snapshot
+        let (status, snapshot) = call(&router, "GET", "/snapshot", Some("secret"), None).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, body) =
+            call(&router, "POST", "/snapshot", Some("secret"), Some(snapshot)).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(
+            body["error"],
+            "Permission denied: http_api (read_only): snapshot import requires tune"
+        );
+        // A refresh queries the device and needs operate
+        let (status, body) =
+            call(&router, "POST", "/channels/1/refresh", Some("secret"), None).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(
+            body["error"],
+            "Permission denied: http_api (read_only): refresh requires operate"
+        );
+        let (status, _) = call(&router, "GET", "/channels", Some("scada-token"), None).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = call(&router, "GET", "/channels", Some("other"), None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        // Records reach the store in the background
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        let (status, body) = call(&router, "GET", "/audit", Some("secret"), None).await;
+        assert_eq!(status, StatusCode::OK);
+        let results: Vec<&str> = body["records"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| r["result"].as_str().unwrap())
+            .collect();
+        assert_eq!(results, vec!["denied", "accepted"]);
+        assert_eq!(body["records"][1]["caller"], "scada");
+        assert_eq!(body["stats"]["recorded"], 2);
+
+        gateway.write().await.stop().await.unwrap();
+    }
+}
@@ -0,0 +1,134 @@
+//! Interlock checks of control and adjustment commands.
+//!
+//! Before [`GatewayRuntime`](super::GatewayRuntime) hands commands to a
+//! channel, every [`InterlockDef`] of the commanded points is evaluated
+//! against the store. If any command is blocked, none of the commands is
+//! sent and the error lists each blocked command with the reason, e.g.
+//!
+//! ```text
+//! Interlock violated: point 2001: channel 3 point 1005 is 1 (expected 0)
+//! ```
+
+use chrono::{DateTime, Utc};
+
+use crate::core::data::DataPoint;
+use crate::core::error::{GatewayError, Result};
+use crate::store::DataStore;
+
+use super::config::InterlockDef;
+
+/// Check the interlocks of `commands` `(point_id, value)` sent to
+/// `channel_id`, ages taken at `now`.
+///
+/// Returns `GatewayError::Interlock` listing every blocked command.
+pub(crate) async fn check(
+    store: &dyn DataStore,
+    channel_id: u32,
+    interlocks: &[InterlockDef],
+    commands: &[(u32, f64)],
+    now: DateTime<Utc>,
+) -> Result<()> {
+    if interlocks.is_empty() {
+        return Ok(());
+    }
+
+    let mut blocked = Vec::new();
+    for &(point_id, _) in commands {
+        for interlock in interlocks.iter().filter(|i| i.point_id == point_id) {
+            let check_channel = interlock.check_channel.unwrap_or(channel_id);
+            let value = store.read(check_channel, interlock.check_point).await?;
+            if let Some(reason) = interlock.violation(value.as_ref(), now) {
+                blocked.push(format!(
+                    "point {}: channel {} point {} {}",
+                    point_id, check_channel, interlock.check_point, reason
+                ));
+            }
+        }
+    }
+
+    if blocked.is_empty() {
+        Ok(())
+    } else {
+        #[cfg(feature = "tracing-support")]
+        tracing::warn!(channel_id, blocked = ?blocked, "Commands blocked by interlock");
+        Err(GatewayError::Interlock(blocked.join("; ")))
+    }
+}
+
+impl InterlockDef {
+    /// Why the checked point's stored value blocks the command, if it does.
+    fn violation(&self, point: Option<&DataPoint>, now: DateTime<Utc>) -> Option<String> {
+        let Some(point) = point else {
+            return Some("has no value".into());
+        };
+        if !point.quality.is_good() {
+            return Some(format!("has quality '{}'", point.quality));
+        }
+        let age_ms = (now - point.timestamp).num_milliseconds();
+        if self.max_age_ms > 0 && age_ms > self.max_age_ms as i64 {
+            return Some(format!("is {} ms old (max {} ms)", age_ms, self.max_age_ms));
+        }
+        let Some(value) = point.value.as_f64() else {
+            return Some(format!("has non-numeric value {:?}", point.value));
+        };
+
+        if let Some(expected) = self.equals {
+            return (value != expected).then(|| format!("is {} (expected {})", value, expected));
+        }
+        match (self.min, self.max) {
+            (Some(min), _) if value < min => Some(format!("is {} (min {})", value, min)),
+            (_, Some(max)) if value > max => Some(format!("is {} (max {})", value, max)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::quality::Quality;
+
+    fn interlock(json: serde_json::Value) -> InterlockDef {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn test_violation() {
+        let now = Utc::now();
+        let equals = interlock(serde_json::json!({
+            "point_id": 2, "check_point": 1, "equals": 0, "max_age_ms": 1000
+        }));
+        let open = DataPoint::new(1, false);
+        assert_eq!(equals.violation(Some(&open), now), None);
+        assert_eq!(
+            equals.violation(Some(&DataPoint::new(1, true)), now),
+            Some("is 1 (expected 0)".into())
+        );
+        assert_eq!(equals.violation(None, now), Some("has no value".into()));
+        assert_eq!(
+            equals.violation(Some(&open.clone().with_quality(Quality::CommFailure)), now),
+            Some(format!("has quality '{}'", Quality::CommFailure))
+        );
+        let later = open.timestamp + chrono::Duration::milliseconds(1500);
+        assert_eq!(
+            equals.violation(Some(&open), later),
+            Some("is 1500 ms old (max 1000 ms)".into())
+        );
+
+        let range = interlock(serde_json::json!({
+            "point_id": 2, "check_point": 1, "min": 380.0, "max": 420.0, "max_age_ms": 0
+        }));
+        assert_eq!(
+            range.violation(Some(&DataPoint::new(1, 400.0)), later),
+            None
+        );
+        assert_eq!(
+            range.violation(Some(&DataPoint::new(1, 430.0)), now),
+            Some("is 430 (max 420)".into())
+        );
+        assert_eq!(
+            range.violation(Some(&DataPoint::new(1, 12.0)), now),
+            Some("is 12 (min 380)".into())
+        );
+    }
+}
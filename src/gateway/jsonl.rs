@@ -0,0 +1,575 @@
+//! JSON Lines event output.
+//!
+//! When `jsonl_output` is enabled, [`GatewayRuntime`](super::GatewayRuntime)
+//! writes one JSON object per line for every batch it stores, every channel
+//! connection state change, every failed connect (with the error's
+//! [code and category](crate::core::error)), every redundancy switchover, every point that
+//! goes stale (unchanged for longer than its `max_age_ms`), every command
+//! feedback check and a diagnostics snapshot of each channel, with counts
+//! of its stored points per value kind and quality, every
+//! `diagnostics_interval_ms`:
+//!
+//! ```text
+//! {"type":"data","channel_id":1,"timestamp":"2024-05-01T12:00:00.000Z","points":[{"id":1001,"value":21.5,...}]}
+//! {"type":"connection_state","channel_id":1,"timestamp":"2024-05-01T12:00:01.000Z","state":"reconnecting"}
+//! {"type":"channel_error","channel_id":1,"timestamp":"2024-05-01T12:00:01.000Z","code":1001,"category":"connectivity","message":"Connection error: ..."}
+//! {"type":"diagnostics","channel_id":1,"timestamp":"2024-05-01T12:00:05.000Z","diagnostics":{...},"points":{"total":12,...}}
+//! {"type":"switchover","channel_id":1,"timestamp":"2024-05-01T12:00:05.500Z","active_channel_id":2}
+//! {"type":"point_stale","channel_id":1,"timestamp":"2024-05-01T12:00:05.800Z","point_id":1001,"last_change":"2024-05-01T11:59:00.000Z","age_ms":65800,"max_age_ms":60000}
+//! {"type":"command","channel_id":1,"timestamp":"2024-05-01T12:00:05.900Z","point_id":2001,"stage":"confirmed"}
+//! {"type":"events_dropped","timestamp":"2024-05-01T12:00:06.000Z","count":12}
+//! ```
+//!
+//! Output goes to stdout or to [`JsonlConfig::path`], rotated to
+//! `<path>.1`, `<path>.2`, ... once it reaches `max_file_bytes`.
+//!
+//! Emitting never blocks: events are queued on a bounded broadcast channel
+//! and written by a dedicated blocking thread. If the writer falls behind
+//! (e.g. a stalled disk), the oldest queued events are dropped, counted in
+//! [`JsonlSink::dropped()`] and reported with an `events_dropped` line.
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::sync::broadcast::{
+    self,
+    error::{RecvError, TryRecvError},
+};
+use tokio::task::JoinHandle;
+
+use crate::core::data::{BatchSummary, DataBatch, PointId};
+use crate::core::error::{ErrorReport, GatewayError, Result};
+use crate::core::traits::{CommandOutcome, ConnectionState, Diagnostics};
+use crate::store::PointAge;
+
+use super::audit::CommandAudit;
+use super::config::JsonlConfig;
+
+/// One line of JSON Lines output.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JsonlEvent {
+    /// A batch written to the store.
+    Data {
+        /// Channel id.
+        channel_id: u32,
+        /// When the batch was stored.
+        timestamp: DateTime<Utc>,
+        /// Stored points.
+        #[serde(flatten)]
+        batch: Arc<DataBatch>,
+    },
+
+    /// A channel connection state change.
+    ConnectionState {
+        /// Channel id.
+        channel_id: u32,
+        /// When the state changed.
+        timestamp: DateTime<Utc>,
+        /// New state.
+        state: ConnectionState,
+    },
+
+    /// A channel failed to connect.
+    ChannelError {
+        /// Channel id.
+        channel_id: u32,
+        /// When the connect failed.
+        timestamp: DateTime<Utc>,
+        /// Code, category and message of the error.
+        #[serde(flatten)]
+        error: ErrorReport,
+    },
+
+    /// Periodic diagnostics snapshot of a channel.
+    Diagnostics {
+        /// Channel id.
+        channel_id: u32,
+        /// When the snapshot was taken.
+        timestamp: DateTime<Utc>,
+        /// Channel diagnostics.
+        diagnostics: Diagnostics,
+        /// Counts of the channel's stored points per value kind and
+        /// quality.
+        points: Box<BatchSummary>,
+    },
+
+    /// A redundant device pair switched the channel serving its points.
+    Switchover {
+        /// Primary channel id (the points' channel id).
+        channel_id: u32,
+        /// When the switch happened.
+        timestamp: DateTime<Utc>,
+        /// Channel now serving the points (primary or backup).
+        active_channel_id: u32,
+    },
+
+    /// A point's value has not changed for longer than its `max_age_ms`;
+    /// it is stored as `Uncertain` until the value moves again.
+    PointStale {
+        /// Channel id the point is stored under.
+        channel_id: u32,
+        /// When the point was found stale.
+        timestamp: DateTime<Utc>,
+        /// Point id.
+        point_id: PointId,
+        /// Timestamp of the sample that last changed the value.
+        last_change: DateTime<Utc>,
+        /// Time since the last change, in milliseconds.
+        age_ms: u64,
+        /// Configured maximum age, in milliseconds.
+        max_age_ms: u64,
+    },
+
+    /// Outcome of a command's feedback check.
+    Command {
+        /// Channel id the command was sent to.
+        channel_id: u32,
+        /// When the outcome was known.
+        timestamp: DateTime<Utc>,
+        /// Commanded point and stage reached.
+        #[serde(flatten)]
+        outcome: CommandOutcome,
+    },
+
+    /// A command call with its caller and result (see
+    /// [`audit`](super::audit)).
+    CommandAudit(CommandAudit),
+
+    /// Events the writer had to drop because it fell behind.
+    EventsDropped {
+        /// When the loss was noticed.
+        timestamp: DateTime<Utc>,
+        /// Number of events lost.
+        count: u64,
+    },
+}
+
+impl JsonlEvent {
+    /// A stored batch.
+    pub fn data(channel_id: u32, batch: impl Into<Arc<DataBatch>>) -> Self {
+        Self::Data {
+            channel_id,
+            timestamp: Utc::now(),
+            batch: batch.into(),
+        }
+    }
+
+    /// A connection state change.
+    pub fn connection_state(channel_id: u32, state: ConnectionState) -> Self {
+        Self::ConnectionState {
+            channel_id,
+            timestamp: Utc::now(),
+            state,
+        }
+    }
+
+    /// A failed connect.
+    pub fn channel_error(channel_id: u32, error: &GatewayError) -> Self {
+        Self::ChannelError {
+            channel_id,
+            timestamp: Utc::now(),
+            error: error.report(),
+        }
+    }
+
+    /// A redundancy switchover.
+    pub fn switchover(channel_id: u32, active_channel_id: u32) -> Self {
+        Self::Switchover {
+            channel_id,
+            timestamp: Utc::now(),
+            active_channel_id,
+        }
+    }
+
+    /// A point found stale.
+    pub fn point_stale(channel_id: u32, point_id: PointId, age: PointAge, max_age_ms: u64) -> Self {
+        let timestamp = Utc::now();
+        Self::PointStale {
+            channel_id,
+            timestamp,
+            point_id,
+            last_change: age.last_change,
+            age_ms: age.age_at(timestamp).as_millis() as u64,
+            max_age_ms,
+        }
+    }
+
+    /// A command outcome.
+    pub fn command(channel_id: u32, outcome: CommandOutcome) -> Self {
+        Self::Command {
+            channel_id,
+            timestamp: Utc::now(),
+            outcome,
+        }
+    }
+
+    /// A diagnostics snapshot, with a summary of the stored points.
+    pub fn diagnostics(channel_id: u32, diagnostics: Diagnostics, points: BatchSummary) -> Self {
+        Self::Diagnostics {
+            channel_id,
+            timestamp: Utc::now(),
+            diagnostics,
+            points: Box::new(points),
+        }
+    }
+}
+
+/// Cheap, cloneable handle for emitting events to the writer.
+#[derive(Debug, Clone)]
+pub struct JsonlSink {
+    tx: broadcast::Sender<JsonlEvent>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl JsonlSink {
+    /// Queue an event for writing. Never blocks.
+    pub fn emit(&self, event: JsonlEvent) {
+        // Only fails once the writer is gone
+        let _ = self.tx.send(event);
+    }
+
+    /// Number of events dropped because the writer fell behind.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+/// A running JSON Lines writer.
+///
+/// The writer thread exits once the [`JsonlOutput`] and every
+/// [`JsonlSink`] cloned from it are gone, after writing what is queued.
+#[derive(Debug)]
+pub struct JsonlOutput {
+    sink: JsonlSink,
+    handle: JoinHandle<()>,
+}
+
+impl JsonlOutput {
+    /// Open the output described by `config` and start the writer thread.
+    ///
+    /// Must be called from within a Tokio runtime.
+    pub fn spawn(config: &JsonlConfig) -> Result<Self> {
+        let output = match &config.path {
+            Some(path) => Output::File(RotatingFile::open(
+                path.clone(),
+                config.max_file_bytes,
+                config.max_files,
+            )?),
+            None => Output::Stdout(io::stdout()),
+        };
+
+        let (tx, rx) = broadcast::channel(config.queue_capacity.max(1));
+        let dropped = Arc::new(AtomicU64::new(0));
+        let writer = Writer {
+            output,
+            dropped: Arc::clone(&dropped),
+        };
+
+        Ok(Self {
+            sink: JsonlSink { tx, dropped },
+            handle: tokio::task::spawn_blocking(move || writer.run(rx)),
+        })
+    }
+
+    /// Handle for emitting events.
+    pub fn sink(&self) -> &JsonlSink {
+        &self.sink
+    }
+
+    /// Drop this handle and wait until the writer has flushed and exited.
+    ///
+    /// Waits for every other [`JsonlSink`] clone to be dropped as well.
+    pub async fn close(self) {
+        let Self { sink, handle } = self;
+        drop(sink);
+        let _ = handle.await;
+    }
+}
+
+enum Output {
+    Stdout(io::Stdout),
+    File(RotatingFile),
+}
+
+impl Output {
+    fn write_line(&mut self, line: &[u8]) -> io::Result<()> {
+        match self {
+            Self::Stdout(stdout) => stdout.lock().write_all(line),
+            Self::File(file) => file.write_line(line),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Stdout(stdout) => stdout.flush(),
+            Self::File(file) => file.file.flush(),
+        }
+    }
+}
+
+/// Writer thread state.
+struct Writer {
+    output: Output,
+    dropped: Arc<AtomicU64>,
+}
+
+impl Writer {
+    fn run(mut self, mut rx: broadcast::Receiver<JsonlEvent>) {
+        while let Some(next) = self.next(&mut rx) {
+            match next {
+                Ok(event) => self.write(&event),
+                Err(count) => {
+                    self.dropped.fetch_add(count, Ordering::Relaxed);
+                    self.write(&JsonlEvent::EventsDropped {
+                        timestamp: Utc::now(),
+                        count,
+                    });
+                }
+            }
+        }
+        report(self.output.flush());
+    }
+
+    /// Next queued event, `Err(n)` after `n` events were dropped, `None`
+    /// once every sink is gone and the queue is drained.
+    fn next(
+        &mut self,
+        rx: &mut broadcast::Receiver<JsonlEvent>,
+    ) -> Option<std::result::Result<JsonlEvent, u64>> {
+        let result = match rx.try_recv() {
+            Ok(event) => Ok(event),
+            Err(TryRecvError::Empty) => {
+                // Flush only once the queue is drained
+                report(self.output.flush());
+                rx.blocking_recv()
+            }
+            Err(TryRecvError::Lagged(count)) => Err(RecvError::Lagged(count)),
+            Err(TryRecvError::Closed) => Err(RecvError::Closed),
+        };
+        match result {
+            Ok(event) => Some(Ok(event)),
+            Err(RecvError::Lagged(count)) => Some(Err(count)),
+            Err(RecvError::Closed) => None,
+        }
+    }
+
+    fn write(&mut self, event: &JsonlEvent) {
+        let mut line = match serde_json::to_vec(event) {
+            Ok(line) => line,
+            Err(_e) => {
+                #[cfg(feature = "tracing-support")]
+                tracing::error!("JSON Lines serialization failed: {}", _e);
+                return;
+            }
+        };
+        line.push(b'\n');
+        report(self.output.write_line(&line));
+    }
+}
+
+fn report(result: io::Result<()>) {
+    if let Err(_e) = result {
+        #[cfg(feature = "tracing-support")]
+        tracing::error!("JSON Lines write failed: {}", _e);
+    }
+}
+
+/// Size-rotated output file.
+struct RotatingFile {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    file: BufWriter<File>,
+    size: u64,
+}
+
+impl RotatingFile {
+    fn open(path: PathBuf, max_bytes: u64, max_files: usize) -> io::Result<Self> {
+        let file = File::options().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path,
+            max_bytes,
+            max_files,
+            file: BufWriter::new(file),
+            size,
+        })
+    }
+
+    fn write_line(&mut self, line: &[u8]) -> io::Result<()> {
+        if self.size > 0 && self.size + line.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        self.file.write_all(line)?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    /// Shift `path.N` to `path.N+1` (dropping the oldest) and start afresh.
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        if self.max_files == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            for n in (1..self.max_files).rev() {
+                rename_if_exists(&rotated(&self.path, n), &rotated(&self.path, n + 1))?;
+            }
+            fs::rename(&self.path, rotated(&self.path, 1))?;
+        }
+
+        self.file = BufWriter::new(File::create(&self.path)?);
+        self.size = 0;
+        Ok(())
+    }
+}
+
+fn rotated(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
+
+fn rename_if_exists(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::data::DataPoint;
+
+    /// Unique file path in the system temp dir; the file and its rotations
+    /// are removed on drop.
+    struct TempPath(PathBuf);
+
+    impl TempPath {
+        fn new(name: &str) -> Self {
+            let nanos = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos();
+            Self(std::env::temp_dir().join(format!(
+                "igw-{}-{}-{}.jsonl",
+                name,
+                std::process::id(),
+                nanos
+            )))
+        }
+    }
+
+    impl Drop for TempPath {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+            for n in 1..10 {
+                let _ = fs::remove_file(rotated(&self.0, n));
+            }
+        }
+    }
+
+    fn lines(path: &Path) -> Vec<serde_json::Value> {
+        fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_event_lines() {
+        let temp = TempPath::new("events");
+        let output = JsonlOutput::spawn(&JsonlConfig {
+            path: Some(temp.0.clone()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let sink = output.sink().clone();
+        sink.emit(JsonlEvent::data(
+            3,
+            DataBatch::from_points(vec![DataPoint::new(1001, 21.5)]),
+        ));
+        sink.emit(JsonlEvent::connection_state(
+            3,
+            ConnectionState::Reconnecting,
+        ));
+        sink.emit(JsonlEvent::diagnostics(
+            3,
+            Diagnostics::new("modbus"),
+            BatchSummary::default(),
+        ));
+        sink.emit(JsonlEvent::channel_error(3, &GatewayError::NotConnected));
+        drop(sink);
+        output.close().await;
+
+        let lines = lines(&temp.0);
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0]["type"], "data");
+        assert_eq!(lines[0]["channel_id"], 3);
+        assert_eq!(lines[0]["points"][0]["id"], 1001);
+        assert!(lines[0]["timestamp"]
+            .as_str()
+            .unwrap()
+            .parse::<DateTime<Utc>>()
+            .is_ok());
+        assert_eq!(lines[1]["type"], "connection_state");
+        assert_eq!(lines[1]["state"], "reconnecting");
+        assert_eq!(lines[2]["type"], "diagnostics");
+        assert_eq!(lines[2]["diagnostics"]["protocol"], "modbus");
+        assert_eq!(lines[2]["points"]["total"], 0);
+        assert_eq!(lines[3]["type"], "channel_error");
+        assert_eq!(lines[3]["code"], 1002);
+        assert_eq!(lines[3]["category"], "connectivity");
+        assert_eq!(lines[3]["message"], "Not connected");
+    }
+
+    #[test]
+    fn test_rotation() {
+        let temp = TempPath::new("rotate");
+        let mut file = RotatingFile::open(temp.0.clone(), 10, 2).unwrap();
+        for line in ["aaaaaa\n", "bbbbbb\n", "cccccc\n", "dddddd\n"] {
+            file.write_line(line.as_bytes()).unwrap();
+        }
+        file.file.flush().unwrap();
+
+        let read = |path: PathBuf| fs::read_to_string(path).unwrap();
+        assert_eq!(read(temp.0.clone()), "dddddd\n");
+        assert_eq!(read(rotated(&temp.0, 1)), "cccccc\n");
+        assert_eq!(read(rotated(&temp.0, 2)), "bbbbbb\n");
+        assert!(!rotated(&temp.0, 3).exists());
+    }
+
+    #[test]
+    fn test_overflow_drops_oldest() {
+        let temp = TempPath::new("overflow");
+        let (tx, rx) = broadcast::channel(2);
+        let dropped = Arc::new(AtomicU64::new(0));
+        let writer = Writer {
+            output: Output::File(RotatingFile::open(temp.0.clone(), u64::MAX, 0).unwrap()),
+            dropped: Arc::clone(&dropped),
+        };
+
+        // Writer not draining yet: only the newest two survive
+        for id in 1..=5 {
+            tx.send(JsonlEvent::connection_state(id, ConnectionState::Connected))
+                .unwrap();
+        }
+        drop(tx);
+        writer.run(rx);
+
+        assert_eq!(dropped.load(Ordering::Relaxed), 3);
+        let lines = lines(&temp.0);
+        assert_eq!(lines[0]["type"], "events_dropped");
+        assert_eq!(lines[0]["count"], 3);
+        assert_eq!(lines[1]["channel_id"], 4);
+        assert_eq!(lines[2]["channel_id"], 5);
+    }
+}
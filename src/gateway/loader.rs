@@ -0,0 +1,487 @@
+//! Configuration file loading (feature `cli`).
+//!
+//! On top of plain TOML, [`GatewayConfig::parse()`] and
+//! [`GatewayConfig::from_file()`] support:
+//!
+//! - **Environment variables** in string values: `${VAR}` is replaced with
+//!   the variable's value and `${VAR:-default}` falls back to `default` if
+//!   `VAR` is unset or empty. `$${` yields a literal `${`. Only string values
+//!   are substituted, never keys or comments.
+//! - **Includes** (files only): a top-level `include = ["channels/*.toml"]`
+//!   lists files or glob patterns, relative to the including file, whose
+//!   `[[channels]]` are appended to the main file's channels. Included files
+//!   may only define channels; a channel id defined twice is an error naming
+//!   both files.
+//!
+//! ```toml
+//! include = ["channels/*.toml"]
+//!
+//! [gateway]
+//! name = "${SITE_NAME}"
+//!
+//! [[channels]]
+//! id = 1
+//! name = "PLC"
+//! protocol = "modbus"
+//!
+//! [channels.parameters]
+//! host = "${PLC_HOST:-192.168.1.100}"
+//! ```
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use toml::Value;
+
+use super::config::{ConfigError, GatewayConfig};
+
+/// Label used in errors for configs parsed from a string.
+const STRING_ORIGIN: &str = "<config>";
+
+/// Parse a configuration string (includes are not allowed).
+pub(crate) fn parse_str(content: &str) -> Result<GatewayConfig, ConfigError> {
+    let root = parse_toml(content, STRING_ORIGIN, &env_var)?;
+    if root.get("include").is_some() {
+        return Err(ConfigError::Include {
+            file: STRING_ORIGIN.into(),
+            message: "'include' is only supported when loading from a file".into(),
+        });
+    }
+    into_config(root, STRING_ORIGIN)
+}
+
+/// Load a configuration file and everything it includes.
+pub(crate) fn load_file(path: &Path) -> Result<GatewayConfig, ConfigError> {
+    let origin = path.display().to_string();
+    let mut root = read_toml(path, &env_var)?;
+    let patterns = take_includes(&mut root, &origin)?;
+
+    // Channel id -> file that defines it
+    let mut defined_in: HashMap<i64, String> = HashMap::new();
+    for id in channel_ids(&root) {
+        defined_in.entry(id).or_insert_with(|| origin.clone());
+    }
+
+    let base = path.parent().unwrap_or(Path::new(""));
+    let mut included = Vec::new();
+    for pattern in &patterns {
+        for file in expand(base, pattern, &origin)? {
+            let file_origin = file.display().to_string();
+            let channels = included_channels(read_toml(&file, &env_var)?, &file_origin)?;
+            for channel in channels {
+                if let Some(id) = channel.get("id").and_then(Value::as_integer) {
+                    if let Some(first) = defined_in.insert(id, file_origin.clone()) {
+                        return Err(ConfigError::Include {
+                            file: file_origin,
+                            message: format!(
+                                "duplicate channel id {} (already defined in {})",
+                                id, first
+                            ),
+                        });
+                    }
+                }
+                included.push(channel);
+            }
+        }
+    }
+
+    if !included.is_empty() {
+        if let Value::Table(table) = &mut root {
+            let channels = table
+                .entry("channels")
+                .or_insert_with(|| Value::Array(Vec::new()));
+            match channels {
+                Value::Array(channels) => channels.extend(included),
+                _ => {
+                    return Err(ConfigError::Parse(format!(
+                        "{}: 'channels' must be an array of tables",
+                        origin
+                    )))
+                }
+            }
+        }
+    }
+
+    into_config(root, &origin)
+}
+
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(name).ok()
+}
+
+fn read_toml(path: &Path, env: &dyn Fn(&str) -> Option<String>) -> Result<Value, ConfigError> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| ConfigError::Io(format!("{}: {}", path.display(), e)))?;
+    parse_toml(&content, &path.display().to_string(), env)
+}
+
+fn parse_toml(
+    content: &str,
+    origin: &str,
+    env: &dyn Fn(&str) -> Option<String>,
+) -> Result<Value, ConfigError> {
+    let mut root: Value =
+        toml::from_str(content).map_err(|e| ConfigError::Parse(format!("{}: {}", origin, e)))?;
+    substitute_value(&mut root, origin, env)?;
+    Ok(root)
+}
+
+/// Deserialize the merged document, expand device templates and resolve
+/// shared transports.
+fn into_config(root: Value, origin: &str) -> Result<GatewayConfig, ConfigError> {
+    let mut config: GatewayConfig = root
+        .try_into()
+        .map_err(|e| ConfigError::Parse(format!("{}: {}", origin, e)))?;
+    // Channels that cannot be expanded or resolved keep their template or
+    // transport reference; validate() reports why
+    config.expand_templates();
+    config.resolve_transports();
+    Ok(config)
+}
+
+/// Remove and return the top-level `include` list.
+fn take_includes(root: &mut Value, origin: &str) -> Result<Vec<String>, ConfigError> {
+    let Some(include) = root.as_table_mut().and_then(|t| t.remove("include")) else {
+        return Ok(Vec::new());
+    };
+    let invalid = || ConfigError::Include {
+        file: origin.into(),
+        message: "'include' must be an array of file names or glob patterns".into(),
+    };
+    match include {
+        Value::Array(items) => items
+            .into_iter()
+            .map(|item| match item {
+                Value::String(pattern) => Ok(pattern),
+                _ => Err(invalid()),
+            })
+            .collect(),
+        _ => Err(invalid()),
+    }
+}
+
+fn channel_ids(root: &Value) -> Vec<i64> {
+    root.get("channels")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|c| c.get("id").and_then(Value::as_integer))
+        .collect()
+}
+
+/// The `[[channels]]` of an included file, which must define nothing else.
+fn included_channels(root: Value, origin: &str) -> Result<Vec<Value>, ConfigError> {
+    let error = |message: &str| ConfigError::Include {
+        file: origin.into(),
+        message: message.into(),
+    };
+    let Value::Table(mut table) = root else {
+        return Err(error("expected a TOML table"));
+    };
+    let channels = table.remove("channels");
+    if let Some(key) = table.keys().next() {
+        return Err(error(&format!(
+            "only [[channels]] may be defined in included files, found '{}'",
+            key
+        )));
+    }
+    match channels {
+        None => Ok(Vec::new()),
+        Some(Value::Array(channels)) => Ok(channels),
+        Some(_) => Err(error("'channels' must be an array of tables")),
+    }
+}
+
+/// Files matching an include pattern, sorted for a stable channel order.
+///
+/// A pattern without wildcards must name an existing file; a glob may match
+/// nothing.
+fn expand(base: &Path, pattern: &str, origin: &str) -> Result<Vec<PathBuf>, ConfigError> {
+    let full = base.join(pattern);
+    let error = |message: String| ConfigError::Include {
+        file: origin.into(),
+        message,
+    };
+
+    if !pattern.contains(['*', '?', '[']) {
+        if !full.is_file() {
+            return Err(error(format!(
+                "included file '{}' not found",
+                full.display()
+            )));
+        }
+        return Ok(vec![full]);
+    }
+
+    let full = full.to_string_lossy();
+    let mut files = glob::glob(&full)
+        .map_err(|e| error(format!("invalid include pattern '{}': {}", pattern, e)))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| error(format!("include '{}': {}", pattern, e)))?;
+    files.retain(|f| f.is_file());
+    files.sort();
+    Ok(files)
+}
+
+/// Substitute `${...}` references in every string value.
+fn substitute_value(
+    value: &mut Value,
+    origin: &str,
+    env: &dyn Fn(&str) -> Option<String>,
+) -> Result<(), ConfigError> {
+    match value {
+        Value::String(s) if s.contains("${") => *s = substitute(s, origin, env)?,
+        Value::Array(items) => {
+            for item in items {
+                substitute_value(item, origin, env)?;
+            }
+        }
+        Value::Table(table) => {
+            for (_, item) in table.iter_mut() {
+                substitute_value(item, origin, env)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Expand `${VAR}` / `${VAR:-default}` in one string.
+fn substitute(
+    input: &str,
+    origin: &str,
+    env: &dyn Fn(&str) -> Option<String>,
+) -> Result<String, ConfigError> {
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(start) = rest.find('$') {
+        out.push_str(&rest[..start]);
+        let tail = &rest[start..];
+
+        if let Some(after) = tail.strip_prefix("$${") {
+            out.push_str("${");
+            rest = after;
+            continue;
+        }
+        let Some(body) = tail.strip_prefix("${") else {
+            out.push('$');
+            rest = &tail[1..];
+            continue;
+        };
+        let Some(end) = body.find('}') else {
+            return Err(ConfigError::Parse(format!(
+                "{}: unterminated '${{' in \"{}\"",
+                origin, input
+            )));
+        };
+
+        let reference = &body[..end];
+        let (name, default) = match reference.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (reference, None),
+        };
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(ConfigError::Parse(format!(
+                "{}: invalid variable reference '${{{}}}'",
+                origin, reference
+            )));
+        }
+
+        let value = env(name).filter(|v| !v.is_empty() || default.is_none());
+        match (value, default) {
+            (Some(value), _) => out.push_str(&value),
+            (None, Some(default)) => out.push_str(default),
+            (None, None) => {
+                return Err(ConfigError::MissingEnvVar {
+                    file: origin.into(),
+                    variable: name.into(),
+                })
+            }
+        }
+        rest = &body[end + 1..];
+    }
+
+    out.push_str(rest);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(name: &str) -> Option<String> {
+        match name {
+            "HOST" => Some("10.0.0.5".into()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_substitute() {
+        let sub = |s: &str| substitute(s, "site.toml", &env);
+        assert_eq!(sub("${HOST}:502").unwrap(), "10.0.0.5:502");
+        assert_eq!(sub("${PORT:-502}").unwrap(), "502");
+        assert_eq!(sub("${EMPTY:-fallback}").unwrap(), "fallback");
+        assert_eq!(sub("${EMPTY}").unwrap(), "");
+        assert_eq!(sub("cost $5, $${HOST}").unwrap(), "cost $5, ${HOST}");
+
+        let err = sub("${SITE_NAME}").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "site.toml: environment variable 'SITE_NAME' is not set"
+        );
+        assert!(sub("${HOST").is_err());
+        assert!(sub("${BAD-NAME}").is_err());
+    }
+
+    #[test]
+    fn test_parse_substitutes_strings_only() {
+        let root = parse_toml(
+            r#"
+# ${NOT_SUBSTITUTED} in a comment
+[gateway]
+name = "${HOST}"
+
+[[channels]]
+id = 1
+name = "plc"
+protocol = "modbus"
+parameters = { host = "${HOST}", port = 502 }
+"#,
+            STRING_ORIGIN,
+            &env,
+        )
+        .unwrap();
+        let config = into_config(root, STRING_ORIGIN).unwrap();
+        assert_eq!(config.gateway.name, "10.0.0.5");
+        assert_eq!(config.channels[0].parameters["host"], "10.0.0.5");
+        assert_eq!(config.channels[0].parameters["port"], 502);
+    }
+
+    #[test]
+    fn test_parse_expands_templates() {
+        let config = parse_str(
+            r#"
+[gateway]
+name = "site"
+
+[templates.meter]
+parameters = { buffer_size = 64 }
+
+[[templates.meter.points]]
+id = 1
+name = "power"
+address = "power"
+transform = { scale = 0.1 }
+
+[[channels]]
+id = 1
+name = "meter-1"
+protocol = "virtual"
+template = "meter"
+template_overrides = { point_id_offset = 100, address_prefix = "m1.", transforms = { "1" = { offset = 5.0 } } }
+
+[[channels]]
+id = 2
+name = "meter-2"
+protocol = "virtual"
+template = "meter"
+template_overrides = { point_id_offset = 200, address_prefix = "m2." }
+"#,
+        )
+        .unwrap();
+        assert!(config.validate().is_empty());
+
+        let points: Vec<_> = config
+            .channels
+            .iter()
+            .map(|c| {
+                (
+                    c.points[0].id,
+                    c.points[0].address.as_str(),
+                    c.points[0].transform.offset,
+                )
+            })
+            .collect();
+        assert_eq!(points, vec![(101, "m1.power", 5.0), (201, "m2.power", 0.0)]);
+        assert!(config.channels.iter().all(|c| c.template.is_none()));
+        assert_eq!(config.channels[1].points[0].transform.scale, 0.1);
+        assert_eq!(config.channels[1].parameters["buffer_size"], 64);
+    }
+
+    /// Temporary directory removed on drop.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let nanos = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos();
+            let path =
+                std::env::temp_dir().join(format!("igw-{}-{}-{}", name, std::process::id(), nanos));
+            std::fs::create_dir_all(path.join("channels")).unwrap();
+            Self(path)
+        }
+
+        fn write(&self, name: &str, content: &str) -> PathBuf {
+            let path = self.0.join(name);
+            std::fs::write(&path, content).unwrap();
+            path
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn channel(id: u32) -> String {
+        format!(
+            "[[channels]]\nid = {}\nname = \"ch{}\"\nprotocol = \"virtual\"\n",
+            id, id
+        )
+    }
+
+    #[test]
+    fn test_includes() {
+        let dir = TempDir::new("include");
+        let main = dir.write(
+            "gateway.toml",
+            &format!(
+                "include = [\"channels/*.toml\"]\n[gateway]\nname = \"site\"\n{}",
+                channel(1)
+            ),
+        );
+        dir.write("channels/b.toml", &channel(3));
+        dir.write("channels/a.toml", &format!("{}{}", channel(2), channel(4)));
+
+        let config = load_file(&main).unwrap();
+        let ids: Vec<u32> = config.channels.iter().map(|c| c.id).collect();
+        assert_eq!(ids, vec![1, 2, 4, 3]);
+
+        // Duplicate id across files names both
+        dir.write("channels/c.toml", &channel(2));
+        let err = load_file(&main).unwrap_err().to_string();
+        assert!(err.contains("c.toml"), "{}", err);
+        assert!(err.contains("duplicate channel id 2 (already defined in"));
+        assert!(err.contains("a.toml"), "{}", err);
+
+        // Included files may only define channels
+        dir.write("channels/c.toml", "[gateway]\nname = \"x\"\n");
+        let err = load_file(&main).unwrap_err().to_string();
+        assert!(err.contains("only [[channels]] may be defined"), "{}", err);
+
+        // A missing literal include is an error
+        let main = dir.write(
+            "missing.toml",
+            "include = [\"nope.toml\"]\n[gateway]\nname = \"site\"\n",
+        );
+        let err = load_file(&main).unwrap_err().to_string();
+        assert!(err.contains("missing.toml"), "{}", err);
+        assert!(err.contains("nope.toml' not found"), "{}", err);
+    }
+}
@@ -0,0 +1,4993 @@
+//! Gateway runtime orchestrator.
+//!
+//! [`GatewayRuntime`] owns the channels built from a [`GatewayConfig`], runs
+//! one supervisor task per channel and writes everything the channels
+//! produce into a [`DataStore`].
+//!
+//! Each channel task:
+//! 1. connects, retrying with exponential backoff while the error
+//!    [`needs_reconnect()`](GatewayError::needs_reconnect) (its
+//!    [`category()`](GatewayError::category) is `Connectivity`) or
+//!    [`is_retryable()`](GatewayError::is_retryable) (other errors stop the
+//!    channel);
+//! 2. polls every `poll_interval_ms` (channel override, else the gateway
+//!    default), or, for event-driven channels, starts event streaming and
+//!    pumps `DataUpdate` batches. Polls never overlap: if a poll takes
+//!    longer than the interval, the missed cycles are skipped, counted in
+//!    [`Diagnostics::poll_overruns`], and the next poll runs one full
+//!    interval after the slow one finished;
+//! 3. writes data into the store; points that failed to read keep their
+//!    stored value with `Quality::CommFailure`, and all stored points of a
+//!    channel are marked `Quality::NotConnected` while it is down;
+//! 4. reconnects when the connection is lost (a poll returns no data and the
+//!    channel reports it is not connected, or the event stream ends).
+//!
+//! Before the first connect, point configurations are written to the store
+//! and the channel is offered the store's last-known values via
+//! [`ChannelRuntime::restore()`].
+//!
+//! # Concurrency
+//!
+//! Channels are independent: each runs in a task of its own, so a slow or
+//! unreachable device only delays its own channel. With
+//! `max_concurrent_polls` set, at most that many connect attempts and polls
+//! are in flight across the runtime at once; the others wait for a slot,
+//! counted in `Diagnostics::extra` (`polls_throttled`).
+//!
+//! A panic in a channel task (e.g. in a protocol decoder) ends that channel
+//! only. It is logged, the channel's points are marked
+//! `Quality::NotConnected` and the channel reports `ConnectionState::Error`
+//! with the panic message as `last_error` (and a `task_panics` count in
+//! `Diagnostics::extra`) until it is restarted, by an operator or by the
+//! watchdog.
+//!
+//! # Bus scheduling
+//!
+//! Polling channels naming the same `bus` (e.g. devices on one RS-485
+//! trunk) are staggered across their poll interval rather than all polling
+//! at once; a channel's `poll_offset_ms` fixes its phase explicitly (see
+//! [`schedule`](super::schedule)). Offsets are taken when a channel starts
+//! and are kept across reconnects. After an overrun, a phased channel
+//! resumes at its next slot. Each bus channel reports its offset and the
+//! most polls ever in flight on its bus in `Diagnostics::extra`
+//! (`poll_offset_ms`, `bus_max_concurrent_polls`).
+//!
+//! # Event buffering
+//!
+//! Events of an event-driven channel wait in a bounded buffer
+//! ([`EventBufferConfig`](super::config::EventBufferConfig)) while the store
+//! is slow. Beyond its capacity, updates are coalesced to the newest value
+//! per point by default; dropped and coalesced events are counted in
+//! `Diagnostics::extra` (`events_dropped`, `events_coalesced`).
+//!
+//! # Change detection
+//!
+//! A polling channel with `change_detection` (see
+//! [`ChangeDetectionConfig`](super::config::ChangeDetectionConfig)) stores
+//! only the points of a poll whose value or quality changed since they were
+//! last stored, plus a full poll result after every connect and every
+//! `full_refresh_ms` (see [`delta`](super::delta)). Dropped points are
+//! counted in `Diagnostics::extra` (`points_suppressed`).
+//!
+//! # Stale points
+//!
+//! Points with a `max_age_ms` are checked against the store's
+//! [`point_age()`](DataStore::point_age): once a point's value has not
+//! changed for longer than that (a flatlined sensor, or a device that
+//! stopped sending), it is stored as `Quality::Uncertain`, logged and
+//! written as a `point_stale` JSON Lines event. Polls returning the same
+//! value keep it `Uncertain`; the next changed value is stored as read.
+//! The check runs before change detection, so full refreshes store a stale
+//! point as `Uncertain` too.
+//!
+//! # Alarms
+//!
+//! Limit alarms configured on points (see [`alarm`](crate::core::alarm))
+//! are evaluated in one place, as batches are written to the store: the
+//! signal points are stored and written to the JSON Lines output together
+//! with the samples they were evaluated from, so quality changes (e.g.
+//! `CommFailure` while a channel is down) reach the signals too. The alarm
+//! state is kept by the runtime and survives channel reconnects and
+//! restarts.
+//!
+//! # Watchdog
+//!
+//! With `[gateway.watchdog]` configured, [`GatewayRuntime::check_watchdog()`]
+//! (run periodically by [`GatewayRuntime::spawn_watchdog()`]) restarts
+//! channels whose task stopped making progress and parks channels that keep
+//! stalling. Restart counts appear in `Diagnostics::extra` as
+//! `watchdog_restarts`.
+//!
+//! # Heartbeat
+//!
+//! With `[gateway.heartbeat]` configured, a task of its own toggles or
+//! pulses a control point for an external safety relay while the runtime
+//! runs, and stops as soon as a monitored channel stops returning data
+//! (see [`heartbeat`](super::heartbeat)). Its state is returned by
+//! [`GatewayRuntime::heartbeat_status()`] and shown in the heartbeat
+//! channel's `Diagnostics::extra` (`heartbeat`).
+//!
+//! # Availability
+//!
+//! Every connection state change of a channel is timestamped, whichever
+//! protocol it uses. `Diagnostics::extra` carries `connected_since`,
+//! `state_changed_at`, `disconnect_count_24h` and `availability_percent`
+//! (share of the time since the channel was created spent connected);
+//! [`GatewayRuntime::connection_history()`] returns the last transitions.
+//!
+//! # Redundancy
+//!
+//! A channel with `backup_of = <primary id>` backs up a redundant device.
+//! While the primary is healthy the backup stays connected without polling
+//! (`standby = "warm"`) or disconnected (`"cold"`). Once the primary has
+//! been down for `failover_after_ms` the backup takes over and stores its
+//! data under the primary's channel id, so consumers keep reading the same
+//! point ids; when the primary reconnects it takes the points back. Both
+//! switchovers are logged, written as `switchover` JSON Lines events and
+//! counted in `Diagnostics::extra` (`switchovers`, and `standby_active` on
+//! the backup).
+//!
+//! # Startup dependencies
+//!
+//! A channel with `depends_on = [<channel ids>]` connects only once those
+//! channels are connected, plus its `startup_delay_ms`, or after
+//! `dependency_timeout_ms` without them. Channels start in dependency order
+//! and stop in reverse order; a dependency cycle fails
+//! [`GatewayRuntime::start()`] (see [`startup`](super::startup)).
+//!
+//! # Interlocks
+//!
+//! [`GatewayRuntime::write_control()`] and
+//! [`GatewayRuntime::write_adjustment()`] check the channel's `interlocks`
+//! against the store before anything is sent: a command whose interlock
+//! point is missing, not `Good`, too old or outside the expected value is
+//! rejected with `GatewayError::Interlock` (see
+//! [`InterlockDef`](super::config::InterlockDef)). Safe-state writes on
+//! shutdown are not interlocked.
+//!
+//! Before that, adjustments outside their point's `transform.min_value` /
+//! `max_value` fail individually, naming the allowed range, or are clamped
+//! to it with the channel's `clamp_adjustments` (see
+//! [`command`](super::command)).
+//!
+//! # Authorization
+//!
+//! [`GatewayRuntime::write_control_as()`] and
+//! [`GatewayRuntime::write_adjustment_as()`] send commands on behalf of a
+//! [`Caller`](super::Caller), as the HTTP API and the northbound servers
+//! do. Before interlocks are checked, each point's required permission
+//! level is compared with the caller's; a call exceeding it is refused
+//! with `GatewayError::PermissionDenied` (see
+//! [`AuthorizationConfig`](super::config::AuthorizationConfig)).
+//! `write_control()` and `write_adjustment()` act for the embedding
+//! application itself and are not restricted.
+//!
+//! Every call, refused or not, is written as a `command_audit` JSON Lines
+//! event and, with `audit` configured, appended to the data store's audit
+//! history (see [`audit`](super::audit)). Neither ever delays or fails the
+//! command.
+//!
+//! # Overrides
+//!
+//! [`GatewayRuntime::set_override()`] forces a point to a fixed value for
+//! commissioning: every sample of the point is stored with that value and
+//! `Quality::Substituted` until [`GatewayRuntime::clear_override()`] or the
+//! override's expiry. Overrides are kept by the store, restored by
+//! `start()` and listed in [`ChannelDiagnostics::overrides`] (see
+//! [`overrides`](super::overrides)).
+//!
+//! # Backfill
+//!
+//! A channel with `backfill` configured (see
+//! [`BackfillConfig`](super::config::BackfillConfig)) asks the device for
+//! its recorded samples after every connect, before the first poll or
+//! event: each point from its last stored sample (at most `max_gap_ms`
+//! back) up to now, through [`ChannelRuntime::read_history()`]. The
+//! samples go to the store's point history
+//! ([`DataStore::write_history()`]) with the quality the device reported.
+//! They are not forwarded like live data: the latest values, alarms and
+//! store watchers are left alone, and the JSON Lines output only gets them
+//! with `forward = true`. Channels or stores without history support log
+//! the failure and carry on.
+//!
+//! # Command feedback
+//!
+//! After a successful write to a point with a `feedback` check (see
+//! [`FeedbackDef`](super::config::FeedbackDef)), a background task watches
+//! the store until the feedback point reports the expected value or the
+//! timeout expires. The outcome, `CommandStage::Confirmed` or
+//! `CommandStage::FeedbackTimeout`, is published as
+//! `DataEvent::CommandUpdate` to [`GatewayRuntime::subscribe()`] receivers
+//! and written as a `command` JSON Lines event. A partially failed write
+//! still checks every command; the failed ones time out.
+//!
+//! # Command queue
+//!
+//! A channel with a `command_queue` (see
+//! [`CommandQueueConfig`](super::config::CommandQueueConfig)) does not get
+//! commands written directly: `write_control()`/`write_adjustment()` queue
+//! them and return the number accepted, or `GatewayError::QueueFull`. The
+//! queue sends them at the configured rate and publishes each outcome as
+//! `DataEvent::CommandUpdate` (`Confirmed` or `Failed`) and a `command` JSON
+//! Lines event. Commands still queued when the channel stops are failed.
+//! Safe-state writes on shutdown bypass the queue. Depth, rejected and
+//! coalesced commands appear in `Diagnostics::extra`.
+//!
+//! # Shutdown
+//!
+//! [`GatewayRuntime::stop()`] stops every channel task, writes the
+//! channel's configured `safe_state` values to its control/adjustment
+//! points, disconnects it (each step bounded by `shutdown_timeout_ms`) and
+//! finally flushes the JSON Lines output and the store. Channels go down
+//! before the channels they depend on.
+//!
+//! # JSON Lines output
+//!
+//! With `jsonl_output` enabled, every stored batch, connection state change
+//! and a periodic diagnostics snapshot per channel are also written as JSON
+//! Lines (see [`jsonl`](super::jsonl)). The writer is opened by `start()` and
+//! closed by `stop()`.
+//!
+//! # Recording
+//!
+//! [`GatewayRuntime::start_recording()`] appends every poll result and data
+//! event of every channel to a file until
+//! [`stop_recording()`](GatewayRuntime::stop_recording) (or `stop()`), for
+//! later replay through a `"replay"` channel (see
+//! [`recording`](super::recording)).
+//!
+//! # Operator control
+//!
+//! Channels can be taken out of service with
+//! [`GatewayRuntime::disable_channel()`], put back with
+//! [`GatewayRuntime::enable_channel()`] and rebuilt from their configuration
+//! with [`GatewayRuntime::restart_channel()`]. Every connection state change
+//! is published as `DataEvent::ConnectionChanged` on the channel's
+//! [`subscribe()`](GatewayRuntime::subscribe) stream.
+//! [`GatewayRuntime::refresh()`] has a channel report all its points without
+//! waiting for the next cycle (a general interrogation on IEC 104, a full
+//! poll on Modbus).
+//!
+//! # Hot reload
+//!
+//! [`GatewayRuntime::reload()`] diffs a new configuration against the
+//! running one channel by channel: unchanged channels keep running, channels
+//! whose only change is their point list get the new points in place when
+//! the protocol supports it ([`ChannelRuntime::update_points()`]), other
+//! changed channels are rebuilt and restarted, and added/removed channels
+//! are started/torn down. The returned [`ReloadReport`] lists what happened.
+//!
+//! # Tracing
+//!
+//! With `tracing-support`, each channel task runs in a `channel` span
+//! (`channel_id`, `protocol`). Every connect attempt and poll cycle gets a
+//! child `connect` or `poll` span, so protocol events carry the channel, and
+//! each poll logs its point and failure counts and latency at debug level.
+
+use std::any::Any;
+use std::collections::{HashMap, VecDeque};
+use std::panic::AssertUnwindSafe;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use futures::future::join_all;
+use futures::FutureExt;
+use serde::Serialize;
+use tokio::sync::{watch, Mutex, RwLock};
+use tokio::task::JoinHandle;
+use tokio::time::{timeout, Instant, MissedTickBehavior};
+
+use crate::core::alarm::AlarmEvaluator;
+use crate::core::clock::{SharedClock, SystemClock};
+use crate::core::data::{BatchSummary, DataBatch, PointId, Value};
+use crate::core::diagnostics::{ConnectionHistory, StateTransition};
+use crate::core::error::{GatewayError, Result};
+use crate::core::event::OverflowCounters;
+use crate::core::point::PointConfig;
+use crate::core::quality::Quality;
+use crate::core::traits::{
+    ConnectionState, DataEvent, DataEventReceiver, Diagnostics, EventBus, PointFailure, PollResult,
+    WriteResult,
+};
+use crate::store::{AuditQuery, DataStore, PointAge};
+
+use super::audit::{AuditRecorder, AuditStats, CommandAudit};
+use super::authorization::Caller;
+use super::command::{CommandKind, CommandQueue, CommandTarget, WriteLimits};
+use super::config::{
+    BackfillConfig, ChannelConfig, EventBufferConfig, GatewayConfig, HeartbeatConfig,
+    PermissionLevel, PointDef, SafeStateDef, SafeStateKind, StandbyMode,
+};
+use super::delta::DeltaFilter;
+use super::factory::{build_point_configs, create_channel};
+use super::heartbeat::{Heartbeat, HeartbeatStatus, Wiring};
+use super::jsonl::{JsonlEvent, JsonlOutput, JsonlSink};
+use super::overrides::{authorize_override, Overrides, PointOverride};
+use super::recording::{RecordedEntry, Recorder, Recording};
+use super::runtime::{ChannelCapabilities, ChannelRuntime};
+use super::schedule::{BusLoad, Buses, PollPhase, PollSlot};
+use super::startup;
+use super::validate::ensure_valid;
+
+/// Default delay before the first reconnect attempt.
+pub const DEFAULT_RECONNECT_MIN: Duration = Duration::from_secs(1);
+
+/// Default upper bound for the reconnect delay.
+pub const DEFAULT_RECONNECT_MAX: Duration = Duration::from_secs(30);
+
+pub(super) type SharedChannel = Arc<Mutex<Box<dyn ChannelRuntime>>>;
+
+/// Caller of `write_control()`/`write_adjustment()`: the embedding
+/// application, which may send anything.
+fn local_caller() -> Caller {
+    Caller::new(Caller::LOCAL, PermissionLevel::Tune)
+}
+
+/// Configured `max_age` of a channel's points, by point id.
+type MaxAges = Arc<HashMap<PointId, Duration>>;
+
+/// Reconnect delay range (doubling from `min` up to `max`).
+#[derive(Debug, Clone, Copy)]
+struct Backoff {
+    min: Duration,
+    max: Duration,
+}
+
+/// JSON Lines output handed to a channel task.
+#[derive(Clone)]
+struct ChannelOutput {
+    sink: JsonlSink,
+    /// Diagnostics snapshot interval (`None`: no snapshots).
+    diagnostics_interval: Option<Duration>,
+}
+
+/// A channel managed by the runtime.
+struct ManagedChannel {
+    config: ChannelConfig,
+    poll_interval: Duration,
+    points: Vec<PointConfig>,
+    runtime: SharedChannel,
+    task: Option<RunningTask>,
+    /// Counters shared with the channel task, kept across restarts.
+    stats: Arc<ChannelStats>,
+    /// Taken out of service by an operator.
+    disabled: bool,
+    /// Stopped by the watchdog after too many restarts.
+    parked: bool,
+    /// Watchdog restarts within the current restart window.
+    recent_restarts: VecDeque<Instant>,
+    /// Connection state changes and command outcomes, kept across restarts.
+    events: EventBus,
+    /// Redundancy group, set when started.
+    link: Option<GroupLink>,
+    /// Poll phase, set when started.
+    phase: PollPhase,
+}
+
+/// Counters and watchdog heartbeat of a channel.
+#[derive(Debug)]
+pub(super) struct ChannelStats {
+    /// Skipped poll cycles, see [`Diagnostics::poll_overruns`].
+    poll_overruns: AtomicU64,
+    /// Unchanged points dropped from poll results by change detection.
+    points_suppressed: AtomicU64,
+    /// Restarts by the watchdog.
+    watchdog_restarts: AtomicU64,
+    /// Connects and polls that waited for a slot under
+    /// `max_concurrent_polls`.
+    polls_throttled: AtomicU64,
+    /// Panics of the channel task.
+    panics: AtomicU64,
+    /// Refreshes requested through [`GatewayRuntime::refresh()`].
+    refreshes: AtomicU64,
+    /// Message of the panic that ended the current task, if one did.
+    panic: std::sync::Mutex<Option<String>>,
+    /// Last successful poll, received event or finished connect attempt.
+    last_activity: std::sync::Mutex<Instant>,
+    /// Last successful poll or received data.
+    last_data: std::sync::Mutex<Instant>,
+    /// Cold backup waiting for its primary to fail (exempt from the
+    /// watchdog).
+    standby: AtomicBool,
+    /// Events lost by the event receivers of this channel.
+    event_losses: std::sync::Mutex<EventLosses>,
+    /// Commands waiting for a channel with a `command_queue`.
+    commands: Arc<CommandQueue>,
+    /// Connection state transitions.
+    connection: std::sync::Mutex<ConnectionHistory>,
+}
+
+impl Default for ChannelStats {
+    fn default() -> Self {
+        Self {
+            poll_overruns: AtomicU64::new(0),
+            points_suppressed: AtomicU64::new(0),
+            watchdog_restarts: AtomicU64::new(0),
+            polls_throttled: AtomicU64::new(0),
+            panics: AtomicU64::new(0),
+            refreshes: AtomicU64::new(0),
+            panic: std::sync::Mutex::default(),
+            last_activity: std::sync::Mutex::new(Instant::now()),
+            last_data: std::sync::Mutex::new(Instant::now()),
+            standby: AtomicBool::new(false),
+            event_losses: std::sync::Mutex::default(),
+            commands: Arc::default(),
+            connection: std::sync::Mutex::default(),
+        }
+    }
+}
+
+/// Totals of earlier event receivers plus the current one's counters.
+#[derive(Debug, Default)]
+struct EventLosses {
+    dropped: u64,
+    coalesced: u64,
+    current: Option<Arc<OverflowCounters>>,
+}
+
+impl EventLosses {
+    /// `(dropped, coalesced)` events.
+    fn totals(&self) -> (u64, u64) {
+        let (dropped, coalesced) = self
+            .current
+            .as_ref()
+            .map_or((0, 0), |c| (c.dropped(), c.coalesced()));
+        (self.dropped + dropped, self.coalesced + coalesced)
+    }
+}
+
+impl ChannelStats {
+    /// Count the losses of a new event receiver from now on.
+    fn track_events(&self, counters: Arc<OverflowCounters>) {
+        let mut losses = self.event_losses.lock().unwrap_or_else(|e| e.into_inner());
+        let (dropped, coalesced) = losses.totals();
+        *losses = EventLosses {
+            dropped,
+            coalesced,
+            current: Some(counters),
+        };
+    }
+
+    /// `(dropped, coalesced)` events over all event receivers.
+    fn event_losses(&self) -> (u64, u64) {
+        self.event_losses
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .totals()
+    }
+
+    /// Record that the channel task made progress.
+    fn beat(&self) {
+        *self.last_activity.lock().unwrap_or_else(|e| e.into_inner()) = Instant::now();
+    }
+
+    /// Time since the channel task last made progress.
+    fn idle(&self) -> Duration {
+        self.last_activity
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .elapsed()
+    }
+
+    /// Record a successful poll or received data.
+    fn received(&self) {
+        *self.last_data.lock().unwrap_or_else(|e| e.into_inner()) = Instant::now();
+        self.beat();
+    }
+
+    /// Time since the channel last polled successfully or received data.
+    pub(super) fn since_data(&self) -> Duration {
+        self.last_data
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .elapsed()
+    }
+
+    fn connection(&self) -> std::sync::MutexGuard<'_, ConnectionHistory> {
+        self.connection.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Count a connect or poll that had to wait for its slot.
+    fn throttled(&self, slot: &PollSlot) {
+        if slot.waited {
+            self.polls_throttled.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn panic(&self) -> std::sync::MutexGuard<'_, Option<String>> {
+        self.panic.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Shared state of a primary channel and its backup.
+#[derive(Debug)]
+struct RedundancyGroup {
+    /// Latest connection state of the primary.
+    primary_state: watch::Sender<ConnectionState>,
+    /// Whether the backup currently serves the primary's points.
+    backup_active: watch::Sender<bool>,
+    /// Switchovers in either direction.
+    switchovers: AtomicU64,
+}
+
+impl Default for RedundancyGroup {
+    fn default() -> Self {
+        Self {
+            primary_state: watch::channel(ConnectionState::Disconnected).0,
+            backup_active: watch::channel(false).0,
+            switchovers: AtomicU64::new(0),
+        }
+    }
+}
+
+/// Redundancy groups by primary channel id.
+///
+/// Every channel belongs to the group of its primary (its own id unless it
+/// is a backup), so primaries need not know whether they have a backup.
+#[derive(Clone, Default)]
+struct RedundancyGroups(Arc<std::sync::Mutex<HashMap<u32, Arc<RedundancyGroup>>>>);
+
+impl RedundancyGroups {
+    fn link(&self, config: &ChannelConfig) -> GroupLink {
+        let primary_id = config.backup_of.unwrap_or(config.id);
+        let mut groups = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        // Channels are linked in dependency order, so a dependency without
+        // a group is not running (disabled)
+        let dependencies = config
+            .depends_on
+            .iter()
+            .filter_map(|id| Some((*id, groups.get(id)?.primary_state.subscribe())))
+            .collect();
+        GroupLink {
+            group: Arc::clone(groups.entry(primary_id).or_default()),
+            primary_id,
+            standby: config.backup_of.map(|_| Standby {
+                mode: config.standby,
+                failover_after: Duration::from_millis(config.failover_after_ms),
+            }),
+            dependencies,
+        }
+    }
+}
+
+/// A channel's place in its redundancy group, and the connection states
+/// of the channels it depends on.
+#[derive(Debug, Clone)]
+struct GroupLink {
+    group: Arc<RedundancyGroup>,
+    /// Channel id the points are stored under.
+    primary_id: u32,
+    /// Set for backups.
+    standby: Option<Standby>,
+    /// Connection states of the managed `depends_on` channels, by id.
+    dependencies: Vec<(u32, watch::Receiver<ConnectionState>)>,
+}
+
+impl GroupLink {
+    /// Whether this channel currently serves (stores) the group's points.
+    fn serving(&self) -> bool {
+        let backup_active = *self.group.backup_active.borrow();
+        backup_active == self.standby.is_some()
+    }
+}
+
+/// Backup channel settings.
+#[derive(Debug, Clone, Copy)]
+struct Standby {
+    mode: StandbyMode,
+    failover_after: Duration,
+}
+
+/// Supervisor task of a started channel.
+struct RunningTask {
+    shutdown: watch::Sender<bool>,
+    handle: JoinHandle<()>,
+}
+
+impl ManagedChannel {
+    /// Build the channel via the factory (not started).
+    fn build(config: &ChannelConfig, default_poll_interval_ms: u64) -> Result<Self> {
+        let context = |e: GatewayError| {
+            GatewayError::Config(format!("channel {} ({}): {}", config.id, config.name, e))
+        };
+
+        let runtime = create_channel(config).map_err(context)?;
+        let points = build_point_configs(config).map_err(context)?;
+
+        Ok(Self {
+            config: config.clone(),
+            poll_interval: poll_interval(config, default_poll_interval_ms),
+            points,
+            runtime: Arc::new(Mutex::new(runtime)),
+            task: None,
+            stats: Arc::default(),
+            disabled: false,
+            parked: false,
+            recent_restarts: VecDeque::new(),
+            events: EventBus::default(),
+            link: None,
+            phase: PollPhase::default(),
+        })
+    }
+
+    fn id(&self) -> u32 {
+        self.config.id
+    }
+
+    /// Channel id the points are stored under (the primary's for backups).
+    fn store_id(&self) -> u32 {
+        self.config.backup_of.unwrap_or(self.id())
+    }
+
+    /// Register points with the store, restore last-known values and spawn
+    /// the supervisor task.
+    ///
+    /// Backups store under their primary's id, which registers the points.
+    #[allow(clippy::too_many_arguments)]
+    async fn start(
+        &mut self,
+        store: &Arc<dyn DataStore>,
+        alarms: &Arc<AlarmEvaluator>,
+        overrides: &Arc<Overrides>,
+        clock: &SharedClock,
+        backoff: Backoff,
+        output: Option<ChannelOutput>,
+        recorder: watch::Receiver<Option<Recorder>>,
+        link: GroupLink,
+        phase: PollPhase,
+    ) -> Result<()> {
+        if link.standby.is_none() {
+            store.set_point_configs(self.id(), &self.points).await?;
+            alarms.configure(self.id(), &self.points);
+            let last_known = store.last_known(self.id()).await?;
+            if !last_known.is_empty() {
+                self.runtime.lock().await.restore(&last_known).await;
+            }
+        }
+
+        self.stats.beat();
+        *self.stats.panic() = None;
+        self.link = Some(link.clone());
+        self.phase = phase.clone();
+        let (shutdown, shutdown_rx) = watch::channel(false);
+        let task = ChannelTask {
+            channel_id: self.id(),
+            active: link.group.backup_active.subscribe(),
+            link,
+            poll_interval: self.poll_interval,
+            phase,
+            runtime: Arc::clone(&self.runtime),
+            store: Arc::clone(store),
+            alarms: Arc::clone(alarms),
+            overrides: Arc::clone(overrides),
+            clock: Arc::clone(clock),
+            shutdown: shutdown_rx,
+            backoff,
+            stats: Arc::clone(&self.stats),
+            events: self.events.clone(),
+            state: None,
+            output,
+            recorder,
+            max_ages: Arc::new(max_ages(&self.points)),
+            event_buffer: self.config.event_buffer,
+            backfill: self.config.backfill.clone().map(|mut backfill| {
+                if backfill.points.is_empty() {
+                    backfill.points = self.points.iter().map(|p| p.id).collect();
+                }
+                backfill
+            }),
+            delta: self
+                .config
+                .change_detection
+                .map(|config| DeltaFilter::new(&config, &self.points)),
+            startup_delay: Duration::from_millis(self.config.startup_delay_ms),
+            dependency_timeout: Duration::from_millis(self.config.dependency_timeout_ms),
+        };
+        let run = task.run();
+        #[cfg(feature = "tracing-support")]
+        let run = tracing::Instrument::instrument(
+            run,
+            tracing::info_span!("channel", channel_id = self.id(), protocol = %self.config.protocol),
+        );
+        self.task = Some(RunningTask {
+            shutdown,
+            handle: tokio::spawn(run),
+        });
+        Ok(())
+    }
+
+    async fn diagnostics(&self, overrides: &Overrides) -> ChannelDiagnostics {
+        let capabilities = self.runtime.lock().await.capabilities();
+        let (diagnostics, error) =
+            match read_diagnostics(&self.runtime, &self.stats, self.link.as_ref(), &self.phase)
+                .await
+            {
+                Ok(mut diag) => {
+                    if self.parked {
+                        diag.connection_state = ConnectionState::Error;
+                    }
+                    (Some(diag), None)
+                }
+                Err(e) => (None, Some(e.to_string())),
+            };
+        ChannelDiagnostics {
+            channel_id: self.id(),
+            name: self.config.name.clone(),
+            enabled: !self.disabled,
+            capabilities,
+            diagnostics,
+            error,
+            overrides: overrides.list(Some(self.store_id())),
+        }
+    }
+
+    /// Publish a connection state change made by an operator action.
+    fn publish_state(&self, state: ConnectionState, jsonl: Option<&JsonlSink>) {
+        self.stats.connection().record(state);
+        self.events.publish(DataEvent::ConnectionChanged(state));
+        if let Some(link) = self.link.as_ref().filter(|l| l.standby.is_none()) {
+            link.group.primary_state.send_replace(state);
+        }
+        if let Some(sink) = jsonl {
+            sink.emit(JsonlEvent::connection_state(self.id(), state));
+        }
+    }
+
+    /// Stop the supervisor task and disconnect.
+    async fn stop(&mut self) {
+        self.stats.commands.discard("channel stopped");
+        if let Some(task) = self.task.take() {
+            task.shutdown.send_replace(true);
+            let _ = task.handle.await;
+        }
+        self.release_points();
+        self.disconnect().await;
+    }
+
+    /// A stopped backup hands the points back to its primary.
+    fn release_points(&self) {
+        if let Some(link) = self.link.as_ref().filter(|l| l.standby.is_some()) {
+            link.group.backup_active.send_replace(false);
+        }
+    }
+
+    /// Like [`stop()`](Self::stop), but abort the task and give up on
+    /// disconnecting after `limit` each. Returns `false` on timeout.
+    async fn stop_within(&mut self, limit: Duration) -> bool {
+        let stopped = self.stop_task_within(limit).await;
+        timeout(limit, self.disconnect()).await.is_ok() && stopped
+    }
+
+    /// Stop the supervisor task, aborting it if it does not finish within
+    /// `limit`. Returns `false` on timeout.
+    async fn stop_task_within(&mut self, limit: Duration) -> bool {
+        self.stats.commands.discard("channel stopped");
+        let Some(task) = self.task.take() else {
+            return true;
+        };
+        task.shutdown.send_replace(true);
+        let abort = task.handle.abort_handle();
+        let stopped = timeout(limit, task.handle).await.is_ok();
+        if !stopped {
+            abort.abort();
+        }
+        self.release_points();
+        stopped
+    }
+
+    /// Stop the supervisor task, write the safe state and disconnect, each
+    /// step bounded by `limit`.
+    async fn shutdown(&mut self, limit: Duration) -> ShutdownOutcome {
+        let mut outcome = ShutdownOutcome {
+            timed_out: !self.stop_task_within(limit).await,
+            ..Default::default()
+        };
+        if self.disabled || self.parked {
+            // Already disconnected by `disable_channel()` or the watchdog
+            return outcome;
+        }
+
+        if !self.config.safe_state.is_empty() {
+            let expected = self.config.safe_state.len();
+            outcome.safe_state_error = match timeout(limit, self.write_safe_state()).await {
+                Ok(Ok(written)) if written >= expected => None,
+                Ok(Ok(written)) => Some(format!(
+                    "{} of {} commands failed",
+                    expected - written,
+                    expected
+                )),
+                Ok(Err(e)) => Some(e.to_string()),
+                Err(_) => Some(format!("timed out after {} ms", limit.as_millis())),
+            };
+            if let Some(_e) = &outcome.safe_state_error {
+                #[cfg(feature = "tracing-support")]
+                tracing::warn!("Channel {} safe state not applied: {}", self.id(), _e);
+            }
+        }
+
+        if timeout(limit, self.disconnect()).await.is_err() {
+            outcome.timed_out = true;
+        }
+        outcome
+    }
+
+    /// Write the configured safe-state values, returning how many
+    /// commands succeeded.
+    async fn write_safe_state(&self) -> Result<usize> {
+        let (controls, adjustments): (Vec<_>, Vec<_>) = self
+            .config
+            .safe_state
+            .iter()
+            .partition(|entry| entry.kind == SafeStateKind::Control);
+        let commands = |entries: Vec<&SafeStateDef>| -> Vec<(u32, f64)> {
+            entries.iter().map(|e| (e.point_id, e.value)).collect()
+        };
+        let (controls, adjustments) = (commands(controls), commands(adjustments));
+
+        let mut runtime = self.runtime.lock().await;
+        let mut written = 0;
+        if !controls.is_empty() {
+            written += runtime.write_control(&controls).await?.success_count;
+        }
+        if !adjustments.is_empty() {
+            written += runtime.write_adjustment(&adjustments).await?.success_count;
+        }
+        Ok(written)
+    }
+
+    /// Stop event streaming and disconnect.
+    async fn disconnect(&self) {
+        let mut runtime = self.runtime.lock().await;
+        if runtime.is_event_driven() {
+            let _ = runtime.stop_events().await;
+        }
+        if let Err(_e) = runtime.disconnect().await {
+            #[cfg(feature = "tracing-support")]
+            tracing::warn!("Channel {} disconnect failed: {}", self.id(), _e);
+        }
+    }
+}
+
+/// Result of shutting down one channel.
+#[derive(Default)]
+struct ShutdownOutcome {
+    /// A step did not finish within the shutdown timeout.
+    timed_out: bool,
+    safe_state_error: Option<String>,
+}
+
+/// Channel diagnostics including the scheduler's overrun count and
+/// throttled polls (`extra.polls_throttled`), task panics
+/// (`extra.task_panics`, and the `Error` state after one), requested
+/// refreshes (`extra.refreshes`), connection accounting (`extra.connected_since`, `extra.state_changed_at`,
+/// `extra.disconnect_count_24h`, `extra.availability_percent`), the
+/// watchdog restart count (`extra.watchdog_restarts`), lost events
+/// (`extra.events_dropped`, `extra.events_coalesced`), the command queue
+/// (`extra.command_queue_depth`, `extra.commands_rejected`,
+/// `extra.commands_coalesced`) and, for redundant pairs, the switchover
+/// count (`extra.switchovers`) and whether a backup serves the points
+/// (`extra.standby_active`), and the poll phase (`extra.poll_offset_ms`,
+/// `extra.bus_max_concurrent_polls`).
+async fn read_diagnostics(
+    runtime: &SharedChannel,
+    stats: &ChannelStats,
+    link: Option<&GroupLink>,
+    phase: &PollPhase,
+) -> Result<Diagnostics> {
+    let mut diag = runtime.lock().await.diagnostics().await?;
+    diag.poll_overruns = stats.poll_overruns.load(Ordering::Relaxed);
+    if let Some(panic) = stats.panic().clone() {
+        diag.connection_state = ConnectionState::Error;
+        diag.last_error = Some(format!("channel task panicked: {}", panic));
+    }
+
+    let mut extra = serde_json::Map::new();
+    let connection = stats.connection().summary();
+    if let Some(since) = connection.connected_since {
+        extra.insert("connected_since".into(), since.to_rfc3339().into());
+    }
+    if let Some(changed) = connection.state_changed_at {
+        extra.insert("state_changed_at".into(), changed.to_rfc3339().into());
+    }
+    extra.insert(
+        "disconnect_count_24h".into(),
+        connection.disconnect_count_24h.into(),
+    );
+    extra.insert(
+        "availability_percent".into(),
+        connection.availability_percent.into(),
+    );
+    let suppressed = stats.points_suppressed.load(Ordering::Relaxed);
+    if suppressed > 0 {
+        extra.insert("points_suppressed".into(), suppressed.into());
+    }
+    let restarts = stats.watchdog_restarts.load(Ordering::Relaxed);
+    if restarts > 0 {
+        extra.insert("watchdog_restarts".into(), restarts.into());
+    }
+    let throttled = stats.polls_throttled.load(Ordering::Relaxed);
+    if throttled > 0 {
+        extra.insert("polls_throttled".into(), throttled.into());
+    }
+    let panics = stats.panics.load(Ordering::Relaxed);
+    if panics > 0 {
+        extra.insert("task_panics".into(), panics.into());
+    }
+    let refreshes = stats.refreshes.load(Ordering::Relaxed);
+    if refreshes > 0 {
+        extra.insert("refreshes".into(), refreshes.into());
+    }
+    let (dropped, coalesced) = stats.event_losses();
+    if dropped > 0 {
+        extra.insert("events_dropped".into(), dropped.into());
+    }
+    if coalesced > 0 {
+        extra.insert("events_coalesced".into(), coalesced.into());
+    }
+    if let Some(queue) = stats.commands.counters() {
+        extra.insert("command_queue_depth".into(), queue.depth.into());
+        extra.insert("commands_rejected".into(), queue.rejected.into());
+        extra.insert("commands_coalesced".into(), queue.coalesced.into());
+    }
+    if let Some(link) = link {
+        let switchovers = link.group.switchovers.load(Ordering::Relaxed);
+        if switchovers > 0 || link.standby.is_some() {
+            extra.insert("switchovers".into(), switchovers.into());
+        }
+        if link.standby.is_some() {
+            extra.insert("standby_active".into(), link.serving().into());
+        }
+    }
+    if let Some(offset) = phase.offset() {
+        extra.insert("poll_offset_ms".into(), (offset.as_millis() as u64).into());
+    }
+    if let Some(bus) = phase.bus() {
+        extra.insert(
+            "bus_max_concurrent_polls".into(),
+            bus.max_concurrent().into(),
+        );
+    }
+    if !extra.is_empty() {
+        match diag.extra.as_object_mut() {
+            Some(existing) => existing.extend(extra),
+            None => diag.extra = serde_json::Value::Object(extra),
+        }
+    }
+    Ok(diag)
+}
+
+fn max_ages(points: &[PointConfig]) -> HashMap<PointId, Duration> {
+    points
+        .iter()
+        .filter_map(|p| Some((p.id, p.max_age()?)))
+        .collect()
+}
+
+fn poll_interval(config: &ChannelConfig, default_poll_interval_ms: u64) -> Duration {
+    let ms = config.poll_interval_ms.unwrap_or(default_poll_interval_ms);
+    Duration::from_millis(ms.max(1))
+}
+
+/// Give backups without points the points of their primary.
+fn inherit_backup_points(config: &mut GatewayConfig) {
+    let inherited: Vec<(usize, Vec<PointDef>)> = config
+        .channels
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| c.points.is_empty())
+        .filter_map(|(index, backup)| {
+            let primary_id = backup.backup_of?;
+            let primary = config.channels.iter().find(|c| c.id == primary_id)?;
+            Some((index, primary.points.clone()))
+        })
+        .collect();
+    for (index, points) in inherited {
+        config.channels[index].points = points;
+    }
+}
+
+/// Whether two channel configurations differ in their points only.
+fn points_only_changed(old: &ChannelConfig, new: &ChannelConfig) -> bool {
+    let strip = |c: &ChannelConfig| ChannelConfig {
+        points: Vec::new(),
+        ..c.clone()
+    };
+    old.points != new.points && strip(old) == strip(new)
+}
+
+/// Diagnostics of one managed channel.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChannelDiagnostics {
+    /// Channel id.
+    pub channel_id: u32,
+
+    /// Channel name.
+    pub name: String,
+
+    /// Whether the channel is in service (`false` after
+    /// [`GatewayRuntime::disable_channel()`]).
+    pub enabled: bool,
+
+    /// What the channel supports.
+    pub capabilities: ChannelCapabilities,
+
+    /// Channel diagnostics, if they could be read.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diagnostics: Option<Diagnostics>,
+
+    /// Error returned while reading diagnostics.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+
+    /// Overrides in effect on the channel's points.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub overrides: Vec<PointOverride>,
+}
+
+/// What [`GatewayRuntime::reload()`] did, by channel id.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ReloadReport {
+    /// Channels left running untouched.
+    pub kept: Vec<u32>,
+
+    /// Channels whose points were replaced without reconnecting.
+    pub updated: Vec<u32>,
+
+    /// Channels rebuilt and restarted.
+    pub restarted: Vec<u32>,
+
+    /// Channels added.
+    pub added: Vec<u32>,
+
+    /// Channels torn down (removed or disabled).
+    pub removed: Vec<u32>,
+}
+
+impl ReloadReport {
+    /// Whether the reload changed anything.
+    pub fn has_changes(&self) -> bool {
+        !(self.updated.is_empty()
+            && self.restarted.is_empty()
+            && self.added.is_empty()
+            && self.removed.is_empty())
+    }
+}
+
+/// What [`GatewayRuntime::stop()`] could not do cleanly, by channel id.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ShutdownReport {
+    /// Channels whose safe state was not (fully) written, with the reason.
+    pub safe_state_failed: Vec<(u32, String)>,
+
+    /// Channels where stopping the task or disconnecting exceeded
+    /// `shutdown_timeout_ms`.
+    pub timed_out: Vec<u32>,
+}
+
+impl ShutdownReport {
+    /// Whether every channel shut down cleanly.
+    pub fn is_clean(&self) -> bool {
+        self.safe_state_failed.is_empty() && self.timed_out.is_empty()
+    }
+}
+
+/// What [`GatewayRuntime::check_watchdog()`] did, by channel id.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct WatchdogReport {
+    /// Stalled channels rebuilt and restarted.
+    pub restarted: Vec<u32>,
+
+    /// Stalled channels parked in the `Error` state after too many restarts.
+    pub parked: Vec<u32>,
+}
+
+/// Planned reload action for one channel of the new configuration.
+enum ReloadAction {
+    Keep,
+    /// Replace points in place; the prebuilt channel is used if the
+    /// protocol cannot do that.
+    UpdatePoints(ManagedChannel),
+    Restart(ManagedChannel),
+    Add(ManagedChannel),
+}
+
+/// Runs all enabled channels of a gateway configuration against a store.
+pub struct GatewayRuntime {
+    config: GatewayConfig,
+    store: Arc<dyn DataStore>,
+    /// Alarm state of all channels, kept across channel restarts.
+    alarms: Arc<AlarmEvaluator>,
+    channels: Vec<ManagedChannel>,
+    backoff: Backoff,
+    running: bool,
+    jsonl: Option<JsonlOutput>,
+    /// Command audit history, while running with `audit` configured.
+    audit: Option<AuditRecorder>,
+    groups: RedundancyGroups,
+    buses: Buses,
+    recording: Option<Recording>,
+    /// Recorder handed to the channel tasks while recording.
+    recorder: watch::Sender<Option<Recorder>>,
+    /// Point overrides, kept across channel restarts.
+    overrides: Arc<Overrides>,
+    clock: SharedClock,
+    /// Heartbeat output task, while running with `heartbeat` configured.
+    heartbeat: Option<Heartbeat>,
+}
+
+impl GatewayRuntime {
+    /// Build all enabled channels of `config` via the channel factory.
+    ///
+    /// Fails if the configuration does not pass
+    /// [`GatewayConfig::validate()`] or any enabled channel cannot be
+    /// created.
+    pub fn from_config(mut config: GatewayConfig, store: Arc<dyn DataStore>) -> Result<Self> {
+        ensure_valid(config.validate())?;
+        config.expand_templates();
+        config.resolve_transports();
+        inherit_backup_points(&mut config);
+        let default_poll_interval_ms = config.gateway.default_poll_interval_ms;
+        let channels = config
+            .enabled_channels()
+            .map(|c| ManagedChannel::build(c, default_poll_interval_ms))
+            .collect::<Result<Vec<_>>>()?;
+        let buses = Buses::default();
+        buses.configure(config.enabled_channels());
+        buses.set_limit(config.gateway.max_concurrent_polls);
+
+        Ok(Self {
+            config,
+            store,
+            alarms: Arc::default(),
+            channels,
+            backoff: Backoff {
+                min: DEFAULT_RECONNECT_MIN,
+                max: DEFAULT_RECONNECT_MAX,
+            },
+            running: false,
+            jsonl: None,
+            audit: None,
+            groups: RedundancyGroups::default(),
+            buses,
+            recording: None,
+            recorder: watch::Sender::new(None),
+            overrides: Arc::new(Overrides::new(SystemClock::shared())),
+            clock: SystemClock::shared(),
+            heartbeat: None,
+        })
+    }
+
+    /// Set the reconnect backoff range (doubling from `min` up to `max`).
+    #[must_use]
+    pub fn with_reconnect_backoff(mut self, min: Duration, max: Duration) -> Self {
+        self.backoff = Backoff {
+            min,
+            max: max.max(min),
+        };
+        self
+    }
+
+    /// Use `clock` for staleness, interlock age and override expiry checks
+    /// instead of the system clock, e.g. a
+    /// [`SimulatedClock`](crate::core::clock::SimulatedClock) in tests.
+    ///
+    /// Channels pick it up when they start.
+    #[must_use]
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.overrides = Arc::new(self.overrides.with_clock(Arc::clone(&clock)));
+        self.clock = clock;
+        self
+    }
+
+    /// Gateway name from the configuration.
+    pub fn name(&self) -> &str {
+        &self.config.gateway.name
+    }
+
+    /// The configuration currently applied.
+    pub fn config(&self) -> &GatewayConfig {
+        &self.config
+    }
+
+    /// The store channel data is written to.
+    pub fn store(&self) -> &Arc<dyn DataStore> {
+        &self.store
+    }
+
+    /// Ids of the managed channels.
+    pub fn channel_ids(&self) -> Vec<u32> {
+        self.channels.iter().map(ManagedChannel::id).collect()
+    }
+
+    /// Whether the channel tasks are running.
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    /// Restore last-known values and spawn the channel tasks, in
+    /// dependency order (see [`ChannelConfig::depends_on`]).
+    ///
+    /// Fails with `GatewayError::Config` if the channels' `depends_on`
+    /// form a cycle.
+    pub async fn start(&mut self) -> Result<()> {
+        if self.running {
+            return Ok(());
+        }
+        let order = startup::levels(self.channels.iter().map(|c| &c.config)).map_err(|cycle| {
+            GatewayError::Config(format!(
+                "channel {}: {}",
+                cycle[0],
+                startup::cycle_message(&cycle)
+            ))
+        })?;
+        if self.config.gateway.jsonl_output && self.jsonl.is_none() {
+            self.jsonl = Some(JsonlOutput::spawn(&self.config.gateway.jsonl)?);
+        }
+        if let (Some(config), None) = (&self.config.gateway.audit, &self.audit) {
+            // Fail now rather than on every command
+            let probe = AuditQuery::new().with_limit(0);
+            if let Err(GatewayError::Unsupported(_)) = self.store.query_audit(&probe).await {
+                return Err(GatewayError::Config(
+                    "audit is configured but the data store does not support it".into(),
+                ));
+            }
+            self.audit = Some(AuditRecorder::start(config, Arc::clone(&self.store)));
+        }
+        match self.store.load_overrides().await {
+            Ok(kept) => {
+                for point_override in kept {
+                    self.overrides.set(point_override);
+                }
+                self.expire_overrides().await;
+            }
+            Err(GatewayError::Unsupported(_)) => {}
+            Err(e) => return Err(e),
+        }
+
+        let output = self.channel_output();
+        for id in order.into_iter().flatten() {
+            let Some(channel) = self
+                .channels
+                .iter_mut()
+                .find(|c| c.id() == id && !c.disabled && !c.parked)
+            else {
+                continue;
+            };
+            let link = self.groups.link(&channel.config);
+            let phase = self.buses.phase(&channel.config, channel.poll_interval);
+            channel
+                .start(
+                    &self.store,
+                    &self.alarms,
+                    &self.overrides,
+                    &self.clock,
+                    self.backoff,
+                    output.clone(),
+                    self.recorder.subscribe(),
+                    link,
+                    phase,
+                )
+                .await?;
+        }
+        self.running = true;
+        self.wire_heartbeat();
+
+        #[cfg(feature = "tracing-support")]
+        tracing::info!(
+            "Gateway '{}' started with {} channel(s)",
+            self.config.gateway.name,
+            self.channels.len()
+        );
+
+        Ok(())
+    }
+
+    /// Shut the gateway down.
+    ///
+    /// Channels are shut down in reverse dependency order (see
+    /// [`ChannelConfig::depends_on`]), channels that do not depend on each
+    /// other concurrently: the channel task is stopped, the channel's
+    /// `safe_state` values are written and the channel is disconnected,
+    /// each step bounded by `shutdown_timeout_ms`. Then the
+    /// JSON Lines output is closed and the store flushed. Safe-state
+    /// failures and timeouts do not abort the shutdown; they are listed in
+    /// the returned report.
+    pub async fn stop(&mut self) -> Result<ShutdownReport> {
+        // The relay trips while the channels shut down
+        if let Some(heartbeat) = self.heartbeat.take() {
+            heartbeat.stop();
+        }
+        let limit = Duration::from_millis(self.config.gateway.shutdown_timeout_ms.max(1));
+        let levels = startup::levels(self.channels.iter().map(|c| &c.config))
+            .unwrap_or_else(|_| vec![self.channel_ids()]);
+        let mut outcomes = Vec::new();
+        for level in levels.iter().rev() {
+            outcomes.extend(
+                join_all(
+                    self.channels
+                        .iter_mut()
+                        .filter(|channel| level.contains(&channel.id()))
+                        .map(
+                            |channel| async move { (channel.id(), channel.shutdown(limit).await) },
+                        ),
+                )
+                .await,
+            );
+        }
+        self.running = false;
+
+        let mut report = ShutdownReport::default();
+        for (channel_id, outcome) in outcomes {
+            if let Some(error) = outcome.safe_state_error {
+                report.safe_state_failed.push((channel_id, error));
+            }
+            if outcome.timed_out {
+                report.timed_out.push(channel_id);
+            }
+        }
+
+        self.stop_recording().await;
+        if let Some(jsonl) = self.jsonl.take() {
+            jsonl.close().await;
+        }
+        if let Some(audit) = self.audit.take() {
+            audit.close().await;
+        }
+        self.store.flush().await?;
+        Ok(report)
+    }
+
+    /// Apply a new configuration without restarting unaffected channels.
+    ///
+    /// The configuration is validated and all new and changed channels are
+    /// built before anything is stopped, so an invalid configuration returns
+    /// an error and leaves the running
+    /// channels untouched. If the runtime has not been started, changed
+    /// channels are only rebuilt.
+    pub async fn reload(&mut self, mut new_config: GatewayConfig) -> Result<ReloadReport> {
+        ensure_valid(new_config.validate())?;
+        new_config.expand_templates();
+        new_config.resolve_transports();
+        inherit_backup_points(&mut new_config);
+        let default_poll_interval_ms = new_config.gateway.default_poll_interval_ms;
+
+        let mut plan = Vec::new();
+        for new in new_config.enabled_channels() {
+            let action = match self.channels.iter().find(|c| c.id() == new.id) {
+                None => ReloadAction::Add(ManagedChannel::build(new, default_poll_interval_ms)?),
+                Some(old) => {
+                    let same_interval =
+                        old.poll_interval == poll_interval(new, default_poll_interval_ms);
+                    if same_interval && old.config == *new {
+                        ReloadAction::Keep
+                    } else if same_interval && points_only_changed(&old.config, new) {
+                        ReloadAction::UpdatePoints(ManagedChannel::build(
+                            new,
+                            default_poll_interval_ms,
+                        )?)
+                    } else {
+                        ReloadAction::Restart(ManagedChannel::build(new, default_poll_interval_ms)?)
+                    }
+                }
+            };
+            plan.push((new.id, action));
+        }
+        // Added channels start after the channels they depend on
+        let order: Vec<u32> = startup::levels(new_config.enabled_channels())
+            .unwrap_or_default()
+            .concat();
+        plan.sort_by_key(|(id, _)| order.iter().position(|o| o == id));
+        self.buses.configure(new_config.enabled_channels());
+        self.buses
+            .set_limit(new_config.gateway.max_concurrent_polls);
+
+        let mut report = ReloadReport::default();
+
+        let (mut removed, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut self.channels)
+            .into_iter()
+            .partition(|c| !plan.iter().any(|(id, _)| *id == c.id()));
+        self.channels = kept;
+        for channel in &mut removed {
+            channel.stop().await;
+            self.alarms.configure(channel.id(), &[]);
+            report.removed.push(channel.id());
+        }
+
+        for (id, action) in plan {
+            match action {
+                ReloadAction::Keep => report.kept.push(id),
+                ReloadAction::UpdatePoints(replacement) => {
+                    if self.update_points(&replacement).await {
+                        report.updated.push(id);
+                    } else {
+                        self.restart(replacement).await?;
+                        report.restarted.push(id);
+                    }
+                }
+                ReloadAction::Restart(replacement) => {
+                    self.restart(replacement).await?;
+                    report.restarted.push(id);
+                }
+                ReloadAction::Add(mut channel) => {
+                    if self.running {
+                        let link = self.groups.link(&channel.config);
+                        let phase = self.buses.phase(&channel.config, channel.poll_interval);
+                        channel
+                            .start(
+                                &self.store,
+                                &self.alarms,
+                                &self.overrides,
+                                &self.clock,
+                                self.backoff,
+                                self.channel_output(),
+                                self.recorder.subscribe(),
+                                link,
+                                phase,
+                            )
+                            .await?;
+                    }
+                    self.channels.push(channel);
+                    report.added.push(id);
+                }
+            }
+        }
+
+        self.config = new_config;
+        self.wire_heartbeat();
+
+        #[cfg(feature = "tracing-support")]
+        tracing::info!(
+            "Gateway '{}' reloaded: kept {:?}, updated {:?}, restarted {:?}, added {:?}, removed {:?}",
+            self.config.gateway.name,
+            report.kept,
+            report.updated,
+            report.restarted,
+            report.added,
+            report.removed
+        );
+
+        Ok(report)
+    }
+
+    /// Apply the points of `replacement` to the running channel in place.
+    ///
+    /// Returns `false` if the channel has to be restarted instead.
+    async fn update_points(&mut self, replacement: &ManagedChannel) -> bool {
+        let Some(channel) = self
+            .channels
+            .iter_mut()
+            .find(|c| c.id() == replacement.id())
+        else {
+            return false;
+        };
+        // The channel task holds the max ages it was started with
+        if max_ages(&channel.points) != max_ages(&replacement.points) {
+            return false;
+        }
+
+        if let Err(_e) = channel
+            .runtime
+            .lock()
+            .await
+            .update_points(&replacement.points)
+            .await
+        {
+            #[cfg(feature = "tracing-support")]
+            tracing::debug!("Channel {} needs a restart: {}", channel.id(), _e);
+            return false;
+        }
+
+        channel.config = replacement.config.clone();
+        channel.points = replacement.points.clone();
+        if self.running && channel.config.backup_of.is_none() {
+            self.alarms.configure(channel.id(), &channel.points);
+        }
+        if self.running {
+            if let Err(_e) = self
+                .store
+                .set_point_configs(channel.id(), &channel.points)
+                .await
+            {
+                #[cfg(feature = "tracing-support")]
+                tracing::error!(
+                    "Channel {} point config update failed: {}",
+                    channel.id(),
+                    _e
+                );
+            }
+        }
+        true
+    }
+
+    /// Replace a channel with a rebuilt one, restarting it if running.
+    async fn restart(&mut self, mut replacement: ManagedChannel) -> Result<()> {
+        let Some(index) = self
+            .channels
+            .iter()
+            .position(|c| c.id() == replacement.id())
+        else {
+            return Err(GatewayError::Internal(format!(
+                "channel {} is not managed",
+                replacement.id()
+            )));
+        };
+
+        let current = &mut self.channels[index];
+        current.stop().await;
+        replacement.disabled = current.disabled;
+        replacement.events = current.events.clone();
+        replacement.stats = Arc::clone(&current.stats);
+        if self.running && !replacement.disabled {
+            let link = self.groups.link(&replacement.config);
+            let phase = self
+                .buses
+                .phase(&replacement.config, replacement.poll_interval);
+            replacement
+                .start(
+                    &self.store,
+                    &self.alarms,
+                    &self.overrides,
+                    &self.clock,
+                    self.backoff,
+                    self.channel_output(),
+                    self.recorder.subscribe(),
+                    link,
+                    phase,
+                )
+                .await?;
+        }
+        self.channels[index] = replacement;
+        Ok(())
+    }
+
+    /// Restart stalled channels (see [`WatchdogConfig`]).
+    ///
+    /// A running channel whose task has not made progress for
+    /// `stall_timeout_ms` gets its stored points marked
+    /// `Quality::CommFailure` and is stopped (aborting a wedged task after
+    /// `shutdown_timeout_ms`), rebuilt via the factory and restarted. A
+    /// channel stalling again after `max_restarts` restarts within
+    /// `restart_window_ms` is parked in the `Error` state until
+    /// [`restart_channel()`](Self::restart_channel). Does nothing without a
+    /// `[gateway.watchdog]` section.
+    ///
+    /// [`WatchdogConfig`]: super::WatchdogConfig
+    pub async fn check_watchdog(&mut self) -> Result<WatchdogReport> {
+        let mut report = WatchdogReport::default();
+        let Some(watchdog) = self.config.gateway.watchdog.clone() else {
+            return Ok(report);
+        };
+        if !self.running {
+            return Ok(report);
+        }
+        let stall = Duration::from_millis(watchdog.stall_timeout_ms);
+        let window = Duration::from_millis(watchdog.restart_window_ms);
+        let limit = Duration::from_millis(self.config.gateway.shutdown_timeout_ms.max(1));
+        let default_poll_interval_ms = self.config.gateway.default_poll_interval_ms;
+        let (store, backoff, output) =
+            (Arc::clone(&self.store), self.backoff, self.channel_output());
+        let (groups, buses) = (self.groups.clone(), self.buses.clone());
+        let recorder = self.recorder.clone();
+        let (jsonl, alarms) = (self.jsonl_sink(), Arc::clone(&self.alarms));
+        let (overrides, clock) = (Arc::clone(&self.overrides), Arc::clone(&self.clock));
+
+        let stalled: Vec<u32> = self
+            .channels
+            .iter()
+            .filter(|c| c.task.is_some() && !c.disabled && !c.parked)
+            .filter(|c| !c.stats.standby.load(Ordering::Relaxed) && c.stats.idle() >= stall)
+            .map(ManagedChannel::id)
+            .collect();
+
+        for channel_id in stalled {
+            #[cfg(feature = "tracing-support")]
+            tracing::warn!("Channel {} stalled, restarting", channel_id);
+
+            let stored = store.read_all(channel_id).await?;
+            mark_quality(
+                store.as_ref(),
+                jsonl.as_ref(),
+                &alarms,
+                &overrides,
+                channel_id,
+                stored,
+                Quality::CommFailure,
+            )
+            .await?;
+
+            let now = Instant::now();
+            let channel = self.channel_mut(channel_id)?;
+            while channel
+                .recent_restarts
+                .front()
+                .is_some_and(|t| now.duration_since(*t) >= window)
+            {
+                channel.recent_restarts.pop_front();
+            }
+
+            if channel.recent_restarts.len() >= watchdog.max_restarts as usize {
+                channel.stop_within(limit).await;
+                channel.parked = true;
+                channel.publish_state(ConnectionState::Error, jsonl.as_ref());
+                report.parked.push(channel_id);
+
+                #[cfg(feature = "tracing-support")]
+                tracing::error!(
+                    "Channel {} parked after {} restarts",
+                    channel_id,
+                    watchdog.max_restarts
+                );
+                continue;
+            }
+
+            // Not `restart()`: its unbounded stop could hang on the wedged
+            // channel again
+            channel.stop_within(limit).await;
+            channel.publish_state(ConnectionState::Reconnecting, jsonl.as_ref());
+            channel
+                .stats
+                .watchdog_restarts
+                .fetch_add(1, Ordering::Relaxed);
+
+            let mut replacement = ManagedChannel::build(&channel.config, default_poll_interval_ms)?;
+            replacement.events = channel.events.clone();
+            replacement.stats = Arc::clone(&channel.stats);
+            replacement.recent_restarts = std::mem::take(&mut channel.recent_restarts);
+            replacement.recent_restarts.push_back(now);
+            let link = groups.link(&replacement.config);
+            let phase = buses.phase(&replacement.config, replacement.poll_interval);
+            replacement
+                .start(
+                    &store,
+                    &alarms,
+                    &overrides,
+                    &clock,
+                    backoff,
+                    output.clone(),
+                    recorder.subscribe(),
+                    link,
+                    phase,
+                )
+                .await?;
+            *channel = replacement;
+            report.restarted.push(channel_id);
+        }
+        if !report.restarted.is_empty() || !report.parked.is_empty() {
+            self.wire_heartbeat();
+        }
+        Ok(report)
+    }
+
+    /// Spawn a task calling [`check_watchdog()`](Self::check_watchdog)
+    /// every quarter of the stall timeout. The task ends when the
+    /// configuration no longer has a `[gateway.watchdog]` section.
+    pub fn spawn_watchdog(gateway: Arc<RwLock<GatewayRuntime>>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                let period = match &gateway.read().await.config.gateway.watchdog {
+                    Some(watchdog) => Duration::from_millis((watchdog.stall_timeout_ms / 4).max(1)),
+                    None => return,
+                };
+                tokio::time::sleep(period).await;
+                if let Err(_e) = gateway.write().await.check_watchdog().await {
+                    #[cfg(feature = "tracing-support")]
+                    tracing::error!("Watchdog check failed: {}", _e);
+                }
+            }
+        })
+    }
+
+    /// Subscribe to connection state changes and command outcomes of a
+    /// channel.
+    ///
+    /// Yields `DataEvent::ConnectionChanged` when the channel connects or
+    /// loses its connection and when it is disabled, enabled or restarted,
+    /// and `DataEvent::CommandUpdate` when a command's feedback check ends.
+    /// The subscription survives restarts and reloads of the channel.
+    pub fn subscribe(&self, channel_id: u32) -> Result<DataEventReceiver> {
+        Ok(self.channel(channel_id)?.events.subscribe())
+    }
+
+    /// Take a channel out of service.
+    ///
+    /// Stops polling and event streaming, disconnects and marks all stored
+    /// points of the channel `Quality::OutOfService`. The channel stays
+    /// disabled across reloads and `stop()`/`start()` until
+    /// [`enable_channel()`](Self::enable_channel).
+    pub async fn disable_channel(&mut self, channel_id: u32) -> Result<()> {
+        let (store, jsonl) = (Arc::clone(&self.store), self.jsonl_sink());
+        let (alarms, overrides) = (Arc::clone(&self.alarms), Arc::clone(&self.overrides));
+        let channel = self.channel_mut(channel_id)?;
+        if channel.disabled {
+            return Ok(());
+        }
+
+        channel.disabled = true;
+        channel.stop().await;
+        let stored = store.read_all(channel_id).await?;
+        mark_quality(
+            store.as_ref(),
+            jsonl.as_ref(),
+            &alarms,
+            &overrides,
+            channel_id,
+            stored,
+            Quality::OutOfService,
+        )
+        .await?;
+        channel.publish_state(ConnectionState::Disconnected, jsonl.as_ref());
+        self.wire_heartbeat();
+
+        #[cfg(feature = "tracing-support")]
+        tracing::info!("Channel {} disabled", channel_id);
+        Ok(())
+    }
+
+    /// Put a disabled channel back into service and reconnect it.
+    pub async fn enable_channel(&mut self, channel_id: u32) -> Result<()> {
+        let (running, store, backoff) = (self.running, Arc::clone(&self.store), self.backoff);
+        let (output, groups) = (self.channel_output(), self.groups.clone());
+        let (recorder, alarms) = (self.recorder.subscribe(), Arc::clone(&self.alarms));
+        let (buses, clock) = (self.buses.clone(), Arc::clone(&self.clock));
+        let overrides = Arc::clone(&self.overrides);
+        let channel = self.channel_mut(channel_id)?;
+        if !channel.disabled {
+            return Ok(());
+        }
+
+        channel.disabled = false;
+        if running {
+            let jsonl = output.as_ref().map(|o| &o.sink);
+            channel.publish_state(ConnectionState::Connecting, jsonl);
+            let link = groups.link(&channel.config);
+            let phase = buses.phase(&channel.config, channel.poll_interval);
+            channel
+                .start(
+                    &store, &alarms, &overrides, &clock, backoff, output, recorder, link, phase,
+                )
+                .await?;
+        }
+        self.wire_heartbeat();
+
+        #[cfg(feature = "tracing-support")]
+        tracing::info!("Channel {} enabled", channel_id);
+        Ok(())
+    }
+
+    /// Tear a channel down and rebuild it from its configuration.
+    ///
+    /// A disabled channel is rebuilt but stays disabled.
+    pub async fn restart_channel(&mut self, channel_id: u32) -> Result<()> {
+        let channel = self.channel(channel_id)?;
+        let replacement = ManagedChannel::build(
+            &channel.config,
+            self.config.gateway.default_poll_interval_ms,
+        )?;
+        if self.running && !channel.disabled {
+            channel.publish_state(ConnectionState::Reconnecting, self.jsonl_sink().as_ref());
+        }
+        self.restart(replacement).await?;
+        self.wire_heartbeat();
+
+        #[cfg(feature = "tracing-support")]
+        tracing::info!("Channel {} restarted", channel_id);
+        Ok(())
+    }
+
+    /// Diagnostics of every managed channel.
+    pub async fn diagnostics_snapshot(&self) -> Vec<ChannelDiagnostics> {
+        let mut snapshot = Vec::with_capacity(self.channels.len());
+        for channel in &self.channels {
+            snapshot.push(self.channel_diagnostics_of(channel).await);
+        }
+        snapshot
+    }
+
+    /// Diagnostics of one channel.
+    pub async fn channel_diagnostics(&self, channel_id: u32) -> Result<ChannelDiagnostics> {
+        Ok(self.channel_diagnostics_of(self.channel(channel_id)?).await)
+    }
+
+    /// State of the heartbeat output, while running with `heartbeat`
+    /// configured.
+    pub fn heartbeat_status(&self) -> Option<HeartbeatStatus> {
+        self.heartbeat.as_ref().map(Heartbeat::status)
+    }
+
+    /// Diagnostics of `channel`, with the heartbeat status on the heartbeat
+    /// channel.
+    async fn channel_diagnostics_of(&self, channel: &ManagedChannel) -> ChannelDiagnostics {
+        let mut diagnostics = channel.diagnostics(&self.overrides).await;
+        let heartbeat = self
+            .heartbeat
+            .as_ref()
+            .filter(|h| h.config().channel_id == channel.id());
+        let extra = diagnostics
+            .diagnostics
+            .as_mut()
+            .and_then(|d| d.extra.as_object_mut());
+        if let (Some(heartbeat), Some(extra)) = (heartbeat, extra) {
+            if let Ok(status) = serde_json::to_value(heartbeat.status()) {
+                extra.insert("heartbeat".into(), status);
+            }
+        }
+        diagnostics
+    }
+
+    /// Last connection state transitions of a channel, oldest first.
+    pub fn connection_history(&self, channel_id: u32) -> Result<Vec<StateTransition>> {
+        let channel = self.channel(channel_id)?;
+        let history = channel.stats.connection();
+        Ok(history.transitions().copied().collect())
+    }
+
+    /// Force a point to `value` until
+    /// [`clear_override()`](Self::clear_override) or, with `expires_after`,
+    /// until that much time has passed (see [Overrides](self#overrides)).
+    ///
+    /// The substituted value is stored at once. Replaces an earlier override
+    /// of the point. Fails if the store cannot keep the override.
+    pub async fn set_override(
+        &self,
+        channel_id: u32,
+        point_id: PointId,
+        value: Value,
+        expires_after: Option<Duration>,
+    ) -> Result<PointOverride> {
+        self.set_override_as(&local_caller(), channel_id, point_id, value, expires_after)
+            .await
+    }
+
+    /// Force a point to a value on behalf of `caller`.
+    ///
+    /// Like [`set_override()`](Self::set_override), but fails with
+    /// `GatewayError::PermissionDenied` below the `tune` level.
+    pub async fn set_override_as(
+        &self,
+        caller: &Caller,
+        channel_id: u32,
+        point_id: PointId,
+        value: Value,
+        expires_after: Option<Duration>,
+    ) -> Result<PointOverride> {
+        authorize_override(caller)?;
+        let channel = self.channel(channel_id)?;
+        if !channel.points.iter().any(|p| p.id == point_id) {
+            return Err(GatewayError::PointNotFound(format!(
+                "channel {} has no point {}",
+                channel_id, point_id
+            )));
+        }
+        self.expire_overrides().await;
+
+        let now = self.clock.now_utc();
+        let mut point_override =
+            PointOverride::new(caller, channel.store_id(), point_id, value, now);
+        if let Some(expires_after) = expires_after {
+            point_override = point_override.with_expiry(now + expires_after);
+        }
+        match self.store.save_override(&point_override).await {
+            Ok(()) | Err(GatewayError::Unsupported(_)) => {}
+            Err(e) => return Err(e),
+        }
+        self.overrides.set(point_override.clone());
+
+        #[cfg(feature = "tracing-support")]
+        tracing::warn!(
+            channel_id,
+            point_id,
+            caller = %caller.identity,
+            value = ?point_override.value,
+            expires_at = ?point_override.expires_at,
+            "Point override set"
+        );
+        store_batch(
+            self.store.as_ref(),
+            self.jsonl_sink().as_ref(),
+            &self.alarms,
+            &self.overrides,
+            point_override.channel_id,
+            DataBatch::from_points(vec![point_override.sample(now)]),
+        )
+        .await?;
+        Ok(point_override)
+    }
+
+    /// Stop overriding a point, returning the override it had.
+    ///
+    /// The stored value stays until the device next reports the point.
+    pub async fn clear_override(
+        &self,
+        channel_id: u32,
+        point_id: PointId,
+    ) -> Result<Option<PointOverride>> {
+        self.clear_override_as(&local_caller(), channel_id, point_id)
+            .await
+    }
+
+    /// Stop overriding a point on behalf of `caller`.
+    ///
+    /// Like [`clear_override()`](Self::clear_override), but fails with
+    /// `GatewayError::PermissionDenied` below the `tune` level.
+    pub async fn clear_override_as(
+        &self,
+        caller: &Caller,
+        channel_id: u32,
+        point_id: PointId,
+    ) -> Result<Option<PointOverride>> {
+        authorize_override(caller)?;
+        let store_id = self.channel(channel_id)?.store_id();
+        self.expire_overrides().await;
+        let Some(cleared) = self.overrides.remove(store_id, point_id) else {
+            return Ok(None);
+        };
+        match self.store.delete_override(store_id, point_id).await {
+            Ok(()) | Err(GatewayError::Unsupported(_)) => {}
+            Err(e) => {
+                self.overrides.set(cleared);
+                return Err(e);
+            }
+        }
+
+        #[cfg(feature = "tracing-support")]
+        tracing::info!(channel_id, point_id, caller = %caller.identity, "Point override cleared");
+        Ok(Some(cleared))
+    }
+
+    /// Overrides in effect, by channel and point id.
+    pub fn overrides(&self) -> Vec<PointOverride> {
+        self.overrides.list(None)
+    }
+
+    /// Forget lapsed overrides, also in the store.
+    async fn expire_overrides(&self) {
+        for lapsed in self.overrides.expire() {
+            let _ = self
+                .store
+                .delete_override(lapsed.channel_id, lapsed.point_id)
+                .await;
+        }
+    }
+
+    /// Poll a running channel once, outside its schedule.
+    ///
+    /// The result is stored like a scheduled poll (failed points are flagged
+    /// `Quality::CommFailure`) and returned. Results of a backup that does
+    /// not currently serve its primary's points are only returned.
+    pub async fn poll_now(&self, channel_id: u32) -> Result<PollResult> {
+        let channel = self.channel(channel_id)?;
+        if channel.task.is_none() {
+            return Err(GatewayError::NotConnected);
+        }
+
+        let result = channel.runtime.lock().await.poll_once().await;
+        if let Some(recorder) = self.recorder.borrow().as_ref() {
+            recorder.record(RecordedEntry::poll(
+                channel_id,
+                result.data.clone(),
+                &result.failures,
+            ));
+        }
+        let channel_id = match &channel.link {
+            Some(link) if link.standby.is_some() && !link.serving() => return Ok(result),
+            Some(link) => link.primary_id,
+            None => channel_id,
+        };
+        let jsonl = self.jsonl_sink();
+        if !result.data.is_empty() {
+            store_batch(
+                self.store.as_ref(),
+                jsonl.as_ref(),
+                &self.alarms,
+                &self.overrides,
+                channel_id,
+                result.data.clone(),
+            )
+            .await?;
+        }
+        if result.has_failures() {
+            let ids: Vec<PointId> = result.failures.iter().map(|f| f.point_id).collect();
+            let stored = self.store.read_points(channel_id, &ids).await?;
+            mark_quality(
+                self.store.as_ref(),
+                jsonl.as_ref(),
+                &self.alarms,
+                &self.overrides,
+                channel_id,
+                stored,
+                Quality::CommFailure,
+            )
+            .await?;
+        }
+        Ok(result)
+    }
+
+    /// Have a running channel report every point again, outside its
+    /// schedule (see [`ChannelRuntime::refresh()`]).
+    ///
+    /// A polling channel is then polled at once, like
+    /// [`poll_now()`](Self::poll_now), and the result is returned; protocols
+    /// without a refresh of their own are just polled. An event-driven
+    /// channel returns `None` once the values are requested, they arrive
+    /// as events. Refreshes are counted in the channel's diagnostics
+    /// (`extra.refreshes`).
+    pub async fn refresh(&self, channel_id: u32) -> Result<Option<PollResult>> {
+        let channel = self.channel(channel_id)?;
+        if channel.task.is_none() {
+            return Err(GatewayError::NotConnected);
+        }
+
+        let event_driven = {
+            let mut runtime = channel.runtime.lock().await;
+            let event_driven = runtime.is_event_driven();
+            match runtime.refresh().await {
+                Err(GatewayError::Unsupported(_)) if !event_driven => {}
+                result => result?,
+            }
+            event_driven
+        };
+        let result = if event_driven {
+            None
+        } else {
+            Some(self.poll_now(channel_id).await?)
+        };
+        channel.stats.refreshes.fetch_add(1, Ordering::Relaxed);
+
+        #[cfg(feature = "tracing-support")]
+        tracing::info!("Channel {} refreshed", channel_id);
+        Ok(result)
+    }
+
+    /// Send control commands `(point_id, value)` to a channel.
+    ///
+    /// Fails with `GatewayError::Interlock`, without sending anything, if
+    /// an interlock of a commanded point does not hold. Commands to points
+    /// with a feedback check are verified in the background (see
+    /// [Command feedback](self#command-feedback)). On a channel with a
+    /// `command_queue`, returns once the commands are queued (see
+    /// [Command queue](self#command-queue)).
+    ///
+    /// Commands refused on their own, such as an adjustment outside its
+    /// point's limits, or failed by the channel are listed in
+    /// [`WriteResult::failures`] while the others are sent.
+    pub async fn write_control(
+        &self,
+        channel_id: u32,
+        commands: &[(u32, f64)],
+    ) -> Result<WriteResult> {
+        self.write_commands(&local_caller(), channel_id, CommandKind::Control, commands)
+            .await
+    }
+
+    /// Send adjustment commands `(point_id, value)` to a channel.
+    ///
+    /// Checks interlocks and feedback and queues like
+    /// [`write_control()`](Self::write_control).
+    pub async fn write_adjustment(
+        &self,
+        channel_id: u32,
+        adjustments: &[(u32, f64)],
+    ) -> Result<WriteResult> {
+        self.write_commands(
+            &local_caller(),
+            channel_id,
+            CommandKind::Adjustment,
+            adjustments,
+        )
+        .await
+    }
+
+    /// Counters of the command audit history (`None` unless running with
+    /// `audit` configured).
+    pub fn audit_stats(&self) -> Option<AuditStats> {
+        self.audit.as_ref().map(AuditRecorder::stats)
+    }
+
+    /// Caller `identity` with the level the configuration grants it.
+    pub fn caller(&self, identity: impl Into<String>) -> Caller {
+        self.config.caller(identity)
+    }
+
+    /// Send control commands on behalf of `caller`.
+    ///
+    /// Like [`write_control()`](Self::write_control), but first fails with
+    /// `GatewayError::PermissionDenied`, without sending anything, if the
+    /// caller's level is below that of a commanded point (see
+    /// [Authorization](self#authorization)).
+    pub async fn write_control_as(
+        &self,
+        caller: &Caller,
+        channel_id: u32,
+        commands: &[(u32, f64)],
+    ) -> Result<WriteResult> {
+        self.write_commands(caller, channel_id, CommandKind::Control, commands)
+            .await
+    }
+
+    /// Send adjustment commands on behalf of `caller`.
+    ///
+    /// Authorizes like [`write_control_as()`](Self::write_control_as).
+    pub async fn write_adjustment_as(
+        &self,
+        caller: &Caller,
+        channel_id: u32,
+        adjustments: &[(u32, f64)],
+    ) -> Result<WriteResult> {
+        self.write_commands(caller, channel_id, CommandKind::Adjustment, adjustments)
+            .await
+    }
+
+    /// Authorize and send commands, writing the call to the audit trail.
+    async fn write_commands(
+        &self,
+        caller: &Caller,
+        channel_id: u32,
+        kind: CommandKind,
+        commands: &[(u32, f64)],
+    ) -> Result<WriteResult> {
+        let result = self.send_commands(caller, channel_id, kind, commands).await;
+        let audit = CommandAudit::new(caller, channel_id, kind, commands, &result);
+        if let Some(jsonl) = self.jsonl_sink() {
+            jsonl.emit(JsonlEvent::CommandAudit(audit.clone()));
+        }
+        if let Some(recorder) = &self.audit {
+            recorder.record(audit);
+        }
+        result
+    }
+
+    async fn send_commands(
+        &self,
+        caller: &Caller,
+        channel_id: u32,
+        kind: CommandKind,
+        commands: &[(u32, f64)],
+    ) -> Result<WriteResult> {
+        let channel = self.channel(channel_id)?;
+        caller.authorize(&channel.config, kind, commands)?;
+        let target = self.command_target(channel);
+        let mut rejected = Vec::new();
+        let commands = target.check_limits(kind, commands, &mut rejected);
+        let mut result = match &channel.config.command_queue {
+            _ if commands.is_empty() => WriteResult::success(0),
+            None => target.send(kind, &commands).await?,
+            Some(queue) => {
+                target.check_interlocks(&commands).await?;
+                // Queued commands report their outcome later, as command
+                // updates
+                let queued = channel
+                    .stats
+                    .commands
+                    .push(queue, target, kind, &commands)?;
+                WriteResult::accepted(queued)
+            }
+        };
+        result.failures.extend(rejected);
+        Ok(result)
+    }
+
+    fn command_target(&self, channel: &ManagedChannel) -> CommandTarget {
+        let channel_id = channel.id();
+        CommandTarget {
+            channel_id,
+            store_id: channel
+                .link
+                .as_ref()
+                .map_or(channel_id, |link| link.primary_id),
+            runtime: Arc::clone(&channel.runtime),
+            store: Arc::clone(&self.store),
+            clock: Arc::clone(&self.clock),
+            events: channel.events.clone(),
+            jsonl: self.jsonl_sink(),
+            interlocks: channel.config.interlocks.clone(),
+            feedback: channel
+                .config
+                .points
+                .iter()
+                .filter_map(|p| Some((p.id, p.feedback.clone()?)))
+                .collect(),
+            limits: channel
+                .config
+                .points
+                .iter()
+                .filter_map(|p| Some((p.id, WriteLimits::of(&p.transform)?)))
+                .collect(),
+            clamp: channel.config.clamp_adjustments,
+        }
+    }
+
+    /// Start recording every poll result and data event of every channel
+    /// to `path` (appending), replacing any active recording.
+    ///
+    /// Takes effect immediately for running channels, see
+    /// [`recording`](super::recording).
+    pub async fn start_recording(&mut self, path: impl Into<PathBuf>) -> Result<()> {
+        let recording = Recording::start(path)?;
+        self.stop_recording().await;
+
+        #[cfg(feature = "tracing-support")]
+        tracing::info!("Recording to {}", recording.path().display());
+
+        self.recorder
+            .send_replace(Some(recording.recorder().clone()));
+        self.recording = Some(recording);
+        Ok(())
+    }
+
+    /// Stop recording and wait until the recording file is flushed.
+    pub async fn stop_recording(&mut self) {
+        self.recorder.send_replace(None);
+        if let Some(recording) = self.recording.take() {
+            recording.close().await;
+        }
+    }
+
+    /// File being recorded to, if a recording is active.
+    pub fn recording_path(&self) -> Option<&Path> {
+        self.recording.as_ref().map(Recording::path)
+    }
+
+    /// Start, stop or restart the heartbeat task to match the
+    /// configuration, and point it at the current channels.
+    fn wire_heartbeat(&mut self) {
+        let config = self
+            .config
+            .gateway
+            .heartbeat
+            .clone()
+            .filter(|_| self.running);
+        if self.heartbeat.as_ref().map(Heartbeat::config) == config.as_ref() {
+            if let Some(heartbeat) = &self.heartbeat {
+                heartbeat.rewire(self.heartbeat_wiring(heartbeat.config()));
+            }
+            return;
+        }
+        if let Some(heartbeat) = self.heartbeat.take() {
+            heartbeat.stop();
+        }
+        self.heartbeat = config.map(|config| {
+            let wiring = self.heartbeat_wiring(&config);
+            Heartbeat::spawn(config, wiring)
+        });
+    }
+
+    fn heartbeat_wiring(&self, config: &HeartbeatConfig) -> Wiring {
+        Wiring {
+            target: self
+                .channels
+                .iter()
+                .find(|c| c.id() == config.channel_id && c.task.is_some())
+                .map(|c| Arc::clone(&c.runtime)),
+            monitors: self
+                .channels
+                .iter()
+                .filter(|c| config.monitored_channels.contains(&c.id()))
+                .map(|c| (c.id(), Arc::clone(&c.stats)))
+                .collect(),
+        }
+    }
+
+    fn jsonl_sink(&self) -> Option<JsonlSink> {
+        self.jsonl.as_ref().map(|jsonl| jsonl.sink().clone())
+    }
+
+    fn channel_output(&self) -> Option<ChannelOutput> {
+        let interval_ms = self.config.gateway.diagnostics_interval_ms;
+        self.jsonl_sink().map(|sink| ChannelOutput {
+            sink,
+            diagnostics_interval: (interval_ms > 0).then(|| Duration::from_millis(interval_ms)),
+        })
+    }
+
+    fn channel(&self, channel_id: u32) -> Result<&ManagedChannel> {
+        self.channels
+            .iter()
+            .find(|c| c.id() == channel_id)
+            .ok_or_else(|| GatewayError::Config(format!("unknown channel {}", channel_id)))
+    }
+
+    fn channel_mut(&mut self, channel_id: u32) -> Result<&mut ManagedChannel> {
+        self.channels
+            .iter_mut()
+            .find(|c| c.id() == channel_id)
+            .ok_or_else(|| GatewayError::Config(format!("unknown channel {}", channel_id)))
+    }
+}
+
+/// Write a batch, with the alarm signals it triggers, to the store and, on
+/// success, to the JSON Lines output.
+async fn store_batch(
+    store: &dyn DataStore,
+    jsonl: Option<&JsonlSink>,
+    alarms: &AlarmEvaluator,
+    overrides: &Overrides,
+    channel_id: u32,
+    batch: impl Into<Arc<DataBatch>>,
+) -> Result<()> {
+    let mut batch = overrides.apply(channel_id, batch.into());
+    let signals = alarms.evaluate(channel_id, &batch);
+    if !signals.is_empty() {
+        // Re-written stored signals are replaced by the fresh evaluation
+        let mut merged = Arc::unwrap_or_clone(batch);
+        merged.retain(|p| signals.get(p.id).is_none());
+        merged.merge(signals);
+        batch = Arc::new(merged);
+    }
+    store.write_batch(channel_id, &batch).await?;
+    if let Some(sink) = jsonl {
+        sink.emit(JsonlEvent::data(channel_id, batch));
+    }
+    Ok(())
+}
+
+/// Re-write stored points with a new quality, keeping their values.
+async fn mark_quality(
+    store: &dyn DataStore,
+    jsonl: Option<&JsonlSink>,
+    alarms: &AlarmEvaluator,
+    overrides: &Overrides,
+    channel_id: u32,
+    stored: DataBatch,
+    quality: Quality,
+) -> Result<()> {
+    let changed: DataBatch = stored
+        .into_iter()
+        .filter(|p| p.quality != quality)
+        .map(|p| p.with_quality(quality))
+        .collect();
+    if changed.is_empty() {
+        return Ok(());
+    }
+    store_batch(store, jsonl, alarms, overrides, channel_id, changed).await
+}
+
+/// Why a connected session ended.
+enum SessionEnd {
+    Shutdown,
+    ConnectionLost(String),
+    /// A cold backup was demoted.
+    Standby,
+}
+
+/// Supervisor for one channel.
+struct ChannelTask {
+    channel_id: u32,
+    poll_interval: Duration,
+    phase: PollPhase,
+    runtime: SharedChannel,
+    store: Arc<dyn DataStore>,
+    alarms: Arc<AlarmEvaluator>,
+    overrides: Arc<Overrides>,
+    clock: SharedClock,
+    shutdown: watch::Receiver<bool>,
+    backoff: Backoff,
+    stats: Arc<ChannelStats>,
+    events: EventBus,
+    /// Last connection state published on `events`.
+    state: Option<ConnectionState>,
+    output: Option<ChannelOutput>,
+    /// Recorder while a recording is active.
+    recorder: watch::Receiver<Option<Recorder>>,
+    link: GroupLink,
+    /// Whether the group's backup serves the points.
+    active: watch::Receiver<bool>,
+    max_ages: MaxAges,
+    event_buffer: EventBufferConfig,
+    /// Backfill after connecting, with the points resolved.
+    backfill: Option<BackfillConfig>,
+    /// Change detection on poll results.
+    delta: Option<DeltaFilter>,
+    /// Wait after the dependencies connected.
+    startup_delay: Duration,
+    /// Longest wait for the dependencies to connect.
+    dependency_timeout: Duration,
+}
+
+impl ChannelTask {
+    async fn run(mut self) {
+        let snapshots = self.output.as_ref().and_then(|output| {
+            Some(snapshot_loop(
+                self.channel_id,
+                Arc::clone(&self.runtime),
+                Arc::clone(&self.store),
+                Arc::clone(&self.stats),
+                self.link.clone(),
+                self.phase.clone(),
+                output.sink.clone(),
+                output.diagnostics_interval?,
+            ))
+        });
+        let failover = self.link.standby.map(|standby| {
+            failover_monitor(
+                self.channel_id,
+                self.link.clone(),
+                standby.failover_after,
+                self.jsonl().cloned(),
+            )
+        });
+
+        let staleness = (!self.max_ages.is_empty()).then(|| {
+            staleness_monitor(
+                Arc::clone(&self.store),
+                Arc::clone(&self.alarms),
+                Arc::clone(&self.overrides),
+                Arc::clone(&self.clock),
+                self.link.clone(),
+                Arc::clone(&self.max_ages),
+                self.jsonl().cloned(),
+            )
+        });
+
+        // A panic (e.g. in a protocol's decoder) ends this channel only
+        let panic = tokio::select! {
+            result = AssertUnwindSafe(self.supervise()).catch_unwind() => result.err(),
+            _ = optional(snapshots) => None,
+            _ = optional(failover) => None,
+            _ = optional(staleness) => None,
+        };
+        if let Some(panic) = panic {
+            self.crashed(panic).await;
+        }
+    }
+
+    /// Put the channel into the `Error` state after its task panicked.
+    async fn crashed(&mut self, panic: Box<dyn Any + Send>) {
+        let message = panic
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| panic.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown cause".into());
+        #[cfg(feature = "tracing-support")]
+        tracing::error!("Channel {} task panicked: {}", self.channel_id, message);
+        self.stats.panics.fetch_add(1, Ordering::Relaxed);
+        *self.stats.panic() = Some(message);
+        self.mark_all(Quality::NotConnected).await;
+        self.publish_state(ConnectionState::Error);
+    }
+
+    /// Id the channel's points are stored under.
+    fn store_id(&self) -> u32 {
+        self.link.primary_id
+    }
+
+    /// Backup waiting disconnected while its primary is healthy.
+    fn cold_standby(&self) -> bool {
+        self.link
+            .standby
+            .is_some_and(|s| s.mode == StandbyMode::Cold)
+    }
+
+    /// Wait until this backup is promoted; `false` on shutdown.
+    async fn wait_until_promoted(&mut self) -> bool {
+        self.stats.standby.store(true, Ordering::Relaxed);
+        let promoted = loop {
+            if *self.active.borrow_and_update() {
+                break true;
+            }
+            tokio::select! {
+                _ = self.shutdown.changed() => break false,
+                changed = self.active.changed() => {
+                    if changed.is_err() {
+                        break false;
+                    }
+                }
+            }
+        };
+        self.stats.standby.store(false, Ordering::Relaxed);
+        self.stats.beat();
+        promoted
+    }
+
+    /// Wait until the channels this one depends on are connected, or for
+    /// `dependency_timeout`, then for `startup_delay`; `false` on shutdown.
+    async fn wait_for_dependencies(&mut self) -> bool {
+        if self.link.dependencies.is_empty() {
+            return true;
+        }
+        // Exempt from the watchdog while waiting
+        self.stats.standby.store(true, Ordering::Relaxed);
+        let mut dependencies = self.link.dependencies.clone();
+        let connected = join_all(dependencies.iter_mut().map(|(_, state)| async move {
+            let _ = state.wait_for(ConnectionState::is_connected).await;
+        }));
+        let mut shutdown = self.shutdown.clone();
+        let stopped = async move {
+            let _ = shutdown.wait_for(|stop| *stop).await;
+        };
+        tokio::pin!(stopped);
+        let waited = tokio::select! {
+            _ = &mut stopped => None,
+            result = timeout(self.dependency_timeout, connected) => Some(result.is_ok()),
+        };
+        let started = match waited {
+            None => false,
+            Some(true) => {
+                tokio::select! {
+                    _ = stopped => false,
+                    _ = tokio::time::sleep(self.startup_delay) => true,
+                }
+            }
+            Some(false) => {
+                let missing: Vec<String> = dependencies
+                    .iter()
+                    .filter(|(_, state)| !state.borrow().is_connected())
+                    .map(|(id, _)| id.to_string())
+                    .collect();
+                let e = GatewayError::connection(format!(
+                    "channel(s) {} not connected after {} ms, connecting anyway",
+                    missing.join(", "),
+                    self.dependency_timeout.as_millis()
+                ));
+                #[cfg(feature = "tracing-support")]
+                tracing::warn!("Channel {} dependencies: {}", self.channel_id, e);
+                if let Some(sink) = self.jsonl() {
+                    sink.emit(JsonlEvent::channel_error(self.channel_id, &e));
+                }
+                true
+            }
+        };
+        self.stats.standby.store(false, Ordering::Relaxed);
+        self.stats.beat();
+        started
+    }
+
+    async fn supervise(&mut self) {
+        if !self.wait_for_dependencies().await {
+            return;
+        }
+        let mut delay = self.backoff.min;
+
+        while !*self.shutdown.borrow() {
+            if self.cold_standby() && !self.link.serving() && !self.wait_until_promoted().await {
+                break;
+            }
+
+            let connected = self.connect().await;
+            self.stats.beat();
+            match connected {
+                Ok(events) => {
+                    delay = self.backoff.min;
+                    self.publish_state(ConnectionState::Connected);
+                    self.backfill().await;
+                    let end = match events {
+                        Some(rx) => self.pump_events(rx).await,
+                        None => self.poll_loop().await,
+                    };
+                    match end {
+                        SessionEnd::Shutdown => break,
+                        SessionEnd::Standby => {
+                            let _ = self.runtime.lock().await.disconnect().await;
+                            self.publish_state(ConnectionState::Disconnected);
+                            continue;
+                        }
+                        SessionEnd::ConnectionLost(_reason) => {
+                            #[cfg(feature = "tracing-support")]
+                            tracing::warn!(
+                                "Channel {} connection lost: {}",
+                                self.channel_id,
+                                _reason
+                            );
+                            let _ = self.runtime.lock().await.disconnect().await;
+                        }
+                    }
+                }
+                Err(e) => {
+                    #[cfg(feature = "tracing-support")]
+                    tracing::warn!("Channel {} connect failed: {}", self.channel_id, e);
+                    if let Some(sink) = self.jsonl() {
+                        sink.emit(JsonlEvent::channel_error(self.channel_id, &e));
+                    }
+                    if !e.needs_reconnect() && !e.is_retryable() {
+                        self.mark_all(Quality::NotConnected).await;
+                        self.publish_state(ConnectionState::Error);
+                        break;
+                    }
+                }
+            }
+
+            self.mark_all(Quality::NotConnected).await;
+            self.publish_state(ConnectionState::Reconnecting);
+            tokio::select! {
+                _ = self.shutdown.changed() => {}
+                _ = tokio::time::sleep(delay) => {}
+            }
+            delay = (delay * 2).min(self.backoff.max);
+        }
+    }
+
+    /// Connect in a `connect` span, logging how long it took.
+    async fn connect(&self) -> Result<Option<DataEventReceiver>> {
+        let _started = Instant::now();
+        let connect = self.open();
+        #[cfg(feature = "tracing-support")]
+        let connect = tracing::Instrument::instrument(connect, tracing::debug_span!("connect"));
+        let result = connect.await;
+        #[cfg(feature = "tracing-support")]
+        if result.is_ok() {
+            tracing::debug!(
+                elapsed_ms = _started.elapsed().as_millis() as u64,
+                "Connected"
+            );
+        }
+        result
+    }
+
+    /// Connect; for event-driven channels also start and subscribe to events.
+    async fn open(&self) -> Result<Option<DataEventReceiver>> {
+        let slot = self.phase.acquire().await;
+        self.stats.throttled(&slot);
+        let mut runtime = self.runtime.lock().await;
+        runtime.connect().await?;
+        if runtime.is_event_driven() {
+            runtime.start_events().await?;
+            return Ok(runtime.subscribe());
+        }
+        Ok(None)
+    }
+
+    async fn poll_loop(&mut self) -> SessionEnd {
+        let mut ticker = match self.phase.next_poll(Instant::now(), self.poll_interval) {
+            // Stay on the grid of the phase even if a tick is late
+            Some(first) => {
+                let mut ticker = tokio::time::interval_at(first, self.poll_interval);
+                ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+                ticker
+            }
+            None => {
+                let mut ticker = tokio::time::interval(self.poll_interval);
+                ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+                ticker
+            }
+        };
+        let cold = self.cold_standby();
+        if let Some(delta) = &mut self.delta {
+            delta.reset();
+        }
+
+        loop {
+            tokio::select! {
+                _ = self.shutdown.changed() => return SessionEnd::Shutdown,
+                _ = demoted(&mut self.active, cold) => return SessionEnd::Standby,
+                _ = ticker.tick() => {}
+            }
+            if !self.link.serving() {
+                // Warm backup: stay connected, do not poll. The primary
+                // writes meanwhile, so store everything after a takeover.
+                if let Some(delta) = &mut self.delta {
+                    delta.reset();
+                }
+                self.stats.beat();
+                continue;
+            }
+
+            let started = Instant::now();
+            let slot = tokio::select! {
+                _ = self.shutdown.changed() => return SessionEnd::Shutdown,
+                slot = self.phase.acquire() => slot,
+            };
+            self.stats.throttled(&slot);
+            let on_bus = self.phase.bus().map(BusLoad::enter);
+            let poll = async {
+                let mut runtime = self.runtime.lock().await;
+                let result = runtime.poll_once().await;
+                // Only ask for the connection state when nothing came back
+                let state = if result.data.is_empty() && result.has_failures() {
+                    runtime.diagnostics().await.ok().map(|d| d.connection_state)
+                } else {
+                    None
+                };
+                (result, state)
+            };
+            #[cfg(feature = "tracing-support")]
+            let poll = tracing::Instrument::instrument(poll, tracing::debug_span!("poll"));
+            let (result, state) = poll.await;
+            drop(on_bus);
+            drop(slot);
+
+            let elapsed = started.elapsed();
+            #[cfg(feature = "tracing-support")]
+            tracing::debug!(
+                points = %result.data.summary(),
+                failures = result.failures.len(),
+                elapsed_ms = elapsed.as_millis() as u64,
+                "Polled"
+            );
+            if elapsed > self.poll_interval {
+                // Skip the cycles we missed instead of polling back-to-back
+                let missed = elapsed.as_nanos() / self.poll_interval.as_nanos();
+                self.stats
+                    .poll_overruns
+                    .fetch_add(missed as u64, Ordering::Relaxed);
+                match self.phase.next_poll(Instant::now(), self.poll_interval) {
+                    Some(next) => ticker.reset_at(next),
+                    None => ticker.reset(),
+                }
+            }
+
+            if !result.data.is_empty() || !result.has_failures() {
+                self.stats.received();
+            }
+            let data = Arc::new(result.data);
+            self.record(|| {
+                Some(RecordedEntry::poll(
+                    self.channel_id,
+                    Arc::clone(&data),
+                    &result.failures,
+                ))
+            });
+            // Flag stale points first, so change detection compares with
+            // the quality they are stored with
+            let data = self.flag_stale(data).await;
+            let data = self.drop_unchanged(data, &result.failures);
+            self.persist(data).await;
+            self.mark_failures(&result.failures).await;
+
+            if let Some(state) = state.filter(|s| !s.is_connected()) {
+                return SessionEnd::ConnectionLost(format!("channel reports {:?}", state));
+            }
+        }
+    }
+
+    async fn pump_events(&mut self, mut rx: DataEventReceiver) -> SessionEnd {
+        rx.set_buffer(self.event_buffer.capacity, self.event_buffer.overflow);
+        self.stats.track_events(rx.counters());
+        let cold = self.cold_standby();
+        loop {
+            let event = tokio::select! {
+                _ = self.shutdown.changed() => return SessionEnd::Shutdown,
+                _ = demoted(&mut self.active, cold) => return SessionEnd::Standby,
+                event = rx.recv() => event,
+            };
+            self.stats.beat();
+            if let Some(event) = &event {
+                self.record(|| RecordedEntry::event(self.channel_id, event));
+            }
+
+            match event {
+                Some(DataEvent::DataUpdate(batch)) => {
+                    self.stats.received();
+                    self.write(batch).await;
+                }
+                Some(DataEvent::ConnectionChanged(state)) if !state.is_connected() => {
+                    return SessionEnd::ConnectionLost(format!("channel reports {:?}", state));
+                }
+                Some(DataEvent::Error(_e)) => {
+                    #[cfg(feature = "tracing-support")]
+                    tracing::warn!("Channel {} event error: {}", self.channel_id, _e);
+                }
+                Some(_) => {}
+                None => return SessionEnd::ConnectionLost("event stream closed".into()),
+            }
+        }
+    }
+
+    /// Add the samples the device recorded since the last stored sample of
+    /// each backfilled point to the point history.
+    async fn backfill(&self) {
+        let Some(config) = &self.backfill else {
+            return;
+        };
+        if config.points.is_empty() || !self.link.serving() {
+            return;
+        }
+        let store_id = self.store_id();
+        let end = self.clock.now_utc();
+        let oldest = end - Duration::from_millis(config.max_gap_ms);
+        let Ok(stored) = self.store.read_points(store_id, &config.points).await else {
+            return;
+        };
+        let since: HashMap<PointId, DateTime<Utc>> = config
+            .points
+            .iter()
+            .map(|&id| {
+                let last = stored.get(id).map_or(oldest, |p| p.timestamp);
+                (id, last.max(oldest))
+            })
+            .collect();
+        let Some(start) = since.values().min().copied() else {
+            return;
+        };
+
+        let history = self
+            .runtime
+            .lock()
+            .await
+            .read_history(&config.points, start, end)
+            .await;
+        let samples: DataBatch = match history {
+            Ok(samples) => samples
+                .into_iter()
+                .filter(|p| p.timestamp <= end)
+                .filter(|p| since.get(&p.id).is_some_and(|&last| p.timestamp > last))
+                .collect(),
+            Err(_e) => {
+                #[cfg(feature = "tracing-support")]
+                tracing::warn!("Channel {} backfill failed: {}", self.channel_id, _e);
+                return;
+            }
+        };
+        if samples.is_empty() {
+            return;
+        }
+
+        match self.store.write_history(store_id, &samples).await {
+            Ok(_added) => {
+                #[cfg(feature = "tracing-support")]
+                tracing::info!(
+                    samples = samples.len(),
+                    added = _added,
+                    "Channel {} backfilled since {}",
+                    self.channel_id,
+                    start
+                );
+            }
+            Err(_e) => {
+                #[cfg(feature = "tracing-support")]
+                tracing::warn!("Channel {} backfill not stored: {}", self.channel_id, _e);
+            }
+        }
+        if config.forward {
+            if let Some(sink) = self.jsonl() {
+                sink.emit(JsonlEvent::data(store_id, samples));
+            }
+        }
+    }
+
+    /// Drop the points of a poll result that are stored unchanged already.
+    ///
+    /// Failed points are stored as `CommFailure`, so their next sample is
+    /// always written.
+    fn drop_unchanged(
+        &mut self,
+        data: Arc<DataBatch>,
+        failures: &[PointFailure],
+    ) -> Arc<DataBatch> {
+        let store_id = self.store_id();
+        let Some(delta) = &mut self.delta else {
+            return data;
+        };
+        let overrides = &self.overrides;
+        let (data, dropped) =
+            delta.filter(data, Instant::now(), |id| overrides.is_active(store_id, id));
+        delta.forget(failures.iter().map(|f| f.point_id));
+        if dropped > 0 {
+            self.stats
+                .points_suppressed
+                .fetch_add(dropped as u64, Ordering::Relaxed);
+        }
+        data
+    }
+
+    /// Flag stale points of a batch and store it. Idle backups drop their
+    /// data.
+    async fn write(&self, batch: impl Into<Arc<DataBatch>>) {
+        let batch = self.flag_stale(batch.into()).await;
+        self.persist(batch).await;
+    }
+
+    /// Store a batch as it is. Idle backups drop their data.
+    async fn persist(&self, batch: Arc<DataBatch>) {
+        if batch.is_empty() || self.idle_backup() {
+            return;
+        }
+        let result = store_batch(
+            self.store.as_ref(),
+            self.jsonl(),
+            &self.alarms,
+            &self.overrides,
+            self.store_id(),
+            batch,
+        )
+        .await;
+        if let Err(_e) = result {
+            #[cfg(feature = "tracing-support")]
+            tracing::error!("Channel {} store write failed: {}", self.channel_id, _e);
+        }
+    }
+
+    /// Keep points whose value has not changed for longer than their
+    /// `max_age` at `Uncertain`, so re-polling a flatlined sensor does not
+    /// make it look healthy again.
+    async fn flag_stale(&self, batch: Arc<DataBatch>) -> Arc<DataBatch> {
+        if self.max_ages.is_empty() || batch.is_empty() || self.idle_backup() {
+            return batch;
+        }
+        let store_id = self.store_id();
+        let now = self.clock.now_utc();
+        let expired: Vec<(PointId, PointAge, Duration)> = batch
+            .iter()
+            .filter(|p| p.quality == Quality::Good)
+            .filter_map(|p| {
+                let (age, max_age) =
+                    expired_age(self.store.as_ref(), store_id, &self.max_ages, p.id, now)?;
+                Some((p.id, age, max_age))
+            })
+            .collect();
+        if expired.is_empty() {
+            return batch;
+        }
+        let ids: Vec<PointId> = expired.iter().map(|(id, ..)| *id).collect();
+        let Ok(stored) = self.store.read_points(store_id, &ids).await else {
+            return batch;
+        };
+
+        let mut batch = Arc::unwrap_or_clone(batch);
+        for (id, age, max_age) in expired {
+            let Some(old) = stored.get(id) else {
+                continue;
+            };
+            for point in batch.iter_mut().filter(|p| p.id == id) {
+                if point.value == old.value {
+                    point.quality = Quality::Uncertain;
+                }
+            }
+            if old.quality != Quality::Uncertain
+                && batch
+                    .get(id)
+                    .is_some_and(|p| p.quality == Quality::Uncertain)
+            {
+                report_stale(store_id, id, age, max_age, self.jsonl());
+            }
+        }
+        Arc::new(batch)
+    }
+
+    /// Whether this is a backup channel that does not serve the group.
+    fn idle_backup(&self) -> bool {
+        self.link.standby.is_some() && !self.link.serving()
+    }
+
+    /// Keep the stored values of failed points but flag them `CommFailure`.
+    async fn mark_failures(&self, failures: &[PointFailure]) {
+        if failures.is_empty() || !self.link.serving() {
+            return;
+        }
+        #[cfg(feature = "tracing-support")]
+        for failure in failures {
+            tracing::debug!(point_id = failure.point_id, error = %failure.error, "Point read failed");
+        }
+        let ids: Vec<PointId> = failures.iter().map(|f| f.point_id).collect();
+        let Ok(stored) = self.store.read_points(self.store_id(), &ids).await else {
+            return;
+        };
+        self.mark(stored, Quality::CommFailure).await;
+    }
+
+    /// Mark every stored point, unless the other channel of the redundancy
+    /// group serves them.
+    async fn mark_all(&self, quality: Quality) {
+        if !self.link.serving() {
+            return;
+        }
+        let Ok(stored) = self.store.read_all(self.store_id()).await else {
+            return;
+        };
+        self.mark(stored, quality).await;
+    }
+
+    async fn mark(&self, stored: DataBatch, quality: Quality) {
+        let result = mark_quality(
+            self.store.as_ref(),
+            self.jsonl(),
+            &self.alarms,
+            &self.overrides,
+            self.store_id(),
+            stored,
+            quality,
+        )
+        .await;
+        if let Err(_e) = result {
+            #[cfg(feature = "tracing-support")]
+            tracing::error!("Channel {} store write failed: {}", self.channel_id, _e);
+        }
+    }
+
+    /// Publish a connection state change (repeats are suppressed).
+    fn publish_state(&mut self, state: ConnectionState) {
+        if self.state != Some(state) {
+            self.state = Some(state);
+            self.stats.connection().record(state);
+            self.events.publish(DataEvent::ConnectionChanged(state));
+            if self.link.standby.is_none() {
+                self.link.group.primary_state.send_replace(state);
+            }
+            if let Some(sink) = self.jsonl() {
+                sink.emit(JsonlEvent::connection_state(self.channel_id, state));
+            }
+        }
+    }
+
+    fn jsonl(&self) -> Option<&JsonlSink> {
+        self.output.as_ref().map(|o| &o.sink)
+    }
+
+    /// Record an entry if a recording is active.
+    fn record(&self, entry: impl FnOnce() -> Option<RecordedEntry>) {
+        if let Some(recorder) = self.recorder.borrow().as_ref() {
+            if let Some(entry) = entry() {
+                recorder.record(entry);
+            }
+        }
+    }
+}
+
+/// Emit a diagnostics snapshot of the channel every `interval`.
+#[allow(clippy::too_many_arguments)]
+async fn snapshot_loop(
+    channel_id: u32,
+    runtime: SharedChannel,
+    store: Arc<dyn DataStore>,
+    stats: Arc<ChannelStats>,
+    link: GroupLink,
+    phase: PollPhase,
+    sink: JsonlSink,
+    interval: Duration,
+) {
+    let mut ticker = tokio::time::interval_at(Instant::now() + interval, interval);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        ticker.tick().await;
+        match read_diagnostics(&runtime, &stats, Some(&link), &phase).await {
+            Ok(diagnostics) => {
+                let points = match store.read_all(link.primary_id).await {
+                    Ok(stored) => stored.summary(),
+                    Err(_) => BatchSummary::default(),
+                };
+                sink.emit(JsonlEvent::diagnostics(channel_id, diagnostics, points));
+            }
+            Err(_e) => {
+                #[cfg(feature = "tracing-support")]
+                tracing::debug!("Channel {} diagnostics failed: {}", channel_id, _e);
+            }
+        }
+    }
+}
+
+/// Mark points `Uncertain` once their value has not changed for longer than
+/// their `max_age`, also when no new samples arrive at all.
+async fn staleness_monitor(
+    store: Arc<dyn DataStore>,
+    alarms: Arc<AlarmEvaluator>,
+    overrides: Arc<Overrides>,
+    clock: SharedClock,
+    link: GroupLink,
+    max_ages: MaxAges,
+    sink: Option<JsonlSink>,
+) {
+    let Some(shortest) = max_ages.values().min() else {
+        return std::future::pending().await;
+    };
+    let interval = (*shortest / 2).clamp(Duration::from_millis(100), Duration::from_secs(10));
+    let store_id = link.primary_id;
+
+    loop {
+        clock.sleep(interval).await;
+        if !link.serving() {
+            continue;
+        }
+
+        let now = clock.now_utc();
+        let expired: Vec<(PointId, PointAge, Duration)> = max_ages
+            .keys()
+            .filter_map(|&id| {
+                let (age, max_age) = expired_age(store.as_ref(), store_id, &max_ages, id, now)?;
+                Some((id, age, max_age))
+            })
+            .collect();
+        if expired.is_empty() {
+            continue;
+        }
+        let ids: Vec<PointId> = expired.iter().map(|(id, ..)| *id).collect();
+        let Ok(stored) = store.read_points(store_id, &ids).await else {
+            continue;
+        };
+
+        // Points already flagged, or bad for another reason, stay as they are
+        let stale: DataBatch = stored
+            .into_iter()
+            .filter(|p| p.quality == Quality::Good)
+            .collect();
+        for (id, age, max_age) in &expired {
+            if stale.get(*id).is_some() {
+                report_stale(store_id, *id, *age, *max_age, sink.as_ref());
+            }
+        }
+        if let Err(_e) = mark_quality(
+            store.as_ref(),
+            sink.as_ref(),
+            &alarms,
+            &overrides,
+            store_id,
+            stale,
+            Quality::Uncertain,
+        )
+        .await
+        {
+            #[cfg(feature = "tracing-support")]
+            tracing::error!("Channel {} store write failed: {}", store_id, _e);
+        }
+    }
+}
+
+/// The point's age and `max_age` if its value has not changed for longer
+/// than that at `now`.
+fn expired_age(
+    store: &dyn DataStore,
+    store_id: u32,
+    max_ages: &HashMap<PointId, Duration>,
+    point_id: PointId,
+    now: DateTime<Utc>,
+) -> Option<(PointAge, Duration)> {
+    let max_age = *max_ages.get(&point_id)?;
+    let age = store.point_age(store_id, point_id).ok()??;
+    (age.age_at(now) > max_age).then_some((age, max_age))
+}
+
+/// Log a point that went stale and write a `point_stale` event.
+fn report_stale(
+    channel_id: u32,
+    point_id: PointId,
+    age: PointAge,
+    max_age: Duration,
+    sink: Option<&JsonlSink>,
+) {
+    let max_age_ms = max_age.as_millis() as u64;
+    if let Some(sink) = sink {
+        sink.emit(JsonlEvent::point_stale(
+            channel_id, point_id, age, max_age_ms,
+        ));
+    }
+
+    #[cfg(feature = "tracing-support")]
+    tracing::warn!(
+        "Channel {} point {}: value unchanged since {} (max age {} ms)",
+        channel_id,
+        point_id,
+        age.last_change,
+        max_age_ms
+    );
+}
+
+/// Promote the backup once the primary has been down for
+/// `failover_after`, demote it when the primary reconnects.
+async fn failover_monitor(
+    backup_id: u32,
+    link: GroupLink,
+    failover_after: Duration,
+    sink: Option<JsonlSink>,
+) {
+    let group = &link.group;
+    let mut primary = group.primary_state.subscribe();
+    let mut down_since = None;
+
+    loop {
+        let primary_up = primary.borrow_and_update().is_connected();
+        let backup_active = *group.backup_active.borrow();
+
+        if primary_up {
+            down_since = None;
+            if backup_active {
+                switchover(&link, link.primary_id, sink.as_ref());
+            }
+        } else if !backup_active {
+            let since = *down_since.get_or_insert_with(Instant::now);
+            tokio::select! {
+                _ = tokio::time::sleep_until(since + failover_after) => {
+                    switchover(&link, backup_id, sink.as_ref());
+                    continue;
+                }
+                changed = primary.changed() => {
+                    if changed.is_err() {
+                        return std::future::pending().await;
+                    }
+                    continue;
+                }
+            }
+        }
+
+        if primary.changed().await.is_err() {
+            return std::future::pending().await;
+        }
+    }
+}
+
+/// Hand the group's points to `active_id`.
+fn switchover(link: &GroupLink, active_id: u32, sink: Option<&JsonlSink>) {
+    let primary_id = link.primary_id;
+    link.group
+        .backup_active
+        .send_replace(active_id != primary_id);
+    link.group.switchovers.fetch_add(1, Ordering::Relaxed);
+    if let Some(sink) = sink {
+        sink.emit(JsonlEvent::switchover(primary_id, active_id));
+    }
+
+    #[cfg(feature = "tracing-support")]
+    tracing::warn!(
+        "Channel {}: switched over to channel {}",
+        primary_id,
+        active_id
+    );
+}
+
+/// Resolves when a cold backup is demoted (never for other channels).
+async fn demoted(active: &mut watch::Receiver<bool>, cold: bool) {
+    if cold {
+        while active.changed().await.is_ok() {
+            if !*active.borrow_and_update() {
+                return;
+            }
+        }
+    }
+    std::future::pending().await
+}
+
+/// Await `future` if present, else never resolve.
+async fn optional(future: Option<impl std::future::Future<Output = ()>>) {
+    match future {
+        Some(future) => future.await,
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::clock::{Clock, SimulatedClock};
+    use crate::core::data::{DataPoint, Value};
+    use crate::core::traits::{CommandOutcome, CommandStage};
+    use crate::gateway::audit::AuditResult;
+    use crate::gateway::config::{
+        AuditConfig, ChangeDetectionConfig, CommandQueueConfig, QueuePolicy,
+    };
+    use crate::store::MemoryStore;
+    use crate::testing::{MockCall, MockClient, MockHandle};
+
+    fn virtual_config() -> GatewayConfig {
+        serde_json::from_value(serde_json::json!({
+            "gateway": { "name": "test", "default_poll_interval_ms": 10 },
+            "channels": [
+                {
+                    "id": 1,
+                    "name": "hub",
+                    "protocol": "virtual",
+                    "points": [
+                        { "id": 10, "name": "a", "address": "a" },
+                        { "id": 11, "name": "b", "address": "b" }
+                    ]
+                },
+                { "id": 2, "name": "off", "protocol": "virtual", "enabled": false }
+            ]
+        }))
+        .unwrap()
+    }
+
+    fn virtual_channel(id: u32, points: &[PointId]) -> ChannelConfig {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "name": format!("ch{}", id),
+            "protocol": "virtual",
+            "points": points
+                .iter()
+                .map(|p| serde_json::json!({ "id": p, "name": "p", "address": p.to_string() }))
+                .collect::<Vec<_>>()
+        }))
+        .unwrap()
+    }
+
+    /// Add a mock channel polled every `interval_ms`.
+    fn add_mock(
+        runtime: &mut GatewayRuntime,
+        config: ChannelConfig,
+        interval_ms: u64,
+        mock: MockClient,
+    ) -> MockHandle {
+        let handle = mock.handle();
+        runtime.channels.push(ManagedChannel {
+            poll_interval: Duration::from_millis(interval_ms),
+            config,
+            points: Vec::new(),
+            runtime: Arc::new(Mutex::new(Box::new(mock))),
+            task: None,
+            stats: Arc::default(),
+            disabled: false,
+            parked: false,
+            recent_restarts: VecDeque::new(),
+            events: EventBus::default(),
+            link: None,
+            phase: PollPhase::default(),
+        });
+        handle
+    }
+
+    /// Add a mock channel polled every `interval_ms`, taking `poll_time`
+    /// per poll.
+    fn add_counting(
+        runtime: &mut GatewayRuntime,
+        id: u32,
+        interval_ms: u64,
+        poll_time: Duration,
+    ) -> MockHandle {
+        let mut config = virtual_channel(id, &[]);
+        config.poll_interval_ms = Some(interval_ms);
+        let mock = MockClient::new().with_poll_latency(poll_time);
+        add_mock(runtime, config, interval_ms, mock)
+    }
+
+    fn empty_runtime() -> GatewayRuntime {
+        let mut config = virtual_config();
+        config.channels.clear();
+        GatewayRuntime::from_config(config, Arc::new(MemoryStore::new())).unwrap()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_channels_poll_at_their_own_interval() {
+        let mut runtime = empty_runtime();
+        let fast = add_counting(&mut runtime, 1, 100, Duration::ZERO);
+        let slow = add_counting(&mut runtime, 2, 1000, Duration::ZERO);
+
+        runtime.start().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(9950)).await;
+        runtime.stop().await.unwrap();
+
+        // Ticks at 0, 100, ..., 9900 and 0, 1000, ..., 9000
+        assert_eq!(fast.polls(), 100);
+        assert_eq!(slow.polls(), 10);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_bus_channels_are_staggered() {
+        /// Most polls in flight at once on the bus of channel 1.
+        async fn max_concurrent(offsets: [Option<u64>; 4]) -> (u64, Vec<serde_json::Value>) {
+            let mut runtime = empty_runtime();
+            for (id, offset) in (1..).zip(offsets) {
+                add_counting(&mut runtime, id, 100, Duration::from_millis(20));
+                let channel = runtime.channels.last_mut().unwrap();
+                channel.config.bus = Some("rs485-1".into());
+                channel.config.poll_offset_ms = offset;
+            }
+            // Mocks are not part of the runtime's configuration
+            runtime
+                .buses
+                .configure(runtime.channels.iter().map(|c| &c.config));
+            runtime.start().await.unwrap();
+            tokio::time::sleep(Duration::from_millis(1000)).await;
+
+            let mut offsets = Vec::new();
+            let mut max = 0;
+            for id in 1..=4 {
+                let diagnostics = runtime.channel_diagnostics(id).await.unwrap();
+                let extra = diagnostics.diagnostics.unwrap().extra;
+                offsets.push(extra["poll_offset_ms"].clone());
+                max = extra["bus_max_concurrent_polls"].as_u64().unwrap();
+            }
+            runtime.stop().await.unwrap();
+            (max, offsets)
+        }
+
+        // Same phase: all four poll at once
+        let (max, _) = max_concurrent([Some(0); 4]).await;
+        assert_eq!(max, 4);
+
+        // Staggered by the runtime: one poll at a time
+        let (max, offsets) = max_concurrent([None; 4]).await;
+        assert_eq!(max, 1);
+        assert_eq!(offsets, vec![0, 25, 50, 75]);
+
+        // Explicit offsets
+        let (max, offsets) = max_concurrent([Some(50), Some(0), Some(25), None]).await;
+        assert_eq!(max, 1);
+        assert_eq!(offsets, vec![50, 0, 25, 75]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_stop_writes_safe_state_and_bounds_disconnect() {
+        let mut runtime = empty_runtime();
+        runtime.config.gateway.shutdown_timeout_ms = 50;
+        let mut mocks = Vec::new();
+        for (id, hang) in [(1, false), (2, true)] {
+            let mut config = virtual_channel(id, &[10, 11]);
+            config.safe_state = serde_json::from_value(serde_json::json!([
+                { "point_id": 10, "kind": "control", "value": 0 },
+                { "point_id": 11, "kind": "adjustment", "value": 12.5 }
+            ]))
+            .unwrap();
+            let mock = add_mock(&mut runtime, config, 100, MockClient::new());
+            if hang {
+                mock.reject_points([10]);
+                mock.hang_disconnect(true);
+            }
+            mocks.push(mock);
+        }
+
+        runtime.start().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        let report = runtime.stop().await.unwrap();
+
+        let log: Vec<_> = mocks[0]
+            .calls()
+            .into_iter()
+            .filter_map(|call| match call {
+                MockCall::Control(c) => Some(format!("control {:?}", (c[0].id, c[0].value))),
+                MockCall::Adjustment(a) => Some(format!("adjustment {:?}", (a[0].id, a[0].value))),
+                MockCall::Disconnect => Some("disconnect".into()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            log,
+            ["control (10, false)", "adjustment (11, 12.5)", "disconnect"]
+        );
+        assert_eq!(
+            report.safe_state_failed,
+            vec![(2, "1 of 2 commands failed".to_string())]
+        );
+        assert_eq!(report.timed_out, vec![2]);
+        assert!(!report.is_clean());
+        assert!(!runtime.is_running());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_watchdog_restarts_then_parks_stalled_channel() {
+        fn build_stuck(_config: &ChannelConfig) -> Result<Box<dyn ChannelRuntime>> {
+            let mock = MockClient::new();
+            mock.handle().hang_polls(true);
+            Ok(Box::new(mock))
+        }
+        let _ = super::super::factory::register_protocol("watchdog-test-stuck", build_stuck);
+
+        let config: GatewayConfig = serde_json::from_value(serde_json::json!({
+            "gateway": {
+                "name": "test",
+                "default_poll_interval_ms": 100,
+                "shutdown_timeout_ms": 100,
+                "watchdog": { "stall_timeout_ms": 1000, "max_restarts": 2 }
+            },
+            "channels": [{ "id": 1, "name": "stuck", "protocol": "watchdog-test-stuck" }]
+        }))
+        .unwrap();
+        let mut runtime =
+            GatewayRuntime::from_config(config, Arc::new(MemoryStore::new())).unwrap();
+        let mut events = runtime.subscribe(1).unwrap();
+        runtime.start().await.unwrap();
+
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        assert_eq!(
+            runtime.check_watchdog().await.unwrap(),
+            WatchdogReport::default()
+        );
+
+        for _ in 0..2 {
+            tokio::time::sleep(Duration::from_millis(1100)).await;
+            let report = runtime.check_watchdog().await.unwrap();
+            assert_eq!(report.restarted, vec![1]);
+        }
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        let report = runtime.check_watchdog().await.unwrap();
+        assert_eq!(report.parked, vec![1]);
+
+        let diagnostics = runtime.channel_diagnostics(1).await.unwrap();
+        let diagnostics = diagnostics.diagnostics.unwrap();
+        assert_eq!(diagnostics.connection_state, ConnectionState::Error);
+        assert_eq!(diagnostics.extra["watchdog_restarts"], 2);
+
+        // Parked channels are left alone
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        assert_eq!(
+            runtime.check_watchdog().await.unwrap(),
+            WatchdogReport::default()
+        );
+
+        let mut states = Vec::new();
+        while let Ok(Some(DataEvent::ConnectionChanged(state))) =
+            tokio::time::timeout(Duration::ZERO, events.recv()).await
+        {
+            states.push(state);
+        }
+        assert_eq!(states.last(), Some(&ConnectionState::Error));
+        assert_eq!(
+            states
+                .iter()
+                .filter(|s| **s == ConnectionState::Reconnecting)
+                .count(),
+            2
+        );
+
+        runtime.stop().await.unwrap();
+    }
+
+    async fn stored_value(store: &MemoryStore) -> (Option<f64>, Quality) {
+        let point = store.read(1, 10).await.unwrap().unwrap();
+        (point.value.as_f64(), point.quality)
+    }
+
+    /// `(standby_active, switchovers)` of backup channel 2.
+    async fn standby_active(runtime: &GatewayRuntime) -> (serde_json::Value, serde_json::Value) {
+        let diagnostics = runtime.channel_diagnostics(2).await.unwrap();
+        let extra = diagnostics.diagnostics.unwrap().extra;
+        (
+            extra["standby_active"].clone(),
+            extra["switchovers"].clone(),
+        )
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_channels_wait_for_their_dependencies() {
+        let mut runtime = empty_runtime();
+        let mut devices = virtual_channel(2, &[1]);
+        devices.depends_on = vec![1];
+        devices.startup_delay_ms = 10_000;
+        let mut impatient = virtual_channel(3, &[1]);
+        impatient.depends_on = vec![1];
+        impatient.dependency_timeout_ms = 3000;
+        // Listed before the channel they depend on
+        let devices = add_mock(&mut runtime, devices, 1000, MockClient::new());
+        let impatient = add_mock(&mut runtime, impatient, 1000, MockClient::new());
+        let power = add_mock(
+            &mut runtime,
+            virtual_channel(1, &[1]),
+            1000,
+            MockClient::new(),
+        );
+        power.set_reachable(false);
+
+        runtime.start().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(2000)).await;
+        assert_eq!(devices.connects() + impatient.connects(), 0);
+
+        // The timed-out channel connects without its dependency
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        assert!(impatient.is_connected());
+        assert_eq!(devices.connects(), 0);
+
+        // The other one only after its startup delay
+        power.set_reachable(true);
+        while !power.is_connected() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        tokio::time::sleep(Duration::from_millis(9900)).await;
+        assert_eq!(devices.connects(), 0);
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(devices.is_connected());
+        runtime.stop().await.unwrap();
+
+        // Cycles are refused
+        let mut runtime = empty_runtime();
+        for (id, depends_on) in [(1, 2), (2, 1)] {
+            let mut config = virtual_channel(id, &[1]);
+            config.depends_on = vec![depends_on];
+            add_mock(&mut runtime, config, 1000, MockClient::new());
+        }
+        let err = runtime.start().await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Configuration error: channel 1: depends_on forms a cycle: 1 -> 2 -> 1"
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_backup_takes_over_and_hands_back() {
+        /// Reports its channel id as the value of point 10.
+        fn build(config: &ChannelConfig) -> Result<Box<dyn ChannelRuntime>> {
+            let mock = MockClient::new().with_id(config.id);
+            let point = DataPoint::new(10, f64::from(config.id));
+            mock.handle()
+                .set_steady(DataBatch::from_points(vec![point]));
+            Ok(Box::new(mock))
+        }
+        let _ = super::super::factory::register_protocol("redundancy-test", build);
+
+        let config: GatewayConfig = serde_json::from_value(serde_json::json!({
+            "gateway": { "name": "test", "default_poll_interval_ms": 100 },
+            "channels": [
+                {
+                    "id": 1,
+                    "name": "primary",
+                    "protocol": "redundancy-test",
+                    "points": [{ "id": 10, "name": "p", "address": "p" }]
+                },
+                {
+                    "id": 2,
+                    "name": "backup",
+                    "protocol": "redundancy-test",
+                    "backup_of": 1,
+                    "failover_after_ms": 500
+                }
+            ]
+        }))
+        .unwrap();
+        let store = Arc::new(MemoryStore::new());
+        let mut runtime = GatewayRuntime::from_config(config, store.clone())
+            .unwrap()
+            .with_reconnect_backoff(Duration::from_millis(50), Duration::from_millis(50));
+        // The backup inherits the primary's points
+        assert_eq!(runtime.channels[1].config.points.len(), 1);
+        let primary = MockClient::new().with_id(1);
+        let primary_mock = primary.handle();
+        primary_mock.set_steady(DataBatch::from_points(vec![DataPoint::new(10, 1.0)]));
+        runtime.channels[0].runtime = Arc::new(Mutex::new(Box::new(primary)));
+
+        runtime.start().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        assert_eq!(stored_value(&store).await, (Some(1.0), Quality::Good));
+        assert!(store.read(2, 10).await.unwrap().is_none());
+        assert_eq!(
+            standby_active(&runtime).await,
+            (serde_json::json!(false), serde_json::json!(0))
+        );
+
+        // Primary fails: its points go stale, then the backup takes over
+        primary_mock.set_reachable(false);
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(
+            stored_value(&store).await,
+            (Some(1.0), Quality::NotConnected)
+        );
+        tokio::time::sleep(Duration::from_millis(600)).await;
+        assert_eq!(stored_value(&store).await, (Some(2.0), Quality::Good));
+        assert_eq!(
+            standby_active(&runtime).await,
+            (serde_json::json!(true), serde_json::json!(1))
+        );
+
+        // Primary recovers and takes its points back
+        primary_mock.set_reachable(true);
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(stored_value(&store).await, (Some(1.0), Quality::Good));
+        assert_eq!(
+            standby_active(&runtime).await,
+            (serde_json::json!(false), serde_json::json!(2))
+        );
+        assert!(store.read(2, 10).await.unwrap().is_none());
+
+        runtime.stop().await.unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_heartbeat_stops_when_polling_stalls() {
+        let mut runtime = empty_runtime()
+            .with_reconnect_backoff(Duration::from_millis(100), Duration::from_millis(100));
+        let relay = add_counting(&mut runtime, 1, 100, Duration::ZERO);
+        let meter = add_counting(&mut runtime, 2, 100, Duration::ZERO);
+        runtime.config.gateway.heartbeat = Some(HeartbeatConfig {
+            period_ms: 500,
+            monitored_channels: vec![2],
+            stall_timeout_ms: 1000,
+            ..HeartbeatConfig::new(1, 5)
+        });
+        let toggles = || -> Vec<bool> { relay.controls().iter().map(|c| c.value).collect() };
+
+        runtime.start().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(1750)).await;
+        assert_eq!(toggles(), [true, false, true, false]);
+        let status = runtime.heartbeat_status().unwrap();
+        assert!(status.driving);
+        assert_eq!(status.beats, 4);
+
+        // The meter stops answering: no beat after its stall timeout
+        meter.set_reachable(false);
+        meter.drop_connection();
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        let beats = toggles().len();
+        assert!(beats <= 7, "{} beats", beats);
+        tokio::time::sleep(Duration::from_millis(2000)).await;
+        assert_eq!(toggles().len(), beats);
+        let diagnostics = runtime.channel_diagnostics(1).await.unwrap();
+        let heartbeat = &diagnostics.diagnostics.unwrap().extra["heartbeat"];
+        assert_eq!(heartbeat["driving"], false);
+        assert_eq!(heartbeat["stalled_channels"], serde_json::json!([2]));
+
+        // Back once the meter polls again, still alternating
+        meter.set_reachable(true);
+        tokio::time::sleep(Duration::from_millis(3000)).await;
+        let toggles = toggles();
+        assert!(toggles.len() > beats);
+        assert!(toggles.windows(2).all(|pair| pair[0] != pair[1]));
+        assert!(runtime.heartbeat_status().unwrap().driving);
+
+        // Stopping the runtime stops the heartbeat
+        runtime.stop().await.unwrap();
+        assert_eq!(runtime.heartbeat_status(), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_polls_within_concurrency_limit() {
+        let mut config = virtual_config();
+        config.channels.clear();
+        config.gateway.max_concurrent_polls = Some(2);
+        let mut runtime =
+            GatewayRuntime::from_config(config, Arc::new(MemoryStore::new())).unwrap();
+        let mocks: Vec<_> = (1..=4)
+            .map(|id| add_counting(&mut runtime, id, 1000, Duration::from_millis(100)))
+            .collect();
+        let polls = || mocks.iter().map(MockHandle::polls).sum::<usize>();
+
+        runtime.start().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(polls(), 2);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(polls(), 4);
+
+        let throttled: u64 = runtime
+            .diagnostics_snapshot()
+            .await
+            .iter()
+            .filter_map(|d| d.diagnostics.as_ref()?.extra["polls_throttled"].as_u64())
+            .sum();
+        assert!(throttled >= 2, "{} throttled", throttled);
+        runtime.stop().await.unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_panicking_channel_is_isolated() {
+        let mut runtime = empty_runtime();
+        let healthy = add_counting(&mut runtime, 1, 100, Duration::ZERO);
+        let buggy = add_counting(&mut runtime, 2, 100, Duration::ZERO);
+        buggy.panic_polls(true);
+
+        runtime.start().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(950)).await;
+        assert_eq!(healthy.polls(), 10);
+        assert_eq!(buggy.polls(), 1);
+
+        let diagnostics = runtime.channel_diagnostics(2).await.unwrap();
+        let diagnostics = diagnostics.diagnostics.unwrap();
+        assert_eq!(diagnostics.connection_state, ConnectionState::Error);
+        assert_eq!(
+            diagnostics.last_error.as_deref(),
+            Some("channel task panicked: mock poll panicked")
+        );
+        assert_eq!(diagnostics.extra["task_panics"], 1);
+
+        // Starting the channel again clears the error state
+        buggy.panic_polls(false);
+        runtime.disable_channel(2).await.unwrap();
+        runtime.enable_channel(2).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        assert!(buggy.polls() > 1);
+        let diagnostics = runtime.channel_diagnostics(2).await.unwrap();
+        assert_eq!(
+            diagnostics.diagnostics.unwrap().connection_state,
+            ConnectionState::Connected
+        );
+
+        runtime.stop().await.unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_slow_polls_are_skipped_not_queued() {
+        let mut runtime = empty_runtime();
+        let polls = add_counting(&mut runtime, 1, 100, Duration::from_millis(250));
+
+        runtime.start().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(1000)).await;
+
+        // Polls start at 0, 350 and 700, each skipping two cycles
+        assert_eq!(polls.polls(), 3);
+        let diagnostics = runtime.diagnostics_snapshot().await;
+        let overruns = diagnostics[0].diagnostics.as_ref().unwrap().poll_overruns;
+        assert_eq!(overruns, 6);
+
+        runtime.stop().await.unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_change_detection_stores_changes_only() {
+        async fn suppressed(runtime: &GatewayRuntime) -> serde_json::Value {
+            let diagnostics = runtime.diagnostics_snapshot().await;
+            diagnostics[0].diagnostics.as_ref().unwrap().extra["points_suppressed"].clone()
+        }
+
+        let mut runtime = empty_runtime();
+        let mut config = virtual_channel(1, &[10, 11]);
+        config.change_detection = Some(ChangeDetectionConfig {
+            full_refresh_ms: 1000,
+        });
+        let mock = add_mock(&mut runtime, config, 100, MockClient::new());
+        mock.set_steady(DataBatch::from_points(vec![
+            DataPoint::new(10, 1.0),
+            DataPoint::new(11, 2.0),
+        ]));
+
+        runtime.start().await.unwrap();
+        // Polls at 0 (stored in full), 100, ..., 900
+        tokio::time::sleep(Duration::from_millis(950)).await;
+        assert_eq!(suppressed(&runtime).await, 18);
+
+        // The full refresh at 1000 fails for point 10
+        mock.push_poll(PollResult::failed(vec![PointFailure::new(10, "timeout")]));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let point = runtime.store().read(1, 10).await.unwrap().unwrap();
+        assert_eq!(point.quality, Quality::CommFailure);
+
+        // The next sample of the failed point is stored although unchanged
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let point = runtime.store().read(1, 10).await.unwrap().unwrap();
+        assert_eq!(point.quality, Quality::Good);
+        assert_eq!(suppressed(&runtime).await, 19);
+
+        runtime.stop().await.unwrap();
+    }
+
+    fn next_state(rx: &mut DataEventReceiver) -> Option<ConnectionState> {
+        match rx.try_recv() {
+            Some(DataEvent::ConnectionChanged(state)) => Some(state),
+            _ => None,
+        }
+    }
+
+    /// Wait until the channel task has published its next state.
+    async fn wait_state(rx: &mut DataEventReceiver) -> ConnectionState {
+        match tokio::time::timeout(Duration::from_secs(1), rx.recv()).await {
+            Ok(Some(DataEvent::ConnectionChanged(state))) => state,
+            other => panic!("expected ConnectionChanged, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_disable_enable_restart() {
+        let store = Arc::new(MemoryStore::new());
+        let mut runtime = GatewayRuntime::from_config(virtual_config(), store.clone()).unwrap();
+        let mut events = runtime.subscribe(1).unwrap();
+        runtime.start().await.unwrap();
+        assert_eq!(wait_state(&mut events).await, ConnectionState::Connected);
+
+        store
+            .write_batch(1, &DataBatch::from_points(vec![DataPoint::new(10, 1.0)]))
+            .await
+            .unwrap();
+
+        runtime.disable_channel(1).await.unwrap();
+        assert_eq!(next_state(&mut events), Some(ConnectionState::Disconnected));
+        let point = store.read(1, 10).await.unwrap().unwrap();
+        assert_eq!(point.quality, Quality::OutOfService);
+        assert!(runtime.channels[0].task.is_none());
+        assert!(!runtime.diagnostics_snapshot().await[0].enabled);
+
+        // Stays disabled across restarts of the gateway and the channel
+        runtime.stop().await.unwrap();
+        runtime.start().await.unwrap();
+        runtime.restart_channel(1).await.unwrap();
+        assert!(runtime.channels[0].task.is_none());
+        assert_eq!(next_state(&mut events), None);
+
+        runtime.enable_channel(1).await.unwrap();
+        assert_eq!(next_state(&mut events), Some(ConnectionState::Connecting));
+        assert_eq!(wait_state(&mut events).await, ConnectionState::Connected);
+
+        let before = Arc::clone(&runtime.channels[0].runtime);
+        runtime.restart_channel(1).await.unwrap();
+        assert!(!Arc::ptr_eq(&before, &runtime.channels[0].runtime));
+        assert_eq!(next_state(&mut events), Some(ConnectionState::Reconnecting));
+        assert_eq!(wait_state(&mut events).await, ConnectionState::Connected);
+
+        // Disabling and restarting both cost the connection
+        let history = runtime.connection_history(1).unwrap();
+        assert_eq!(history.last().unwrap().state, ConnectionState::Connected);
+        assert!(history
+            .windows(2)
+            .all(|pair| pair[0].timestamp <= pair[1].timestamp));
+        let diagnostics = runtime.channel_diagnostics(1).await.unwrap();
+        let extra = diagnostics.diagnostics.unwrap().extra;
+        assert_eq!(extra["disconnect_count_24h"], 2);
+        assert!(extra["connected_since"].is_string());
+        let availability = extra["availability_percent"].as_f64().unwrap();
+        assert!((0.0..=100.0).contains(&availability));
+        assert!(runtime.connection_history(9).is_err());
+
+        assert!(runtime.disable_channel(9).await.is_err());
+        runtime.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_interlock_blocks_commands() {
+        let mut runtime = empty_runtime();
+        let mut config = virtual_channel(1, &[1, 2, 3]);
+        config.interlocks = serde_json::from_value(serde_json::json!([
+            { "point_id": 2, "check_point": 1, "equals": 0, "max_age_ms": 60000 },
+            { "point_id": 3, "check_point": 1, "equals": 0, "max_age_ms": 1 }
+        ]))
+        .unwrap();
+        let mock = add_mock(&mut runtime, config, 10, MockClient::new());
+        runtime.channels[0]
+            .runtime
+            .lock()
+            .await
+            .connect()
+            .await
+            .unwrap();
+        let store = Arc::clone(runtime.store());
+
+        // No value yet
+        let err = runtime.write_control(1, &[(2, 1.0)]).await.unwrap_err();
+        assert!(matches!(err, GatewayError::Interlock(_)));
+        assert_eq!(
+            err.to_string(),
+            "Interlock violated: point 2: channel 1 point 1 has no value"
+        );
+
+        let breaker_open = DataBatch::from_points(vec![DataPoint::new(1, false)]);
+        store.write_batch(1, &breaker_open).await.unwrap();
+        assert_eq!(
+            runtime
+                .write_control(1, &[(2, 1.0)])
+                .await
+                .unwrap()
+                .success_count,
+            1
+        );
+
+        // One stale interlock blocks the whole call
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        let err = runtime
+            .write_adjustment(1, &[(2, 1.0), (3, 1.0)])
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("point 3: channel 1 point 1 is "));
+
+        let breaker_closed = DataBatch::from_points(vec![DataPoint::new(1, true)]);
+        store.write_batch(1, &breaker_closed).await.unwrap();
+        let err = runtime.write_control(1, &[(2, 0.0)]).await.unwrap_err();
+        assert!(err.to_string().ends_with("is 1 (expected 0)"));
+
+        // Only the accepted command reached the device
+        assert_eq!(mock.controls().len(), 1);
+        assert!(mock.adjustments().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_adjustment_limits() {
+        let mut config = virtual_config();
+        config.channels.clear();
+        config.gateway.audit = Some(AuditConfig::default());
+        let store = Arc::new(MemoryStore::new());
+        let mut runtime = GatewayRuntime::from_config(config, store.clone()).unwrap();
+        let mut channel = virtual_channel(1, &[1, 2, 3]);
+        channel.points[0].transform.min_value = Some(0.0);
+        channel.points[0].transform.max_value = Some(100.0);
+        channel.points[1].transform.max_value = Some(10.0);
+        let mock = add_mock(&mut runtime, channel, 1000, MockClient::new());
+        runtime.start().await.unwrap();
+        while !mock.is_connected() {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+
+        // The setpoint within range and the unlimited one are sent
+        let written = runtime
+            .write_adjustment(1, &[(1, 5000.0), (2, 10.0), (3, 5000.0)])
+            .await
+            .unwrap();
+        assert_eq!(written.success_count, 2);
+        assert_eq!(
+            written.failures,
+            [(1, "5000 is outside the allowed range 0 to 100".to_string())]
+        );
+        let written = runtime.write_adjustment(1, &[(1, f64::NAN)]).await.unwrap();
+        assert_eq!(written.success_count, 0);
+        assert_eq!(
+            written.failures,
+            [(1, "NaN is outside the allowed range 0 to 100".to_string())]
+        );
+        // Control commands are not limited
+        assert_eq!(
+            runtime
+                .write_control(1, &[(1, 5000.0)])
+                .await
+                .unwrap()
+                .success_count,
+            1
+        );
+        let sent: Vec<(PointId, f64)> =
+            mock.adjustments().iter().map(|c| (c.id, c.value)).collect();
+        assert_eq!(sent, [(2, 10.0), (3, 5000.0)]);
+
+        // With clamping, the violated bound is sent instead
+        runtime.channels[0].config.clamp_adjustments = true;
+        let written = runtime
+            .write_adjustment(1, &[(1, -3.0), (2, 11.0)])
+            .await
+            .unwrap();
+        assert_eq!((written.success_count, written.failures.len()), (2, 0));
+        let sent: Vec<f64> = mock.adjustments().iter().map(|c| c.value).collect();
+        assert_eq!(sent[2..], [0.0, 10.0]);
+        // NaN has no bound to clamp to
+        let written = runtime.write_adjustment(1, &[(1, f64::NAN)]).await.unwrap();
+        assert_eq!(written.success_count, 0);
+        assert_eq!(
+            written.failures,
+            [(1, "NaN is outside the allowed range 0 to 100".to_string())]
+        );
+
+        runtime.stop().await.unwrap();
+        let history = store.query_audit(&AuditQuery::new()).await.unwrap();
+        assert_eq!(history[0].result, AuditResult::Partial);
+        assert_eq!(
+            history[0].commands[0].error.as_deref(),
+            Some("5000 is outside the allowed range 0 to 100")
+        );
+        assert_eq!(history[0].commands[1].stage, Some(CommandStage::Confirmed));
+        assert_eq!(history[1].result, AuditResult::Failed);
+        assert_eq!(
+            history[1].commands[0].error.as_deref(),
+            Some("NaN is outside the allowed range 0 to 100")
+        );
+        assert_eq!(history[3].result, AuditResult::Accepted);
+    }
+
+    #[tokio::test]
+    async fn test_command_authorization() {
+        let path = std::env::temp_dir().join(format!("igw-audit-{}.jsonl", std::process::id()));
+        let mut config = virtual_config();
+        config.channels.clear();
+        config.gateway.jsonl_output = true;
+        config.gateway.jsonl.path = Some(path.clone());
+        config.gateway.audit = Some(AuditConfig::default());
+        config.gateway.authorization = Some(
+            serde_json::from_value(serde_json::json!({
+                "callers": [{ "identity": "scada", "level": "operate" }]
+            }))
+            .unwrap(),
+        );
+        let store = Arc::new(MemoryStore::new());
+        let mut runtime = GatewayRuntime::from_config(config, store.clone()).unwrap();
+        let mut channel = virtual_channel(1, &[1, 2]);
+        channel.points[1].permission = Some(PermissionLevel::Tune);
+        let mock = add_mock(&mut runtime, channel, 1000, MockClient::new());
+        runtime.start().await.unwrap();
+        while !mock.is_connected() {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+
+        let scada = runtime.caller("scada");
+        assert_eq!(
+            runtime
+                .write_control_as(&scada, 1, &[(1, 1.0)])
+                .await
+                .unwrap()
+                .success_count,
+            1
+        );
+        let err = runtime
+            .write_control_as(&scada, 1, &[(1, 0.0), (2, 1.0)])
+            .await
+            .unwrap_err();
+        assert!(matches!(err, GatewayError::PermissionDenied(_)));
+        let guest = runtime.caller("guest");
+        assert!(runtime
+            .write_adjustment_as(&guest, 1, &[(1, 5.0)])
+            .await
+            .is_err());
+        // The library API is not restricted
+        assert_eq!(
+            runtime
+                .write_control(1, &[(2, 1.0)])
+                .await
+                .unwrap()
+                .success_count,
+            1
+        );
+        assert_eq!(runtime.audit_stats().unwrap().dropped, 0);
+        runtime.stop().await.unwrap();
+
+        // The same records went to the store's audit history
+        let history = store.query_audit(&AuditQuery::new()).await.unwrap();
+        assert_eq!(history.len(), 4);
+        assert_eq!(history[1].result, AuditResult::Denied);
+        assert_eq!(history[3].commands[0].stage, Some(CommandStage::Confirmed));
+
+        // Refused calls never reach the device
+        let controls: Vec<u32> = mock.controls().iter().map(|c| c.id).collect();
+        assert_eq!(controls, vec![1, 2]);
+        assert!(mock.adjustments().is_empty());
+
+        let audits: Vec<serde_json::Value> = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str::<serde_json::Value>(l).unwrap())
+            .filter(|l| l["type"] == "command_audit")
+            .collect();
+        let _ = std::fs::remove_file(&path);
+        let summary: Vec<(&str, &str, &str)> = audits
+            .iter()
+            .map(|a| {
+                (
+                    a["caller"].as_str().unwrap(),
+                    a["kind"].as_str().unwrap(),
+                    a["result"].as_str().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("scada", "control", "accepted"),
+                ("scada", "control", "denied"),
+                ("guest", "adjustment", "denied"),
+                ("local", "control", "accepted"),
+            ]
+        );
+        assert_eq!(
+            audits[1]["error"],
+            "Permission denied: scada (operate): point 2 requires tune"
+        );
+        assert_eq!(audits[1]["commands"][1]["point_id"], 2);
+    }
+
+    #[tokio::test]
+    async fn test_command_queue() {
+        let mut runtime = empty_runtime();
+        let mut config = virtual_channel(1, &[1, 2, 3]);
+        config.command_queue = Some(CommandQueueConfig {
+            max_rate: 20.0,
+            max_depth: 2,
+            policy: QueuePolicy::Coalesce,
+        });
+        let mock = add_mock(&mut runtime, config, 10, MockClient::new());
+        runtime.channels[0]
+            .runtime
+            .lock()
+            .await
+            .connect()
+            .await
+            .unwrap();
+        let mut events = runtime.subscribe(1).unwrap();
+
+        // Nothing is sent before the test yields
+        let started = Instant::now();
+        assert_eq!(
+            runtime
+                .write_adjustment(1, &[(1, 1.0)])
+                .await
+                .unwrap()
+                .success_count,
+            1
+        );
+        assert_eq!(
+            runtime
+                .write_adjustment(1, &[(2, 1.0), (2, 0.5)])
+                .await
+                .unwrap()
+                .success_count,
+            2
+        );
+        assert_eq!(
+            runtime
+                .write_adjustment(1, &[(1, 5.0)])
+                .await
+                .unwrap()
+                .success_count,
+            1
+        );
+        let err = runtime.write_adjustment(1, &[(3, 1.0)]).await.unwrap_err();
+        assert!(matches!(err, GatewayError::QueueFull(_)));
+
+        // Last writer wins, at most 20 commands per second
+        for point_id in [1, 2] {
+            let outcome = next_outcome(&mut events).await;
+            assert_eq!(
+                outcome,
+                CommandOutcome::new(point_id, CommandStage::Confirmed)
+            );
+        }
+        assert!(started.elapsed() >= Duration::from_millis(50));
+        let sent: Vec<_> = mock.adjustments().iter().map(|a| (a.id, a.value)).collect();
+        assert_eq!(sent, vec![(1, 5.0), (2, 0.5)]);
+
+        let extra = runtime.diagnostics_snapshot().await[0]
+            .diagnostics
+            .clone()
+            .unwrap()
+            .extra;
+        assert_eq!(extra["command_queue_depth"], 0);
+        assert_eq!(extra["commands_rejected"], 1);
+        assert_eq!(extra["commands_coalesced"], 2);
+
+        // Queued commands fail when the channel stops
+        runtime.write_adjustment(1, &[(3, 1.0)]).await.unwrap();
+        runtime.write_adjustment(1, &[(1, 2.0)]).await.unwrap();
+        runtime.disable_channel(1).await.unwrap();
+        let outcome = next_outcome(&mut events).await;
+        assert_eq!(outcome.stage, CommandStage::Failed);
+        assert_eq!(outcome.error.as_deref(), Some("discarded: channel stopped"));
+    }
+
+    /// Wait for the next command outcome, skipping state changes.
+    async fn next_outcome(rx: &mut DataEventReceiver) -> CommandOutcome {
+        loop {
+            match tokio::time::timeout(Duration::from_secs(2), rx.recv()).await {
+                Ok(Some(DataEvent::CommandUpdate(outcome))) => return outcome,
+                Ok(Some(DataEvent::ConnectionChanged(_))) => continue,
+                other => panic!("expected CommandUpdate, got {:?}", other),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_command_feedback() {
+        let config: GatewayConfig = serde_json::from_value(serde_json::json!({
+            "gateway": { "name": "feedback" },
+            "channels": [{
+                "id": 1,
+                "name": "hub",
+                "protocol": "virtual",
+                "parameters": { "computed": [{ "id": 3, "expression": "p2 * 2" }] },
+                "points": [
+                    {
+                        "id": 1, "name": "echo", "address": "1",
+                        "feedback": { "point_id": 1 }
+                    },
+                    {
+                        "id": 2, "name": "mode_cmd", "address": "2",
+                        "feedback": { "point_id": 3, "map": [{ "command": 1, "feedback": 2 }] }
+                    },
+                    { "id": 3, "name": "mode", "address": "3" },
+                    {
+                        "id": 4, "name": "silent", "address": "4",
+                        "feedback": { "point_id": 5, "timeout_ms": 100 }
+                    },
+                    { "id": 5, "name": "silent_fb", "address": "5" },
+                    { "id": 6, "name": "plain", "address": "6" }
+                ]
+            }]
+        }))
+        .unwrap();
+        let store = Arc::new(MemoryStore::new());
+        let mut runtime = GatewayRuntime::from_config(config, store.clone()).unwrap();
+        runtime.start().await.unwrap();
+
+        // Give the channel task time to subscribe
+        for _ in 0..100 {
+            runtime.write_control(1, &[(6, 1.0)]).await.unwrap();
+            if store.read(1, 6).await.unwrap().is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let mut events = runtime.subscribe(1).unwrap();
+
+        runtime.write_control(1, &[(1, 1.0)]).await.unwrap();
+        let outcome = next_outcome(&mut events).await;
+        assert_eq!(outcome, CommandOutcome::new(1, CommandStage::Confirmed));
+
+        runtime.write_adjustment(1, &[(2, 1.0)]).await.unwrap();
+        let outcome = next_outcome(&mut events).await;
+        assert_eq!(outcome, CommandOutcome::new(2, CommandStage::Confirmed));
+
+        runtime.write_control(1, &[(4, 1.0)]).await.unwrap();
+        let outcome = next_outcome(&mut events).await;
+        assert_eq!(outcome.point_id, 4);
+        assert_eq!(outcome.stage, CommandStage::FeedbackTimeout);
+        assert_eq!(
+            outcome.error.as_deref(),
+            Some("feedback channel 1 point 5 has no value after 100 ms")
+        );
+
+        runtime.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_event_data_reaches_store() {
+        let store = Arc::new(MemoryStore::new());
+        let mut runtime = GatewayRuntime::from_config(virtual_config(), store.clone()).unwrap();
+        assert_eq!(runtime.channel_ids(), vec![1]);
+
+        runtime.start().await.unwrap();
+        assert_eq!(store.point_configs(1).await.unwrap().len(), 2);
+
+        // Give the channel task time to subscribe
+        let mut written = 0;
+        for _ in 0..100 {
+            written = runtime
+                .write_adjustment(1, &[(10, 4.5)])
+                .await
+                .unwrap()
+                .success_count;
+            if store.read(1, 10).await.unwrap().is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(written, 1);
+        let point = store.read(1, 10).await.unwrap().unwrap();
+        assert_eq!(point.value, Value::Float(4.5));
+
+        let diagnostics = runtime.diagnostics_snapshot().await;
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].diagnostics.is_some());
+
+        runtime.stop().await.unwrap();
+        assert!(!runtime.is_running());
+    }
+
+    #[tokio::test]
+    async fn test_restores_last_known_values() {
+        let store = Arc::new(MemoryStore::new());
+        store
+            .write_batch(1, &DataBatch::from_points(vec![DataPoint::new(11, 7.0)]))
+            .await
+            .unwrap();
+
+        let mut runtime = GatewayRuntime::from_config(virtual_config(), store.clone()).unwrap();
+        runtime.start().await.unwrap();
+
+        let result = runtime.channels[0].runtime.lock().await.poll_once().await;
+        let point = result.data.iter().find(|p| p.id == 11).unwrap();
+        assert_eq!(point.quality, Quality::LastKnown);
+
+        runtime.stop().await.unwrap();
+    }
+
+    #[test]
+    fn test_unknown_protocol_fails() {
+        let mut config = virtual_config();
+        config.channels[0].protocol = "nope".into();
+        let err = GatewayRuntime::from_config(config, Arc::new(MemoryStore::new()))
+            .err()
+            .unwrap();
+        assert!(err
+            .to_string()
+            .contains("channel 1: unknown protocol 'nope'"));
+    }
+
+    #[tokio::test]
+    async fn test_reload_diff() {
+        let mut config = virtual_config();
+        config.channels = vec![
+            virtual_channel(1, &[1]),
+            virtual_channel(2, &[1]),
+            virtual_channel(3, &[1]),
+            virtual_channel(4, &[1]),
+        ];
+        let store = Arc::new(MemoryStore::new());
+        let mut runtime = GatewayRuntime::from_config(config.clone(), store.clone()).unwrap();
+        runtime.start().await.unwrap();
+        let untouched = Arc::clone(&runtime.channels[0].runtime);
+        let updated = Arc::clone(&runtime.channels[1].runtime);
+
+        // 1 kept, 2 gains a point, 3 gets new parameters, 4 removed, 5 added
+        let mut new_config = config.clone();
+        new_config.channels[1] = virtual_channel(2, &[1, 2]);
+        new_config.channels[2].parameters = serde_json::json!({ "buffer_size": 64 });
+        new_config.channels[3].enabled = false;
+        new_config.channels.push(virtual_channel(5, &[1]));
+
+        let report = runtime.reload(new_config.clone()).await.unwrap();
+        assert_eq!(
+            report,
+            ReloadReport {
+                kept: vec![1],
+                updated: vec![2],
+                restarted: vec![3],
+                added: vec![5],
+                removed: vec![4],
+            }
+        );
+        assert_eq!(runtime.channel_ids(), vec![1, 2, 3, 5]);
+        assert!(Arc::ptr_eq(&untouched, &runtime.channels[0].runtime));
+        assert!(Arc::ptr_eq(&updated, &runtime.channels[1].runtime));
+        assert_eq!(store.point_configs(2).await.unwrap().len(), 2);
+        assert_eq!(store.point_configs(5).await.unwrap().len(), 1);
+        assert!(runtime.channels.iter().all(|c| c.task.is_some()));
+
+        // Same config again: nothing to do
+        let report = runtime.reload(new_config).await.unwrap();
+        assert!(!report.has_changes());
+
+        runtime.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_invalid_reload_keeps_running_channels() {
+        let store = Arc::new(MemoryStore::new());
+        let mut runtime = GatewayRuntime::from_config(virtual_config(), store).unwrap();
+        runtime.start().await.unwrap();
+
+        let mut bad = virtual_config();
+        bad.channels[1].enabled = true;
+        bad.channels[1].protocol = "nope".into();
+        let err = runtime.reload(bad).await.err().unwrap();
+        assert!(err.to_string().contains("channel 2: unknown protocol"));
+        assert_eq!(runtime.channel_ids(), vec![1]);
+        assert!(runtime.channels[0].task.is_some());
+        assert!(!runtime.config().channels[1].enabled);
+
+        runtime.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_jsonl_output() {
+        let path =
+            std::env::temp_dir().join(format!("igw-orchestrator-{}.jsonl", std::process::id()));
+        let mut config = virtual_config();
+        config.gateway.jsonl_output = true;
+        config.gateway.jsonl.path = Some(path.clone());
+        config.gateway.diagnostics_interval_ms = 10;
+
+        let store = Arc::new(MemoryStore::new());
+        let mut runtime = GatewayRuntime::from_config(config, store.clone()).unwrap();
+        let mut events = runtime.subscribe(1).unwrap();
+        runtime.start().await.unwrap();
+        assert_eq!(wait_state(&mut events).await, ConnectionState::Connected);
+
+        store
+            .write_batch(1, &DataBatch::from_points(vec![DataPoint::new(10, 1.0)]))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        runtime.disable_channel(1).await.unwrap();
+        runtime.stop().await.unwrap();
+
+        let lines: Vec<serde_json::Value> = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        let _ = std::fs::remove_file(&path);
+
+        let of_type = |t: &'static str| lines.iter().filter(move |l| l["type"] == t);
+        let states: Vec<_> = of_type("connection_state").map(|l| &l["state"]).collect();
+        assert_eq!(states, vec!["connected", "disconnected"]);
+        assert!(of_type("diagnostics").count() >= 2);
+        // Taking the channel out of service re-writes its stored point
+        let data = of_type("data").next_back().unwrap();
+        assert_eq!(data["channel_id"], 1);
+        assert_eq!(data["points"][0]["id"], 10);
+    }
+
+    #[tokio::test]
+    async fn test_record_and_replay() {
+        let path = std::env::temp_dir().join(format!("igw-recording-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut runtime = empty_runtime();
+        let mock = add_mock(&mut runtime, virtual_channel(1, &[]), 10, MockClient::new());
+        for value in [1.0, 2.0, 3.0] {
+            mock.push_batch(DataBatch::from_points(vec![DataPoint::new(10, value)]));
+        }
+        mock.push_poll(PollResult::failed(vec![PointFailure::new(10, "timeout")]));
+        runtime.start_recording(&path).await.unwrap();
+        assert_eq!(runtime.recording_path(), Some(path.as_path()));
+        runtime.start().await.unwrap();
+        while mock.polls() < 5 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        runtime.stop().await.unwrap();
+        assert!(runtime.recording_path().is_none());
+
+        // Replay as fast as possible into a fresh store
+        let mut config = virtual_config();
+        config.channels = vec![serde_json::from_value(serde_json::json!({
+            "id": 1,
+            "name": "incident",
+            "protocol": "replay",
+            "poll_interval_ms": 1,
+            "parameters": { "file": path, "speed": 0 },
+            "points": [{ "id": 10, "name": "a", "address": "a" }]
+        }))
+        .unwrap()];
+        let store = Arc::new(MemoryStore::new());
+        let mut replay = GatewayRuntime::from_config(config, store.clone()).unwrap();
+        let mut changes = store.watch(1, &[]).unwrap();
+        replay.start().await.unwrap();
+        let mut values = Vec::new();
+        while values.len() < 4 {
+            let Some(DataEvent::DataUpdate(batch)) = changes.recv().await else {
+                continue;
+            };
+            values.extend(batch.iter().map(|p| (p.value.as_f64(), p.quality)));
+        }
+        replay.stop().await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            values,
+            vec![
+                (Some(1.0), Quality::Good),
+                (Some(2.0), Quality::Good),
+                (Some(3.0), Quality::Good),
+                (Some(3.0), Quality::CommFailure),
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_poll_now() {
+        let mut runtime = empty_runtime();
+        let polls = add_counting(&mut runtime, 1, 60_000, Duration::ZERO);
+        assert!(matches!(
+            runtime.poll_now(1).await,
+            Err(GatewayError::NotConnected)
+        ));
+
+        runtime.start().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        runtime.poll_now(1).await.unwrap();
+        // The scheduled first poll plus the on-demand one
+        assert_eq!(polls.polls(), 2);
+
+        runtime.stop().await.unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_refresh() {
+        let mut runtime = empty_runtime();
+        let polled = add_counting(&mut runtime, 1, 60_000, Duration::ZERO);
+        let events = add_mock(
+            &mut runtime,
+            virtual_channel(2, &[]),
+            60_000,
+            MockClient::new().event_driven(),
+        );
+        assert!(matches!(
+            runtime.refresh(1).await,
+            Err(GatewayError::NotConnected)
+        ));
+
+        runtime.start().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        // A polling channel is refreshed and polled at once
+        assert!(runtime.refresh(1).await.unwrap().is_some());
+        assert_eq!(polled.polls(), 2);
+        assert!(polled
+            .calls()
+            .iter()
+            .any(|call| matches!(call, MockCall::Refresh)));
+        // An event-driven one only asked for its values
+        let polls = events.polls();
+        assert!(runtime.refresh(2).await.unwrap().is_none());
+        runtime.refresh(2).await.unwrap();
+        assert_eq!(events.polls(), polls);
+
+        let diagnostics = runtime
+            .channel_diagnostics(2)
+            .await
+            .unwrap()
+            .diagnostics
+            .unwrap();
+        assert_eq!(diagnostics.extra["refreshes"], 2);
+
+        runtime.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_flatlined_point_goes_uncertain() {
+        async fn wait_quality(store: &MemoryStore, quality: Quality) {
+            for _ in 0..200 {
+                if store.read(1, 10).await.unwrap().map(|p| p.quality) == Some(quality) {
+                    return;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            panic!("point never became {:?}", quality);
+        }
+
+        let store = Arc::new(MemoryStore::new());
+        let mut config = virtual_config();
+        config.channels.clear();
+        let mut runtime = GatewayRuntime::from_config(config, store.clone()).unwrap();
+        let mock = add_mock(
+            &mut runtime,
+            virtual_channel(1, &[10]),
+            20,
+            MockClient::new(),
+        );
+        runtime.channels[0].points = vec![PointConfig::new(
+            10,
+            crate::core::point::ProtocolAddress::Generic("10".into()),
+        )
+        .with_max_age(Duration::from_millis(150))];
+        mock.set_steady(DataBatch::from_points(vec![DataPoint::new(10, 5.0)]));
+        runtime.start().await.unwrap();
+
+        // Polls of the same value keep the point flagged
+        wait_quality(&store, Quality::Uncertain).await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(stored_value(&store).await, (Some(5.0), Quality::Uncertain));
+
+        mock.set_steady(DataBatch::from_points(vec![DataPoint::new(10, 6.0)]));
+        wait_quality(&store, Quality::Good).await;
+        let age = store.point_age(1, 10).unwrap().unwrap();
+        assert!(age.age() < Duration::from_millis(150));
+
+        // No samples at all: the monitor flags the point
+        mock.set_steady(DataBatch::new());
+        wait_quality(&store, Quality::Uncertain).await;
+        assert_eq!(stored_value(&store).await.0, Some(6.0));
+
+        runtime.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_stale_point_survives_full_refresh() {
+        let store = Arc::new(MemoryStore::new());
+        let mut config = virtual_config();
+        config.channels.clear();
+        let mut runtime = GatewayRuntime::from_config(config, store.clone()).unwrap();
+        let mut channel = virtual_channel(1, &[10]);
+        channel.change_detection = Some(ChangeDetectionConfig {
+            full_refresh_ms: 100,
+        });
+        let mock = add_mock(&mut runtime, channel, 20, MockClient::new());
+        runtime.channels[0].points = vec![PointConfig::new(
+            10,
+            crate::core::point::ProtocolAddress::Generic("10".into()),
+        )
+        .with_max_age(Duration::from_millis(150))];
+        mock.set_steady(DataBatch::from_points(vec![DataPoint::new(10, 5.0)]));
+        runtime.start().await.unwrap();
+
+        for _ in 0..200 {
+            let stored = store.read(1, 10).await.unwrap();
+            if stored.is_some_and(|p| p.quality == Quality::Uncertain) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        // Several full refreshes store the unchanged value, still flagged
+        for _ in 0..40 {
+            assert_eq!(stored_value(&store).await, (Some(5.0), Quality::Uncertain));
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        mock.set_steady(DataBatch::from_points(vec![DataPoint::new(10, 6.0)]));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(stored_value(&store).await, (Some(6.0), Quality::Good));
+
+        runtime.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_stale_exactly_at_max_age() {
+        let store = Arc::new(MemoryStore::new());
+        let clock = Arc::new(SimulatedClock::new());
+        let mut config = virtual_config();
+        config.channels.clear();
+        let mut runtime = GatewayRuntime::from_config(config, store.clone())
+            .unwrap()
+            .with_clock(clock.clone());
+        let mock = add_mock(
+            &mut runtime,
+            virtual_channel(1, &[10]),
+            10,
+            MockClient::new(),
+        );
+        runtime.channels[0].points = vec![PointConfig::new(
+            10,
+            crate::core::point::ProtocolAddress::Generic("10".into()),
+        )
+        .with_max_age(Duration::from_secs(1))];
+        let mut sample = DataPoint::new(10, 5.0);
+        sample.timestamp = clock.now_utc();
+        mock.set_steady(DataBatch::from_points(vec![sample]));
+        runtime.start().await.unwrap();
+        while store.read(1, 10).await.unwrap().is_none() {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        mock.set_steady(DataBatch::new());
+
+        // Exactly at its max age the point is still fresh ...
+        clock.advance(Duration::from_secs(1));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(stored_value(&store).await, (Some(5.0), Quality::Good));
+
+        // ... and stale at the monitor's next check, half the max age later
+        clock.advance(Duration::from_millis(500));
+        for _ in 0..100 {
+            if stored_value(&store).await.1 == Quality::Uncertain {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(stored_value(&store).await, (Some(5.0), Quality::Uncertain));
+
+        runtime.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_point_override() {
+        let store = Arc::new(MemoryStore::new());
+        let clock = Arc::new(SimulatedClock::new());
+        let mut runtime = GatewayRuntime::from_config(virtual_config(), store.clone())
+            .unwrap()
+            .with_clock(clock.clone());
+        let mock = add_mock(
+            &mut runtime,
+            virtual_channel(3, &[10, 11]),
+            10,
+            MockClient::new(),
+        );
+        runtime.channels.last_mut().unwrap().points = vec![
+            PointConfig::new(
+                10,
+                crate::core::point::ProtocolAddress::Generic("10".into()),
+            ),
+            PointConfig::new(
+                11,
+                crate::core::point::ProtocolAddress::Generic("11".into()),
+            ),
+        ];
+        mock.set_steady(DataBatch::from_points(vec![
+            DataPoint::new(10, 5.0),
+            DataPoint::new(11, 6.0),
+        ]));
+        runtime.start().await.unwrap();
+
+        let set = runtime
+            .set_override(3, 10, Value::Float(42.0), None)
+            .await
+            .unwrap();
+        assert_eq!(set.set_by, Caller::LOCAL);
+        runtime
+            .set_override(3, 11, Value::Float(1.0), Some(Duration::from_secs(60)))
+            .await
+            .unwrap();
+        // Stored at once, and every poll after that is replaced
+        let point = store.read(3, 10).await.unwrap().unwrap();
+        assert_eq!(point.value, Value::Float(42.0));
+        assert_eq!(point.quality, Quality::Substituted);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let point = store.read(3, 10).await.unwrap().unwrap();
+        assert_eq!(point.value, Value::Float(42.0));
+        assert_eq!(point.quality, Quality::Substituted);
+
+        let diagnostics = runtime.diagnostics_snapshot().await;
+        let listed: Vec<PointId> = diagnostics
+            .last()
+            .unwrap()
+            .overrides
+            .iter()
+            .map(|o| o.point_id)
+            .collect();
+        assert_eq!(listed, vec![10, 11]);
+        assert!(diagnostics[0].overrides.is_empty());
+
+        // Below tune, and for unknown points, nothing changes
+        let scada = Caller::new("scada", PermissionLevel::Operate);
+        let err = runtime
+            .set_override_as(&scada, 3, 11, Value::Float(0.0), None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, GatewayError::PermissionDenied(_)));
+        assert!(runtime.clear_override_as(&scada, 3, 10).await.is_err());
+        assert!(runtime
+            .set_override(3, 12, Value::Float(0.0), None)
+            .await
+            .is_err());
+
+        // The lapsed override gives way to the device's value
+        clock.advance(Duration::from_secs(60));
+        let mut value = None;
+        for _ in 0..100 {
+            value = store.read(3, 11).await.unwrap().map(|p| p.value);
+            if value == Some(Value::Float(6.0)) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(value, Some(Value::Float(6.0)));
+        runtime.stop().await.unwrap();
+
+        // Persisted: a new runtime on the same store restores it
+        let mut restarted = GatewayRuntime::from_config(virtual_config(), store.clone())
+            .unwrap()
+            .with_clock(clock.clone());
+        add_mock(
+            &mut restarted,
+            virtual_channel(3, &[10, 11]),
+            10,
+            MockClient::new(),
+        );
+        restarted.start().await.unwrap();
+        let overrides = restarted.overrides();
+        assert_eq!(overrides.len(), 1);
+        assert_eq!(overrides[0], set);
+        assert_eq!(store.load_overrides().await.unwrap().len(), 1);
+        let cleared = restarted.clear_override(3, 10).await.unwrap();
+        assert_eq!(cleared, Some(set));
+        assert_eq!(restarted.clear_override(3, 10).await.unwrap(), None);
+        assert!(store.load_overrides().await.unwrap().is_empty());
+        restarted.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_alarm_signal_survives_reconnect() {
+        async fn wait_signal(store: &MemoryStore, expected: (Option<bool>, Quality)) {
+            for _ in 0..200 {
+                let signal = store.read(1, 101).await.unwrap();
+                if signal.map(|p| (p.value.as_bool(), p.quality)) == Some(expected) {
+                    return;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            panic!("signal never became {:?}", expected);
+        }
+
+        let store = Arc::new(MemoryStore::new());
+        let mut config = virtual_config();
+        config.channels.clear();
+        let mut runtime = GatewayRuntime::from_config(config, store.clone()).unwrap();
+        let mock = add_mock(
+            &mut runtime,
+            virtual_channel(1, &[10]),
+            10,
+            MockClient::new(),
+        );
+        let alarms = crate::core::AlarmConfig {
+            high: Some(crate::core::AlarmLimit::new(101, 80.0).with_hysteresis(5.0)),
+            ..Default::default()
+        };
+        runtime.channels[0].points = vec![PointConfig::new(
+            10,
+            crate::core::point::ProtocolAddress::Generic("10".into()),
+        )
+        .with_alarms(alarms)];
+        mock.set_steady(DataBatch::from_points(vec![DataPoint::new(10, 90.0)]));
+        runtime.start().await.unwrap();
+        wait_signal(&store, (Some(true), Quality::Good)).await;
+
+        // The signal follows the source's quality while the channel is down
+        mock.set_reachable(false);
+        wait_signal(&store, (Some(true), Quality::NotConnected)).await;
+
+        // Within the hysteresis after the reconnect: still active
+        mock.set_steady(DataBatch::from_points(vec![DataPoint::new(10, 78.0)]));
+        mock.set_reachable(true);
+        wait_signal(&store, (Some(true), Quality::Good)).await;
+
+        mock.set_steady(DataBatch::from_points(vec![DataPoint::new(10, 70.0)]));
+        wait_signal(&store, (Some(false), Quality::Good)).await;
+
+        runtime.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_backfill_on_reconnect() {
+        async fn wait_history(store: &MemoryStore, len: usize) -> Vec<DataPoint> {
+            for _ in 0..200 {
+                let history = store
+                    .read_history(1, 10, DateTime::<Utc>::MIN_UTC, 100)
+                    .await
+                    .unwrap();
+                if history.len() >= len {
+                    return history;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            panic!("history never reached {} samples", len);
+        }
+        let sample = |id: PointId, value: f64, at: DateTime<Utc>| {
+            let mut point = DataPoint::new(id, value);
+            point.timestamp = at;
+            point
+        };
+
+        let path = std::env::temp_dir().join(format!("igw-backfill-{}.jsonl", std::process::id()));
+        let mut config = virtual_config();
+        config.channels.clear();
+        config.gateway.jsonl_output = true;
+        config.gateway.jsonl.path = Some(path.clone());
+        let store = Arc::new(MemoryStore::new().with_history(Default::default()));
+        let mut runtime = GatewayRuntime::from_config(config, store.clone()).unwrap();
+
+        // Last sample stored before the outage
+        let before = Utc::now() - chrono::Duration::minutes(10);
+        store
+            .write_batch(1, &DataBatch::from_points(vec![sample(10, 1.0, before)]))
+            .await
+            .unwrap();
+
+        let mut channel = virtual_channel(1, &[10, 11]);
+        channel.backfill = Some(BackfillConfig {
+            points: vec![10],
+            forward: true,
+            ..Default::default()
+        });
+        let mock = add_mock(&mut runtime, channel, 10, MockClient::new());
+        mock.set_history(DataBatch::from_points(vec![
+            sample(10, 0.5, before - chrono::Duration::minutes(1)),
+            sample(10, 1.0, before),
+            sample(10, 2.0, before + chrono::Duration::minutes(2)).with_quality(Quality::Uncertain),
+            sample(10, 3.0, before + chrono::Duration::minutes(5)),
+            sample(11, 9.0, before + chrono::Duration::minutes(5)),
+        ]));
+        mock.set_steady(DataBatch::from_points(vec![DataPoint::new(10, 50.0)]));
+        runtime.start().await.unwrap();
+
+        // Only the samples after the stored one, ahead of the polled values
+        let history = wait_history(&store, 4).await;
+        let values: Vec<_> = history.iter().map(|p| p.value.as_f64().unwrap()).collect();
+        assert_eq!(values[..4], [1.0, 2.0, 3.0, 50.0]);
+        assert_eq!(history[1].quality, Quality::Uncertain);
+        assert!(store
+            .read_history(1, 11, before, 10)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // After a reconnect, the gap since the last poll is backfilled
+        mock.set_reachable(false);
+        while mock.is_connected() {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        let during = Utc::now();
+        mock.set_history(DataBatch::from_points(vec![sample(10, 4.0, during)]));
+        mock.set_reachable(true);
+        let mut backfilled = false;
+        for _ in 0..200 {
+            let history = wait_history(&store, 1).await;
+            backfilled = history.iter().any(|p| p.value.as_f64() == Some(4.0));
+            if backfilled {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(backfilled);
+        let backfills = mock
+            .calls()
+            .iter()
+            .filter(|call| matches!(call, MockCall::ReadHistory(points) if points == &[10]))
+            .count();
+        assert_eq!(backfills, 2);
+        // Backfilled samples do not replace the latest value
+        assert_eq!(
+            store.read(1, 10).await.unwrap().unwrap().value,
+            Value::Float(50.0)
+        );
+        runtime.stop().await.unwrap();
+
+        // Forwarded to the JSON Lines output
+        let output = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        let forwarded = output
+            .lines()
+            .map(|l| serde_json::from_str::<serde_json::Value>(l).unwrap())
+            .filter(|l| l["type"] == "data")
+            .any(|l| l["points"][0]["value"] == 2.0);
+        assert!(forwarded);
+    }
+
+    #[tokio::test]
+    async fn test_event_overflow_keeps_latest_values() {
+        let mut runtime = empty_runtime();
+        let mut config = virtual_channel(1, &[]);
+        config.event_buffer.capacity = 2;
+        let mock = add_mock(&mut runtime, config, 10, MockClient::new().event_driven());
+        runtime.start().await.unwrap();
+        while !mock.is_connected() {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        // Without yielding, the channel task cannot drain the buffer
+        for value in 1..=10 {
+            mock.emit(DataBatch::from_points(vec![
+                DataPoint::new(10, f64::from(value)),
+                DataPoint::new(11, 0.0),
+            ]))
+            .await;
+        }
+        let store = Arc::clone(runtime.store());
+        for _ in 0..200 {
+            if store.read(1, 10).await.unwrap().map(|p| p.value) == Some(Value::Float(10.0)) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        assert_eq!(
+            store.read(1, 10).await.unwrap().unwrap().value,
+            Value::Float(10.0)
+        );
+        assert!(store.read(1, 11).await.unwrap().is_some());
+
+        let diagnostics = runtime.channel_diagnostics(1).await.unwrap();
+        let extra = diagnostics.diagnostics.unwrap().extra;
+        assert!(extra["events_coalesced"].as_u64().unwrap() > 0);
+        assert!(extra.get("events_dropped").is_none());
+
+        runtime.stop().await.unwrap();
+    }
+}
@@ -0,0 +1,287 @@
+//! Point overrides for commissioning.
+//!
+//! During factory acceptance tests a point often has to show a fixed value
+//! whatever the device reports. [`GatewayRuntime::set_override()`] forces
+//! a point to a value: every sample of the point is replaced before it is
+//! stored, evaluated for alarms or written to the JSON Lines output, and
+//! carries `Quality::Substituted` so consumers can tell. The override
+//! takes effect at once (the substituted value is stored immediately) and
+//! lasts until [`GatewayRuntime::clear_override()`] or its optional
+//! expiry; the next sample after that is stored as reported.
+//!
+//! So that an override is not silently lost, nor forgotten in production:
+//!
+//! - overrides are kept by the data store ([`DataStore::save_override()`])
+//!   and restored by [`GatewayRuntime::start()`], so they survive a restart
+//!   with a persistent store;
+//! - every active override is listed in the channel's
+//!   [`ChannelDiagnostics`](super::ChannelDiagnostics), by
+//!   [`GatewayRuntime::overrides()`] and by `GET /overrides` of the HTTP
+//!   API.
+//!
+//! Setting or clearing an override requires the `tune` level (see
+//! [`PermissionLevel`]).
+//!
+//! [`GatewayRuntime::set_override()`]: super::GatewayRuntime::set_override
+//! [`GatewayRuntime::clear_override()`]: super::GatewayRuntime::clear_override
+//! [`GatewayRuntime::start()`]: super::GatewayRuntime::start
+//! [`GatewayRuntime::overrides()`]: super::GatewayRuntime::overrides
+//! [`DataStore::save_override()`]: crate::store::DataStore::save_override
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::core::clock::SharedClock;
+use crate::core::data::{DataBatch, DataPoint, PointId, Value};
+use crate::core::error::{GatewayError, Result};
+use crate::core::quality::Quality;
+
+use super::authorization::Caller;
+use super::config::PermissionLevel;
+
+/// Level required to set or clear an override.
+pub const OVERRIDE_LEVEL: PermissionLevel = PermissionLevel::Tune;
+
+/// A point forced to a fixed value.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PointOverride {
+    /// Channel id the point is stored under.
+    pub channel_id: u32,
+
+    /// Overridden point.
+    pub point_id: PointId,
+
+    /// Value stored instead of the device's.
+    pub value: Value,
+
+    /// Identity of the caller who set the override.
+    pub set_by: String,
+
+    /// When the override was set.
+    pub set_at: DateTime<Utc>,
+
+    /// When the override lapses, if ever.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl PointOverride {
+    /// Override of `point_id` set by `caller` at `now`, without expiry.
+    pub fn new(
+        caller: &Caller,
+        channel_id: u32,
+        point_id: PointId,
+        value: impl Into<Value>,
+        now: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            channel_id,
+            point_id,
+            value: value.into(),
+            set_by: caller.identity.clone(),
+            set_at: now,
+            expires_at: None,
+        }
+    }
+
+    /// Let the override lapse at `expires_at`.
+    #[must_use]
+    pub fn with_expiry(mut self, expires_at: DateTime<Utc>) -> Self {
+        self.expires_at = Some(expires_at);
+        self
+    }
+
+    /// Whether the override has lapsed at `now`.
+    pub fn is_expired_at(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|at| at <= now)
+    }
+
+    /// `point` with the overridden value, keeping its timestamps.
+    pub fn apply(&self, point: &DataPoint) -> DataPoint {
+        DataPoint {
+            value: self.value.clone(),
+            quality: Quality::Substituted,
+            ..point.clone()
+        }
+    }
+
+    /// A sample of the overridden value taken at `now`.
+    pub fn sample(&self, now: DateTime<Utc>) -> DataPoint {
+        let mut point = DataPoint::new(self.point_id, self.value.clone());
+        point.timestamp = now;
+        point.quality = Quality::Substituted;
+        point
+    }
+}
+
+/// Fail with `GatewayError::PermissionDenied` unless `caller` may set or
+/// clear overrides.
+pub(crate) fn authorize_override(caller: &Caller) -> Result<()> {
+    if caller.level >= OVERRIDE_LEVEL {
+        return Ok(());
+    }
+    Err(GatewayError::PermissionDenied(format!(
+        "{} ({}): overrides require {}",
+        caller.identity, caller.level, OVERRIDE_LEVEL
+    )))
+}
+
+/// Active overrides by `(channel_id, point_id)`, shared by the runtime and
+/// its channel tasks.
+#[derive(Debug)]
+pub(crate) struct Overrides {
+    points: RwLock<HashMap<(u32, PointId), PointOverride>>,
+    /// Decides when overrides lapse.
+    clock: SharedClock,
+}
+
+impl Overrides {
+    /// No overrides, lapsing by `clock`.
+    pub(crate) fn new(clock: SharedClock) -> Self {
+        Self {
+            points: RwLock::default(),
+            clock,
+        }
+    }
+
+    /// The same overrides, lapsing by `clock`.
+    pub(crate) fn with_clock(&self, clock: SharedClock) -> Self {
+        let points = self.points.read().unwrap_or_else(|e| e.into_inner());
+        Self {
+            points: RwLock::new(points.clone()),
+            clock,
+        }
+    }
+
+    /// Add `point_override`, replacing one of the same point.
+    pub(crate) fn set(&self, point_override: PointOverride) {
+        let key = (point_override.channel_id, point_override.point_id);
+        self.write().insert(key, point_override);
+    }
+
+    /// Remove the override of a point, returning it.
+    pub(crate) fn remove(&self, channel_id: u32, point_id: PointId) -> Option<PointOverride> {
+        self.write().remove(&(channel_id, point_id))
+    }
+
+    /// Whether a point has an override in effect.
+    pub(crate) fn is_active(&self, channel_id: u32, point_id: PointId) -> bool {
+        let now = self.clock.now_utc();
+        self.points
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&(channel_id, point_id))
+            .is_some_and(|o| !o.is_expired_at(now))
+    }
+
+    /// Remove the lapsed overrides, returning them.
+    pub(crate) fn expire(&self) -> Vec<PointOverride> {
+        let now = self.clock.now_utc();
+        let mut points = self.write();
+        let expired: Vec<(u32, PointId)> = points
+            .iter()
+            .filter(|(_, o)| o.is_expired_at(now))
+            .map(|(key, _)| *key)
+            .collect();
+        expired
+            .iter()
+            .filter_map(|key| points.remove(key))
+            .collect()
+    }
+
+    /// Overrides in effect (of `channel_id`, or all), by channel and point
+    /// id.
+    pub(crate) fn list(&self, channel_id: Option<u32>) -> Vec<PointOverride> {
+        let now = self.clock.now_utc();
+        let points = self.points.read().unwrap_or_else(|e| e.into_inner());
+        let mut list: Vec<PointOverride> = points
+            .values()
+            .filter(|o| channel_id.is_none_or(|id| o.channel_id == id))
+            .filter(|o| !o.is_expired_at(now))
+            .cloned()
+            .collect();
+        list.sort_by_key(|o| (o.channel_id, o.point_id));
+        list
+    }
+
+    /// `batch` of channel `channel_id` with the overrides in effect
+    /// applied.
+    pub(crate) fn apply(&self, channel_id: u32, batch: Arc<DataBatch>) -> Arc<DataBatch> {
+        let now = self.clock.now_utc();
+        let points = self.points.read().unwrap_or_else(|e| e.into_inner());
+        if points.is_empty() {
+            return batch;
+        }
+        let active = |point: &DataPoint| {
+            points
+                .get(&(channel_id, point.id))
+                .filter(|o| !o.is_expired_at(now))
+        };
+        if !batch.iter().any(|p| active(p).is_some()) {
+            return batch;
+        }
+        Arc::new(
+            batch
+                .iter()
+                .map(|p| active(p).map_or_else(|| p.clone(), |o| o.apply(p)))
+                .collect(),
+        )
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, HashMap<(u32, PointId), PointOverride>> {
+        self.points.write().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::clock::{Clock, SimulatedClock};
+    use std::time::Duration;
+
+    #[test]
+    fn test_apply_and_expire() {
+        let clock = Arc::new(SimulatedClock::new());
+        let now = clock.now_utc();
+        let caller = Caller::new("fat", PermissionLevel::Tune);
+        let overrides = Overrides::new(clock.clone());
+        overrides.set(PointOverride::new(&caller, 1, 10, 42.0, now));
+        overrides.set(
+            PointOverride::new(&caller, 1, 11, true, now)
+                .with_expiry(now + chrono::Duration::minutes(5)),
+        );
+        overrides.set(PointOverride::new(&caller, 2, 10, 7.0, now));
+
+        let batch = Arc::new(DataBatch::from_points(vec![
+            DataPoint::new(10, 1.0).with_quality(Quality::CommFailure),
+            DataPoint::new(11, false),
+            DataPoint::new(12, 3.0),
+        ]));
+        let applied = overrides.apply(1, Arc::clone(&batch));
+        let point = applied.get(10).unwrap();
+        assert_eq!(point.value, Value::Float(42.0));
+        assert_eq!(point.quality, Quality::Substituted);
+        assert_eq!(point.timestamp, batch.get(10).unwrap().timestamp);
+        assert_eq!(applied.get(11).unwrap().value, Value::Bool(true));
+        assert_eq!(applied.get(12).unwrap().value, Value::Float(3.0));
+        // Nothing overridden on channel 3
+        assert!(Arc::ptr_eq(&overrides.apply(3, Arc::clone(&batch)), &batch));
+
+        // Lapsed overrides no longer apply and are removed by expire()
+        clock.advance(Duration::from_secs(300));
+        let applied = overrides.apply(1, Arc::clone(&batch));
+        assert_eq!(applied.get(11).unwrap().value, Value::Bool(false));
+        let ids = |list: Vec<PointOverride>| -> Vec<(u32, PointId)> {
+            list.iter().map(|o| (o.channel_id, o.point_id)).collect()
+        };
+        assert_eq!(ids(overrides.list(None)), vec![(1, 10), (2, 10)]);
+        assert_eq!(ids(overrides.expire()), vec![(1, 11)]);
+        assert_eq!(ids(overrides.list(Some(2))), vec![(2, 10)]);
+
+        assert!(overrides.remove(1, 10).is_some());
+        assert!(overrides.remove(1, 10).is_none());
+    }
+}
@@ -0,0 +1,757 @@
+//! Typed channel parameters.
+//!
+//! The `[channels.parameters]` table of every built-in protocol is
+//! deserialized into one of the structs below. They reject unknown fields,
+//! so a typo such as `hosts = "10.0.0.5"` is reported by
+//! [`GatewayConfig::validate()`](super::GatewayConfig::validate) with the
+//! field's name instead of being ignored:
+//!
+//! ```text
+//! channel 1: invalid modbus parameters of 'PLC1': unknown field `hosts`, expected one of `host`, `port`, ...
+//! ```
+//!
+//! The structs are defined here whether or not their protocol's feature is
+//! enabled, so every configuration can be checked by any build. The
+//! protocol modules convert them into their channel configurations. The
+//! accepted fields of each protocol are listed by [`PROTOCOL_PARAMS`]
+//! (printed by `igw config-schema`), taken from the struct definitions.
+
+use std::path::PathBuf;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::core::error::{GatewayError, Result};
+use crate::protocols::virtual_channel::{ComputedPointConfig, IntegratorConfig, StrictMode};
+
+use super::config::ChannelConfig;
+
+/// A field of a parameter struct.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ParamField {
+    /// Field name as written in `[channels.parameters]`.
+    pub name: &'static str,
+
+    /// Rust type of the field.
+    pub ty: &'static str,
+
+    /// Whether the field must be given.
+    pub required: bool,
+
+    /// Description, from the field's doc comment.
+    pub doc: &'static str,
+}
+
+/// Parameter struct of a channel protocol.
+pub trait ChannelParams: DeserializeOwned {
+    /// Accepted fields, in declaration order.
+    const FIELDS: &'static [ParamField];
+}
+
+/// Define a parameter struct rejecting unknown fields, with its
+/// [`ChannelParams::FIELDS`] taken from the field declarations.
+///
+/// Field attributes are doc comments, then any number of `#[serde(...)]`,
+/// then `#[param(required)]` on the fields that must be given. The test
+/// `test_required_fields` checks the markers against what serde accepts.
+macro_rules! channel_params {
+    (
+        $(#[$attr:meta])*
+        pub struct $name:ident {
+            $(
+                $(#[doc = $doc:literal])*
+                $(#[serde($($serde:tt)*)])*
+                $(#[param($required:ident)])?
+                pub $field:ident: $ty:ty,
+            )*
+        }
+    ) => {
+        $(#[$attr])*
+        #[serde(deny_unknown_fields)]
+        pub struct $name {
+            $(
+                $(#[doc = $doc])*
+                $(#[serde($($serde)*)])*
+                pub $field: $ty,
+            )*
+        }
+
+        impl ChannelParams for $name {
+            const FIELDS: &'static [ParamField] = &[$(ParamField {
+                name: stringify!($field),
+                ty: stringify!($ty),
+                required: channel_params!(@required $($required)?),
+                doc: concat!($($doc),*),
+            }),*];
+        }
+    };
+    (@required required) => {
+        true
+    };
+    (@required) => {
+        false
+    };
+}
+
+/// Parameters of one built-in protocol.
+#[derive(Debug, Clone, Copy)]
+pub struct ProtocolParams {
+    /// Protocol name, as in [`ChannelConfig::protocol`].
+    pub protocol: &'static str,
+
+    /// Name of the parameter struct.
+    pub type_name: &'static str,
+
+    /// Accepted fields.
+    pub fields: &'static [ParamField],
+
+    check: fn(&serde_json::Value) -> std::result::Result<(), serde_json::Error>,
+}
+
+impl ProtocolParams {
+    const fn of<T: ChannelParams>(protocol: &'static str, type_name: &'static str) -> Self {
+        Self {
+            protocol,
+            type_name,
+            fields: T::FIELDS,
+            check: |parameters| parse_value::<T>(parameters).map(drop),
+        }
+    }
+
+    /// Check `parameters` against the struct, naming the first unknown,
+    /// missing or mistyped field.
+    pub fn check(&self, parameters: &serde_json::Value) -> std::result::Result<(), String> {
+        (self.check)(parameters).map_err(|e| e.to_string())
+    }
+}
+
+/// Parameters of the built-in protocols, whether or not their feature is
+/// enabled.
+pub const PROTOCOL_PARAMS: &[ProtocolParams] = &[
+    ProtocolParams::of::<ModbusChannelParamsConfig>("modbus", "ModbusChannelParamsConfig"),
+    ProtocolParams::of::<Iec104ParamsConfig>("iec104", "Iec104ParamsConfig"),
+    ProtocolParams::of::<OpcUaParamsConfig>("opcua", "OpcUaParamsConfig"),
+    ProtocolParams::of::<MqttParamsConfig>("mqtt", "MqttParamsConfig"),
+    ProtocolParams::of::<CanChannelParamsConfig>("can", "CanChannelParamsConfig"),
+    ProtocolParams::of::<GpioChannelParamsConfig>("gpio", "GpioChannelParamsConfig"),
+    ProtocolParams::of::<VirtualChannelParamsConfig>("virtual", "VirtualChannelParamsConfig"),
+    ProtocolParams::of::<ReplayParamsConfig>("replay", "ReplayParamsConfig"),
+];
+
+/// Parameters of built-in protocol `protocol` (case-insensitive); `None`
+/// for registered protocols, whose parameters are up to their builder.
+pub fn protocol_params(protocol: &str) -> Option<&'static ProtocolParams> {
+    PROTOCOL_PARAMS
+        .iter()
+        .find(|p| p.protocol.eq_ignore_ascii_case(protocol))
+}
+
+/// The parameters of `config` as `T`.
+///
+/// Missing parameters count as an empty table.
+pub(crate) fn parse<T: ChannelParams>(config: &ChannelConfig) -> Result<T> {
+    parse_value(&config.parameters).map_err(|e| {
+        GatewayError::Config(format!(
+            "invalid {} parameters of channel {} ('{}'): {}",
+            config.protocol, config.id, config.name, e
+        ))
+    })
+}
+
+fn parse_value<T: DeserializeOwned>(
+    parameters: &serde_json::Value,
+) -> std::result::Result<T, serde_json::Error> {
+    match parameters {
+        serde_json::Value::Null => T::deserialize(serde_json::json!({})),
+        parameters => T::deserialize(parameters),
+    }
+}
+
+channel_params! {
+    /// Modbus channel parameters configuration (deserialized from parameters_json).
+    ///
+    /// # TCP Mode
+    /// ```json
+    /// {
+    ///     "host": "192.168.1.100",
+    ///     "port": 502
+    /// }
+    /// ```
+    ///
+    /// # RTU Mode
+    /// ```json
+    /// {
+    ///     "device": "/dev/ttyUSB0",
+    ///     "baud_rate": 9600
+    /// }
+    /// ```
+    #[derive(Debug, Clone, Deserialize)]
+    pub struct ModbusChannelParamsConfig {
+        /// Target host (TCP mode).
+        #[serde(default)]
+        pub host: Option<String>,
+
+        /// Target port (TCP mode, default: 502).
+        #[serde(default = "default_modbus_port")]
+        pub port: u16,
+
+        /// Serial device path (RTU mode).
+        #[serde(default)]
+        pub device: Option<String>,
+
+        /// Baud rate (RTU mode, default: 9600).
+        #[serde(default = "default_baud_rate")]
+        pub baud_rate: u32,
+
+        /// Connect timeout in milliseconds (default: 5000).
+        #[serde(default = "default_modbus_connect_timeout")]
+        pub connect_timeout_ms: u64,
+
+        /// I/O timeout in milliseconds (default: 3000).
+        #[serde(default = "default_io_timeout_ms")]
+        pub io_timeout_ms: u64,
+
+        /// Maximum registers per batch read (default: 125).
+        #[serde(default = "default_max_batch_size")]
+        pub max_batch_size: u16,
+
+        /// Maximum gap between registers to allow merging (default: 10).
+        #[serde(default = "default_max_gap")]
+        pub max_gap: u16,
+
+        /// Quiet time after a broadcast write in milliseconds (default: 100).
+        /// Keep it below `io_timeout_ms`.
+        #[serde(default = "default_broadcast_delay_ms")]
+        pub broadcast_delay_ms: u64,
+
+        /// Write a bit point by reading its register and writing it back
+        /// with only that bit changed (default: false, the register is
+        /// written with 0 or 1).
+        #[serde(default)]
+        pub preserve_register_bits: bool,
+    }
+}
+
+fn default_modbus_port() -> u16 {
+    502
+}
+
+fn default_baud_rate() -> u32 {
+    9600
+}
+
+fn default_modbus_connect_timeout() -> u64 {
+    5000
+}
+
+fn default_io_timeout_ms() -> u64 {
+    3000
+}
+
+fn default_max_batch_size() -> u16 {
+    125
+}
+
+fn default_max_gap() -> u16 {
+    10
+}
+
+fn default_broadcast_delay_ms() -> u64 {
+    100
+}
+
+impl ModbusChannelParamsConfig {
+    /// Check if this is a TCP configuration.
+    pub fn is_tcp(&self) -> bool {
+        self.host.is_some()
+    }
+
+    /// Check if this is an RTU configuration.
+    pub fn is_rtu(&self) -> bool {
+        self.device.is_some()
+    }
+
+    /// Get TCP address string (host:port).
+    pub fn tcp_address(&self) -> Option<String> {
+        self.host.as_ref().map(|h| format!("{}:{}", h, self.port))
+    }
+}
+
+channel_params! {
+    /// IEC 104 channel parameters for JSON configuration.
+    ///
+    /// This is a serde-friendly version of the configuration that can be
+    /// deserialized from JSON and converted to `Iec104ChannelConfig`.
+    ///
+    /// # Example JSON
+    ///
+    /// ```json
+    /// {
+    ///     "address": "192.168.1.100:2404",
+    ///     "common_address": 1,
+    ///     "connect_timeout_ms": 10000
+    /// }
+    /// ```
+    #[derive(Debug, Clone, Deserialize)]
+    pub struct Iec104ParamsConfig {
+        /// Target address (e.g., "192.168.1.100:2404")
+        #[param(required)]
+        pub address: String,
+
+        /// Common address of ASDU (station address)
+        #[serde(default = "default_common_address")]
+        pub common_address: u16,
+
+        /// Connection timeout in milliseconds
+        #[serde(default = "default_iec104_connect_timeout")]
+        pub connect_timeout_ms: u64,
+
+        /// T1 timeout in seconds
+        #[serde(default = "default_t1_timeout")]
+        pub t1_timeout_s: u64,
+
+        /// T2 timeout in seconds
+        #[serde(default = "default_t2_timeout")]
+        pub t2_timeout_s: u64,
+
+        /// T3 timeout in seconds
+        #[serde(default = "default_t3_timeout")]
+        pub t3_timeout_s: u64,
+    }
+}
+
+fn default_common_address() -> u16 {
+    1
+}
+
+fn default_iec104_connect_timeout() -> u64 {
+    10000
+}
+
+fn default_t1_timeout() -> u64 {
+    15
+}
+
+fn default_t2_timeout() -> u64 {
+    10
+}
+
+fn default_t3_timeout() -> u64 {
+    20
+}
+
+channel_params! {
+    /// OPC UA channel parameters for JSON configuration.
+    ///
+    /// This is a serde-friendly version of the configuration that can be
+    /// deserialized from JSON and converted to `OpcUaChannelConfig`.
+    ///
+    /// # Example JSON
+    ///
+    /// ```json
+    /// {
+    ///     "endpoint_url": "opc.tcp://192.168.1.100:4840",
+    ///     "username": "user",
+    ///     "password": "pass",
+    ///     "trust_server_certs": true
+    /// }
+    /// ```
+    #[derive(Debug, Clone, Deserialize)]
+    pub struct OpcUaParamsConfig {
+        /// OPC UA server endpoint URL
+        #[param(required)]
+        pub endpoint_url: String,
+
+        /// Application name (optional)
+        #[serde(default = "default_app_name")]
+        pub application_name: String,
+
+        /// Username for authentication (optional, for username/password mode)
+        #[serde(default)]
+        pub username: Option<String>,
+
+        /// Password for authentication (optional)
+        #[serde(default)]
+        pub password: Option<String>,
+
+        /// Connection timeout in milliseconds
+        #[serde(default = "default_opcua_connect_timeout")]
+        pub connect_timeout_ms: u64,
+
+        /// Session timeout in milliseconds
+        #[serde(default = "default_session_timeout")]
+        pub session_timeout_ms: u64,
+
+        /// Whether to trust server certificates
+        #[serde(default = "default_trust_certs")]
+        pub trust_server_certs: bool,
+
+        /// Publishing interval in milliseconds for subscription
+        #[serde(default = "default_publishing_interval")]
+        pub publishing_interval_ms: u64,
+
+        /// Sampling interval in milliseconds for monitored items
+        #[serde(default = "default_sampling_interval")]
+        pub sampling_interval_ms: u64,
+    }
+}
+
+fn default_app_name() -> String {
+    "igw OPC UA Client".to_string()
+}
+
+fn default_opcua_connect_timeout() -> u64 {
+    10000
+}
+
+fn default_session_timeout() -> u64 {
+    60000
+}
+
+fn default_trust_certs() -> bool {
+    true
+}
+
+fn default_publishing_interval() -> u64 {
+    1000
+}
+
+fn default_sampling_interval() -> u64 {
+    500
+}
+
+channel_params! {
+    /// MQTT channel parameters (from `ChannelConfig::parameters`).
+    ///
+    /// ```json
+    /// {
+    ///     "host": "broker.local",
+    ///     "port": 1883,
+    ///     "username": "igw",
+    ///     "password": "secret",
+    ///     "qos": 1
+    /// }
+    /// ```
+    #[derive(Debug, Clone, Deserialize)]
+    pub struct MqttParamsConfig {
+        /// Broker host name or IP address
+        #[param(required)]
+        pub host: String,
+
+        /// Broker port
+        #[serde(default = "default_mqtt_port")]
+        pub port: u16,
+
+        /// Client identifier (default: `igw-<channel id>`)
+        #[serde(default)]
+        pub client_id: Option<String>,
+
+        /// Username for authentication (optional)
+        #[serde(default)]
+        pub username: Option<String>,
+
+        /// Password for authentication (optional)
+        #[serde(default)]
+        pub password: Option<String>,
+
+        /// Keep-alive interval in seconds
+        #[serde(default = "default_keep_alive")]
+        pub keep_alive_secs: u64,
+
+        /// Connection timeout in milliseconds
+        #[serde(default = "default_mqtt_connect_timeout")]
+        pub connect_timeout_ms: u64,
+
+        /// QoS of subscriptions and commands (0, 1 or 2)
+        #[serde(default = "default_qos")]
+        pub qos: u8,
+    }
+}
+
+fn default_mqtt_port() -> u16 {
+    1883
+}
+
+fn default_keep_alive() -> u64 {
+    30
+}
+
+fn default_mqtt_connect_timeout() -> u64 {
+    10000
+}
+
+fn default_qos() -> u8 {
+    1
+}
+
+channel_params! {
+    /// CAN channel parameters configuration (deserialized from parameters_json).
+    ///
+    /// # Example JSON
+    /// ```json
+    /// {
+    ///     "interface": "can0",
+    ///     "bitrate": 250000,
+    ///     "rx_poll_interval_ms": 50,
+    ///     "data_read_interval_ms": 1000
+    /// }
+    /// ```
+    #[derive(Debug, Clone, Deserialize)]
+    pub struct CanChannelParamsConfig {
+        /// CAN interface name (e.g., "can0").
+        #[serde(default = "default_can_interface")]
+        pub interface: String,
+
+        /// CAN bitrate in bits per second.
+        #[serde(default = "default_bitrate")]
+        pub bitrate: u32,
+
+        /// RX polling interval in milliseconds.
+        #[serde(default = "default_rx_poll_interval")]
+        pub rx_poll_interval_ms: u64,
+
+        /// Data reading interval in milliseconds.
+        #[serde(default = "default_data_read_interval")]
+        pub data_read_interval_ms: u64,
+    }
+}
+
+fn default_can_interface() -> String {
+    "can0".to_string()
+}
+
+fn default_bitrate() -> u32 {
+    250000
+}
+
+fn default_rx_poll_interval() -> u64 {
+    50
+}
+
+fn default_data_read_interval() -> u64 {
+    1000
+}
+
+channel_params! {
+    /// GPIO channel parameters configuration (deserialized from parameters_json).
+    ///
+    /// # Example JSON
+    /// ```json
+    /// {
+    ///     "driver": "gpiod",
+    ///     "poll_interval_ms": 200
+    /// }
+    /// ```
+    #[derive(Debug, Clone, Deserialize)]
+    pub struct GpioChannelParamsConfig {
+        /// Driver type: "gpiod" or "sysfs".
+        #[serde(default = "default_driver")]
+        pub driver: String,
+
+        /// Sysfs base path (only for sysfs driver).
+        #[serde(default = "default_sysfs_path")]
+        pub sysfs_base_path: String,
+
+        /// Poll interval in milliseconds.
+        #[serde(default = "default_poll_interval")]
+        pub poll_interval_ms: u64,
+    }
+}
+
+fn default_driver() -> String {
+    "gpiod".to_string()
+}
+
+fn default_sysfs_path() -> String {
+    "/sys/class/gpio".to_string()
+}
+
+fn default_poll_interval() -> u64 {
+    200
+}
+
+channel_params! {
+    /// Virtual channel parameters configuration (deserialized from parameters_json).
+    ///
+    /// Virtual channels are simple data hubs and don't require complex configuration.
+    ///
+    /// # Example JSON
+    /// ```json
+    /// {
+    ///     "name": "data_hub",
+    ///     "buffer_size": 2048,
+    ///     "strict": "reject",
+    ///     "computed": [
+    ///         { "id": 3, "expression": "sqrt(p1^2 + p2^2)" }
+    ///     ],
+    ///     "integrators": [
+    ///         { "id": 20, "input": 3, "time_base_secs": 3600, "reset_point": 21 }
+    ///     ]
+    /// }
+    /// ```
+    #[derive(Debug, Clone, Deserialize, Default)]
+    pub struct VirtualChannelParamsConfig {
+        /// Channel name for identification.
+        #[serde(default = "default_virtual_name")]
+        pub name: String,
+
+        /// Event buffer size.
+        #[serde(default = "default_buffer_size")]
+        pub buffer_size: usize,
+
+        /// Computed point definitions.
+        #[serde(default)]
+        pub computed: Vec<ComputedPointConfig>,
+
+        /// Totalizer definitions.
+        #[serde(default)]
+        pub integrators: Vec<IntegratorConfig>,
+
+        /// Checking of written points against the configured ones: `off`,
+        /// `drop` or `reject`.
+        #[serde(default)]
+        pub strict: StrictMode,
+    }
+}
+
+fn default_virtual_name() -> String {
+    "virtual".to_string()
+}
+
+fn default_buffer_size() -> usize {
+    1024
+}
+
+channel_params! {
+    /// Replay parameters of a `"replay"` channel.
+    #[derive(Debug, Clone, Deserialize)]
+    pub struct ReplayParamsConfig {
+        /// Recording to replay.
+        #[param(required)]
+        pub file: PathBuf,
+
+        /// Recorded channel to replay (default: the replay channel's own id).
+        #[serde(default)]
+        pub channel_id: Option<u32>,
+
+        /// Replay speed factor (default 1.0, original pace).
+        #[serde(default = "default_speed")]
+        pub speed: f64,
+    }
+}
+
+fn default_speed() -> f64 {
+    1.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channel(protocol: &str, parameters: serde_json::Value) -> ChannelConfig {
+        serde_json::from_value(serde_json::json!({
+            "id": 1,
+            "name": "PLC1",
+            "protocol": protocol,
+            "parameters": parameters
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_fields() {
+        let modbus = protocol_params("Modbus").unwrap();
+        assert_eq!(modbus.type_name, "ModbusChannelParamsConfig");
+        assert_eq!(
+            modbus.fields[1],
+            ParamField {
+                name: "port",
+                ty: "u16",
+                required: false,
+                doc: " Target port (TCP mode, default: 502).",
+            }
+        );
+        let required: Vec<&str> = PROTOCOL_PARAMS
+            .iter()
+            .flat_map(|p| p.fields.iter().filter(|f| f.required))
+            .map(|f| f.name)
+            .collect();
+        assert_eq!(required, vec!["address", "endpoint_url", "host", "file"]);
+        assert!(protocol_params("my-udp").is_none());
+    }
+
+    #[test]
+    fn test_parse() {
+        let params: ModbusChannelParamsConfig = parse(&channel(
+            "modbus",
+            serde_json::json!({ "host": "10.0.0.5" }),
+        ))
+        .unwrap();
+        assert_eq!(params.tcp_address().as_deref(), Some("10.0.0.5:502"));
+
+        // Missing parameters take the defaults
+        let params: CanChannelParamsConfig =
+            parse(&channel("can", serde_json::Value::Null)).unwrap();
+        assert_eq!(params.interface, "can0");
+
+        let err = parse::<ModbusChannelParamsConfig>(&channel(
+            "modbus",
+            serde_json::json!({ "hosts": "10.0.0.5" }),
+        ))
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("channel 1 ('PLC1')"), "{}", err);
+        assert!(err.contains("unknown field `hosts`"), "{}", err);
+    }
+
+    #[test]
+    fn test_check() {
+        let iec104 = protocol_params("iec104").unwrap();
+        assert!(iec104
+            .check(&serde_json::json!({ "address": "10.0.0.5:2404" }))
+            .is_ok());
+        assert_eq!(
+            iec104.check(&serde_json::json!({ "common_address": 3 })),
+            Err("missing field `address`".into())
+        );
+        assert!(iec104
+            .check(&serde_json::json!({ "address": "10.0.0.5:2404", "common_address": "3" }))
+            .unwrap_err()
+            .contains("invalid type"));
+    }
+
+    #[test]
+    fn test_required_fields() {
+        // The required fields of the built-in protocols are all strings or
+        // paths: with just those, every struct parses, and without any one
+        // of them it does not.
+        for params in PROTOCOL_PARAMS {
+            let required: Vec<&str> = params
+                .fields
+                .iter()
+                .filter(|f| f.required)
+                .map(|f| f.name)
+                .collect();
+            let given: serde_json::Map<String, serde_json::Value> = required
+                .iter()
+                .map(|&name| (name.to_string(), "x".into()))
+                .collect();
+            assert_eq!(
+                params.check(&given.clone().into()),
+                Ok(()),
+                "{}",
+                params.protocol
+            );
+            for name in required {
+                let mut missing = given.clone();
+                missing.remove(name);
+                assert_eq!(
+                    params.check(&missing.into()),
+                    Err(format!("missing field `{}`", name)),
+                    "{}",
+                    params.protocol
+                );
+            }
+        }
+    }
+}
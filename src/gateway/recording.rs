@@ -0,0 +1,699 @@
+//! Recording and replay of channel data.
+//!
+//! While [`GatewayRuntime::start_recording()`](super::GatewayRuntime::start_recording)
+//! is active, every poll result and every data event of every channel is
+//! appended to a JSON Lines file, one [`RecordedEntry`] per line:
+//!
+//! ```text
+//! {"type":"poll","channel_id":1,"timestamp":"2024-05-01T03:00:00.000Z","points":[...],"failures":[{"point_id":7,"error":"timeout"}]}
+//! {"type":"data","channel_id":2,"timestamp":"2024-05-01T03:00:00.120Z","points":[...]}
+//! {"type":"connection_state","channel_id":2,"timestamp":"2024-05-01T03:00:04.000Z","state":"disconnected"}
+//! ```
+//!
+//! Points are in the [`DataBatch` wire format](crate::core::data::DataBatch#wire-format),
+//! so recordings replay across igw versions with integers and floats kept
+//! apart.
+//!
+//! Recording can be started and stopped at any time without restarting
+//! channels. Like the JSON Lines output, it never blocks a channel: entries
+//! are queued and written by a dedicated thread, and entries that do not fit
+//! the queue are dropped and counted in [`Recorder::dropped()`].
+//!
+//! A [`ReplayChannel`] feeds one channel of a recording back through the
+//! gateway, at the original pace or accelerated. Configure it like any
+//! other channel with protocol `"replay"`:
+//!
+//! ```toml
+//! [[channels]]
+//! id = 1
+//! name = "incident-0300"
+//! protocol = "replay"
+//! poll_interval_ms = 100
+//!
+//! [channels.parameters]
+//! file = "/var/log/igw/recording.jsonl"
+//! channel_id = 1   # recorded channel (default: this channel's id)
+//! speed = 10.0     # 1.0 = original pace, 0 = as fast as possible
+//! ```
+//!
+//! A recording of a polled channel is replayed one recorded poll per
+//! `poll_once()`, so the poll interval must be short enough to keep up with
+//! the requested speed. A recording of an event-driven channel is replayed
+//! as events.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+use crate::core::data::DataBatch;
+use crate::core::error::{GatewayError, Result};
+use crate::core::traits::{
+    CommunicationMode, ConnectionState, DataEvent, DataEventReceiver, Diagnostics, EventBus,
+    PointFailure, PollResult, WriteResult,
+};
+
+use super::config::ChannelConfig;
+use super::runtime::{ChannelCapabilities, ChannelRuntime};
+
+/// Entries queued for the writer before new ones are dropped.
+const QUEUE_CAPACITY: usize = 4096;
+
+/// One line of a recording.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RecordedEntry {
+    /// Result of one `poll_once()` of a polled channel.
+    Poll {
+        /// Channel id.
+        channel_id: u32,
+        /// When the poll returned.
+        timestamp: DateTime<Utc>,
+        /// Points read.
+        #[serde(flatten)]
+        batch: Arc<DataBatch>,
+        /// Points that failed to read.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        failures: Vec<PointFailure>,
+    },
+
+    /// A data update of an event-driven channel.
+    Data {
+        /// Channel id.
+        channel_id: u32,
+        /// When the update was received.
+        timestamp: DateTime<Utc>,
+        /// Updated points.
+        #[serde(flatten)]
+        batch: Arc<DataBatch>,
+    },
+
+    /// A connection state change reported by an event-driven channel.
+    ConnectionState {
+        /// Channel id.
+        channel_id: u32,
+        /// When the change was received.
+        timestamp: DateTime<Utc>,
+        /// New state.
+        state: ConnectionState,
+    },
+}
+
+impl RecordedEntry {
+    /// A poll result.
+    pub fn poll(
+        channel_id: u32,
+        batch: impl Into<Arc<DataBatch>>,
+        failures: &[PointFailure],
+    ) -> Self {
+        Self::Poll {
+            channel_id,
+            timestamp: Utc::now(),
+            batch: batch.into(),
+            failures: failures.to_vec(),
+        }
+    }
+
+    /// A data event; `None` for events that are not recorded.
+    pub fn event(channel_id: u32, event: &DataEvent) -> Option<Self> {
+        let timestamp = Utc::now();
+        match event {
+            DataEvent::DataUpdate(batch) => Some(Self::Data {
+                channel_id,
+                timestamp,
+                batch: Arc::clone(batch),
+            }),
+            DataEvent::ConnectionChanged(state) => Some(Self::ConnectionState {
+                channel_id,
+                timestamp,
+                state: *state,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Recorded channel id.
+    pub fn channel_id(&self) -> u32 {
+        match self {
+            Self::Poll { channel_id, .. }
+            | Self::Data { channel_id, .. }
+            | Self::ConnectionState { channel_id, .. } => *channel_id,
+        }
+    }
+
+    /// When the entry was recorded.
+    pub fn timestamp(&self) -> DateTime<Utc> {
+        match self {
+            Self::Poll { timestamp, .. }
+            | Self::Data { timestamp, .. }
+            | Self::ConnectionState { timestamp, .. } => *timestamp,
+        }
+    }
+}
+
+/// Read all entries of `channel_id` from a recording, in file order.
+///
+/// Blank lines are skipped; any other line that is not a valid entry fails
+/// with `GatewayError::InvalidData`.
+pub fn read_recording(path: impl AsRef<Path>, channel_id: u32) -> Result<Vec<RecordedEntry>> {
+    let reader = BufReader::new(File::open(path.as_ref())?);
+    let mut entries = Vec::new();
+    for (number, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry: RecordedEntry = serde_json::from_str(&line).map_err(|e| {
+            GatewayError::invalid_data(format!(
+                "{} line {}: {}",
+                path.as_ref().display(),
+                number + 1,
+                e
+            ))
+        })?;
+        if entry.channel_id() == channel_id {
+            entries.push(entry);
+        }
+    }
+    Ok(entries)
+}
+
+/// Cheap, cloneable handle for queueing entries to a [`Recording`].
+#[derive(Debug, Clone)]
+pub struct Recorder {
+    tx: mpsc::Sender<RecordedEntry>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl Recorder {
+    /// Queue an entry for writing. Never blocks.
+    pub fn record(&self, entry: RecordedEntry) {
+        if let Err(TrySendError::Full(_)) = self.tx.try_send(entry) {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Number of entries dropped because the writer fell behind.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+/// A recording being written to a file.
+///
+/// The writer thread exits once the [`Recording`] and every [`Recorder`]
+/// cloned from it are gone, after writing what is queued.
+#[derive(Debug)]
+pub struct Recording {
+    path: PathBuf,
+    recorder: Recorder,
+    handle: JoinHandle<()>,
+}
+
+impl Recording {
+    /// Open `path` for appending and start the writer thread.
+    ///
+    /// Must be called from within a Tokio runtime.
+    pub fn start(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let file = File::options().create(true).append(true).open(&path)?;
+        let (tx, rx) = mpsc::channel(QUEUE_CAPACITY);
+        Ok(Self {
+            path,
+            recorder: Recorder {
+                tx,
+                dropped: Arc::new(AtomicU64::new(0)),
+            },
+            handle: tokio::task::spawn_blocking(move || write_entries(BufWriter::new(file), rx)),
+        })
+    }
+
+    /// File being written.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Handle for queueing entries.
+    pub fn recorder(&self) -> &Recorder {
+        &self.recorder
+    }
+
+    /// Drop this handle and wait until the writer has flushed and exited.
+    ///
+    /// Waits for every other [`Recorder`] clone to be dropped as well.
+    pub async fn close(self) {
+        let Self {
+            recorder, handle, ..
+        } = self;
+        drop(recorder);
+        let _ = handle.await;
+    }
+}
+
+/// Writer thread: one JSON line per entry, flushed whenever the queue is
+/// drained.
+fn write_entries(mut file: BufWriter<File>, mut rx: mpsc::Receiver<RecordedEntry>) {
+    while let Some(entry) = rx.blocking_recv() {
+        let mut next = Some(entry);
+        while let Some(entry) = next {
+            if let Err(_e) = write_entry(&mut file, &entry) {
+                #[cfg(feature = "tracing-support")]
+                tracing::error!("Recording write failed: {}", _e);
+            }
+            next = rx.try_recv().ok();
+        }
+        if let Err(_e) = file.flush() {
+            #[cfg(feature = "tracing-support")]
+            tracing::error!("Recording write failed: {}", _e);
+        }
+    }
+}
+
+fn write_entry(file: &mut impl Write, entry: &RecordedEntry) -> io::Result<()> {
+    let mut line = serde_json::to_vec(entry)?;
+    line.push(b'\n');
+    file.write_all(&line)
+}
+
+pub use super::params::ReplayParamsConfig;
+
+/// Maps recorded timestamps to replay instants.
+#[derive(Debug, Clone, Copy)]
+struct ReplayClock {
+    started: Instant,
+    origin: DateTime<Utc>,
+    speed: f64,
+}
+
+impl ReplayClock {
+    fn due(&self, timestamp: DateTime<Utc>) -> Instant {
+        if self.speed <= 0.0 || !self.speed.is_finite() {
+            return self.started;
+        }
+        let offset = (timestamp - self.origin).to_std().unwrap_or_default();
+        self.started + offset.div_f64(self.speed)
+    }
+}
+
+/// Channel replaying a recording.
+///
+/// The first `connect()` starts the replay clock; entries are handed out
+/// when their recorded offset from the first entry, divided by the speed
+/// factor, has elapsed. Reconnecting continues where the replay left off.
+/// Once all entries are replayed, polls return empty results.
+///
+/// Replay channels are read-only: writes fail with
+/// `GatewayError::Unsupported`.
+pub struct ReplayChannel {
+    id: u32,
+    name: String,
+    entries: Arc<[RecordedEntry]>,
+    /// Index of the next entry, shared with the event task.
+    next: Arc<AtomicUsize>,
+    speed: f64,
+    event_driven: bool,
+    clock: Option<ReplayClock>,
+    state: ConnectionState,
+    events: EventBus,
+    task: Option<JoinHandle<()>>,
+}
+
+impl ReplayChannel {
+    /// Replay recorded `entries` (of one channel) as channel `id`.
+    ///
+    /// A recording without poll results is replayed as an event-driven
+    /// channel.
+    pub fn new(id: u32, entries: Vec<RecordedEntry>) -> Self {
+        let event_driven = !entries.is_empty()
+            && !entries
+                .iter()
+                .any(|e| matches!(e, RecordedEntry::Poll { .. }));
+        Self {
+            id,
+            name: format!("replay-{}", id),
+            entries: entries.into(),
+            next: Arc::new(AtomicUsize::new(0)),
+            speed: 1.0,
+            event_driven,
+            clock: None,
+            state: ConnectionState::Disconnected,
+            events: EventBus::default(),
+            task: None,
+        }
+    }
+
+    /// Replay `channel_id` of the recording at `path` as that channel.
+    pub fn open(path: impl AsRef<Path>, channel_id: u32) -> Result<Self> {
+        Ok(Self::new(channel_id, read_recording(path, channel_id)?))
+    }
+
+    /// Build a replay channel from a `"replay"` channel configuration.
+    pub fn from_config(config: &ChannelConfig) -> Result<Self> {
+        let params: ReplayParamsConfig = super::params::parse(config)?;
+        let entries = read_recording(&params.file, params.channel_id.unwrap_or(config.id))?;
+        Ok(Self::new(config.id, entries)
+            .with_name(config.name.clone())
+            .with_speed(params.speed))
+    }
+
+    /// Set the display name.
+    #[must_use]
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Set the speed factor: 1.0 replays at the original pace, 10.0 ten
+    /// times faster, 0 without any delay.
+    #[must_use]
+    pub fn with_speed(mut self, speed: f64) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    /// Number of entries not replayed yet.
+    pub fn remaining(&self) -> usize {
+        self.entries
+            .len()
+            .saturating_sub(self.next.load(Ordering::Relaxed))
+    }
+
+    fn clock(&mut self) -> ReplayClock {
+        let (entries, speed) = (&self.entries, self.speed);
+        *self.clock.get_or_insert_with(|| ReplayClock {
+            started: Instant::now(),
+            origin: entries.first().map_or_else(Utc::now, |e| e.timestamp()),
+            speed,
+        })
+    }
+
+    fn stop_task(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+    }
+}
+
+impl std::fmt::Debug for ReplayChannel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReplayChannel")
+            .field("id", &self.id)
+            .field("name", &self.name)
+            .field("entries", &self.entries.len())
+            .field("remaining", &self.remaining())
+            .field("speed", &self.speed)
+            .field("event_driven", &self.event_driven)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for ReplayChannel {
+    fn drop(&mut self) {
+        self.stop_task();
+    }
+}
+
+/// Publish the entries from `next` on, each when it is due.
+async fn replay_events(
+    entries: Arc<[RecordedEntry]>,
+    next: Arc<AtomicUsize>,
+    clock: ReplayClock,
+    events: EventBus,
+) {
+    loop {
+        let index = next.load(Ordering::Relaxed);
+        let Some(entry) = entries.get(index) else {
+            return;
+        };
+        tokio::time::sleep_until(clock.due(entry.timestamp())).await;
+        // Advance first: a replayed disconnect gets this task aborted
+        next.store(index + 1, Ordering::Relaxed);
+        match entry {
+            RecordedEntry::Poll { batch, .. } | RecordedEntry::Data { batch, .. } => {
+                events.publish(DataEvent::DataUpdate(Arc::clone(batch)));
+            }
+            RecordedEntry::ConnectionState { state, .. } => {
+                events.publish(DataEvent::ConnectionChanged(*state));
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl ChannelRuntime for ReplayChannel {
+    fn id(&self) -> u32 {
+        self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn protocol(&self) -> &str {
+        "replay"
+    }
+
+    fn is_event_driven(&self) -> bool {
+        self.event_driven
+    }
+
+    fn capabilities(&self) -> ChannelCapabilities {
+        let mode = if self.event_driven {
+            CommunicationMode::EventDriven
+        } else {
+            CommunicationMode::Polling
+        };
+        ChannelCapabilities::new("Replay", &[mode]).read_only()
+    }
+
+    async fn connect(&mut self) -> Result<()> {
+        self.clock();
+        self.state = ConnectionState::Connected;
+        Ok(())
+    }
+
+    async fn disconnect(&mut self) -> Result<()> {
+        self.stop_task();
+        self.state = ConnectionState::Disconnected;
+        Ok(())
+    }
+
+    async fn poll_once(&mut self) -> PollResult {
+        if self.event_driven || !self.state.is_connected() {
+            return PollResult::default();
+        }
+        let clock = self.clock();
+        loop {
+            let index = self.next.load(Ordering::Relaxed);
+            let Some(entry) = self.entries.get(index) else {
+                return PollResult::default();
+            };
+            self.next.store(index + 1, Ordering::Relaxed);
+            if let RecordedEntry::Poll {
+                timestamp,
+                batch,
+                failures,
+                ..
+            } = entry
+            {
+                tokio::time::sleep_until(clock.due(*timestamp)).await;
+                return PollResult::partial(batch.as_ref().clone(), failures.clone());
+            }
+        }
+    }
+
+    async fn write_control(&mut self, _commands: &[(u32, f64)]) -> Result<WriteResult> {
+        Err(GatewayError::Unsupported(
+            "replay channels are read-only".into(),
+        ))
+    }
+
+    async fn write_adjustment(&mut self, _adjustments: &[(u32, f64)]) -> Result<WriteResult> {
+        Err(GatewayError::Unsupported(
+            "replay channels are read-only".into(),
+        ))
+    }
+
+    fn subscribe(&self) -> Option<DataEventReceiver> {
+        self.event_driven.then(|| self.events.subscribe())
+    }
+
+    async fn start_events(&mut self) -> Result<()> {
+        if self.event_driven && self.task.is_none() {
+            self.task = Some(tokio::spawn(replay_events(
+                Arc::clone(&self.entries),
+                Arc::clone(&self.next),
+                self.clock(),
+                self.events.clone(),
+            )));
+        }
+        Ok(())
+    }
+
+    async fn stop_events(&mut self) -> Result<()> {
+        self.stop_task();
+        Ok(())
+    }
+
+    async fn diagnostics(&self) -> Result<Diagnostics> {
+        let mut diagnostics = Diagnostics::new("replay");
+        diagnostics.connection_state = self.state;
+        diagnostics.read_count = self.next.load(Ordering::Relaxed).min(self.entries.len()) as u64;
+        diagnostics.extra = serde_json::json!({
+            "entries": self.entries.len(),
+            "remaining": self.remaining(),
+            "speed": self.speed,
+        });
+        Ok(diagnostics)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::data::DataPoint;
+    use std::time::Duration;
+
+    fn at(ms: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp_millis(1_714_532_400_000 + ms).unwrap()
+    }
+
+    fn poll(ms: i64, value: f64) -> RecordedEntry {
+        RecordedEntry::Poll {
+            channel_id: 1,
+            timestamp: at(ms),
+            batch: Arc::new(DataBatch::from_points(vec![DataPoint::new(10, value)])),
+            failures: vec![],
+        }
+    }
+
+    #[test]
+    fn test_entry_lines_roundtrip() {
+        let entry = RecordedEntry::Poll {
+            channel_id: 1,
+            timestamp: at(0),
+            batch: Arc::new(DataBatch::from_points(vec![DataPoint::new(10, 1.5)])),
+            failures: vec![PointFailure::new(11, "timeout")],
+        };
+        let line = serde_json::to_string(&entry).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(json["type"], "poll");
+        assert_eq!(json["points"][0]["id"], 10);
+        assert_eq!(json["failures"][0]["point_id"], 11);
+
+        let RecordedEntry::Poll {
+            batch, failures, ..
+        } = serde_json::from_str(&line).unwrap()
+        else {
+            panic!("not a poll entry");
+        };
+        assert_eq!(batch.get(10).unwrap().value.as_f64(), Some(1.5));
+        assert_eq!(failures[0].error, "timeout");
+
+        let event = RecordedEntry::event(2, &DataEvent::Heartbeat);
+        assert!(event.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_recording_file() {
+        let path = std::env::temp_dir().join(format!("igw-recording-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let recording = Recording::start(&path).unwrap();
+        let recorder = recording.recorder().clone();
+        recorder.record(poll(0, 1.0));
+        recorder.record(
+            RecordedEntry::event(
+                2,
+                &DataEvent::ConnectionChanged(ConnectionState::Disconnected),
+            )
+            .unwrap(),
+        );
+        recorder.record(poll(100, 2.0));
+        drop(recorder);
+        recording.close().await;
+
+        let entries = read_recording(&path, 1).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].timestamp(), at(100));
+        let entries = read_recording(&path, 2).unwrap();
+        assert!(matches!(
+            entries[0],
+            RecordedEntry::ConnectionState {
+                state: ConnectionState::Disconnected,
+                ..
+            }
+        ));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_replay_polls_at_recorded_pace() {
+        let mut replay =
+            ReplayChannel::new(1, vec![poll(0, 1.0), poll(1000, 2.0), poll(3000, 3.0)])
+                .with_speed(2.0);
+        assert!(!replay.is_event_driven());
+        replay.connect().await.unwrap();
+
+        let started = Instant::now();
+        let mut values = Vec::new();
+        for _ in 0..3 {
+            let result = replay.poll_once().await;
+            values.push(result.data.get(10).unwrap().value.as_f64().unwrap());
+        }
+        assert_eq!(values, [1.0, 2.0, 3.0]);
+        assert_eq!(started.elapsed(), Duration::from_millis(1500));
+        assert_eq!(replay.remaining(), 0);
+        assert!(replay.poll_once().await.data.is_empty());
+        assert!(replay.write_control(&[(10, 1.0)]).await.is_err());
+        let capabilities = replay.capabilities();
+        assert!(!capabilities.writable);
+        assert!(capabilities.supports(CommunicationMode::Polling));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_replay_events_resume_after_disconnect() {
+        let data = |ms: i64, value: f64| RecordedEntry::Data {
+            channel_id: 1,
+            timestamp: at(ms),
+            batch: Arc::new(DataBatch::from_points(vec![DataPoint::new(10, value)])),
+        };
+        let entries = vec![
+            data(0, 1.0),
+            RecordedEntry::ConnectionState {
+                channel_id: 1,
+                timestamp: at(500),
+                state: ConnectionState::Disconnected,
+            },
+            data(1000, 2.0),
+        ];
+        let mut replay = ReplayChannel::new(1, entries).with_speed(0.0);
+        assert!(replay.is_event_driven());
+
+        replay.connect().await.unwrap();
+        let mut rx = replay.subscribe().unwrap();
+        replay.start_events().await.unwrap();
+        assert!(matches!(rx.recv().await, Some(DataEvent::DataUpdate(_))));
+        assert!(matches!(
+            rx.recv().await,
+            Some(DataEvent::ConnectionChanged(ConnectionState::Disconnected))
+        ));
+        replay.disconnect().await.unwrap();
+
+        replay.connect().await.unwrap();
+        replay.start_events().await.unwrap();
+        let Some(DataEvent::DataUpdate(batch)) = rx.recv().await else {
+            panic!("expected a data update");
+        };
+        assert_eq!(batch.get(10).unwrap().value.as_f64(), Some(2.0));
+        assert_eq!(replay.remaining(), 0);
+    }
+}
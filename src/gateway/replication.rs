@@ -0,0 +1,1029 @@
+//! Store replication from a primary gateway to a warm standby.
+//!
+//! With `[gateway.replication]` (see [`ReplicationConfig`]), the primary
+//! wraps its data store in a [`ReplicatedStore`], which numbers every write
+//! (values and point configurations) with a sequence number, and a
+//! [`Primary`] streams these writes to any standby that connects. The
+//! [`Standby`] writes them into its own store, so its values are current
+//! when it takes over.
+//!
+//! The link is a TCP connection carrying one JSON object per line. After
+//! the handshake, messages only flow from the primary to the standby:
+//!
+//! ```text
+//! {"type":"hello","token":"..."}                        (standby)
+//! {"type":"hello","token":"..."}                        (primary)
+//! {"type":"snapshot","seq":41,"snapshot":{"format_version":1,...}}
+//! {"type":"data","seq":42,"channel_id":1,"points":[{"id":1001,"value":21.5,...}]}
+//! {"type":"points","seq":43,"channel_id":1,"points":[{"id":1001,"address":"...",...}]}
+//! {"type":"heartbeat","seq":43}
+//! ```
+//!
+//! # Handshake
+//!
+//! Both gateways are configured with the same `token`. The standby opens
+//! each connection with a `hello` carrying its token, and the primary
+//! drops connections whose first line is not a `hello` with its own token.
+//! It then answers with its `hello`, which the standby checks in turn
+//! before it accepts the snapshot, so neither end exchanges store contents
+//! with a gateway that does not share the secret. The token is sent in
+//! clear text: the link belongs on a private network.
+//!
+//! # Resynchronization
+//!
+//! Every connection starts with a full [`StoreSnapshot`] of the primary,
+//! tagged with the sequence number of the last write it contains (writes
+//! wait while it is taken); the writes streamed after it follow without
+//! interruption. A heartbeat carries the
+//! sequence number of the last write sent.
+//!
+//! The standby checks every sequence number. A missing one, a primary that
+//! stays silent for three heartbeat intervals, or a broken connection make
+//! it reconnect, and the new connection's snapshot resynchronizes it. A
+//! standby that falls `queue_capacity` writes behind is disconnected by the
+//! primary for the same reason. Gaps and resyncs are counted in
+//! [`ReplicationStats`].
+//!
+//! # Replicated values
+//!
+//! The primary always wins: its values and point configurations replace
+//! the standby's, whatever their timestamps. Values that are `Good` on the
+//! primary are stored as `Quality::Substituted` on the standby, since the
+//! standby did not read them itself; worse qualities are kept. Points that
+//! disappear from the primary are not removed from the standby, and point
+//! history ([`DataStore::write_history()`]) is not replicated.
+//!
+//! The standby does not stop its own channels: leave the replicated
+//! channels out of its configuration (or disabled) until it takes over.
+
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader, BufWriter, Lines};
+use tokio::net::tcp::OwnedReadHalf;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::Mutex;
+use tokio::task::{JoinHandle, JoinSet};
+use tokio::time::{timeout, MissedTickBehavior};
+
+use crate::core::data::{DataBatch, DataPoint, PointId};
+use crate::core::error::{GatewayError, Result};
+use crate::core::point::{PointConfig, PointMeta};
+use crate::core::quality::Quality;
+use crate::core::traits::ReadRequest;
+use crate::store::{AuditQuery, DataStore, PointAge, PointWatch, Snapshot, StoreSnapshot};
+
+use super::audit::CommandAudit;
+use super::authorization::token_matches;
+use super::config::{ReplicationConfig, ReplicationRole};
+use super::overrides::PointOverride;
+
+/// First delay before the standby reconnects.
+const RECONNECT_MIN: Duration = Duration::from_millis(500);
+
+/// Longest delay between reconnect attempts of the standby.
+const RECONNECT_MAX: Duration = Duration::from_secs(30);
+
+/// Heartbeat intervals without a message after which the standby
+/// reconnects.
+const SILENT_HEARTBEATS: u32 = 3;
+
+/// First line of each end of a connection.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename = "hello")]
+struct Hello {
+    token: String,
+}
+
+/// One line on the replication link after the handshake.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Message {
+    /// Whole store of the primary, up to and including write `seq`.
+    Snapshot { seq: u64, snapshot: StoreSnapshot },
+
+    /// A batch written to the store.
+    Data {
+        seq: u64,
+        channel_id: u32,
+        #[serde(flatten)]
+        batch: Arc<DataBatch>,
+    },
+
+    /// Point configurations of a channel replaced.
+    Points {
+        seq: u64,
+        channel_id: u32,
+        points: Arc<Vec<PointConfig>>,
+    },
+
+    /// Nothing written since write `seq`.
+    Heartbeat { seq: u64 },
+}
+
+impl Message {
+    fn seq(&self) -> u64 {
+        match self {
+            Self::Snapshot { seq, .. }
+            | Self::Data { seq, .. }
+            | Self::Points { seq, .. }
+            | Self::Heartbeat { seq } => *seq,
+        }
+    }
+}
+
+/// Sequence counter and fan-out of the writes of a [`ReplicatedStore`].
+#[derive(Debug)]
+struct Feed {
+    /// Sequence number of the last write (0 before the first).
+    seq: u64,
+    tx: broadcast::Sender<Message>,
+}
+
+impl Feed {
+    /// Number the next write and send it to the connected standbys.
+    fn publish(&mut self, message: impl FnOnce(u64) -> Message) {
+        self.seq += 1;
+        if self.tx.receiver_count() > 0 {
+            let _ = self.tx.send(message(self.seq));
+        }
+    }
+}
+
+/// Data store wrapper that numbers every write for replication.
+///
+/// Every [`write_batch()`](DataStore::write_batch) and
+/// [`set_point_configs()`](DataStore::set_point_configs) gets the next
+/// sequence number once the wrapped store accepted it; everything else is
+/// passed through. Writes are serialized so the sequence order is the
+/// order in which the wrapped store applied them.
+pub struct ReplicatedStore {
+    inner: Arc<dyn DataStore>,
+    feed: Mutex<Feed>,
+}
+
+impl ReplicatedStore {
+    /// Wrap `inner`, keeping up to `queue_capacity` writes per standby that
+    /// has not sent them yet.
+    pub fn new(inner: Arc<dyn DataStore>, queue_capacity: usize) -> Self {
+        let (tx, _) = broadcast::channel(queue_capacity.max(1));
+        Self {
+            inner,
+            feed: Mutex::new(Feed { seq: 0, tx }),
+        }
+    }
+
+    /// Sequence number of the last write (0 before the first).
+    pub async fn sequence(&self) -> u64 {
+        self.feed.lock().await.seq
+    }
+
+    /// Snapshot of the wrapped store, the sequence number of the last write
+    /// it contains and a receiver for the following ones.
+    ///
+    /// The snapshot is taken under the feed lock, so no write can land
+    /// between it and the subscription.
+    async fn subscribe(&self) -> Result<(u64, StoreSnapshot, broadcast::Receiver<Message>)> {
+        let feed = self.feed.lock().await;
+        let snapshot = self.inner.export_snapshot().await?;
+        Ok((feed.seq, snapshot, feed.tx.subscribe()))
+    }
+}
+
+impl std::fmt::Debug for ReplicatedStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReplicatedStore").finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl DataStore for ReplicatedStore {
+    async fn write_batch(&self, channel_id: u32, batch: &DataBatch) -> Result<()> {
+        let mut feed = self.feed.lock().await;
+        self.inner.write_batch(channel_id, batch).await?;
+        feed.publish(|seq| Message::Data {
+            seq,
+            channel_id,
+            batch: Arc::new(batch.clone()),
+        });
+        Ok(())
+    }
+
+    fn watch(&self, channel_id: u32, point_ids: &[PointId]) -> Result<PointWatch> {
+        self.inner.watch(channel_id, point_ids)
+    }
+
+    fn point_age(&self, channel_id: u32, point_id: PointId) -> Result<Option<PointAge>> {
+        self.inner.point_age(channel_id, point_id)
+    }
+
+    async fn read(&self, channel_id: u32, point_id: PointId) -> Result<Option<DataPoint>> {
+        self.inner.read(channel_id, point_id).await
+    }
+
+    async fn read_points(&self, channel_id: u32, point_ids: &[PointId]) -> Result<DataBatch> {
+        self.inner.read_points(channel_id, point_ids).await
+    }
+
+    async fn read_all(&self, channel_id: u32) -> Result<DataBatch> {
+        self.inner.read_all(channel_id).await
+    }
+
+    async fn snapshot(&self, channel_id: u32) -> Result<Snapshot> {
+        self.inner.snapshot(channel_id).await
+    }
+
+    async fn read_request(&self, channel_id: u32, request: &ReadRequest) -> Result<DataBatch> {
+        self.inner.read_request(channel_id, request).await
+    }
+
+    async fn read_history(
+        &self,
+        channel_id: u32,
+        point_id: PointId,
+        since: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<Vec<DataPoint>> {
+        self.inner
+            .read_history(channel_id, point_id, since, limit)
+            .await
+    }
+
+    async fn write_history(&self, channel_id: u32, samples: &DataBatch) -> Result<usize> {
+        self.inner.write_history(channel_id, samples).await
+    }
+
+    async fn channels(&self) -> Result<Vec<u32>> {
+        self.inner.channels().await
+    }
+
+    async fn set_point_configs(&self, channel_id: u32, points: &[PointConfig]) -> Result<()> {
+        let mut feed = self.feed.lock().await;
+        self.inner.set_point_configs(channel_id, points).await?;
+        feed.publish(|seq| Message::Points {
+            seq,
+            channel_id,
+            points: Arc::new(points.to_vec()),
+        });
+        Ok(())
+    }
+
+    async fn point_configs(&self, channel_id: u32) -> Result<Vec<PointConfig>> {
+        self.inner.point_configs(channel_id).await
+    }
+
+    async fn point_meta(&self, channel_id: u32, point_id: PointId) -> Result<Option<PointMeta>> {
+        self.inner.point_meta(channel_id, point_id).await
+    }
+
+    async fn last_known(&self, channel_id: u32) -> Result<DataBatch> {
+        self.inner.last_known(channel_id).await
+    }
+
+    async fn export_snapshot(&self) -> Result<StoreSnapshot> {
+        self.inner.export_snapshot().await
+    }
+
+    async fn append_audit(&self, records: &[CommandAudit]) -> Result<()> {
+        self.inner.append_audit(records).await
+    }
+
+    async fn query_audit(&self, query: &AuditQuery) -> Result<Vec<CommandAudit>> {
+        self.inner.query_audit(query).await
+    }
+
+    async fn prune_audit(&self, before: DateTime<Utc>) -> Result<usize> {
+        self.inner.prune_audit(before).await
+    }
+
+    async fn save_override(&self, point_override: &PointOverride) -> Result<()> {
+        self.inner.save_override(point_override).await
+    }
+
+    async fn delete_override(&self, channel_id: u32, point_id: PointId) -> Result<()> {
+        self.inner.delete_override(channel_id, point_id).await
+    }
+
+    async fn load_overrides(&self) -> Result<Vec<PointOverride>> {
+        self.inner.load_overrides().await
+    }
+
+    async fn flush(&self) -> Result<()> {
+        self.inner.flush().await
+    }
+}
+
+/// Either end of a running replication link.
+#[derive(Debug)]
+pub enum ReplicationLink {
+    /// Streaming this gateway's store to standbys.
+    Primary(Primary),
+    /// Following a primary.
+    Standby(Standby),
+}
+
+/// Start the configured end of the replication link for `store`.
+///
+/// Returns the store the gateway must use: the [`ReplicatedStore`] wrapping
+/// `store` on the primary, `store` itself on the standby.
+pub async fn start(
+    config: &ReplicationConfig,
+    store: Arc<dyn DataStore>,
+) -> Result<(Arc<dyn DataStore>, ReplicationLink)> {
+    Ok(match config.role {
+        ReplicationRole::Primary => {
+            let store = Arc::new(ReplicatedStore::new(store, config.queue_capacity));
+            let primary = Primary::start(config, Arc::clone(&store)).await?;
+            (store, ReplicationLink::Primary(primary))
+        }
+        ReplicationRole::Standby => {
+            let standby = Standby::start(config, Arc::clone(&store));
+            (store, ReplicationLink::Standby(standby))
+        }
+    })
+}
+
+/// Primary end of the link: serves the writes of a [`ReplicatedStore`] to
+/// connecting standbys.
+///
+/// Dropping it closes the listener and every standby connection.
+#[derive(Debug)]
+pub struct Primary {
+    local_addr: SocketAddr,
+    task: JoinHandle<()>,
+}
+
+impl Primary {
+    /// Listen on the configured address. Must be called from within a Tokio
+    /// runtime.
+    pub async fn start(config: &ReplicationConfig, store: Arc<ReplicatedStore>) -> Result<Self> {
+        let listener = TcpListener::bind(&config.address).await.map_err(|e| {
+            GatewayError::Config(format!(
+                "replication: cannot bind {}: {}",
+                config.address, e
+            ))
+        })?;
+        let link = Link {
+            token: config.token.clone(),
+            heartbeat: Duration::from_millis(config.heartbeat_interval_ms.max(1)),
+        };
+        Ok(Self {
+            local_addr: listener.local_addr()?,
+            task: tokio::spawn(accept(listener, store, Arc::new(link))),
+        })
+    }
+
+    /// Address the primary listens on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Close the listener and every standby connection.
+    pub async fn stop(mut self) {
+        self.task.abort();
+        let _ = (&mut self.task).await;
+    }
+}
+
+impl Drop for Primary {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Settings of the primary shared by its connections.
+#[derive(Debug)]
+struct Link {
+    token: String,
+    heartbeat: Duration,
+}
+
+/// Accept standbys until aborted, which also ends their connections.
+async fn accept(listener: TcpListener, store: Arc<ReplicatedStore>, link: Arc<Link>) {
+    let mut connections = JoinSet::new();
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _peer)) => {
+                    #[cfg(feature = "tracing-support")]
+                    tracing::info!(peer = %_peer, "Replication standby connected");
+                    let store = Arc::clone(&store);
+                    let link = Arc::clone(&link);
+                    connections.spawn(async move {
+                        let _result = serve_standby(&store, stream, &link).await;
+                        #[cfg(feature = "tracing-support")]
+                        match _result {
+                            Ok(()) => tracing::info!(peer = %_peer, "Replication standby disconnected"),
+                            Err(e) => tracing::warn!(peer = %_peer, "Replication standby dropped: {}", e),
+                        }
+                    });
+                }
+                Err(_e) => {
+                    #[cfg(feature = "tracing-support")]
+                    tracing::warn!("Replication accept failed: {}", _e);
+                }
+            },
+            Some(_) = connections.join_next(), if !connections.is_empty() => {}
+        }
+    }
+}
+
+/// Check the standby's token, then send the snapshot, every write and a
+/// heartbeat per interval.
+///
+/// Ends with an error when the standby lags behind the queue, so it
+/// reconnects and resynchronizes.
+async fn serve_standby(store: &ReplicatedStore, stream: TcpStream, link: &Link) -> Result<()> {
+    let _ = stream.set_nodelay(true);
+    let (reader, writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    let mut writer = BufWriter::new(writer);
+    let hello: Hello = receive(&mut lines, link.heartbeat * SILENT_HEARTBEATS).await?;
+    if !token_matches(&hello.token, &link.token) {
+        return Err(GatewayError::PermissionDenied(
+            "replication: standby presented a wrong token".into(),
+        ));
+    }
+    send(
+        &mut writer,
+        &Hello {
+            token: link.token.clone(),
+        },
+    )
+    .await?;
+
+    let heartbeat = link.heartbeat;
+    let (mut seq, snapshot, mut rx) = store.subscribe().await?;
+    send(&mut writer, &Message::Snapshot { seq, snapshot }).await?;
+
+    let mut ticker = tokio::time::interval(heartbeat);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    ticker.tick().await;
+    loop {
+        tokio::select! {
+            message = rx.recv() => match message {
+                Ok(message) => {
+                    seq = message.seq();
+                    send(&mut writer, &message).await?;
+                }
+                Err(RecvError::Lagged(missed)) => {
+                    return Err(GatewayError::Internal(format!(
+                        "standby fell {} writes behind after write {}",
+                        missed, seq
+                    )));
+                }
+                Err(RecvError::Closed) => return Ok(()),
+            },
+            _ = ticker.tick() => send(&mut writer, &Message::Heartbeat { seq }).await?,
+        }
+    }
+}
+
+async fn send(writer: &mut (impl AsyncWrite + Unpin), message: &impl Serialize) -> Result<()> {
+    let mut line = serde_json::to_vec(message)
+        .map_err(|e| GatewayError::InvalidData(format!("replication message: {}", e)))?;
+    line.push(b'\n');
+    writer.write_all(&line).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// State of a [`Standby`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ReplicationStats {
+    /// Whether the standby is following the primary.
+    pub connected: bool,
+    /// Sequence number of the last write applied.
+    pub last_seq: u64,
+    /// Snapshots applied, one per connection.
+    pub resyncs: u64,
+    /// Missing sequence numbers detected.
+    pub gaps: u64,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    connected: AtomicBool,
+    last_seq: AtomicU64,
+    resyncs: AtomicU64,
+    gaps: AtomicU64,
+}
+
+/// Standby end of the link: applies the primary's writes to a store.
+///
+/// Dropping it disconnects from the primary.
+#[derive(Debug)]
+pub struct Standby {
+    counters: Arc<Counters>,
+    task: JoinHandle<()>,
+}
+
+impl Standby {
+    /// Connect to the configured primary, reconnecting with backoff until
+    /// stopped. Must be called from within a Tokio runtime.
+    pub fn start(config: &ReplicationConfig, store: Arc<dyn DataStore>) -> Self {
+        let counters = Arc::new(Counters::default());
+        let silence =
+            Duration::from_millis(config.heartbeat_interval_ms.max(1)) * SILENT_HEARTBEATS;
+        Self {
+            task: tokio::spawn(follow_primary(
+                config.address.clone(),
+                config.token.clone(),
+                store,
+                silence,
+                Arc::clone(&counters),
+            )),
+            counters,
+        }
+    }
+
+    /// Current state of the link.
+    pub fn stats(&self) -> ReplicationStats {
+        ReplicationStats {
+            connected: self.counters.connected.load(Ordering::Relaxed),
+            last_seq: self.counters.last_seq.load(Ordering::Relaxed),
+            resyncs: self.counters.resyncs.load(Ordering::Relaxed),
+            gaps: self.counters.gaps.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Disconnect from the primary.
+    pub async fn stop(mut self) {
+        self.task.abort();
+        let _ = (&mut self.task).await;
+    }
+}
+
+impl Drop for Standby {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Connect, follow and reconnect until aborted.
+async fn follow_primary(
+    address: String,
+    token: String,
+    store: Arc<dyn DataStore>,
+    silence: Duration,
+    counters: Arc<Counters>,
+) {
+    let mut delay = RECONNECT_MIN;
+    loop {
+        match TcpStream::connect(&address).await {
+            Ok(stream) => {
+                let resyncs = counters.resyncs.load(Ordering::Relaxed);
+                let _result = follow(stream, &token, store.as_ref(), silence, &counters).await;
+                counters.connected.store(false, Ordering::Relaxed);
+                #[cfg(feature = "tracing-support")]
+                if let Err(e) = &_result {
+                    tracing::warn!(primary = %address, "Replication link lost: {}", e);
+                }
+                // A link that got as far as its snapshot reconnects at once.
+                if counters.resyncs.load(Ordering::Relaxed) > resyncs {
+                    delay = RECONNECT_MIN;
+                    continue;
+                }
+            }
+            Err(_e) => {
+                #[cfg(feature = "tracing-support")]
+                tracing::debug!(primary = %address, "Replication connect failed: {}", _e);
+            }
+        }
+        tokio::time::sleep(delay).await;
+        delay = (delay * 2).min(RECONNECT_MAX);
+    }
+}
+
+/// Exchange tokens, then apply the snapshot and the following writes of
+/// one connection.
+async fn follow(
+    stream: TcpStream,
+    token: &str,
+    store: &dyn DataStore,
+    silence: Duration,
+    counters: &Counters,
+) -> Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    send(
+        &mut writer,
+        &Hello {
+            token: token.to_string(),
+        },
+    )
+    .await?;
+    let hello: Hello = receive(&mut lines, silence).await?;
+    if !token_matches(&hello.token, token) {
+        return Err(GatewayError::PermissionDenied(
+            "replication: primary presented a wrong token".into(),
+        ));
+    }
+
+    let (mut last, snapshot) = match receive(&mut lines, silence).await? {
+        Message::Snapshot { seq, snapshot } => (seq, snapshot),
+        other => {
+            return Err(GatewayError::Protocol(format!(
+                "replication: expected a snapshot, got write {}",
+                other.seq()
+            )))
+        }
+    };
+    apply_snapshot(store, &snapshot).await?;
+    counters.last_seq.store(last, Ordering::Relaxed);
+    counters.resyncs.fetch_add(1, Ordering::Relaxed);
+    counters.connected.store(true, Ordering::Relaxed);
+
+    loop {
+        let message = receive(&mut lines, silence).await?;
+        let expected = match message {
+            Message::Heartbeat { .. } => last,
+            _ => last + 1,
+        };
+        if message.seq() != expected {
+            counters.gaps.fetch_add(1, Ordering::Relaxed);
+            return Err(GatewayError::Protocol(format!(
+                "replication gap: expected write {}, got {}",
+                expected,
+                message.seq()
+            )));
+        }
+        match message {
+            Message::Data {
+                channel_id, batch, ..
+            } => {
+                let batch: DataBatch = batch.iter().map(replicated).collect();
+                store.write_batch(channel_id, &batch).await?;
+            }
+            Message::Points {
+                channel_id, points, ..
+            } => store.set_point_configs(channel_id, &points).await?,
+            Message::Heartbeat { .. } => continue,
+            Message::Snapshot { .. } => {
+                return Err(GatewayError::Protocol(
+                    "replication: unexpected snapshot".into(),
+                ))
+            }
+        }
+        last = expected;
+        counters.last_seq.store(last, Ordering::Relaxed);
+    }
+}
+
+/// Next message, failing if the other end stays silent for `silence`.
+async fn receive<T: DeserializeOwned>(
+    lines: &mut Lines<BufReader<OwnedReadHalf>>,
+    silence: Duration,
+) -> Result<T> {
+    let line = timeout(silence, lines.next_line())
+        .await
+        .map_err(|_| GatewayError::ReadTimeout)??
+        .ok_or(GatewayError::ChannelClosed)?;
+    serde_json::from_str(&line)
+        .map_err(|e| GatewayError::InvalidData(format!("replication message: {}", e)))
+}
+
+/// Replace the standby's values and point configurations with the
+/// snapshot's.
+async fn apply_snapshot(store: &dyn DataStore, snapshot: &StoreSnapshot) -> Result<()> {
+    snapshot.check_compatible()?;
+    for channel in &snapshot.channels {
+        if !channel.points.is_empty() {
+            store
+                .set_point_configs(channel.channel_id, &channel.points)
+                .await?;
+        }
+        let batch: DataBatch = channel.values.iter().map(replicated).collect();
+        if !batch.is_empty() {
+            store.write_batch(channel.channel_id, &batch).await?;
+        }
+    }
+    Ok(())
+}
+
+/// A primary's value as stored by the standby.
+fn replicated(point: &DataPoint) -> DataPoint {
+    DataPoint {
+        quality: point.quality.worst(Quality::Substituted),
+        ..point.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::data::Value;
+    use crate::core::point::{ProtocolAddress, VirtualAddress};
+    use crate::store::MemoryStore;
+
+    fn batch(points: Vec<DataPoint>) -> DataBatch {
+        points.into_iter().collect()
+    }
+
+    const TOKEN: &str = "replication-secret";
+
+    fn hello(token: &str) -> String {
+        serde_json::to_string(&Hello {
+            token: token.into(),
+        })
+        .unwrap()
+    }
+
+    fn config(role: ReplicationRole, address: impl Into<String>) -> ReplicationConfig {
+        ReplicationConfig {
+            heartbeat_interval_ms: 50,
+            ..ReplicationConfig::new(role, address, TOKEN)
+        }
+    }
+
+    async fn eventually(mut check: impl AsyncFnMut() -> bool) {
+        for _ in 0..200 {
+            if check().await {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("condition not reached");
+    }
+
+    async fn value(store: &dyn DataStore, point_id: PointId) -> Option<DataPoint> {
+        store.read(1, point_id).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_then_stream() {
+        let primary_store = Arc::new(ReplicatedStore::new(Arc::new(MemoryStore::new()), 64));
+        primary_store
+            .set_point_configs(
+                1,
+                &[PointConfig::new(
+                    1001,
+                    ProtocolAddress::Virtual(VirtualAddress::new("t")),
+                )],
+            )
+            .await
+            .unwrap();
+        primary_store
+            .write_batch(1, &batch(vec![DataPoint::new(1001, 1.5)]))
+            .await
+            .unwrap();
+        assert_eq!(primary_store.sequence().await, 2);
+
+        let primary = Primary::start(
+            &config(ReplicationRole::Primary, "127.0.0.1:0"),
+            Arc::clone(&primary_store),
+        )
+        .await
+        .unwrap();
+        let standby_store: Arc<dyn DataStore> = Arc::new(MemoryStore::new());
+        let standby = Standby::start(
+            &config(ReplicationRole::Standby, primary.local_addr().to_string()),
+            Arc::clone(&standby_store),
+        );
+
+        // Written before the standby connected: arrives with the snapshot.
+        eventually(async || standby.stats().connected).await;
+        let point = value(standby_store.as_ref(), 1001).await.unwrap();
+        assert_eq!(point.value, Value::Float(1.5));
+        assert_eq!(point.quality, Quality::Substituted);
+        assert_eq!(standby_store.point_configs(1).await.unwrap().len(), 1);
+        assert_eq!(standby.stats().last_seq, 2);
+
+        // Written afterwards: streamed, keeping non-good qualities.
+        primary_store
+            .write_batch(
+                1,
+                &batch(vec![
+                    DataPoint::new(1001, 2.5),
+                    DataPoint::new(1002, 0.0).with_quality(Quality::CommFailure),
+                ]),
+            )
+            .await
+            .unwrap();
+        eventually(async || standby.stats().last_seq == 3).await;
+        assert_eq!(
+            value(standby_store.as_ref(), 1001).await.unwrap().value,
+            Value::Float(2.5)
+        );
+        assert_eq!(
+            value(standby_store.as_ref(), 1002).await.unwrap().quality,
+            Quality::CommFailure
+        );
+
+        // Heartbeats keep an idle link up.
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        let stats = standby.stats();
+        assert!(stats.connected);
+        assert_eq!((stats.resyncs, stats.gaps), (1, 0));
+    }
+
+    #[tokio::test]
+    async fn test_primary_wins_on_resync() {
+        let primary_store = Arc::new(ReplicatedStore::new(Arc::new(MemoryStore::new()), 64));
+        primary_store
+            .write_batch(1, &batch(vec![DataPoint::new(1001, 1.0)]))
+            .await
+            .unwrap();
+        let standby_store: Arc<dyn DataStore> = Arc::new(MemoryStore::new());
+        // Newer than the primary's value, but the primary wins.
+        standby_store
+            .write_batch(1, &batch(vec![DataPoint::new(1001, 9.0)]))
+            .await
+            .unwrap();
+
+        let primary = Primary::start(
+            &config(ReplicationRole::Primary, "127.0.0.1:0"),
+            Arc::clone(&primary_store),
+        )
+        .await
+        .unwrap();
+        let standby = Standby::start(
+            &config(ReplicationRole::Standby, primary.local_addr().to_string()),
+            Arc::clone(&standby_store),
+        );
+        eventually(async || standby.stats().connected).await;
+        assert_eq!(
+            value(standby_store.as_ref(), 1001).await.unwrap().value,
+            Value::Float(1.0)
+        );
+    }
+
+    /// Fake primary sending `lines` on every connection, then staying open.
+    async fn fake_primary(lines: Vec<String>) -> (SocketAddr, JoinHandle<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let task = tokio::spawn(async move {
+            let mut open = Vec::new();
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                for line in &lines {
+                    stream.write_all(line.as_bytes()).await.unwrap();
+                    stream.write_all(b"\n").await.unwrap();
+                }
+                open.push(stream);
+            }
+        });
+        (addr, task)
+    }
+
+    fn line(message: &Message) -> String {
+        serde_json::to_string(message).unwrap()
+    }
+
+    fn data(seq: u64, point_id: PointId, value: f64) -> Message {
+        Message::Data {
+            seq,
+            channel_id: 1,
+            batch: Arc::new(batch(vec![DataPoint::new(point_id, value)])),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_gap_resyncs() {
+        let snapshot = Message::Snapshot {
+            seq: 10,
+            snapshot: StoreSnapshot::new(Vec::new()),
+        };
+        // Write 12 follows write 10: write 11 went missing.
+        let (addr, _primary) = fake_primary(vec![
+            hello(TOKEN),
+            line(&snapshot),
+            line(&data(11, 1001, 1.0)),
+            line(&data(13, 1002, 2.0)),
+        ])
+        .await;
+        let store: Arc<dyn DataStore> = Arc::new(MemoryStore::new());
+        let standby = Standby::start(
+            &config(ReplicationRole::Standby, addr.to_string()),
+            Arc::clone(&store),
+        );
+
+        eventually(async || standby.stats().resyncs >= 2).await;
+        let stats = standby.stats();
+        assert!(stats.gaps >= 1);
+        assert!(value(store.as_ref(), 1001).await.is_some());
+        // The write after the gap is never applied.
+        assert!(value(store.as_ref(), 1002).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_gap_resyncs() {
+        let snapshot = Message::Snapshot {
+            seq: 5,
+            snapshot: StoreSnapshot::new(Vec::new()),
+        };
+        let (addr, _primary) = fake_primary(vec![
+            hello(TOKEN),
+            line(&snapshot),
+            line(&Message::Heartbeat { seq: 6 }),
+        ])
+        .await;
+        let standby = Standby::start(
+            &config(ReplicationRole::Standby, addr.to_string()),
+            Arc::new(MemoryStore::new()),
+        );
+        eventually(async || standby.stats().gaps >= 1).await;
+        eventually(async || standby.stats().resyncs >= 2).await;
+    }
+
+    #[tokio::test]
+    async fn test_silent_primary_resyncs() {
+        let snapshot = Message::Snapshot {
+            seq: 0,
+            snapshot: StoreSnapshot::new(Vec::new()),
+        };
+        let (addr, _primary) = fake_primary(vec![hello(TOKEN), line(&snapshot)]).await;
+        let standby = Standby::start(
+            &config(ReplicationRole::Standby, addr.to_string()),
+            Arc::new(MemoryStore::new()),
+        );
+        eventually(async || standby.stats().resyncs >= 2).await;
+        assert_eq!(standby.stats().gaps, 0);
+    }
+
+    #[tokio::test]
+    async fn test_primary_checks_token() {
+        let store = Arc::new(ReplicatedStore::new(Arc::new(MemoryStore::new()), 64));
+        store
+            .write_batch(1, &batch(vec![DataPoint::new(1001, 1.0)]))
+            .await
+            .unwrap();
+        let primary = Primary::start(&config(ReplicationRole::Primary, "127.0.0.1:0"), store)
+            .await
+            .unwrap();
+
+        // A wrong token or no hello at all: closed without a reply.
+        for first in [hello("guess"), line(&Message::Heartbeat { seq: 0 })] {
+            let mut stream = TcpStream::connect(primary.local_addr()).await.unwrap();
+            stream.write_all(first.as_bytes()).await.unwrap();
+            stream.write_all(b"\n").await.unwrap();
+            let mut lines = BufReader::new(stream).lines();
+            assert_eq!(lines.next_line().await.unwrap(), None);
+        }
+
+        let standby_store: Arc<dyn DataStore> = Arc::new(MemoryStore::new());
+        let standby = Standby::start(
+            &ReplicationConfig {
+                token: "guess".into(),
+                ..config(ReplicationRole::Standby, primary.local_addr().to_string())
+            },
+            Arc::clone(&standby_store),
+        );
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(standby.stats(), ReplicationStats::default());
+        assert!(value(standby_store.as_ref(), 1001).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_standby_checks_token() {
+        let snapshot = Message::Snapshot {
+            seq: 1,
+            snapshot: StoreSnapshot::new(Vec::new()),
+        };
+        let (addr, _primary) = fake_primary(vec![hello("guess"), line(&snapshot)]).await;
+        let standby = Standby::start(
+            &config(ReplicationRole::Standby, addr.to_string()),
+            Arc::new(MemoryStore::new()),
+        );
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(standby.stats(), ReplicationStats::default());
+    }
+
+    #[tokio::test]
+    async fn test_feed_lag() {
+        let store = ReplicatedStore::new(Arc::new(MemoryStore::new()), 1);
+        let (_, _, mut rx) = store.subscribe().await.unwrap();
+        for value in 0..3 {
+            store
+                .write_batch(1, &batch(vec![DataPoint::new(1001, value as f64)]))
+                .await
+                .unwrap();
+        }
+        assert!(matches!(rx.recv().await, Err(RecvError::Lagged(2))));
+        assert_eq!(rx.recv().await.unwrap().seq(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_snapshot() {
+        let store = ReplicatedStore::new(Arc::new(MemoryStore::new()), 8);
+        store
+            .write_batch(1, &batch(vec![DataPoint::new(1001, 1.0)]))
+            .await
+            .unwrap();
+        let (seq, snapshot, mut rx) = store.subscribe().await.unwrap();
+        assert_eq!(seq, 1);
+        assert_eq!(snapshot.channels[0].values.len(), 1);
+        store
+            .write_batch(1, &batch(vec![DataPoint::new(1002, 2.0)]))
+            .await
+            .unwrap();
+        assert_eq!(rx.recv().await.unwrap().seq(), 2);
+    }
+}
@@ -4,9 +4,16 @@
 //! that allows heterogeneous protocol channels to be managed uniformly.
 
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
 
-use crate::core::error::Result;
-use crate::core::traits::{DataEventReceiver, Diagnostics, PollResult};
+use crate::core::data::{DataBatch, PointId};
+use crate::core::error::{GatewayError, Result};
+use crate::core::point::PointConfig;
+use crate::core::traits::{
+    CommunicationMode, DataEventReceiver, Diagnostics, PollResult, ProtocolCapabilities,
+    WriteResult,
+};
 
 /// Object-safe wrapper for protocol channels.
 ///
@@ -34,6 +41,20 @@ pub trait ChannelRuntime: Send + Sync {
     /// Whether this channel is event-driven (vs polling).
     fn is_event_driven(&self) -> bool;
 
+    /// What the channel supports, for tooling to adapt to it.
+    ///
+    /// The default describes a writable client in the mode given by
+    /// `is_event_driven()`; the protocol wrappers report their channel's
+    /// [`ProtocolCapabilities`] and limits instead.
+    fn capabilities(&self) -> ChannelCapabilities {
+        let mode = if self.is_event_driven() {
+            CommunicationMode::EventDriven
+        } else {
+            CommunicationMode::Polling
+        };
+        ChannelCapabilities::new(self.protocol(), &[mode])
+    }
+
     // === Lifecycle ===
 
     /// Connect to the remote device/server.
@@ -50,10 +71,13 @@ pub trait ChannelRuntime: Send + Sync {
     async fn poll_once(&mut self) -> PollResult;
 
     /// Write control commands.
-    async fn write_control(&mut self, commands: &[(u32, f64)]) -> Result<usize>;
+    ///
+    /// The result lists the commands the device refused, so callers can
+    /// tell which of them failed.
+    async fn write_control(&mut self, commands: &[(u32, f64)]) -> Result<WriteResult>;
 
     /// Write adjustment commands.
-    async fn write_adjustment(&mut self, adjustments: &[(u32, f64)]) -> Result<usize>;
+    async fn write_adjustment(&mut self, adjustments: &[(u32, f64)]) -> Result<WriteResult>;
 
     // === Event-Driven Support ===
 
@@ -68,25 +92,161 @@ pub trait ChannelRuntime: Send + Sync {
     /// Stop event streaming (event-driven channels only).
     async fn stop_events(&mut self) -> Result<()>;
 
+    // === State Recovery ===
+
+    /// Pre-populate the channel's cache with last-known values.
+    ///
+    /// Called once before `connect()` with values from the data store. The
+    /// default implementation ignores them; channels that serve cached data
+    /// (virtual, J1939) override it.
+    async fn restore(&mut self, _batch: &DataBatch) {}
+
+    // === Reconfiguration ===
+
+    /// Replace the channel's points without reconnecting.
+    ///
+    /// Returns `GatewayError::Unsupported` (the default) if the channel has
+    /// to be rebuilt for point changes to take effect.
+    async fn update_points(&mut self, _points: &[PointConfig]) -> Result<()> {
+        Err(GatewayError::Unsupported(format!(
+            "{} channels cannot update points in place",
+            self.protocol()
+        )))
+    }
+
+    // === History ===
+
+    /// Samples of `points` the device recorded between `start` and `end`,
+    /// oldest first, for backfilling the store after an outage.
+    ///
+    /// Returns `GatewayError::Unsupported` (the default) if the protocol
+    /// has no historical access.
+    async fn read_history(
+        &mut self,
+        _points: &[PointId],
+        _start: DateTime<Utc>,
+        _end: DateTime<Utc>,
+    ) -> Result<DataBatch> {
+        Err(GatewayError::Unsupported(format!(
+            "{} channels cannot read history",
+            self.protocol()
+        )))
+    }
+
+    // === Refresh ===
+
+    /// Have the device report every point again, outside the channel's
+    /// schedule.
+    ///
+    /// Event-driven channels request the values (IEC 104 sends a general
+    /// interrogation, OPC UA reads all monitored nodes), which then arrive
+    /// as events. Polling channels prepare their next poll to return every
+    /// point (Modbus resets its deadbands). Returns
+    /// `GatewayError::Unsupported` (the default) if the protocol cannot
+    /// request a refresh.
+    async fn refresh(&mut self) -> Result<()> {
+        Err(GatewayError::Unsupported(format!(
+            "{} channels cannot be refreshed",
+            self.protocol()
+        )))
+    }
+
     // === Diagnostics ===
 
     /// Get channel diagnostics.
     async fn diagnostics(&self) -> Result<Diagnostics>;
+
+    /// Set the channel's diagnostics counters back to zero.
+    ///
+    /// Returns `GatewayError::Unsupported` (the default) if the channel's
+    /// counters cannot be reset.
+    async fn reset_diagnostics(&mut self) -> Result<()> {
+        Err(GatewayError::Unsupported(format!(
+            "{} channels cannot reset diagnostics",
+            self.protocol()
+        )))
+    }
+}
+
+/// Capabilities of a channel, as reported by
+/// [`ChannelRuntime::capabilities()`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChannelCapabilities {
+    /// Protocol name, e.g. `Modbus TCP`.
+    pub protocol: String,
+
+    /// Protocol version or profile.
+    pub version: String,
+
+    /// Supported communication modes.
+    pub modes: Vec<CommunicationMode>,
+
+    /// Whether the protocol can act as a client.
+    pub client: bool,
+
+    /// Whether the protocol can act as a server.
+    pub server: bool,
+
+    /// Whether the channel accepts control and adjustment commands.
+    pub writable: bool,
+
+    /// Protocol-specific limits, e.g. `max_registers_per_read` for Modbus.
+    #[serde(skip_serializing_if = "serde_json::Value::is_null")]
+    pub limits: serde_json::Value,
+}
+
+impl ChannelCapabilities {
+    /// Writable client of `protocol` (version `1.0`) supporting `modes`.
+    pub fn new(protocol: impl Into<String>, modes: &[CommunicationMode]) -> Self {
+        Self {
+            protocol: protocol.into(),
+            version: "1.0".into(),
+            modes: modes.to_vec(),
+            client: true,
+            server: false,
+            writable: true,
+            limits: serde_json::Value::Null,
+        }
+    }
+
+    /// Capabilities of a protocol implementation.
+    pub fn of<P: ProtocolCapabilities + ?Sized>(protocol: &P) -> Self {
+        Self {
+            version: protocol.version().into(),
+            client: protocol.supports_client(),
+            server: protocol.supports_server(),
+            ..Self::new(protocol.name(), protocol.supported_modes())
+        }
+    }
+
+    /// Mark the channel as not accepting commands.
+    #[must_use]
+    pub fn read_only(mut self) -> Self {
+        self.writable = false;
+        self
+    }
+
+    /// Set protocol-specific limits.
+    #[must_use]
+    pub fn with_limits(mut self, limits: serde_json::Value) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Whether the channel supports `mode`.
+    pub fn supports(&self, mode: CommunicationMode) -> bool {
+        self.modes.contains(&mode)
+    }
 }
 
 /// Channel communication mode.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum ChannelMode {
     /// Polling mode: data is fetched periodically via `poll_once()`.
+    #[default]
     Polling,
     /// Event-driven mode: data is pushed via `subscribe()`.
     EventDriven,
     /// Hybrid mode: both polling and event-driven.
     Hybrid,
 }
-
-impl Default for ChannelMode {
-    fn default() -> Self {
-        Self::Polling
-    }
-}
@@ -0,0 +1,270 @@
+//! Poll phases of channels sharing a bus.
+//!
+//! Channels polled at the same interval tick together, so the devices on
+//! one RS-485 trunk are all asked at once and answer one after the other.
+//! Channels naming the same `bus` are staggered instead: the i-th of the
+//! n enabled channels on a bus (in configuration order) polls at
+//! `i * interval / n`, unless it sets `poll_offset_ms`. Offsets count from
+//! an epoch shared by the whole runtime, so a channel polls at
+//! `epoch + offset + k * interval` however often it reconnects.
+//!
+//! Each bus counts the polls in flight on it and the most seen at once.
+//!
+//! With `max_concurrent_polls` set, every phase also carries the runtime's
+//! limit of connects and polls in flight: a channel takes a slot before
+//! either and waits while all are taken. A changed limit applies to the
+//! channels (re)started after the change.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::time::Instant;
+
+use super::config::ChannelConfig;
+
+/// Polls in flight on one bus.
+#[derive(Debug, Default)]
+pub(super) struct BusLoad {
+    active: AtomicU64,
+    max_concurrent: AtomicU64,
+}
+
+impl BusLoad {
+    /// Count a poll until the returned guard is dropped.
+    pub(super) fn enter(self: &Arc<Self>) -> BusPoll {
+        let active = self.active.fetch_add(1, Ordering::Relaxed) + 1;
+        self.max_concurrent.fetch_max(active, Ordering::Relaxed);
+        BusPoll(Arc::clone(self))
+    }
+
+    /// Most polls seen in flight at once.
+    pub(super) fn max_concurrent(&self) -> u64 {
+        self.max_concurrent.load(Ordering::Relaxed)
+    }
+}
+
+/// A poll in flight on a bus.
+pub(super) struct BusPoll(Arc<BusLoad>);
+
+impl Drop for BusPoll {
+    fn drop(&mut self) {
+        self.0.active.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// A slot under the runtime's limit of connects and polls in flight.
+pub(super) struct PollSlot {
+    _permit: Option<OwnedSemaphorePermit>,
+    /// Whether the channel had to wait for it.
+    pub(super) waited: bool,
+}
+
+/// When a channel polls, the bus it shares and the runtime's limit of
+/// polls in flight.
+#[derive(Debug, Clone, Default)]
+pub(super) struct PollPhase {
+    /// Instant of a poll (`epoch + offset`); `None`: poll from connect on.
+    anchor: Option<Instant>,
+    offset: Duration,
+    bus: Option<Arc<BusLoad>>,
+    limit: Option<Arc<Semaphore>>,
+}
+
+impl PollPhase {
+    /// Offset within the interval, if the channel is phased.
+    pub(super) fn offset(&self) -> Option<Duration> {
+        self.anchor.map(|_| self.offset)
+    }
+
+    /// The channel's bus.
+    pub(super) fn bus(&self) -> Option<&Arc<BusLoad>> {
+        self.bus.as_ref()
+    }
+
+    /// Take a slot for a connect or poll, waiting while all are taken.
+    ///
+    /// The slot is held until it is dropped.
+    pub(super) async fn acquire(&self) -> PollSlot {
+        let Some(limit) = &self.limit else {
+            return PollSlot {
+                _permit: None,
+                waited: false,
+            };
+        };
+        if let Ok(permit) = Arc::clone(limit).try_acquire_owned() {
+            return PollSlot {
+                _permit: Some(permit),
+                waited: false,
+            };
+        }
+        PollSlot {
+            // The semaphore is never closed
+            _permit: Arc::clone(limit).acquire_owned().await.ok(),
+            waited: true,
+        }
+    }
+
+    /// First poll instant at or after `now` (`None` if not phased).
+    pub(super) fn next_poll(&self, now: Instant, interval: Duration) -> Option<Instant> {
+        let anchor = self.anchor?;
+        if now <= anchor {
+            return Some(anchor);
+        }
+        let interval = interval.as_nanos().max(1);
+        let cycles = (now - anchor).as_nanos().div_ceil(interval);
+        let since = u64::try_from(cycles * interval).unwrap_or(u64::MAX);
+        Some(anchor + Duration::from_nanos(since))
+    }
+}
+
+/// Bus membership and load of a runtime's channels.
+#[derive(Debug, Clone)]
+pub(super) struct Buses(Arc<Mutex<BusState>>);
+
+#[derive(Debug)]
+struct BusState {
+    epoch: Instant,
+    /// Enabled channel ids by bus, in configuration order.
+    members: HashMap<String, Vec<u32>>,
+    loads: HashMap<String, Arc<BusLoad>>,
+    /// Limit of connects and polls in flight, and its slots.
+    limit: Option<(usize, Arc<Semaphore>)>,
+}
+
+impl Default for Buses {
+    fn default() -> Self {
+        Self(Arc::new(Mutex::new(BusState {
+            epoch: Instant::now(),
+            members: HashMap::new(),
+            loads: HashMap::new(),
+            limit: None,
+        })))
+    }
+}
+
+impl Buses {
+    /// Set the members of every bus. Load counters of buses still in use
+    /// are kept.
+    pub(super) fn configure<'a>(&self, channels: impl IntoIterator<Item = &'a ChannelConfig>) {
+        let mut members: HashMap<String, Vec<u32>> = HashMap::new();
+        for channel in channels {
+            if let Some(bus) = &channel.bus {
+                members.entry(bus.clone()).or_default().push(channel.id);
+            }
+        }
+        let mut state = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        state.loads.retain(|bus, _| members.contains_key(bus));
+        state.members = members;
+    }
+
+    /// Set the limit of connects and polls in flight (`None`: unlimited).
+    /// Slots taken under an unchanged limit stay valid.
+    pub(super) fn set_limit(&self, max_concurrent_polls: Option<usize>) {
+        let mut state = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        if state.limit.as_ref().map(|(max, _)| *max) != max_concurrent_polls {
+            state.limit = max_concurrent_polls.map(|max| (max, Arc::new(Semaphore::new(max))));
+        }
+    }
+
+    /// Phase of a channel polled every `interval`.
+    pub(super) fn phase(&self, config: &ChannelConfig, interval: Duration) -> PollPhase {
+        let mut state = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        let limit = state.limit.as_ref().map(|(_, slots)| Arc::clone(slots));
+        if config.bus.is_none() && config.poll_offset_ms.is_none() {
+            return PollPhase {
+                limit,
+                ..PollPhase::default()
+            };
+        }
+        let offset = match (config.poll_offset_ms, &config.bus) {
+            (Some(ms), _) => Duration::from_millis(ms),
+            (None, Some(bus)) => {
+                let members = state.members.get(bus).map_or(&[][..], Vec::as_slice);
+                let index = members.iter().position(|&id| id == config.id).unwrap_or(0);
+                let count = members.len().max(1);
+                interval * index as u32 / count as u32
+            }
+            (None, None) => Duration::ZERO,
+        };
+        let bus = config
+            .bus
+            .as_ref()
+            .map(|bus| Arc::clone(state.loads.entry(bus.clone()).or_default()));
+        PollPhase {
+            anchor: Some(state.epoch + offset),
+            offset,
+            bus,
+            limit,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channel(id: u32, bus: Option<&str>, poll_offset_ms: Option<u64>) -> ChannelConfig {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "name": format!("ch{}", id),
+            "protocol": "virtual",
+            "bus": bus,
+            "poll_offset_ms": poll_offset_ms
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_phases() {
+        let channels = [
+            channel(1, Some("trunk"), None),
+            channel(2, None, None),
+            channel(3, Some("trunk"), None),
+            channel(4, Some("trunk"), Some(10)),
+            channel(5, None, Some(300)),
+        ];
+        let buses = Buses::default();
+        buses.configure(&channels);
+        let interval = Duration::from_millis(900);
+        let offsets: Vec<Option<u64>> = channels
+            .iter()
+            .map(|c| {
+                let offset = buses.phase(c, interval).offset()?;
+                Some(offset.as_millis() as u64)
+            })
+            .collect();
+        assert_eq!(offsets, vec![Some(0), None, Some(300), Some(10), Some(300)]);
+
+        let trunk = buses.phase(&channels[0], interval);
+        assert!(Arc::ptr_eq(
+            trunk.bus().unwrap(),
+            buses.phase(&channels[3], interval).bus().unwrap()
+        ));
+        assert!(buses.phase(&channels[4], interval).bus().is_none());
+
+        // Polls stay on the grid of the epoch
+        let phase = buses.phase(&channels[2], interval);
+        let epoch = Instant::now();
+        let at = |ms: u64| epoch + Duration::from_millis(ms);
+        assert_eq!(phase.next_poll(epoch, interval), Some(at(300)));
+        assert_eq!(phase.next_poll(at(300), interval), Some(at(300)));
+        assert_eq!(phase.next_poll(at(301), interval), Some(at(1200)));
+        assert_eq!(phase.next_poll(at(5000), interval), Some(at(5700)));
+        assert_eq!(PollPhase::default().next_poll(epoch, interval), None);
+    }
+
+    #[test]
+    fn test_bus_load() {
+        let load = Arc::new(BusLoad::default());
+        let first = load.enter();
+        let second = load.enter();
+        drop(first);
+        drop(second);
+        let _third = load.enter();
+        assert_eq!(load.max_concurrent(), 2);
+        assert_eq!(load.active.load(Ordering::Relaxed), 1);
+    }
+}
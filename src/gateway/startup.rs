@@ -0,0 +1,137 @@
+//! Startup dependencies between channels.
+//!
+//! A channel listing other channels in `depends_on` connects only once all
+//! of them are connected and its `startup_delay_ms` has passed, e.g. Modbus
+//! devices powered up through a GPIO output:
+//!
+//! ```toml
+//! [[channels]]
+//! id = 2
+//! protocol = "modbus"
+//! depends_on = [1]
+//! startup_delay_ms = 10000
+//! ```
+//!
+//! [`GatewayRuntime`](super::GatewayRuntime) starts the channels in
+//! dependency order and shuts them down in reverse order, one
+//! [level](levels) at a time; channels within a level do not depend on
+//! each other and start and stop concurrently. A channel still waiting
+//! after `dependency_timeout_ms` reports which dependencies are missing
+//! (logged and written as a `channel_error` JSON Lines event) and connects
+//! anyway.
+
+use std::collections::{HashMap, HashSet};
+
+use super::config::ChannelConfig;
+
+/// Group `channels` into levels: each channel's dependencies are in
+/// earlier levels. Dependencies on channels not in `channels` are ignored.
+///
+/// Fails with a dependency cycle, as the channel ids along it with the
+/// first repeated at the end (e.g. `[1, 2, 1]`).
+pub(crate) fn levels<'a>(
+    channels: impl IntoIterator<Item = &'a ChannelConfig>,
+) -> Result<Vec<Vec<u32>>, Vec<u32>> {
+    let channels: Vec<&ChannelConfig> = channels.into_iter().collect();
+    let ids: HashSet<u32> = channels.iter().map(|c| c.id).collect();
+    let mut pending: HashMap<u32, Vec<u32>> = channels
+        .iter()
+        .map(|c| {
+            let depends_on = c
+                .depends_on
+                .iter()
+                .copied()
+                .filter(|id| ids.contains(id))
+                .collect();
+            (c.id, depends_on)
+        })
+        .collect();
+
+    let mut levels = Vec::new();
+    while !pending.is_empty() {
+        let mut level: Vec<u32> = pending
+            .iter()
+            .filter(|(_, depends_on)| depends_on.iter().all(|id| !pending.contains_key(id)))
+            .map(|(&id, _)| id)
+            .collect();
+        if level.is_empty() {
+            return Err(cycle(&pending));
+        }
+        level.sort_unstable();
+        for id in &level {
+            pending.remove(id);
+        }
+        levels.push(level);
+    }
+    Ok(levels)
+}
+
+/// Problem report for a cycle returned by [`levels()`].
+pub(crate) fn cycle_message(cycle: &[u32]) -> String {
+    let path: Vec<String> = cycle.iter().map(u32::to_string).collect();
+    format!("depends_on forms a cycle: {}", path.join(" -> "))
+}
+
+/// A cycle among `pending` channels, each of which has a pending
+/// dependency.
+fn cycle(pending: &HashMap<u32, Vec<u32>>) -> Vec<u32> {
+    let mut path = vec![*pending.keys().min().expect("pending channels")];
+    loop {
+        let last = path[path.len() - 1];
+        let next = pending[&last]
+            .iter()
+            .copied()
+            .filter(|id| pending.contains_key(id))
+            .min()
+            .expect("pending dependency");
+        if let Some(start) = path.iter().position(|&id| id == next) {
+            let mut cycle = path.split_off(start);
+            cycle.push(next);
+            return cycle;
+        }
+        path.push(next);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channel(id: u32, depends_on: &[u32]) -> ChannelConfig {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "name": format!("ch{}", id),
+            "protocol": "virtual",
+            "depends_on": depends_on
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_levels() {
+        let channels = [
+            channel(3, &[1, 2]),
+            channel(1, &[]),
+            channel(2, &[1]),
+            channel(4, &[]),
+            channel(5, &[9]),
+        ];
+        assert_eq!(
+            levels(&channels).unwrap(),
+            vec![vec![1, 4, 5], vec![2], vec![3]]
+        );
+        assert_eq!(levels(&[]).unwrap(), Vec::<Vec<u32>>::new());
+    }
+
+    #[test]
+    fn test_cycles() {
+        let channels = [
+            channel(1, &[]),
+            channel(2, &[3]),
+            channel(3, &[4]),
+            channel(4, &[2, 1]),
+        ];
+        assert_eq!(levels(&channels).unwrap_err(), vec![2, 3, 4, 2]);
+        assert_eq!(levels(&[channel(7, &[7])]).unwrap_err(), vec![7, 7]);
+    }
+}
@@ -0,0 +1,415 @@
+//! Device templates.
+//!
+//! Sites with many identical devices define the point list once in a
+//! `[templates.<name>]` section and let each channel reference it:
+//!
+//! ```toml
+//! [templates.sungrow_sg110]
+//! parameters = { port = 502, timeout_ms = 1000 }
+//!
+//! [[templates.sungrow_sg110.points]]
+//! id = 1
+//! name = "Active power"
+//! address = "5031"
+//!
+//! [templates.sungrow_sg110.points.transform]
+//! scale = 0.1
+//!
+//! [[channels]]
+//! id = 7
+//! name = "Inverter 7"
+//! protocol = "modbus"
+//! template = "sungrow_sg110"
+//! parameters = { host = "10.0.0.7" }
+//!
+//! [channels.template_overrides]
+//! point_id_offset = 7000
+//! address_prefix = "1:"
+//! transforms = { "1" = { scale = 0.01 } }
+//! ```
+//!
+//! [`GatewayConfig::expand_templates()`] turns such a channel into a plain
+//! channel, so everything downstream (validation, the channel factory, the
+//! runtime) only sees ordinary point lists. The expanded channel gets:
+//!
+//! 1. **Points**: the template's points in template order, each with
+//!    `point_id_offset` added to its id, `address_prefix` put in front of its
+//!    address and the fields listed under `transforms."<template point id>"`
+//!    replacing those of its transform (other transform fields keep the
+//!    template's values). Alarm signal ids are offset like point ids. The
+//!    channel's own `points` follow. A point id
+//!    used twice, e.g. a channel point colliding with an offset template
+//!    point, is a validation error.
+//! 2. **Parameters**: the template's `parameters` with the channel's
+//!    `parameters` laid over them; the channel wins on every key it sets,
+//!    nested tables are merged key by key.
+//!
+//! Everything else (protocol, poll interval, safe state, ...) comes from the
+//! channel alone.
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::alarm::{AlarmConfig, AlarmLimit};
+use crate::core::data::PointId;
+use crate::core::point::TransformConfig;
+
+use super::config::{ChannelConfig, FeedbackDef, GatewayConfig, PointDef};
+use super::validate::ValidationError;
+
+/// Reusable point list and default parameters for identical devices.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct DeviceTemplate {
+    /// Default protocol parameters; the channel's own parameters take
+    /// precedence.
+    #[serde(default, skip_serializing_if = "serde_json::Value::is_null")]
+    pub parameters: serde_json::Value,
+
+    /// Point definitions, with ids and addresses before the channel's
+    /// overrides are applied.
+    #[serde(default)]
+    pub points: Vec<PointDef>,
+}
+
+/// Per-channel adjustments of a referenced template's points.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct TemplateOverrides {
+    /// Added to the id of every template point.
+    #[serde(default)]
+    pub point_id_offset: PointId,
+
+    /// Put in front of the address of every template point (e.g. `"7:"` for
+    /// a Modbus slave id).
+    #[serde(default)]
+    pub address_prefix: String,
+
+    /// Transform fields to replace, keyed by template point id.
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub transforms: serde_json::Map<String, serde_json::Value>,
+}
+
+impl TemplateOverrides {
+    /// Whether no override is set.
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+impl GatewayConfig {
+    /// Expand every channel referencing a template into a plain channel (see
+    /// [`template`](self) for the precedence rules) and return the problems
+    /// found.
+    ///
+    /// Channels whose template is unknown or whose overrides do not apply
+    /// are left unchanged; [`validate()`](Self::validate) reports them as
+    /// well.
+    pub fn expand_templates(&mut self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        for channel in &mut self.channels {
+            let Some(name) = &channel.template else {
+                continue;
+            };
+            let Some(template) = self.templates.get(name) else {
+                let mut defined: Vec<&str> = self.templates.keys().map(String::as_str).collect();
+                defined.sort_unstable();
+                errors.push(ValidationError::channel(
+                    channel.id,
+                    format!(
+                        "unknown template '{}' (defined: {})",
+                        name,
+                        if defined.is_empty() {
+                            "none".into()
+                        } else {
+                            defined.join(", ")
+                        }
+                    ),
+                ));
+                continue;
+            };
+            match expand(channel, template) {
+                Ok(expanded) => *channel = expanded,
+                Err(problems) => errors.extend(problems),
+            }
+        }
+        errors
+    }
+}
+
+/// The plain channel for `channel` using `template`.
+fn expand(
+    channel: &ChannelConfig,
+    template: &DeviceTemplate,
+) -> Result<ChannelConfig, Vec<ValidationError>> {
+    let overrides = &channel.template_overrides;
+    let name = channel.template.as_deref().unwrap_or_default();
+    let mut errors = Vec::new();
+
+    for key in overrides.transforms.keys() {
+        let known = key
+            .trim()
+            .parse::<PointId>()
+            .is_ok_and(|id| template.points.iter().any(|p| p.id == id));
+        if !known {
+            errors.push(ValidationError::channel(
+                channel.id,
+                format!(
+                    "template_overrides.transforms: '{}' is not a point id of template '{}'",
+                    key, name
+                ),
+            ));
+        }
+    }
+
+    let mut points = Vec::with_capacity(template.points.len() + channel.points.len());
+    for point in &template.points {
+        let Some(id) = point.id.checked_add(overrides.point_id_offset) else {
+            errors.push(ValidationError::channel(
+                channel.id,
+                format!(
+                    "template point {} plus point_id_offset {} overflows",
+                    point.id, overrides.point_id_offset
+                ),
+            ));
+            continue;
+        };
+        let tweak = overrides
+            .transforms
+            .iter()
+            .find(|(key, _)| key.trim().parse() == Ok(point.id))
+            .map(|(_, tweak)| tweak);
+        let transform = match tweak {
+            Some(tweak) => match merged_transform(&point.transform, tweak) {
+                Ok(transform) => transform,
+                Err(message) => {
+                    errors.push(ValidationError::point(
+                        channel.id,
+                        id,
+                        format!("template_overrides.transforms: {}", message),
+                    ));
+                    continue;
+                }
+            },
+            None => point.transform.clone(),
+        };
+        let alarms = match &point.alarms {
+            Some(alarms) => match offset_alarms(alarms, overrides.point_id_offset) {
+                Some(alarms) => Some(alarms),
+                None => {
+                    errors.push(ValidationError::point(
+                        channel.id,
+                        id,
+                        "alarm signal id plus point_id_offset overflows",
+                    ));
+                    continue;
+                }
+            },
+            None => None,
+        };
+        // Feedback points of the same channel move with the offset
+        let feedback = match &point.feedback {
+            Some(feedback) if feedback.channel.is_none() => {
+                match feedback.point_id.checked_add(overrides.point_id_offset) {
+                    Some(point_id) => Some(FeedbackDef {
+                        point_id,
+                        ..feedback.clone()
+                    }),
+                    None => {
+                        errors.push(ValidationError::point(
+                            channel.id,
+                            id,
+                            "feedback point id plus point_id_offset overflows",
+                        ));
+                        continue;
+                    }
+                }
+            }
+            other => other.clone(),
+        };
+        points.push(PointDef {
+            id,
+            address: format!("{}{}", overrides.address_prefix, point.address),
+            transform,
+            alarms,
+            feedback,
+            ..point.clone()
+        });
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    points.extend(channel.points.iter().cloned());
+
+    let mut parameters = template.parameters.clone();
+    merge(&mut parameters, &channel.parameters);
+    Ok(ChannelConfig {
+        parameters,
+        points,
+        template: None,
+        template_overrides: TemplateOverrides::default(),
+        ..channel.clone()
+    })
+}
+
+/// `alarms` with `offset` added to every signal id (`None` on overflow).
+fn offset_alarms(alarms: &AlarmConfig, offset: PointId) -> Option<AlarmConfig> {
+    let shift = |limit: &Option<AlarmLimit>| -> Option<Option<AlarmLimit>> {
+        match limit {
+            Some(limit) => Some(Some(AlarmLimit {
+                id: limit.id.checked_add(offset)?,
+                ..limit.clone()
+            })),
+            None => Some(None),
+        }
+    };
+    Some(AlarmConfig {
+        high: shift(&alarms.high)?,
+        high_high: shift(&alarms.high_high)?,
+        low: shift(&alarms.low)?,
+        low_low: shift(&alarms.low_low)?,
+    })
+}
+
+/// `transform` with the fields present in `tweak` replaced.
+fn merged_transform(
+    transform: &TransformConfig,
+    tweak: &serde_json::Value,
+) -> Result<TransformConfig, String> {
+    if !tweak.is_object() {
+        return Err("expected a table of transform fields".into());
+    }
+    let mut value = serde_json::to_value(transform).map_err(|e| e.to_string())?;
+    merge(&mut value, tweak);
+    serde_json::from_value(value).map_err(|e| e.to_string())
+}
+
+/// Lay `over` on top of `base`: tables are merged key by key, anything else
+/// in `over` replaces `base` (a null `over` keeps it).
+fn merge(base: &mut serde_json::Value, over: &serde_json::Value) {
+    match (base, over) {
+        (_, serde_json::Value::Null) => {}
+        (serde_json::Value::Object(base), serde_json::Value::Object(over)) => {
+            for (key, value) in over {
+                merge(
+                    base.entry(key.clone()).or_insert(serde_json::Value::Null),
+                    value,
+                );
+            }
+        }
+        (base, over) => *base = over.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(value: serde_json::Value) -> GatewayConfig {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn inverters(channel_overrides: serde_json::Value) -> GatewayConfig {
+        let mut channel = serde_json::json!({
+            "id": 7,
+            "name": "inv7",
+            "protocol": "virtual",
+            "template": "inverter",
+            "parameters": { "name": "inv7" }
+        });
+        channel
+            .as_object_mut()
+            .unwrap()
+            .extend(channel_overrides.as_object().unwrap().clone());
+        config(serde_json::json!({
+            "gateway": { "name": "site" },
+            "templates": {
+                "inverter": {
+                    "parameters": { "name": "inverter", "buffer_size": 64 },
+                    "points": [
+                        { "id": 1, "name": "power", "address": "5031", "transform": { "scale": 0.1, "offset": 2.0 },
+                          "alarms": { "high": { "id": 11, "setpoint": 100.0 } } },
+                        { "id": 2, "name": "state", "address": "5038:u16" }
+                    ]
+                }
+            },
+            "channels": [channel]
+        }))
+    }
+
+    #[test]
+    fn test_expand_with_overrides() {
+        let mut config = inverters(serde_json::json!({
+            "template_overrides": {
+                "point_id_offset": 7000,
+                "address_prefix": "7:",
+                "transforms": { "1": { "scale": 0.01 } }
+            },
+            "points": [{ "id": 9000, "name": "extra", "address": "7:6000" }]
+        }));
+        assert!(config.expand_templates().is_empty());
+
+        let channel = &config.channels[0];
+        assert!(channel.template.is_none());
+        let ids: Vec<PointId> = channel.points.iter().map(|p| p.id).collect();
+        assert_eq!(ids, vec![7001, 7002, 9000]);
+        assert_eq!(channel.points[0].address, "7:5031");
+        assert_eq!(channel.points[1].address, "7:5038:u16");
+        // Only the overridden field changes
+        assert_eq!(channel.points[0].transform.scale, 0.01);
+        assert_eq!(channel.points[0].transform.offset, 2.0);
+        let high = channel.points[0].alarms.as_ref().unwrap().high.as_ref();
+        assert_eq!(high.map(|l| l.id), Some(7011));
+        assert_eq!(
+            channel.parameters,
+            serde_json::json!({ "name": "inv7", "buffer_size": 64 })
+        );
+        assert!(config.validate().is_empty());
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut base =
+            serde_json::json!({ "port": 502, "options": { "retries": 3, "timeout_ms": 1000 } });
+        merge(
+            &mut base,
+            &serde_json::json!({ "host": "10.0.0.7", "options": { "retries": 5 }, "port": null }),
+        );
+        assert_eq!(
+            base,
+            serde_json::json!({
+                "host": "10.0.0.7",
+                "port": 502,
+                "options": { "retries": 5, "timeout_ms": 1000 }
+            })
+        );
+    }
+
+    #[test]
+    fn test_expand_errors() {
+        let mut unknown = inverters(serde_json::json!({ "template": "missing" }));
+        let errors = unknown.expand_templates();
+        assert!(errors[0].message.contains("unknown template 'missing'"));
+        assert!(errors[0].message.contains("inverter"));
+        assert_eq!(unknown.channels[0].template.as_deref(), Some("missing"));
+
+        let mut bad = inverters(serde_json::json!({
+            "template_overrides": { "transforms": { "3": { "scale": 2.0 }, "1": { "scale": "x" } } }
+        }));
+        let errors = bad.expand_templates();
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+        assert!(errors[0].message.contains("'3' is not a point id"));
+        assert_eq!(errors[1].point, Some(1));
+        assert!(bad.channels[0].points.is_empty());
+    }
+
+    #[test]
+    fn test_duplicate_ids_after_expansion() {
+        let config = inverters(serde_json::json!({
+            "template_overrides": { "point_id_offset": 100 },
+            "points": [{ "id": 102, "name": "clash", "address": "1:6000" }]
+        }));
+        let errors = config.validate();
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert_eq!(errors[0].point, Some(102));
+        assert!(errors[0].message.contains("duplicate point id"));
+    }
+}
@@ -0,0 +1,248 @@
+//! Shared transports.
+//!
+//! Channels reaching their devices through the same port, e.g. Modbus RTU
+//! slaves on one RS-485 trunk split over several channels, must not each
+//! open that port. The port is defined once in a `[transports.<name>]`
+//! section and referenced by every channel using it:
+//!
+//! ```toml
+//! [transports.rs485_1]
+//! type = "serial"
+//! device = "/dev/ttyUSB0"
+//! baud_rate = 19200
+//!
+//! [[channels]]
+//! id = 1
+//! name = "Meters 1-16"
+//! protocol = "modbus"
+//! transport = "rs485_1"
+//!
+//! [[channels]]
+//! id = 2
+//! name = "Meters 17-32"
+//! protocol = "modbus"
+//! transport = "rs485_1"
+//! ```
+//!
+//! [`GatewayConfig::resolve_transports()`] copies the transport's
+//! connection parameters (`device` and `baud_rate`, or `host` and `port`)
+//! into the `parameters` of each referencing channel and, unless the
+//! channel names a `bus`, puts it on the bus of the transport's name so
+//! the channels' polls are staggered. The channel factory then gives all
+//! channels of one port a single
+//! [`SharedTransport`](crate::core::transport::SharedTransport): the port
+//! is opened once and carries one request at a time, the channels taking
+//! turns.
+//!
+//! Only the `modbus` protocol supports shared transports.
+
+use serde::{Deserialize, Serialize};
+
+use super::config::GatewayConfig;
+use super::validate::ValidationError;
+
+/// Protocols whose channels can use a shared transport.
+pub const SHARED_TRANSPORT_PROTOCOLS: &[&str] = &["modbus"];
+
+/// A port shared by several channels.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TransportConfig {
+    /// Serial port.
+    Serial {
+        /// Device path, e.g. `/dev/ttyUSB0`.
+        device: String,
+
+        /// Baud rate.
+        #[serde(default = "default_baud_rate")]
+        baud_rate: u32,
+    },
+
+    /// TCP connection, e.g. to a serial device server.
+    Tcp {
+        /// Host name or IP address.
+        host: String,
+
+        /// TCP port.
+        #[serde(default = "default_tcp_port")]
+        port: u16,
+    },
+}
+
+fn default_baud_rate() -> u32 {
+    9600
+}
+
+fn default_tcp_port() -> u16 {
+    502
+}
+
+impl TransportConfig {
+    /// Channel parameters selecting this port.
+    pub fn parameters(&self) -> serde_json::Map<String, serde_json::Value> {
+        let mut parameters = serde_json::Map::new();
+        match self {
+            Self::Serial { device, baud_rate } => {
+                parameters.insert("device".into(), device.as_str().into());
+                parameters.insert("baud_rate".into(), (*baud_rate).into());
+            }
+            Self::Tcp { host, port } => {
+                parameters.insert("host".into(), host.as_str().into());
+                parameters.insert("port".into(), (*port).into());
+            }
+        }
+        parameters
+    }
+}
+
+impl GatewayConfig {
+    /// Give every channel referencing a transport the transport's
+    /// connection parameters and bus (see the [module docs](self)).
+    ///
+    /// Returns a problem for every unknown transport and every channel
+    /// parameter contradicting its transport; such channels are left
+    /// unchanged. Resolving twice changes nothing.
+    pub fn resolve_transports(&mut self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        for channel in &mut self.channels {
+            let Some(name) = &channel.transport else {
+                continue;
+            };
+            let Some(transport) = self.transports.get(name) else {
+                let mut defined: Vec<&str> = self.transports.keys().map(String::as_str).collect();
+                defined.sort_unstable();
+                errors.push(ValidationError::channel(
+                    channel.id,
+                    format!(
+                        "unknown transport '{}' (defined: {})",
+                        name,
+                        if defined.is_empty() {
+                            "none".into()
+                        } else {
+                            defined.join(", ")
+                        }
+                    ),
+                ));
+                continue;
+            };
+
+            let mut parameters = match &channel.parameters {
+                serde_json::Value::Object(parameters) => parameters.clone(),
+                serde_json::Value::Null => serde_json::Map::new(),
+                _ => {
+                    errors.push(ValidationError::channel(
+                        channel.id,
+                        "parameters must be a table to use a transport",
+                    ));
+                    continue;
+                }
+            };
+            let mut conflicts = Vec::new();
+            for (key, value) in transport.parameters() {
+                match parameters.get(&key) {
+                    Some(own) if *own != value => conflicts.push(key),
+                    _ => {
+                        parameters.insert(key, value);
+                    }
+                }
+            }
+            if !conflicts.is_empty() {
+                errors.push(ValidationError::channel(
+                    channel.id,
+                    format!(
+                        "parameters {} contradict transport '{}'",
+                        conflicts.join(", "),
+                        name
+                    ),
+                ));
+                continue;
+            }
+            channel.parameters = serde_json::Value::Object(parameters);
+            if channel.bus.is_none() {
+                channel.bus = Some(name.clone());
+            }
+        }
+        errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(channels: serde_json::Value) -> GatewayConfig {
+        serde_json::from_value(serde_json::json!({
+            "gateway": { "name": "trunk" },
+            "transports": {
+                "rs485_1": { "type": "serial", "device": "/dev/ttyUSB0", "baud_rate": 19200 },
+                "moxa": { "type": "tcp", "host": "10.0.0.9" }
+            },
+            "channels": channels
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_resolve() {
+        let mut config = config(serde_json::json!([
+            { "id": 1, "name": "a", "protocol": "modbus", "transport": "rs485_1" },
+            {
+                "id": 2,
+                "name": "b",
+                "protocol": "modbus",
+                "transport": "rs485_1",
+                "bus": "trunk",
+                "parameters": { "baud_rate": 19200, "io_timeout_ms": 500 }
+            },
+            { "id": 3, "name": "c", "protocol": "modbus", "transport": "moxa" },
+            { "id": 4, "name": "d", "protocol": "modbus", "parameters": { "host": "10.0.0.4" } }
+        ]));
+        assert!(config.resolve_transports().is_empty());
+
+        let channels = &config.channels;
+        assert_eq!(
+            channels[0].parameters,
+            serde_json::json!({ "device": "/dev/ttyUSB0", "baud_rate": 19200 })
+        );
+        assert_eq!(channels[0].bus.as_deref(), Some("rs485_1"));
+        assert_eq!(channels[1].parameters["io_timeout_ms"], 500);
+        assert_eq!(channels[1].bus.as_deref(), Some("trunk"));
+        assert_eq!(
+            channels[2].parameters,
+            serde_json::json!({ "host": "10.0.0.9", "port": 502 })
+        );
+        assert_eq!(channels[3].bus, None);
+
+        // Idempotent
+        let resolved = config.clone();
+        assert!(config.resolve_transports().is_empty());
+        assert_eq!(config, resolved);
+    }
+
+    #[test]
+    fn test_resolve_errors() {
+        let mut config = config(serde_json::json!([
+            { "id": 1, "name": "a", "protocol": "modbus", "transport": "rs485_2" },
+            {
+                "id": 2,
+                "name": "b",
+                "protocol": "modbus",
+                "transport": "rs485_1",
+                "parameters": { "device": "/dev/ttyUSB1", "baud_rate": 9600 }
+            }
+        ]));
+        let errors: Vec<String> = config
+            .resolve_transports()
+            .iter()
+            .map(|e| e.to_string())
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                "channel 1: unknown transport 'rs485_2' (defined: moxa, rs485_1)",
+                "channel 2: parameters baud_rate, device contradict transport 'rs485_1'",
+            ]
+        );
+        assert_eq!(config.channels[1].bus, None);
+    }
+}
@@ -0,0 +1,1748 @@
+//! Configuration validation.
+//!
+//! [`GatewayConfig::validate()`] checks a parsed configuration for problems
+//! that would otherwise only surface once channels are built or running,
+//! and reports all of them at once.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::core::data::PointId;
+use crate::core::error::{GatewayError, Result};
+use crate::core::point::{DataFormat, TransformKind};
+
+use super::address::parse_address;
+use super::config::{ChannelConfig, GatewayConfig, HeartbeatPattern, RegisterArea};
+use super::factory::{get_channel_factory_registry, BUILTIN_PROTOCOLS};
+use super::params::protocol_params;
+use super::startup;
+use super::transport::{TransportConfig, SHARED_TRANSPORT_PROTOCOLS};
+
+/// A configuration problem found by [`GatewayConfig::validate()`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ValidationError {
+    /// Channel the problem belongs to (`None` for gateway settings).
+    pub channel: Option<u32>,
+
+    /// Point the problem belongs to.
+    pub point: Option<PointId>,
+
+    /// What is wrong.
+    pub message: String,
+}
+
+impl ValidationError {
+    pub(super) fn gateway(message: impl Into<String>) -> Self {
+        Self {
+            channel: None,
+            point: None,
+            message: message.into(),
+        }
+    }
+
+    pub(super) fn channel(channel: u32, message: impl Into<String>) -> Self {
+        Self {
+            channel: Some(channel),
+            point: None,
+            message: message.into(),
+        }
+    }
+
+    pub(super) fn point(channel: u32, point: PointId, message: impl Into<String>) -> Self {
+        Self {
+            channel: Some(channel),
+            point: Some(point),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.channel, self.point) {
+            (Some(channel), Some(point)) => {
+                write!(f, "channel {}, point {}: {}", channel, point, self.message)
+            }
+            (Some(channel), None) => write!(f, "channel {}: {}", channel, self.message),
+            _ => write!(f, "gateway: {}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+impl GatewayConfig {
+    /// Check the configuration and return every problem found.
+    ///
+    /// Covers zero poll intervals, duplicate channel ids, duplicate point
+    /// ids within a channel, unknown or disabled-at-build-time protocols,
+    /// unknown, missing or mistyped [parameters](super::params),
+    /// unparseable addresses and protocol-specific address limits (e.g. a
+    /// Modbus value running past register 0xFFFF). Protocol, parameter and
+    /// address checks are skipped for disabled channels and points;
+    /// parameters and addresses of protocols registered with
+    /// [`register_protocol()`](super::factory::register_protocol) are not
+    /// checked.
+    ///
+    /// Channels referencing a [template](super::template) or a
+    /// [transport](super::transport) are checked as expanded and resolved;
+    /// unknown templates and transports, overrides that do not apply and
+    /// parameters contradicting a transport are reported as well.
+    ///
+    /// An empty list means the configuration is valid.
+    pub fn validate(&self) -> Vec<ValidationError> {
+        if self
+            .channels
+            .iter()
+            .any(|c| c.template.is_some() || c.transport.is_some())
+        {
+            let mut expanded = self.clone();
+            let mut errors = expanded.expand_templates();
+            errors.extend(expanded.resolve_transports());
+            errors.extend(expanded.validate_expanded());
+            return errors;
+        }
+        self.validate_expanded()
+    }
+
+    fn validate_expanded(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+
+        if self.gateway.default_poll_interval_ms == 0 {
+            errors.push(ValidationError::gateway(
+                "default_poll_interval_ms must be greater than 0",
+            ));
+        }
+        if self.gateway.max_concurrent_polls == Some(0) {
+            errors.push(ValidationError::gateway(
+                "max_concurrent_polls must be greater than 0",
+            ));
+        }
+        if self.gateway.jsonl_output {
+            let jsonl = &self.gateway.jsonl;
+            if jsonl.max_file_bytes == 0 {
+                errors.push(ValidationError::gateway(
+                    "jsonl.max_file_bytes must be greater than 0",
+                ));
+            }
+            if jsonl.queue_capacity == 0 {
+                errors.push(ValidationError::gateway(
+                    "jsonl.queue_capacity must be greater than 0",
+                ));
+            }
+        }
+        if self.gateway.shutdown_timeout_ms == 0 {
+            errors.push(ValidationError::gateway(
+                "shutdown_timeout_ms must be greater than 0",
+            ));
+        }
+        if let Some(watchdog) = &self.gateway.watchdog {
+            if watchdog.stall_timeout_ms == 0 {
+                errors.push(ValidationError::gateway(
+                    "watchdog.stall_timeout_ms must be greater than 0",
+                ));
+            }
+            if watchdog.restart_window_ms == 0 {
+                errors.push(ValidationError::gateway(
+                    "watchdog.restart_window_ms must be greater than 0",
+                ));
+            }
+        }
+        if let Some(http_api) = &self.gateway.http_api {
+            if cfg!(not(feature = "http-api")) {
+                errors.push(ValidationError::gateway(
+                    "http_api is configured but this build lacks the 'http-api' feature",
+                ));
+            }
+            if http_api.bind.parse::<std::net::SocketAddr>().is_err() {
+                errors.push(ValidationError::gateway(format!(
+                    "http_api.bind '{}' is not a valid host:port address",
+                    http_api.bind
+                )));
+            }
+        }
+
+        let mut seen: HashMap<u32, &str> = HashMap::new();
+        for channel in &self.channels {
+            if let Some(first) = seen.insert(channel.id, &channel.name) {
+                errors.push(ValidationError::channel(
+                    channel.id,
+                    format!(
+                        "duplicate channel id (used by '{}' and '{}')",
+                        first, channel.name
+                    ),
+                ));
+            }
+            // Channels that failed to expand were reported already
+            if channel.template.is_none() {
+                errors.extend(validate_channel(channel));
+            }
+        }
+        errors.extend(self.validate_redundancy());
+        errors.extend(self.validate_dependencies());
+        errors.extend(self.validate_interlocks());
+        errors.extend(self.validate_feedback());
+        errors.extend(self.validate_modbus_server());
+        errors.extend(self.validate_opcua_server());
+        errors.extend(self.validate_archive());
+        errors.extend(self.validate_replication());
+        errors.extend(self.validate_heartbeat());
+        errors.extend(self.validate_authorization());
+        errors.extend(self.validate_poll_phases());
+        errors.extend(self.validate_transports());
+
+        errors
+    }
+
+    /// Check shared transports: ports must be named and addressed, and only
+    /// protocols supporting them may use them.
+    fn validate_transports(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        let mut names: Vec<&String> = self.transports.keys().collect();
+        names.sort_unstable();
+        for name in names {
+            let problem = match &self.transports[name] {
+                _ if name.trim().is_empty() => "name must not be empty",
+                TransportConfig::Serial { device, .. } if device.is_empty() => {
+                    "device must not be empty"
+                }
+                TransportConfig::Serial { baud_rate: 0, .. } => "baud_rate must be greater than 0",
+                TransportConfig::Tcp { host, .. } if host.is_empty() => "host must not be empty",
+                _ => continue,
+            };
+            errors.push(ValidationError::gateway(format!(
+                "transports.{}: {}",
+                name, problem
+            )));
+        }
+        for channel in &self.channels {
+            if channel.transport.is_some()
+                && !SHARED_TRANSPORT_PROTOCOLS.contains(&channel.protocol.as_str())
+            {
+                errors.push(ValidationError::channel(
+                    channel.id,
+                    format!(
+                        "protocol '{}' cannot use a shared transport",
+                        channel.protocol
+                    ),
+                ));
+            }
+        }
+        errors
+    }
+
+    /// Check bus names and poll offsets: a bus must be named and an offset
+    /// must fall within the channel's poll interval.
+    fn validate_poll_phases(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        for channel in &self.channels {
+            if channel
+                .bus
+                .as_deref()
+                .is_some_and(|bus| bus.trim().is_empty())
+            {
+                errors.push(ValidationError::channel(
+                    channel.id,
+                    "bus must not be empty",
+                ));
+            }
+            let interval_ms = channel
+                .poll_interval_ms
+                .unwrap_or(self.gateway.default_poll_interval_ms);
+            if let Some(offset_ms) = channel.poll_offset_ms.filter(|&ms| ms >= interval_ms) {
+                errors.push(ValidationError::channel(
+                    channel.id,
+                    format!(
+                        "poll_offset_ms {} must be less than the poll interval of {} ms",
+                        offset_ms, interval_ms
+                    ),
+                ));
+            }
+        }
+        errors
+    }
+
+    /// Check the Modbus server's register table: every entry must expose a
+    /// defined point and entries of one area must not overlap.
+    fn validate_modbus_server(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        let Some(server) = &self.gateway.modbus_server else {
+            return errors;
+        };
+        if cfg!(not(feature = "modbus-server")) {
+            errors.push(ValidationError::gateway(
+                "modbus_server is configured but this build lacks the 'modbus-server' feature",
+            ));
+        }
+        if server.bind.parse::<std::net::SocketAddr>().is_err() {
+            errors.push(ValidationError::gateway(format!(
+                "modbus_server.bind '{}' is not a valid host:port address",
+                server.bind
+            )));
+        }
+
+        let mut ranges: Vec<(RegisterArea, u32, u32, usize)> = Vec::new();
+        for (index, entry) in server.registers.iter().enumerate() {
+            let mut fail = |message: String| {
+                errors.push(ValidationError::gateway(format!(
+                    "modbus_server.registers[{}]: {}",
+                    index, message
+                )))
+            };
+            match self.channels.iter().find(|c| c.id == entry.channel_id) {
+                None => fail(format!("channel {} is not defined", entry.channel_id)),
+                Some(channel) if !channel.points.iter().any(|p| p.id == entry.point_id) => {
+                    fail(format!(
+                        "point {} is not defined in channel {}",
+                        entry.point_id, entry.channel_id
+                    ))
+                }
+                Some(_) => {}
+            }
+            if entry.area != RegisterArea::Coil && entry.format == DataFormat::String {
+                fail("string registers are not supported".into());
+            }
+
+            let start = u32::from(entry.address);
+            let end = start + u32::from(entry.register_count());
+            if end > 0x1_0000 {
+                fail(format!(
+                    "address {} runs past register 65535",
+                    entry.address
+                ));
+            }
+            if let Some((_, _, _, other)) = ranges
+                .iter()
+                .find(|(area, s, e, _)| *area == entry.area && start < *e && *s < end)
+            {
+                fail(format!("overlaps registers[{}]", other));
+            }
+            ranges.push((entry.area, start, end, index));
+        }
+        errors
+    }
+
+    /// Check authorization and audit: caller identities and HTTP API user
+    /// identities and tokens must be non-empty and unique, and the audit
+    /// queue must hold at least one record.
+    fn validate_authorization(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        if let Some(authorization) = &self.gateway.authorization {
+            let mut identities = HashSet::new();
+            for caller in &authorization.callers {
+                if caller.identity.is_empty() {
+                    errors.push(ValidationError::gateway(
+                        "authorization.callers: identity must not be empty",
+                    ));
+                } else if !identities.insert(caller.identity.as_str()) {
+                    errors.push(ValidationError::gateway(format!(
+                        "authorization.callers: '{}' is listed more than once",
+                        caller.identity
+                    )));
+                }
+            }
+        }
+        if self
+            .gateway
+            .audit
+            .as_ref()
+            .is_some_and(|audit| audit.queue_capacity == 0)
+        {
+            errors.push(ValidationError::gateway(
+                "audit.queue_capacity must be greater than 0",
+            ));
+        }
+        if let Some(http_api) = &self.gateway.http_api {
+            let mut identities = HashSet::new();
+            let mut tokens: HashSet<&str> = http_api.token.iter().map(String::as_str).collect();
+            for user in &http_api.users {
+                if user.identity.is_empty() {
+                    errors.push(ValidationError::gateway(
+                        "http_api.users: identity must not be empty",
+                    ));
+                } else if !identities.insert(user.identity.as_str()) {
+                    errors.push(ValidationError::gateway(format!(
+                        "http_api.users: '{}' is listed more than once",
+                        user.identity
+                    )));
+                }
+                if user.token.is_empty() {
+                    errors.push(ValidationError::gateway(format!(
+                        "http_api.users: token of '{}' must not be empty",
+                        user.identity
+                    )));
+                } else if !tokens.insert(user.token.as_str()) {
+                    errors.push(ValidationError::gateway(format!(
+                        "http_api.users: token of '{}' is already in use",
+                        user.identity
+                    )));
+                }
+            }
+        }
+        errors
+    }
+
+    /// Check the archive: the feature must be built in and every archived
+    /// channel must be defined.
+    fn validate_archive(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        let Some(archive) = &self.gateway.archive else {
+            return errors;
+        };
+        if cfg!(not(feature = "export")) {
+            errors.push(ValidationError::gateway(
+                "archive is configured but this build lacks the 'export' feature",
+            ));
+        }
+        if archive.queue_capacity == 0 {
+            errors.push(ValidationError::gateway(
+                "archive.queue_capacity must be greater than 0",
+            ));
+        }
+        for channel_id in &archive.channels {
+            if !self.channels.iter().any(|c| c.id == *channel_id) {
+                errors.push(ValidationError::gateway(format!(
+                    "archive.channels: channel {} is not defined",
+                    channel_id
+                )));
+            }
+        }
+        errors
+    }
+
+    /// Check the replication link: the feature must be built in and the
+    /// link must have an address, a token, a heartbeat and a queue.
+    fn validate_replication(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        let Some(replication) = &self.gateway.replication else {
+            return errors;
+        };
+        if cfg!(not(feature = "replication")) {
+            errors.push(ValidationError::gateway(
+                "replication is configured but this build lacks the 'replication' feature",
+            ));
+        }
+        if replication.address.trim().is_empty() {
+            errors.push(ValidationError::gateway(
+                "replication.address must not be empty",
+            ));
+        }
+        if replication.token.is_empty() {
+            errors.push(ValidationError::gateway(
+                "replication.token must not be empty",
+            ));
+        }
+        if replication.heartbeat_interval_ms == 0 {
+            errors.push(ValidationError::gateway(
+                "replication.heartbeat_interval_ms must be greater than 0",
+            ));
+        }
+        if replication.queue_capacity == 0 {
+            errors.push(ValidationError::gateway(
+                "replication.queue_capacity must be greater than 0",
+            ));
+        }
+        errors
+    }
+
+    /// Check the heartbeat output: it must be a point of an enabled channel,
+    /// monitor enabled channels and have usable timing.
+    fn validate_heartbeat(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        let Some(heartbeat) = &self.gateway.heartbeat else {
+            return errors;
+        };
+        match self
+            .enabled_channels()
+            .find(|c| c.id == heartbeat.channel_id)
+        {
+            None => errors.push(ValidationError::gateway(format!(
+                "heartbeat.channel_id {} is not an enabled channel",
+                heartbeat.channel_id
+            ))),
+            Some(channel) if !channel.points.iter().any(|p| p.id == heartbeat.point_id) => errors
+                .push(ValidationError::gateway(format!(
+                    "heartbeat.point_id {} is not defined on channel {}",
+                    heartbeat.point_id, heartbeat.channel_id
+                ))),
+            Some(_) => {}
+        }
+        for &id in &heartbeat.monitored_channels {
+            if !self.enabled_channels().any(|c| c.id == id) {
+                errors.push(ValidationError::gateway(format!(
+                    "heartbeat monitors channel {} which is not an enabled channel",
+                    id
+                )));
+            }
+        }
+        if heartbeat.period_ms == 0 {
+            errors.push(ValidationError::gateway(
+                "heartbeat.period_ms must be greater than 0",
+            ));
+        }
+        if heartbeat.stall_timeout_ms == 0 {
+            errors.push(ValidationError::gateway(
+                "heartbeat.stall_timeout_ms must be greater than 0",
+            ));
+        }
+        if heartbeat.pattern == HeartbeatPattern::Pulse
+            && (heartbeat.pulse_ms == 0 || heartbeat.pulse_ms >= heartbeat.period_ms)
+        {
+            errors.push(ValidationError::gateway(
+                "heartbeat.pulse_ms must be greater than 0 and shorter than period_ms",
+            ));
+        }
+        errors
+    }
+
+    /// Check the OPC UA server: a login must be possible and every writable
+    /// entry must be a defined point.
+    fn validate_opcua_server(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        let Some(server) = &self.gateway.opcua_server else {
+            return errors;
+        };
+        if cfg!(not(feature = "opcua-server")) {
+            errors.push(ValidationError::gateway(
+                "opcua_server is configured but this build lacks the 'opcua-server' feature",
+            ));
+        }
+        if server.bind.parse::<std::net::SocketAddr>().is_err() {
+            errors.push(ValidationError::gateway(format!(
+                "opcua_server.bind '{}' is not a valid host:port address",
+                server.bind
+            )));
+        }
+        if !server.anonymous && server.users.is_empty() {
+            errors.push(ValidationError::gateway(
+                "opcua_server: anonymous is off and no users are configured",
+            ));
+        }
+        let mut names = HashSet::new();
+        for user in &server.users {
+            if user.username.is_empty() {
+                errors.push(ValidationError::gateway(
+                    "opcua_server.users: username must not be empty",
+                ));
+            } else if !names.insert(user.username.as_str()) {
+                errors.push(ValidationError::gateway(format!(
+                    "opcua_server.users: '{}' is listed more than once",
+                    user.username
+                )));
+            }
+        }
+
+        for (index, entry) in server.writable.iter().enumerate() {
+            let message = match self.channels.iter().find(|c| c.id == entry.channel_id) {
+                None => format!("channel {} is not defined", entry.channel_id),
+                Some(channel) if !channel.points.iter().any(|p| p.id == entry.point_id) => {
+                    format!(
+                        "point {} is not defined in channel {}",
+                        entry.point_id, entry.channel_id
+                    )
+                }
+                Some(_) => continue,
+            };
+            errors.push(ValidationError::gateway(format!(
+                "opcua_server.writable[{}]: {}",
+                index, message
+            )));
+        }
+        errors
+    }
+
+    /// Check that interlocks refer to defined channels.
+    fn validate_interlocks(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        for channel in &self.channels {
+            for interlock in &channel.interlocks {
+                let Some(check_channel) = interlock.check_channel else {
+                    continue;
+                };
+                if !self.channels.iter().any(|c| c.id == check_channel) {
+                    errors.push(ValidationError::point(
+                        channel.id,
+                        interlock.point_id,
+                        format!(
+                            "interlock checks channel {} which is not defined",
+                            check_channel
+                        ),
+                    ));
+                }
+            }
+        }
+        errors
+    }
+
+    /// Check that command feedback points refer to defined channels.
+    fn validate_feedback(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        for channel in &self.channels {
+            for point in &channel.points {
+                let Some(feedback_channel) = point.feedback.as_ref().and_then(|f| f.channel) else {
+                    continue;
+                };
+                if !self.channels.iter().any(|c| c.id == feedback_channel) {
+                    errors.push(ValidationError::point(
+                        channel.id,
+                        point.id,
+                        format!(
+                            "feedback reads channel {} which is not defined",
+                            feedback_channel
+                        ),
+                    ));
+                }
+            }
+        }
+        errors
+    }
+
+    /// Check `depends_on`: dependencies must be other defined, non-backup
+    /// channels without cycles among the enabled ones.
+    fn validate_dependencies(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        for channel in &self.channels {
+            let id = channel.id;
+            for &dependency in &channel.depends_on {
+                let message = match self.channels.iter().find(|c| c.id == dependency) {
+                    _ if dependency == id => "depends_on refers to the channel itself".into(),
+                    None => format!(
+                        "depends_on refers to channel {} which is not defined",
+                        dependency
+                    ),
+                    Some(c) if c.backup_of.is_some() => format!(
+                        "depends_on refers to channel {} which is a backup",
+                        dependency
+                    ),
+                    Some(_) => continue,
+                };
+                errors.push(ValidationError::channel(id, message));
+            }
+            if !channel.depends_on.is_empty() && channel.dependency_timeout_ms == 0 {
+                errors.push(ValidationError::channel(
+                    id,
+                    "dependency_timeout_ms must be greater than 0",
+                ));
+            }
+        }
+
+        let enabled = self
+            .enabled_channels()
+            .filter(|c| !c.depends_on.contains(&c.id));
+        if let Err(cycle) = startup::levels(enabled) {
+            errors.push(ValidationError::channel(
+                cycle[0],
+                startup::cycle_message(&cycle),
+            ));
+        }
+        errors
+    }
+
+    /// Check `backup_of` links: the primary must be another enabled,
+    /// non-backup channel with at most one backup.
+    fn validate_redundancy(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        let mut backups: HashMap<u32, u32> = HashMap::new();
+
+        for backup in self.enabled_channels() {
+            let Some(primary_id) = backup.backup_of else {
+                continue;
+            };
+            let id = backup.id;
+            if backup.failover_after_ms == 0 {
+                errors.push(ValidationError::channel(
+                    id,
+                    "failover_after_ms must be greater than 0",
+                ));
+            }
+
+            let primary = self.enabled_channels().find(|c| c.id == primary_id);
+            let message = match primary {
+                _ if primary_id == id => Some("backup_of refers to the channel itself".into()),
+                None => Some(format!(
+                    "backup_of refers to channel {} which is not defined or not enabled",
+                    primary_id
+                )),
+                Some(primary) if primary.backup_of.is_some() => Some(format!(
+                    "backup_of refers to channel {} which is itself a backup",
+                    primary_id
+                )),
+                Some(primary)
+                    if backup.points.is_empty()
+                        && !primary.protocol.eq_ignore_ascii_case(&backup.protocol) =>
+                {
+                    Some(format!(
+                        "a backup without points uses the points of channel {} and needs its protocol '{}'",
+                        primary_id, primary.protocol
+                    ))
+                }
+                Some(_) => backups.insert(primary_id, id).map(|other| {
+                    format!(
+                        "channel {} already has backup {}",
+                        primary_id, other
+                    )
+                }),
+            };
+            if let Some(message) = message {
+                errors.push(ValidationError::channel(id, message));
+            }
+        }
+        errors
+    }
+}
+
+/// Check a single channel configuration.
+pub(crate) fn validate_channel(config: &ChannelConfig) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    let id = config.id;
+
+    if let Some(template) = &config.template {
+        errors.push(ValidationError::channel(
+            id,
+            format!(
+                "template '{}' must be expanded with GatewayConfig::expand_templates() first",
+                template
+            ),
+        ));
+    }
+    if config.poll_interval_ms == Some(0) {
+        errors.push(ValidationError::channel(
+            id,
+            "poll_interval_ms must be greater than 0",
+        ));
+    }
+    if config.event_buffer.capacity == 0 {
+        errors.push(ValidationError::channel(
+            id,
+            "event_buffer.capacity must be greater than 0",
+        ));
+    }
+    if let Some(queue) = &config.command_queue {
+        if queue.max_depth == 0 {
+            errors.push(ValidationError::channel(
+                id,
+                "command_queue.max_depth must be greater than 0",
+            ));
+        }
+        if !queue.max_rate.is_finite() || queue.max_rate < 0.0 {
+            errors.push(ValidationError::channel(
+                id,
+                "command_queue.max_rate must be 0 or more",
+            ));
+        }
+    }
+
+    let mut seen: HashMap<PointId, &str> = HashMap::new();
+    for point in &config.points {
+        if let Some(first) = seen.insert(point.id, &point.name) {
+            errors.push(ValidationError::point(
+                id,
+                point.id,
+                format!(
+                    "duplicate point id (used by '{}' and '{}')",
+                    first, point.name
+                ),
+            ));
+        }
+        if point.max_age_ms == Some(0) {
+            errors.push(ValidationError::point(
+                id,
+                point.id,
+                "max_age_ms must be greater than 0",
+            ));
+        }
+        match (point.ttl_ms, point.out_of_service_ms) {
+            (Some(0), _) => errors.push(ValidationError::point(
+                id,
+                point.id,
+                "ttl_ms must be greater than 0",
+            )),
+            (None, Some(_)) => errors.push(ValidationError::point(
+                id,
+                point.id,
+                "out_of_service_ms requires ttl_ms",
+            )),
+            (Some(ttl), Some(out_of_service)) if out_of_service <= ttl => {
+                errors.push(ValidationError::point(
+                    id,
+                    point.id,
+                    format!(
+                        "out_of_service_ms ({}) must be greater than ttl_ms ({})",
+                        out_of_service, ttl
+                    ),
+                ))
+            }
+            _ => {}
+        }
+        if let TransformKind::Polynomial { coefficients } = &point.transform.kind {
+            if coefficients.is_empty() {
+                errors.push(ValidationError::point(
+                    id,
+                    point.id,
+                    "transform: a polynomial needs at least one coefficient",
+                ));
+            }
+        }
+        if let (Some(min), Some(max)) = (point.transform.min_value, point.transform.max_value) {
+            if min > max {
+                errors.push(ValidationError::point(
+                    id,
+                    point.id,
+                    format!(
+                        "transform: min_value {} is greater than max_value {}",
+                        min, max
+                    ),
+                ));
+            }
+        }
+        if let Some(conversion) = &point.transform.unit_conversion {
+            let mut conversion = conversion.clone();
+            if conversion.from.is_none() {
+                conversion.from = point.unit.clone();
+            }
+            if let Err(e) = conversion.resolve() {
+                errors.push(ValidationError::point(
+                    id,
+                    point.id,
+                    format!("transform.unit_conversion: {}", message(e)),
+                ));
+            }
+        }
+    }
+
+    let mut signals: HashSet<PointId> = HashSet::new();
+    for point in &config.points {
+        let Some(alarms) = &point.alarms else {
+            continue;
+        };
+        for (name, _, limit) in alarms.limits() {
+            let message = if seen.contains_key(&limit.id) {
+                Some(format!("alarms.{}: id {} is a point id", name, limit.id))
+            } else if !signals.insert(limit.id) {
+                Some(format!("alarms.{}: id {} is used twice", name, limit.id))
+            } else if !limit.setpoint.is_finite() {
+                Some(format!("alarms.{}: setpoint must be finite", name))
+            } else if limit.hysteresis < 0.0 || !limit.hysteresis.is_finite() {
+                Some(format!("alarms.{}: hysteresis must be 0 or more", name))
+            } else {
+                None
+            };
+            if let Some(message) = message {
+                errors.push(ValidationError::point(id, point.id, message));
+            }
+        }
+    }
+
+    let mut safe: HashSet<PointId> = HashSet::new();
+    for entry in &config.safe_state {
+        if !seen.contains_key(&entry.point_id) {
+            errors.push(ValidationError::point(
+                id,
+                entry.point_id,
+                "safe_state refers to a point that is not defined",
+            ));
+        } else if !safe.insert(entry.point_id) {
+            errors.push(ValidationError::point(
+                id,
+                entry.point_id,
+                "safe_state lists the point more than once",
+            ));
+        }
+        if !entry.value.is_finite() {
+            errors.push(ValidationError::point(
+                id,
+                entry.point_id,
+                "safe_state value must be finite",
+            ));
+        }
+    }
+
+    for point in &config.points {
+        let Some(feedback) = &point.feedback else {
+            continue;
+        };
+        let mut fail = |message: &str| {
+            errors.push(ValidationError::point(id, point.id, message));
+        };
+        if feedback.timeout_ms == 0 {
+            fail("feedback timeout_ms must be greater than 0");
+        }
+        let values = feedback.map.iter().flat_map(|m| [m.command, m.feedback]);
+        if values.into_iter().any(|v| !v.is_finite()) {
+            fail("feedback map values must be finite");
+        }
+        let mut commands = feedback.map.iter().map(|m| m.command).collect::<Vec<_>>();
+        commands.sort_by(f64::total_cmp);
+        if commands.windows(2).any(|w| w[0] == w[1]) {
+            fail("feedback map lists a command value more than once");
+        }
+    }
+
+    for interlock in &config.interlocks {
+        let mut fail = |message: &str| {
+            errors.push(ValidationError::point(id, interlock.point_id, message));
+        };
+        if !seen.contains_key(&interlock.point_id) {
+            fail("interlock refers to a point that is not defined");
+        }
+        match (interlock.equals, interlock.min, interlock.max) {
+            (None, None, None) => fail("interlock needs 'equals' or 'min'/'max'"),
+            (Some(_), Some(_), _) | (Some(_), _, Some(_)) => {
+                fail("interlock cannot combine 'equals' with 'min'/'max'")
+            }
+            (_, Some(min), Some(max)) if min > max => fail("interlock min is greater than max"),
+            _ => {}
+        }
+        let values = [interlock.equals, interlock.min, interlock.max];
+        if values.iter().flatten().any(|v| !v.is_finite()) {
+            fail("interlock values must be finite");
+        }
+    }
+
+    if let Some(backfill) = &config.backfill {
+        if backfill.max_gap_ms == 0 {
+            errors.push(ValidationError::channel(
+                id,
+                "backfill.max_gap_ms must be greater than 0",
+            ));
+        }
+        for &point_id in &backfill.points {
+            if !seen.contains_key(&point_id) {
+                errors.push(ValidationError::point(
+                    id,
+                    point_id,
+                    "backfill refers to a point that is not defined",
+                ));
+            }
+        }
+    }
+
+    if !config.enabled {
+        return errors;
+    }
+
+    let protocol = config.protocol.to_lowercase();
+    if let Some(params) = protocol_params(&protocol) {
+        if let Err(e) = params.check(&config.parameters) {
+            errors.push(ValidationError::channel(
+                id,
+                format!(
+                    "invalid {} parameters of '{}': {}",
+                    protocol, config.name, e
+                ),
+            ));
+        }
+    }
+    let builtin = BUILTIN_PROTOCOLS.contains(&protocol.as_str());
+    let registry = get_channel_factory_registry();
+    if !registry.contains(&protocol) {
+        let message = if builtin {
+            format!(
+                "protocol '{}' is not available in this build (enable the '{}' feature)",
+                config.protocol, protocol
+            )
+        } else {
+            format!(
+                "unknown protocol '{}' (supported: {})",
+                config.protocol,
+                registry.protocols().join(", ")
+            )
+        };
+        errors.push(ValidationError::channel(id, message));
+        return errors;
+    }
+    if !builtin {
+        // Addresses of registered protocols are up to their builder
+        return errors;
+    }
+
+    for point in config.points.iter().filter(|p| p.enabled) {
+        let result =
+            parse_address(&protocol, &point.address).and_then(|address| address.validate());
+        if let Err(e) = result {
+            errors.push(ValidationError::point(
+                id,
+                point.id,
+                format!("address '{}': {}", point.address, message(e)),
+            ));
+        }
+    }
+
+    errors
+}
+
+/// Turn validation problems into a single `GatewayError::Config`.
+pub(crate) fn ensure_valid(errors: Vec<ValidationError>) -> Result<()> {
+    if errors.is_empty() {
+        return Ok(());
+    }
+    let problems: Vec<String> = errors.iter().map(ToString::to_string).collect();
+    Err(GatewayError::Config(format!(
+        "invalid configuration: {}",
+        problems.join("; ")
+    )))
+}
+
+fn message(error: GatewayError) -> String {
+    match error {
+        GatewayError::Config(message) => message,
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(value: serde_json::Value) -> GatewayConfig {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_valid_config() {
+        let config = config(serde_json::json!({
+            "gateway": { "name": "ok" },
+            "channels": [{
+                "id": 1,
+                "name": "hub",
+                "protocol": "virtual",
+                "points": [{ "id": 1, "name": "a", "address": "a" }]
+            }]
+        }));
+        assert!(config.validate().is_empty());
+    }
+
+    #[test]
+    fn test_safe_state() {
+        let config = config(serde_json::json!({
+            "gateway": { "name": "safe", "shutdown_timeout_ms": 0 },
+            "channels": [{
+                "id": 1,
+                "name": "hub",
+                "protocol": "virtual",
+                "points": [{ "id": 1, "name": "a", "address": "a" }],
+                "safe_state": [
+                    { "point_id": 1, "kind": "control", "value": 0 },
+                    { "point_id": 1, "kind": "adjustment", "value": 1 },
+                    { "point_id": 2, "kind": "control", "value": 0 }
+                ]
+            }]
+        }));
+        let errors: Vec<String> = config.validate().iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            errors,
+            vec![
+                "gateway: shutdown_timeout_ms must be greater than 0",
+                "channel 1, point 1: safe_state lists the point more than once",
+                "channel 1, point 2: safe_state refers to a point that is not defined",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_backfill() {
+        let config = config(serde_json::json!({
+            "gateway": { "name": "backfill" },
+            "channels": [{
+                "id": 1,
+                "name": "hub",
+                "protocol": "virtual",
+                "points": [{ "id": 1, "name": "a", "address": "a" }],
+                "backfill": { "points": [1, 2], "max_gap_ms": 0 }
+            }]
+        }));
+        let errors: Vec<String> = config.validate().iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            errors,
+            vec![
+                "channel 1: backfill.max_gap_ms must be greater than 0",
+                "channel 1, point 2: backfill refers to a point that is not defined",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_opcua_server() {
+        let config = config(serde_json::json!({
+            "gateway": {
+                "name": "ua",
+                "opcua_server": {
+                    "bind": "0.0.0.0:4840",
+                    "anonymous": false,
+                    "users": [
+                        { "username": "scada", "password": "a" },
+                        { "username": "scada", "password": "b" }
+                    ],
+                    "writable": [
+                        { "channel_id": 1, "point_id": 1, "kind": "control" },
+                        { "channel_id": 1, "point_id": 2, "kind": "adjustment" },
+                        { "channel_id": 9, "point_id": 1, "kind": "control" }
+                    ]
+                }
+            },
+            "channels": [{
+                "id": 1,
+                "name": "feeder",
+                "protocol": "virtual",
+                "points": [{ "id": 1, "name": "breaker", "address": "b" }]
+            }]
+        }));
+        let mut expected = vec![
+            "gateway: opcua_server.users: 'scada' is listed more than once",
+            "gateway: opcua_server.writable[1]: point 2 is not defined in channel 1",
+            "gateway: opcua_server.writable[2]: channel 9 is not defined",
+        ];
+        if cfg!(not(feature = "opcua-server")) {
+            expected.insert(
+                0,
+                "gateway: opcua_server is configured but this build lacks the 'opcua-server' feature",
+            );
+        }
+        let errors: Vec<String> = config
+            .validate_opcua_server()
+            .iter()
+            .map(|e| e.to_string())
+            .collect();
+        assert_eq!(errors, expected);
+    }
+
+    #[test]
+    fn test_archive() {
+        let config = config(serde_json::json!({
+            "gateway": {
+                "name": "archive",
+                "archive": { "dir": "/tmp/igw", "channels": [1, 9], "queue_capacity": 0 }
+            },
+            "channels": [{ "id": 1, "name": "feeder", "protocol": "virtual" }]
+        }));
+        let mut expected = vec![
+            "gateway: archive.queue_capacity must be greater than 0",
+            "gateway: archive.channels: channel 9 is not defined",
+        ];
+        if cfg!(not(feature = "export")) {
+            expected.insert(
+                0,
+                "gateway: archive is configured but this build lacks the 'export' feature",
+            );
+        }
+        let errors: Vec<String> = config
+            .validate_archive()
+            .iter()
+            .map(|e| e.to_string())
+            .collect();
+        assert_eq!(errors, expected);
+    }
+
+    #[test]
+    fn test_replication() {
+        let config = config(serde_json::json!({
+            "gateway": {
+                "name": "replication",
+                "replication": {
+                    "role": "standby",
+                    "address": " ",
+                    "token": "",
+                    "heartbeat_interval_ms": 0,
+                    "queue_capacity": 0
+                }
+            }
+        }));
+        let mut expected = vec![
+            "gateway: replication.address must not be empty",
+            "gateway: replication.token must not be empty",
+            "gateway: replication.heartbeat_interval_ms must be greater than 0",
+            "gateway: replication.queue_capacity must be greater than 0",
+        ];
+        if cfg!(not(feature = "replication")) {
+            expected.insert(
+                0,
+                "gateway: replication is configured but this build lacks the 'replication' feature",
+            );
+        }
+        let errors: Vec<String> = config
+            .validate_replication()
+            .iter()
+            .map(|e| e.to_string())
+            .collect();
+        assert_eq!(errors, expected);
+    }
+
+    #[test]
+    fn test_heartbeat() {
+        let config = config(serde_json::json!({
+            "gateway": {
+                "name": "heartbeat",
+                "heartbeat": {
+                    "channel_id": 1,
+                    "point_id": 7,
+                    "period_ms": 500,
+                    "pattern": "pulse",
+                    "pulse_ms": 500,
+                    "monitored_channels": [1, 2],
+                    "stall_timeout_ms": 0
+                }
+            },
+            "channels": [{
+                "id": 1,
+                "name": "relay",
+                "protocol": "virtual",
+                "points": [{ "id": 1, "name": "hb", "address": "hb" }]
+            }]
+        }));
+        let errors: Vec<String> = config
+            .validate_heartbeat()
+            .iter()
+            .map(|e| e.to_string())
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                "gateway: heartbeat.point_id 7 is not defined on channel 1",
+                "gateway: heartbeat monitors channel 2 which is not an enabled channel",
+                "gateway: heartbeat.stall_timeout_ms must be greater than 0",
+                "gateway: heartbeat.pulse_ms must be greater than 0 and shorter than period_ms",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_authorization() {
+        let config = config(serde_json::json!({
+            "gateway": {
+                "name": "auth",
+                "authorization": {
+                    "callers": [
+                        { "identity": "scada", "level": "operate" },
+                        { "identity": "", "level": "tune" },
+                        { "identity": "scada", "level": "tune" }
+                    ]
+                },
+                "http_api": {
+                    "bind": "127.0.0.1:8080",
+                    "token": "main",
+                    "users": [
+                        { "identity": "scada", "token": "s" },
+                        { "identity": "ops", "token": "main" },
+                        { "identity": "ops", "token": "" }
+                    ]
+                },
+                "audit": { "queue_capacity": 0 }
+            }
+        }));
+        let errors: Vec<String> = config
+            .validate_authorization()
+            .iter()
+            .map(|e| e.to_string())
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                "gateway: authorization.callers: identity must not be empty",
+                "gateway: authorization.callers: 'scada' is listed more than once",
+                "gateway: audit.queue_capacity must be greater than 0",
+                "gateway: http_api.users: token of 'ops' is already in use",
+                "gateway: http_api.users: 'ops' is listed more than once",
+                "gateway: http_api.users: token of 'ops' must not be empty",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_interlocks() {
+        let config = config(serde_json::json!({
+            "gateway": { "name": "interlock" },
+            "channels": [{
+                "id": 1,
+                "name": "feeder",
+                "protocol": "virtual",
+                "points": [{ "id": 1, "name": "breaker", "address": "b" }],
+                "interlocks": [
+                    { "point_id": 1, "check_point": 5, "equals": 0 },
+                    { "point_id": 1, "check_channel": 9, "check_point": 5, "min": 1, "max": 2 },
+                    { "point_id": 1, "check_point": 5, "equals": 0, "max": 1 },
+                    { "point_id": 1, "check_point": 5, "min": 2, "max": 1 },
+                    { "point_id": 2, "check_point": 5 }
+                ]
+            }]
+        }));
+        let errors: Vec<String> = config.validate().iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            errors,
+            vec![
+                "channel 1, point 1: interlock cannot combine 'equals' with 'min'/'max'",
+                "channel 1, point 1: interlock min is greater than max",
+                "channel 1, point 2: interlock refers to a point that is not defined",
+                "channel 1, point 2: interlock needs 'equals' or 'min'/'max'",
+                "channel 1, point 1: interlock checks channel 9 which is not defined",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_feedback() {
+        let config = config(serde_json::json!({
+            "gateway": { "name": "feedback" },
+            "channels": [{
+                "id": 1,
+                "name": "feeder",
+                "protocol": "virtual",
+                "points": [
+                    {
+                        "id": 1, "name": "breaker_cmd", "address": "c",
+                        "feedback": { "point_id": 2, "timeout_ms": 0 }
+                    },
+                    {
+                        "id": 2, "name": "breaker", "address": "b",
+                        "feedback": {
+                            "point_id": 3,
+                            "channel": 9,
+                            "map": [
+                                { "command": 1, "feedback": 2 },
+                                { "command": 1, "feedback": 0 }
+                            ]
+                        }
+                    },
+                    {
+                        "id": 3, "name": "mode", "address": "m",
+                        "feedback": { "point_id": 3, "map": [{ "command": 1, "feedback": 2 }] }
+                    }
+                ]
+            }]
+        }));
+        let errors: Vec<String> = config.validate().iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            errors,
+            vec![
+                "channel 1, point 1: feedback timeout_ms must be greater than 0",
+                "channel 1, point 2: feedback map lists a command value more than once",
+                "channel 1, point 2: feedback reads channel 9 which is not defined",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_redundancy_links() {
+        let channel = |id: u32, backup_of: Option<u32>, protocol: &str| {
+            serde_json::json!({
+                "id": id,
+                "name": format!("ch{}", id),
+                "protocol": protocol,
+                "backup_of": backup_of
+            })
+        };
+        let config = config(serde_json::json!({
+            "gateway": { "name": "pairs" },
+            "channels": [
+                channel(1, None, "virtual"),
+                channel(2, Some(1), "virtual"),
+                channel(3, Some(1), "virtual"),
+                channel(4, Some(2), "virtual"),
+                channel(5, Some(5), "virtual"),
+                channel(6, Some(9), "virtual"),
+                channel(7, Some(1), "modbus"),
+            ]
+        }));
+        let errors: Vec<String> = config
+            .validate_redundancy()
+            .iter()
+            .map(|e| e.to_string())
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                "channel 3: channel 1 already has backup 2",
+                "channel 4: backup_of refers to channel 2 which is itself a backup",
+                "channel 5: backup_of refers to the channel itself",
+                "channel 6: backup_of refers to channel 9 which is not defined or not enabled",
+                "channel 7: a backup without points uses the points of channel 1 and needs its protocol 'virtual'",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dependencies() {
+        let config = config(serde_json::json!({
+            "gateway": { "name": "plant" },
+            "channels": [
+                { "id": 1, "name": "gpio", "protocol": "virtual" },
+                { "id": 2, "name": "a", "protocol": "virtual", "depends_on": [1, 3] },
+                { "id": 3, "name": "b", "protocol": "virtual", "depends_on": [4] },
+                { "id": 4, "name": "c", "protocol": "virtual", "depends_on": [2] },
+                { "id": 5, "name": "d", "protocol": "virtual", "depends_on": [5, 9, 6] },
+                { "id": 6, "name": "e", "protocol": "virtual", "backup_of": 1 },
+                { "id": 7, "name": "f", "protocol": "virtual", "depends_on": [1], "dependency_timeout_ms": 0 }
+            ]
+        }));
+        let errors: Vec<String> = config
+            .validate_dependencies()
+            .iter()
+            .map(|e| e.to_string())
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                "channel 5: depends_on refers to the channel itself",
+                "channel 5: depends_on refers to channel 9 which is not defined",
+                "channel 5: depends_on refers to channel 6 which is a backup",
+                "channel 7: dependency_timeout_ms must be greater than 0",
+                "channel 2: depends_on forms a cycle: 2 -> 3 -> 4 -> 2",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_poll_phases() {
+        let config = config(serde_json::json!({
+            "gateway": { "name": "trunk", "default_poll_interval_ms": 1000 },
+            "channels": [
+                { "id": 1, "name": "a", "protocol": "virtual", "bus": "rs485-1" },
+                { "id": 2, "name": "b", "protocol": "virtual", "bus": "rs485-1", "poll_offset_ms": 500 },
+                { "id": 3, "name": "c", "protocol": "virtual", "bus": " " },
+                { "id": 4, "name": "d", "protocol": "virtual", "poll_offset_ms": 1000 },
+                { "id": 5, "name": "e", "protocol": "virtual", "poll_interval_ms": 200, "poll_offset_ms": 250 }
+            ]
+        }));
+        let errors: Vec<String> = config
+            .validate_poll_phases()
+            .iter()
+            .map(|e| e.to_string())
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                "channel 3: bus must not be empty",
+                "channel 4: poll_offset_ms 1000 must be less than the poll interval of 1000 ms",
+                "channel 5: poll_offset_ms 250 must be less than the poll interval of 200 ms",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_transports() {
+        let config = config(serde_json::json!({
+            "gateway": { "name": "trunk" },
+            "transports": {
+                "rs485_1": { "type": "serial", "device": "/dev/ttyUSB0" },
+                "rs485_2": { "type": "serial", "device": "/dev/ttyUSB1", "baud_rate": 0 },
+                "moxa": { "type": "tcp", "host": "" }
+            },
+            "channels": [
+                { "id": 1, "name": "a", "protocol": "modbus", "transport": "rs485_1" },
+                { "id": 2, "name": "b", "protocol": "virtual", "transport": "rs485_1" },
+                { "id": 3, "name": "c", "protocol": "modbus", "transport": "rs485_3" }
+            ]
+        }));
+        // Whether the modbus protocol is built in does not matter here
+        let errors: Vec<String> = config
+            .validate()
+            .iter()
+            .map(|e| e.to_string())
+            .filter(|e| e.contains("transport"))
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                "channel 3: unknown transport 'rs485_3' (defined: moxa, rs485_1, rs485_2)",
+                "gateway: transports.moxa: host must not be empty",
+                "gateway: transports.rs485_2: baud_rate must be greater than 0",
+                "channel 2: protocol 'virtual' cannot use a shared transport",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_modbus_server_registers() {
+        let register = |area: &str, address: u16, point_id: u32, format: &str| {
+            serde_json::json!({
+                "area": area,
+                "address": address,
+                "channel_id": 1,
+                "point_id": point_id,
+                "format": format
+            })
+        };
+        let config = config(serde_json::json!({
+            "gateway": {
+                "name": "hmi",
+                "modbus_server": {
+                    "bind": "0.0.0.0:1502",
+                    "registers": [
+                        register("holding", 0, 1, "float32"),
+                        register("holding", 1, 2, "u16"),
+                        register("input", 1, 2, "u16"),
+                        register("coil", 0, 1, "u16"),
+                        register("holding", 65534, 9, "float64"),
+                        register("input", 10, 1, "string"),
+                        { "address": 20, "channel_id": 7, "point_id": 1 }
+                    ]
+                }
+            },
+            "channels": [{
+                "id": 1,
+                "name": "hub",
+                "protocol": "virtual",
+                "points": [
+                    { "id": 1, "name": "a", "address": "a" },
+                    { "id": 2, "name": "b", "address": "b" }
+                ]
+            }]
+        }));
+        let errors: Vec<String> = config
+            .validate_modbus_server()
+            .iter()
+            .map(|e| e.to_string())
+            .collect();
+        let mut expected = Vec::new();
+        if cfg!(not(feature = "modbus-server")) {
+            expected.push(
+                "gateway: modbus_server is configured but this build lacks the 'modbus-server' feature",
+            );
+        }
+        expected.extend([
+            "gateway: modbus_server.registers[1]: overlaps registers[0]",
+            "gateway: modbus_server.registers[4]: point 9 is not defined in channel 1",
+            "gateway: modbus_server.registers[4]: address 65534 runs past register 65535",
+            "gateway: modbus_server.registers[5]: string registers are not supported",
+            "gateway: modbus_server.registers[6]: channel 7 is not defined",
+        ]);
+        assert_eq!(errors, expected);
+    }
+
+    #[test]
+    fn test_reports_every_problem() {
+        let config = config(serde_json::json!({
+            "gateway": { "name": "bad", "default_poll_interval_ms": 0, "max_concurrent_polls": 0 },
+            "channels": [
+                {
+                    "id": 1,
+                    "name": "hub",
+                    "protocol": "virtual",
+                    "poll_interval_ms": 0,
+                    "event_buffer": { "capacity": 0 },
+                    "command_queue": { "max_rate": -5, "max_depth": 0 },
+                    "points": [
+                        {
+                            "id": 1, "name": "a", "address": "a",
+                            "transform": { "kind": { "type": "polynomial", "coefficients": [] } }
+                        },
+                        {
+                            "id": 1, "name": "b", "address": "b", "max_age_ms": 0,
+                            "transform": { "min_value": 10.0, "max_value": 0.0 }
+                        }
+                    ]
+                },
+                { "id": 1, "name": "copy", "protocol": "virtual" },
+                { "id": 2, "name": "typo", "protocol": "modbsu" },
+                { "id": 3, "name": "off", "protocol": "modbsu", "enabled": false }
+            ]
+        }));
+
+        let errors: Vec<String> = config.validate().iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            errors[..11],
+            [
+                "gateway: default_poll_interval_ms must be greater than 0",
+                "gateway: max_concurrent_polls must be greater than 0",
+                "channel 1: poll_interval_ms must be greater than 0",
+                "channel 1: event_buffer.capacity must be greater than 0",
+                "channel 1: command_queue.max_depth must be greater than 0",
+                "channel 1: command_queue.max_rate must be 0 or more",
+                "channel 1, point 1: transform: a polynomial needs at least one coefficient",
+                "channel 1, point 1: duplicate point id (used by 'a' and 'b')",
+                "channel 1, point 1: max_age_ms must be greater than 0",
+                "channel 1, point 1: transform: min_value 10 is greater than max_value 0",
+                "channel 1: duplicate channel id (used by 'hub' and 'copy')",
+            ]
+        );
+        // Other tests may register protocols concurrently
+        assert_eq!(errors.len(), 12);
+        assert!(errors[11].starts_with("channel 2: unknown protocol 'modbsu' (supported: "));
+        assert!(errors[11].contains("virtual"));
+    }
+
+    #[test]
+    fn test_point_ttl() {
+        let config = config(serde_json::json!({
+            "gateway": { "name": "ttl" },
+            "channels": [{
+                "id": 1,
+                "name": "hub",
+                "protocol": "virtual",
+                "points": [
+                    { "id": 1, "name": "a", "address": "a", "ttl_ms": 5000, "out_of_service_ms": 60000 },
+                    { "id": 2, "name": "b", "address": "b", "ttl_ms": 0 },
+                    { "id": 3, "name": "c", "address": "c", "out_of_service_ms": 60000 },
+                    { "id": 4, "name": "d", "address": "d", "ttl_ms": 5000, "out_of_service_ms": 5000 }
+                ]
+            }]
+        }));
+
+        let errors: Vec<String> = config.validate().iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            errors,
+            [
+                "channel 1, point 2: ttl_ms must be greater than 0",
+                "channel 1, point 3: out_of_service_ms requires ttl_ms",
+                "channel 1, point 4: out_of_service_ms (5000) must be greater than ttl_ms (5000)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_alarm_checks() {
+        let channel: ChannelConfig = serde_json::from_value(serde_json::json!({
+            "id": 7,
+            "name": "tank",
+            "protocol": "virtual",
+            "points": [
+                {
+                    "id": 1, "name": "level", "address": "level",
+                    "alarms": {
+                        "high": { "id": 11, "setpoint": 90.0, "hysteresis": 2.0 },
+                        "low": { "id": 2, "setpoint": 10.0 }
+                    }
+                },
+                {
+                    "id": 2, "name": "temp", "address": "temp",
+                    "alarms": {
+                        "high": { "id": 11, "setpoint": 60.0 },
+                        "low_low": { "id": 12, "setpoint": 0.0, "hysteresis": -1.0 }
+                    }
+                }
+            ]
+        }))
+        .unwrap();
+
+        let errors: Vec<String> = validate_channel(&channel)
+            .iter()
+            .map(|e| e.to_string())
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                "channel 7, point 1: alarms.low: id 2 is a point id",
+                "channel 7, point 2: alarms.high: id 11 is used twice",
+                "channel 7, point 2: alarms.low_low: hysteresis must be 0 or more",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unit_conversion_checks() {
+        let channel: ChannelConfig = serde_json::from_value(serde_json::json!({
+            "id": 7,
+            "name": "skid",
+            "protocol": "virtual",
+            "points": [
+                {
+                    "id": 1, "name": "temp", "address": "temp", "unit": "degC",
+                    "transform": { "unit_conversion": { "to": "degF" } }
+                },
+                {
+                    "id": 2, "name": "flow", "address": "flow",
+                    "transform": { "unit_conversion": { "from": "L/min", "to": "m3/h" } }
+                },
+                {
+                    "id": 3, "name": "pressure", "address": "pressure", "unit": "kPa",
+                    "transform": { "unit_conversion": { "to": "degF" } }
+                },
+                {
+                    "id": 4, "name": "energy", "address": "energy", "unit": "kWh",
+                    "transform": { "unit_conversion": { "to": "therm" } }
+                },
+                {
+                    "id": 5, "name": "level", "address": "level",
+                    "transform": { "unit_conversion": { "to": "bar" } }
+                }
+            ]
+        }))
+        .unwrap();
+
+        let errors: Vec<String> = validate_channel(&channel)
+            .iter()
+            .map(|e| e.to_string())
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                "channel 7, point 3: transform.unit_conversion: cannot convert kPa (pressure) to degF (temperature)",
+                "channel 7, point 4: transform.unit_conversion: unknown unit 'therm'",
+                "channel 7, point 5: transform.unit_conversion: no unit to convert to bar from (set the point's unit or `from`)",
+            ]
+        );
+
+        // The point reports the converted unit
+        let points = super::super::factory::build_point_configs(&channel).unwrap();
+        assert_eq!(points[0].unit.as_deref(), Some("degF"));
+        assert!((points[0].transform.apply(100.0) - 212.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_address_checks() {
+        let mut channel: ChannelConfig = serde_json::from_value(serde_json::json!({
+            "id": 7,
+            "name": "rtu",
+            "protocol": "iec104",
+            "parameters": { "address": "10.0.0.5:2404" },
+            "points": [
+                { "id": 1, "name": "ok", "address": "1001" },
+                { "id": 2, "name": "nan", "address": "abc" },
+                { "id": 3, "name": "big", "address": "16777216" },
+                { "id": 4, "name": "off", "address": "abc", "enabled": false }
+            ]
+        }))
+        .unwrap();
+
+        let errors = validate_channel(&channel);
+        if super::super::factory::SUPPORTED_PROTOCOLS.contains(&"iec104") {
+            let points: Vec<_> = errors.iter().map(|e| e.point).collect();
+            assert_eq!(points, vec![Some(2), Some(3)]);
+        } else {
+            assert_eq!(errors.len(), 1);
+            assert!(errors[0].message.contains("enable the 'iec104' feature"));
+        }
+
+        channel.enabled = false;
+        assert!(validate_channel(&channel).is_empty());
+    }
+
+    #[test]
+    fn test_parameter_checks() {
+        let config = config(serde_json::json!({
+            "gateway": { "name": "params" },
+            "channels": [
+                { "id": 1, "name": "PLC1", "protocol": "modbus", "parameters": { "hosts": "10.0.0.5" } },
+                { "id": 2, "name": "RTU1", "protocol": "IEC104", "parameters": { "common_address": 3 } },
+                { "id": 3, "name": "hub", "protocol": "virtual" },
+                { "id": 4, "name": "off", "protocol": "mqtt", "enabled": false }
+            ]
+        }));
+
+        let errors: Vec<String> = config
+            .validate()
+            .iter()
+            .filter(|e| e.message.contains("parameters"))
+            .map(|e| e.to_string())
+            .collect();
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].starts_with(
+            "channel 1: invalid modbus parameters of 'PLC1': unknown field `hosts`, expected one of `host`"
+        ));
+        assert_eq!(
+            errors[1],
+            "channel 2: invalid iec104 parameters of 'RTU1': missing field `address`"
+        );
+    }
+
+    #[test]
+    fn test_ensure_valid() {
+        assert!(ensure_valid(Vec::new()).is_ok());
+        let err = ensure_valid(vec![ValidationError::channel(1, "broken")]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Configuration error: invalid configuration: channel 1: broken"
+        );
+    }
+}
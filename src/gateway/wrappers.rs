@@ -5,13 +5,15 @@
 
 use async_trait::async_trait;
 
+use crate::core::data::DataBatch;
 use crate::core::error::Result;
+use crate::core::point::PointConfig;
 use crate::core::traits::{
     AdjustmentCommand, ControlCommand, DataEventReceiver, Diagnostics, EventDrivenProtocol,
-    PollResult, Protocol, ProtocolClient,
+    PollResult, Protocol, ProtocolClient, WriteResult,
 };
 
-use super::runtime::ChannelRuntime;
+use super::runtime::{ChannelCapabilities, ChannelRuntime};
 
 // ============================================================================
 // Virtual Channel Wrapper
@@ -50,6 +52,10 @@ impl ChannelRuntime for VirtualRuntime {
         true
     }
 
+    fn capabilities(&self) -> ChannelCapabilities {
+        ChannelCapabilities::of(&self.channel)
+    }
+
     async fn connect(&mut self) -> Result<()> {
         self.channel.connect().await
     }
@@ -62,22 +68,20 @@ impl ChannelRuntime for VirtualRuntime {
         self.channel.poll_once().await
     }
 
-    async fn write_control(&mut self, commands: &[(u32, f64)]) -> Result<usize> {
+    async fn write_control(&mut self, commands: &[(u32, f64)]) -> Result<WriteResult> {
         let cmds: Vec<_> = commands
             .iter()
             .map(|(id, value)| ControlCommand::latching(*id, *value != 0.0))
             .collect();
-        let result = self.channel.write_control(&cmds).await?;
-        Ok(result.success_count)
+        self.channel.write_control(&cmds).await
     }
 
-    async fn write_adjustment(&mut self, adjustments: &[(u32, f64)]) -> Result<usize> {
+    async fn write_adjustment(&mut self, adjustments: &[(u32, f64)]) -> Result<WriteResult> {
         let adjs: Vec<_> = adjustments
             .iter()
             .map(|(id, value)| AdjustmentCommand::new(*id, *value))
             .collect();
-        let result = self.channel.write_adjustment(&adjs).await?;
-        Ok(result.success_count)
+        self.channel.write_adjustment(&adjs).await
     }
 
     fn subscribe(&self) -> Option<DataEventReceiver> {
@@ -92,6 +96,15 @@ impl ChannelRuntime for VirtualRuntime {
         self.channel.stop().await
     }
 
+    async fn restore(&mut self, batch: &DataBatch) {
+        self.channel.restore(batch);
+    }
+
+    async fn update_points(&mut self, points: &[PointConfig]) -> Result<()> {
+        self.channel.set_points(points.to_vec());
+        Ok(())
+    }
+
     async fn diagnostics(&self) -> Result<Diagnostics> {
         self.channel.diagnostics().await
     }
@@ -107,7 +120,7 @@ pub use modbus_wrapper::ModbusRuntime;
 #[cfg(feature = "modbus")]
 mod modbus_wrapper {
     use super::*;
-    use crate::protocols::modbus::ModbusChannel;
+    use crate::protocols::modbus::{ModbusChannel, SUPPORTED_FUNCTION_CODES};
 
     /// Modbus channel runtime wrapper.
     pub struct ModbusRuntime {
@@ -140,6 +153,15 @@ mod modbus_wrapper {
             false
         }
 
+        fn capabilities(&self) -> ChannelCapabilities {
+            let config = self.channel.config();
+            ChannelCapabilities::of(&self.channel).with_limits(serde_json::json!({
+                "max_registers_per_read": config.max_batch_size,
+                "max_gap": config.max_gap,
+                "function_codes": SUPPORTED_FUNCTION_CODES,
+            }))
+        }
+
         async fn connect(&mut self) -> Result<()> {
             self.channel.connect().await
         }
@@ -152,22 +174,20 @@ mod modbus_wrapper {
             self.channel.poll_once().await
         }
 
-        async fn write_control(&mut self, commands: &[(u32, f64)]) -> Result<usize> {
+        async fn write_control(&mut self, commands: &[(u32, f64)]) -> Result<WriteResult> {
             let cmds: Vec<_> = commands
                 .iter()
                 .map(|(id, value)| ControlCommand::latching(*id, *value != 0.0))
                 .collect();
-            let result = self.channel.write_control(&cmds).await?;
-            Ok(result.success_count)
+            self.channel.write_control(&cmds).await
         }
 
-        async fn write_adjustment(&mut self, adjustments: &[(u32, f64)]) -> Result<usize> {
+        async fn write_adjustment(&mut self, adjustments: &[(u32, f64)]) -> Result<WriteResult> {
             let adjs: Vec<_> = adjustments
                 .iter()
                 .map(|(id, value)| AdjustmentCommand::new(*id, *value))
                 .collect();
-            let result = self.channel.write_adjustment(&adjs).await?;
-            Ok(result.success_count)
+            self.channel.write_adjustment(&adjs).await
         }
 
         fn subscribe(&self) -> Option<DataEventReceiver> {
@@ -182,9 +202,24 @@ mod modbus_wrapper {
             Ok(()) // No-op for polling channel
         }
 
+        async fn update_points(&mut self, points: &[PointConfig]) -> Result<()> {
+            self.channel.set_points(points.to_vec()).await;
+            Ok(())
+        }
+
+        async fn refresh(&mut self) -> Result<()> {
+            self.channel.refresh();
+            Ok(())
+        }
+
         async fn diagnostics(&self) -> Result<Diagnostics> {
             self.channel.diagnostics().await
         }
+
+        async fn reset_diagnostics(&mut self) -> Result<()> {
+            self.channel.reset_diagnostics().await;
+            Ok(())
+        }
     }
 }
 
@@ -231,6 +266,10 @@ mod iec104_wrapper {
             true
         }
 
+        fn capabilities(&self) -> ChannelCapabilities {
+            ChannelCapabilities::of(&self.channel)
+        }
+
         async fn connect(&mut self) -> Result<()> {
             self.channel.connect().await
         }
@@ -243,22 +282,20 @@ mod iec104_wrapper {
             self.channel.poll_once().await
         }
 
-        async fn write_control(&mut self, commands: &[(u32, f64)]) -> Result<usize> {
+        async fn write_control(&mut self, commands: &[(u32, f64)]) -> Result<WriteResult> {
             let cmds: Vec<_> = commands
                 .iter()
                 .map(|(id, value)| ControlCommand::latching(*id, *value != 0.0))
                 .collect();
-            let result = self.channel.write_control(&cmds).await?;
-            Ok(result.success_count)
+            self.channel.write_control(&cmds).await
         }
 
-        async fn write_adjustment(&mut self, adjustments: &[(u32, f64)]) -> Result<usize> {
+        async fn write_adjustment(&mut self, adjustments: &[(u32, f64)]) -> Result<WriteResult> {
             let adjs: Vec<_> = adjustments
                 .iter()
                 .map(|(id, value)| AdjustmentCommand::new(*id, *value))
                 .collect();
-            let result = self.channel.write_adjustment(&adjs).await?;
-            Ok(result.success_count)
+            self.channel.write_adjustment(&adjs).await
         }
 
         fn subscribe(&self) -> Option<DataEventReceiver> {
@@ -273,6 +310,10 @@ mod iec104_wrapper {
             self.channel.stop().await
         }
 
+        async fn refresh(&mut self) -> Result<()> {
+            self.channel.general_interrogation().await
+        }
+
         async fn diagnostics(&self) -> Result<Diagnostics> {
             self.channel.diagnostics().await
         }
@@ -289,7 +330,9 @@ pub use opcua_wrapper::OpcUaRuntime;
 #[cfg(feature = "opcua")]
 mod opcua_wrapper {
     use super::*;
+    use crate::core::data::PointId;
     use crate::protocols::opcua::OpcUaChannel;
+    use chrono::{DateTime, Utc};
 
     /// OPC UA channel runtime wrapper.
     pub struct OpcUaRuntime {
@@ -322,6 +365,123 @@ mod opcua_wrapper {
             true
         }
 
+        fn capabilities(&self) -> ChannelCapabilities {
+            ChannelCapabilities::of(&self.channel)
+        }
+
+        async fn connect(&mut self) -> Result<()> {
+            self.channel.connect().await
+        }
+
+        async fn disconnect(&mut self) -> Result<()> {
+            self.channel.disconnect().await
+        }
+
+        async fn poll_once(&mut self) -> PollResult {
+            self.channel.poll_once().await
+        }
+
+        async fn write_control(&mut self, commands: &[(u32, f64)]) -> Result<WriteResult> {
+            let cmds: Vec<_> = commands
+                .iter()
+                .map(|(id, value)| ControlCommand::latching(*id, *value != 0.0))
+                .collect();
+            self.channel.write_control(&cmds).await
+        }
+
+        async fn write_adjustment(&mut self, adjustments: &[(u32, f64)]) -> Result<WriteResult> {
+            let adjs: Vec<_> = adjustments
+                .iter()
+                .map(|(id, value)| AdjustmentCommand::new(*id, *value))
+                .collect();
+            self.channel.write_adjustment(&adjs).await
+        }
+
+        fn subscribe(&self) -> Option<DataEventReceiver> {
+            Some(self.channel.subscribe())
+        }
+
+        async fn start_events(&mut self) -> Result<()> {
+            self.channel.start().await
+        }
+
+        async fn stop_events(&mut self) -> Result<()> {
+            self.channel.stop().await
+        }
+
+        async fn read_history(
+            &mut self,
+            points: &[PointId],
+            start: DateTime<Utc>,
+            end: DateTime<Utc>,
+        ) -> Result<DataBatch> {
+            let mut samples = DataBatch::new();
+            for &point_id in points {
+                for point in self.channel.history_read(point_id, start, end).await? {
+                    samples.add(point);
+                }
+            }
+            samples.sort_by_timestamp();
+            Ok(samples)
+        }
+
+        async fn refresh(&mut self) -> Result<()> {
+            self.channel.read_all().await.map(|_| ())
+        }
+
+        async fn diagnostics(&self) -> Result<Diagnostics> {
+            self.channel.diagnostics().await
+        }
+    }
+}
+
+// ============================================================================
+// MQTT Channel Wrapper
+// ============================================================================
+
+#[cfg(feature = "mqtt")]
+pub use mqtt_wrapper::MqttRuntime;
+
+#[cfg(feature = "mqtt")]
+mod mqtt_wrapper {
+    use super::*;
+    use crate::protocols::mqtt::MqttChannel;
+
+    /// MQTT channel runtime wrapper.
+    pub struct MqttRuntime {
+        id: u32,
+        name: String,
+        channel: MqttChannel,
+    }
+
+    impl MqttRuntime {
+        pub fn new(id: u32, name: String, channel: MqttChannel) -> Self {
+            Self { id, name, channel }
+        }
+    }
+
+    #[async_trait]
+    impl ChannelRuntime for MqttRuntime {
+        fn id(&self) -> u32 {
+            self.id
+        }
+
+        fn name(&self) -> &str {
+            &self.name
+        }
+
+        fn protocol(&self) -> &str {
+            "mqtt"
+        }
+
+        fn is_event_driven(&self) -> bool {
+            true
+        }
+
+        fn capabilities(&self) -> ChannelCapabilities {
+            ChannelCapabilities::of(&self.channel)
+        }
+
         async fn connect(&mut self) -> Result<()> {
             self.channel.connect().await
         }
@@ -334,22 +494,20 @@ mod opcua_wrapper {
             self.channel.poll_once().await
         }
 
-        async fn write_control(&mut self, commands: &[(u32, f64)]) -> Result<usize> {
+        async fn write_control(&mut self, commands: &[(u32, f64)]) -> Result<WriteResult> {
             let cmds: Vec<_> = commands
                 .iter()
                 .map(|(id, value)| ControlCommand::latching(*id, *value != 0.0))
                 .collect();
-            let result = self.channel.write_control(&cmds).await?;
-            Ok(result.success_count)
+            self.channel.write_control(&cmds).await
         }
 
-        async fn write_adjustment(&mut self, adjustments: &[(u32, f64)]) -> Result<usize> {
+        async fn write_adjustment(&mut self, adjustments: &[(u32, f64)]) -> Result<WriteResult> {
             let adjs: Vec<_> = adjustments
                 .iter()
                 .map(|(id, value)| AdjustmentCommand::new(*id, *value))
                 .collect();
-            let result = self.channel.write_adjustment(&adjs).await?;
-            Ok(result.success_count)
+            self.channel.write_adjustment(&adjs).await
         }
 
         fn subscribe(&self) -> Option<DataEventReceiver> {
@@ -413,6 +571,10 @@ mod can_wrapper {
             true
         }
 
+        fn capabilities(&self) -> ChannelCapabilities {
+            ChannelCapabilities::of(&self.channel).read_only()
+        }
+
         async fn connect(&mut self) -> Result<()> {
             self.channel.connect().await
         }
@@ -425,14 +587,14 @@ mod can_wrapper {
             self.channel.poll_once().await
         }
 
-        async fn write_control(&mut self, _commands: &[(u32, f64)]) -> Result<usize> {
+        async fn write_control(&mut self, _commands: &[(u32, f64)]) -> Result<WriteResult> {
             // CAN write not supported
-            Ok(0)
+            Ok(WriteResult::success(0))
         }
 
-        async fn write_adjustment(&mut self, _adjustments: &[(u32, f64)]) -> Result<usize> {
+        async fn write_adjustment(&mut self, _adjustments: &[(u32, f64)]) -> Result<WriteResult> {
             // CAN write not supported
-            Ok(0)
+            Ok(WriteResult::success(0))
         }
 
         fn subscribe(&self) -> Option<DataEventReceiver> {
@@ -496,6 +658,10 @@ mod gpio_wrapper {
             false
         }
 
+        fn capabilities(&self) -> ChannelCapabilities {
+            ChannelCapabilities::of(&self.channel)
+        }
+
         async fn connect(&mut self) -> Result<()> {
             self.channel.connect().await
         }
@@ -508,22 +674,20 @@ mod gpio_wrapper {
             self.channel.poll_once().await
         }
 
-        async fn write_control(&mut self, commands: &[(u32, f64)]) -> Result<usize> {
+        async fn write_control(&mut self, commands: &[(u32, f64)]) -> Result<WriteResult> {
             let cmds: Vec<_> = commands
                 .iter()
                 .map(|(id, value)| ControlCommand::latching(*id, *value != 0.0))
                 .collect();
-            let result = self.channel.write_control(&cmds).await?;
-            Ok(result.success_count)
+            self.channel.write_control(&cmds).await
         }
 
-        async fn write_adjustment(&mut self, adjustments: &[(u32, f64)]) -> Result<usize> {
+        async fn write_adjustment(&mut self, adjustments: &[(u32, f64)]) -> Result<WriteResult> {
             let adjs: Vec<_> = adjustments
                 .iter()
                 .map(|(id, value)| AdjustmentCommand::new(*id, *value))
                 .collect();
-            let result = self.channel.write_adjustment(&adjs).await?;
-            Ok(result.success_count)
+            self.channel.write_adjustment(&adjs).await
         }
 
         fn subscribe(&self) -> Option<DataEventReceiver> {
@@ -42,11 +42,22 @@ pub mod config;
 pub mod core;
 pub mod gateway;
 pub mod protocols;
+pub mod store;
+
+#[cfg(any(test, feature = "test-util"))]
+#[cfg_attr(docsrs, doc(cfg(feature = "test-util")))]
+pub mod testing;
+
+// Compile the README examples as doctests
+#[cfg(all(doctest, feature = "modbus", feature = "iec104"))]
+#[doc = include_str!("../README.md")]
+struct ReadmeDoctests;
 
 /// Prelude module for convenient imports
 pub mod prelude {
     pub use crate::core::{
         data::*,
+        diagnostics::DiagnosticsRecorder,
         error::{GatewayError, Result},
         logging::*,
         point::*,
@@ -56,7 +67,7 @@ pub mod prelude {
 }
 
 // Re-export core types at crate root for convenience
-pub use crate::core::data::{DataBatch, DataPoint, Value};
+pub use crate::core::data::{DataBatch, DataPoint, PointId, Value};
 pub use crate::core::error::{GatewayError, Result};
 pub use crate::core::logging::{
     ChannelLogConfig, ChannelLogEvent, ChannelLogHandler, LogContext, LogEventType, LogVerbosity,
@@ -77,6 +88,6 @@ pub use crate::config::ChannelBuildResult;
 // Re-export gateway types (runtime trait + config)
 // 注：Gateway struct 已移至 examples/gateway_demo.rs
 pub use crate::gateway::{
-    parse_address, ChannelConfig, ChannelMode, ChannelModeConfig, ChannelRuntime, ConfigError,
-    GatewayConfig, GatewayGlobalConfig, PointDef,
+    parse_address, ChannelCapabilities, ChannelConfig, ChannelMode, ChannelModeConfig,
+    ChannelRuntime, ConfigError, GatewayConfig, GatewayGlobalConfig, PointDef,
 };
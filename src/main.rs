@@ -1,20 +1,74 @@
 //! IGW CLI Entry Point
 //!
-//! 工具命令行界面，提供协议查询和示例配置生成。
+//! 工具命令行界面，提供协议查询、示例配置生成、网关运行和现场调试命令。
 //!
-//! 要运行完整的网关，请使用 `examples/gateway_demo.rs`：
+//! 运行网关（数据保存在内存中）：
 //! ```bash
-//! cargo run --example gateway_demo --features full -- config.toml
+//! cargo run --features full -- run config.toml
 //! ```
+//!
+//! 现场调试：
+//! ```bash
+//! igw validate -c config.toml
+//! igw config-schema modbus                   # 列出通道参数字段
+//! igw monitor -c config.toml      # 需要 `tui` feature
+//! igw read -c config.toml --channel 1 --points 1001,1002
+//! igw write -c config.toml --channel 1 --point 2001 --value 1
+//! igw scan modbus 192.168.1.0/24:502
+//! igw probe -c config.toml --channel 1       # 检查 Modbus 点的功能码（只读）
+//! igw discover modbus 192.168.1.10:502 --slave 1 --range 0-500 > device.toml
+//! igw snapshot save -c config.toml state.json   # 需要 `http-api` feature
+//! igw snapshot load -c config.toml state.json
+//! igw override set -c config.toml --channel 1 --point 1001 --value 42 --expires-in 3600
+//! igw override clear -c config.toml --channel 1 --point 1001
+//! igw override list -c config.toml
+//! igw refresh -c config.toml --channel 3     # 立即总召/全量轮询
+//! ```
+//!
+//! 日志输出到 stderr：`-v` 为 info，`-vv` 为 igw 的 debug，`-vvv` 为 trace；
+//! 设置 `RUST_LOG` 时以其为准（例如 `RUST_LOG=igw::protocols::modbus=debug`）。
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 
 use clap::{Parser, Subcommand};
+use tokio::sync::RwLock;
 
+use igw::core::data::{PointId, Value};
 use igw::core::metadata::get_protocol_registry;
+use igw::core::traits::{DataEvent, DataEventReceiver};
+use igw::gateway::params::{protocol_params, ProtocolParams, PROTOCOL_PARAMS};
+use igw::gateway::{
+    factory, Caller, ChannelConfig, ChannelRuntime, GatewayConfig, GatewayRuntime, PermissionLevel,
+};
+use igw::store::{DataStore, MemoryStore};
+
+#[path = "cli/discover.rs"]
+mod discover;
+#[path = "cli/logs.rs"]
+mod logs;
+#[cfg(feature = "tui")]
+#[path = "cli/monitor.rs"]
+mod monitor;
+#[path = "cli/probe.rs"]
+mod probe;
+#[path = "cli/scan.rs"]
+mod scan;
+#[cfg(feature = "http-api")]
+#[path = "cli/snapshot.rs"]
+mod snapshot;
+
+type CliResult<T = ()> = Result<T, Box<dyn std::error::Error>>;
 
 /// Industrial Gateway - Universal SCADA Protocol Gateway
 #[derive(Parser, Debug)]
 #[command(name = "igw", version, about, long_about = None)]
 struct Cli {
+    /// Log more (-v info, -vv debug, -vvv trace); RUST_LOG takes precedence
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -30,10 +84,253 @@ enum Commands {
         #[arg(default_value = "modbus")]
         protocol: String,
     },
+
+    /// Run the gateway until Ctrl+C or SIGTERM (SIGHUP reloads the configuration)
+    Run {
+        /// Configuration file path
+        config: PathBuf,
+    },
+
+    /// Run the gateway with a live terminal dashboard
+    #[cfg(feature = "tui")]
+    Monitor {
+        /// Configuration file path
+        #[arg(short, long, default_value = "config.toml")]
+        config: PathBuf,
+    },
+
+    /// Check a configuration file and print every problem found
+    Validate {
+        /// Configuration file path
+        #[arg(short, long, default_value = "config.toml")]
+        config: PathBuf,
+    },
+
+    /// Print the accepted [channels.parameters] fields per protocol
+    ConfigSchema {
+        /// Protocol to print (all built-in protocols if omitted)
+        protocol: Option<String>,
+    },
+
+    /// Connect one channel, poll it once and print the values
+    Read {
+        /// Configuration file path
+        #[arg(short, long, default_value = "config.toml")]
+        config: PathBuf,
+
+        /// Channel id
+        #[arg(long)]
+        channel: u32,
+
+        /// Point ids to read (all points of the channel if omitted)
+        #[arg(long, value_delimiter = ',')]
+        points: Vec<PointId>,
+    },
+
+    /// Send a single command, checked and audited like those of a running gateway
+    Write {
+        /// Configuration file path
+        #[arg(short, long, default_value = "config.toml")]
+        config: PathBuf,
+
+        /// Channel id
+        #[arg(long)]
+        channel: u32,
+
+        /// Point id
+        #[arg(long)]
+        point: PointId,
+
+        /// Value (0/1 for controls)
+        #[arg(long)]
+        value: f64,
+
+        /// Send as an adjustment (setpoint) instead of a control
+        #[arg(long)]
+        adjustment: bool,
+    },
+
+    /// Probe a network range for responding devices
+    Scan {
+        /// Protocol to scan for (only "modbus" for now)
+        protocol: String,
+
+        /// Host, IPv4 network or range with optional port, e.g. 192.168.1.0/24:502
+        target: String,
+
+        /// Unit ids to probe, e.g. 1-247 or 1,2,10-20
+        #[arg(long, default_value = "1-247")]
+        units: String,
+
+        /// Response timeout per probe in milliseconds
+        #[arg(long, default_value_t = 200)]
+        timeout_ms: u64,
+    },
+
+    /// Check the function codes of a Modbus TCP channel's points (read-only)
+    Probe {
+        /// Configuration file path
+        #[arg(short, long, default_value = "config.toml")]
+        config: PathBuf,
+
+        /// Channel id
+        #[arg(long)]
+        channel: u32,
+
+        /// Point ids to probe (all points of the channel if omitted)
+        #[arg(long, value_delimiter = ',')]
+        points: Vec<PointId>,
+
+        /// Pause between requests in milliseconds
+        #[arg(long, default_value_t = 100)]
+        interval_ms: u64,
+
+        /// Response timeout per request in milliseconds
+        #[arg(long, default_value_t = 1000)]
+        timeout_ms: u64,
+    },
+
+    /// Sweep a device's registers and print a skeleton channel configuration
+    Discover {
+        /// Protocol to discover (only "modbus" for now)
+        protocol: String,
+
+        /// Device address with optional port, e.g. 192.168.1.10:502
+        target: String,
+
+        /// Unit id (slave id) of the device
+        #[arg(long, default_value_t = 1)]
+        slave: u8,
+
+        /// Registers to sweep, e.g. 0-500
+        #[arg(long, default_value = "0-500")]
+        range: String,
+
+        /// Channel id of the generated configuration
+        #[arg(long, default_value_t = 1)]
+        channel: u32,
+
+        /// Pause between requests in milliseconds
+        #[arg(long, default_value_t = 100)]
+        interval_ms: u64,
+
+        /// Response timeout per request in milliseconds
+        #[arg(long, default_value_t = 1000)]
+        timeout_ms: u64,
+    },
+
+    /// Save or load the data store of a running gateway (over its HTTP API)
+    #[cfg(feature = "http-api")]
+    Snapshot {
+        #[command(subcommand)]
+        action: SnapshotAction,
+    },
+
+    /// Force points to fixed values on a running gateway (over its HTTP API)
+    #[cfg(feature = "http-api")]
+    Override {
+        #[command(subcommand)]
+        action: OverrideAction,
+    },
+
+    /// Have a channel of a running gateway report all points now (over its HTTP API)
+    #[cfg(feature = "http-api")]
+    Refresh {
+        /// Configuration file path (for the HTTP API address and token)
+        #[arg(short, long, default_value = "config.toml")]
+        config: PathBuf,
+
+        /// Channel id
+        #[arg(long)]
+        channel: u32,
+    },
+}
+
+#[cfg(feature = "http-api")]
+#[derive(Subcommand, Debug)]
+enum SnapshotAction {
+    /// Write point configs and latest values of every channel to a JSON file
+    Save {
+        /// Configuration file path (for the HTTP API address and token)
+        #[arg(short, long, default_value = "config.toml")]
+        config: PathBuf,
+
+        /// Snapshot file to write
+        file: PathBuf,
+    },
+
+    /// Load a JSON snapshot; values are marked last-known, newer values are kept
+    Load {
+        /// Configuration file path (for the HTTP API address and token)
+        #[arg(short, long, default_value = "config.toml")]
+        config: PathBuf,
+
+        /// Snapshot file to read
+        file: PathBuf,
+    },
+}
+
+#[cfg(feature = "http-api")]
+#[derive(Subcommand, Debug)]
+enum OverrideAction {
+    /// Force a point to a value until cleared or expired (requires tune)
+    Set {
+        /// Configuration file path (for the HTTP API address and token)
+        #[arg(short, long, default_value = "config.toml")]
+        config: PathBuf,
+
+        /// Channel id
+        #[arg(long)]
+        channel: u32,
+
+        /// Point id
+        #[arg(long)]
+        point: PointId,
+
+        /// Value: a number, true/false or text
+        #[arg(long)]
+        value: String,
+
+        /// Clear the override after this many seconds
+        #[arg(long)]
+        expires_in: Option<u64>,
+    },
+
+    /// Clear a point's override
+    Clear {
+        /// Configuration file path (for the HTTP API address and token)
+        #[arg(short, long, default_value = "config.toml")]
+        config: PathBuf,
+
+        /// Channel id
+        #[arg(long)]
+        channel: u32,
+
+        /// Point id
+        #[arg(long)]
+        point: PointId,
+    },
+
+    /// List the overrides in effect
+    List {
+        /// Configuration file path (for the HTTP API address and token)
+        #[arg(short, long, default_value = "config.toml")]
+        config: PathBuf,
+    },
 }
 
 fn main() {
     let cli = Cli::parse();
+    let verbose = cli.verbose;
+
+    match &cli.command {
+        // `run` picks the log format from its configuration
+        Commands::Run { .. } => {}
+        // The dashboard owns the terminal
+        #[cfg(feature = "tui")]
+        Commands::Monitor { .. } => {}
+        _ => logs::init(verbose, false),
+    }
 
     match cli.command {
         Commands::ListProtocols => {
@@ -42,6 +339,848 @@ fn main() {
         Commands::Example { protocol } => {
             generate_example(&protocol);
         }
+        Commands::Run { config } => exit_on_error(run(&config, verbose)),
+        #[cfg(feature = "tui")]
+        Commands::Monitor { config } => exit_on_error(monitor(&config)),
+        Commands::Validate { config } => {
+            exit_on_error(load_config(&config).map(|config| {
+                println!(
+                    "Configuration OK: {} channel(s), {} point(s)",
+                    config.channels.len(),
+                    config
+                        .channels
+                        .iter()
+                        .map(|c| c.points.len())
+                        .sum::<usize>()
+                );
+            }));
+        }
+        Commands::ConfigSchema { protocol } => exit_on_error(config_schema(protocol.as_deref())),
+        Commands::Read {
+            config,
+            channel,
+            points,
+        } => exit_on_error(read(&config, channel, &points)),
+        Commands::Write {
+            config,
+            channel,
+            point,
+            value,
+            adjustment,
+        } => exit_on_error(write(&config, channel, point, value, adjustment)),
+        Commands::Scan {
+            protocol,
+            target,
+            units,
+            timeout_ms,
+        } => exit_on_error(scan(&protocol, &target, &units, timeout_ms)),
+        Commands::Probe {
+            config,
+            channel,
+            points,
+            interval_ms,
+            timeout_ms,
+        } => exit_on_error(probe(&config, channel, &points, interval_ms, timeout_ms)),
+        Commands::Discover {
+            protocol,
+            target,
+            slave,
+            range,
+            channel,
+            interval_ms,
+            timeout_ms,
+        } => exit_on_error(discover(
+            &protocol,
+            &target,
+            slave,
+            &range,
+            channel,
+            interval_ms,
+            timeout_ms,
+        )),
+        #[cfg(feature = "http-api")]
+        Commands::Snapshot { action } => exit_on_error(match action {
+            SnapshotAction::Save { config, file } => snapshot_save(&config, &file),
+            SnapshotAction::Load { config, file } => snapshot_load(&config, &file),
+        }),
+        #[cfg(feature = "http-api")]
+        Commands::Override { action } => exit_on_error(match action {
+            OverrideAction::Set {
+                config,
+                channel,
+                point,
+                value,
+                expires_in,
+            } => override_set(&config, channel, point, &value, expires_in),
+            OverrideAction::Clear {
+                config,
+                channel,
+                point,
+            } => override_clear(&config, channel, point),
+            OverrideAction::List { config } => override_list(&config),
+        }),
+        #[cfg(feature = "http-api")]
+        Commands::Refresh { config, channel } => exit_on_error(refresh(&config, channel)),
+    }
+}
+
+fn exit_on_error(result: CliResult) {
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// Load and validate a configuration, printing every problem found.
+fn load_config(path: &Path) -> CliResult<GatewayConfig> {
+    let config = GatewayConfig::from_file(path)?;
+    let problems = config.validate();
+    if !problems.is_empty() {
+        for problem in &problems {
+            eprintln!("  - {}", problem);
+        }
+        return Err(format!("{} configuration problem(s) found", problems.len()).into());
+    }
+    Ok(config)
+}
+
+/// Load the configuration of one channel, keeping only `points` if given.
+fn load_channel(path: &Path, channel_id: u32, points: &[PointId]) -> CliResult<ChannelConfig> {
+    select_channel(load_config(path)?, path, channel_id, points)
+}
+
+/// Channel `channel_id` of `config`, enabled and cut down to `points`
+/// (all points if empty).
+fn select_channel(
+    config: GatewayConfig,
+    path: &Path,
+    channel_id: u32,
+    points: &[PointId],
+) -> CliResult<ChannelConfig> {
+    let mut channel = config
+        .channels
+        .into_iter()
+        .find(|c| c.id == channel_id)
+        .ok_or_else(|| format!("channel {} not found in {}", channel_id, path.display()))?;
+
+    if !points.is_empty() {
+        if let Some(missing) = points
+            .iter()
+            .find(|id| !channel.points.iter().any(|p| p.id == **id))
+        {
+            return Err(format!("point {} not found in channel {}", missing, channel_id).into());
+        }
+        channel.points.retain(|p| points.contains(&p.id));
+    }
+    channel.enabled = true;
+    Ok(channel)
+}
+
+async fn connect(config: &ChannelConfig) -> CliResult<Box<dyn ChannelRuntime>> {
+    let mut channel = factory::create_channel(config)?;
+    channel.connect().await?;
+    Ok(channel)
+}
+
+fn read(path: &Path, channel_id: u32, points: &[PointId]) -> CliResult {
+    let config = load_channel(path, channel_id, points)?;
+    let runtime = tokio::runtime::Runtime::new()?;
+
+    runtime.block_on(async {
+        let mut channel = connect(&config).await?;
+        let result = channel.poll_once().await;
+        let _ = channel.disconnect().await;
+
+        println!(
+            "{:>8}  {:<24} {:>16}  {:<8} QUALITY",
+            "ID", "NAME", "VALUE", "UNIT"
+        );
+        for point in &config.points {
+            let name = point.display_name.as_deref().unwrap_or(&point.name);
+            let unit = point.unit.as_deref().unwrap_or("");
+            if let Some(dp) = result.data.iter().find(|dp| dp.id == point.id) {
+                println!(
+                    "{:>8}  {:<24} {:>16}  {:<8} {}",
+                    point.id,
+                    name,
+                    format_value(&dp.value),
+                    unit,
+                    dp.quality
+                );
+            } else if let Some(failure) = result.failures.iter().find(|f| f.point_id == point.id) {
+                println!(
+                    "{:>8}  {:<24} {:>16}  {:<8} failed: {}",
+                    point.id, name, "-", unit, failure.error
+                );
+            } else {
+                println!(
+                    "{:>8}  {:<24} {:>16}  {:<8} no data",
+                    point.id, name, "-", unit
+                );
+            }
+        }
+        println!("\n{}", result.data.summary());
+        Ok(())
+    })
+}
+
+fn config_schema(protocol: Option<&str>) -> CliResult {
+    let protocols: Vec<&ProtocolParams> = match protocol {
+        Some(protocol) => vec![protocol_params(protocol).ok_or_else(|| {
+            format!(
+                "unknown protocol '{}' (built-in: {})",
+                protocol,
+                PROTOCOL_PARAMS
+                    .iter()
+                    .map(|p| p.protocol)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        })?],
+        None => PROTOCOL_PARAMS.iter().collect(),
+    };
+
+    for (i, params) in protocols.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        println!("{} ({})", params.protocol, params.type_name);
+        for field in params.fields {
+            let required = if field.required { "required" } else { "" };
+            println!(
+                "  {:<22} {:<16} {:<9} {}",
+                field.name,
+                field.ty,
+                required,
+                field.doc.trim()
+            );
+        }
+    }
+    Ok(())
+}
+
+fn format_value(value: &Value) -> String {
+    match value {
+        Value::Float(v) => v.to_string(),
+        Value::Integer(v) => v.to_string(),
+        Value::Bool(v) => v.to_string(),
+        Value::String(v) => v.clone(),
+        other => serde_json::to_string(other).unwrap_or_default(),
+    }
+}
+
+/// Caller identity of `igw write`. Whoever runs it can read the
+/// configuration and reach the devices, so it is the local operator and
+/// not restricted by `[gateway.authorization]`, like the library API.
+const CLI_CALLER: &str = "cli";
+
+/// How long `igw write` waits for its channels to connect.
+const WRITE_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Send one command through a [`GatewayRuntime`], so that limits,
+/// interlocks, authorization and the audit trail apply as they do to
+/// commands of a running gateway.
+///
+/// Only the commanded channel, the channels of its interlocks and its
+/// primary run, without safe states, dependencies or a heartbeat.
+fn write(path: &Path, channel_id: u32, point: PointId, value: f64, adjustment: bool) -> CliResult {
+    let mut config = load_config(path)?;
+    let channel = select_channel(config.clone(), path, channel_id, &[point])?;
+    let mut keep: Vec<u32> = channel
+        .interlocks
+        .iter()
+        .filter(|interlock| interlock.point_id == point)
+        .map(|interlock| interlock.check_channel.unwrap_or(channel_id))
+        .chain([channel_id])
+        .chain(channel.backup_of)
+        .collect();
+    keep.sort_unstable();
+    keep.dedup();
+    for channel in &mut config.channels {
+        if keep.contains(&channel.id) {
+            channel.enabled = true;
+            channel.safe_state.clear();
+            channel.depends_on.clear();
+        } else {
+            channel.enabled = false;
+        }
+    }
+    config.gateway.heartbeat = None;
+    let runtime = tokio::runtime::Runtime::new()?;
+
+    runtime.block_on(async {
+        let mut gateway = GatewayRuntime::from_config(config, Arc::new(MemoryStore::new()))?;
+        let mut events = Vec::new();
+        for &id in &keep {
+            events.push((id, gateway.subscribe(id)?));
+        }
+        gateway.start().await?;
+        let result = async {
+            for (id, rx) in &mut events {
+                wait_connected(*id, rx).await?;
+                // Interlocks check stored values: have the sources report
+                if *id != channel_id {
+                    let _ = gateway.refresh(*id).await;
+                }
+            }
+            let caller = Caller::new(CLI_CALLER, PermissionLevel::Tune);
+            let commands = [(point, value)];
+            let result = if adjustment {
+                gateway
+                    .write_adjustment_as(&caller, channel_id, &commands)
+                    .await
+            } else {
+                gateway
+                    .write_control_as(&caller, channel_id, &commands)
+                    .await
+            };
+            Ok::<_, Box<dyn std::error::Error>>(result?)
+        }
+        .await;
+        gateway.stop().await?;
+
+        let result = result?;
+        if let Some((_, error)) = result.failures.first() {
+            return Err(format!("channel {} refused the command: {}", channel_id, error).into());
+        }
+        match result.success_count {
+            0 => Err(format!("channel {} did not accept the command", channel_id).into()),
+            _ => {
+                let kind = if adjustment { "Adjustment" } else { "Control" };
+                println!("{} sent: point {} = {}", kind, point, value);
+                Ok(())
+            }
+        }
+    })
+}
+
+/// Wait until channel `channel_id` reports itself connected.
+async fn wait_connected(channel_id: u32, events: &mut DataEventReceiver) -> CliResult {
+    let connected = async {
+        while let Some(event) = events.recv().await {
+            if let DataEvent::ConnectionChanged(state) = event {
+                if state.is_connected() {
+                    return true;
+                }
+            }
+        }
+        false
+    };
+    match tokio::time::timeout(WRITE_CONNECT_TIMEOUT, connected).await {
+        Ok(true) => Ok(()),
+        _ => Err(format!(
+            "channel {} did not connect within {} s",
+            channel_id,
+            WRITE_CONNECT_TIMEOUT.as_secs()
+        )
+        .into()),
+    }
+}
+
+fn scan(protocol: &str, target: &str, units: &str, timeout_ms: u64) -> CliResult {
+    if !protocol.eq_ignore_ascii_case("modbus") {
+        return Err(format!("scan supports 'modbus' only, not '{}'", protocol).into());
+    }
+    let targets = scan::parse_target(target)?;
+    let units = scan::parse_units(units)?;
+    let runtime = tokio::runtime::Runtime::new()?;
+
+    println!(
+        "Scanning {} host(s), unit ids {}..={} ({} probe(s) per host)",
+        targets.len(),
+        units.first().copied().unwrap_or_default(),
+        units.last().copied().unwrap_or_default(),
+        units.len()
+    );
+    let found = runtime.block_on(scan::scan_modbus(
+        targets,
+        units,
+        Duration::from_millis(timeout_ms),
+    ));
+
+    if found.is_empty() {
+        println!("No Modbus devices found");
+    }
+    for (addr, units) in found {
+        let units: Vec<String> = units.iter().map(ToString::to_string).collect();
+        println!("{}  unit ids: {}", addr, units.join(", "));
+    }
+    Ok(())
+}
+
+fn probe(
+    path: &Path,
+    channel_id: u32,
+    points: &[PointId],
+    interval_ms: u64,
+    timeout_ms: u64,
+) -> CliResult {
+    let config = load_channel(path, channel_id, points)?;
+    if !config.protocol.eq_ignore_ascii_case("modbus") {
+        return Err(format!(
+            "probe supports Modbus channels only, channel {} is '{}'",
+            channel_id, config.protocol
+        )
+        .into());
+    }
+    let host = config.parameters["host"].as_str().ok_or_else(|| {
+        format!(
+            "probe supports Modbus TCP only, channel {} has no host",
+            channel_id
+        )
+    })?;
+    let port = config.parameters["port"].as_u64().unwrap_or(502);
+    let mut probes = probe::plan(&config)?;
+    let runtime = tokio::runtime::Runtime::new()?;
+
+    println!(
+        "Probing {} point(s) of channel {} at {}:{}, {} ms apart",
+        probes.iter().filter(|p| p.target.is_some()).count(),
+        channel_id,
+        host,
+        port,
+        interval_ms
+    );
+    let mut prober = probe::Prober::new(
+        format!("{}:{}", host, port),
+        Duration::from_millis(timeout_ms),
+        Duration::from_millis(interval_ms),
+    );
+    runtime.block_on(prober.run(&mut probes));
+
+    for point in &probes {
+        println!("{}", point);
+    }
+    let wrong = probes
+        .iter()
+        .filter(|p| matches!(p.finding, probe::Finding::WrongFunctionCode { .. }))
+        .count();
+    if wrong > 0 {
+        println!(
+            "{} point(s) respond on another function code; update their addresses",
+            wrong
+        );
+    }
+    Ok(())
+}
+
+fn discover(
+    protocol: &str,
+    target: &str,
+    slave: u8,
+    range: &str,
+    channel_id: u32,
+    interval_ms: u64,
+    timeout_ms: u64,
+) -> CliResult {
+    if !protocol.eq_ignore_ascii_case("modbus") {
+        return Err(format!("discover supports 'modbus' only, not '{}'", protocol).into());
+    }
+    let addr = match scan::parse_target(target)?[..] {
+        [addr] => addr,
+        _ => return Err(format!("discover needs a single device, not '{}'", target).into()),
+    };
+    let range = discover::parse_range(range)?;
+    let runtime = tokio::runtime::Runtime::new()?;
+
+    // Progress goes to stderr so the configuration can be redirected
+    eprintln!(
+        "Sweeping registers {}..={} of unit {} at {}, {} ms apart",
+        range.start(),
+        range.end(),
+        slave,
+        addr,
+        interval_ms
+    );
+    let mut prober = probe::Prober::new(
+        addr.to_string(),
+        Duration::from_millis(timeout_ms),
+        Duration::from_millis(interval_ms),
+    );
+    let mut points = Vec::new();
+    for (function_code, area) in [(3, "holding"), (4, "input")] {
+        let registers = runtime.block_on(discover::sweep_modbus(
+            &mut prober,
+            slave,
+            function_code,
+            range.clone(),
+        ))?;
+        eprintln!("{} {} register(s) respond", registers.len(), area);
+        points.extend(discover::guess_modbus_points(
+            slave,
+            function_code,
+            &registers,
+        ));
+    }
+    if points.is_empty() {
+        return Err(format!("no registers of unit {} respond at {}", slave, addr).into());
+    }
+
+    let channel = discover::DiscoveredChannel {
+        name: format!("device_{}", slave),
+        protocol: "modbus".into(),
+        parameters: vec![
+            ("host".into(), addr.ip().to_string().into()),
+            ("port".into(), toml::Value::Integer(addr.port().into())),
+        ],
+        points,
+    };
+    println!("# Generated by `igw discover`: names are placeholders, formats are guesses");
+    println!();
+    print!("{}", channel.to_toml(channel_id, 1));
+    Ok(())
+}
+
+/// HTTP API settings of a configuration, required by `igw snapshot` and
+/// `igw override`.
+#[cfg(feature = "http-api")]
+fn http_api_config(path: &Path) -> CliResult<igw::gateway::HttpApiConfig> {
+    load_config(path)?.gateway.http_api.ok_or_else(|| {
+        format!(
+            "{} has no [gateway.http_api] section to reach the gateway through",
+            path.display()
+        )
+        .into()
+    })
+}
+
+#[cfg(feature = "http-api")]
+fn snapshot_save(path: &Path, file: &Path) -> CliResult {
+    use igw::store::StoreSnapshot;
+
+    let api = http_api_config(path)?;
+    let body = tokio::runtime::Runtime::new()?.block_on(snapshot::request(
+        &api,
+        "GET",
+        "/snapshot",
+        None,
+    ))?;
+    let snapshot = StoreSnapshot::from_json(&body)?;
+    std::fs::write(file, serde_json::to_string_pretty(&snapshot)?)?;
+    println!(
+        "Saved {} value(s) of {} channel(s) to {}",
+        snapshot.value_count(),
+        snapshot.channels.len(),
+        file.display()
+    );
+    Ok(())
+}
+
+#[cfg(feature = "http-api")]
+fn snapshot_load(path: &Path, file: &Path) -> CliResult {
+    use igw::store::StoreSnapshot;
+
+    let api = http_api_config(path)?;
+    let text = std::fs::read_to_string(file)?;
+    // Refuse incompatible files before touching the gateway
+    let snapshot = StoreSnapshot::from_json(&text)?;
+    let body = tokio::runtime::Runtime::new()?.block_on(snapshot::request(
+        &api,
+        "POST",
+        "/snapshot",
+        Some(&text),
+    ))?;
+    let imported = serde_json::from_str::<serde_json::Value>(&body)?["imported"]
+        .as_u64()
+        .unwrap_or_default();
+    println!(
+        "Loaded {} of {} value(s) from {} (taken {})",
+        imported,
+        snapshot.value_count(),
+        file.display(),
+        snapshot.created_at.to_rfc3339()
+    );
+    Ok(())
+}
+
+#[cfg(feature = "http-api")]
+fn override_set(
+    path: &Path,
+    channel: u32,
+    point: PointId,
+    value: &str,
+    expires_in: Option<u64>,
+) -> CliResult {
+    let api = http_api_config(path)?;
+    // Numbers and booleans as such, anything else as text
+    let value =
+        serde_json::from_str::<Value>(value).unwrap_or_else(|_| Value::String(value.to_string()));
+    let body = serde_json::json!({ "value": value, "expires_in_s": expires_in }).to_string();
+    let body = tokio::runtime::Runtime::new()?.block_on(snapshot::request(
+        &api,
+        "PUT",
+        &format!("/channels/{}/points/{}/override", channel, point),
+        Some(&body),
+    ))?;
+    let set: igw::gateway::PointOverride = serde_json::from_str(&body)?;
+    print_override(&set);
+    Ok(())
+}
+
+#[cfg(feature = "http-api")]
+fn override_clear(path: &Path, channel: u32, point: PointId) -> CliResult {
+    let api = http_api_config(path)?;
+    let body = tokio::runtime::Runtime::new()?.block_on(snapshot::request(
+        &api,
+        "DELETE",
+        &format!("/channels/{}/points/{}/override", channel, point),
+        None,
+    ))?;
+    let cleared = serde_json::from_str::<serde_json::Value>(&body)?["cleared"]
+        .as_bool()
+        .unwrap_or_default();
+    if cleared {
+        println!(
+            "Cleared the override of point {} on channel {}",
+            point, channel
+        );
+    } else {
+        println!("Point {} on channel {} was not overridden", point, channel);
+    }
+    Ok(())
+}
+
+#[cfg(feature = "http-api")]
+fn override_list(path: &Path) -> CliResult {
+    let api = http_api_config(path)?;
+    let body = tokio::runtime::Runtime::new()?.block_on(snapshot::request(
+        &api,
+        "GET",
+        "/overrides",
+        None,
+    ))?;
+    let overrides: Vec<igw::gateway::PointOverride> = serde_json::from_str(&body)?;
+    if overrides.is_empty() {
+        println!("No overrides in effect");
+    }
+    for point_override in &overrides {
+        print_override(point_override);
+    }
+    Ok(())
+}
+
+#[cfg(feature = "http-api")]
+fn print_override(point_override: &igw::gateway::PointOverride) {
+    println!(
+        "channel {} point {} = {} (set by {} at {}{})",
+        point_override.channel_id,
+        point_override.point_id,
+        serde_json::to_string(&point_override.value).unwrap_or_default(),
+        point_override.set_by,
+        point_override.set_at.to_rfc3339(),
+        point_override
+            .expires_at
+            .map(|at| format!(", expires {}", at.to_rfc3339()))
+            .unwrap_or_default()
+    );
+}
+
+#[cfg(feature = "http-api")]
+fn refresh(path: &Path, channel: u32) -> CliResult {
+    let api = http_api_config(path)?;
+    let body = tokio::runtime::Runtime::new()?.block_on(snapshot::request(
+        &api,
+        "POST",
+        &format!("/channels/{}/refresh", channel),
+        None,
+    ))?;
+    let response: serde_json::Value = serde_json::from_str(&body)?;
+    match (response["points"].as_u64(), response["failed"].as_u64()) {
+        (Some(points), Some(failed)) => println!(
+            "Refreshed channel {}: {} point(s) read, {} failed",
+            channel, points, failed
+        ),
+        _ => println!(
+            "Refresh requested on channel {}, values follow as events",
+            channel
+        ),
+    }
+    Ok(())
+}
+
+#[cfg(feature = "tui")]
+fn monitor(path: &Path) -> CliResult {
+    let config = load_config(path)?;
+    tokio::runtime::Runtime::new()?.block_on(monitor::run(config))
+}
+
+fn run(path: &Path, verbose: u8) -> CliResult {
+    let config = load_config(path)?;
+    logs::init(verbose, config.gateway.jsonl_output);
+
+    let runtime = tokio::runtime::Runtime::new()?;
+
+    runtime.block_on(async {
+        let http_api = config.gateway.http_api.clone();
+        let modbus_server = config.gateway.modbus_server.clone();
+        let opcua_server = config.gateway.opcua_server.clone();
+        let replication = config.gateway.replication.clone();
+        // Channels start in the background: name them instead of asking the
+        // store, which may not know them yet.
+        let archive = config.gateway.archive.clone().map(|mut archive| {
+            if archive.channels.is_empty() {
+                archive.channels = config.enabled_channels().map(|c| c.id).collect();
+            }
+            archive
+        });
+        let opcua_channels: Vec<(u32, String)> = config
+            .enabled_channels()
+            .map(|c| (c.id, c.name.clone()))
+            .collect();
+        let store: Arc<dyn DataStore> = Arc::new(MemoryStore::new());
+
+        // The primary's store must be wrapped before the gateway writes to it.
+        #[cfg(feature = "replication")]
+        let (store, replication) = match &replication {
+            Some(config) => {
+                use igw::gateway::replication::{self, ReplicationLink};
+
+                let (store, link) = replication::start(config, store).await?;
+                match &link {
+                    ReplicationLink::Primary(primary) => {
+                        println!("Replicating to standbys on {}", primary.local_addr())
+                    }
+                    ReplicationLink::Standby(_) => {
+                        println!("Replicating from primary at {}", config.address)
+                    }
+                }
+                (store, Some(link))
+            }
+            None => (store, None),
+        };
+        #[cfg(not(feature = "replication"))]
+        let _ = replication;
+
+        let mut gateway = GatewayRuntime::from_config(config, store.clone())?;
+        gateway.start().await?;
+        println!(
+            "Gateway '{}' running {} channel(s), press Ctrl+C to stop",
+            gateway.name(),
+            gateway.channel_ids().len()
+        );
+        let gateway = Arc::new(RwLock::new(gateway));
+        let watchdog = GatewayRuntime::spawn_watchdog(Arc::clone(&gateway));
+
+        #[cfg(feature = "http-api")]
+        if let Some(http_api) = &http_api {
+            let api = igw::gateway::http_api::HttpApi::bind(http_api, Arc::clone(&gateway)).await?;
+            println!("HTTP API listening on {}", api.local_addr()?);
+            tokio::spawn(api.serve(std::future::pending()));
+        }
+        #[cfg(not(feature = "http-api"))]
+        let _ = http_api;
+
+        #[cfg(feature = "export")]
+        let archiver = match &archive {
+            Some(config) => {
+                let archiver = igw::gateway::export::Archiver::start(config, store.clone()).await?;
+                println!("Archiving to {}", config.dir.display());
+                Some(archiver)
+            }
+            None => None,
+        };
+        #[cfg(not(feature = "export"))]
+        let _ = archive;
+
+        #[cfg(feature = "modbus-server")]
+        let _modbus_server = match &modbus_server {
+            Some(config) => {
+                use igw::core::traits::ProtocolServer;
+                use igw::protocols::modbus_server::ModbusServer;
+
+                let mut server =
+                    ModbusServer::new(config, store.clone()).with_command_handler(gateway.clone());
+                server.listen(&config.bind).await?;
+                if let Some(addr) = server.local_addr() {
+                    println!("Modbus server listening on {}", addr);
+                }
+                Some(server)
+            }
+            None => None,
+        };
+        #[cfg(not(feature = "modbus-server"))]
+        let _ = modbus_server;
+
+        #[cfg(feature = "opcua-server")]
+        let _opcua_server = match &opcua_server {
+            Some(config) => {
+                use igw::core::traits::ProtocolServer;
+                use igw::protocols::opcua_server::OpcUaServer;
+
+                let mut server = OpcUaServer::new(config, store)
+                    .with_channels(opcua_channels)
+                    .with_command_handler(gateway.clone());
+                server.listen(&config.bind).await?;
+                if let Some(url) = server.endpoint_url() {
+                    println!("OPC UA server listening on {}", url);
+                }
+                Some(server)
+            }
+            None => None,
+        };
+        #[cfg(not(feature = "opcua-server"))]
+        let _ = (opcua_server, opcua_channels, store);
+
+        wait_for_shutdown(&gateway, path).await?;
+        watchdog.abort();
+        println!("Shutting down");
+        let report = gateway.write().await.stop().await?;
+        for (channel_id, error) in &report.safe_state_failed {
+            eprintln!("Channel {}: safe state not applied: {}", channel_id, error);
+        }
+        for channel_id in &report.timed_out {
+            eprintln!("Channel {}: shutdown timed out", channel_id);
+        }
+        #[cfg(feature = "export")]
+        if let Some(archiver) = archiver {
+            archiver.stop().await;
+        }
+        #[cfg(feature = "replication")]
+        drop(replication);
+        println!("Gateway stopped");
+        Ok(())
+    })
+}
+
+/// Wait for Ctrl+C or SIGTERM, reloading the configuration on SIGHUP
+/// (Unix only).
+#[cfg(unix)]
+async fn wait_for_shutdown(gateway: &RwLock<GatewayRuntime>, path: &Path) -> CliResult {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangup = signal(SignalKind::hangup())?;
+    let mut terminate = signal(SignalKind::terminate())?;
+    loop {
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => return Ok(result?),
+            _ = terminate.recv() => return Ok(()),
+            _ = hangup.recv() => reload(gateway, path).await,
+        }
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_for_shutdown(_gateway: &RwLock<GatewayRuntime>, _path: &Path) -> CliResult {
+    Ok(tokio::signal::ctrl_c().await?)
+}
+
+#[cfg(unix)]
+async fn reload(gateway: &RwLock<GatewayRuntime>, path: &Path) {
+    let result = match GatewayConfig::from_file(path) {
+        Ok(config) => gateway
+            .write()
+            .await
+            .reload(config)
+            .await
+            .map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+    match result {
+        Ok(report) => println!(
+            "Reloaded: kept {:?}, updated {:?}, restarted {:?}, added {:?}, removed {:?}",
+            report.kept, report.updated, report.restarted, report.added, report.removed
+        ),
+        Err(e) => eprintln!("Reload failed, keeping current configuration: {}", e),
     }
 }
 
@@ -68,6 +1207,18 @@ fn list_protocols() {
         println!();
     }
 
+    println!("To run a gateway from a configuration file:");
+    println!("  igw run <config.toml>");
+    println!();
+    println!("Commissioning commands:");
+    println!("  igw validate -c <config.toml>");
+    println!("  igw config-schema [protocol]");
+    println!("  igw monitor -c <config.toml>   (requires the 'tui' feature)");
+    println!("  igw read -c <config.toml> --channel <id> [--points <id,...>]");
+    println!("  igw write -c <config.toml> --channel <id> --point <id> --value <v>");
+    println!("  igw scan modbus <network/prefix[:port]>");
+    println!("  igw discover modbus <host[:port]> --slave <id> --range <from-to>");
+    println!();
     println!("For a complete gateway demo, run:");
     println!("  cargo run --example gateway_demo --features full -- <config.toml>");
 }
@@ -13,6 +13,11 @@ pub mod modbus;
 #[cfg_attr(docsrs, doc(cfg(feature = "modbus")))]
 pub mod command_batcher;
 
+// Northbound Modbus TCP server (no external dependencies)
+#[cfg(feature = "modbus-server")]
+#[cfg_attr(docsrs, doc(cfg(feature = "modbus-server")))]
+pub mod modbus_server;
+
 #[cfg(feature = "iec104")]
 #[cfg_attr(docsrs, doc(cfg(feature = "iec104")))]
 pub mod iec104;
@@ -21,6 +26,15 @@ pub mod iec104;
 #[cfg_attr(docsrs, doc(cfg(feature = "opcua")))]
 pub mod opcua;
 
+#[cfg(feature = "mqtt")]
+#[cfg_attr(docsrs, doc(cfg(feature = "mqtt")))]
+pub mod mqtt;
+
+// Northbound OPC UA server
+#[cfg(feature = "opcua-server")]
+#[cfg_attr(docsrs, doc(cfg(feature = "opcua-server")))]
+pub mod opcua_server;
+
 #[cfg(all(feature = "can", target_os = "linux"))]
 #[cfg_attr(docsrs, doc(cfg(all(feature = "can", target_os = "linux"))))]
 pub mod can;
@@ -7,7 +7,6 @@ use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 
 use socketcan::{CanSocket, EmbeddedFrame, Frame, Socket};
-use tokio::sync::broadcast;
 use tokio::sync::RwLock;
 use tokio::task::JoinHandle;
 
@@ -16,8 +15,8 @@ use crate::core::error::{GatewayError, Result};
 
 use crate::core::traits::{
     AdjustmentCommand, CommunicationMode, ConnectionState, ControlCommand, DataEvent,
-    DataEventHandler, DataEventReceiver, DataEventSender, Diagnostics, EventDrivenProtocol,
-    PollResult, Protocol, ProtocolCapabilities, ProtocolClient, WriteResult,
+    DataEventHandler, DataEventReceiver, Diagnostics, EventBus, EventDrivenProtocol, PollResult,
+    Protocol, ProtocolCapabilities, ProtocolClient, WriteResult,
 };
 
 use super::config::{CanConfig, CanFrameCache, LynkCanId};
@@ -47,8 +46,8 @@ pub struct CanClient {
     receive_handle: Option<JoinHandle<()>>,
     read_handle: Option<JoinHandle<()>>,
 
-    // Event channel (broadcast for multiple subscribers)
-    event_tx: DataEventSender,
+    // Event bus (multiple subscribers)
+    event_bus: EventBus,
     event_handler: Option<Arc<dyn DataEventHandler>>,
 
     // CAN frame cache
@@ -65,8 +64,7 @@ impl CanClient {
     /// Create a new CAN client with the given configuration.
     pub fn new(config: CanConfig) -> Self {
         let point_manager = PointManager::new();
-        // Use broadcast channel for multiple subscribers
-        let (event_tx, _) = broadcast::channel(1024);
+        let event_bus = EventBus::default();
 
         Self {
             config,
@@ -77,7 +75,7 @@ impl CanClient {
             last_error: Arc::new(RwLock::new(None)),
             receive_handle: None,
             read_handle: None,
-            event_tx,
+            event_bus,
             event_handler: None,
             frame_cache: Arc::new(RwLock::new(CanFrameCache::new())),
             point_manager: Arc::new(point_manager),
@@ -264,7 +262,7 @@ impl CanClient {
         let read_count = Arc::clone(&self.read_count);
         let error_count = Arc::clone(&self.error_count);
         let last_error = Arc::clone(&self.last_error);
-        let event_tx = self.event_tx.clone();
+        let event_bus = self.event_bus.clone();
         let event_handler = self.event_handler.clone();
         let read_interval = self.config.data_read_interval_ms;
 
@@ -332,10 +330,11 @@ impl CanClient {
                                 batch.len()
                             );
 
-                            // Send event (broadcast is sync, not async)
+                            // Publish event (non-blocking)
                             #[cfg(feature = "tracing-support")]
-                            tracing::debug!("Sending DataUpdate event via event_tx");
-                            let _ = event_tx.send(DataEvent::DataUpdate(batch.clone()));
+                            tracing::debug!("Sending DataUpdate event via event_bus");
+                            let batch = Arc::new(batch);
+                            let _ = event_bus.publish(DataEvent::DataUpdate(Arc::clone(&batch)));
 
                             // Call handler
                             if let Some(ref handler) = event_handler {
@@ -410,6 +409,7 @@ impl Protocol for CanClient {
                 "can_interface": self.config.can_interface,
                 "bitrate": self.config.bitrate,
             }),
+            ..Default::default()
         })
     }
 }
@@ -420,10 +420,13 @@ impl ProtocolClient for CanClient {
 
         // Verify CAN interface exists
         let _socket = CanSocket::open(&self.config.can_interface).map_err(|e| {
-            GatewayError::Connection(format!(
-                "Failed to open CAN interface {}: {}",
-                self.config.can_interface, e
-            ))
+            GatewayError::connection_with(
+                format!(
+                    "Failed to open CAN interface {}: {}",
+                    self.config.can_interface, e
+                ),
+                e,
+            )
         })?;
 
         #[cfg(feature = "tracing-support")]
@@ -492,7 +495,7 @@ impl ProtocolClient for CanClient {
 impl EventDrivenProtocol for CanClient {
     fn subscribe(&self) -> DataEventReceiver {
         // Broadcast channel supports multiple subscribers
-        self.event_tx.subscribe()
+        self.event_bus.subscribe()
     }
 
     fn set_event_handler(&mut self, handler: Arc<dyn DataEventHandler>) {
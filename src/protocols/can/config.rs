@@ -129,51 +129,7 @@ impl CanMappingConfig {
     }
 }
 
-/// CAN channel parameters configuration (deserialized from parameters_json).
-///
-/// # Example JSON
-/// ```json
-/// {
-///     "interface": "can0",
-///     "bitrate": 250000,
-///     "rx_poll_interval_ms": 50,
-///     "data_read_interval_ms": 1000
-/// }
-/// ```
-#[derive(Debug, Clone, Deserialize)]
-pub struct CanChannelParamsConfig {
-    /// CAN interface name (e.g., "can0").
-    #[serde(default = "default_can_interface")]
-    pub interface: String,
-
-    /// CAN bitrate in bits per second.
-    #[serde(default = "default_bitrate")]
-    pub bitrate: u32,
-
-    /// RX polling interval in milliseconds.
-    #[serde(default = "default_rx_poll_interval")]
-    pub rx_poll_interval_ms: u64,
-
-    /// Data reading interval in milliseconds.
-    #[serde(default = "default_data_read_interval")]
-    pub data_read_interval_ms: u64,
-}
-
-fn default_can_interface() -> String {
-    "can0".to_string()
-}
-
-fn default_bitrate() -> u32 {
-    250000
-}
-
-fn default_rx_poll_interval() -> u64 {
-    50
-}
-
-fn default_data_read_interval() -> u64 {
-    1000
-}
+pub use crate::gateway::params::CanChannelParamsConfig;
 
 impl CanChannelParamsConfig {
     /// Convert to CanConfig.
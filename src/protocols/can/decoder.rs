@@ -36,7 +36,7 @@ impl PointManager {
 
         self.points_by_can_id
             .entry(can_id)
-            .or_insert_with(Vec::new)
+            .or_default()
             .push(point_id);
     }
 
@@ -11,6 +11,15 @@
 //! - **Event-Driven**: Passively listens to CAN bus, decodes all known PGNs automatically
 //! - **SPN Database**: Pre-defined SPNs covering engine/generator parameters
 //! - **Point ID = SPN**: Uses globally unique SPN numbers as point identifiers
+//! - **Deadbands**: Per-SPN deadbands (`J1939Config::deadbands`) keep jitter out of events
+//! - **Unit conversion**: `J1939Config::unit_conversions` converts SPNs from their database
+//!   unit (e.g. `C`, `kPa`, `L/h`) to the configured one
+//! - **On-request PGNs**: `J1939Client::refresh()` sends a Request PGN for each of
+//!   `J1939Config::request_pgns`
+//! - **Fault clearing**: DM3/DM11 requests through reserved control points, acknowledged
+//!   by the ECU and enabled by `J1939Config::allow_fault_clear` (see [`dtc`])
+//! - **Receive statistics**: frames and rates per PGN, PGNs missing from the database,
+//!   and the bus load seen vs filtered, in `Diagnostics::extra`
 //!
 //! ## Dependencies
 //!
@@ -45,9 +54,12 @@
 //! ```
 
 mod client;
+pub mod dtc;
+mod stats;
 
 // Re-export client
 pub use client::{J1939Client, J1939Config};
+pub use stats::pgn_name;
 
 // Re-export voltage_j1939 types for convenience
 pub use voltage_j1939::{
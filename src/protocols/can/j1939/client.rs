@@ -3,21 +3,34 @@
 //! Implements the igw Protocol traits for J1939/CAN communication.
 
 use std::collections::HashMap;
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use socketcan::{CanSocket, EmbeddedFrame, Frame, Socket};
-use tokio::sync::{broadcast, RwLock};
+use chrono::{DateTime, Utc};
+use socketcan::{CanFrame, CanSocket, EmbeddedFrame, ExtendedId, Id, Socket};
+use tokio::sync::{oneshot, RwLock};
 use tokio::task::JoinHandle;
-use voltage_j1939::{database_stats, decode_frame, extract_source_address};
+use voltage_j1939::{
+    build_request_pgn, database_stats, decode_frame, extract_pgn, extract_source_address,
+    get_spn_def,
+};
 
-use crate::core::data::{DataBatch, DataPoint, Value};
+use crate::core::data::{DataBatch, DataPoint, PointId, Value};
+use crate::core::deadband::DeadbandFilter;
+use crate::core::diagnostics::DiagnosticsRecorder;
 use crate::core::error::{GatewayError, Result};
+use crate::core::point::{PointMeta, PointMetaMap};
+use crate::core::quality::Quality;
 use crate::core::traits::{
     AdjustmentCommand, CommunicationMode, ConnectionState, ControlCommand, DataEvent,
-    DataEventHandler, DataEventReceiver, DataEventSender, Diagnostics, EventDrivenProtocol,
-    PollResult, Protocol, ProtocolCapabilities, ProtocolClient, WriteResult,
+    DataEventHandler, DataEventReceiver, Diagnostics, EventBus, EventDrivenProtocol, PollResult,
+    Protocol, ProtocolCapabilities, ProtocolClient, ReadRequest, ReadResponse, WriteResult,
 };
+use crate::core::units::Conversion;
+
+use super::dtc::{self, Acknowledgement, PendingAcks, ACK_PGN};
+use super::stats::BusStats;
 
 // ============================================================================
 // Configuration
@@ -37,6 +50,38 @@ pub struct J1939Config {
 
     /// Request interval for on-demand PGNs in milliseconds.
     pub request_interval_ms: u64,
+
+    /// PGNs the ECU only transmits on request (e.g. 65253 engine hours),
+    /// requested by [`J1939Client::refresh()`].
+    pub request_pgns: Vec<u32>,
+
+    /// Age in milliseconds after which a cached SPN value is reported with
+    /// `Quality::LastKnown` by `poll_once()` (0 = never stale).
+    pub stale_timeout_ms: u64,
+
+    /// Deadband per SPN, in the SPN's engineering units (after any unit
+    /// conversion).
+    ///
+    /// Received values within the deadband of the last published one are
+    /// kept in the cache but not published as events (see
+    /// [`DeadbandFilter`]).
+    pub deadbands: HashMap<PointId, f64>,
+
+    /// Unit to convert each SPN to, from the unit of the SPN database
+    /// (e.g. `110 => "degF"` for coolant temperature).
+    ///
+    /// Conversions that cannot be resolved (unknown SPN or unit, or units
+    /// of different families) are recorded as errors on start and the SPN
+    /// is left in its own unit.
+    pub unit_conversions: HashMap<PointId, String>,
+
+    /// Accept the DM3/DM11 fault clear commands (see
+    /// [`dtc`](super::dtc)); refused otherwise.
+    pub allow_fault_clear: bool,
+
+    /// How long to wait for the ECU to acknowledge a fault clear request,
+    /// in milliseconds.
+    pub ack_timeout_ms: u64,
 }
 
 impl Default for J1939Config {
@@ -46,6 +91,12 @@ impl Default for J1939Config {
             source_address: 0x00,
             our_address: 0xFE,
             request_interval_ms: 1000,
+            request_pgns: Vec::new(),
+            stale_timeout_ms: 5000,
+            deadbands: HashMap::new(),
+            unit_conversions: HashMap::new(),
+            allow_fault_clear: false,
+            ack_timeout_ms: 1250,
         }
     }
 }
@@ -66,59 +117,230 @@ pub struct J1939Client {
     is_connected: Arc<AtomicBool>,
 
     // Statistics
-    read_count: Arc<AtomicU64>,
-    error_count: Arc<AtomicU64>,
-    last_error: Arc<RwLock<Option<String>>>,
+    diagnostics: Arc<DiagnosticsRecorder>,
+    bus_stats: Arc<BusStats>,
 
     // Tasks
     receive_handle: Option<JoinHandle<()>>,
 
-    // Event channel (broadcast for multiple subscribers)
-    event_tx: DataEventSender,
+    // Fault clear requests waiting for their acknowledgement
+    pending_acks: PendingAcks,
+
+    // Event bus (multiple subscribers)
+    event_bus: EventBus,
     event_handler: Option<Arc<dyn DataEventHandler>>,
 
-    // Cached data (latest values)
-    cached_data: Arc<RwLock<HashMap<String, DataPoint>>>,
+    // Cached data (latest values), keyed by SPN (which is also the point ID)
+    cached_data: Arc<RwLock<HashMap<PointId, DataPoint>>>,
 }
 
 impl J1939Client {
     /// Create a new J1939 client with the given configuration.
     pub fn new(config: J1939Config) -> Self {
-        // Use broadcast channel for multiple subscribers
-        let (event_tx, _) = broadcast::channel(1024);
+        let event_bus = EventBus::default();
 
         Self {
             config,
             connection_state: Arc::new(RwLock::new(ConnectionState::Disconnected)),
             is_connected: Arc::new(AtomicBool::new(false)),
-            read_count: Arc::new(AtomicU64::new(0)),
-            error_count: Arc::new(AtomicU64::new(0)),
-            last_error: Arc::new(RwLock::new(None)),
+            diagnostics: Arc::new(DiagnosticsRecorder::new()),
+            bus_stats: Arc::new(BusStats::new()),
             receive_handle: None,
-            event_tx,
+            pending_acks: PendingAcks::default(),
+            event_bus,
             event_handler: None,
             cached_data: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 
+    /// Metadata for an SPN, taken from the built-in SPN database.
+    ///
+    /// Returns `None` for SPNs the database does not know.
+    pub fn point_meta(spn: PointId) -> Option<PointMeta> {
+        get_spn_def(spn).map(|def| PointMeta {
+            name: Some(def.name.to_string()),
+            display_name: None,
+            unit: Some(def.unit.to_string()).filter(|unit| !unit.is_empty()),
+        })
+    }
+
+    /// Metadata for every SPN received so far, with the units SPNs are
+    /// converted to.
+    pub async fn point_meta_map(&self) -> PointMetaMap {
+        let conversions = self.unit_conversions();
+        self.cached_data
+            .read()
+            .await
+            .keys()
+            .filter_map(|&spn| {
+                let mut meta = Self::point_meta(spn)?;
+                if conversions.contains_key(&spn) {
+                    meta.unit = self.config.unit_conversions.get(&spn).cloned();
+                }
+                Some((spn, meta))
+            })
+            .collect()
+    }
+
+    /// Resolve the configured unit conversions against the SPN database,
+    /// skipping those that cannot be resolved.
+    fn unit_conversions(&self) -> HashMap<PointId, Conversion> {
+        self.config
+            .unit_conversions
+            .iter()
+            .filter_map(|(&spn, to)| Some((spn, spn_unit_conversion(spn, to).ok()?)))
+            .collect()
+    }
+
+    /// Read the cached SPN values selected by `request`.
+    ///
+    /// Values get the same age-based quality as in `poll_once()`, so
+    /// [`ReadRequest::bad_quality()`] lists the SPNs the ECU stopped
+    /// broadcasting. SPNs have no poll group; a group filter selects none.
+    pub async fn read(&self, request: &ReadRequest) -> ReadResponse {
+        let cached = self.cached_data.read().await;
+        let connected = self.is_connected.load(Ordering::SeqCst);
+        let batch = cached_snapshot(&cached, connected, self.config.stale_timeout_ms, Utc::now());
+        ReadResponse::success(
+            batch
+                .iter()
+                .filter(|point| request.matches(point, None))
+                .cloned()
+                .collect(),
+        )
+    }
+
+    /// Pre-populate the SPN cache, e.g. with last-known values from a store.
+    ///
+    /// SPNs that have already been received are kept.
+    pub async fn restore(&self, batch: &DataBatch) {
+        let mut cached = self.cached_data.write().await;
+        for point in batch.iter() {
+            cached.entry(point.id).or_insert_with(|| point.clone());
+        }
+    }
+
+    /// Ask the ECU to transmit each of the `request_pgns` now, with one
+    /// Request PGN (59904) frame per PGN.
+    ///
+    /// The answers arrive like broadcast PGNs, as events. Returns the number
+    /// of requests sent.
+    pub fn refresh(&self) -> Result<usize> {
+        if !self.is_connected.load(Ordering::SeqCst) {
+            return Err(GatewayError::NotConnected);
+        }
+        if self.config.request_pgns.is_empty() {
+            return Ok(0);
+        }
+
+        let socket = self.open_socket()?;
+        for &pgn in &self.config.request_pgns {
+            self.request(&socket, pgn)?;
+        }
+        Ok(self.config.request_pgns.len())
+    }
+
+    /// Ask the ECU to clear its fault codes with the DM3 or DM11 request
+    /// `pgn`, and wait for its acknowledgement.
+    async fn clear_faults(&self, pgn: u32) -> std::result::Result<(), String> {
+        let name = dtc::request_name(pgn);
+        if !self.config.allow_fault_clear {
+            return Err(format!(
+                "{} fault clearing is disabled (allow_fault_clear)",
+                name
+            ));
+        }
+
+        let ack = self.pending_acks.expect(pgn);
+        if let Err(e) = self
+            .open_socket()
+            .and_then(|socket| self.request(&socket, pgn))
+        {
+            self.pending_acks.cancel(pgn);
+            return Err(e.to_string());
+        }
+        self.await_ack(pgn, ack).await
+    }
+
+    /// Wait up to `ack_timeout_ms` for the acknowledgement of the clear
+    /// request `pgn`.
+    async fn await_ack(
+        &self,
+        pgn: u32,
+        ack: oneshot::Receiver<Acknowledgement>,
+    ) -> std::result::Result<(), String> {
+        let name = dtc::request_name(pgn);
+        let limit = Duration::from_millis(self.config.ack_timeout_ms);
+        match tokio::time::timeout(limit, ack).await {
+            Ok(Ok(Acknowledgement::Ack)) => Ok(()),
+            Ok(Ok(answer)) => Err(format!(
+                "ECU 0x{:02X} refused {}: {}",
+                self.config.source_address, name, answer
+            )),
+            Ok(Err(_)) | Err(_) => {
+                self.pending_acks.cancel(pgn);
+                Err(format!(
+                    "ECU 0x{:02X} did not acknowledge {} within {} ms",
+                    self.config.source_address, name, self.config.ack_timeout_ms
+                ))
+            }
+        }
+    }
+
+    fn open_socket(&self) -> Result<CanSocket> {
+        CanSocket::open(&self.config.can_interface).map_err(|e| {
+            GatewayError::connection_with(
+                format!(
+                    "Failed to open CAN interface {}: {}",
+                    self.config.can_interface, e
+                ),
+                e,
+            )
+        })
+    }
+
+    /// Send a Request PGN for `pgn` to the ECU.
+    fn request(&self, socket: &CanSocket, pgn: u32) -> Result<()> {
+        let (can_id, data) =
+            build_request_pgn(self.config.our_address, self.config.source_address, pgn);
+        let frame = ExtendedId::new(can_id)
+            .and_then(|id| CanFrame::new(id, &data))
+            .ok_or_else(|| GatewayError::InvalidData(format!("cannot request PGN {}", pgn)))?;
+        socket.write_frame(&frame).map_err(|e| {
+            self.diagnostics
+                .record_error(format!("Request PGN {}: {}", pgn, e));
+            GatewayError::connection_with(format!("Failed to request PGN {}: {}", pgn, e), e)
+        })?;
+        self.diagnostics.record_bytes_sent(data.len() as u64);
+        Ok(())
+    }
+
     /// Start the receive task.
     fn start_receive_task(&mut self) -> Result<()> {
         let can_interface = self.config.can_interface.clone();
         let source_address = self.config.source_address;
+        let our_address = self.config.our_address;
+        let pending_acks = self.pending_acks.clone();
         let is_connected = Arc::clone(&self.is_connected);
         let cached_data = Arc::clone(&self.cached_data);
-        let read_count = Arc::clone(&self.read_count);
-        let error_count = Arc::clone(&self.error_count);
-        let last_error = Arc::clone(&self.last_error);
-        let event_tx = self.event_tx.clone();
+        let diagnostics = Arc::clone(&self.diagnostics);
+        let bus_stats = Arc::clone(&self.bus_stats);
+        let event_bus = self.event_bus.clone();
         let event_handler = self.event_handler.clone();
+        let mut deadband = DeadbandFilter::from_deadbands(self.config.deadbands.clone());
+        for (&spn, to) in &self.config.unit_conversions {
+            if let Err(e) = spn_unit_conversion(spn, to) {
+                self.diagnostics
+                    .record_error(format!("SPN {} unit conversion: {}", spn, e));
+            }
+        }
+        let conversions = self.unit_conversions();
 
         let handle = tokio::spawn(async move {
             let socket = match CanSocket::open(&can_interface) {
                 Ok(s) => s,
                 Err(e) => {
-                    *last_error.write().await = Some(format!("Failed to open CAN socket: {}", e));
-                    error_count.fetch_add(1, Ordering::Relaxed);
+                    diagnostics.record_error(format!("Failed to open CAN socket: {}", e));
                     return;
                 }
             };
@@ -130,15 +352,17 @@ impl J1939Client {
 
                 match socket.read_frame() {
                     Ok(frame) => {
-                        if let Some(id) = frame.id().as_extended() {
-                            let can_id = id.as_raw();
-                            let sa = extract_source_address(can_id);
-
-                            // Filter by source address
-                            if sa != source_address {
+                        let len = frame.data().len();
+                        diagnostics.record_bytes_received(len as u64);
+                        let accepted = accepted_id(frame.id(), source_address);
+                        bus_stats.record(accepted.map(extract_pgn), len, Instant::now());
+                        if let Some(can_id) = accepted {
+                            if extract_pgn(can_id) == ACK_PGN {
+                                if addressed_to(can_id, our_address) {
+                                    pending_acks.deliver(frame.data());
+                                }
                                 continue;
                             }
-
                             // Use voltage_j1939 to decode the frame
                             let decoded_spns = decode_frame(can_id, frame.data());
                             if decoded_spns.is_empty() {
@@ -148,24 +372,28 @@ impl J1939Client {
                             let mut batch = DataBatch::new();
 
                             for decoded in decoded_spns {
+                                let value = match conversions.get(&decoded.spn) {
+                                    Some(conversion) => conversion.apply(decoded.value),
+                                    None => decoded.value,
+                                };
                                 // J1939 uses SPN as point ID (converted to u32)
-                                let data_point =
-                                    DataPoint::new(decoded.spn, Value::Float(decoded.value));
+                                let data_point = DataPoint::new(decoded.spn, Value::Float(value));
 
-                                batch.add(data_point.clone());
+                                if deadband.admit(&data_point) {
+                                    batch.add(data_point.clone());
+                                }
 
-                                // Update cache using SPN string as key
-                                cached_data
-                                    .write()
-                                    .await
-                                    .insert(decoded.spn.to_string(), data_point);
+                                // Update cache keyed by SPN
+                                cached_data.write().await.insert(decoded.spn, data_point);
                             }
 
                             if !batch.is_empty() {
-                                read_count.fetch_add(1, Ordering::Relaxed);
+                                diagnostics.record_read(1);
 
-                                // Send event (broadcast is sync)
-                                let _ = event_tx.send(DataEvent::DataUpdate(batch.clone()));
+                                // Publish event (non-blocking)
+                                let batch = Arc::new(batch);
+                                let _ =
+                                    event_bus.publish(DataEvent::DataUpdate(Arc::clone(&batch)));
 
                                 // Call handler
                                 if let Some(ref handler) = event_handler {
@@ -175,8 +403,7 @@ impl J1939Client {
                         }
                     }
                     Err(e) => {
-                        *last_error.write().await = Some(format!("CAN read error: {}", e));
-                        error_count.fetch_add(1, Ordering::Relaxed);
+                        diagnostics.record_error(format!("CAN read error: {}", e));
                         tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
                     }
                 }
@@ -188,6 +415,55 @@ impl J1939Client {
     }
 }
 
+/// The 29-bit identifier of a frame from the ECU at `source_address`;
+/// `None` for 11-bit frames and frames from other addresses.
+fn accepted_id(id: Id, source_address: u8) -> Option<u32> {
+    match id {
+        Id::Extended(id) => Some(id.as_raw()),
+        Id::Standard(_) => None,
+    }
+    .filter(|&id| extract_source_address(id) == source_address)
+}
+
+/// Whether a PDU1 frame (e.g. an Acknowledgement) is addressed to
+/// `address` or to all nodes.
+fn addressed_to(can_id: u32, address: u8) -> bool {
+    let destination = ((can_id >> 8) & 0xFF) as u8;
+    destination == address || destination == 0xFF
+}
+
+/// Conversion of `spn` from its unit in the SPN database to `to`.
+fn spn_unit_conversion(spn: PointId, to: &str) -> Result<Conversion> {
+    let def = get_spn_def(spn)
+        .ok_or_else(|| GatewayError::Config(format!("SPN {} is not in the database", spn)))?;
+    Conversion::between(def.unit, to)
+}
+
+/// Build a snapshot of the SPN cache with age-based quality.
+///
+/// Values that are older than `stale_timeout_ms` (or any value while the bus
+/// is disconnected) are downgraded from `Good` to `LastKnown`, so the caller
+/// can tell a live reading from one the ECU stopped broadcasting.
+fn cached_snapshot(
+    cached: &HashMap<PointId, DataPoint>,
+    connected: bool,
+    stale_timeout_ms: u64,
+    now: DateTime<Utc>,
+) -> DataBatch {
+    cached
+        .values()
+        .map(|point| {
+            let age_ms = (now - point.timestamp).num_milliseconds().max(0) as u64;
+            let stale = !connected || (stale_timeout_ms > 0 && age_ms > stale_timeout_ms);
+            if stale && point.quality.is_good() {
+                point.clone().with_quality(Quality::LastKnown)
+            } else {
+                point.clone()
+            }
+        })
+        .collect()
+}
+
 // ============================================================================
 // Trait Implementations
 // ============================================================================
@@ -220,22 +496,25 @@ impl Protocol for J1939Client {
     }
 
     async fn diagnostics(&self) -> Result<Diagnostics> {
-        let (spn_count, pgn_count) = database_stats();
-
-        Ok(Diagnostics {
-            protocol: "J1939".to_string(),
-            connection_state: *self.connection_state.read().await,
-            read_count: self.read_count.load(Ordering::Relaxed),
-            write_count: 0,
-            error_count: self.error_count.load(Ordering::Relaxed),
-            last_error: self.last_error.read().await.clone(),
-            extra: serde_json::json!({
-                "can_interface": self.config.can_interface,
-                "source_address": format!("0x{:02X}", self.config.source_address),
-                "spn_count": spn_count,
-                "pgn_count": pgn_count,
-            }),
-        })
+        let (pgn_count, spn_count) = database_stats();
+
+        let mut diag = self
+            .diagnostics
+            .to_diagnostics("J1939", *self.connection_state.read().await);
+        let mut extra = serde_json::json!({
+            "can_interface": self.config.can_interface,
+            "source_address": format!("0x{:02X}", self.config.source_address),
+            "spn_count": spn_count,
+            "pgn_count": pgn_count,
+        });
+        if let (Some(extra), serde_json::Value::Object(stats)) = (
+            extra.as_object_mut(),
+            self.bus_stats.to_json(Instant::now()),
+        ) {
+            extra.extend(stats);
+        }
+        diag.extra = extra;
+        Ok(diag)
     }
 }
 
@@ -245,10 +524,13 @@ impl ProtocolClient for J1939Client {
 
         // Verify CAN interface exists
         let _socket = CanSocket::open(&self.config.can_interface).map_err(|e| {
-            GatewayError::Connection(format!(
-                "Failed to open CAN interface {}: {}",
-                self.config.can_interface, e
-            ))
+            GatewayError::connection_with(
+                format!(
+                    "Failed to open CAN interface {}: {}",
+                    self.config.can_interface, e
+                ),
+                e,
+            )
         })?;
 
         self.is_connected.store(true, Ordering::SeqCst);
@@ -257,10 +539,10 @@ impl ProtocolClient for J1939Client {
         // Start receive task
         self.start_receive_task()?;
 
-        // Notify connection change (broadcast is sync)
+        // Notify connection change
         let _ = self
-            .event_tx
-            .send(DataEvent::ConnectionChanged(ConnectionState::Connected));
+            .event_bus
+            .publish(DataEvent::ConnectionChanged(ConnectionState::Connected));
         if let Some(ref handler) = self.event_handler {
             handler
                 .on_connection_changed(ConnectionState::Connected)
@@ -279,10 +561,10 @@ impl ProtocolClient for J1939Client {
 
         *self.connection_state.write().await = ConnectionState::Disconnected;
 
-        // Notify connection change (broadcast is sync)
+        // Notify connection change
         let _ = self
-            .event_tx
-            .send(DataEvent::ConnectionChanged(ConnectionState::Disconnected));
+            .event_bus
+            .publish(DataEvent::ConnectionChanged(ConnectionState::Disconnected));
         if let Some(ref handler) = self.event_handler {
             handler
                 .on_connection_changed(ConnectionState::Disconnected)
@@ -292,21 +574,45 @@ impl ProtocolClient for J1939Client {
         Ok(())
     }
 
-    async fn write_control(&mut self, _commands: &[ControlCommand]) -> Result<WriteResult> {
-        // J1939 control requires proprietary PGN support
-        Err(GatewayError::Unsupported(
-            "J1939 control commands require proprietary PGN implementation".to_string(),
-        ))
+    /// Clear fault codes through the reserved DM3/DM11 points (see
+    /// [`dtc`](super::dtc)); other points fail, as J1939 control requires
+    /// proprietary PGNs.
+    async fn write_control(&mut self, commands: &[ControlCommand]) -> Result<WriteResult> {
+        if !self.is_connected.load(Ordering::SeqCst) {
+            return Err(GatewayError::NotConnected);
+        }
+
+        let mut result = WriteResult::success(0);
+        for command in commands {
+            let outcome = match dtc::clear_pgn(command.id) {
+                None => Err("J1939 control commands require proprietary PGN implementation".into()),
+                Some(pgn) if !command.value => {
+                    Err(format!("write true to send {}", dtc::request_name(pgn)))
+                }
+                Some(pgn) => self.clear_faults(pgn).await,
+            };
+            match outcome {
+                Ok(()) => result.success_count += 1,
+                Err(e) => {
+                    self.diagnostics
+                        .record_error(format!("Point {}: {}", command.id, e));
+                    result.failures.push((command.id, e));
+                }
+            }
+        }
+        Ok(result)
     }
 
     async fn poll_once(&mut self) -> PollResult {
-        // J1939 is event-driven, but poll_once returns cached data
+        // J1939 is event-driven, but poll_once returns a snapshot of cached data
         let cached = self.cached_data.read().await;
-        let mut batch = DataBatch::new();
-        for point in cached.values() {
-            batch.add(point.clone());
-        }
-        PollResult::success(batch)
+        let connected = self.is_connected.load(Ordering::SeqCst);
+        PollResult::success(cached_snapshot(
+            &cached,
+            connected,
+            self.config.stale_timeout_ms,
+            Utc::now(),
+        ))
     }
 
     async fn write_adjustment(
@@ -323,7 +629,7 @@ impl ProtocolClient for J1939Client {
 impl EventDrivenProtocol for J1939Client {
     fn subscribe(&self) -> DataEventReceiver {
         // Broadcast channel supports multiple subscribers
-        self.event_tx.subscribe()
+        self.event_bus.subscribe()
     }
 
     fn set_event_handler(&mut self, handler: Arc<dyn DataEventHandler>) {
@@ -352,8 +658,21 @@ impl EventDrivenProtocol for J1939Client {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::protocols::can::j1939::dtc::{CLEAR_ACTIVE_DTCS, DM11_PGN, DM3_PGN};
+    use socketcan::StandardId;
     use voltage_j1939::parse_can_id;
 
+    /// A client that believes it is connected to a CAN interface that does
+    /// not exist.
+    fn connected(config: J1939Config) -> J1939Client {
+        let client = J1939Client::new(J1939Config {
+            can_interface: "igw-test0".to_string(),
+            ..config
+        });
+        client.is_connected.store(true, Ordering::SeqCst);
+        client
+    }
+
     #[test]
     fn test_parse_can_id() {
         // EEC1 from SA=0x00: CAN ID = 0x0CF00400
@@ -389,6 +708,54 @@ mod tests {
         assert_eq!(config.can_interface, "can0");
         assert_eq!(config.source_address, 0x00);
         assert_eq!(config.our_address, 0xFE);
+        assert_eq!(config.stale_timeout_ms, 5000);
+        assert!(!config.allow_fault_clear);
+    }
+
+    #[test]
+    fn test_cached_snapshot_age_quality() {
+        let now = Utc::now();
+        let mut cached = HashMap::new();
+
+        let fresh = DataPoint::new(190, 1500.0);
+        let mut old = DataPoint::new(110, 85.0);
+        old.timestamp = now - chrono::Duration::seconds(10);
+        cached.insert(190, fresh);
+        cached.insert(110, old);
+
+        let batch = cached_snapshot(&cached, true, 5000, now);
+        let quality = |id| batch.iter().find(|p| p.id == id).unwrap().quality;
+        assert_eq!(quality(190), Quality::Good);
+        assert_eq!(quality(110), Quality::LastKnown);
+
+        // Stale check disabled
+        let batch = cached_snapshot(&cached, true, 0, now);
+        assert!(batch.iter().all(|p| p.quality.is_good()));
+
+        // Disconnected: everything is last-known
+        let batch = cached_snapshot(&cached, false, 0, now);
+        assert!(batch.iter().all(|p| p.quality == Quality::LastKnown));
+    }
+
+    #[tokio::test]
+    async fn test_read_request() {
+        let client = J1939Client::new(J1939Config::default());
+        client
+            .restore(&DataBatch::from_points(vec![
+                DataPoint::new(190, 1500.0),
+                DataPoint::new(110, 85.0),
+            ]))
+            .await;
+
+        // Not connected: every cached value is last-known
+        assert_eq!(client.read(&ReadRequest::bad_quality()).await.data.len(), 2);
+        let response = client.read(&ReadRequest::by_ids(vec![190])).await;
+        assert_eq!(response.data.len(), 1);
+        assert!(client
+            .read(&ReadRequest::by_group("engine"))
+            .await
+            .data
+            .is_empty());
     }
 
     #[test]
@@ -398,4 +765,125 @@ mod tests {
         assert_eq!(client.name(), "J1939");
         assert_eq!(client.supported_modes(), &[CommunicationMode::EventDriven]);
     }
+
+    #[test]
+    fn test_accepted_id() {
+        let extended = |raw| Id::Extended(ExtendedId::new(raw).unwrap());
+        // EEC1 from SA=0x00
+        assert_eq!(accepted_id(extended(0x0CF00400), 0x00), Some(0x0CF00400));
+        assert_eq!(accepted_id(extended(0x0CF00401), 0x00), None);
+        assert_eq!(
+            accepted_id(Id::Standard(StandardId::new(0x100).unwrap()), 0x00),
+            None
+        );
+
+        // Acknowledgement (59392 = 0xE800) from SA=0x00 to 0xFE, and to all
+        assert!(addressed_to(0x18E8FE00, 0xFE));
+        assert!(addressed_to(0x18E8FF00, 0xFE));
+        assert!(!addressed_to(0x18E81700, 0xFE));
+    }
+
+    #[tokio::test]
+    async fn test_fault_clear_guard() {
+        let mut client = connected(J1939Config::default());
+        let result = client
+            .write_control(&[
+                ControlCommand::latching(CLEAR_ACTIVE_DTCS, true),
+                ControlCommand::latching(CLEAR_ACTIVE_DTCS, false),
+                ControlCommand::latching(190, true),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(result.success_count, 0);
+        let errors: Vec<&str> = result.failures.iter().map(|(_, e)| e.as_str()).collect();
+        assert_eq!(
+            errors[0],
+            "DM11 fault clearing is disabled (allow_fault_clear)"
+        );
+        assert_eq!(errors[1], "write true to send DM11");
+        assert!(errors[2].contains("proprietary PGN"));
+
+        // Allowed, but the request cannot be sent
+        let mut client = connected(J1939Config {
+            allow_fault_clear: true,
+            ..Default::default()
+        });
+        let result = client
+            .write_control(&[ControlCommand::latching(CLEAR_ACTIVE_DTCS, true)])
+            .await
+            .unwrap();
+        assert!(result.failures[0].1.contains("igw-test0"));
+
+        client.is_connected.store(false, Ordering::SeqCst);
+        assert!(matches!(
+            client.write_control(&[]).await,
+            Err(GatewayError::NotConnected)
+        ));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_fault_clear_acknowledgement() {
+        let client = connected(J1939Config {
+            allow_fault_clear: true,
+            ack_timeout_ms: 1000,
+            ..Default::default()
+        });
+        let pending = client.pending_acks.clone();
+
+        // Positive acknowledgement of DM11 (65235 = 0x00FED3)
+        let ack = pending.expect(DM11_PGN);
+        pending.deliver(&[0, 0xFF, 0xFF, 0xFF, 0xFE, 0xD3, 0xFE, 0x00]);
+        assert_eq!(client.await_ack(DM11_PGN, ack).await, Ok(()));
+
+        // Negative acknowledgement of DM3 (65228 = 0x00FECC)
+        let ack = pending.expect(DM3_PGN);
+        pending.deliver(&[1, 0xFF, 0xFF, 0xFF, 0xFE, 0xCC, 0xFE, 0x00]);
+        assert_eq!(
+            client.await_ack(DM3_PGN, ack).await,
+            Err("ECU 0x00 refused DM3: not acknowledged".to_string())
+        );
+
+        // No answer: the wait is given up, and a late answer goes nowhere
+        let ack = pending.expect(DM11_PGN);
+        assert_eq!(
+            client.await_ack(DM11_PGN, ack).await,
+            Err("ECU 0x00 did not acknowledge DM11 within 1000 ms".to_string())
+        );
+        pending.deliver(&[0, 0xFF, 0xFF, 0xFF, 0xFE, 0xD3, 0xFE, 0x00]);
+    }
+
+    #[test]
+    fn test_refresh() {
+        let client = J1939Client::new(J1939Config {
+            request_pgns: vec![65253],
+            ..Default::default()
+        });
+        assert!(matches!(client.refresh(), Err(GatewayError::NotConnected)));
+
+        let client = connected(J1939Config::default());
+        assert_eq!(client.refresh().unwrap(), 0);
+
+        let client = connected(J1939Config {
+            request_pgns: vec![65253],
+            ..Default::default()
+        });
+        assert!(matches!(
+            client.refresh(),
+            Err(GatewayError::Connection { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_bus_stats_diagnostics() {
+        let client = J1939Client::new(J1939Config::default());
+        let now = Instant::now();
+        client.bus_stats.record(Some(61444), 8, now);
+        client.bus_stats.record(None, 8, now);
+
+        let extra = client.diagnostics().await.unwrap().extra;
+        assert_eq!(extra["can_interface"], "can0");
+        assert_eq!(extra["bus"]["seen"]["frames"], 2);
+        assert_eq!(extra["bus"]["filtered"]["frames"], 1);
+        assert_eq!(extra["pgns"]["61444"]["frames"], 1);
+    }
 }
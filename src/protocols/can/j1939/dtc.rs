@@ -0,0 +1,172 @@
+//! Clearing diagnostic trouble codes (DM3, DM11).
+//!
+//! Writing `true` to one of the reserved control points asks the ECU at
+//! `J1939Config::source_address` to clear its fault codes, with a Request
+//! PGN (59904) for DM3 or DM11 addressed to it:
+//!
+//! | Point id                          | Request | Clears                       |
+//! |-----------------------------------|---------|------------------------------|
+//! | [`CLEAR_PREVIOUSLY_ACTIVE_DTCS`]  | DM3     | previously active DTCs       |
+//! | [`CLEAR_ACTIVE_DTCS`]             | DM11    | active DTCs                  |
+//!
+//! The ids are 1 000 000 plus the PGN, outside the 19-bit SPN range used
+//! for data points. The command succeeds once the ECU answers with a
+//! positive Acknowledgement (PGN 59392); a negative one, or none within
+//! `J1939Config::ack_timeout_ms`, fails it. Both are refused unless
+//! `J1939Config::allow_fault_clear` is set, so monitoring deployments
+//! cannot clear faults by accident.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::oneshot;
+
+use crate::core::data::PointId;
+
+/// DM3: diagnostic data clear/reset of previously active DTCs.
+pub const DM3_PGN: u32 = 65228;
+
+/// DM11: diagnostic data clear/reset of active DTCs.
+pub const DM11_PGN: u32 = 65235;
+
+/// Acknowledgement PGN.
+pub(super) const ACK_PGN: u32 = 59392;
+
+/// Control point clearing previously active DTCs (DM3).
+pub const CLEAR_PREVIOUSLY_ACTIVE_DTCS: PointId = 1_000_000 + DM3_PGN;
+
+/// Control point clearing active DTCs (DM11).
+pub const CLEAR_ACTIVE_DTCS: PointId = 1_000_000 + DM11_PGN;
+
+/// PGN to request for a control point, if it is one of the reserved ones.
+pub(super) fn clear_pgn(point_id: PointId) -> Option<u32> {
+    match point_id {
+        CLEAR_PREVIOUSLY_ACTIVE_DTCS => Some(DM3_PGN),
+        CLEAR_ACTIVE_DTCS => Some(DM11_PGN),
+        _ => None,
+    }
+}
+
+/// Name of a clear request, for messages.
+pub(super) fn request_name(pgn: u32) -> &'static str {
+    if pgn == DM3_PGN {
+        "DM3"
+    } else {
+        "DM11"
+    }
+}
+
+/// Control byte of an Acknowledgement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Acknowledgement {
+    /// Positive acknowledgement: the request was carried out.
+    Ack,
+    /// Negative acknowledgement: the request is not supported.
+    Nack,
+    /// The ECU refused access.
+    AccessDenied,
+    /// The ECU cannot respond right now.
+    CannotRespond,
+    /// Any other control byte.
+    Other(u8),
+}
+
+impl fmt::Display for Acknowledgement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ack => f.write_str("acknowledged"),
+            Self::Nack => f.write_str("not acknowledged"),
+            Self::AccessDenied => f.write_str("access denied"),
+            Self::CannotRespond => f.write_str("cannot respond"),
+            Self::Other(control) => write!(f, "control byte {}", control),
+        }
+    }
+}
+
+/// Parse an Acknowledgement frame into the acknowledged PGN and the
+/// answer.
+///
+/// Byte 0 is the control byte and bytes 5 to 7 the PGN, least
+/// significant byte first.
+pub(super) fn parse_ack(data: &[u8]) -> Option<(u32, Acknowledgement)> {
+    if data.len() < 8 {
+        return None;
+    }
+    let answer = match data[0] {
+        0 => Acknowledgement::Ack,
+        1 => Acknowledgement::Nack,
+        2 => Acknowledgement::AccessDenied,
+        3 => Acknowledgement::CannotRespond,
+        control => Acknowledgement::Other(control),
+    };
+    let pgn = u32::from_le_bytes([data[5], data[6], data[7], 0]);
+    Some((pgn, answer))
+}
+
+/// Requests waiting for their Acknowledgement, by requested PGN.
+#[derive(Debug, Clone, Default)]
+pub(super) struct PendingAcks(Arc<Mutex<HashMap<u32, oneshot::Sender<Acknowledgement>>>>);
+
+impl PendingAcks {
+    /// Wait for the Acknowledgement of `pgn`, replacing an earlier wait.
+    pub(super) fn expect(&self, pgn: u32) -> oneshot::Receiver<Acknowledgement> {
+        let (tx, rx) = oneshot::channel();
+        self.lock().insert(pgn, tx);
+        rx
+    }
+
+    /// Stop waiting for `pgn`.
+    pub(super) fn cancel(&self, pgn: u32) {
+        self.lock().remove(&pgn);
+    }
+
+    /// Hand a received Acknowledgement frame to the request waiting for it.
+    pub(super) fn deliver(&self, data: &[u8]) {
+        let Some((pgn, answer)) = parse_ack(data) else {
+            return;
+        };
+        if let Some(tx) = self.lock().remove(&pgn) {
+            let _ = tx.send(answer);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<u32, oneshot::Sender<Acknowledgement>>> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reserved_points() {
+        assert_eq!(clear_pgn(CLEAR_PREVIOUSLY_ACTIVE_DTCS), Some(DM3_PGN));
+        assert_eq!(clear_pgn(CLEAR_ACTIVE_DTCS), Some(DM11_PGN));
+        assert_eq!(clear_pgn(190), None);
+        // Beyond the 19-bit SPN range
+        const { assert!(CLEAR_PREVIOUSLY_ACTIVE_DTCS > 0x7FFFF) };
+    }
+
+    #[tokio::test]
+    async fn test_acknowledgements() {
+        let pending = PendingAcks::default();
+        let dm11 = pending.expect(DM11_PGN);
+        let dm3 = pending.expect(DM3_PGN);
+
+        // Negative acknowledgement of DM3 (65228 = 0x00FECC)
+        pending.deliver(&[1, 0xFF, 0xFF, 0xFF, 0xFE, 0xCC, 0xFE, 0x00]);
+        assert_eq!(dm3.await.unwrap(), Acknowledgement::Nack);
+        // Truncated frames and unrequested PGNs are ignored
+        pending.deliver(&[0, 0xFF, 0xFF]);
+        pending.deliver(&[0, 0xFF, 0xFF, 0xFF, 0xFE, 0xE5, 0xFE, 0x00]);
+        pending.deliver(&[0, 0xFF, 0xFF, 0xFF, 0xFE, 0xD3, 0xFE, 0x00]);
+        assert_eq!(dm11.await.unwrap(), Acknowledgement::Ack);
+
+        assert_eq!(
+            parse_ack(&[3, 0xFF, 0xFF, 0xFF, 0xFE, 0xD3, 0xFE, 0x00]),
+            Some((DM11_PGN, Acknowledgement::CannotRespond))
+        );
+    }
+}
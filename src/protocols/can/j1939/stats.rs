@@ -0,0 +1,240 @@
+//! Receive statistics of a J1939 bus.
+//!
+//! Every frame read from the CAN socket is counted, once for the bus as a
+//! whole and, if it comes from the configured source address, once for its
+//! PGN. Frames from other source addresses and 11-bit frames are counted
+//! as filtered. Rates are averaged over windows of [`RATE_WINDOW`], so a
+//! PGN that stops arriving decays to 0 frames/s instead of keeping its
+//! last rate.
+//!
+//! Recording a frame takes one uncontended lock and one map lookup, cheap
+//! enough for a fully loaded 250 kbit/s bus (about 2000 frames/s).
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde_json::json;
+use voltage_j1939::get_spns_for_pgn;
+
+/// Period over which receive rates are averaged.
+const RATE_WINDOW: Duration = Duration::from_secs(5);
+
+/// Names of the PGNs in the SPN database.
+const PGN_NAMES: &[(u32, &str)] = &[
+    (61443, "EEC2 - Electronic Engine Controller 2"),
+    (61444, "EEC1 - Electronic Engine Controller 1"),
+    (65217, "VH - Vehicle Hours"),
+    (65247, "EEC3 - Electronic Engine Controller 3"),
+    (65248, "DD - Distance"),
+    (65253, "HOURS - Engine Hours, Revolutions"),
+    (65257, "FC - Fuel Consumption"),
+    (65262, "ET1 - Engine Temperature 1"),
+    (65263, "EFL/P1 - Engine Fluid Level/Pressure 1"),
+    (65265, "CCVS - Cruise Control/Vehicle Speed"),
+    (65266, "LFE - Liquid Fuel Economy"),
+    (65269, "AMB - Ambient Conditions"),
+    (65270, "IC1 - Inlet/Exhaust Conditions 1"),
+    (65271, "VEP1 - Vehicle Electrical Power 1"),
+];
+
+/// Name of a PGN the SPN database decodes, e.g. `EEC1 - Electronic
+/// Engine Controller 1` for 61444.
+///
+/// Returns `None` for PGNs without SPN definitions.
+pub fn pgn_name(pgn: u32) -> Option<&'static str> {
+    get_spns_for_pgn(pgn)?;
+    let name = PGN_NAMES.iter().find(|(known, _)| *known == pgn);
+    Some(name.map_or("unnamed", |(_, name)| *name))
+}
+
+/// Frames and bytes received, with their rolling rates.
+#[derive(Debug, Clone)]
+struct Counter {
+    frames: u64,
+    bytes: u64,
+    window_start: Instant,
+    window_frames: u64,
+    window_bytes: u64,
+    /// Frames/s and bytes/s over the last complete window.
+    rates: (f64, f64),
+}
+
+impl Counter {
+    fn new(now: Instant) -> Self {
+        Self {
+            frames: 0,
+            bytes: 0,
+            window_start: now,
+            window_frames: 0,
+            window_bytes: 0,
+            rates: (0.0, 0.0),
+        }
+    }
+
+    fn add(&mut self, bytes: usize, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.window_start);
+        if elapsed >= RATE_WINDOW {
+            self.rates = per_second(self.window_frames, self.window_bytes, elapsed);
+            self.window_start = now;
+            self.window_frames = 0;
+            self.window_bytes = 0;
+        }
+        self.frames += 1;
+        self.bytes += bytes as u64;
+        self.window_frames += 1;
+        self.window_bytes += bytes as u64;
+    }
+
+    /// Frames/s and bytes/s as of `now`.
+    fn rates(&self, now: Instant) -> (f64, f64) {
+        let elapsed = now.saturating_duration_since(self.window_start);
+        if elapsed < RATE_WINDOW {
+            self.rates
+        } else {
+            per_second(self.window_frames, self.window_bytes, elapsed)
+        }
+    }
+
+    fn to_json(&self, now: Instant) -> serde_json::Value {
+        let (frames_per_sec, bytes_per_sec) = self.rates(now);
+        json!({
+            "frames": self.frames,
+            "bytes": self.bytes,
+            "frames_per_sec": round(frames_per_sec),
+            "bytes_per_sec": round(bytes_per_sec),
+        })
+    }
+}
+
+fn per_second(frames: u64, bytes: u64, elapsed: Duration) -> (f64, f64) {
+    let secs = elapsed.as_secs_f64();
+    (frames as f64 / secs, bytes as f64 / secs)
+}
+
+/// Round a rate to one decimal.
+fn round(rate: f64) -> f64 {
+    (rate * 10.0).round() / 10.0
+}
+
+/// Per-PGN and whole-bus receive counters of a J1939 client.
+#[derive(Debug)]
+pub(super) struct BusStats {
+    inner: Mutex<Counters>,
+}
+
+#[derive(Debug)]
+struct Counters {
+    /// Every frame read from the bus.
+    seen: Counter,
+    /// Frames not from the configured source address.
+    filtered: Counter,
+    /// Frames from the source address, by PGN.
+    pgns: HashMap<u32, Counter>,
+}
+
+impl BusStats {
+    pub(super) fn new() -> Self {
+        let now = Instant::now();
+        Self {
+            inner: Mutex::new(Counters {
+                seen: Counter::new(now),
+                filtered: Counter::new(now),
+                pgns: HashMap::new(),
+            }),
+        }
+    }
+
+    /// Count a frame of `bytes` data bytes, with the PGN it was accepted
+    /// for or `None` if it was filtered out.
+    pub(super) fn record(&self, pgn: Option<u32>, bytes: usize, now: Instant) {
+        let mut counters = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        counters.seen.add(bytes, now);
+        match pgn {
+            Some(pgn) => counters
+                .pgns
+                .entry(pgn)
+                .or_insert_with(|| Counter::new(now))
+                .add(bytes, now),
+            None => counters.filtered.add(bytes, now),
+        }
+    }
+
+    /// `Diagnostics::extra` entries: `bus` (`seen` and `filtered` frames),
+    /// `pgns` keyed by PGN with each PGN's `name` (`null` if the SPN
+    /// database does not know it) and `unknown_pgns`, the PGNs received
+    /// but not in the database.
+    pub(super) fn to_json(&self, now: Instant) -> serde_json::Value {
+        let counters = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let mut pgns = serde_json::Map::new();
+        let mut unknown = Vec::new();
+        for (&pgn, counter) in &counters.pgns {
+            let mut entry = counter.to_json(now);
+            let name = pgn_name(pgn);
+            if name.is_none() {
+                unknown.push(pgn);
+            }
+            entry["name"] = json!(name);
+            pgns.insert(pgn.to_string(), entry);
+        }
+        unknown.sort_unstable();
+        json!({
+            "bus": {
+                "seen": counters.seen.to_json(now),
+                "filtered": counters.filtered.to_json(now),
+            },
+            "pgns": pgns,
+            "unknown_pgns": unknown,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use voltage_j1939::list_supported_pgns;
+
+    #[test]
+    fn test_pgn_names() {
+        assert_eq!(
+            pgn_name(61444),
+            Some("EEC1 - Electronic Engine Controller 1")
+        );
+        assert_eq!(pgn_name(0xFF00), None);
+        // Every PGN of the database is named
+        assert!(list_supported_pgns()
+            .into_iter()
+            .all(|pgn| PGN_NAMES.iter().any(|(named, _)| *named == pgn)));
+    }
+
+    #[test]
+    fn test_receive_rates() {
+        let start = Instant::now();
+        let stats = BusStats::new();
+        // 20 frames/s of EEC1 for 6 s, and one frame of a proprietary PGN
+        for i in 0..120 {
+            let now = start + Duration::from_millis(50 * i);
+            stats.record(Some(61444), 8, now);
+            stats.record(None, 8, now);
+        }
+        stats.record(Some(0xFF00), 3, start + Duration::from_secs(6));
+
+        let now = start + Duration::from_secs(6);
+        let extra = stats.to_json(now);
+        let eec1 = &extra["pgns"]["61444"];
+        assert_eq!(eec1["name"], "EEC1 - Electronic Engine Controller 1");
+        assert_eq!(eec1["frames"], 120);
+        assert_eq!(eec1["bytes"], 960);
+        assert_eq!(eec1["frames_per_sec"], 20.0);
+        assert_eq!(eec1["bytes_per_sec"], 160.0);
+        assert_eq!(extra["pgns"]["65280"]["name"], serde_json::Value::Null);
+        assert_eq!(extra["unknown_pgns"], json!([65280]));
+        assert_eq!(extra["bus"]["seen"]["frames"], 241);
+        assert_eq!(extra["bus"]["filtered"]["frames"], 120);
+        assert_eq!(extra["bus"]["filtered"]["frames_per_sec"], 20.0);
+
+        // A PGN that stops arriving decays
+        let extra = stats.to_json(start + Duration::from_secs(20));
+        assert!(extra["pgns"]["61444"]["frames_per_sec"].as_f64().unwrap() < 2.0);
+    }
+}
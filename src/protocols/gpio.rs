@@ -71,8 +71,9 @@ use crate::core::logging::{
 };
 use crate::core::metadata::{DriverMetadata, HasMetadata, ParameterMetadata, ParameterType};
 use crate::core::traits::{
-    AdjustmentCommand, CommunicationMode, ConnectionState, ControlCommand, Diagnostics,
-    PointFailure, PollResult, Protocol, ProtocolCapabilities, ProtocolClient, WriteResult,
+    AdjustmentCommand, CommandStage, CommunicationMode, ConnectionState, ControlCommand,
+    Diagnostics, PointFailure, PollResult, Protocol, ProtocolCapabilities, ProtocolClient,
+    QualityFilter, ReadRequest, ReadResponse, WriteResult,
 };
 
 // ============================================================================
@@ -116,6 +117,7 @@ impl Default for GpioDriverType {
 /// - `gpio_chip`: GPIO chip name (default: "gpiochip0")
 /// - `active_low`: Invert the logic level (default: false)
 /// - `debounce_us`: Debounce time in microseconds (default: 0)
+/// - `poll_group`: Poll group the pin belongs to (default: none)
 ///
 /// # Example JSON
 /// ```json
@@ -141,6 +143,10 @@ pub struct GpioMappingConfig {
     /// Debounce time in microseconds.
     #[serde(default)]
     pub debounce_us: u64,
+
+    /// Poll group the pin belongs to.
+    #[serde(default)]
+    pub poll_group: Option<String>,
 }
 
 fn default_gpio_chip() -> String {
@@ -153,10 +159,12 @@ impl GpioMappingConfig {
     /// If `gpio_chip` is "gpiochip0" (default) and `gpio_number` >= 32,
     /// the driver will auto-resolve the global GPIO number to the correct chip.
     pub fn to_input_pin_config(&self, point_id: u32) -> GpioPinConfig {
-        GpioPinConfig::digital_input(&self.gpio_chip, self.gpio_number, point_id)
+        let mut pin = GpioPinConfig::digital_input(&self.gpio_chip, self.gpio_number, point_id)
             .with_gpio_number(self.gpio_number) // Enable auto-resolution
             .with_active_low(self.active_low)
-            .with_debounce(self.debounce_us)
+            .with_debounce(self.debounce_us);
+        pin.poll_group = self.poll_group.clone();
+        pin
     }
 
     /// Convert to igw GpioPinConfig for output (DO).
@@ -164,48 +172,15 @@ impl GpioMappingConfig {
     /// If `gpio_chip` is "gpiochip0" (default) and `gpio_number` >= 32,
     /// the driver will auto-resolve the global GPIO number to the correct chip.
     pub fn to_output_pin_config(&self, point_id: u32) -> GpioPinConfig {
-        GpioPinConfig::digital_output(&self.gpio_chip, self.gpio_number, point_id)
+        let mut pin = GpioPinConfig::digital_output(&self.gpio_chip, self.gpio_number, point_id)
             .with_gpio_number(self.gpio_number) // Enable auto-resolution
-            .with_active_low(self.active_low)
+            .with_active_low(self.active_low);
+        pin.poll_group = self.poll_group.clone();
+        pin
     }
 }
 
-/// GPIO channel parameters configuration (deserialized from parameters_json).
-///
-/// # Example JSON
-/// ```json
-/// {
-///     "driver": "gpiod",
-///     "gpio_chip": "gpiochip0",
-///     "poll_interval_ms": 200
-/// }
-/// ```
-#[derive(Debug, Clone, Deserialize)]
-pub struct GpioChannelParamsConfig {
-    /// Driver type: "gpiod" or "sysfs".
-    #[serde(default = "default_driver")]
-    pub driver: String,
-
-    /// Sysfs base path (only for sysfs driver).
-    #[serde(default = "default_sysfs_path")]
-    pub sysfs_base_path: String,
-
-    /// Poll interval in milliseconds.
-    #[serde(default = "default_poll_interval")]
-    pub poll_interval_ms: u64,
-}
-
-fn default_driver() -> String {
-    "gpiod".to_string()
-}
-
-fn default_sysfs_path() -> String {
-    "/sys/class/gpio".to_string()
-}
-
-fn default_poll_interval() -> u64 {
-    200
-}
+pub use crate::gateway::params::GpioChannelParamsConfig;
 
 impl GpioChannelParamsConfig {
     /// Get the GPIO driver type from configuration.
@@ -687,6 +662,9 @@ pub struct GpioPinConfig {
 
     /// Debounce time for inputs (microseconds).
     pub debounce_us: Option<u64>,
+
+    /// Poll group (selects the pin in [`ReadRequest::by_group()`]).
+    pub poll_group: Option<String>,
 }
 
 impl GpioPinConfig {
@@ -705,6 +683,7 @@ impl GpioPinConfig {
             point_id,
             active_low: false,
             debounce_us: Some(1000), // 1ms default debounce
+            poll_group: None,
         }
     }
 
@@ -718,6 +697,7 @@ impl GpioPinConfig {
             point_id,
             active_low: false,
             debounce_us: None,
+            poll_group: None,
         }
     }
 
@@ -735,6 +715,7 @@ impl GpioPinConfig {
             point_id,
             active_low: false,
             debounce_us: Some(1000),
+            poll_group: None,
         }
     }
 
@@ -748,6 +729,7 @@ impl GpioPinConfig {
             point_id,
             active_low: false,
             debounce_us: None,
+            poll_group: None,
         }
     }
 
@@ -768,6 +750,12 @@ impl GpioPinConfig {
         self.debounce_us = Some(debounce_us);
         self
     }
+
+    /// Set the poll group.
+    pub fn with_poll_group(mut self, group: impl Into<String>) -> Self {
+        self.poll_group = Some(group.into());
+        self
+    }
 }
 
 /// GPIO channel configuration.
@@ -1000,11 +988,58 @@ impl GpioChannel {
     ///
     /// This method reads all input pins and output states, collecting any failures.
     async fn read_all(&self) -> (DataBatch, Vec<PointFailure>) {
+        self.read_pins(|_| true).await
+    }
+
+    /// Read the pins selected by `request`.
+    ///
+    /// Like `poll_once()`, inputs are read from the hardware and outputs
+    /// from the state cache. Only pins whose id and poll group match are
+    /// read; the quality filter applies to the values read. Failed reads are
+    /// reported in the response unless the request asks for good quality.
+    pub async fn read(&self, request: &ReadRequest) -> ReadResponse {
+        let (batch, failures) = self
+            .read_pins(|pin| {
+                request
+                    .point_ids
+                    .as_ref()
+                    .is_none_or(|ids| ids.contains(&pin.point_id))
+                    && request
+                        .poll_group
+                        .as_ref()
+                        .is_none_or(|group| pin.poll_group.as_ref() == Some(group))
+            })
+            .await;
+        let data = batch
+            .iter()
+            .filter(|point| {
+                request
+                    .quality_filter
+                    .is_none_or(|filter| filter.matches(point.quality))
+            })
+            .cloned()
+            .collect();
+        let errors = if request.quality_filter == Some(QualityFilter::Good) {
+            Vec::new()
+        } else {
+            failures
+                .into_iter()
+                .map(|f| (f.point_id, f.error))
+                .collect()
+        };
+        ReadResponse::with_errors(data, errors)
+    }
+
+    /// Read the selected input pins and output states.
+    async fn read_pins(
+        &self,
+        selected: impl Fn(&GpioPinConfig) -> bool,
+    ) -> (DataBatch, Vec<PointFailure>) {
         let mut batch = DataBatch::new();
         let mut failures = Vec::new();
 
         // Read all input pins
-        for pin in self.config.input_pins() {
+        for pin in self.config.input_pins().filter(|pin| selected(pin)) {
             match self.read_pin(pin).await {
                 Ok(point) => batch.add(point),
                 Err(e) => {
@@ -1017,7 +1052,7 @@ impl GpioChannel {
         }
 
         // Also include output states as feedback
-        for pin in self.config.output_pins() {
+        for pin in self.config.output_pins().filter(|pin| selected(pin)) {
             if let Some(state) = self.read_output_state(pin.point_id).await {
                 batch.add(DataPoint::new(pin.point_id, state));
             }
@@ -1053,6 +1088,7 @@ impl Protocol for GpioChannel {
                 "input_pins": input_count,
                 "output_pins": output_count,
             }),
+            ..Default::default()
         })
     }
 }
@@ -1148,6 +1184,7 @@ impl ProtocolClient for GpioChannel {
         let result = WriteResult {
             success_count,
             failures,
+            stage: CommandStage::Confirmed,
         };
 
         // Log control write
@@ -1231,4 +1268,19 @@ mod tests {
         assert_eq!(diag.extra["input_pins"], 1);
         assert_eq!(diag.extra["output_pins"], 1);
     }
+
+    #[tokio::test]
+    async fn test_gpio_read_by_group() {
+        // No such chip: each selected pin is reported as an error
+        let config = GpioChannelConfig::new()
+            .add_pin(GpioPinConfig::digital_input("igw-test-chip", 18, 101).with_poll_group("fast"))
+            .add_pin(GpioPinConfig::digital_input("igw-test-chip", 19, 102));
+        let gpio = GpioChannel::new(config);
+
+        let response = gpio.read(&ReadRequest::by_group("fast")).await;
+        assert!(response.data.is_empty());
+        let ids: Vec<_> = response.partial_errors.iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids, vec![101]);
+        assert_eq!(gpio.read(&ReadRequest::all()).await.partial_errors.len(), 2);
+    }
 }
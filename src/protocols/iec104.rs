@@ -28,23 +28,36 @@
 //!     }
 //! }
 //! ```
+//!
+//! # Deadbands
+//!
+//! Measured values of points with a
+//! [`deadband`](crate::core::point::TransformConfig::deadband) are dropped
+//! while their scaled value stays within it of the value last delivered
+//! (see [`DeadbandFilter`]); a quality change always gets through.
+//! Sending a general interrogation forgets the values last delivered, so
+//! the interrogation still refreshes every point. Dropped values are counted in
+//! `Diagnostics::extra` (`deadband_suppressed`).
 
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
 
-use chrono::{DateTime, TimeZone, Utc};
-use tokio::sync::{broadcast, RwLock};
+use chrono::Utc;
+use tokio::sync::RwLock;
 use voltage_iec104::{ClientConfig, Cp56Time2a, Iec104Client, Iec104Event};
 
+use crate::codec::iec_time;
 use crate::core::data::{DataBatch, DataPoint, Value};
+use crate::core::deadband::DeadbandFilter;
 use crate::core::error::{GatewayError, Result};
 use crate::core::point::PointConfig;
 use crate::core::quality::Quality;
 use crate::core::traits::{
-    AdjustmentCommand, CommunicationMode, ConnectionState, ControlCommand, DataEvent,
-    DataEventHandler, DataEventReceiver, DataEventSender, Diagnostics, EventDrivenProtocol,
-    PollResult, Protocol, ProtocolCapabilities, ProtocolClient, WriteResult,
+    AdjustmentCommand, CommandOutcome, CommandStage, CommunicationMode, ConnectionState,
+    ControlCommand, DataEvent, DataEventHandler, DataEventReceiver, Diagnostics, EventBus,
+    EventDrivenProtocol, OperateMode, PollResult, Protocol, ProtocolCapabilities, ProtocolClient,
+    WriteResult,
 };
 
 /// IEC 104 channel configuration.
@@ -153,65 +166,7 @@ impl Iec104ChannelConfig {
     }
 }
 
-/// IEC 104 channel parameters for JSON configuration.
-///
-/// This is a serde-friendly version of the configuration that can be
-/// deserialized from JSON and converted to `Iec104ChannelConfig`.
-///
-/// # Example JSON
-///
-/// ```json
-/// {
-///     "address": "192.168.1.100:2404",
-///     "common_address": 1,
-///     "connect_timeout_ms": 10000
-/// }
-/// ```
-#[derive(Debug, Clone, serde::Deserialize)]
-pub struct Iec104ParamsConfig {
-    /// Target address (e.g., "192.168.1.100:2404")
-    pub address: String,
-
-    /// Common address of ASDU (station address)
-    #[serde(default = "default_common_address")]
-    pub common_address: u16,
-
-    /// Connection timeout in milliseconds
-    #[serde(default = "default_connect_timeout_ms")]
-    pub connect_timeout_ms: u64,
-
-    /// T1 timeout in seconds
-    #[serde(default = "default_t1_timeout")]
-    pub t1_timeout_s: u64,
-
-    /// T2 timeout in seconds
-    #[serde(default = "default_t2_timeout")]
-    pub t2_timeout_s: u64,
-
-    /// T3 timeout in seconds
-    #[serde(default = "default_t3_timeout")]
-    pub t3_timeout_s: u64,
-}
-
-fn default_common_address() -> u16 {
-    1
-}
-
-fn default_connect_timeout_ms() -> u64 {
-    10000
-}
-
-fn default_t1_timeout() -> u64 {
-    15
-}
-
-fn default_t2_timeout() -> u64 {
-    10
-}
-
-fn default_t3_timeout() -> u64 {
-    20
-}
+pub use crate::gateway::params::Iec104ParamsConfig;
 
 impl Iec104ParamsConfig {
     /// Convert to Iec104ChannelConfig.
@@ -239,12 +194,14 @@ pub struct Iec104Channel {
     client: Iec104Client,
     state: Arc<std::sync::RwLock<ConnectionState>>,
     diagnostics: Arc<RwLock<ChannelDiagnostics>>,
-    /// Broadcast sender for event-driven subscribers (multiple subscribers supported).
-    event_tx: DataEventSender,
+    /// Event bus for event-driven subscribers (multiple subscribers supported).
+    event_bus: EventBus,
     event_handler: Option<Arc<dyn DataEventHandler>>,
     poll_task: Option<tokio::task::JoinHandle<()>>,
     /// Point ID -> index lookup for O(1) access
     point_index: HashMap<u32, usize>,
+    /// Drops values within their point's deadband.
+    deadband: std::sync::Mutex<DeadbandFilter>,
 }
 
 #[derive(Debug, Default)]
@@ -254,6 +211,7 @@ struct ChannelDiagnostics {
     error_count: u64,
     last_error: Option<String>,
     last_interrogation: Option<std::time::Instant>,
+    deadband_suppressed: u64,
 }
 
 impl Iec104Channel {
@@ -261,8 +219,7 @@ impl Iec104Channel {
     pub fn new(config: Iec104ChannelConfig) -> Self {
         let client_config = config.to_client_config();
         let client = Iec104Client::new(client_config);
-        // Use broadcast channel for multiple subscribers
-        let (event_tx, _) = broadcast::channel(1024);
+        let event_bus = EventBus::default();
 
         // Build point ID -> index mapping for O(1) lookup
         let point_index: HashMap<u32, usize> = config
@@ -271,16 +228,18 @@ impl Iec104Channel {
             .enumerate()
             .map(|(i, p)| (p.id, i))
             .collect();
+        let deadband = std::sync::Mutex::new(DeadbandFilter::new(&config.points));
 
         Self {
             config,
             client,
             state: Arc::new(std::sync::RwLock::new(ConnectionState::Disconnected)),
             diagnostics: Arc::new(RwLock::new(ChannelDiagnostics::default())),
-            event_tx,
+            event_bus,
             event_handler: None,
             poll_task: None,
             point_index,
+            deadband,
         }
     }
 
@@ -321,6 +280,8 @@ impl Iec104Channel {
             .general_interrogation(self.config.common_address)
             .await
             .map_err(|e| GatewayError::Protocol(e.to_string()))?;
+        // Answers within a deadband of the last value still get through
+        self.reset_deadband();
 
         let mut diag = self.diagnostics.write().await;
         diag.last_interrogation = Some(std::time::Instant::now());
@@ -339,7 +300,9 @@ impl Iec104Channel {
 
     /// Send clock synchronization command.
     pub async fn clock_sync(&mut self) -> Result<()> {
-        let time = cp56time2a_now();
+        let tag = iec_time::Cp56Time2a::new(Utc::now()).encode()?;
+        let time =
+            Cp56Time2a::from_bytes(&tag).map_err(|e| GatewayError::Protocol(e.to_string()))?;
         self.client
             .clock_sync(self.config.common_address, time)
             .await
@@ -366,15 +329,16 @@ impl Iec104Channel {
         match event {
             Iec104Event::Connected => {
                 self.set_state(ConnectionState::Connected);
+                self.reset_deadband();
                 let _ = self
-                    .event_tx
-                    .send(DataEvent::ConnectionChanged(ConnectionState::Connected));
+                    .event_bus
+                    .publish(DataEvent::ConnectionChanged(ConnectionState::Connected));
             }
             Iec104Event::Disconnected => {
                 self.set_state(ConnectionState::Disconnected);
                 let _ = self
-                    .event_tx
-                    .send(DataEvent::ConnectionChanged(ConnectionState::Disconnected));
+                    .event_bus
+                    .publish(DataEvent::ConnectionChanged(ConnectionState::Disconnected));
             }
             Iec104Event::DataTransferStarted => {
                 // Data transfer is active
@@ -383,42 +347,80 @@ impl Iec104Channel {
                 // Data transfer stopped
             }
             Iec104Event::DataUpdate(points) => {
+                tracing::debug!(points = points.len(), "Data update");
                 let batch = self.convert_data_points(points).await;
                 if !batch.is_empty() {
                     // Send event (service layer handles storage)
-                    let _ = self.event_tx.send(DataEvent::DataUpdate(batch));
+                    let _ = self
+                        .event_bus
+                        .publish(DataEvent::DataUpdate(Arc::new(batch)));
 
                     // Update diagnostics
                     let mut diag = self.diagnostics.write().await;
                     diag.recv_count += 1;
                 }
             }
-            Iec104Event::AsduReceived(_asdu) => {
+            Iec104Event::AsduReceived(asdu) => {
                 // Raw ASDU - usually for command responses
+                if let Some(ioa) = terminated_command(&asdu) {
+                    let point_id = self.config.ioa_mapping.get(&ioa).copied().unwrap_or(ioa);
+                    let outcome = CommandOutcome::new(point_id, CommandStage::Terminated);
+                    let _ = self.event_bus.publish(DataEvent::CommandUpdate(outcome));
+                }
             }
             Iec104Event::CommandConfirm { ioa, success } => {
-                // Command confirmation
-                let mut diag = self.diagnostics.write().await;
-                if success {
-                    diag.send_count += 1;
-                } else {
-                    diag.error_count += 1;
-                    diag.last_error = Some(format!("Command failed for IOA {}", ioa));
-                }
+                // Command confirmation (ACTCON), delivered to subscribers as a late outcome
+                let point_id = self.config.ioa_mapping.get(&ioa).copied().unwrap_or(ioa);
+                let outcome = {
+                    let mut diag = self.diagnostics.write().await;
+                    if success {
+                        diag.send_count += 1;
+                        CommandOutcome::new(point_id, CommandStage::Confirmed)
+                    } else {
+                        let msg = format!("Command failed for IOA {}", ioa);
+                        tracing::warn!(ioa, point_id, "Command rejected by outstation");
+                        diag.error_count += 1;
+                        diag.last_error = Some(msg.clone());
+                        CommandOutcome::failed(point_id, msg)
+                    }
+                };
+                let _ = self.event_bus.publish(DataEvent::CommandUpdate(outcome));
             }
             Iec104Event::InterrogationComplete { common_address: _ } => {
                 // Interrogation finished
             }
             Iec104Event::Error(msg) => {
+                tracing::warn!(error = %msg, "IEC 104 error");
                 self.record_error(&msg).await;
-                let _ = self.event_tx.send(DataEvent::Error(msg));
+                let _ = self.event_bus.publish(DataEvent::Error(msg));
             }
         }
     }
 
+    /// Forget the values last delivered, so the next ones all get through.
+    fn reset_deadband(&self) {
+        if let Ok(mut filter) = self.deadband.lock() {
+            filter.reset();
+        }
+    }
+
     /// Convert IEC 104 data points to igw DataBatch.
+    ///
+    /// Values within their point's deadband are left out.
     async fn convert_data_points(&self, points: Vec<voltage_iec104::DataPoint>) -> DataBatch {
+        let (batch, suppressed) = self.decode_points(points);
+        if suppressed > 0 {
+            self.diagnostics.write().await.deadband_suppressed += suppressed;
+        }
+        batch
+    }
+
+    /// Decode data points, returning the batch and how many values the
+    /// deadband filter dropped.
+    fn decode_points(&self, points: Vec<voltage_iec104::DataPoint>) -> (DataBatch, u64) {
         let mut batch = DataBatch::new();
+        let mut suppressed = 0u64;
+        let mut filter = self.deadband.lock().unwrap_or_else(|e| e.into_inner());
 
         for point in points {
             // Look up point ID from IOA, or use IOA directly as fallback
@@ -435,8 +437,26 @@ impl Iec104Channel {
             // Convert quality
             let quality = convert_iec104_quality(&point.quality);
 
+            // Apply configured transform and range check; device quality wins if already bad
+            let (value, quality) = match self.find_point(point_id) {
+                Some(cfg) => {
+                    let (value, range_quality) = cfg.transform.apply_value(value);
+                    let quality = if quality.is_good() {
+                        range_quality
+                    } else {
+                        quality
+                    };
+                    (value, quality)
+                }
+                None => (value, quality),
+            };
+
             // Convert source timestamp
-            let source_timestamp = point.timestamp.as_ref().and_then(cp56time2a_to_datetime);
+            // An invalid or out-of-range time tag leaves the source time unset
+            let source_timestamp = point
+                .timestamp
+                .and_then(|time| iec_time::Cp56Time2a::decode(&time.to_bytes()).ok())
+                .map(|tag| tag.time);
 
             let dp = DataPoint {
                 id: point_id,
@@ -446,10 +466,45 @@ impl Iec104Channel {
                 source_timestamp,
             };
 
+            if !filter.admit(&dp) {
+                suppressed += 1;
+                continue;
+            }
+
             batch.add(dp);
         }
+        (batch, suppressed)
+    }
 
-        batch
+    /// Wait for the ACTCON of a select command on `ioa`.
+    ///
+    /// Other events received meanwhile are handled normally. Returns whether
+    /// the outstation confirmed the selection positively.
+    async fn await_select_confirmation(&mut self, ioa: u32, timeout: Duration) -> Result<bool> {
+        let deadline = tokio::time::Instant::now() + timeout;
+
+        loop {
+            let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
+            if remaining.is_zero() {
+                tracing::warn!(
+                    ioa,
+                    timeout_ms = timeout.as_millis() as u64,
+                    "No select confirmation"
+                );
+                return Err(GatewayError::WriteTimeout);
+            }
+
+            match tokio::time::timeout(remaining, self.client.poll()).await {
+                Ok(Ok(Some(Iec104Event::CommandConfirm {
+                    ioa: confirmed,
+                    success,
+                }))) if confirmed == ioa => return Ok(success),
+                Ok(Ok(Some(event))) => self.handle_iec104_event(event).await,
+                Ok(Ok(None)) => tokio::time::sleep(Duration::from_millis(10)).await,
+                Ok(Err(e)) => return Err(GatewayError::Protocol(e.to_string())),
+                Err(_) => continue,
+            }
+        }
     }
 
     /// Record an error.
@@ -489,6 +544,15 @@ impl Protocol for Iec104Channel {
     async fn diagnostics(&self) -> Result<Diagnostics> {
         let state = self.get_state();
         let diag = self.diagnostics.read().await;
+        let mut extra = serde_json::json!({
+            "address": self.config.address,
+            "common_address": self.config.common_address,
+            "points": self.config.points.len(),
+            "last_interrogation": diag.last_interrogation.map(|t| t.elapsed().as_secs()),
+        });
+        if diag.deadband_suppressed > 0 {
+            extra["deadband_suppressed"] = diag.deadband_suppressed.into();
+        }
 
         Ok(Diagnostics {
             protocol: self.name().to_string(),
@@ -497,12 +561,8 @@ impl Protocol for Iec104Channel {
             write_count: diag.send_count,
             error_count: diag.error_count,
             last_error: diag.last_error.clone(),
-            extra: serde_json::json!({
-                "address": self.config.address,
-                "common_address": self.config.common_address,
-                "points": self.config.points.len(),
-                "last_interrogation": diag.last_interrogation.map(|t| t.elapsed().as_secs()),
-            }),
+            extra,
+            ..Default::default()
         })
     }
 }
@@ -514,12 +574,13 @@ impl ProtocolClient for Iec104Channel {
         match self.client.connect().await {
             Ok(()) => {
                 self.set_state(ConnectionState::Connected);
+                self.reset_deadband();
                 Ok(())
             }
             Err(e) => {
                 self.set_state(ConnectionState::Error);
                 self.record_error(&e.to_string()).await;
-                Err(GatewayError::Connection(e.to_string()))
+                Err(GatewayError::connection_with(e.to_string(), e))
             }
         }
     }
@@ -539,7 +600,7 @@ impl ProtocolClient for Iec104Channel {
                 self.set_state(ConnectionState::Disconnected);
                 Ok(())
             }
-            Err(e) => Err(GatewayError::Connection(e.to_string())),
+            Err(e) => Err(GatewayError::connection_with(e.to_string(), e)),
         }
     }
 
@@ -594,15 +655,42 @@ impl ProtocolClient for Iec104Channel {
                 }
             };
 
-            // Send single command
+            let ioa = iec_addr.ioa;
+            let common_address = self.config.common_address;
+
+            // Select-before-operate: select, wait for ACTCON, then execute
+            if cmd.operate_mode == OperateMode::SelectThenExecute {
+                let timeout = cmd
+                    .timeout_ms
+                    .map(|ms| Duration::from_millis(ms as u64))
+                    .unwrap_or(self.config.t1_timeout);
+
+                if let Err(e) = self
+                    .client
+                    .single_command(common_address, ioa, cmd.value, true)
+                    .await
+                {
+                    failures.push((cmd.id, format!("Select failed: {}", e)));
+                    continue;
+                }
+
+                match self.await_select_confirmation(ioa, timeout).await {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        failures.push((cmd.id, "Select rejected by outstation".into()));
+                        continue;
+                    }
+                    Err(e) => {
+                        failures.push((cmd.id, e.to_string()));
+                        continue;
+                    }
+                }
+            }
+
+            // Execute single command (ACTCON and ACTTERM arrive later as DataEvent::CommandUpdate)
             let result = self
                 .client
-                .single_command(
-                    self.config.common_address,
-                    iec_addr.ioa,
-                    cmd.value,
-                    false, // not select
-                )
+                .single_command(common_address, ioa, cmd.value, false)
                 .await;
 
             match result {
@@ -621,6 +709,7 @@ impl ProtocolClient for Iec104Channel {
         Ok(WriteResult {
             success_count,
             failures,
+            stage: CommandStage::Accepted,
         })
     }
 
@@ -683,6 +772,7 @@ impl ProtocolClient for Iec104Channel {
         Ok(WriteResult {
             success_count,
             failures,
+            stage: CommandStage::Accepted,
         })
     }
 }
@@ -691,7 +781,7 @@ impl EventDrivenProtocol for Iec104Channel {
     fn subscribe(&self) -> DataEventReceiver {
         // Broadcast channel supports multiple subscribers
         // Each call to subscribe() returns a new receiver that gets all future events
-        self.event_tx.subscribe()
+        self.event_bus.subscribe()
     }
 
     fn set_event_handler(&mut self, handler: Arc<dyn DataEventHandler>) {
@@ -738,46 +828,6 @@ fn convert_iec104_value(value: &voltage_iec104::DataValue) -> Value {
     }
 }
 
-/// Convert Cp56Time2a to DateTime<Utc>.
-fn cp56time2a_to_datetime(time: &Cp56Time2a) -> Option<DateTime<Utc>> {
-    if time.invalid {
-        return None;
-    }
-
-    // Year is stored as offset from 2000
-    let year = 2000 + time.year as i32;
-    let month = time.month as u32;
-    let day = time.day as u32;
-    let hour = time.hours as u32;
-    let minute = time.minutes as u32;
-    let second = (time.milliseconds / 1000) as u32;
-    let millisecond = (time.milliseconds % 1000) as u32;
-
-    Utc.with_ymd_and_hms(year, month, day, hour, minute, second)
-        .single()
-        .map(|dt| dt + chrono::Duration::milliseconds(millisecond as i64))
-}
-
-/// Create Cp56Time2a from current time.
-fn cp56time2a_now() -> Cp56Time2a {
-    use chrono::Datelike;
-    use chrono::Timelike;
-
-    let now = Utc::now();
-
-    Cp56Time2a {
-        milliseconds: now.second() as u16 * 1000 + now.timestamp_subsec_millis() as u16,
-        minutes: now.minute() as u8,
-        hours: now.hour() as u8,
-        day: now.day() as u8,
-        day_of_week: now.weekday().num_days_from_monday() as u8 + 1, // 1=Monday
-        month: now.month() as u8,
-        year: ((now.year() as u16).saturating_sub(2000) & 0x7F) as u8,
-        invalid: false,
-        summer_time: false,
-    }
-}
-
 /// Convert IEC 104 Quality to igw Quality.
 fn convert_iec104_quality(quality: &voltage_iec104::Quality) -> Quality {
     if quality.is_good() {
@@ -857,6 +907,24 @@ impl HasMetadata for Iec104Channel {
     }
 }
 
+/// IOA of the process command an ACTTERM (cause of transmission 10)
+/// reports as completed, or `None` for any other ASDU. Interrogations and
+/// other system commands (type 100 and up) are not point commands.
+fn terminated_command(asdu: &voltage_iec104::Asdu) -> Option<u32> {
+    let header = &asdu.header;
+    if header.cot != voltage_iec104::Cot::ActivationTermination
+        || header.negative
+        || !header.type_id.is_control()
+        || header.type_id.as_u8() >= 100
+    {
+        return None;
+    }
+    match asdu.raw_data.get(..3)? {
+        [lo, mid, hi] => Some(u32::from_le_bytes([*lo, *mid, *hi, 0])),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -883,6 +951,64 @@ mod tests {
             .contains(&CommunicationMode::EventDriven));
     }
 
+    fn asdu(type_id: voltage_iec104::TypeId, cot: voltage_iec104::Cot) -> voltage_iec104::Asdu {
+        let mut asdu =
+            voltage_iec104::Asdu::new(voltage_iec104::AsduHeader::new(type_id, 1, cot, 1));
+        // IOA 0x012345, then the command qualifier
+        asdu.raw_data = vec![0x45, 0x23, 0x01, 0x01].into();
+        asdu
+    }
+
+    #[test]
+    fn test_terminated_command() {
+        use voltage_iec104::{Cot, TypeId};
+
+        let term = asdu(TypeId::SingleCommand, Cot::ActivationTermination);
+        assert_eq!(terminated_command(&term), Some(0x012345));
+
+        let mut negative = term.clone();
+        negative.header.negative = true;
+        assert_eq!(terminated_command(&negative), None);
+        assert_eq!(
+            terminated_command(&asdu(TypeId::SingleCommand, Cot::ActivationConfirm)),
+            None
+        );
+        assert_eq!(
+            terminated_command(&asdu(
+                TypeId::CounterInterrogation,
+                Cot::ActivationTermination
+            )),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_actterm_publishes_terminated() {
+        use crate::core::point::{Iec104Address, ProtocolAddress};
+        use voltage_iec104::{Cot, TypeId};
+
+        let point = PointConfig::new(
+            7,
+            ProtocolAddress::Iec104(Iec104Address::new(0x012345, 45, 1)),
+        );
+        let channel =
+            Iec104Channel::new(Iec104ChannelConfig::new("127.0.0.1:2404").with_points(vec![point]));
+        let mut rx = channel.subscribe();
+
+        channel
+            .handle_iec104_event(Iec104Event::AsduReceived(asdu(
+                TypeId::SingleCommand,
+                Cot::ActivationTermination,
+            )))
+            .await;
+        match rx.try_recv() {
+            Some(DataEvent::CommandUpdate(outcome)) => {
+                assert_eq!(outcome, CommandOutcome::new(7, CommandStage::Terminated))
+            }
+            other => panic!("expected CommandUpdate, got {:?}", other),
+        }
+    }
+
     #[test]
     fn test_convert_iec104_value() {
         assert_eq!(
@@ -913,10 +1039,16 @@ mod tests {
             summer_time: false,
         };
 
-        let dt = cp56time2a_to_datetime(&time).unwrap();
+        let dt = iec_time::Cp56Time2a::decode(&time.to_bytes()).unwrap().time;
         assert_eq!(
-            dt.format("%Y-%m-%d %H:%M:%S").to_string(),
-            "2024-12-25 10:15:30"
+            dt.format("%Y-%m-%d %H:%M:%S%.3f").to_string(),
+            "2024-12-25 10:15:30.500"
         );
+
+        let invalid = Cp56Time2a {
+            invalid: true,
+            ..time
+        };
+        assert!(iec_time::Cp56Time2a::decode(&invalid.to_bytes()).is_err());
     }
 }
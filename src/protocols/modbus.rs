@@ -20,19 +20,93 @@
 //! let batch = channel.poll_once().await?;
 //! store.write_batch(channel_id, &batch).await?;
 //! ```
+//!
+//! # Shared ports
+//!
+//! Channels built [`with_shared_transport()`](ModbusChannel::with_shared_transport)
+//! use one client per port (serial device or TCP address) instead of each
+//! opening their own: the first channel to connect opens the port, the
+//! last to disconnect closes it. Reads take the port for one request at a
+//! time, so the channels' polls interleave request by request; a write
+//! call keeps it for all of its commands.
+//!
+//! # Broadcast writes
+//!
+//! Points addressed to unit `*` (see
+//! [`parse_address()`](crate::gateway::parse_address)) are broadcast: their
+//! writes go to unit 0, which every device on the bus executes, e.g. to set
+//! the frequency reference of several drives at once. Devices do not answer
+//! broadcasts: a broadcast write counts as sent when its response times out
+//! (`io_timeout_ms`), and the port then stays quiet for `broadcast_delay_ms`
+//! (default 100) before its next request, giving the devices time to
+//! process the write. Broadcast points are write-only: they are not polled
+//! and reading them is a configuration error.
+//!
+//! # Bit writes
+//!
+//! A control point with a `bit_position` on a holding register (function
+//! code 6 or 16) writes 0 or 1 to the whole register by default, clearing
+//! its other bits. With `preserve_register_bits` set on the channel, it
+//! sets just that bit instead: the register is read (FC03) and written
+//! back (FC06) with the bit changed, the port held in between so no other
+//! request of the gateway slips in. Broadcast points cannot be written
+//! this way, as a broadcast register cannot be read.
+//!
+//! The read-modify-write is not atomic on the device: if another master,
+//! or the device itself, changes another bit of the register between the
+//! read and the write, that change is overwritten. Mask Write Register
+//! (FC22) would do it in one request, but voltage_modbus cannot send it
+//! (its function codes stop at FC16). Map bits that other masters write to
+//! a coil or a register of their own.
+//!
+//! # Deadbands
+//!
+//! Points with a [`deadband`](crate::core::point::TransformConfig::deadband)
+//! are left out of a poll result while their scaled value stays within it
+//! of the value last returned (see [`DeadbandFilter`]). The first read
+//! after a connect, a failed read or a [`refresh()`](ModbusChannel::refresh),
+//! and any quality change, is always returned. Suppressed reads are counted in `Diagnostics::extra`
+//! (`deadband_suppressed`).
+//!
+//! # Diagnostics
+//!
+//! Besides the channel totals, `Diagnostics::extra` breaks the requests
+//! down by slave and function code (`slaves`), to tell a failing device
+//! on a multi-drop line from a failing request type:
+//!
+//! ```text
+//! "slaves": {
+//!   "3": {
+//!     "last_error": "FC03: Timeout after 3000ms: read",
+//!     "function_codes": {
+//!       "3": { "requests": 120, "errors": 4, "timeouts": 3, "crc_errors": 1,
+//!              "exceptions": {} },
+//!       "6": { "requests": 2, "errors": 1, "timeouts": 0, "crc_errors": 0,
+//!              "exceptions": { "illegal_data_value": 1 } }
+//!     }
+//!   }
+//! }
+//! ```
+//!
+//! `errors` counts every failed request; timeouts include slaves reported
+//! as not responding. [`ModbusChannel::reset_diagnostics()`] sets all
+//! counters back to zero.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 use std::time::Duration;
 
+use once_cell::sync::Lazy;
 use tokio::sync::{Mutex, RwLock};
-use tracing::debug;
+use tracing::{debug, warn};
 use voltage_modbus::{ModbusClient, ModbusTcpClient};
 
 #[cfg(feature = "modbus")]
 use voltage_modbus::ModbusRtuClient;
 
 use crate::core::data::{DataBatch, DataPoint, Value};
+use crate::core::deadband::DeadbandFilter;
+use crate::core::diagnostics::DiagnosticsRecorder;
 use crate::core::error::{GatewayError, Result};
 use crate::core::logging::{
     ChannelLogConfig, ChannelLogHandler, ErrorContext, LogContext, LoggableProtocol,
@@ -41,15 +115,28 @@ use crate::core::metadata::{DriverMetadata, HasMetadata, ParameterMetadata, Para
 use serde::Deserialize;
 
 use crate::core::point::{ByteOrder, DataFormat, ModbusAddress, PointConfig, ProtocolAddress};
+use crate::core::quality::Quality;
 use crate::core::traits::{
-    AdjustmentCommand, CommunicationMode, ConnectionState, ControlCommand, Diagnostics,
-    PointFailure, PollResult, Protocol, ProtocolCapabilities, ProtocolClient, WriteResult,
+    AdjustmentCommand, CommandStage, CommunicationMode, ConnectionState, ControlCommand,
+    Diagnostics, PointFailure, PollResult, Protocol, ProtocolCapabilities, ProtocolClient,
+    WriteResult,
 };
+use crate::core::transport::{SharedTransport, TransportGuard, TransportRegistry};
 use crate::protocols::command_batcher::{BatchCommand, CommandBatcher};
 
+/// Function codes the channel reads (01-04) and writes (05, 06, 0F, 10).
+pub const SUPPORTED_FUNCTION_CODES: &[u8] = &[1, 2, 3, 4, 5, 6, 15, 16];
+
 // Type alias for grouped points: (slave_id, function_code) -> Vec<PointConfig>
 type GroupedPoints = HashMap<(u8, u8), Vec<PointConfig>>;
 
+/// Port of a Modbus channel, possibly shared with other channels.
+type ModbusTransport = SharedTransport<ModbusClientWrapper>;
+
+/// Ports of the channels built with `with_shared_transport()`.
+static SHARED_TRANSPORTS: Lazy<TransportRegistry<ModbusClientWrapper>> =
+    Lazy::new(TransportRegistry::new);
+
 // ============================================================================
 // Strongly-typed mapping configs for JSON deserialization
 // ============================================================================
@@ -66,6 +153,7 @@ type GroupedPoints = HashMap<(u8, u8), Vec<PointConfig>>;
 /// - `data_type`: Data format (default: uint16)
 /// - `byte_order`: Byte order for multi-byte values (default: ABCD)
 /// - `bit_position`: Bit position for boolean extraction from register (0-15)
+/// - `count`: Read this many consecutive values as one array point
 ///
 /// # Example JSON
 /// ```json
@@ -102,8 +190,15 @@ pub struct ModbusMappingConfig {
     pub byte_order: ByteOrder,
 
     /// Bit position for boolean values (0-15).
+    ///
+    /// Control writes set the whole register to 0 or 1, unless the channel
+    /// has `preserve_register_bits` (see "Bit writes" in the module docs).
     #[serde(default)]
     pub bit_position: Option<u8>,
+
+    /// Number of consecutive values read as one array.
+    #[serde(default)]
+    pub count: Option<u16>,
 }
 
 fn default_slave_id() -> u8 {
@@ -124,105 +219,15 @@ impl ModbusMappingConfig {
             format: self.data_type,
             byte_order: self.byte_order,
             bit_position: self.bit_position,
+            count: self.count,
+            broadcast: false,
         }
     }
 }
 
-/// Modbus channel parameters configuration (deserialized from parameters_json).
-///
-/// # TCP Mode
-/// ```json
-/// {
-///     "host": "192.168.1.100",
-///     "port": 502
-/// }
-/// ```
-///
-/// # RTU Mode
-/// ```json
-/// {
-///     "device": "/dev/ttyUSB0",
-///     "baud_rate": 9600
-/// }
-/// ```
-#[derive(Debug, Clone, Deserialize)]
-pub struct ModbusChannelParamsConfig {
-    // TCP mode fields
-    /// Target host (TCP mode).
-    #[serde(default)]
-    pub host: Option<String>,
-
-    /// Target port (TCP mode, default: 502).
-    #[serde(default = "default_modbus_port")]
-    pub port: u16,
-
-    // RTU mode fields
-    /// Serial device path (RTU mode).
-    #[serde(default)]
-    pub device: Option<String>,
-
-    /// Baud rate (RTU mode, default: 9600).
-    #[serde(default = "default_baud_rate")]
-    pub baud_rate: u32,
-
-    // Common fields
-    /// Connect timeout in milliseconds (default: 5000).
-    #[serde(default = "default_connect_timeout_ms")]
-    pub connect_timeout_ms: u64,
-
-    /// I/O timeout in milliseconds (default: 3000).
-    #[serde(default = "default_io_timeout_ms")]
-    pub io_timeout_ms: u64,
-
-    /// Maximum registers per batch read (default: 125).
-    #[serde(default = "default_max_batch_size_config")]
-    pub max_batch_size: u16,
-
-    /// Maximum gap between registers to allow merging (default: 10).
-    #[serde(default = "default_max_gap_config")]
-    pub max_gap: u16,
-}
-
-fn default_modbus_port() -> u16 {
-    502
-}
-
-fn default_baud_rate() -> u32 {
-    9600
-}
-
-fn default_connect_timeout_ms() -> u64 {
-    5000
-}
-
-fn default_io_timeout_ms() -> u64 {
-    3000
-}
-
-fn default_max_batch_size_config() -> u16 {
-    125
-}
-
-fn default_max_gap_config() -> u16 {
-    10
-}
+pub use crate::gateway::params::ModbusChannelParamsConfig;
 
 impl ModbusChannelParamsConfig {
-    /// Check if this is a TCP configuration.
-    pub fn is_tcp(&self) -> bool {
-        self.host.is_some()
-    }
-
-    /// Check if this is an RTU configuration.
-    pub fn is_rtu(&self) -> bool {
-        self.device.is_some()
-    }
-
-    /// Get TCP address string (host:port).
-    pub fn tcp_address(&self) -> Option<String> {
-        self.host.as_ref().map(|h| format!("{}:{}", h, self.port))
-    }
-
     /// Convert to ModbusChannelConfig.
     ///
     /// Note: Points must be set separately via `with_points()`.
@@ -233,11 +238,15 @@ impl ModbusChannelParamsConfig {
                 .with_io_timeout(std::time::Duration::from_millis(self.io_timeout_ms))
                 .with_max_batch_size(self.max_batch_size)
                 .with_max_gap(self.max_gap)
+                .with_broadcast_delay(Duration::from_millis(self.broadcast_delay_ms))
+                .with_preserve_register_bits(self.preserve_register_bits)
         } else if let Some(device) = &self.device {
             ModbusChannelConfig::rtu(device, self.baud_rate)
                 .with_io_timeout(std::time::Duration::from_millis(self.io_timeout_ms))
                 .with_max_batch_size(self.max_batch_size)
                 .with_max_gap(self.max_gap)
+                .with_broadcast_delay(Duration::from_millis(self.broadcast_delay_ms))
+                .with_preserve_register_bits(self.preserve_register_bits)
         } else {
             // Default to TCP with empty address (will fail on connect)
             ModbusChannelConfig::tcp("")
@@ -251,6 +260,9 @@ const DEFAULT_MAX_BATCH_SIZE: u16 = 125;
 /// Default maximum gap between registers to allow merging
 const DEFAULT_MAX_GAP: u16 = 10;
 
+/// Default quiet time after a broadcast write in milliseconds
+const DEFAULT_BROADCAST_DELAY_MS: u64 = 100;
+
 /// Default reconnect cooldown in milliseconds (60 seconds)
 const DEFAULT_RECONNECT_COOLDOWN_MS: u64 = 60_000;
 
@@ -484,6 +496,12 @@ pub struct ModbusChannelConfig {
     /// Maximum gap between registers to allow merging (default: 10)
     pub max_gap: u16,
 
+    /// Quiet time after a broadcast write (default: 100 ms)
+    pub broadcast_delay: Duration,
+
+    /// Write bit points by read-modify-write (default: false)
+    pub preserve_register_bits: bool,
+
     /// Reconnect configuration
     pub reconnect: ReconnectConfig,
 }
@@ -503,6 +521,8 @@ impl ModbusChannelConfig {
             points: Vec::new(),
             max_batch_size: DEFAULT_MAX_BATCH_SIZE,
             max_gap: DEFAULT_MAX_GAP,
+            broadcast_delay: Duration::from_millis(DEFAULT_BROADCAST_DELAY_MS),
+            preserve_register_bits: false,
             reconnect: ReconnectConfig::default(),
         }
     }
@@ -531,6 +551,8 @@ impl ModbusChannelConfig {
             points: Vec::new(),
             max_batch_size: DEFAULT_MAX_BATCH_SIZE,
             max_gap: DEFAULT_MAX_GAP,
+            broadcast_delay: Duration::from_millis(DEFAULT_BROADCAST_DELAY_MS),
+            preserve_register_bits: false,
             reconnect: ReconnectConfig::default(),
         }
     }
@@ -565,11 +587,34 @@ impl ModbusChannelConfig {
         self
     }
 
+    /// Set the quiet time after a broadcast write.
+    pub fn with_broadcast_delay(mut self, delay: Duration) -> Self {
+        self.broadcast_delay = delay;
+        self
+    }
+
+    /// Write bit points by reading their register and writing it back with
+    /// only their bit changed, instead of writing 0 or 1 to the register.
+    pub fn with_preserve_register_bits(mut self, preserve: bool) -> Self {
+        self.preserve_register_bits = preserve;
+        self
+    }
+
     /// Set reconnect configuration.
     pub fn with_reconnect(mut self, config: ReconnectConfig) -> Self {
         self.reconnect = config;
         self
     }
+
+    /// Port the channel talks through: the TCP address or the serial
+    /// device.
+    pub fn port(&self) -> &str {
+        match self.connection_mode {
+            ConnectionMode::Tcp => &self.address,
+            #[cfg(feature = "modbus")]
+            ConnectionMode::Rtu => &self.rtu_device,
+        }
+    }
 }
 
 /// Modbus channel adapter.
@@ -598,17 +643,25 @@ pub struct ModbusChannel {
     config: ModbusChannelConfig,
     /// Channel identifier for logging.
     channel_id: u32,
-    /// Client wrapped in Arc<Mutex> for shared access from polling task.
-    /// Uses ModbusClientWrapper to support both TCP and RTU transports.
-    client: Arc<Mutex<Option<ModbusClientWrapper>>>,
+    /// Port client, taken per request. Uses ModbusClientWrapper to support
+    /// both TCP and RTU transports.
+    transport: Arc<ModbusTransport>,
+    /// Whether the port is shared with other channels.
+    shared: bool,
+    /// Whether this channel holds the port open.
+    attached: bool,
     state: Arc<std::sync::RwLock<ConnectionState>>,
     diagnostics: Arc<RwLock<ChannelDiagnostics>>,
+    /// Request latency and poll timing.
+    timing: Arc<DiagnosticsRecorder>,
 
     // === Polling support ===
     /// Pre-grouped points by (slave_id, function_code)
     grouped_points: Arc<RwLock<GroupedPoints>>,
     /// Polling interval in milliseconds
     polling_interval_ms: u64,
+    /// Drops reads within their point's deadband.
+    deadband: DeadbandFilter,
 
     // === Command batching ===
     /// Command batcher for optimizing write operations
@@ -625,6 +678,235 @@ struct ChannelDiagnostics {
     write_count: u64,
     error_count: u64,
     last_error: Option<String>,
+    deadband_suppressed: u64,
+    /// Request outcomes per slave and function code.
+    requests: RequestCounters,
+}
+
+/// Modbus exception code returned by a slave.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ExceptionCode {
+    /// 01: function code not supported.
+    IllegalFunction,
+    /// 02: address out of the slave's range.
+    IllegalDataAddress,
+    /// 03: value not accepted.
+    IllegalDataValue,
+    /// 04: unrecoverable error in the slave.
+    ServerDeviceFailure,
+    /// 05: request accepted, processing takes long.
+    Acknowledge,
+    /// 06: slave busy with a long-running command.
+    ServerDeviceBusy,
+    /// 08: parity error in the slave's extended memory.
+    MemoryParityError,
+    /// 0A: gateway has no path to the target.
+    GatewayPathUnavailable,
+    /// 0B: gateway target did not respond.
+    GatewayTargetFailedToRespond,
+    /// Any other code.
+    Other(u8),
+}
+
+impl ExceptionCode {
+    /// Exception of a raw code.
+    pub fn from_code(code: u8) -> Self {
+        match code {
+            0x01 => Self::IllegalFunction,
+            0x02 => Self::IllegalDataAddress,
+            0x03 => Self::IllegalDataValue,
+            0x04 => Self::ServerDeviceFailure,
+            0x05 => Self::Acknowledge,
+            0x06 => Self::ServerDeviceBusy,
+            0x08 => Self::MemoryParityError,
+            0x0A => Self::GatewayPathUnavailable,
+            0x0B => Self::GatewayTargetFailedToRespond,
+            code => Self::Other(code),
+        }
+    }
+
+    /// Raw code.
+    pub fn code(self) -> u8 {
+        match self {
+            Self::IllegalFunction => 0x01,
+            Self::IllegalDataAddress => 0x02,
+            Self::IllegalDataValue => 0x03,
+            Self::ServerDeviceFailure => 0x04,
+            Self::Acknowledge => 0x05,
+            Self::ServerDeviceBusy => 0x06,
+            Self::MemoryParityError => 0x08,
+            Self::GatewayPathUnavailable => 0x0A,
+            Self::GatewayTargetFailedToRespond => 0x0B,
+            Self::Other(code) => code,
+        }
+    }
+}
+
+/// snake_case name, e.g. `illegal_data_address`; `code_0c` for other codes.
+impl std::fmt::Display for ExceptionCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::IllegalFunction => "illegal_function",
+            Self::IllegalDataAddress => "illegal_data_address",
+            Self::IllegalDataValue => "illegal_data_value",
+            Self::ServerDeviceFailure => "server_device_failure",
+            Self::Acknowledge => "acknowledge",
+            Self::ServerDeviceBusy => "server_device_busy",
+            Self::MemoryParityError => "memory_parity_error",
+            Self::GatewayPathUnavailable => "gateway_path_unavailable",
+            Self::GatewayTargetFailedToRespond => "gateway_target_failed_to_respond",
+            Self::Other(code) => return write!(f, "code_{:02x}", code),
+        })
+    }
+}
+
+/// Outcomes of the requests of one function code to one slave.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct RequestStats {
+    requests: u64,
+    /// Failed requests, whatever the cause.
+    errors: u64,
+    exceptions: BTreeMap<ExceptionCode, u64>,
+    timeouts: u64,
+    crc_errors: u64,
+}
+
+impl RequestStats {
+    fn merge(&mut self, other: &RequestStats) {
+        self.requests += other.requests;
+        self.errors += other.errors;
+        for (code, count) in &other.exceptions {
+            *self.exceptions.entry(*code).or_default() += count;
+        }
+        self.timeouts += other.timeouts;
+        self.crc_errors += other.crc_errors;
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let exceptions: serde_json::Map<_, _> = self
+            .exceptions
+            .iter()
+            .map(|(code, count)| (code.to_string(), (*count).into()))
+            .collect();
+        serde_json::json!({
+            "requests": self.requests,
+            "errors": self.errors,
+            "exceptions": exceptions,
+            "timeouts": self.timeouts,
+            "crc_errors": self.crc_errors,
+        })
+    }
+}
+
+/// Request counters of one slave.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct SlaveStats {
+    function_codes: BTreeMap<u8, RequestStats>,
+    last_error: Option<String>,
+}
+
+/// Request outcomes per slave and function code, shown in
+/// `Diagnostics::extra` (`slaves`).
+///
+/// Requests are counted into a local instance while the port is held and
+/// merged into the channel's diagnostics afterwards.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct RequestCounters {
+    slaves: BTreeMap<u8, SlaveStats>,
+}
+
+impl RequestCounters {
+    /// Count a request of `function_code` to `slave_id` that ended with
+    /// `outcome`.
+    fn record<T>(
+        &mut self,
+        slave_id: u8,
+        function_code: u8,
+        outcome: &voltage_modbus::ModbusResult<T>,
+    ) {
+        use voltage_modbus::ModbusError;
+
+        let slave = self.slaves.entry(slave_id).or_default();
+        let stats = slave.function_codes.entry(function_code).or_default();
+        stats.requests += 1;
+        let Err(error) = outcome else {
+            return;
+        };
+        stats.errors += 1;
+        // voltage_modbus reports exception responses as protocol errors and
+        // RTU checksum failures as frame errors, keeping the details in the
+        // message only
+        match error {
+            ModbusError::Exception { code, .. } => {
+                *stats
+                    .exceptions
+                    .entry(ExceptionCode::from_code(*code))
+                    .or_default() += 1;
+            }
+            ModbusError::Protocol { message } => {
+                let code = message
+                    .strip_prefix("Modbus exception: Modbus Exception 0x")
+                    .and_then(|rest| u8::from_str_radix(rest.get(..2)?, 16).ok());
+                if let Some(code) = code {
+                    *stats
+                        .exceptions
+                        .entry(ExceptionCode::from_code(code))
+                        .or_default() += 1;
+                }
+            }
+            ModbusError::Timeout { .. } | ModbusError::DeviceNotResponding { .. } => {
+                stats.timeouts += 1;
+            }
+            ModbusError::CrcMismatch { .. } => stats.crc_errors += 1,
+            ModbusError::Frame { message } if message.starts_with("CRC mismatch") => {
+                stats.crc_errors += 1;
+            }
+            _ => {}
+        }
+        slave.last_error = Some(format!("FC{:02}: {}", function_code, error));
+    }
+
+    fn merge(&mut self, other: RequestCounters) {
+        for (slave_id, other) in other.slaves {
+            let slave = self.slaves.entry(slave_id).or_default();
+            for (function_code, stats) in &other.function_codes {
+                slave
+                    .function_codes
+                    .entry(*function_code)
+                    .or_default()
+                    .merge(stats);
+            }
+            if other.last_error.is_some() {
+                slave.last_error = other.last_error;
+            }
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.slaves.is_empty()
+    }
+
+    /// `{"<slave>": {"last_error", "function_codes": {"<fc>": {...}}}}`.
+    fn to_json(&self) -> serde_json::Value {
+        self.slaves
+            .iter()
+            .map(|(slave_id, slave)| {
+                let function_codes: serde_json::Map<_, _> = slave
+                    .function_codes
+                    .iter()
+                    .map(|(fc, stats)| (fc.to_string(), stats.to_json()))
+                    .collect();
+                (
+                    slave_id.to_string(),
+                    serde_json::json!({
+                        "last_error": slave.last_error,
+                        "function_codes": function_codes,
+                    }),
+                )
+            })
+            .collect::<serde_json::Map<_, _>>()
+            .into()
+    }
 }
 
 /// Default polling interval in milliseconds
@@ -649,14 +931,20 @@ impl ModbusChannel {
     /// let batch = channel.poll_once().await?;
     /// ```
     pub fn new(config: ModbusChannelConfig, channel_id: u32) -> Self {
+        let transport = Arc::new(SharedTransport::new(config.port()));
+        let deadband = DeadbandFilter::new(&config.points);
         Self {
             config,
             channel_id,
-            client: Arc::new(Mutex::new(None)),
+            transport,
+            shared: false,
+            attached: false,
             state: Arc::new(std::sync::RwLock::new(ConnectionState::Disconnected)),
             diagnostics: Arc::new(RwLock::new(ChannelDiagnostics::default())),
+            timing: Arc::new(DiagnosticsRecorder::new()),
             grouped_points: Arc::new(RwLock::new(HashMap::new())),
             polling_interval_ms: DEFAULT_POLLING_INTERVAL_MS,
+            deadband,
             command_batcher: Arc::new(Mutex::new(CommandBatcher::new())),
             log_context: Arc::new(LogContext::new(channel_id)),
         }
@@ -668,6 +956,29 @@ impl ModbusChannel {
         self
     }
 
+    /// Channel configuration.
+    pub fn config(&self) -> &ModbusChannelConfig {
+        &self.config
+    }
+
+    /// Share the port with every other channel of the process using the
+    /// same serial device or TCP address (see [Shared ports](self#shared-ports)).
+    pub fn with_shared_transport(mut self) -> Self {
+        self.transport = SHARED_TRANSPORTS.get(self.config.port());
+        self.shared = true;
+        self
+    }
+
+    /// Stop using the port, closing it unless other channels still do.
+    async fn detach(&mut self) {
+        if !std::mem::take(&mut self.attached) {
+            return;
+        }
+        if let Some(mut client) = self.transport.detach().await {
+            let _ = client.close().await;
+        }
+    }
+
     /// Set connection state (internal helper).
     fn set_state(&self, state: ConnectionState) {
         if let Ok(mut s) = self.state.write() {
@@ -688,16 +999,31 @@ impl ModbusChannel {
         &self.config.points
     }
 
+    /// Replace the point configurations and rebuild the read plan.
+    ///
+    /// Takes effect on the next poll; the connection is kept.
+    pub async fn set_points(&mut self, points: Vec<PointConfig>) {
+        self.deadband.set_points(&points);
+        self.config.points = points;
+        self.group_points_for_polling().await;
+    }
+
     /// Read a single Modbus address and convert to DataPoint.
     #[allow(dead_code)]
     async fn read_modbus_point(&self, point: &PointConfig) -> Result<DataPoint> {
-        let mut client_guard = self.client.lock().await;
-        let client = client_guard.as_mut().ok_or(GatewayError::NotConnected)?;
-
         let modbus_addr = match &point.address {
             ProtocolAddress::Modbus(addr) => addr,
             _ => return Err(GatewayError::Config("Invalid address type".into())),
         };
+        if modbus_addr.broadcast {
+            return Err(GatewayError::Config(format!(
+                "Point {} is a broadcast point and cannot be read",
+                point.id
+            )));
+        }
+
+        let mut guard = self.transport.acquire().await;
+        let client = guard.client().ok_or(GatewayError::NotConnected)?;
 
         // Read registers based on function code
         let value = match modbus_addr.function_code {
@@ -719,31 +1045,21 @@ impl ModbusChannel {
             }
             3 => {
                 // Read holding registers (FC03)
-                let count = modbus_addr.format.register_count();
+                let count = modbus_addr.register_count();
                 let regs = client
                     .read_03(modbus_addr.slave_id, modbus_addr.register, count)
                     .await
                     .map_err(|e| GatewayError::Protocol(e.to_string()))?;
-                decode_registers(
-                    &regs,
-                    modbus_addr.format,
-                    modbus_addr.byte_order,
-                    modbus_addr.bit_position,
-                )?
+                decode_registers(&regs, modbus_addr)?
             }
             4 => {
                 // Read input registers (FC04)
-                let count = modbus_addr.format.register_count();
+                let count = modbus_addr.register_count();
                 let regs = client
                     .read_04(modbus_addr.slave_id, modbus_addr.register, count)
                     .await
                     .map_err(|e| GatewayError::Protocol(e.to_string()))?;
-                decode_registers(
-                    &regs,
-                    modbus_addr.format,
-                    modbus_addr.byte_order,
-                    modbus_addr.bit_position,
-                )?
+                decode_registers(&regs, modbus_addr)?
             }
             _ => {
                 return Err(GatewayError::Unsupported(format!(
@@ -754,9 +1070,9 @@ impl ModbusChannel {
         };
 
         // Apply transform
-        let transformed_value = apply_transform(value, &point.transform);
+        let (transformed_value, quality) = apply_transform(value, &point.transform);
 
-        Ok(DataPoint::new(point.id, transformed_value))
+        Ok(DataPoint::new(point.id, transformed_value).with_quality(quality))
     }
 
     /// Record an error in diagnostics.
@@ -766,16 +1082,34 @@ impl ModbusChannel {
         diag.last_error = Some(error.to_string());
     }
 
+    /// Reset the read, write and error counters, the last error and the
+    /// per-slave request counters (see [Diagnostics](self#diagnostics)).
+    ///
+    /// Latency and poll timing are kept.
+    pub async fn reset_diagnostics(&self) {
+        *self.diagnostics.write().await = ChannelDiagnostics::default();
+    }
+
+    /// Make the next poll return every point read, whether or not it moved
+    /// out of its deadband.
+    pub fn refresh(&mut self) {
+        self.deadband.reset();
+    }
+
     /// Pre-group points by (slave_id, function_code) for polling optimization.
     ///
-    /// All configured points are included. The application layer determines
-    /// which points should be polled based on their SCADA type.
+    /// All configured points except broadcast points are included. The
+    /// application layer determines which points should be polled based on
+    /// their SCADA type.
     async fn group_points_for_polling(&self) {
         let mut groups: GroupedPoints = HashMap::new();
 
         for point in &self.config.points {
             // Extract Modbus address
             if let ProtocolAddress::Modbus(addr) = &point.address {
+                if addr.broadcast {
+                    continue;
+                }
                 let key = (addr.slave_id, addr.function_code);
                 groups.entry(key).or_default().push(point.clone());
             }
@@ -795,12 +1129,17 @@ impl ModbusChannel {
     /// Read a group of points with the same slave_id and function_code.
     ///
     /// Uses batch reading optimization: consecutive registers are read in single requests.
-    /// Returns a list of (point_id, DataPoint) tuples for successfully read points.
+    /// Returns a list of (point_id, DataPoint) tuples for successfully read points;
+    /// every point that could not be read or decoded is pushed to `failures`.
+    /// Every request sent is counted in `requests`.
     async fn read_point_group(
-        client: &mut ModbusClientWrapper,
+        transport: &ModbusTransport,
         points: &[PointConfig],
         max_batch_size: u16,
         max_gap: u16,
+        timing: &DiagnosticsRecorder,
+        failures: &mut Vec<PointFailure>,
+        requests: &mut RequestCounters,
     ) -> Vec<(u32, DataPoint)> {
         if points.is_empty() {
             return Vec::new();
@@ -814,27 +1153,42 @@ impl ModbusChannel {
 
         // For coils/discrete inputs (FC01/FC02), read individually (simpler logic)
         if function_code == 1 || function_code == 2 {
-            return Self::read_coils_individually(client, points, slave_id, function_code).await;
+            return Self::read_coils_individually(
+                transport,
+                points,
+                slave_id,
+                function_code,
+                timing,
+                failures,
+                requests,
+            )
+            .await;
         }
 
         // For registers (FC03/FC04), use batch optimization
         Self::read_registers_batched(
-            client,
+            transport,
             points,
             slave_id,
             function_code,
             max_batch_size,
             max_gap,
+            timing,
+            failures,
+            requests,
         )
         .await
     }
 
     /// Read coils or discrete inputs individually (FC01/FC02).
     async fn read_coils_individually(
-        client: &mut ModbusClientWrapper,
+        transport: &ModbusTransport,
         points: &[PointConfig],
         slave_id: u8,
         function_code: u8,
+        timing: &DiagnosticsRecorder,
+        failures: &mut Vec<PointFailure>,
+        requests: &mut RequestCounters,
     ) -> Vec<(u32, DataPoint)> {
         let mut results = Vec::with_capacity(points.len());
 
@@ -844,6 +1198,12 @@ impl ModbusChannel {
                 _ => continue,
             };
 
+            let mut guard = transport.acquire().await;
+            let Some(client) = guard.client() else {
+                failures.push(PointFailure::new(point.id, "Not connected"));
+                continue;
+            };
+            let request_start = std::time::Instant::now();
             let value_result = match function_code {
                 1 => client
                     .read_01(slave_id, modbus_addr.register, 1)
@@ -855,10 +1215,35 @@ impl ModbusChannel {
                     .map(|inputs| Value::Bool(inputs.first().copied().unwrap_or(false))),
                 _ => continue,
             };
-
-            if let Ok(value) = value_result {
-                let transformed = apply_transform(value, &point.transform);
-                results.push((point.id, DataPoint::new(point.id, transformed)));
+            let latency = request_start.elapsed();
+            drop(guard);
+            timing.record_latency(latency);
+            requests.record(slave_id, function_code, &value_result);
+            debug!(
+                slave_id,
+                function_code,
+                start = modbus_addr.register,
+                point_id = point.id,
+                ok = value_result.is_ok(),
+                latency_us = latency.as_micros() as u64,
+                "Coil read"
+            );
+
+            match value_result {
+                Ok(value) => {
+                    let (transformed, quality) = apply_transform(value, &point.transform);
+                    results.push((
+                        point.id,
+                        DataPoint::new(point.id, transformed).with_quality(quality),
+                    ));
+                }
+                Err(e) => failures.push(PointFailure::new(
+                    point.id,
+                    format!(
+                        "FC{:02} read @{} failed: {}",
+                        function_code, modbus_addr.register, e
+                    ),
+                )),
             }
         }
 
@@ -868,20 +1253,25 @@ impl ModbusChannel {
     /// Read registers in batches (FC03/FC04).
     ///
     /// Groups consecutive registers (within max_gap) and reads them in single requests.
+    /// A failed segment read marks every point in that segment as failed.
+    #[allow(clippy::too_many_arguments)]
     async fn read_registers_batched(
-        client: &mut ModbusClientWrapper,
+        transport: &ModbusTransport,
         points: &[PointConfig],
         slave_id: u8,
         function_code: u8,
         max_batch_size: u16,
         max_gap: u16,
+        timing: &DiagnosticsRecorder,
+        failures: &mut Vec<PointFailure>,
+        requests: &mut RequestCounters,
     ) -> Vec<(u32, DataPoint)> {
         // Sort points by register address
         let mut sorted_points: Vec<_> = points
             .iter()
             .filter_map(|p| {
                 if let ProtocolAddress::Modbus(addr) = &p.address {
-                    Some((addr.register, addr.format.register_count(), p))
+                    Some((addr.register, addr.register_count(), p))
                 } else {
                     None
                 }
@@ -900,8 +1290,31 @@ impl ModbusChannel {
         let mut results = Vec::with_capacity(points.len());
 
         for segment in segments {
+            let mut guard = transport.acquire().await;
+            let Some(client) = guard.client() else {
+                for &(_, _, point) in &segment.points {
+                    failures.push(PointFailure::new(point.id, "Not connected"));
+                }
+                continue;
+            };
+            let request_start = std::time::Instant::now();
             let batch_result =
-                Self::read_register_segment(client, slave_id, function_code, &segment).await;
+                Self::read_register_segment(client, slave_id, function_code, &segment, failures)
+                    .await;
+            let latency = request_start.elapsed();
+            drop(guard);
+            timing.record_latency(latency);
+            requests.record(slave_id, function_code, &batch_result);
+            debug!(
+                slave_id,
+                function_code,
+                start = segment.start_address,
+                registers = segment.end_address - segment.start_address,
+                points = segment.points.len(),
+                ok = batch_result.is_ok(),
+                latency_us = latency.as_micros() as u64,
+                "Register read"
+            );
 
             match batch_result {
                 Ok(batch_results) => results.extend(batch_results),
@@ -910,6 +1323,13 @@ impl ModbusChannel {
                         "Batch read failed for segment @{}: {}",
                         segment.start_address, e
                     );
+                    let error = format!(
+                        "FC{:02} read @{}..{} failed: {}",
+                        function_code, segment.start_address, segment.end_address, e
+                    );
+                    for &(_, _, point) in &segment.points {
+                        failures.push(PointFailure::new(point.id, error.clone()));
+                    }
                 }
             }
         }
@@ -966,12 +1386,16 @@ impl ModbusChannel {
     }
 
     /// Read a segment of consecutive registers and decode individual points.
+    ///
+    /// Points whose registers are missing from the response or fail to decode
+    /// are pushed to `failures`; the segment itself still succeeds.
     #[allow(clippy::needless_lifetimes)]
     async fn read_register_segment<'a>(
         client: &mut ModbusClientWrapper,
         slave_id: u8,
         function_code: u8,
         segment: &RegisterSegment<'a>,
+        failures: &mut Vec<PointFailure>,
     ) -> std::result::Result<Vec<(u32, DataPoint)>, voltage_modbus::ModbusError> {
         let total_registers = segment.end_address - segment.start_address;
 
@@ -997,18 +1421,64 @@ impl ModbusChannel {
             let offset = (addr - segment.start_address) as usize;
             let end = offset + count as usize;
 
-            if end <= registers.len() {
-                let point_regs = &registers[offset..end];
-
-                if let ProtocolAddress::Modbus(modbus_addr) = &point.address {
-                    if let Ok(value) = decode_registers(
-                        point_regs,
-                        modbus_addr.format,
-                        modbus_addr.byte_order,
-                        modbus_addr.bit_position,
-                    ) {
-                        let transformed = apply_transform(value, &point.transform);
-                        results.push((point.id, DataPoint::new(point.id, transformed)));
+            if end > registers.len() {
+                warn!(
+                    point_id = point.id,
+                    point = point.name.as_deref().unwrap_or_default(),
+                    expected = total_registers,
+                    received = registers.len(),
+                    "Short register response"
+                );
+                failures.push(PointFailure::new(
+                    point.id,
+                    format!(
+                        "Short response: expected {} registers, got {}",
+                        total_registers,
+                        registers.len()
+                    ),
+                ));
+                continue;
+            }
+
+            let point_regs = &registers[offset..end];
+
+            if let ProtocolAddress::Modbus(modbus_addr) = &point.address {
+                match decode_registers(point_regs, modbus_addr) {
+                    Ok(value) => {
+                        let (transformed, quality) = apply_transform(value, &point.transform);
+                        results.push((
+                            point.id,
+                            DataPoint::new(point.id, transformed).with_quality(quality),
+                        ));
+                    }
+                    // The registers were read but hold no valid number (e.g. a
+                    // BCD nibble above 9 or a NaN float16): report the point
+                    // as invalid.
+                    Err(GatewayError::DataConversion(e)) => {
+                        warn!(
+                            point_id = point.id,
+                            point = point.name.as_deref().unwrap_or_default(),
+                            register = addr,
+                            error = %e,
+                            "Decode failed"
+                        );
+                        results.push((
+                            point.id,
+                            DataPoint::new(point.id, Value::Null).with_quality(Quality::Invalid),
+                        ));
+                    }
+                    Err(e) => {
+                        warn!(
+                            point_id = point.id,
+                            point = point.name.as_deref().unwrap_or_default(),
+                            register = addr,
+                            error = %e,
+                            "Decode failed"
+                        );
+                        failures.push(PointFailure::new(
+                            point.id,
+                            format!("Decode @{} failed: {}", addr, e),
+                        ));
                     }
                 }
             }
@@ -1132,6 +1602,19 @@ impl ModbusChannel {
                 ))
             }
         };
+        if modbus_addr.count.is_some() {
+            return Err(GatewayError::Unsupported(format!(
+                "Point {} is an array and cannot be written",
+                adj.id
+            )));
+        }
+        if modbus_addr.broadcast {
+            // Synchronized commands must not wait for a batch window
+            return Err(GatewayError::Unsupported(format!(
+                "Point {} is a broadcast point; write it with write_adjustment()",
+                adj.id
+            )));
+        }
 
         // Apply reverse transform to get raw value
         let raw_value = reverse_transform(adj.value, &point.transform)?;
@@ -1190,15 +1673,17 @@ impl ModbusChannel {
             return Ok(WriteResult {
                 success_count: 0,
                 failures: Vec::new(),
+                stage: CommandStage::Confirmed,
             });
         }
 
         let mut success_count = 0;
         let mut failures = Vec::new();
+        let mut requests = RequestCounters::default();
 
-        // Lock client for execution
-        let mut client_guard = self.client.lock().await;
-        let client = match client_guard.as_mut() {
+        // Keep the port for all commands
+        let mut guard = self.transport.acquire().await;
+        let client = match guard.client() {
             Some(c) => c,
             None => return Err(GatewayError::NotConnected),
         };
@@ -1209,7 +1694,7 @@ impl ModbusChannel {
             {
                 // Merge all commands into single FC16 request
                 match self
-                    .execute_merged_fc16(client, slave_id, &commands, &mut failures)
+                    .execute_merged_fc16(client, slave_id, &commands, &mut failures, &mut requests)
                     .await
                 {
                     Ok(count) => success_count += count,
@@ -1223,32 +1708,40 @@ impl ModbusChannel {
             } else {
                 // Execute commands individually
                 for cmd in commands {
-                    let result = match cmd.data_format {
-                        DataFormat::UInt16 | DataFormat::Int16 | DataFormat::Bool => {
-                            let value = cmd.value.as_f64().unwrap_or(0.0) as u16;
-                            client
-                                .write_06(cmd.slave_id, cmd.register_address, value)
-                                .await
-                        }
-                        DataFormat::UInt32 | DataFormat::Int32 | DataFormat::Float32 => {
-                            let raw_value = cmd.value.as_f64().unwrap_or(0.0);
-                            match encode_value(raw_value, cmd.data_format, cmd.byte_order) {
-                                Ok(regs) => {
-                                    client
-                                        .write_10(cmd.slave_id, cmd.register_address, &regs)
-                                        .await
-                                }
-                                Err(e) => {
-                                    failures.push((cmd.point_id, e.to_string()));
-                                    continue;
-                                }
-                            }
-                        }
+                    let function_code = match cmd.data_format {
+                        DataFormat::UInt16 | DataFormat::Int16 | DataFormat::Bool => 6,
+                        DataFormat::UInt32
+                        | DataFormat::Int32
+                        | DataFormat::Float32
+                        | DataFormat::Float16
+                        | DataFormat::Bcd16
+                        | DataFormat::Bcd32 => 16,
                         _ => {
                             failures.push((cmd.point_id, "Unsupported format".into()));
                             continue;
                         }
                     };
+                    let regs = match cmd
+                        .value
+                        .try_as_f64_exact()
+                        .and_then(|raw| encode_value(raw, cmd.data_format, cmd.byte_order))
+                    {
+                        Ok(regs) => regs,
+                        Err(e) => {
+                            failures.push((cmd.point_id, e.to_string()));
+                            continue;
+                        }
+                    };
+                    let result = if function_code == 6 {
+                        client
+                            .write_06(cmd.slave_id, cmd.register_address, regs[0])
+                            .await
+                    } else {
+                        client
+                            .write_10(cmd.slave_id, cmd.register_address, &regs)
+                            .await
+                    };
+                    requests.record(cmd.slave_id, function_code, &result);
 
                     match result {
                         Ok(_) => success_count += 1,
@@ -1259,10 +1752,11 @@ impl ModbusChannel {
         }
 
         // Update diagnostics
-        drop(client_guard);
+        drop(guard);
         {
             let mut diag = self.diagnostics.write().await;
             diag.write_count += success_count as u64;
+            diag.requests.merge(requests);
             if !failures.is_empty() {
                 diag.error_count += failures.len() as u64;
                 if let Some((_, err)) = failures.last() {
@@ -1274,6 +1768,7 @@ impl ModbusChannel {
         Ok(WriteResult {
             success_count,
             failures,
+            stage: CommandStage::Confirmed,
         })
     }
 
@@ -1287,6 +1782,7 @@ impl ModbusChannel {
         slave_id: u8,
         commands: &[BatchCommand],
         failures: &mut Vec<(u32, String)>,
+        requests: &mut RequestCounters,
     ) -> std::result::Result<usize, voltage_modbus::ModbusError> {
         // Sort commands by register address
         let mut sorted = commands.to_vec();
@@ -1297,8 +1793,11 @@ impl ModbusChannel {
         let mut registers = Vec::new();
 
         for cmd in &sorted {
-            let raw_value = cmd.value.as_f64().unwrap_or(0.0);
-            match encode_value(raw_value, cmd.data_format, cmd.byte_order) {
+            let regs = cmd
+                .value
+                .try_as_f64_exact()
+                .and_then(|raw| encode_value(raw, cmd.data_format, cmd.byte_order));
+            match regs {
                 Ok(regs) => registers.extend(regs),
                 Err(e) => {
                     failures.push((cmd.point_id, e.to_string()));
@@ -1319,7 +1818,9 @@ impl ModbusChannel {
             start_addr
         );
 
-        client.write_10(slave_id, start_addr, &registers).await?;
+        let result = client.write_10(slave_id, start_addr, &registers).await;
+        requests.record(slave_id, 16, &result);
+        result?;
 
         // Count successful writes (excluding any that failed encoding)
         Ok(sorted.len() - failures.len())
@@ -1379,6 +1880,22 @@ impl Protocol for ModbusChannel {
     async fn diagnostics(&self) -> Result<Diagnostics> {
         let state = self.get_state();
         let diag = self.diagnostics.read().await;
+        let mut extra = serde_json::json!({
+            "address": self.config.address,
+            "points": self.config.points.len(),
+        });
+        if self.shared {
+            extra["transport"] = serde_json::json!({
+                "port": self.transport.key(),
+                "stats": self.transport.stats(),
+            });
+        }
+        if diag.deadband_suppressed > 0 {
+            extra["deadband_suppressed"] = diag.deadband_suppressed.into();
+        }
+        if !diag.requests.is_empty() {
+            extra["slaves"] = diag.requests.to_json();
+        }
 
         Ok(Diagnostics {
             protocol: self.name().to_string(),
@@ -1387,10 +1904,8 @@ impl Protocol for ModbusChannel {
             write_count: diag.write_count,
             error_count: diag.error_count,
             last_error: diag.last_error.clone(),
-            extra: serde_json::json!({
-                "address": self.config.address,
-                "points": self.config.points.len(),
-            }),
+            extra,
+            ..self.timing.to_diagnostics(self.name(), state)
         })
     }
 }
@@ -1415,36 +1930,19 @@ impl ProtocolClient for ModbusChannel {
             }
         };
 
-        // Create client based on connection mode
-        let connect_result = match self.config.connection_mode {
-            ConnectionMode::Tcp => {
-                // TCP connection
-                match ModbusTcpClient::from_address(
-                    &self.config.address,
-                    self.config.connect_timeout,
-                )
-                .await
-                {
-                    Ok(client) => Ok(ModbusClientWrapper::Tcp(client)),
-                    Err(e) => Err(GatewayError::Connection(e.to_string())),
-                }
-            }
-            #[cfg(feature = "modbus")]
-            ConnectionMode::Rtu => {
-                // RTU serial connection
-                match ModbusRtuClient::new(&self.config.rtu_device, self.config.baud_rate) {
-                    Ok(client) => Ok(ModbusClientWrapper::Rtu(client)),
-                    Err(e) => Err(GatewayError::Connection(e.to_string())),
-                }
-            }
-        };
+        // Let go of the port first: reopened if no other channel uses it
+        self.detach().await;
+
+        // Open the port unless another channel of a shared port already did
+        let config = &self.config;
+        let connect_result = self.transport.attach(|| open_client(config)).await;
 
         let duration_ms = start_time.elapsed().as_millis() as u64;
 
         match connect_result {
-            Ok(wrapper) => {
-                let mut client_guard = self.client.lock().await;
-                *client_guard = Some(wrapper);
+            Ok(()) => {
+                self.attached = true;
+                self.deadband.reset();
                 self.set_state(ConnectionState::Connected);
 
                 // Log successful connection
@@ -1477,10 +1975,7 @@ impl ProtocolClient for ModbusChannel {
     async fn disconnect(&mut self) -> Result<()> {
         let old_state = self.get_state();
 
-        let mut client_guard = self.client.lock().await;
-        if let Some(mut client) = client_guard.take() {
-            let _ = client.close().await;
-        }
+        self.detach().await;
         self.set_state(ConnectionState::Disconnected);
 
         // Log disconnection
@@ -1500,24 +1995,20 @@ impl ProtocolClient for ModbusChannel {
             self.group_points_for_polling().await;
         }
 
-        // Acquire client lock
-        let mut client_guard = self.client.lock().await;
-        let client = match client_guard.as_mut() {
-            Some(c) => c,
-            None => {
-                self.log_context
-                    .log_error("Not connected", ErrorContext::Polling)
-                    .await;
-                // Return failed result for all configured points
-                let failures: Vec<_> = self
-                    .config
-                    .points
-                    .iter()
-                    .map(|p| PointFailure::new(p.id, "Not connected"))
-                    .collect();
-                return PollResult::failed(failures);
-            }
-        };
+        // Each request below takes the port on its own
+        if !self.attached {
+            self.log_context
+                .log_error("Not connected", ErrorContext::Polling)
+                .await;
+            // Return failed result for all configured points
+            let failures: Vec<_> = self
+                .config
+                .points
+                .iter()
+                .map(|p| PointFailure::new(p.id, "Not connected"))
+                .collect();
+            return PollResult::failed(failures);
+        }
 
         // Read all point groups - clone to release lock before async I/O
         let groups: Vec<_> = {
@@ -1527,39 +2018,48 @@ impl ProtocolClient for ModbusChannel {
 
         let mut batch = DataBatch::default();
         let mut failures = Vec::new();
+        let mut requests = RequestCounters::default();
         let mut read_count = 0u64;
-        let mut error_count = 0u64;
 
         for ((_slave_id, _fc), points) in groups.iter() {
             let results = Self::read_point_group(
-                client,
+                &self.transport,
                 points,
                 self.config.max_batch_size,
                 self.config.max_gap,
+                &self.timing,
+                &mut failures,
+                &mut requests,
             )
             .await;
 
-            if results.is_empty() && !points.is_empty() {
-                // Read returned no results - record failures for all points in group
-                error_count += 1;
-                for point in points {
-                    failures.push(PointFailure::new(point.id, "Read failed - no response"));
-                }
-            }
-
             for (_point_id, data_point) in results {
                 batch.add(data_point);
                 read_count += 1;
             }
         }
+        let error_count = failures.len() as u64;
+
+        // Points that failed are returned again on their next good read
+        for failure in &failures {
+            self.deadband.forget(failure.point_id);
+        }
+        let suppressed = self.deadband.retain(&mut batch) as u64;
 
         // Update diagnostics
         {
             let mut diag = self.diagnostics.write().await;
             diag.read_count += read_count;
             diag.error_count += error_count;
+            diag.deadband_suppressed += suppressed;
+            diag.requests.merge(requests);
+            if let Some(first) = failures.first() {
+                diag.last_error = Some(format!("point {}: {}", first.point_id, first.error));
+            }
         }
 
+        self.timing.record_read(read_count);
+        self.timing.record_poll(start_time.elapsed());
         let duration_ms = start_time.elapsed().as_millis() as u64;
 
         debug!(
@@ -1592,10 +2092,12 @@ impl ProtocolClient for ModbusChannel {
         let mut success_count = 0;
         let mut failures = Vec::new();
         let mut errors_to_record = Vec::new();
+        let mut requests = RequestCounters::default();
 
-        // Lock client for the entire operation
-        let mut client_guard = self.client.lock().await;
-        let client = match client_guard.as_mut() {
+        // Keep the port for the entire operation
+        let mut turnaround = Turnaround::new(self.config.broadcast_delay);
+        let mut guard = self.transport.acquire().await;
+        let client = match guard.client() {
             Some(c) => c,
             None => {
                 let err = GatewayError::NotConnected;
@@ -1637,24 +2139,55 @@ impl ProtocolClient for ModbusChannel {
             let result = match modbus_addr.function_code {
                 5 => {
                     // FC05: Write Single Coil (bool)
-                    client
-                        .write_05(modbus_addr.slave_id, modbus_addr.register, value)
-                        .await
+                    let write = client.write_05(modbus_addr.slave_id, modbus_addr.register, value);
+                    turnaround.send(&modbus_addr, write).await
+                }
+                6 | 16
+                    if modbus_addr.bit_position.is_some() && self.config.preserve_register_bits =>
+                {
+                    // Bit of a holding register (see "Bit writes" in the module docs)
+                    let bit = modbus_addr.bit_position.unwrap_or_default();
+                    if modbus_addr.broadcast {
+                        failures.push((cmd.id, "Cannot write a bit of a broadcast point".into()));
+                        continue;
+                    }
+                    let (slave_id, register) = (modbus_addr.slave_id, modbus_addr.register);
+                    turnaround.wait().await;
+                    let read = client.read_03(slave_id, register, 1).await;
+                    requests.record(slave_id, 3, &read);
+                    let current = match read {
+                        Ok(regs) => regs.first().copied(),
+                        Err(e) => {
+                            let err_msg = e.to_string();
+                            failures.push((cmd.id, err_msg.clone()));
+                            errors_to_record.push(err_msg);
+                            continue;
+                        }
+                    };
+                    let Some(reg_value) = current.and_then(|c| set_bit(c, bit, value)) else {
+                        failures.push((
+                            cmd.id,
+                            format!("Cannot set bit {} of register {}", bit, register),
+                        ));
+                        continue;
+                    };
+                    let write = client.write_06(slave_id, register, reg_value);
+                    turnaround.send(&modbus_addr, write).await
                 }
                 6 => {
                     // FC06: Write Single Register (u16)
                     // Convert bool to 0/1
                     let reg_value = if value { 1u16 } else { 0u16 };
-                    client
-                        .write_06(modbus_addr.slave_id, modbus_addr.register, reg_value)
-                        .await
+                    let write =
+                        client.write_06(modbus_addr.slave_id, modbus_addr.register, reg_value);
+                    turnaround.send(&modbus_addr, write).await
                 }
                 16 => {
                     // FC16: Write Multiple Registers
                     let reg_value = if value { 1u16 } else { 0u16 };
-                    client
-                        .write_10(modbus_addr.slave_id, modbus_addr.register, &[reg_value])
-                        .await
+                    let regs = [reg_value];
+                    let write = client.write_10(modbus_addr.slave_id, modbus_addr.register, &regs);
+                    turnaround.send(&modbus_addr, write).await
                 }
                 fc => {
                     failures.push((
@@ -1664,6 +2197,12 @@ impl ProtocolClient for ModbusChannel {
                     continue;
                 }
             };
+            // Bits are written with FC06
+            let function_code = match modbus_addr.function_code {
+                16 if modbus_addr.bit_position.is_some() => 6,
+                fc => fc,
+            };
+            requests.record(modbus_addr.slave_id, function_code, &result);
 
             match result {
                 Ok(_) => {
@@ -1677,13 +2216,15 @@ impl ProtocolClient for ModbusChannel {
             }
         }
 
-        // Drop client lock before acquiring diagnostics lock
-        drop(client_guard);
+        // Release the port before acquiring diagnostics lock
+        turnaround.finish(&mut guard);
+        drop(guard);
 
         // Record errors and update diagnostics after loop
         {
             let mut diag = self.diagnostics.write().await;
             diag.write_count += success_count as u64;
+            diag.requests.merge(requests);
             if let Some(err) = errors_to_record.last() {
                 diag.error_count += errors_to_record.len() as u64;
                 diag.last_error = Some(err.clone());
@@ -1694,6 +2235,7 @@ impl ProtocolClient for ModbusChannel {
         let result = WriteResult {
             success_count,
             failures,
+            stage: CommandStage::Confirmed,
         };
 
         // Log control write
@@ -1710,10 +2252,12 @@ impl ProtocolClient for ModbusChannel {
         let mut success_count = 0;
         let mut failures = Vec::new();
         let mut errors_to_record = Vec::new();
+        let mut requests = RequestCounters::default();
 
-        // Lock client for the entire operation
-        let mut client_guard = self.client.lock().await;
-        let client = match client_guard.as_mut() {
+        // Keep the port for the entire operation
+        let mut turnaround = Turnaround::new(self.config.broadcast_delay);
+        let mut guard = self.transport.acquire().await;
+        let client = match guard.client() {
             Some(c) => c,
             None => {
                 let err = GatewayError::NotConnected;
@@ -1747,6 +2291,10 @@ impl ProtocolClient for ModbusChannel {
                     continue;
                 }
             };
+            if modbus_addr.count.is_some() {
+                failures.push((adj.id, "Array points cannot be written".into()));
+                continue;
+            }
 
             // Apply reverse transform to get raw value
             let raw_value = match reverse_transform(adj.value, &point.transform) {
@@ -1758,24 +2306,34 @@ impl ProtocolClient for ModbusChannel {
             };
 
             // Encode and write register(s)
-            let result = match modbus_addr.format {
-                DataFormat::UInt16 | DataFormat::Int16 => {
-                    let value = raw_value as u16;
-                    client
-                        .write_06(modbus_addr.slave_id, modbus_addr.register, value)
-                        .await
-                }
-                DataFormat::UInt32 | DataFormat::Int32 | DataFormat::Float32 => {
-                    let regs = encode_value(raw_value, modbus_addr.format, modbus_addr.byte_order)?;
-                    client
-                        .write_10(modbus_addr.slave_id, modbus_addr.register, &regs)
-                        .await
-                }
+            let function_code = match modbus_addr.format {
+                DataFormat::UInt16 | DataFormat::Int16 => 6,
+                DataFormat::UInt32
+                | DataFormat::Int32
+                | DataFormat::Float32
+                | DataFormat::Float16
+                | DataFormat::Bcd16
+                | DataFormat::Bcd32 => 16,
                 _ => {
                     failures.push((adj.id, "Unsupported format for write".into()));
                     continue;
                 }
             };
+            let regs = match encode_value(raw_value, modbus_addr.format, modbus_addr.byte_order) {
+                Ok(regs) => regs,
+                Err(e) => {
+                    failures.push((adj.id, e.to_string()));
+                    continue;
+                }
+            };
+            let result = if function_code == 6 {
+                let write = client.write_06(modbus_addr.slave_id, modbus_addr.register, regs[0]);
+                turnaround.send(&modbus_addr, write).await
+            } else {
+                let write = client.write_10(modbus_addr.slave_id, modbus_addr.register, &regs);
+                turnaround.send(&modbus_addr, write).await
+            };
+            requests.record(modbus_addr.slave_id, function_code, &result);
 
             match result {
                 Ok(_) => {
@@ -1789,13 +2347,15 @@ impl ProtocolClient for ModbusChannel {
             }
         }
 
-        // Drop client lock before acquiring diagnostics lock
-        drop(client_guard);
+        // Release the port before acquiring diagnostics lock
+        turnaround.finish(&mut guard);
+        drop(guard);
 
         // Record errors and update diagnostics after loop
         {
             let mut diag = self.diagnostics.write().await;
             diag.write_count += success_count as u64;
+            diag.requests.merge(requests);
             if let Some(err) = errors_to_record.last() {
                 diag.error_count += errors_to_record.len() as u64;
                 diag.last_error = Some(err.clone());
@@ -1806,6 +2366,7 @@ impl ProtocolClient for ModbusChannel {
         let result = WriteResult {
             success_count,
             failures,
+            stage: CommandStage::Confirmed,
         };
 
         // Log adjustment write
@@ -1817,19 +2378,120 @@ impl ProtocolClient for ModbusChannel {
     }
 }
 
-/// Decode Modbus registers to a Value.
-fn decode_registers(
-    regs: &[u16],
-    format: crate::core::point::DataFormat,
-    byte_order: crate::core::point::ByteOrder,
-    bit_position: Option<u8>,
-) -> Result<Value> {
-    use crate::codec::byte_order::decode_registers as codec_decode;
+/// Applies the turn-around delay of broadcast writes while a write call
+/// holds the port.
+struct Turnaround {
+    delay: Duration,
+    quiet_until: Option<tokio::time::Instant>,
+}
+
+impl Turnaround {
+    fn new(delay: Duration) -> Self {
+        Self {
+            delay,
+            quiet_until: None,
+        }
+    }
+
+    /// Send `write` to `address`, after the quiet time of an earlier
+    /// broadcast.
+    ///
+    /// A broadcast is not answered, but voltage_modbus has no request that
+    /// skips the reply: the write runs until the response times out, which
+    /// means the frame went out, and the turn-around delay counts from then.
+    async fn send(
+        &mut self,
+        address: &ModbusAddress,
+        write: impl std::future::Future<Output = voltage_modbus::ModbusResult<()>>,
+    ) -> voltage_modbus::ModbusResult<()> {
+        use voltage_modbus::ModbusError;
+
+        self.wait().await;
+        if !address.broadcast {
+            return write.await;
+        }
+        let result = match write.await {
+            Err(ModbusError::Timeout { operation, .. })
+                if operation.starts_with("read response") =>
+            {
+                Ok(())
+            }
+            result => result,
+        };
+        self.quiet_until = Some(tokio::time::Instant::now() + self.delay);
+        result
+    }
 
-    codec_decode(regs, format, byte_order, bit_position)
+    /// Wait out the quiet time of an earlier broadcast.
+    async fn wait(&mut self) {
+        if let Some(deadline) = self.quiet_until.take() {
+            tokio::time::sleep_until(deadline).await;
+        }
+    }
+
+    /// Keep the port quiet for the rest of the delay after the write call.
+    fn finish(self, guard: &mut TransportGuard<'_, ModbusClientWrapper>) {
+        if let Some(deadline) = self.quiet_until {
+            guard.quiet_until(deadline);
+        }
+    }
 }
 
-/// Encode a Value to Modbus registers.
+/// `register` with `bit` set to `value`, the other bits kept.
+///
+/// `None` unless `bit` is 0-15.
+fn set_bit(register: u16, bit: u8, value: bool) -> Option<u16> {
+    let mask = 1u16.checked_shl(bit.into())?;
+    Some(if value {
+        register | mask
+    } else {
+        register & !mask
+    })
+}
+
+/// Open the port of `config`.
+async fn open_client(config: &ModbusChannelConfig) -> Result<ModbusClientWrapper> {
+    match config.connection_mode {
+        ConnectionMode::Tcp => {
+            // TCP connection
+            match ModbusTcpClient::from_address(&config.address, config.connect_timeout).await {
+                Ok(client) => Ok(ModbusClientWrapper::Tcp(client)),
+                Err(e) => Err(GatewayError::connection_with(e.to_string(), e)),
+            }
+        }
+        #[cfg(feature = "modbus")]
+        ConnectionMode::Rtu => {
+            // RTU serial connection
+            match ModbusRtuClient::new(&config.rtu_device, config.baud_rate) {
+                Ok(client) => Ok(ModbusClientWrapper::Rtu(client)),
+                Err(e) => Err(GatewayError::connection_with(e.to_string(), e)),
+            }
+        }
+    }
+}
+
+/// Decode the registers of a point to a Value (an array if the address has
+/// a count).
+fn decode_registers(regs: &[u16], address: &ModbusAddress) -> Result<Value> {
+    use crate::codec::byte_order::{decode_register_array, decode_registers as codec_decode};
+
+    match address.count {
+        Some(count) => decode_register_array(regs, address.format, address.byte_order, count),
+        None => codec_decode(
+            regs,
+            address.format,
+            address.byte_order,
+            address.bit_position,
+        ),
+    }
+}
+
+/// Encode a raw (reverse-transformed) command value to Modbus registers.
+///
+/// Values for integer formats are rounded to the nearest integer first, as
+/// scaling leaves them a rounding error off (`2.3 / 0.1` is
+/// `22.999999999999996`). Values the format cannot hold (out of range,
+/// NaN, infinite) fail with `GatewayError::DataConversion`.
 fn encode_value(
     value: f64,
     format: crate::core::point::DataFormat,
@@ -1837,17 +2499,19 @@ fn encode_value(
 ) -> Result<Vec<u16>> {
     use crate::codec::byte_order::encode_registers;
 
+    let value = match format {
+        DataFormat::Float16 | DataFormat::Float32 | DataFormat::Float64 => value,
+        _ => value.round(),
+    };
     encode_registers(&Value::Float(value), format, byte_order)
 }
 
-/// Apply transform to a value.
-fn apply_transform(value: Value, transform: &crate::core::point::TransformConfig) -> Value {
-    match value {
-        Value::Float(v) => Value::Float(transform.apply(v)),
-        Value::Integer(v) => Value::Float(transform.apply(v as f64)),
-        Value::Bool(v) => Value::Bool(transform.apply_bool(v)),
-        other => other,
-    }
+/// Apply transform to a value, returning the range-checked quality.
+fn apply_transform(
+    value: Value,
+    transform: &crate::core::point::TransformConfig,
+) -> (Value, Quality) {
+    transform.apply_value(value)
 }
 
 /// Reverse transform to get raw value.
@@ -1861,6 +2525,8 @@ fn reverse_transform(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::data::PointId;
+    use crate::core::point::TransformConfig;
 
     #[test]
     fn test_modbus_channel_config() {
@@ -1942,4 +2608,317 @@ mod tests {
         let channel = ModbusChannel::new(config, 1);
         assert_eq!(channel.name(), "Modbus RTU");
     }
+    #[test]
+    fn test_broadcast_delay_param() {
+        let params: ModbusChannelParamsConfig =
+            serde_json::from_value(serde_json::json!({ "host": "10.0.0.5" })).unwrap();
+        assert_eq!(
+            params.to_channel_config().broadcast_delay,
+            Duration::from_millis(100)
+        );
+        let params: ModbusChannelParamsConfig = serde_json::from_value(
+            serde_json::json!({ "device": "/dev/ttyUSB0", "broadcast_delay_ms": 250 }),
+        )
+        .unwrap();
+        assert_eq!(
+            params.to_channel_config().broadcast_delay,
+            Duration::from_millis(250)
+        );
+    }
+
+    #[test]
+    fn test_preserve_register_bits_param() {
+        let params: ModbusChannelParamsConfig =
+            serde_json::from_value(serde_json::json!({ "host": "10.0.0.5" })).unwrap();
+        assert!(!params.to_channel_config().preserve_register_bits);
+        let params: ModbusChannelParamsConfig = serde_json::from_value(
+            serde_json::json!({ "host": "10.0.0.5", "preserve_register_bits": true }),
+        )
+        .unwrap();
+        assert!(params.to_channel_config().preserve_register_bits);
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_points_are_not_read() {
+        let point = PointConfig::new(
+            7,
+            ProtocolAddress::Modbus(
+                ModbusAddress::holding_register(1, 10, DataFormat::UInt16).broadcast(),
+            ),
+        );
+        let config = ModbusChannelConfig::tcp("127.0.0.1:502").with_points(vec![point.clone()]);
+        let channel = ModbusChannel::new(config, 1);
+
+        channel.group_points_for_polling().await;
+        assert!(channel.grouped_points.read().await.is_empty());
+        let error = channel.read_modbus_point(&point).await.unwrap_err();
+        assert!(matches!(error, GatewayError::Config(_)), "{}", error);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_broadcast_turnaround() {
+        use voltage_modbus::ModbusError;
+
+        let unicast = ModbusAddress::holding_register(1, 10, DataFormat::UInt16);
+        let broadcast = unicast.clone().broadcast();
+        let mut turnaround = Turnaround::new(Duration::from_millis(100));
+        let start = tokio::time::Instant::now();
+
+        // No device answers a broadcast: it counts as sent once the
+        // response times out
+        let unanswered = async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Err(ModbusError::timeout("read response", 50))
+        };
+        turnaround.send(&broadcast, unanswered).await.unwrap();
+        assert_eq!(start.elapsed(), Duration::from_millis(50));
+
+        // The next request waits for the delay after a broadcast
+        turnaround.send(&broadcast, async { Ok(()) }).await.unwrap();
+        assert_eq!(start.elapsed(), Duration::from_millis(150));
+        let error = turnaround
+            .send(&broadcast, async {
+                Err(ModbusError::timeout("send request", 50))
+            })
+            .await
+            .unwrap_err();
+        assert!(matches!(error, ModbusError::Timeout { .. }), "{}", error);
+        assert_eq!(start.elapsed(), Duration::from_millis(250));
+        turnaround.send(&unicast, async { Ok(()) }).await.unwrap();
+        assert_eq!(start.elapsed(), Duration::from_millis(350));
+        turnaround.send(&unicast, async { Ok(()) }).await.unwrap();
+        assert_eq!(start.elapsed(), Duration::from_millis(350));
+    }
+
+    #[tokio::test]
+    async fn test_deadband_suppresses_jitter() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Holding register 0 reads 1000 ± 3 counts, register 1 steps by 20
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            let mut polls = 0u16;
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = [0u8; 12];
+                while stream.read_exact(&mut request).await.is_ok() {
+                    let noisy = 1000 + polls % 7 - 3;
+                    let ramp = 1000 + polls * 20;
+                    polls += 1;
+                    let mut reply = vec![request[0], request[1], 0, 0, 0, 7, request[6], 3, 4];
+                    reply.extend_from_slice(&noisy.to_be_bytes());
+                    reply.extend_from_slice(&ramp.to_be_bytes());
+                    stream.write_all(&reply).await.unwrap();
+                }
+            }
+        });
+
+        // 0.1 per count, deadband of 1.0 (10 counts)
+        let transform = TransformConfig {
+            scale: 0.1,
+            deadband: Some(1.0),
+            ..TransformConfig::default()
+        };
+        let point = |id, register| {
+            PointConfig::new(
+                id,
+                ProtocolAddress::Modbus(ModbusAddress::holding_register(
+                    1,
+                    register,
+                    DataFormat::UInt16,
+                )),
+            )
+            .with_transform(transform.clone())
+        };
+        let config = ModbusChannelConfig::tcp(address).with_points(vec![point(1, 0), point(2, 1)]);
+        let mut channel = ModbusChannel::new(config, 1);
+        channel.connect().await.unwrap();
+
+        let mut events = HashMap::<PointId, usize>::new();
+        for _ in 0..50 {
+            let result = channel.poll_once().await;
+            assert!(result.failures.is_empty());
+            for point in result.data.iter() {
+                *events.entry(point.id).or_default() += 1;
+            }
+        }
+
+        // The jitter is published once, the ramp (2.0 per poll) every time
+        assert_eq!(events.get(&1), Some(&1));
+        assert_eq!(events.get(&2), Some(&50));
+        let extra = channel.diagnostics().await.unwrap().extra;
+        assert_eq!(extra["deadband_suppressed"], 49);
+
+        // A reconnect publishes the current value again
+        channel.connect().await.unwrap();
+        assert_eq!(channel.poll_once().await.data.len(), 2);
+
+        // So does a refresh, once
+        assert_eq!(channel.poll_once().await.data.len(), 1);
+        channel.refresh();
+        assert_eq!(channel.poll_once().await.data.len(), 2);
+    }
+
+    #[test]
+    fn test_encode_command_value() {
+        // 2.3 with scale 0.1
+        let raw = TransformConfig {
+            scale: 0.1,
+            ..TransformConfig::default()
+        }
+        .reverse_apply(2.3)
+        .unwrap();
+        assert_eq!(
+            encode_value(raw, DataFormat::UInt16, ByteOrder::Abcd).unwrap(),
+            vec![23]
+        );
+        assert_eq!(
+            encode_value(-5.0, DataFormat::Int16, ByteOrder::Abcd).unwrap(),
+            vec![0xFFFB]
+        );
+        for (value, format) in [
+            (70_000.0, DataFormat::UInt16),
+            (-1.0, DataFormat::UInt32),
+            (f64::NAN, DataFormat::Int32),
+        ] {
+            assert!(matches!(
+                encode_value(value, format, ByteOrder::Abcd),
+                Err(GatewayError::DataConversion(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_request_counters() {
+        use voltage_modbus::ModbusError;
+
+        let mut counters = RequestCounters::default();
+        counters.record(1, 3, &Ok(()));
+        counters.record::<()>(
+            1,
+            3,
+            &Err(ModbusError::Timeout {
+                operation: "read".into(),
+                timeout_ms: 100,
+            }),
+        );
+        counters.record::<()>(
+            1,
+            3,
+            &Err(ModbusError::CrcMismatch {
+                expected: 1,
+                actual: 2,
+            }),
+        );
+        let mut more = RequestCounters::default();
+        more.record::<()>(
+            2,
+            6,
+            &Err(ModbusError::Exception {
+                function: 0x86,
+                code: 0x03,
+                message: "Illegal data value",
+            }),
+        );
+        more.record::<()>(
+            2,
+            6,
+            &Err(ModbusError::Exception {
+                function: 0x86,
+                code: 0x0c,
+                message: "Unknown",
+            }),
+        );
+        more.record::<()>(
+            2,
+            6,
+            &Err(ModbusError::frame(
+                "CRC mismatch: expected 0x1234, got 0x4321",
+            )),
+        );
+        counters.merge(more);
+
+        let json = counters.to_json();
+        let fc3 = &json["1"]["function_codes"]["3"];
+        assert_eq!(fc3["requests"], 3);
+        assert_eq!(fc3["errors"], 2);
+        assert_eq!(fc3["timeouts"], 1);
+        assert_eq!(fc3["crc_errors"], 1);
+        assert!(json["1"]["last_error"]
+            .as_str()
+            .unwrap()
+            .starts_with("FC03: CRC validation failed"));
+        let fc6 = &json["2"]["function_codes"]["6"];
+        assert_eq!(fc6["exceptions"]["illegal_data_value"], 1);
+        assert_eq!(fc6["exceptions"]["code_0c"], 1);
+        assert_eq!(fc6["crc_errors"], 1);
+        assert_eq!(ExceptionCode::from_code(0x0B).code(), 0x0B);
+    }
+
+    #[tokio::test]
+    async fn test_diagnostics_per_slave() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Slave 1 answers, slave 2 rejects every address
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = [0u8; 12];
+                while stream.read_exact(&mut request).await.is_ok() {
+                    let (unit, function) = (request[6], request[7]);
+                    let reply = if unit == 1 {
+                        vec![request[0], request[1], 0, 0, 0, 5, unit, function, 2, 0, 42]
+                    } else {
+                        vec![request[0], request[1], 0, 0, 0, 3, unit, function | 0x80, 2]
+                    };
+                    stream.write_all(&reply).await.unwrap();
+                }
+            }
+        });
+
+        let point = |id, slave_id| {
+            PointConfig::new(
+                id,
+                ProtocolAddress::Modbus(ModbusAddress::holding_register(
+                    slave_id,
+                    0,
+                    DataFormat::UInt16,
+                )),
+            )
+        };
+        let config = ModbusChannelConfig::tcp(address).with_points(vec![point(1, 1), point(2, 2)]);
+        let mut channel = ModbusChannel::new(config, 1);
+        channel.connect().await.unwrap();
+        for _ in 0..3 {
+            let result = channel.poll_once().await;
+            assert_eq!(result.failures.len(), 1);
+        }
+
+        let slaves = channel.diagnostics().await.unwrap().extra["slaves"].clone();
+        assert_eq!(slaves["1"]["function_codes"]["3"]["requests"], 3);
+        assert_eq!(slaves["1"]["function_codes"]["3"]["errors"], 0);
+        assert!(slaves["1"]["last_error"].is_null());
+        let fc3 = &slaves["2"]["function_codes"]["3"];
+        assert_eq!(fc3["requests"], 3);
+        assert_eq!(fc3["exceptions"]["illegal_data_address"], 3);
+        assert!(slaves["2"]["last_error"]
+            .as_str()
+            .unwrap()
+            .starts_with("FC03:"));
+
+        channel.reset_diagnostics().await;
+        let diagnostics = channel.diagnostics().await.unwrap();
+        assert!(diagnostics.extra.get("slaves").is_none());
+        assert_eq!(diagnostics.error_count, 0);
+    }
+
+    #[test]
+    fn test_set_bit() {
+        assert_eq!(set_bit(0b1010, 0, true), Some(0b1011));
+        assert_eq!(set_bit(0b1010, 3, false), Some(0b0010));
+        assert_eq!(set_bit(0b1010, 1, true), Some(0b1010));
+        assert_eq!(set_bit(0, 15, true), Some(0x8000));
+        assert_eq!(set_bit(0xFFFF, 16, false), None);
+    }
 }
@@ -0,0 +1,913 @@
+//! Northbound Modbus TCP server (feature `modbus-server`).
+//!
+//! [`ModbusServer`] lets Modbus masters such as legacy HMIs read the
+//! gateway's current values and send it commands. A register table
+//! ([`ModbusServerConfig`]) maps coils and registers onto points:
+//!
+//! | Function | Area | Effect |
+//! |----------|------|--------|
+//! | FC01 | `coil` | Current values as bits |
+//! | FC03 / FC04 | `holding` / `input` | Current values, encoded per entry |
+//! | FC05 | `coil` | [`ControlCommand`] |
+//! | FC06 / FC16 | `holding` | [`ControlCommand`] for `bool` entries, else [`AdjustmentCommand`] |
+//!
+//! Values are read from the [`DataStore`] on every request. Registers
+//! without an entry and points without a value read as 0; a read that
+//! touches no entry at all is answered with "illegal data address".
+//!
+//! Writes must cover whole entries and are delivered through a
+//! [`ModbusCommandHandler`], one command per entry; a failing command is
+//! answered with "server device failure". Without a handler, writes are
+//! rejected with "illegal function". A [`GatewayRuntime`] behind a
+//! `tokio::sync::RwLock` is a handler that forwards the commands to its
+//! channels as caller `modbus_server`, subject to the gateway's
+//! authorization levels.
+//!
+//! Requests for another unit id than the configured one are answered with
+//! "gateway target device failed to respond".
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! let gateway = Arc::new(RwLock::new(GatewayRuntime::from_config(config, store.clone())?));
+//! let mut server = ModbusServer::new(&server_config, store).with_command_handler(gateway);
+//! server.listen(&server_config.bind).await?;
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::Serialize;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::RwLock;
+use tokio::task::{JoinHandle, JoinSet};
+
+use crate::codec::{decode_registers, encode_registers};
+use crate::core::data::{PointId, Value};
+use crate::core::error::{GatewayError, Result};
+use crate::core::traits::{
+    AdjustmentCommand, CommunicationMode, ConnectionState, ControlCommand, Diagnostics, Protocol,
+    ProtocolCapabilities, ProtocolServer, WriteResult,
+};
+use crate::gateway::{GatewayRuntime, ModbusServerConfig, RegisterArea, RegisterMapping};
+use crate::store::DataStore;
+
+/// Largest register count of an entry (a `string` entry).
+const MAX_ENTRY_REGISTERS: u16 = 8;
+
+/// Receives the commands Modbus masters write.
+///
+/// This trait uses `async_trait` because the server holds it as
+/// `Arc<dyn ModbusCommandHandler>`.
+#[async_trait]
+pub trait ModbusCommandHandler: Send + Sync {
+    /// Deliver a control command for a point of `channel_id`.
+    async fn control(&self, channel_id: u32, command: ControlCommand) -> Result<()>;
+
+    /// Deliver an adjustment command for a point of `channel_id`.
+    async fn adjustment(&self, channel_id: u32, command: AdjustmentCommand) -> Result<()>;
+}
+
+/// Caller identity of commands written by clients, see
+/// [`AuthorizationConfig`](crate::gateway::AuthorizationConfig).
+const CALLER: &str = "modbus_server";
+
+#[async_trait]
+impl ModbusCommandHandler for RwLock<GatewayRuntime> {
+    async fn control(&self, channel_id: u32, command: ControlCommand) -> Result<()> {
+        let value = if command.value { 1.0 } else { 0.0 };
+        let gateway = self.read().await;
+        let caller = gateway.caller(CALLER);
+        let result = gateway
+            .write_control_as(&caller, channel_id, &[(command.id, value)])
+            .await?;
+        ensure_accepted(result, command.id)
+    }
+
+    async fn adjustment(&self, channel_id: u32, command: AdjustmentCommand) -> Result<()> {
+        let gateway = self.read().await;
+        let caller = gateway.caller(CALLER);
+        let result = gateway
+            .write_adjustment_as(&caller, channel_id, &[(command.id, command.value)])
+            .await?;
+        ensure_accepted(result, command.id)
+    }
+}
+
+/// Fail with the reason the runtime gives if the command was not sent.
+fn ensure_accepted(result: WriteResult, point_id: PointId) -> Result<()> {
+    if let Some((_, reason)) = result.failures.into_iter().next() {
+        return Err(GatewayError::protocol(format!(
+            "point {}: {}",
+            point_id, reason
+        )));
+    }
+    if result.success_count == 0 {
+        return Err(GatewayError::protocol(format!(
+            "point {}: command not accepted",
+            point_id
+        )));
+    }
+    Ok(())
+}
+
+/// Request counters of one connected master.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ModbusClientStats {
+    /// Remote address.
+    pub peer: SocketAddr,
+
+    /// Requests received.
+    pub requests: u64,
+
+    /// Requests answered with an exception.
+    pub exceptions: u64,
+}
+
+/// Northbound Modbus TCP server.
+pub struct ModbusServer {
+    map: Arc<RegisterMap>,
+    unit_id: Option<u8>,
+    store: Arc<dyn DataStore>,
+    handler: Option<Arc<dyn ModbusCommandHandler>>,
+    stats: Arc<ServerStats>,
+    task: Option<JoinHandle<()>>,
+    local_addr: Option<SocketAddr>,
+}
+
+impl ModbusServer {
+    /// Create a server for `config`'s register table, answering from `store`.
+    ///
+    /// The table is expected to have passed [`GatewayConfig::validate()`];
+    /// of overlapping entries, the last one wins.
+    ///
+    /// [`GatewayConfig::validate()`]: crate::gateway::GatewayConfig::validate
+    pub fn new(config: &ModbusServerConfig, store: Arc<dyn DataStore>) -> Self {
+        Self {
+            map: Arc::new(RegisterMap::new(&config.registers)),
+            unit_id: config.unit_id,
+            store,
+            handler: None,
+            stats: Arc::default(),
+            task: None,
+            local_addr: None,
+        }
+    }
+
+    /// Deliver written commands to `handler` (writes are rejected without one).
+    #[must_use]
+    pub fn with_command_handler(mut self, handler: Arc<dyn ModbusCommandHandler>) -> Self {
+        self.handler = Some(handler);
+        self
+    }
+
+    /// The address the server listens on.
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.local_addr
+    }
+
+    /// Request counters of the connected masters, by address.
+    pub fn clients(&self) -> Vec<ModbusClientStats> {
+        let mut clients: Vec<ModbusClientStats> =
+            lock(&self.stats.clients).values().cloned().collect();
+        clients.sort_by_key(|c| c.peer);
+        clients
+    }
+
+    fn is_listening(&self) -> bool {
+        self.task.as_ref().is_some_and(|task| !task.is_finished())
+    }
+}
+
+impl Drop for ModbusServer {
+    fn drop(&mut self) {
+        if let Some(task) = &self.task {
+            task.abort();
+        }
+    }
+}
+
+impl ProtocolCapabilities for ModbusServer {
+    fn name(&self) -> &'static str {
+        "Modbus TCP Server"
+    }
+
+    fn supported_modes(&self) -> &[CommunicationMode] {
+        &[CommunicationMode::Polling]
+    }
+}
+
+impl Protocol for ModbusServer {
+    fn connection_state(&self) -> ConnectionState {
+        if self.is_listening() {
+            ConnectionState::Connected
+        } else {
+            ConnectionState::Disconnected
+        }
+    }
+
+    /// Reads and writes count served requests, errors count exceptions;
+    /// `extra.clients` lists the counters of each connected master.
+    async fn diagnostics(&self) -> Result<Diagnostics> {
+        let stats = &self.stats;
+        Ok(Diagnostics {
+            protocol: self.name().to_string(),
+            connection_state: self.connection_state(),
+            read_count: stats.reads.load(Ordering::Relaxed),
+            write_count: stats.writes.load(Ordering::Relaxed),
+            error_count: stats.exceptions.load(Ordering::Relaxed),
+            last_error: lock(&stats.last_error).clone(),
+            extra: serde_json::json!({
+                "listen": self.local_addr.map(|addr| addr.to_string()),
+                "clients": self.clients(),
+            }),
+            ..Default::default()
+        })
+    }
+}
+
+impl ProtocolServer for ModbusServer {
+    /// Start accepting masters on `addr`; a running listener is stopped
+    /// first.
+    async fn listen(&mut self, addr: &str) -> Result<()> {
+        self.stop().await?;
+        let listener = TcpListener::bind(addr).await.map_err(|e| {
+            GatewayError::connection_with(format!("modbus_server: cannot bind {}: {}", addr, e), e)
+        })?;
+        self.local_addr = Some(listener.local_addr()?);
+
+        let context = Arc::new(Context {
+            map: Arc::clone(&self.map),
+            unit_id: self.unit_id,
+            store: Arc::clone(&self.store),
+            handler: self.handler.clone(),
+            stats: Arc::clone(&self.stats),
+        });
+        self.task = Some(tokio::spawn(accept_loop(listener, context)));
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> Result<()> {
+        if let Some(task) = self.task.take() {
+            task.abort();
+            let _ = task.await;
+        }
+        // The aborted connections deregister themselves asynchronously
+        lock(&self.stats.clients).clear();
+        self.local_addr = None;
+        Ok(())
+    }
+
+    fn connected_clients(&self) -> usize {
+        lock(&self.stats.clients).len()
+    }
+}
+
+/// Counters shared by the server and its connections.
+#[derive(Default)]
+struct ServerStats {
+    clients: Mutex<HashMap<SocketAddr, ModbusClientStats>>,
+    reads: AtomicU64,
+    writes: AtomicU64,
+    exceptions: AtomicU64,
+    last_error: Mutex<Option<String>>,
+}
+
+/// Removes a master from the client list when its connection ends.
+struct ClientGuard {
+    stats: Arc<ServerStats>,
+    peer: SocketAddr,
+}
+
+impl ClientGuard {
+    fn new(stats: Arc<ServerStats>, peer: SocketAddr) -> Self {
+        lock(&stats.clients).insert(
+            peer,
+            ModbusClientStats {
+                peer,
+                requests: 0,
+                exceptions: 0,
+            },
+        );
+        Self { stats, peer }
+    }
+
+    fn count(&self, exception: bool) {
+        if let Some(client) = lock(&self.stats.clients).get_mut(&self.peer) {
+            client.requests += 1;
+            client.exceptions += u64::from(exception);
+        }
+    }
+}
+
+impl Drop for ClientGuard {
+    fn drop(&mut self) {
+        lock(&self.stats.clients).remove(&self.peer);
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Register table, by area and start address.
+struct RegisterMap {
+    areas: HashMap<RegisterArea, BTreeMap<u16, RegisterMapping>>,
+}
+
+impl RegisterMap {
+    fn new(registers: &[RegisterMapping]) -> Self {
+        let mut areas: HashMap<RegisterArea, BTreeMap<u16, RegisterMapping>> = HashMap::new();
+        for entry in registers {
+            areas
+                .entry(entry.area)
+                .or_default()
+                .insert(entry.address, entry.clone());
+        }
+        Self { areas }
+    }
+
+    /// Entry starting at `address`.
+    fn get(&self, area: RegisterArea, address: u16) -> Option<&RegisterMapping> {
+        self.areas.get(&area)?.get(&address)
+    }
+
+    /// Entries overlapping `count` registers from `start`.
+    fn overlapping(&self, area: RegisterArea, start: u16, count: u16) -> Vec<&RegisterMapping> {
+        let Some(entries) = self.areas.get(&area) else {
+            return Vec::new();
+        };
+        let end = u32::from(start) + u32::from(count);
+        entries
+            .range(start.saturating_sub(MAX_ENTRY_REGISTERS - 1)..)
+            .map(|(_, entry)| entry)
+            .take_while(|entry| u32::from(entry.address) < end)
+            .filter(|entry| entry_end(entry) > u32::from(start))
+            .collect()
+    }
+}
+
+fn entry_end(entry: &RegisterMapping) -> u32 {
+    u32::from(entry.address) + u32::from(entry.register_count())
+}
+
+/// Modbus exception answered instead of a normal response.
+#[derive(Debug)]
+enum Exception {
+    IllegalFunction,
+    IllegalDataAddress,
+    IllegalDataValue,
+    DeviceFailure(String),
+    GatewayTargetFailed,
+}
+
+impl Exception {
+    fn code(&self) -> u8 {
+        match self {
+            Self::IllegalFunction => 0x01,
+            Self::IllegalDataAddress => 0x02,
+            Self::IllegalDataValue => 0x03,
+            Self::DeviceFailure(_) => 0x04,
+            Self::GatewayTargetFailed => 0x0B,
+        }
+    }
+}
+
+impl fmt::Display for Exception {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IllegalFunction => f.write_str("illegal function"),
+            Self::IllegalDataAddress => f.write_str("illegal data address"),
+            Self::IllegalDataValue => f.write_str("illegal data value"),
+            Self::DeviceFailure(reason) => write!(f, "server device failure: {}", reason),
+            Self::GatewayTargetFailed => f.write_str("unknown unit id"),
+        }
+    }
+}
+
+type Response = std::result::Result<Vec<u8>, Exception>;
+
+/// What a connection needs to answer requests.
+struct Context {
+    map: Arc<RegisterMap>,
+    unit_id: Option<u8>,
+    store: Arc<dyn DataStore>,
+    handler: Option<Arc<dyn ModbusCommandHandler>>,
+    stats: Arc<ServerStats>,
+}
+
+async fn accept_loop(listener: TcpListener, context: Arc<Context>) {
+    // Dropping the set when the server stops closes every connection
+    let mut connections = JoinSet::new();
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, peer)) => {
+                    connections.spawn(serve_client(stream, peer, Arc::clone(&context)));
+                }
+                Err(_e) => {
+                    // Typically out of file descriptors; give clients time to leave
+                    #[cfg(feature = "tracing-support")]
+                    tracing::warn!("Modbus server accept failed: {}", _e);
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                }
+            },
+            Some(_) = connections.join_next(), if !connections.is_empty() => {}
+        }
+    }
+}
+
+async fn serve_client(mut stream: TcpStream, peer: SocketAddr, context: Arc<Context>) {
+    let client = ClientGuard::new(Arc::clone(&context.stats), peer);
+    #[cfg(feature = "tracing-support")]
+    tracing::debug!("Modbus master {} connected", peer);
+
+    while let Ok((transaction, unit, pdu)) = read_request(&mut stream).await {
+        let function = pdu[0];
+        let response = context.handle(unit, function, &pdu[1..]).await;
+        client.count(response.is_err());
+        let pdu = response.unwrap_or_else(|exception| {
+            context.stats.exceptions.fetch_add(1, Ordering::Relaxed);
+            *lock(&context.stats.last_error) =
+                Some(format!("{}: FC{:02X}: {}", peer, function, exception));
+            vec![function | 0x80, exception.code()]
+        });
+
+        let [tid_hi, tid_lo] = transaction.to_be_bytes();
+        let [len_hi, len_lo] = (pdu.len() as u16 + 1).to_be_bytes();
+        let mut frame = vec![tid_hi, tid_lo, 0, 0, len_hi, len_lo, unit];
+        frame.extend_from_slice(&pdu);
+        if stream.write_all(&frame).await.is_err() {
+            break;
+        }
+    }
+
+    #[cfg(feature = "tracing-support")]
+    tracing::debug!("Modbus master {} disconnected", peer);
+}
+
+/// Read one request frame: (transaction id, unit id, PDU).
+///
+/// Frames of another protocol than Modbus end the connection.
+async fn read_request(stream: &mut TcpStream) -> std::io::Result<(u16, u8, Vec<u8>)> {
+    let mut header = [0u8; 7];
+    stream.read_exact(&mut header).await?;
+    let length = u16::from_be_bytes([header[4], header[5]]) as usize;
+    if header[2..4] != [0, 0] || !(2..=254).contains(&length) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "invalid MBAP header",
+        ));
+    }
+    let mut pdu = vec![0u8; length - 1];
+    stream.read_exact(&mut pdu).await?;
+    Ok((u16::from_be_bytes([header[0], header[1]]), header[6], pdu))
+}
+
+fn u16_at(data: &[u8], index: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([
+        *data.get(index)?,
+        *data.get(index + 1)?,
+    ]))
+}
+
+/// `(start, count)` of a request, with `count` in `1..=max` and the range
+/// inside the address space.
+fn range(data: &[u8], max: u16) -> std::result::Result<(u16, u16), Exception> {
+    let (Some(start), Some(count)) = (u16_at(data, 0), u16_at(data, 2)) else {
+        return Err(Exception::IllegalDataValue);
+    };
+    if !(1..=max).contains(&count) {
+        return Err(Exception::IllegalDataValue);
+    }
+    if u32::from(start) + u32::from(count) > 0x1_0000 {
+        return Err(Exception::IllegalDataAddress);
+    }
+    Ok((start, count))
+}
+
+impl Context {
+    async fn handle(&self, unit: u8, function: u8, data: &[u8]) -> Response {
+        if self.unit_id.is_some_and(|id| id != unit) {
+            return Err(Exception::GatewayTargetFailed);
+        }
+        let response = match function {
+            0x01 => self.read_coils(data).await,
+            0x03 => {
+                self.read_registers(RegisterArea::Holding, function, data)
+                    .await
+            }
+            0x04 => {
+                self.read_registers(RegisterArea::Input, function, data)
+                    .await
+            }
+            0x05 => self.write_coil(data).await,
+            0x06 => self.write_register(data).await,
+            0x10 => self.write_registers(data).await,
+            _ => return Err(Exception::IllegalFunction),
+        }?;
+        let counter = if function <= 0x04 {
+            &self.stats.reads
+        } else {
+            &self.stats.writes
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        Ok(response)
+    }
+
+    /// Current values of `entries`' points.
+    async fn values(
+        &self,
+        entries: &[&RegisterMapping],
+    ) -> std::result::Result<HashMap<(u32, PointId), Value>, Exception> {
+        let mut by_channel: BTreeMap<u32, Vec<PointId>> = BTreeMap::new();
+        for entry in entries {
+            by_channel
+                .entry(entry.channel_id)
+                .or_default()
+                .push(entry.point_id);
+        }
+
+        let mut values = HashMap::new();
+        for (channel_id, point_ids) in by_channel {
+            let batch = self
+                .store
+                .read_points(channel_id, &point_ids)
+                .await
+                .map_err(|e| Exception::DeviceFailure(e.to_string()))?;
+            values.extend(
+                batch
+                    .into_vec()
+                    .into_iter()
+                    .map(|p| ((channel_id, p.id), p.value)),
+            );
+        }
+        Ok(values)
+    }
+
+    async fn read_coils(&self, data: &[u8]) -> Response {
+        let (start, count) = range(data, 2000)?;
+        let entries = self.map.overlapping(RegisterArea::Coil, start, count);
+        if entries.is_empty() {
+            return Err(Exception::IllegalDataAddress);
+        }
+        let values = self.values(&entries).await?;
+
+        let mut bits = vec![0u8; usize::from(count).div_ceil(8)];
+        for entry in entries {
+            let value = values.get(&(entry.channel_id, entry.point_id));
+            if value.and_then(Value::as_bool).unwrap_or(false) {
+                let offset = usize::from(entry.address - start);
+                bits[offset / 8] |= 1 << (offset % 8);
+            }
+        }
+        let mut response = vec![0x01, bits.len() as u8];
+        response.extend_from_slice(&bits);
+        Ok(response)
+    }
+
+    async fn read_registers(&self, area: RegisterArea, function: u8, data: &[u8]) -> Response {
+        let (start, count) = range(data, 125)?;
+        let entries = self.map.overlapping(area, start, count);
+        if entries.is_empty() {
+            return Err(Exception::IllegalDataAddress);
+        }
+        let values = self.values(&entries).await?;
+
+        let mut registers = vec![0u16; usize::from(count)];
+        for entry in entries {
+            let Some(value) = values.get(&(entry.channel_id, entry.point_id)) else {
+                continue;
+            };
+            let Ok(encoded) = encode_registers(value, entry.format, entry.byte_order) else {
+                continue;
+            };
+            // Entries may stick out of either end of the requested range
+            for (address, register) in (u32::from(entry.address)..).zip(encoded) {
+                let Some(offset) = address.checked_sub(u32::from(start)) else {
+                    continue;
+                };
+                if let Some(slot) = registers.get_mut(offset as usize) {
+                    *slot = register;
+                }
+            }
+        }
+
+        let mut response = vec![function, (registers.len() * 2) as u8];
+        response.extend(registers.iter().flat_map(|r| r.to_be_bytes()));
+        Ok(response)
+    }
+
+    async fn write_coil(&self, data: &[u8]) -> Response {
+        let handler = self.handler()?;
+        let (Some(address), Some(raw)) = (u16_at(data, 0), u16_at(data, 2)) else {
+            return Err(Exception::IllegalDataValue);
+        };
+        let value = match raw {
+            0xFF00 => true,
+            0x0000 => false,
+            _ => return Err(Exception::IllegalDataValue),
+        };
+        let entry = self
+            .map
+            .get(RegisterArea::Coil, address)
+            .ok_or(Exception::IllegalDataAddress)?;
+        handler
+            .control(
+                entry.channel_id,
+                ControlCommand::latching(entry.point_id, value),
+            )
+            .await
+            .map_err(|e| Exception::DeviceFailure(e.to_string()))?;
+        Ok([&[0x05], &data[..4]].concat())
+    }
+
+    async fn write_register(&self, data: &[u8]) -> Response {
+        let handler = self.handler()?;
+        let (Some(address), Some(value)) = (u16_at(data, 0), u16_at(data, 2)) else {
+            return Err(Exception::IllegalDataValue);
+        };
+        let entry = self
+            .map
+            .get(RegisterArea::Holding, address)
+            .filter(|entry| entry.register_count() == 1)
+            .ok_or(Exception::IllegalDataAddress)?;
+        deliver(handler, entry, &[value]).await?;
+        Ok([&[0x06], &data[..4]].concat())
+    }
+
+    async fn write_registers(&self, data: &[u8]) -> Response {
+        let handler = self.handler()?;
+        let (start, count) = range(data, 123)?;
+        let values = &data[4..];
+        if values.first() != Some(&(count as u8 * 2)) || values.len() != 1 + usize::from(count) * 2
+        {
+            return Err(Exception::IllegalDataValue);
+        }
+        let registers: Vec<u16> = values[1..]
+            .chunks_exact(2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+            .collect();
+
+        // The written range must consist of whole entries
+        let entries = self.map.overlapping(RegisterArea::Holding, start, count);
+        let end = u32::from(start) + u32::from(count);
+        let covered: u32 = entries.iter().map(|e| u32::from(e.register_count())).sum();
+        if covered != u32::from(count)
+            || entries
+                .iter()
+                .any(|e| e.address < start || entry_end(e) > end)
+        {
+            return Err(Exception::IllegalDataAddress);
+        }
+
+        for entry in entries {
+            let offset = usize::from(entry.address - start);
+            let len = usize::from(entry.register_count());
+            deliver(handler, entry, &registers[offset..offset + len]).await?;
+        }
+        Ok([&[0x10], &data[..4]].concat())
+    }
+
+    fn handler(&self) -> std::result::Result<&dyn ModbusCommandHandler, Exception> {
+        self.handler.as_deref().ok_or(Exception::IllegalFunction)
+    }
+}
+
+/// Turn the registers written to `entry` into a command for its point.
+async fn deliver(
+    handler: &dyn ModbusCommandHandler,
+    entry: &RegisterMapping,
+    registers: &[u16],
+) -> std::result::Result<(), Exception> {
+    let result = if entry.is_control() {
+        let command = ControlCommand::latching(entry.point_id, registers[0] != 0);
+        handler.control(entry.channel_id, command).await
+    } else {
+        // 64-bit integers without an exact f64 are refused, not rounded
+        let value = decode_registers(registers, entry.format, entry.byte_order, None)
+            .and_then(|value| value.try_as_f64_exact())
+            .map_err(|_| Exception::IllegalDataValue)?;
+        let command = AdjustmentCommand::new(entry.point_id, value);
+        handler.adjustment(entry.channel_id, command).await
+    };
+    result.map_err(|e| Exception::DeviceFailure(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::data::{DataBatch, DataPoint};
+    use crate::store::MemoryStore;
+
+    fn config() -> ModbusServerConfig {
+        serde_json::from_value(serde_json::json!({
+            "bind": "127.0.0.1:0",
+            "unit_id": 1,
+            "registers": [
+                { "address": 0, "channel_id": 1, "point_id": 10, "format": "float32", "byte_order": "CDAB" },
+                { "address": 3, "channel_id": 1, "point_id": 11 },
+                { "address": 4, "channel_id": 2, "point_id": 20, "format": "bool" },
+                { "area": "input", "address": 0, "channel_id": 2, "point_id": 21, "format": "int16" },
+                { "area": "coil", "address": 2, "channel_id": 2, "point_id": 20 }
+            ]
+        }))
+        .unwrap()
+    }
+
+    async fn store() -> Arc<MemoryStore> {
+        let store = Arc::new(MemoryStore::new());
+        let first = DataBatch::from_points(vec![
+            DataPoint::new(10, 1.5),
+            DataPoint::new(11, Value::Integer(7)),
+        ]);
+        let second = DataBatch::from_points(vec![
+            DataPoint::new(20, true),
+            DataPoint::new(21, Value::Integer(-2)),
+        ]);
+        store.write_batch(1, &first).await.unwrap();
+        store.write_batch(2, &second).await.unwrap();
+        store
+    }
+
+    /// Records commands as `(channel, point, value)`.
+    #[derive(Default)]
+    struct Recorder {
+        commands: Mutex<Vec<(u32, PointId, f64)>>,
+    }
+
+    #[async_trait]
+    impl ModbusCommandHandler for Recorder {
+        async fn control(&self, channel_id: u32, command: ControlCommand) -> Result<()> {
+            let value = if command.value { 1.0 } else { 0.0 };
+            lock(&self.commands).push((channel_id, command.id, value));
+            Ok(())
+        }
+
+        async fn adjustment(&self, channel_id: u32, command: AdjustmentCommand) -> Result<()> {
+            lock(&self.commands).push((channel_id, command.id, command.value));
+            Ok(())
+        }
+    }
+
+    /// Send one request, return the response PDU.
+    async fn request(stream: &mut TcpStream, unit: u8, pdu: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0, 7, 0, 0, 0, pdu.len() as u8 + 1, unit];
+        frame.extend_from_slice(pdu);
+        stream.write_all(&frame).await.unwrap();
+        let mut header = [0u8; 7];
+        stream.read_exact(&mut header).await.unwrap();
+        assert_eq!(header[..2], [0, 7]);
+        let mut response = vec![0u8; usize::from(header[5]) - 1];
+        stream.read_exact(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn test_reads() {
+        let mut server = ModbusServer::new(&config(), store().await);
+        server.listen("127.0.0.1:0").await.unwrap();
+        let addr = server.local_addr().unwrap();
+        let mut first = TcpStream::connect(addr).await.unwrap();
+        let mut second = TcpStream::connect(addr).await.unwrap();
+
+        // 1.5f32 = 0x3FC00000 in CDAB order, a gap at 2, then 7 and true
+        assert_eq!(
+            request(&mut first, 1, &[0x03, 0, 0, 0, 5]).await,
+            vec![0x03, 10, 0x00, 0x00, 0x3F, 0xC0, 0, 0, 0, 7, 0, 1]
+        );
+        // Second half of the float only
+        assert_eq!(
+            request(&mut second, 1, &[0x03, 0, 1, 0, 1]).await,
+            vec![0x03, 2, 0x3F, 0xC0]
+        );
+        assert_eq!(
+            request(&mut second, 1, &[0x04, 0, 0, 0, 1]).await,
+            vec![0x04, 2, 0xFF, 0xFE]
+        );
+        assert_eq!(
+            request(&mut second, 1, &[0x01, 0, 0, 0, 4]).await,
+            vec![0x01, 1, 0b0100]
+        );
+
+        assert_eq!(
+            request(&mut second, 1, &[0x03, 0, 10, 0, 2]).await,
+            vec![0x83, 0x02]
+        );
+        assert_eq!(
+            request(&mut second, 1, &[0x03, 0, 0, 0, 0]).await,
+            vec![0x83, 0x03]
+        );
+        assert_eq!(request(&mut second, 1, &[0x2B]).await, vec![0xAB, 0x01]);
+        assert_eq!(
+            request(&mut second, 2, &[0x03, 0, 0, 0, 1]).await,
+            vec![0x83, 0x0B]
+        );
+        // No command handler
+        assert_eq!(
+            request(&mut second, 1, &[0x06, 0, 3, 0, 1]).await,
+            vec![0x86, 0x01]
+        );
+
+        assert_eq!(server.connected_clients(), 2);
+        let clients = server.clients();
+        let counts: Vec<(u64, u64)> = clients.iter().map(|c| (c.requests, c.exceptions)).collect();
+        let mut expected = vec![(1, 0), (8, 5)];
+        if clients[0].peer != first.local_addr().unwrap() {
+            expected.reverse();
+        }
+        assert_eq!(counts, expected);
+
+        let diagnostics = server.diagnostics().await.unwrap();
+        assert_eq!(diagnostics.connection_state, ConnectionState::Connected);
+        assert_eq!((diagnostics.read_count, diagnostics.error_count), (4, 5));
+        assert_eq!(diagnostics.extra["clients"].as_array().unwrap().len(), 2);
+
+        drop(first);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(server.connected_clients(), 1);
+
+        server.stop().await.unwrap();
+        assert_eq!(server.connection_state(), ConnectionState::Disconnected);
+        assert_eq!(server.connected_clients(), 0);
+        let mut buf = [0u8; 1];
+        assert_eq!(second.read(&mut buf).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_writes() {
+        let recorder = Arc::new(Recorder::default());
+        let mut server = ModbusServer::new(&config(), store().await)
+            .with_command_handler(Arc::clone(&recorder) as Arc<dyn ModbusCommandHandler>);
+        server.listen("127.0.0.1:0").await.unwrap();
+        let mut stream = TcpStream::connect(server.local_addr().unwrap())
+            .await
+            .unwrap();
+
+        let coil = [0x05, 0, 2, 0xFF, 0x00];
+        assert_eq!(request(&mut stream, 1, &coil).await, coil);
+        let register = [0x06, 0, 3, 0x01, 0x2C];
+        assert_eq!(request(&mut stream, 1, &register).await, register);
+        // -2.5f32 = 0xC0200000 in CDAB order
+        let float = [0x10, 0, 0, 0, 2, 4, 0x00, 0x00, 0xC0, 0x20];
+        assert_eq!(request(&mut stream, 1, &float).await, float[..5]);
+        let multiple = [0x10, 0, 3, 0, 2, 4, 0, 9, 0, 0];
+        assert_eq!(request(&mut stream, 1, &multiple).await, multiple[..5]);
+        assert_eq!(
+            *lock(&recorder.commands),
+            vec![
+                (2, 20, 1.0),
+                (1, 11, 300.0),
+                (1, 10, -2.5),
+                (1, 11, 9.0),
+                (2, 20, 0.0)
+            ]
+        );
+
+        // Half a float, a gap, a bad coil value, a byte count mismatch
+        assert_eq!(
+            request(&mut stream, 1, &[0x06, 0, 1, 0, 1]).await,
+            vec![0x86, 0x02]
+        );
+        assert_eq!(
+            request(&mut stream, 1, &[0x10, 0, 1, 0, 2, 4, 0, 0, 0, 0]).await,
+            vec![0x90, 0x02]
+        );
+        assert_eq!(
+            request(&mut stream, 1, &[0x05, 0, 2, 0x12, 0x34]).await,
+            vec![0x85, 0x03]
+        );
+        assert_eq!(
+            request(&mut stream, 1, &[0x10, 0, 3, 0, 1, 4, 0, 1]).await,
+            vec![0x90, 0x03]
+        );
+        assert_eq!(lock(&recorder.commands).len(), 5);
+
+        let diagnostics = server.diagnostics().await.unwrap();
+        assert_eq!((diagnostics.write_count, diagnostics.error_count), (4, 4));
+        assert!(diagnostics
+            .last_error
+            .unwrap()
+            .ends_with("FC10: illegal data value"));
+    }
+
+    #[test]
+    fn test_ensure_accepted() {
+        assert!(ensure_accepted(WriteResult::success(1), 7).is_ok());
+        let refused = WriteResult {
+            failures: vec![(7, "5000 is outside the allowed range 0 to 100".into())],
+            ..WriteResult::success(0)
+        };
+        assert_eq!(
+            ensure_accepted(refused, 7).unwrap_err().to_string(),
+            "Protocol error: point 7: 5000 is outside the allowed range 0 to 100"
+        );
+        assert!(ensure_accepted(WriteResult::success(0), 7).is_err());
+    }
+}
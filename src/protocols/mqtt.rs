@@ -0,0 +1,892 @@
+//! MQTT subscriber channel.
+//!
+//! `MqttChannel` turns values devices publish to an MQTT broker into data
+//! points. Each point names the topic its value arrives on and, for JSON
+//! payloads, where in the payload the value is ([`MqttAddress`]):
+//!
+//! | Address | Value |
+//! |---------|-------|
+//! | `"site/meter"` | The whole payload (`21.5`, `true`, `"ON"`) |
+//! | `"site/env:/sensors/0/temp"` | JSON pointer into the payload |
+//! | `"site/env:sensors.0.temp"` | Dotted path into the payload |
+//!
+//! Payloads that are not valid JSON are taken as text. Received values pass
+//! through the point's transform and are stamped with the time the message
+//! arrived. Topics are (re)subscribed on every connection to the broker.
+//!
+//! The channel is event-driven. When the broker connection drops, every
+//! point received so far is re-published with `Quality::LastKnown` and the
+//! client keeps reconnecting on its own, so the gateway sees the points go
+//! stale rather than the channel go down.
+//!
+//! Commands are published as JSON (`true`/`false` for control commands, the
+//! reverse-transformed number for adjustments) on the point's command topic.
+//! MQTT has no command confirmation, so writes report `CommandStage::Accepted`.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use igw::prelude::*;
+//! use igw::protocols::mqtt::{MqttChannel, MqttChannelConfig};
+//!
+//! let points = vec![PointConfig::new(
+//!     1,
+//!     ProtocolAddress::Mqtt(MqttAddress::new("site/env").with_json_path("/temp")),
+//! )];
+//! let config = MqttChannelConfig::new("broker.local", 1883)
+//!     .with_credentials("igw", "secret")
+//!     .with_points(points);
+//!
+//! let mut channel = MqttChannel::new(config);
+//! let mut rx = channel.subscribe();
+//! channel.connect().await?;
+//! while let Some(event) = rx.recv().await {
+//!     if let DataEvent::DataUpdate(batch) = event { /* store */ }
+//! }
+//! ```
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use chrono::Utc;
+use rumqttc::{AsyncClient, Event, EventLoop, MqttOptions, Outgoing, Packet, Publish, QoS};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+use crate::core::data::{DataBatch, DataPoint, PointId, Value};
+use crate::core::diagnostics::DiagnosticsRecorder;
+use crate::core::error::{GatewayError, Result};
+use crate::core::metadata::{DriverMetadata, HasMetadata, ParameterMetadata, ParameterType};
+use crate::core::point::{MqttAddress, PointConfig, ProtocolAddress};
+use crate::core::quality::Quality;
+use crate::core::traits::{
+    AdjustmentCommand, CommandStage, CommunicationMode, ConnectionState, ControlCommand, DataEvent,
+    DataEventHandler, DataEventReceiver, Diagnostics, EventBus, EventDrivenProtocol, PollResult,
+    Protocol, ProtocolCapabilities, ProtocolClient, WriteResult,
+};
+
+/// Pause between reconnect attempts after the broker connection dropped.
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+
+/// Capacity of the client's request queue.
+const REQUEST_CAPACITY: usize = 64;
+
+/// MQTT channel configuration.
+#[derive(Debug, Clone)]
+pub struct MqttChannelConfig {
+    /// Broker host name or IP address.
+    pub host: String,
+    /// Broker port.
+    pub port: u16,
+    /// Client identifier; must be unique per broker.
+    pub client_id: String,
+    /// Username (optional).
+    pub username: Option<String>,
+    /// Password (optional, used with `username`).
+    pub password: Option<String>,
+    /// Keep-alive interval (zero or at least one second).
+    pub keep_alive: Duration,
+    /// How long `connect()` waits for the broker to accept the connection.
+    pub connect_timeout: Duration,
+    /// QoS of subscriptions and published commands (0, 1 or 2).
+    pub qos: u8,
+    /// Point configurations.
+    pub points: Vec<PointConfig>,
+}
+
+impl MqttChannelConfig {
+    /// Create a configuration for the broker at `host:port`.
+    pub fn new(host: impl Into<String>, port: u16) -> Self {
+        Self {
+            host: host.into(),
+            port,
+            client_id: "igw".to_string(),
+            username: None,
+            password: None,
+            keep_alive: Duration::from_secs(30),
+            connect_timeout: Duration::from_secs(10),
+            qos: 1,
+            points: Vec::new(),
+        }
+    }
+
+    /// Set the client identifier.
+    pub fn with_client_id(mut self, client_id: impl Into<String>) -> Self {
+        self.client_id = client_id.into();
+        self
+    }
+
+    /// Log in with username and password.
+    pub fn with_credentials(
+        mut self,
+        username: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Self {
+        self.username = Some(username.into());
+        self.password = Some(password.into());
+        self
+    }
+
+    /// Set the keep-alive interval.
+    pub fn with_keep_alive(mut self, keep_alive: Duration) -> Self {
+        self.keep_alive = keep_alive;
+        self
+    }
+
+    /// Set the connect timeout.
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Set the QoS of subscriptions and commands.
+    pub fn with_qos(mut self, qos: u8) -> Self {
+        self.qos = qos;
+        self
+    }
+
+    /// Set point configurations.
+    pub fn with_points(mut self, points: Vec<PointConfig>) -> Self {
+        self.points = points;
+        self
+    }
+
+    /// Points with an MQTT address, grouped by topic.
+    fn topics(&self) -> HashMap<String, Vec<(PointConfig, MqttAddress)>> {
+        let mut topics: HashMap<String, Vec<_>> = HashMap::new();
+        for point in &self.points {
+            if let ProtocolAddress::Mqtt(address) = &point.address {
+                topics
+                    .entry(address.topic.clone())
+                    .or_default()
+                    .push((point.clone(), address.clone()));
+            }
+        }
+        topics
+    }
+}
+
+pub use crate::gateway::params::MqttParamsConfig;
+
+impl MqttParamsConfig {
+    /// Convert to MqttChannelConfig for channel `channel_id`.
+    ///
+    /// Note: Points must be set separately via `with_points()`.
+    pub fn to_config(&self, channel_id: u32) -> MqttChannelConfig {
+        let client_id = self
+            .client_id
+            .clone()
+            .unwrap_or_else(|| format!("igw-{}", channel_id));
+        let mut config = MqttChannelConfig::new(&self.host, self.port)
+            .with_client_id(client_id)
+            .with_keep_alive(Duration::from_secs(self.keep_alive_secs))
+            .with_connect_timeout(Duration::from_millis(self.connect_timeout_ms))
+            .with_qos(self.qos);
+        if let Some(username) = &self.username {
+            config.username = Some(username.clone());
+            config.password = self.password.clone();
+        }
+        config
+    }
+}
+
+/// MQTT subscriber channel.
+///
+/// Implements `ProtocolClient` (connect, commands) and
+/// `EventDrivenProtocol` (received values).
+pub struct MqttChannel {
+    /// Configuration.
+    config: MqttChannelConfig,
+    /// Client handle of the current connection.
+    client: Option<AsyncClient>,
+    /// Task driving the connection.
+    task: Option<JoinHandle<()>>,
+    /// Connection state.
+    state: Arc<RwLock<ConnectionState>>,
+    /// Diagnostics.
+    diagnostics: Arc<DiagnosticsRecorder>,
+    /// Broadcast sender for event-driven subscribers.
+    event_bus: EventBus,
+    /// Event handler.
+    event_handler: Option<Arc<dyn DataEventHandler>>,
+}
+
+impl MqttChannel {
+    /// Create a new MQTT channel.
+    pub fn new(config: MqttChannelConfig) -> Self {
+        Self {
+            config,
+            client: None,
+            task: None,
+            state: Arc::new(RwLock::new(ConnectionState::Disconnected)),
+            diagnostics: Arc::new(DiagnosticsRecorder::new()),
+            event_bus: EventBus::default(),
+            event_handler: None,
+        }
+    }
+
+    fn get_state(&self) -> ConnectionState {
+        self.state
+            .read()
+            .map(|s| *s)
+            .unwrap_or(ConnectionState::Error)
+    }
+
+    fn qos(&self) -> Result<QoS> {
+        rumqttc::qos(self.config.qos).map_err(|_| {
+            GatewayError::Config(format!(
+                "MQTT qos must be 0, 1 or 2, got {}",
+                self.config.qos
+            ))
+        })
+    }
+
+    /// Command topic of a point.
+    fn command_topic(&self, id: PointId) -> std::result::Result<(&PointConfig, &str), String> {
+        let point = self
+            .config
+            .points
+            .iter()
+            .find(|p| p.id == id)
+            .ok_or("Point not found")?;
+        match &point.address {
+            ProtocolAddress::Mqtt(MqttAddress {
+                command_topic: Some(topic),
+                ..
+            }) => Ok((point, topic)),
+            ProtocolAddress::Mqtt(_) => Err("Point has no command topic".into()),
+            _ => Err("Invalid address type".into()),
+        }
+    }
+
+    /// Publish `payload` on the command topic of point `id`.
+    async fn publish_command(
+        &self,
+        id: PointId,
+        payload: impl FnOnce(&PointConfig) -> std::result::Result<serde_json::Value, String>,
+    ) -> std::result::Result<(), String> {
+        let client = self.client.as_ref().ok_or("Not connected")?;
+        let (point, topic) = self.command_topic(id)?;
+        let payload = payload(point)?;
+        let qos = self.qos().map_err(|e| e.to_string())?;
+        client
+            .publish(topic, qos, false, payload.to_string())
+            .await
+            .map_err(|e| format!("Publish failed: {}", e))
+    }
+
+    fn write_result(&self, success_count: usize, failures: Vec<(PointId, String)>) -> WriteResult {
+        self.diagnostics.record_write(success_count as u64);
+        for (id, error) in &failures {
+            #[cfg(feature = "tracing-support")]
+            tracing::warn!(point_id = id, error = %error, "MQTT command not published");
+            self.diagnostics
+                .record_error(format!("point {}: {}", id, error));
+        }
+        WriteResult {
+            success_count,
+            failures,
+            stage: CommandStage::Accepted,
+        }
+    }
+}
+
+impl Drop for MqttChannel {
+    fn drop(&mut self) {
+        if let Some(task) = &self.task {
+            task.abort();
+        }
+    }
+}
+
+impl ProtocolCapabilities for MqttChannel {
+    fn name(&self) -> &'static str {
+        "MQTT"
+    }
+
+    fn supported_modes(&self) -> &[CommunicationMode] {
+        &[CommunicationMode::EventDriven]
+    }
+}
+
+impl Protocol for MqttChannel {
+    fn connection_state(&self) -> ConnectionState {
+        self.get_state()
+    }
+
+    async fn diagnostics(&self) -> Result<Diagnostics> {
+        let mut diag = self
+            .diagnostics
+            .to_diagnostics(self.name(), self.get_state());
+        diag.extra = serde_json::json!({
+            "broker": format!("{}:{}", self.config.host, self.config.port),
+            "client_id": self.config.client_id,
+            "topics": self.config.topics().len(),
+            "points_configured": self.config.points.len(),
+        });
+        Ok(diag)
+    }
+}
+
+impl ProtocolClient for MqttChannel {
+    async fn connect(&mut self) -> Result<()> {
+        self.disconnect().await?;
+        let qos = self.qos()?;
+        *self.state.write().unwrap_or_else(|e| e.into_inner()) = ConnectionState::Connecting;
+
+        let mut options =
+            MqttOptions::new(&self.config.client_id, &self.config.host, self.config.port);
+        options.set_keep_alive(self.config.keep_alive);
+        if let Some(username) = &self.config.username {
+            options.set_credentials(username, self.config.password.as_deref().unwrap_or(""));
+        }
+        let (client, eventloop) = AsyncClient::new(options, REQUEST_CAPACITY);
+
+        let (ready, connected) = oneshot::channel();
+        let session = Session {
+            eventloop,
+            client: client.clone(),
+            qos,
+            topics: self.config.topics(),
+            state: Arc::clone(&self.state),
+            diagnostics: Arc::clone(&self.diagnostics),
+            event_bus: self.event_bus.clone(),
+            event_handler: self.event_handler.clone(),
+            last: HashMap::new(),
+            ready: Some(ready),
+        };
+        let mut task = tokio::spawn(session.run());
+
+        let timeout = self.config.connect_timeout;
+        let result = match tokio::time::timeout(timeout, connected).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(_)) => Err(GatewayError::connection("MQTT session ended")),
+            Err(_) => Err(GatewayError::ConnectionTimeout(timeout.as_millis() as u64)),
+        };
+        if let Err(e) = result {
+            task.abort();
+            let _ = (&mut task).await;
+            *self.state.write().unwrap_or_else(|e| e.into_inner()) = ConnectionState::Error;
+            self.diagnostics.record_error(e.to_string());
+            return Err(e);
+        }
+
+        self.client = Some(client);
+        self.task = Some(task);
+        Ok(())
+    }
+
+    async fn disconnect(&mut self) -> Result<()> {
+        if let Some(client) = self.client.take() {
+            let _ = client.try_disconnect();
+        }
+        if let Some(mut task) = self.task.take() {
+            // The session ends once the DISCONNECT is sent
+            if tokio::time::timeout(Duration::from_millis(500), &mut task)
+                .await
+                .is_err()
+            {
+                task.abort();
+            }
+        }
+        *self.state.write().unwrap_or_else(|e| e.into_inner()) = ConnectionState::Disconnected;
+        Ok(())
+    }
+
+    async fn write_control(&mut self, commands: &[ControlCommand]) -> Result<WriteResult> {
+        let mut success_count = 0;
+        let mut failures = Vec::new();
+        for cmd in commands {
+            let payload = |point: &PointConfig| {
+                Ok(serde_json::Value::Bool(
+                    point.transform.apply_bool(cmd.value),
+                ))
+            };
+            match self.publish_command(cmd.id, payload).await {
+                Ok(()) => success_count += 1,
+                Err(e) => failures.push((cmd.id, e)),
+            }
+        }
+        Ok(self.write_result(success_count, failures))
+    }
+
+    async fn write_adjustment(&mut self, adjustments: &[AdjustmentCommand]) -> Result<WriteResult> {
+        let mut success_count = 0;
+        let mut failures = Vec::new();
+        for adj in adjustments {
+            let payload = |point: &PointConfig| {
+                let raw = point
+                    .transform
+                    .reverse_apply(adj.value)
+                    .map_err(|e| e.to_string())?;
+                Ok(serde_json::json!(raw))
+            };
+            match self.publish_command(adj.id, payload).await {
+                Ok(()) => success_count += 1,
+                Err(e) => failures.push((adj.id, e)),
+            }
+        }
+        Ok(self.write_result(success_count, failures))
+    }
+
+    async fn poll_once(&mut self) -> PollResult {
+        // Values arrive as published messages, nothing to poll
+        PollResult::success(DataBatch::new())
+    }
+}
+
+impl EventDrivenProtocol for MqttChannel {
+    fn subscribe(&self) -> DataEventReceiver {
+        self.event_bus.subscribe()
+    }
+
+    fn set_event_handler(&mut self, handler: Arc<dyn DataEventHandler>) {
+        self.event_handler = Some(handler);
+    }
+
+    /// Topics are subscribed on every connection to the broker, so there is
+    /// nothing left to start.
+    async fn start(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// State of the task driving a broker connection.
+struct Session {
+    eventloop: EventLoop,
+    client: AsyncClient,
+    qos: QoS,
+    /// Points by topic.
+    topics: HashMap<String, Vec<(PointConfig, MqttAddress)>>,
+    state: Arc<RwLock<ConnectionState>>,
+    diagnostics: Arc<DiagnosticsRecorder>,
+    event_bus: EventBus,
+    event_handler: Option<Arc<dyn DataEventHandler>>,
+    /// Latest value of every point received, for marking them `LastKnown`.
+    last: HashMap<PointId, DataPoint>,
+    /// Reports the outcome of the first connection attempt to `connect()`.
+    ready: Option<oneshot::Sender<Result<()>>>,
+}
+
+impl Session {
+    async fn run(mut self) {
+        loop {
+            match self.eventloop.poll().await {
+                Ok(Event::Incoming(Packet::ConnAck(_))) => self.on_connected(),
+                Ok(Event::Incoming(Packet::Publish(publish))) => self.on_publish(publish).await,
+                Ok(Event::Outgoing(Outgoing::Disconnect)) => return,
+                Ok(_) => {}
+                Err(e) => {
+                    let error = GatewayError::connection(format!("MQTT broker: {}", e));
+                    if let Some(ready) = self.ready.take() {
+                        let _ = ready.send(Err(error));
+                        return;
+                    }
+                    self.on_connection_lost(error).await;
+                    tokio::time::sleep(RECONNECT_DELAY).await;
+                }
+            }
+        }
+    }
+
+    fn set_state(&self, state: ConnectionState) -> ConnectionState {
+        let mut current = self.state.write().unwrap_or_else(|e| e.into_inner());
+        std::mem::replace(&mut *current, state)
+    }
+
+    fn on_connected(&mut self) {
+        let filters = self
+            .topics
+            .keys()
+            .map(|topic| rumqttc::SubscribeFilter::new(topic.clone(), self.qos));
+        if !self.topics.is_empty() {
+            // Queued for the event loop; only fails if the queue is full
+            if let Err(e) = self.client.try_subscribe_many(filters) {
+                self.diagnostics
+                    .record_error(format!("MQTT subscribe failed: {}", e));
+            }
+        }
+        let _previous = self.set_state(ConnectionState::Connected);
+        #[cfg(feature = "tracing-support")]
+        if _previous == ConnectionState::Reconnecting {
+            tracing::info!(
+                broker = %self.eventloop.mqtt_options.broker_address().0,
+                "MQTT broker connection restored"
+            );
+        }
+        if let Some(ready) = self.ready.take() {
+            let _ = ready.send(Ok(()));
+        }
+    }
+
+    async fn on_publish(&mut self, publish: Publish) {
+        let Some(points) = self.topics.get(publish.topic.as_str()) else {
+            return;
+        };
+        let received = Utc::now();
+        let payload = parse_payload(&publish.payload);
+
+        let mut batch = DataBatch::new();
+        for (point, address) in points {
+            let Some(raw) = extract(&payload, address.json_path.as_deref()) else {
+                #[cfg(feature = "tracing-support")]
+                tracing::warn!(
+                    point_id = point.id,
+                    topic = %publish.topic,
+                    path = address.json_path.as_deref().unwrap_or_default(),
+                    "MQTT payload has no value for the point"
+                );
+                self.diagnostics.record_error(format!(
+                    "point {}: no value in payload on '{}'",
+                    point.id, publish.topic
+                ));
+                continue;
+            };
+            let (value, quality) = point.transform.apply_value(raw);
+            batch.add(DataPoint {
+                id: point.id,
+                value,
+                quality,
+                timestamp: received,
+                source_timestamp: None,
+            });
+        }
+        self.diagnostics
+            .record_bytes_received(publish.payload.len() as u64);
+        self.emit(batch).await;
+    }
+
+    /// Mark every received point `LastKnown` once the broker goes away.
+    async fn on_connection_lost(&mut self, error: GatewayError) {
+        if self.set_state(ConnectionState::Reconnecting) != ConnectionState::Connected {
+            return;
+        }
+        #[cfg(feature = "tracing-support")]
+        tracing::warn!(error = %error, "MQTT broker connection lost");
+        self.diagnostics.record_error(error.to_string());
+
+        let mut batch = DataBatch::new();
+        for point in self.last.values() {
+            batch.add(DataPoint {
+                quality: Quality::LastKnown,
+                ..point.clone()
+            });
+        }
+        self.emit(batch).await;
+    }
+
+    async fn emit(&mut self, batch: DataBatch) {
+        if batch.is_empty() {
+            return;
+        }
+        for point in batch.iter() {
+            self.last.insert(point.id, point.clone());
+        }
+        self.diagnostics.record_read(batch.len() as u64);
+
+        let batch = Arc::new(batch);
+        self.event_bus
+            .publish(DataEvent::DataUpdate(Arc::clone(&batch)));
+        if let Some(handler) = &self.event_handler {
+            handler.on_data_update(batch).await;
+        }
+    }
+}
+
+/// Parse a payload as JSON, or take it as text if it is not.
+fn parse_payload(payload: &[u8]) -> serde_json::Value {
+    serde_json::from_slice(payload).unwrap_or_else(|_| {
+        serde_json::Value::String(String::from_utf8_lossy(payload).trim().to_string())
+    })
+}
+
+/// Value at `path` (JSON pointer or dotted path) of a payload, the payload
+/// itself without a path.
+fn extract(payload: &serde_json::Value, path: Option<&str>) -> Option<Value> {
+    let found = match path {
+        None => Some(payload),
+        Some(pointer) if pointer.starts_with('/') => payload.pointer(pointer),
+        Some(path) => path.split('.').try_fold(payload, |node, key| match node {
+            serde_json::Value::Array(items) => items.get(key.parse::<usize>().ok()?),
+            _ => node.get(key),
+        }),
+    }?;
+    json_to_value(found)
+}
+
+/// Convert a JSON scalar or array; `None` for null and objects.
+fn json_to_value(json: &serde_json::Value) -> Option<Value> {
+    Some(match json {
+        serde_json::Value::Bool(b) => Value::Bool(*b),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => Value::Integer(i),
+            None => Value::Float(n.as_f64()?),
+        },
+        serde_json::Value::String(s) => Value::String(s.clone()),
+        serde_json::Value::Array(items) => {
+            Value::Array(items.iter().map(json_to_value).collect::<Option<_>>()?)
+        }
+        serde_json::Value::Null | serde_json::Value::Object(_) => return None,
+    })
+}
+
+// ============================================================================
+// HasMetadata Implementation
+// ============================================================================
+
+impl HasMetadata for MqttChannel {
+    fn metadata() -> DriverMetadata {
+        DriverMetadata {
+            name: "mqtt",
+            display_name: "MQTT",
+            description: "MQTT subscriber for devices publishing values to a broker.",
+            is_recommended: true,
+            example_config: serde_json::json!({
+                "host": "broker.local",
+                "port": 1883,
+                "username": "igw",
+                "password": "secret",
+                "qos": 1
+            }),
+            parameters: vec![
+                ParameterMetadata::required(
+                    "host",
+                    "Broker Host",
+                    "MQTT broker host name or IP address",
+                    ParameterType::String,
+                ),
+                ParameterMetadata::optional(
+                    "port",
+                    "Broker Port",
+                    "MQTT broker port",
+                    ParameterType::Integer,
+                    serde_json::json!(1883),
+                ),
+                ParameterMetadata::optional(
+                    "client_id",
+                    "Client ID",
+                    "Client identifier, unique per broker (default: igw-<channel id>)",
+                    ParameterType::String,
+                    serde_json::Value::Null,
+                ),
+                ParameterMetadata::optional(
+                    "username",
+                    "Username",
+                    "Username for authentication",
+                    ParameterType::String,
+                    serde_json::Value::Null,
+                ),
+                ParameterMetadata::optional(
+                    "password",
+                    "Password",
+                    "Password for authentication",
+                    ParameterType::String,
+                    serde_json::Value::Null,
+                ),
+                ParameterMetadata::optional(
+                    "keep_alive_secs",
+                    "Keep Alive (s)",
+                    "Keep-alive interval in seconds",
+                    ParameterType::Integer,
+                    serde_json::json!(30),
+                ),
+                ParameterMetadata::optional(
+                    "connect_timeout_ms",
+                    "Connect Timeout (ms)",
+                    "Connection timeout in milliseconds",
+                    ParameterType::Integer,
+                    serde_json::json!(10000),
+                ),
+                ParameterMetadata::optional(
+                    "qos",
+                    "QoS",
+                    "QoS of subscriptions and commands (0, 1 or 2)",
+                    ParameterType::Integer,
+                    serde_json::json!(1),
+                ),
+            ],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    #[test]
+    fn test_extract() {
+        let payload = parse_payload(br#"{"env": {"temp": 21.5}, "cells": [3, 4], "on": true}"#);
+        assert_eq!(
+            extract(&payload, Some("/env/temp")),
+            Some(Value::Float(21.5))
+        );
+        assert_eq!(
+            extract(&payload, Some("env.temp")),
+            Some(Value::Float(21.5))
+        );
+        assert_eq!(extract(&payload, Some("cells.1")), Some(Value::Integer(4)));
+        assert_eq!(extract(&payload, Some("on")), Some(Value::Bool(true)));
+        assert_eq!(
+            extract(&payload, Some("/cells")),
+            Some(Value::Array(vec![Value::Integer(3), Value::Integer(4)]))
+        );
+        assert_eq!(extract(&payload, Some("env")), None);
+        assert_eq!(extract(&payload, Some("missing.temp")), None);
+
+        assert_eq!(
+            extract(&parse_payload(b"42"), None),
+            Some(Value::Integer(42))
+        );
+        assert_eq!(
+            extract(&parse_payload(b"ON\n"), None),
+            Some(Value::String("ON".into()))
+        );
+    }
+
+    #[test]
+    fn test_params() {
+        let params: MqttParamsConfig =
+            serde_json::from_value(serde_json::json!({ "host": "broker", "username": "igw" }))
+                .unwrap();
+        let config = params.to_config(7);
+        assert_eq!((config.host.as_str(), config.port), ("broker", 1883));
+        assert_eq!(config.client_id, "igw-7");
+        assert_eq!(config.username.as_deref(), Some("igw"));
+        assert_eq!(config.qos, 1);
+    }
+
+    /// Read one MQTT packet: first header byte and body.
+    async fn read_packet(stream: &mut TcpStream) -> (u8, Vec<u8>) {
+        let header = stream.read_u8().await.unwrap();
+        let (mut len, mut shift) = (0usize, 0);
+        loop {
+            let byte = stream.read_u8().await.unwrap();
+            len |= ((byte & 0x7F) as usize) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+        let mut body = vec![0; len];
+        stream.read_exact(&mut body).await.unwrap();
+        (header, body)
+    }
+
+    /// Topic and payload of a PUBLISH body.
+    fn split_publish(header: u8, body: &[u8]) -> (String, String) {
+        let topic_len = u16::from_be_bytes([body[0], body[1]]) as usize;
+        let topic = String::from_utf8(body[2..2 + topic_len].to_vec()).unwrap();
+        // QoS > 0 carries a packet identifier
+        let start = 2 + topic_len + if header & 0x06 != 0 { 2 } else { 0 };
+        (topic, String::from_utf8(body[start..].to_vec()).unwrap())
+    }
+
+    /// A broker that accepts one client, publishes `site/env` once and
+    /// hands the first command it receives back, then drops the client.
+    async fn fake_broker(listener: TcpListener, command: oneshot::Sender<(String, String)>) {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        assert_eq!(read_packet(&mut stream).await.0 >> 4, 1); // CONNECT
+        stream.write_all(&[0x20, 0x02, 0x00, 0x00]).await.unwrap();
+
+        let (header, body) = read_packet(&mut stream).await;
+        assert_eq!(header, 0x82); // SUBSCRIBE
+        stream
+            .write_all(&[0x90, 0x03, body[0], body[1], 0x01])
+            .await
+            .unwrap();
+
+        let (topic, payload) = ("site/env", br#"{"temp": 21.5, "hum": 40}"#);
+        let mut publish = vec![0x30, (2 + topic.len() + payload.len()) as u8, 0];
+        publish.push(topic.len() as u8);
+        publish.extend_from_slice(topic.as_bytes());
+        publish.extend_from_slice(payload);
+        stream.write_all(&publish).await.unwrap();
+
+        loop {
+            let (header, body) = read_packet(&mut stream).await;
+            if header >> 4 == 3 {
+                let _ = command.send(split_publish(header, &body));
+                return;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_channel() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (command_tx, command_rx) = oneshot::channel();
+        let broker = tokio::spawn(fake_broker(listener, command_tx));
+
+        let mqtt = |address: MqttAddress| ProtocolAddress::Mqtt(address);
+        let config = MqttChannelConfig::new("127.0.0.1", port).with_points(vec![
+            PointConfig::new(
+                1,
+                mqtt(MqttAddress::new("site/env").with_json_path("/temp")),
+            ),
+            PointConfig::new(2, mqtt(MqttAddress::new("site/env").with_json_path("hum"))),
+            PointConfig::new(
+                3,
+                mqtt(MqttAddress::new("relay/state").with_command_topic("relay/set")),
+            ),
+        ]);
+        let mut channel = MqttChannel::new(config);
+        let mut rx = channel.subscribe();
+        channel.connect().await.unwrap();
+        assert_eq!(channel.connection_state(), ConnectionState::Connected);
+
+        let Some(DataEvent::DataUpdate(batch)) = rx.recv().await else {
+            panic!("expected a data update");
+        };
+        assert_eq!(batch.get(1).unwrap().value, Value::Float(21.5));
+        assert_eq!(batch.get(2).unwrap().value, Value::Float(40.0));
+        assert_eq!(batch.get(1).unwrap().quality, Quality::Good);
+
+        let result = channel
+            .write_control(&[
+                ControlCommand::latching(3, true),
+                ControlCommand::latching(1, true),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(result.success_count, 1);
+        assert_eq!(result.stage, CommandStage::Accepted);
+        assert_eq!(result.failures[0].0, 1);
+        assert_eq!(
+            command_rx.await.unwrap(),
+            ("relay/set".to_string(), "true".to_string())
+        );
+
+        // The broker hangs up: the received points go stale
+        broker.await.unwrap();
+        let Some(DataEvent::DataUpdate(batch)) = rx.recv().await else {
+            panic!("expected a data update");
+        };
+        assert_eq!(batch.len(), 2);
+        assert!(batch.iter().all(|p| p.quality == Quality::LastKnown));
+        assert_eq!(batch.get(1).unwrap().value, Value::Float(21.5));
+        assert_eq!(channel.connection_state(), ConnectionState::Reconnecting);
+
+        channel.disconnect().await.unwrap();
+        assert_eq!(channel.connection_state(), ConnectionState::Disconnected);
+    }
+
+    #[tokio::test]
+    async fn test_connect_refused() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        drop(listener);
+
+        let mut channel = MqttChannel::new(MqttChannelConfig::new("127.0.0.1", port));
+        let error = channel.connect().await.unwrap_err();
+        assert!(error.needs_reconnect());
+        assert_eq!(channel.connection_state(), ConnectionState::Error);
+    }
+}
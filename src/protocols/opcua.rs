@@ -30,28 +30,39 @@
 //!     }
 //! }
 //! ```
+//!
+//! # Historical access
+//!
+//! [`OpcUaChannel::history_read()`] reads what a server with historical
+//! access recorded for a point's node (HistoryRead with
+//! ReadRawModifiedDetails), following continuation points. The gateway
+//! uses it to backfill the gap after an outage (see
+//! [`BackfillConfig`](crate::gateway::BackfillConfig)).
 
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
 
 use chrono::{DateTime, Utc};
-use opcua::client::{ClientBuilder, DataChangeCallback, IdentityToken, MonitoredItem, Session};
+use opcua::client::{
+    ClientBuilder, DataChangeCallback, HistoryReadAction, IdentityToken, MonitoredItem, Session,
+};
 use opcua::crypto::SecurityPolicy;
 use opcua::types::{
-    AttributeId, DataValue, Identifier, MessageSecurityMode, MonitoredItemCreateRequest, NodeId,
-    StatusCode, TimestampsToReturn, UAString, UserTokenPolicy, Variant, WriteValue,
+    AttributeId, ByteString, DataValue, HistoryData, HistoryReadValueId, Identifier,
+    MessageSecurityMode, MonitoredItemCreateRequest, NodeId, QualifiedName, ReadRawModifiedDetails,
+    ReadValueId, StatusCode, TimestampsToReturn, UAString, UserTokenPolicy, Variant, WriteValue,
 };
-use tokio::sync::{broadcast, RwLock};
+use tokio::sync::RwLock;
 
 use crate::core::data::{DataBatch, DataPoint, Value};
 use crate::core::error::{GatewayError, Result};
 use crate::core::point::{PointConfig, ProtocolAddress};
 use crate::core::quality::Quality;
 use crate::core::traits::{
-    AdjustmentCommand, CommunicationMode, ConnectionState, ControlCommand, DataEvent,
-    DataEventHandler, DataEventReceiver, DataEventSender, Diagnostics, EventDrivenProtocol,
-    PollResult, Protocol, ProtocolCapabilities, ProtocolClient, WriteResult,
+    AdjustmentCommand, CommandStage, CommunicationMode, ConnectionState, ControlCommand, DataEvent,
+    DataEventHandler, DataEventReceiver, Diagnostics, EventBus, EventDrivenProtocol, PollResult,
+    Protocol, ProtocolCapabilities, ProtocolClient, WriteResult,
 };
 
 /// OPC UA security policy.
@@ -390,82 +401,7 @@ impl OpcUaChannelConfig {
     }
 }
 
-/// OPC UA channel parameters for JSON configuration.
-///
-/// This is a serde-friendly version of the configuration that can be
-/// deserialized from JSON and converted to `OpcUaChannelConfig`.
-///
-/// # Example JSON
-///
-/// ```json
-/// {
-///     "endpoint_url": "opc.tcp://192.168.1.100:4840",
-///     "username": "user",
-///     "password": "pass",
-///     "trust_server_certs": true
-/// }
-/// ```
-#[derive(Debug, Clone, serde::Deserialize)]
-pub struct OpcUaParamsConfig {
-    /// OPC UA server endpoint URL
-    pub endpoint_url: String,
-
-    /// Application name (optional)
-    #[serde(default = "default_app_name")]
-    pub application_name: String,
-
-    /// Username for authentication (optional, for username/password mode)
-    #[serde(default)]
-    pub username: Option<String>,
-
-    /// Password for authentication (optional)
-    #[serde(default)]
-    pub password: Option<String>,
-
-    /// Connection timeout in milliseconds
-    #[serde(default = "default_opcua_connect_timeout")]
-    pub connect_timeout_ms: u64,
-
-    /// Session timeout in milliseconds
-    #[serde(default = "default_session_timeout")]
-    pub session_timeout_ms: u64,
-
-    /// Whether to trust server certificates
-    #[serde(default = "default_trust_certs")]
-    pub trust_server_certs: bool,
-
-    /// Publishing interval in milliseconds for subscription
-    #[serde(default = "default_publishing_interval")]
-    pub publishing_interval_ms: u64,
-
-    /// Sampling interval in milliseconds for monitored items
-    #[serde(default = "default_sampling_interval")]
-    pub sampling_interval_ms: u64,
-}
-
-fn default_app_name() -> String {
-    "igw OPC UA Client".to_string()
-}
-
-fn default_opcua_connect_timeout() -> u64 {
-    10000
-}
-
-fn default_session_timeout() -> u64 {
-    60000
-}
-
-fn default_trust_certs() -> bool {
-    true
-}
-
-fn default_publishing_interval() -> u64 {
-    1000
-}
-
-fn default_sampling_interval() -> u64 {
-    500
-}
+pub use crate::gateway::params::OpcUaParamsConfig;
 
 impl OpcUaParamsConfig {
     /// Convert to OpcUaChannelConfig.
@@ -536,7 +472,7 @@ pub struct OpcUaChannel {
     /// Diagnostics.
     diagnostics: Arc<RwLock<ChannelDiagnostics>>,
     /// Broadcast sender for event-driven subscribers (multiple subscribers supported).
-    event_tx: DataEventSender,
+    event_bus: EventBus,
     /// Event handler.
     event_handler: Option<Arc<dyn DataEventHandler>>,
     /// Current subscription ID.
@@ -546,15 +482,14 @@ pub struct OpcUaChannel {
 impl OpcUaChannel {
     /// Create a new OPC UA channel.
     pub fn new(config: OpcUaChannelConfig) -> Self {
-        // Use broadcast channel for multiple subscribers
-        let (event_tx, _) = broadcast::channel(1024);
+        let event_bus = EventBus::default();
 
         Self {
             config,
             session: None,
             state: Arc::new(std::sync::RwLock::new(ConnectionState::Disconnected)),
             diagnostics: Arc::new(RwLock::new(ChannelDiagnostics::default())),
-            event_tx,
+            event_bus,
             event_handler: None,
             subscription_id: None,
         }
@@ -596,7 +531,7 @@ impl OpcUaChannel {
 
         // Create subscription with data change callback
         // Use Arc to avoid cloning the entire config on each callback invocation
-        let event_tx = self.event_tx.clone();
+        let event_bus = self.event_bus.clone();
         let diagnostics = self.diagnostics.clone();
         let config = Arc::new(self.config.clone()); // Clone once, wrap in Arc
         let event_handler = self.event_handler.clone();
@@ -611,7 +546,7 @@ impl OpcUaChannel {
                 sub_config.publishing_enabled,
                 DataChangeCallback::new(move |data_value: DataValue, item: &MonitoredItem| {
                     // Clone Arc (cheap reference count increment) instead of full config
-                    let event_tx = event_tx.clone();
+                    let event_bus = event_bus.clone();
                     let diagnostics = diagnostics.clone();
                     let config = Arc::clone(&config);
                     let event_handler = event_handler.clone();
@@ -624,7 +559,7 @@ impl OpcUaChannel {
                         handle_data_change(
                             &config,
                             &item_data,
-                            &event_tx,
+                            &event_bus,
                             &diagnostics,
                             event_handler.as_ref(),
                         )
@@ -662,7 +597,11 @@ impl OpcUaChannel {
             .filter_map(|point| {
                 if let ProtocolAddress::OpcUa(addr) = &point.address {
                     let node_id = parse_node_id(&addr.node_id, addr.namespace_index);
-                    Some(node_id.into())
+                    let mut request: MonitoredItemCreateRequest = node_id.into();
+                    if let Some(range) = &addr.index_range {
+                        request.item_to_monitor.index_range = UAString::from(range.as_str());
+                    }
+                    Some(request)
                 } else {
                     None
                 }
@@ -692,6 +631,57 @@ impl OpcUaChannel {
         Ok(count)
     }
 
+    /// Read the current value of every point's node and publish them like
+    /// data change notifications.
+    ///
+    /// Used to refresh all points without waiting for the subscription to
+    /// report changes. Returns the number of nodes read.
+    pub async fn read_all(&self) -> Result<usize> {
+        let session = self.session.as_ref().ok_or(GatewayError::NotConnected)?;
+
+        let nodes_to_read: Vec<ReadValueId> = self
+            .config
+            .points
+            .iter()
+            .filter_map(|point| {
+                if let ProtocolAddress::OpcUa(addr) = &point.address {
+                    let mut node: ReadValueId =
+                        parse_node_id(&addr.node_id, addr.namespace_index).into();
+                    if let Some(range) = &addr.index_range {
+                        node.index_range = UAString::from(range.as_str());
+                    }
+                    Some(node)
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        if nodes_to_read.is_empty() {
+            return Ok(0);
+        }
+
+        let values = session
+            .read(&nodes_to_read, TimestampsToReturn::Both, 0.0)
+            .await
+            .map_err(|e| GatewayError::Protocol(format!("Read failed: {}", e)))?;
+        let items: Vec<(NodeId, DataValue)> = nodes_to_read
+            .into_iter()
+            .map(|node| node.node_id)
+            .zip(values)
+            .collect();
+
+        handle_data_change(
+            &self.config,
+            &items,
+            &self.event_bus,
+            &self.diagnostics,
+            self.event_handler.as_ref(),
+        )
+        .await;
+        Ok(items.len())
+    }
+
     /// Write node values.
     async fn write_nodes(&self, write_values: Vec<WriteValue>) -> Result<Vec<StatusCode>> {
         let session = self.session.as_ref().ok_or(GatewayError::NotConnected)?;
@@ -701,8 +691,104 @@ impl OpcUaChannel {
             .await
             .map_err(|e| GatewayError::Protocol(format!("Write failed: {}", e)))
     }
+
+    /// Read the values the server recorded for a point's node between
+    /// `start` and `end`, oldest first.
+    ///
+    /// Issues HistoryRead with ReadRawModifiedDetails and follows the
+    /// continuation points until every value is returned. Values are
+    /// stamped with their source timestamp (the server timestamp if the
+    /// source gave none) and keep the quality of their status code; values
+    /// without either timestamp are skipped.
+    pub async fn history_read(
+        &self,
+        point_id: u32,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<DataPoint>> {
+        let session = self.session.as_ref().ok_or(GatewayError::NotConnected)?;
+        let point = self
+            .find_point(point_id)
+            .ok_or_else(|| GatewayError::PointNotFound(point_id.to_string()))?;
+        let ProtocolAddress::OpcUa(addr) = &point.address else {
+            return Err(GatewayError::InvalidAddress(format!(
+                "point {} has no OPC UA address",
+                point_id
+            )));
+        };
+
+        let details = ReadRawModifiedDetails {
+            is_read_modified: false,
+            start_time: start.into(),
+            end_time: end.into(),
+            num_values_per_node: 0,
+            return_bounds: false,
+        };
+        let mut node = HistoryReadValueId {
+            node_id: parse_node_id(&addr.node_id, addr.namespace_index),
+            index_range: addr
+                .index_range
+                .as_deref()
+                .map_or_else(UAString::null, UAString::from),
+            data_encoding: QualifiedName::null(),
+            continuation_point: ByteString::null(),
+        };
+
+        let mut points = Vec::new();
+        for _ in 0..MAX_HISTORY_REQUESTS {
+            let results = session
+                .history_read(
+                    HistoryReadAction::ReadRawModifiedDetails(details.clone()),
+                    TimestampsToReturn::Both,
+                    false,
+                    std::slice::from_ref(&node),
+                )
+                .await
+                .map_err(|e| GatewayError::Protocol(format!("HistoryRead failed: {}", e)))?;
+            let result = results.into_iter().next().ok_or_else(|| {
+                GatewayError::InvalidResponse("HistoryRead returned no result".into())
+            })?;
+            if result.status_code.is_bad() {
+                return Err(GatewayError::Protocol(format!(
+                    "HistoryRead of {} failed: {}",
+                    addr.node_id, result.status_code
+                )));
+            }
+
+            if let Some(data) = result.history_data.inner_as::<HistoryData>() {
+                points.extend(
+                    data.data_values
+                        .iter()
+                        .flatten()
+                        .filter_map(|dv| convert_history_value(point_id, Some(point), dv)),
+                );
+            }
+            if result.continuation_point.is_null() {
+                return Ok(points);
+            }
+            node.continuation_point = result.continuation_point;
+        }
+
+        // Let the server release the continuation point
+        let _ = session
+            .history_read(
+                HistoryReadAction::ReadRawModifiedDetails(details),
+                TimestampsToReturn::Both,
+                true,
+                std::slice::from_ref(&node),
+            )
+            .await;
+        Err(GatewayError::Protocol(format!(
+            "HistoryRead of {} did not finish within {} requests",
+            addr.node_id, MAX_HISTORY_REQUESTS
+        )))
+    }
 }
 
+/// Most HistoryRead requests per `history_read()`, so a server handing out
+/// continuation points endlessly cannot hold the channel.
+const MAX_HISTORY_REQUESTS: usize = 1000;
+
 impl ProtocolCapabilities for OpcUaChannel {
     fn name(&self) -> &'static str {
         "OPC UA"
@@ -742,6 +828,7 @@ impl Protocol for OpcUaChannel {
                 "points_configured": self.config.points.len(),
                 "last_data_received_secs_ago": diag.last_data_received.map(|t| t.elapsed().as_secs()),
             }),
+            ..Default::default()
         })
     }
 }
@@ -788,7 +875,7 @@ impl ProtocolClient for OpcUaChannel {
             .await
             .map_err(|e| {
                 self.set_state(ConnectionState::Error);
-                GatewayError::Connection(e.to_string())
+                GatewayError::connection_with(e.to_string(), e)
             })?;
 
         // Wait for connection to be established
@@ -799,7 +886,7 @@ impl ProtocolClient for OpcUaChannel {
 
         // Spawn event loop in background
         let state = self.state.clone();
-        let event_tx = self.event_tx.clone();
+        let event_bus = self.event_bus.clone();
         tokio::spawn(async move {
             let _handle = event_loop.spawn();
 
@@ -808,13 +895,13 @@ impl ProtocolClient for OpcUaChannel {
             // For now, we just keep running. In a production implementation,
             // we'd monitor the handle for completion.
             let _ = state;
-            let _ = event_tx;
+            let _ = event_bus;
         });
 
         // Send connection event
         let _ = self
-            .event_tx
-            .send(DataEvent::ConnectionChanged(ConnectionState::Connected));
+            .event_bus
+            .publish(DataEvent::ConnectionChanged(ConnectionState::Connected));
 
         Ok(())
     }
@@ -835,8 +922,8 @@ impl ProtocolClient for OpcUaChannel {
 
         // Send disconnect event
         let _ = self
-            .event_tx
-            .send(DataEvent::ConnectionChanged(ConnectionState::Disconnected));
+            .event_bus
+            .publish(DataEvent::ConnectionChanged(ConnectionState::Disconnected));
 
         Ok(())
     }
@@ -900,6 +987,7 @@ impl ProtocolClient for OpcUaChannel {
         Ok(WriteResult {
             success_count,
             failures,
+            stage: CommandStage::Confirmed,
         })
     }
 
@@ -968,6 +1056,7 @@ impl ProtocolClient for OpcUaChannel {
         Ok(WriteResult {
             success_count,
             failures,
+            stage: CommandStage::Confirmed,
         })
     }
 
@@ -994,7 +1083,7 @@ impl EventDrivenProtocol for OpcUaChannel {
     fn subscribe(&self) -> DataEventReceiver {
         // Broadcast channel supports multiple subscribers
         // Each call to subscribe() returns a new receiver that gets all future events
-        self.event_tx.subscribe()
+        self.event_bus.subscribe()
     }
 
     fn set_event_handler(&mut self, handler: Arc<dyn DataEventHandler>) {
@@ -1056,11 +1145,12 @@ fn parse_node_id(identifier: &str, namespace_index: u16) -> NodeId {
 async fn handle_data_change(
     config: &OpcUaChannelConfig,
     items: &[(NodeId, DataValue)],
-    event_tx: &DataEventSender,
+    event_bus: &EventBus,
     diagnostics: &Arc<RwLock<ChannelDiagnostics>>,
     event_handler: Option<&Arc<dyn DataEventHandler>>,
 ) {
     let mut batch = DataBatch::new();
+    tracing::debug!(items = items.len(), "Data change notification");
 
     for (node_id, data_value) in items {
         // Find point_id from NodeID
@@ -1082,8 +1172,18 @@ async fn handle_data_change(
         // Find point config
         let point_config = config.points.iter().find(|p| p.id == point_id);
 
-        if let Some(dp) = convert_data_value_with_id(point_id, point_config, data_value) {
-            batch.add(dp);
+        match convert_data_value_with_id(point_id, point_config, data_value) {
+            Some(dp) => batch.add(dp),
+            None => {
+                tracing::warn!(
+                    point_id,
+                    point = point_config
+                        .and_then(|p| p.name.as_deref())
+                        .unwrap_or_default(),
+                    node = %identifier,
+                    "Data change without a value"
+                );
+            }
         }
     }
 
@@ -1092,7 +1192,8 @@ async fn handle_data_change(
     }
 
     // Send event (service layer handles storage)
-    let _ = event_tx.send(DataEvent::DataUpdate(batch.clone()));
+    let batch = Arc::new(batch);
+    let _ = event_bus.publish(DataEvent::DataUpdate(Arc::clone(&batch)));
 
     // Call event handler
     if let Some(handler) = event_handler {
@@ -1128,17 +1229,18 @@ fn convert_data_value_with_id(
         .as_ref()
         .and_then(opcua_datetime_to_chrono);
 
-    // Apply transform if config is available
-    let final_value = if let Some(cfg) = config {
-        if let Some(f) = igw_value.as_f64() {
-            Value::Float(cfg.transform.apply(f))
-        } else if let Some(b) = igw_value.as_bool() {
-            Value::Bool(cfg.transform.apply_bool(b))
-        } else {
-            igw_value
+    // Apply transform and range check if config is available
+    let (final_value, quality) = match config {
+        Some(cfg) => {
+            let (value, range_quality) = cfg.transform.apply_value(igw_value);
+            let quality = if quality.is_good() {
+                range_quality
+            } else {
+                quality
+            };
+            (value, quality)
         }
-    } else {
-        igw_value
+        None => (igw_value, quality),
     };
 
     Some(DataPoint {
@@ -1150,6 +1252,23 @@ fn convert_data_value_with_id(
     })
 }
 
+/// Convert a DataValue read from history to a DataPoint stamped with its
+/// source (else server) timestamp; `None` without value or timestamp.
+fn convert_history_value(
+    point_id: u32,
+    config: Option<&PointConfig>,
+    dv: &DataValue,
+) -> Option<DataPoint> {
+    let recorded = dv
+        .source_timestamp
+        .as_ref()
+        .or(dv.server_timestamp.as_ref())
+        .and_then(opcua_datetime_to_chrono)?;
+    let mut point = convert_data_value_with_id(point_id, config, dv)?;
+    point.timestamp = recorded;
+    Some(point)
+}
+
 /// Convert OPC UA Variant to igw Value.
 fn convert_variant_to_value(variant: &Variant) -> Value {
     match variant {
@@ -1165,6 +1284,9 @@ fn convert_variant_to_value(variant: &Variant) -> Value {
         Variant::Float(v) => Value::Float(*v as f64),
         Variant::Double(v) => Value::Float(*v),
         Variant::String(v) => Value::String(v.as_ref().to_string()),
+        Variant::Array(array) => {
+            Value::Array(array.values.iter().map(convert_variant_to_value).collect())
+        }
         _ => Value::Null,
     }
 }
@@ -1329,6 +1451,30 @@ mod tests {
         assert_eq!(quality, Quality::Bad);
     }
 
+    #[test]
+    fn test_history_value_conversion() {
+        let recorded = Utc::now() - chrono::Duration::hours(1);
+        let value = DataValue {
+            value: Some(Variant::Double(1.5)),
+            status: Some(StatusCode::Uncertain),
+            server_timestamp: Some(recorded.into()),
+            ..Default::default()
+        };
+        let point = convert_history_value(7, None, &value).unwrap();
+        assert_eq!(point.id, 7);
+        assert_eq!(
+            point.timestamp.timestamp_millis(),
+            recorded.timestamp_millis()
+        );
+        assert_eq!(point.quality, Quality::Uncertain);
+
+        let unstamped = DataValue {
+            value: Some(Variant::Double(1.5)),
+            ..Default::default()
+        };
+        assert!(convert_history_value(7, None, &unstamped).is_none());
+    }
+
     #[test]
     fn test_channel_capabilities() {
         let config = OpcUaChannelConfig::new("opc.tcp://localhost:4840");
@@ -0,0 +1,997 @@
+//! Northbound OPC UA server (feature `opcua-server`).
+//!
+//! [`OpcUaServer`] lets OPC UA clients browse the gateway, read and
+//! subscribe to its current values and send it commands. When the server
+//! starts, the address space is built from the point configurations in the
+//! [`DataStore`], one folder per channel and one variable per point:
+//!
+//! ```text
+//! Objects
+//! └── feeder                 folder    ns=<igw>;s=1
+//!     ├── breaker            variable  ns=<igw>;s=1/1001
+//!     └── voltage            variable  ns=<igw>;s=1/1002  "Unit: kV"
+//! ```
+//!
+//! Variables hold the stored value with the sample's source timestamp and
+//! the status code of its [`Quality`](crate::core::quality::Quality) (see
+//! `Quality::to_opc_status()`); points without a value yet read as
+//! `BadWaitingForInitialData`. The server watches the store
+//! ([`DataStore::watch()`]) and updates a variable, and with it every
+//! monitored item on it, whenever the store reports a change. Stores
+//! without change tracking are read once a second instead.
+//!
+//! Points listed in `writable` (see [`OpcUaServerConfig`]) accept writes
+//! when a command handler is set. A written value is delivered through the
+//! [`OpcUaCommandHandler`] as a control (any non-zero value is "on") or
+//! adjustment command; the write is answered `Good` once the command is
+//! handed over, and a command that fails later is logged and counted in the
+//! diagnostics. A [`GatewayRuntime`] behind a `tokio::sync::RwLock` is a
+//! handler that forwards the commands to its channels, subject to the
+//! gateway's authorization levels.
+//!
+//! Sessions use security policy `None`, logging in anonymously (if enabled)
+//! or with a configured username and password. Commands are sent as the
+//! session's username, or as caller `opcua_server` for anonymous sessions.
+//! [`ProtocolServer::connected_clients()`] counts the activated sessions
+//! that have neither closed nor timed out.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! let channels = config.enabled_channels().map(|c| (c.id, c.name.clone())).collect();
+//! let gateway = Arc::new(RwLock::new(GatewayRuntime::from_config(config, store.clone())?));
+//! let mut server = OpcUaServer::new(&server_config, store)
+//!     .with_channels(channels)
+//!     .with_command_handler(gateway);
+//! server.listen(&server_config.bind).await?;
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use opcua::nodes::DefaultTypeTree;
+use opcua::server::address_space::{AddressSpace, NodeType, VariableBuilder};
+use opcua::server::authenticator::UserToken;
+use opcua::server::node_manager::memory::{
+    InMemoryNodeManager, InMemoryNodeManagerBuilder, InMemoryNodeManagerImpl, NamespaceMetadata,
+};
+use opcua::server::node_manager::{
+    NodeManager, NodeManagerBuilder, ParsedWriteValue, RequestContext, ServerContext, WriteNode,
+};
+use opcua::server::{
+    ServerBuilder, ServerEndpoint, ServerHandle, ServerUserToken, SubscriptionCache,
+    ANONYMOUS_USER_TOKEN_ID,
+};
+use opcua::types::{
+    AttributeId, ByteString, DataTypeId, DataValue, DateTime, NodeId, StatusCode, UAString, Variant,
+};
+use tokio::net::TcpListener;
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+
+use crate::core::data::{DataPoint, PointId, Value};
+use crate::core::error::{GatewayError, Result};
+use crate::core::point::PointConfig;
+use crate::core::traits::{
+    AdjustmentCommand, CommunicationMode, ConnectionState, ControlCommand, DataEvent, Diagnostics,
+    Protocol, ProtocolCapabilities, ProtocolServer, WriteResult,
+};
+use crate::gateway::{GatewayRuntime, OpcUaServerConfig, OpcUaWritable, SafeStateKind};
+use crate::store::DataStore;
+
+/// How often channels are read from stores without change tracking.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Receives the commands OPC UA clients write.
+///
+/// `caller` is the username of the writing session, or `opcua_server` for
+/// anonymous sessions (see
+/// [`AuthorizationConfig`](crate::gateway::AuthorizationConfig)).
+///
+/// This trait uses `async_trait` because the server holds it as
+/// `Arc<dyn OpcUaCommandHandler>`.
+#[async_trait]
+pub trait OpcUaCommandHandler: Send + Sync {
+    /// Deliver a control command for a point of `channel_id`.
+    async fn control(&self, caller: &str, channel_id: u32, command: ControlCommand) -> Result<()>;
+
+    /// Deliver an adjustment command for a point of `channel_id`.
+    async fn adjustment(
+        &self,
+        caller: &str,
+        channel_id: u32,
+        command: AdjustmentCommand,
+    ) -> Result<()>;
+}
+
+/// Caller identity of commands written by anonymous sessions.
+const ANONYMOUS_CALLER: &str = "opcua_server";
+
+#[async_trait]
+impl OpcUaCommandHandler for RwLock<GatewayRuntime> {
+    async fn control(&self, caller: &str, channel_id: u32, command: ControlCommand) -> Result<()> {
+        let value = if command.value { 1.0 } else { 0.0 };
+        let gateway = self.read().await;
+        let caller = gateway.caller(caller);
+        let result = gateway
+            .write_control_as(&caller, channel_id, &[(command.id, value)])
+            .await?;
+        ensure_accepted(result, command.id)
+    }
+
+    async fn adjustment(
+        &self,
+        caller: &str,
+        channel_id: u32,
+        command: AdjustmentCommand,
+    ) -> Result<()> {
+        let gateway = self.read().await;
+        let caller = gateway.caller(caller);
+        let result = gateway
+            .write_adjustment_as(&caller, channel_id, &[(command.id, command.value)])
+            .await?;
+        ensure_accepted(result, command.id)
+    }
+}
+
+/// Fail with the reason the runtime gives if the command was not sent.
+fn ensure_accepted(result: WriteResult, point_id: PointId) -> Result<()> {
+    if let Some((_, reason)) = result.failures.into_iter().next() {
+        return Err(GatewayError::protocol(format!(
+            "point {}: {}",
+            point_id, reason
+        )));
+    }
+    if result.success_count == 0 {
+        return Err(GatewayError::protocol(format!(
+            "point {}: command not accepted",
+            point_id
+        )));
+    }
+    Ok(())
+}
+
+/// Caller identity of a session's user.
+fn caller_of(token: &UserToken) -> &str {
+    if token.is_anonymous() {
+        ANONYMOUS_CALLER
+    } else {
+        // Users are registered with their username as token id
+        &token.0
+    }
+}
+
+/// Northbound OPC UA server.
+pub struct OpcUaServer {
+    config: OpcUaServerConfig,
+    /// Exposed channels with their folder names.
+    channels: Vec<(u32, String)>,
+    store: Arc<dyn DataStore>,
+    handler: Option<Arc<dyn OpcUaCommandHandler>>,
+    stats: Arc<ServerStats>,
+    running: Option<Running>,
+}
+
+/// A started server and its tasks.
+struct Running {
+    handle: ServerHandle,
+    sessions: Arc<SessionTracker>,
+    /// The server loop, then one value publisher per channel.
+    tasks: Vec<JoinHandle<()>>,
+    endpoint: String,
+    nodes: usize,
+}
+
+/// Write counters shared with the point node manager.
+#[derive(Default)]
+struct ServerStats {
+    writes: AtomicU64,
+    errors: AtomicU64,
+    last_error: Mutex<Option<String>>,
+}
+
+impl ServerStats {
+    fn fail(&self, error: String) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+        *self.last_error.lock().unwrap_or_else(|e| e.into_inner()) = Some(error);
+    }
+}
+
+impl OpcUaServer {
+    /// Create a server for `config`, answering from `store`.
+    ///
+    /// The configuration is expected to have passed
+    /// [`GatewayConfig::validate()`].
+    ///
+    /// [`GatewayConfig::validate()`]: crate::gateway::GatewayConfig::validate
+    pub fn new(config: &OpcUaServerConfig, store: Arc<dyn DataStore>) -> Self {
+        Self {
+            config: config.clone(),
+            channels: Vec::new(),
+            store,
+            handler: None,
+            stats: Arc::default(),
+            running: None,
+        }
+    }
+
+    /// Expose these channels `(id, folder name)`.
+    #[must_use]
+    pub fn with_channels(mut self, channels: Vec<(u32, String)>) -> Self {
+        self.channels = channels;
+        self
+    }
+
+    /// Deliver written commands to `handler` (no point is writable without
+    /// one).
+    #[must_use]
+    pub fn with_command_handler(mut self, handler: Arc<dyn OpcUaCommandHandler>) -> Self {
+        self.handler = Some(handler);
+        self
+    }
+
+    /// Server for the configured namespace, users and endpoint.
+    fn server_builder(
+        &self,
+        host: &str,
+        port: u16,
+        endpoint: &str,
+        sessions: Arc<SessionTracker>,
+    ) -> ServerBuilder {
+        let mut user_token_ids = Vec::new();
+        if self.config.anonymous {
+            user_token_ids.push(ANONYMOUS_USER_TOKEN_ID.to_string());
+        }
+        let mut builder = ServerBuilder::new()
+            .application_name("igw")
+            .application_uri(format!("{}:server", self.config.namespace_uri))
+            .product_uri("urn:igw")
+            .host(host)
+            .port(port)
+            .pki_dir(&self.config.pki_dir)
+            .create_sample_keypair(true)
+            .discovery_urls(vec![endpoint.to_string()])
+            .with_node_manager(self.point_nodes())
+            .with_node_manager(move |_: ServerContext| SessionNodeManager(sessions));
+        for user in &self.config.users {
+            builder = builder.add_user_token(
+                &user.username,
+                ServerUserToken::user_pass(&user.username, &user.password),
+            );
+            user_token_ids.push(user.username.clone());
+        }
+        builder.add_endpoint("none", ServerEndpoint::new_none("/", &user_token_ids))
+    }
+
+    /// The endpoint URL clients connect to.
+    pub fn endpoint_url(&self) -> Option<&str> {
+        self.running.as_ref().map(|r| r.endpoint.as_str())
+    }
+
+    fn is_listening(&self) -> bool {
+        self.running
+            .as_ref()
+            .is_some_and(|r| r.tasks.first().is_some_and(|task| !task.is_finished()))
+    }
+
+    /// The writable entry of a point, if writes are delivered anywhere.
+    fn writable(&self, channel_id: u32, point_id: PointId) -> Option<OpcUaWritable> {
+        self.handler.as_ref()?;
+        self.config
+            .writable
+            .iter()
+            .find(|w| w.channel_id == channel_id && w.point_id == point_id)
+            .copied()
+    }
+
+    /// Builder of the node manager holding the point variables.
+    fn point_nodes(&self) -> impl NodeManagerBuilder {
+        let namespace_uri = self.config.namespace_uri.clone();
+        let handler = self.handler.clone();
+        let writable = if handler.is_some() {
+            self.config.writable.clone()
+        } else {
+            Vec::new()
+        };
+        let stats = Arc::clone(&self.stats);
+        InMemoryNodeManagerBuilder::new(
+            move |context: ServerContext, address_space: &mut AddressSpace| {
+                let ns = context
+                    .type_tree
+                    .write()
+                    .namespaces_mut()
+                    .add_namespace(&namespace_uri);
+                address_space.add_namespace(&namespace_uri, ns);
+                PointNodes {
+                    namespace: NamespaceMetadata {
+                        namespace_uri,
+                        namespace_index: ns,
+                        ..Default::default()
+                    },
+                    writable: writable
+                        .into_iter()
+                        .map(|w| (point_node(ns, w.channel_id, w.point_id), w))
+                        .collect(),
+                    handler,
+                    stats,
+                }
+            },
+        )
+    }
+
+    /// Add the channel folders and point variables, returning the exposed
+    /// points per channel.
+    async fn build_address_space(
+        &self,
+        manager: &PointNodeManager,
+        ns: u16,
+    ) -> Result<HashMap<u32, HashSet<PointId>>> {
+        let mut channels = Vec::with_capacity(self.channels.len());
+        for (channel_id, name) in &self.channels {
+            let points = self.store.point_configs(*channel_id).await?;
+            channels.push((*channel_id, name, points));
+        }
+
+        let mut exposed = HashMap::new();
+        let mut address_space = manager.address_space().write();
+        for (channel_id, name, points) in channels {
+            let folder = NodeId::new(ns, channel_id.to_string());
+            address_space.add_folder(
+                &folder,
+                name.as_str(),
+                name.as_str(),
+                &NodeId::objects_folder_id(),
+            );
+
+            let ids: &mut HashSet<PointId> = exposed.entry(channel_id).or_default();
+            for point in points.iter().filter(|p| p.enabled) {
+                let browse_name = browse_name(point);
+                let display_name = point
+                    .display_name
+                    .clone()
+                    .unwrap_or_else(|| browse_name.clone());
+                let mut variable = VariableBuilder::new(
+                    &point_node(ns, channel_id, point.id),
+                    browse_name.as_str(),
+                    display_name.as_str(),
+                )
+                .data_type(DataTypeId::BaseDataType)
+                .value(Variant::Empty)
+                .organized_by(&folder);
+                if let Some(unit) = &point.unit {
+                    variable = variable.description(format!("Unit: {}", unit).as_str());
+                }
+                if self.writable(channel_id, point.id).is_some() {
+                    variable = variable.writable();
+                }
+                variable.insert(&mut *address_space);
+                ids.insert(point.id);
+            }
+        }
+        Ok(exposed)
+    }
+}
+
+impl Drop for OpcUaServer {
+    fn drop(&mut self) {
+        if let Some(running) = &self.running {
+            running.handle.cancel();
+            for task in &running.tasks {
+                task.abort();
+            }
+        }
+    }
+}
+
+impl ProtocolCapabilities for OpcUaServer {
+    fn name(&self) -> &'static str {
+        "OPC UA Server"
+    }
+
+    fn supported_modes(&self) -> &[CommunicationMode] {
+        &[CommunicationMode::EventDriven]
+    }
+}
+
+impl Protocol for OpcUaServer {
+    fn connection_state(&self) -> ConnectionState {
+        if self.is_listening() {
+            ConnectionState::Connected
+        } else {
+            ConnectionState::Disconnected
+        }
+    }
+
+    /// Writes count forwarded commands, errors count writes that failed;
+    /// `extra` has the endpoint URL and the number of point variables.
+    async fn diagnostics(&self) -> Result<Diagnostics> {
+        let stats = &self.stats;
+        Ok(Diagnostics {
+            protocol: self.name().to_string(),
+            connection_state: self.connection_state(),
+            write_count: stats.writes.load(Ordering::Relaxed),
+            error_count: stats.errors.load(Ordering::Relaxed),
+            last_error: stats
+                .last_error
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .clone(),
+            extra: serde_json::json!({
+                "endpoint": self.endpoint_url(),
+                "nodes": self.running.as_ref().map_or(0, |r| r.nodes),
+                "clients": self.connected_clients(),
+            }),
+            ..Default::default()
+        })
+    }
+}
+
+impl ProtocolServer for OpcUaServer {
+    /// Build the address space and start serving on `addr`; a running
+    /// server is stopped first.
+    async fn listen(&mut self, addr: &str) -> Result<()> {
+        self.stop().await?;
+        let (host, _) = split_host_port(addr)?;
+        let listener = TcpListener::bind(addr).await?;
+        let port = listener.local_addr()?.port();
+        let endpoint = format!("opc.tcp://{}:{}/", host, port);
+
+        let sessions = Arc::new(SessionTracker::default());
+        let (server, handle) = self
+            .server_builder(host, port, &endpoint, Arc::clone(&sessions))
+            .build()
+            .map_err(|e| GatewayError::Config(format!("opcua_server: {}", e)))?;
+
+        let manager = handle
+            .node_managers()
+            .get_of_type::<PointNodeManager>()
+            .ok_or_else(|| GatewayError::Internal("opcua_server: no node manager".into()))?;
+        let ns = handle
+            .get_namespace_index(&self.config.namespace_uri)
+            .ok_or_else(|| GatewayError::Internal("opcua_server: no namespace".into()))?;
+        let exposed = self.build_address_space(&manager, ns).await?;
+
+        let mut tasks = vec![tokio::spawn(async move {
+            if let Err(_e) = server.run_with(listener).await {
+                #[cfg(feature = "tracing-support")]
+                tracing::error!("OPC UA server stopped: {}", _e);
+            }
+        })];
+        let nodes = exposed.values().map(HashSet::len).sum();
+        for (channel_id, points) in exposed {
+            let publisher = ValuePublisher {
+                store: Arc::clone(&self.store),
+                manager: Arc::clone(&manager),
+                subscriptions: Arc::clone(handle.subscriptions()),
+                ns,
+                channel_id,
+                points,
+            };
+            tasks.push(tokio::spawn(publisher.run()));
+        }
+
+        #[cfg(feature = "tracing-support")]
+        tracing::info!(endpoint = %endpoint, nodes, "OPC UA server listening");
+        self.running = Some(Running {
+            handle,
+            sessions,
+            tasks,
+            endpoint,
+            nodes,
+        });
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> Result<()> {
+        if let Some(running) = self.running.take() {
+            running.handle.cancel();
+            for task in running.tasks {
+                task.abort();
+                let _ = task.await;
+            }
+        }
+        Ok(())
+    }
+
+    fn connected_clients(&self) -> usize {
+        self.running.as_ref().map_or(0, |r| r.sessions.count())
+    }
+}
+
+/// Node manager of the point variables.
+type PointNodeManager = InMemoryNodeManager<PointNodes>;
+
+/// Point variables of the gateway's namespace; writes to the writable
+/// points are sent to the command handler as the writing session's user.
+struct PointNodes {
+    namespace: NamespaceMetadata,
+    /// Writable points by variable, empty without a command handler.
+    writable: HashMap<NodeId, OpcUaWritable>,
+    handler: Option<Arc<dyn OpcUaCommandHandler>>,
+    stats: Arc<ServerStats>,
+}
+
+#[async_trait]
+impl InMemoryNodeManagerImpl for PointNodes {
+    async fn init(&self, _address_space: &mut AddressSpace, _context: ServerContext) {}
+
+    fn name(&self) -> &str {
+        "igw"
+    }
+
+    fn namespaces(&self) -> Vec<NamespaceMetadata> {
+        vec![self.namespace.clone()]
+    }
+
+    async fn write(
+        &self,
+        context: &RequestContext,
+        address_space: &opcua::sync::RwLock<AddressSpace>,
+        nodes_to_write: &mut [&mut WriteNode],
+    ) -> std::result::Result<(), StatusCode> {
+        let mut address_space = address_space.write();
+        let type_tree = context.type_tree.read();
+        for write in nodes_to_write {
+            let status = self.write_value(context, &mut address_space, &type_tree, write.value());
+            write.set_status(status);
+        }
+        Ok(())
+    }
+}
+
+impl PointNodes {
+    /// Send a written value as its point's command.
+    fn write_value(
+        &self,
+        context: &RequestContext,
+        address_space: &mut AddressSpace,
+        type_tree: &DefaultTypeTree,
+        write: &ParsedWriteValue,
+    ) -> StatusCode {
+        let node = match address_space.validate_node_write(context, write, type_tree) {
+            Ok(node) => node,
+            Err(status) => return status,
+        };
+        let (NodeType::Variable(_), AttributeId::Value) = (node, write.attribute_id) else {
+            return StatusCode::BadNotWritable;
+        };
+        let (Some(handler), Some(entry)) = (&self.handler, self.writable.get(&write.node_id))
+        else {
+            return StatusCode::BadNotWritable;
+        };
+        let Some(number) = write.value.value.as_ref().and_then(variant_f64) else {
+            return StatusCode::BadTypeMismatch;
+        };
+
+        self.stats.writes.fetch_add(1, Ordering::Relaxed);
+        let (handler, stats, entry) = (Arc::clone(handler), Arc::clone(&self.stats), *entry);
+        let caller = caller_of(&context.token).to_string();
+        tokio::spawn(async move {
+            if let Err(e) = send_command(handler.as_ref(), &caller, entry, number).await {
+                #[cfg(feature = "tracing-support")]
+                tracing::warn!(
+                    channel_id = entry.channel_id,
+                    point_id = entry.point_id,
+                    caller = %caller,
+                    error = %e,
+                    "OPC UA write rejected"
+                );
+                stats.fail(format!(
+                    "channel {} point {}: {}",
+                    entry.channel_id, entry.point_id, e
+                ));
+            }
+        });
+        StatusCode::Good
+    }
+}
+
+/// Liveness check of a session.
+type SessionAlive = Box<dyn Fn() -> bool + Send + Sync>;
+
+/// The sessions clients have activated, by session id.
+///
+/// async-opcua does not count its sessions, but asks every node manager
+/// which namespaces a session may see when it is activated; the tracker is
+/// a node manager without nodes that answers none and remembers the session.
+#[derive(Default)]
+struct SessionTracker {
+    sessions: Mutex<HashMap<u32, SessionAlive>>,
+}
+
+impl SessionTracker {
+    fn track(&self, context: &RequestContext) {
+        // The session type is private to async-opcua, so keep a check
+        // rather than the session
+        let session = Arc::downgrade(&context.session);
+        let alive = move || {
+            session.upgrade().is_some_and(|session| {
+                let session = session.read();
+                session.is_activated() && session.deadline() > Instant::now()
+            })
+        };
+        self.sessions
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(context.session_id, Box::new(alive));
+    }
+
+    /// Number of sessions neither closed nor timed out.
+    fn count(&self) -> usize {
+        let mut sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+        sessions.retain(|_, alive| alive());
+        sessions.len()
+    }
+}
+
+/// Registers [`SessionTracker`] with the server.
+struct SessionNodeManager(Arc<SessionTracker>);
+
+#[async_trait]
+impl NodeManager for SessionNodeManager {
+    fn owns_node(&self, _id: &NodeId) -> bool {
+        false
+    }
+
+    fn name(&self) -> &str {
+        "igw-sessions"
+    }
+
+    fn namespaces_for_user(&self, context: &RequestContext) -> Vec<NamespaceMetadata> {
+        self.0.track(context);
+        Vec::new()
+    }
+
+    async fn init(&self, _type_tree: &mut DefaultTypeTree, _context: ServerContext) {}
+}
+
+/// Keeps a channel's variables in step with the store.
+struct ValuePublisher {
+    store: Arc<dyn DataStore>,
+    manager: Arc<PointNodeManager>,
+    subscriptions: Arc<SubscriptionCache>,
+    ns: u16,
+    channel_id: u32,
+    /// Points with a variable.
+    points: HashSet<PointId>,
+}
+
+impl ValuePublisher {
+    async fn run(self) {
+        let waiting: Vec<(NodeId, DataValue)> = self
+            .points
+            .iter()
+            .map(|id| {
+                (
+                    point_node(self.ns, self.channel_id, *id),
+                    waiting_for_data(),
+                )
+            })
+            .collect();
+        self.set_values(waiting);
+        self.resync().await;
+
+        let mut watch = self.store.watch(self.channel_id, &[]).ok();
+        loop {
+            let Some(changes) = watch.as_mut() else {
+                tokio::time::sleep(POLL_INTERVAL).await;
+                self.resync().await;
+                continue;
+            };
+            match changes.recv().await {
+                Some(DataEvent::DataUpdate(batch)) => self.publish(batch.iter()),
+                // Lagged behind, so some changes are lost
+                Some(DataEvent::Error(_)) => self.resync().await,
+                Some(_) => {}
+                None => return,
+            }
+        }
+    }
+
+    /// Publish the channel's current values.
+    async fn resync(&self) {
+        match self.store.snapshot(self.channel_id).await {
+            Ok(snapshot) => self.publish(snapshot.iter()),
+            Err(_e) => {
+                #[cfg(feature = "tracing-support")]
+                tracing::warn!(
+                    channel_id = self.channel_id,
+                    "OPC UA server cannot read the store: {}",
+                    _e
+                );
+            }
+        }
+    }
+
+    fn publish<'a>(&self, points: impl Iterator<Item = &'a DataPoint>) {
+        let values = points
+            .filter(|p| self.points.contains(&p.id))
+            .map(|p| (point_node(self.ns, self.channel_id, p.id), data_value(p)))
+            .collect();
+        self.set_values(values);
+    }
+
+    fn set_values(&self, values: Vec<(NodeId, DataValue)>) {
+        if values.is_empty() {
+            return;
+        }
+        let updates = values
+            .iter()
+            .map(|(node, value)| (node, None, value.clone()));
+        if let Err(_e) = self.manager.set_values(&self.subscriptions, updates) {
+            #[cfg(feature = "tracing-support")]
+            tracing::warn!(
+                channel_id = self.channel_id,
+                "OPC UA value update failed: {}",
+                _e
+            );
+        }
+    }
+}
+
+/// Send a written value as the entry's command.
+async fn send_command(
+    handler: &dyn OpcUaCommandHandler,
+    caller: &str,
+    entry: OpcUaWritable,
+    value: f64,
+) -> Result<()> {
+    match entry.kind {
+        SafeStateKind::Control => {
+            let command = ControlCommand::latching(entry.point_id, value != 0.0);
+            handler.control(caller, entry.channel_id, command).await
+        }
+        SafeStateKind::Adjustment => {
+            let command = AdjustmentCommand::new(entry.point_id, value);
+            handler.adjustment(caller, entry.channel_id, command).await
+        }
+    }
+}
+
+fn split_host_port(addr: &str) -> Result<(&str, u16)> {
+    addr.rsplit_once(':')
+        .and_then(|(host, port)| Some((host, port.parse().ok()?)))
+        .ok_or_else(|| {
+            GatewayError::Config(format!(
+                "opcua_server: '{}' is not a host:port address",
+                addr
+            ))
+        })
+}
+
+fn point_node(ns: u16, channel_id: u32, point_id: PointId) -> NodeId {
+    NodeId::new(ns, format!("{}/{}", channel_id, point_id))
+}
+
+fn browse_name(point: &PointConfig) -> String {
+    point
+        .name
+        .clone()
+        .unwrap_or_else(|| format!("point_{}", point.id))
+}
+
+/// Variable value of a stored point.
+fn data_value(point: &DataPoint) -> DataValue {
+    DataValue {
+        value: Some(variant(&point.value)),
+        status: Some(StatusCode::from(point.quality.to_opc_status())),
+        source_timestamp: Some(DateTime::from(
+            point.source_timestamp.unwrap_or(point.timestamp),
+        )),
+        server_timestamp: Some(DateTime::from(point.timestamp)),
+        ..Default::default()
+    }
+}
+
+/// Variable value of a point the store has no value for yet.
+fn waiting_for_data() -> DataValue {
+    DataValue {
+        value: None,
+        status: Some(StatusCode::BadWaitingForInitialData),
+        server_timestamp: Some(DateTime::now()),
+        ..Default::default()
+    }
+}
+
+fn variant(value: &Value) -> Variant {
+    match value {
+        Value::Float(v) => Variant::Double(*v),
+        Value::Integer(v) => Variant::Int64(*v),
+        Value::Bool(v) => Variant::Boolean(*v),
+        Value::String(v) => Variant::String(UAString::from(v.as_str())),
+        Value::Bytes(v) => Variant::ByteString(ByteString::from(v.clone())),
+        // Arrays of mixed values have no OPC UA representation
+        Value::Array(_) | Value::Null => Variant::Empty,
+    }
+}
+
+/// Numeric value of a written variant.
+fn variant_f64(variant: &Variant) -> Option<f64> {
+    Some(match variant {
+        Variant::Boolean(v) => f64::from(u8::from(*v)),
+        Variant::SByte(v) => f64::from(*v),
+        Variant::Byte(v) => f64::from(*v),
+        Variant::Int16(v) => f64::from(*v),
+        Variant::UInt16(v) => f64::from(*v),
+        Variant::Int32(v) => f64::from(*v),
+        Variant::UInt32(v) => f64::from(*v),
+        Variant::Int64(v) => *v as f64,
+        Variant::UInt64(v) => *v as f64,
+        Variant::Float(v) => f64::from(*v),
+        Variant::Double(v) => *v,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::point::ProtocolAddress;
+    use crate::core::quality::Quality;
+    use opcua::client::{ClientBuilder, IdentityToken, Session};
+    use opcua::crypto::SecurityPolicy;
+    use opcua::types::{MessageSecurityMode, UserTokenPolicy, WriteValue};
+
+    #[test]
+    fn test_data_value() {
+        let point = DataPoint::new(7, 21.5).with_quality(Quality::CommFailure);
+        let value = data_value(&point);
+        assert_eq!(value.value, Some(Variant::Double(21.5)));
+        assert_eq!(value.status, Some(StatusCode::from(0x8013_0000)));
+        assert_eq!(
+            value.source_timestamp,
+            Some(DateTime::from(point.timestamp))
+        );
+
+        assert_eq!(variant(&Value::Integer(-3)), Variant::Int64(-3));
+        assert_eq!(variant(&Value::Null), Variant::Empty);
+        assert_eq!(
+            waiting_for_data().status,
+            Some(StatusCode::BadWaitingForInitialData)
+        );
+    }
+
+    #[test]
+    fn test_written_values() {
+        assert_eq!(variant_f64(&Variant::Boolean(true)), Some(1.0));
+        assert_eq!(variant_f64(&Variant::Int32(-4)), Some(-4.0));
+        assert_eq!(variant_f64(&Variant::Float(0.5)), Some(0.5));
+        assert_eq!(variant_f64(&Variant::String("1".into())), None);
+    }
+
+    #[test]
+    fn test_node_ids() {
+        assert_eq!(point_node(2, 1, 1001), NodeId::new(2, "1/1001"));
+        assert_eq!(split_host_port("0.0.0.0:4840").unwrap(), ("0.0.0.0", 4840));
+        assert!(split_host_port("localhost").is_err());
+    }
+
+    /// Records the commands it receives with their caller.
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<(String, u32, PointId, f64)>>);
+
+    #[async_trait]
+    impl OpcUaCommandHandler for Recorder {
+        async fn control(&self, _: &str, _: u32, _: ControlCommand) -> Result<()> {
+            unreachable!("only adjustments are writable")
+        }
+
+        async fn adjustment(
+            &self,
+            caller: &str,
+            channel_id: u32,
+            command: AdjustmentCommand,
+        ) -> Result<()> {
+            let mut commands = self.0.lock().unwrap();
+            commands.push((caller.to_string(), channel_id, command.id, command.value));
+            Ok(())
+        }
+    }
+
+    async fn connect(endpoint: &str, identity: IdentityToken) -> Arc<Session> {
+        let mut client = ClientBuilder::new()
+            .application_name("igw-test")
+            .application_uri("urn:igw-test")
+            .trust_server_certs(true)
+            .session_retry_limit(0)
+            .client()
+            .unwrap();
+        let (session, event_loop) = client
+            .connect_to_matching_endpoint(
+                (
+                    endpoint,
+                    SecurityPolicy::None.to_uri(),
+                    MessageSecurityMode::None,
+                    UserTokenPolicy::anonymous(),
+                ),
+                identity,
+            )
+            .await
+            .unwrap();
+        event_loop.spawn();
+        tokio::time::timeout(Duration::from_secs(5), session.wait_for_connection())
+            .await
+            .unwrap();
+        session
+    }
+
+    #[tokio::test]
+    async fn test_sessions() {
+        let pki_dir = std::env::temp_dir().join(format!("igw-opcua-{}", std::process::id()));
+        let config: OpcUaServerConfig = serde_json::from_value(serde_json::json!({
+            "bind": "127.0.0.1:0",
+            "pki_dir": pki_dir.to_string_lossy(),
+            "users": [{ "username": "scada", "password": "secret" }],
+            "writable": [{ "channel_id": 1, "point_id": 7, "kind": "adjustment" }],
+        }))
+        .unwrap();
+        let store = Arc::new(crate::store::MemoryStore::new());
+        let point = PointConfig::new(7, ProtocolAddress::Generic("setpoint".into()));
+        store.set_point_configs(1, &[point]).await.unwrap();
+        let recorder = Arc::new(Recorder::default());
+        let mut server = OpcUaServer::new(&config, store)
+            .with_channels(vec![(1, "feeder".into())])
+            .with_command_handler(recorder.clone());
+        server.listen(&config.bind).await.unwrap();
+        let endpoint = server.endpoint_url().unwrap().to_string();
+        assert!(!endpoint.ends_with(":0/"));
+
+        let anonymous = connect(&endpoint, IdentityToken::Anonymous).await;
+        let scada = connect(
+            &endpoint,
+            IdentityToken::UserName("scada".into(), "secret".into()),
+        )
+        .await;
+        wait_for_clients(&server, 2).await;
+
+        let running = server.running.as_ref().unwrap();
+        let ns = running.handle.get_namespace_index(&config.namespace_uri);
+        let ns = ns.unwrap();
+        let write = |value: f64| WriteValue {
+            node_id: point_node(ns, 1, 7),
+            attribute_id: AttributeId::Value as u32,
+            index_range: UAString::null(),
+            value: DataValue::new_now(value),
+        };
+        let results = anonymous.write(&[write(1.5)]).await.unwrap();
+        assert_eq!(results, vec![StatusCode::Good]);
+        let results = scada.write(&[write(2.5)]).await.unwrap();
+        assert_eq!(results, vec![StatusCode::Good]);
+        for _ in 0..100 {
+            if recorder.0.lock().unwrap().len() == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let mut commands = recorder.0.lock().unwrap().clone();
+        commands.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            commands,
+            vec![
+                ("opcua_server".into(), 1, 7, 1.5),
+                ("scada".into(), 1, 7, 2.5),
+            ]
+        );
+
+        anonymous.disconnect().await.unwrap();
+        wait_for_clients(&server, 1).await;
+
+        server.stop().await.unwrap();
+        assert_eq!(server.connected_clients(), 0);
+        drop(scada);
+        let _ = std::fs::remove_dir_all(pki_dir);
+    }
+
+    async fn wait_for_clients(server: &OpcUaServer, expected: usize) {
+        for _ in 0..100 {
+            if server.connected_clients() == expected {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(server.connected_clients(), expected);
+    }
+}
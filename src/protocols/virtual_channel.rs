@@ -18,28 +18,99 @@
 //! let mut channel = VirtualChannel::new(config);
 //!
 //! // Push data from any source
-//! channel.write_point(DataPoint::telemetry(1, 25.5)).await?;
+//! channel.write_point(DataPoint::new(1, 25.5)).await?;
 //!
 //! // Get accumulated data (service layer handles storage)
-//! let batch = channel.poll_once().await?;
-//! store.write_batch(channel_id, &batch).await?;
+//! let result = channel.poll_once().await;
+//! store.write_batch(channel_id, &result.data).await?;
 //! ```
+//!
+//! # Computed points
+//!
+//! Points can be derived from other points on the same channel with an
+//! [expression](expr):
+//!
+//! ```rust,ignore
+//! let config = VirtualChannelConfig::new("power").with_computed(vec![
+//!     ComputedPointConfig::new(3, "sqrt(p1^2 + p2^2)"),
+//! ]);
+//! let channel = VirtualChannel::try_new(config)?;
+//! ```
+//!
+//! Whenever an input is written, dependent points are recomputed in
+//! dependency order and emitted in the same batch. A computed point takes the
+//! worst quality of its inputs and is skipped until all inputs have a numeric
+//! value.
+//!
+//! # Totalizers
+//!
+//! A point can also hold the running total of another (raw or computed)
+//! point, e.g. volume from flow or energy from power; see
+//! [`integrate`] for the rules:
+//!
+//! ```rust,ignore
+//! let config = VirtualChannelConfig::new("meter").with_integrators(vec![
+//!     IntegratorConfig::new(20, 10).with_time_base(3600.0).with_reset_point(21),
+//! ]);
+//! ```
+//!
+//! Totals are updated in the batch that carries the new input sample.
+//!
+//! # Strict mode
+//!
+//! By default every written point is stored, so a typo in a routing table
+//! silently creates a phantom point. With [`StrictMode::Drop`] or
+//! [`StrictMode::Reject`], a written point must be one of the configured
+//! points (or a computed point, integrator total or reset point), have a
+//! value of the point's `data_type` if it has one, and gets the point's
+//! `TransformConfig` applied. Commands are checked the same way but not
+//! transformed, as they are already in engineering units. Mismatching
+//! points are counted in the diagnostics together with the last one and
+//! why it was refused.
+//!
+//! # Point TTL
+//!
+//! A point configured with `ttl_ms` that is not written for that long is
+//! marked `Quality::LastKnown`, and with `out_of_service_ms` later marked
+//! `Quality::OutOfService`, keeping its value. Each transition is emitted
+//! as a `DataEvent::DataUpdate` together with the computed points it
+//! affects, so northbound servers pass the degradation on; the next write
+//! restores the written quality. Values restored at startup are not
+//! degraded until written. The sweep runs between
+//! [`start()`](EventDrivenProtocol::start) and
+//! [`stop()`](EventDrivenProtocol::stop) and only wakes up when a deadline
+//! is due.
+
+pub mod expr;
+pub mod integrate;
+mod ttl;
 
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 
 use dashmap::DashMap;
-use tokio::sync::{broadcast, RwLock};
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
 
-use crate::core::data::{DataBatch, DataPoint};
-use crate::core::error::Result;
+use crate::core::data::{DataBatch, DataPoint, PointId, Value, ValueKind};
+use crate::core::diagnostics::DiagnosticsRecorder;
+use crate::core::error::{GatewayError, Result};
 use crate::core::metadata::{DriverMetadata, HasMetadata, ParameterMetadata, ParameterType};
-use crate::core::point::PointConfig;
+use crate::core::point::{poll_group_map, PointConfig};
+use crate::core::quality::Quality;
 use crate::core::traits::{
     AdjustmentCommand, CommunicationMode, ConnectionState, ControlCommand, DataEvent,
-    DataEventHandler, DataEventReceiver, DataEventSender, Diagnostics, EventDrivenProtocol,
-    PollResult, Protocol, ProtocolCapabilities, ProtocolClient, WriteResult,
+    DataEventHandler, DataEventReceiver, Diagnostics, EventBus, EventDrivenProtocol, PollResult,
+    Protocol, ProtocolCapabilities, ProtocolClient, ReadRequest, ReadResponse, WriteResult,
 };
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+
+use self::expr::Expr;
+use self::integrate::Integrator;
+pub use self::integrate::IntegratorConfig;
+use self::ttl::Expiry;
 
 /// Virtual channel configuration.
 #[derive(Debug, Clone)]
@@ -52,6 +123,29 @@ pub struct VirtualChannelConfig {
 
     /// Event buffer size.
     pub buffer_size: usize,
+
+    /// Points computed from other points on this channel.
+    pub computed: Vec<ComputedPointConfig>,
+
+    /// Running totals of other points on this channel.
+    pub integrators: Vec<IntegratorConfig>,
+
+    /// Checking of written points against `points`.
+    pub strict: StrictMode,
+}
+
+/// How a virtual channel treats written points that do not match its
+/// configuration.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StrictMode {
+    /// Store every point as written.
+    #[default]
+    Off,
+    /// Drop mismatching points and store the rest of the batch.
+    Drop,
+    /// Fail the whole write with `GatewayError::InvalidData`.
+    Reject,
 }
 
 impl Default for VirtualChannelConfig {
@@ -60,6 +154,9 @@ impl Default for VirtualChannelConfig {
             name: "virtual".to_string(),
             points: Vec::new(),
             buffer_size: 1024,
+            computed: Vec::new(),
+            integrators: Vec::new(),
+            strict: StrictMode::Off,
         }
     }
 }
@@ -84,57 +181,229 @@ impl VirtualChannelConfig {
         self.buffer_size = size;
         self
     }
+
+    /// Add computed point definitions.
+    pub fn with_computed(mut self, computed: Vec<ComputedPointConfig>) -> Self {
+        self.computed = computed;
+        self
+    }
+
+    /// Add totalizer definitions.
+    pub fn with_integrators(mut self, integrators: Vec<IntegratorConfig>) -> Self {
+        self.integrators = integrators;
+        self
+    }
+
+    /// Set the checking of written points.
+    pub fn with_strict(mut self, strict: StrictMode) -> Self {
+        self.strict = strict;
+        self
+    }
 }
 
-// ============================================================================
-// Strongly-typed mapping configs for JSON deserialization
-// ============================================================================
+/// A point derived from other points of the same channel.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComputedPointConfig {
+    /// Id of the computed point.
+    pub id: PointId,
 
-/// Virtual channel parameters configuration (deserialized from parameters_json).
-///
-/// Virtual channels are simple data hubs and don't require complex configuration.
+    /// Expression over other points, e.g. `"sqrt(p1^2 + p2^2)"`.
+    pub expression: String,
+}
+
+impl ComputedPointConfig {
+    /// Create a computed point definition.
+    pub fn new(id: PointId, expression: impl Into<String>) -> Self {
+        Self {
+            id,
+            expression: expression.into(),
+        }
+    }
+}
+
+/// A compiled computed point.
+#[derive(Debug)]
+struct ComputedPoint {
+    id: PointId,
+    expr: Expr,
+    inputs: Vec<PointId>,
+}
+
+/// Parse computed point expressions and order them so every point comes
+/// after the computed points it depends on.
 ///
-/// # Example JSON
-/// ```json
-/// {
-///     "name": "data_hub",
-///     "buffer_size": 2048
-/// }
-/// ```
-#[derive(Debug, Clone, Deserialize, Default)]
-pub struct VirtualChannelParamsConfig {
-    /// Channel name for identification.
-    #[serde(default = "default_virtual_name")]
-    pub name: String,
+/// Fails with `GatewayError::Config` on parse errors, duplicate ids and
+/// dependency cycles.
+fn compile_computed(defs: &[ComputedPointConfig]) -> Result<Vec<ComputedPoint>> {
+    let mut by_id: HashMap<PointId, ComputedPoint> = HashMap::new();
+    for def in defs {
+        let expr = Expr::parse(&def.expression)
+            .map_err(|e| GatewayError::config(format!("computed point {}: {}", def.id, e)))?;
+        let inputs = expr.inputs();
+        let point = ComputedPoint {
+            id: def.id,
+            expr,
+            inputs,
+        };
+        if by_id.insert(def.id, point).is_some() {
+            return Err(GatewayError::config(format!(
+                "computed point {} is defined more than once",
+                def.id
+            )));
+        }
+    }
 
-    /// Event buffer size.
-    #[serde(default = "default_buffer_size")]
-    pub buffer_size: usize,
+    // Depth-first topological sort over computed -> computed edges.
+    fn visit(
+        id: PointId,
+        by_id: &HashMap<PointId, ComputedPoint>,
+        done: &mut HashSet<PointId>,
+        path: &mut Vec<PointId>,
+        order: &mut Vec<PointId>,
+    ) -> Result<()> {
+        if done.contains(&id) {
+            return Ok(());
+        }
+        if let Some(start) = path.iter().position(|&p| p == id) {
+            let cycle: Vec<String> = path[start..]
+                .iter()
+                .chain(std::iter::once(&id))
+                .map(|p| p.to_string())
+                .collect();
+            return Err(GatewayError::config(format!(
+                "computed point cycle: {}",
+                cycle.join(" -> ")
+            )));
+        }
+
+        path.push(id);
+        for input in &by_id[&id].inputs {
+            if by_id.contains_key(input) {
+                visit(*input, by_id, done, path, order)?;
+            }
+        }
+        path.pop();
+
+        done.insert(id);
+        order.push(id);
+        Ok(())
+    }
+
+    let mut done = HashSet::new();
+    let mut order = Vec::with_capacity(defs.len());
+    for def in defs {
+        visit(def.id, &by_id, &mut done, &mut Vec::new(), &mut order)?;
+    }
+
+    Ok(order
+        .into_iter()
+        .filter_map(|id| by_id.remove(&id))
+        .collect())
 }
 
-fn default_virtual_name() -> String {
-    "virtual".to_string()
+/// Position of each point id in `points`.
+fn index_points(points: &[PointConfig]) -> HashMap<PointId, usize> {
+    points.iter().enumerate().map(|(i, p)| (p.id, i)).collect()
 }
 
-fn default_buffer_size() -> usize {
-    1024
+/// Recompute the `computed` points affected by `batch`.
+///
+/// Results are stored in the buffer and returned in dependency order.
+fn recompute(
+    computed: &[ComputedPoint],
+    buffer: &DashMap<u32, DataPoint>,
+    batch: &DataBatch,
+) -> DataBatch {
+    let mut derived = DataBatch::new();
+    if computed.is_empty() {
+        return derived;
+    }
+
+    let mut changed: HashSet<PointId> = batch.iter().map(|p| p.id).collect();
+    for point in computed {
+        if !point.inputs.iter().any(|id| changed.contains(id)) {
+            continue;
+        }
+
+        let mut quality = Quality::Good;
+        let value = point.expr.eval(|id| {
+            let input = buffer.get(&id)?;
+            quality = quality.worst(input.quality);
+            input.value.as_f64()
+        });
+        let Some(value) = value else {
+            continue;
+        };
+        if !value.is_finite() {
+            quality = quality.worst(Quality::Invalid);
+        }
+
+        let result = DataPoint::new(point.id, Value::Float(value)).with_quality(quality);
+        buffer.insert(point.id, result.clone());
+        derived.add(result);
+        changed.insert(point.id);
+    }
+    derived
 }
 
+/// Check totalizer definitions against each other and the computed points.
+///
+/// Totals are updated after computed points, so expressions must not read
+/// them.
+fn compile_integrators(
+    defs: &[IntegratorConfig],
+    computed: &[ComputedPoint],
+) -> Result<Vec<Integrator>> {
+    let mut ids: HashSet<PointId> = computed.iter().map(|p| p.id).collect();
+    for def in defs {
+        if !ids.insert(def.id) {
+            return Err(GatewayError::config(format!(
+                "integrator {} reuses the id of another computed point or integrator",
+                def.id
+            )));
+        }
+        if def.input == def.id || Some(def.id) == def.reset_point {
+            return Err(GatewayError::config(format!(
+                "integrator {} cannot use its own id as input or reset point",
+                def.id
+            )));
+        }
+        if !(def.time_base_secs.is_finite() && def.time_base_secs > 0.0) {
+            return Err(GatewayError::config(format!(
+                "integrator {}: time_base_secs must be greater than 0",
+                def.id
+            )));
+        }
+    }
+    for point in computed {
+        if let Some(total) = defs.iter().find(|d| point.inputs.contains(&d.id)) {
+            return Err(GatewayError::config(format!(
+                "computed point {} cannot use the total of integrator {}",
+                point.id, total.id
+            )));
+        }
+    }
+
+    Ok(defs.iter().cloned().map(Integrator::new).collect())
+}
+
+// ============================================================================
+// Strongly-typed mapping configs for JSON deserialization
+// ============================================================================
+
+pub use crate::gateway::params::VirtualChannelParamsConfig;
+
 impl VirtualChannelParamsConfig {
     /// Convert to VirtualChannelConfig.
     pub fn to_config(&self) -> VirtualChannelConfig {
-        VirtualChannelConfig::new(&self.name).with_buffer_size(self.buffer_size)
+        VirtualChannelConfig::new(&self.name)
+            .with_buffer_size(self.buffer_size)
+            .with_computed(self.computed.clone())
+            .with_integrators(self.integrators.clone())
+            .with_strict(self.strict)
     }
 }
 
-/// Virtual channel diagnostics.
-#[derive(Debug, Default)]
-struct VirtualDiagnostics {
-    write_count: u64,
-    read_count: u64,
-    points_stored: usize,
-}
-
 /// Virtual channel implementation.
 ///
 /// This channel type:
@@ -145,26 +414,87 @@ struct VirtualDiagnostics {
 pub struct VirtualChannel {
     config: VirtualChannelConfig,
     /// Internal data buffer: point_id -> DataPoint
-    data_buffer: DashMap<u32, DataPoint>,
-    diagnostics: Arc<RwLock<VirtualDiagnostics>>,
-    /// Broadcast sender for event-driven subscribers.
-    event_tx: DataEventSender,
-    event_handler: Option<Arc<dyn DataEventHandler>>,
+    data_buffer: Arc<DashMap<u32, DataPoint>>,
+    /// Index of each configured point in `config.points`.
+    point_index: HashMap<PointId, usize>,
+    /// Ids of computed points, totals and reset points, accepted in strict
+    /// mode.
+    derived_ids: HashSet<PointId>,
+    /// Points refused in strict mode.
+    rejected: AtomicU64,
+    /// Last point refused in strict mode, and why.
+    last_rejected: Mutex<Option<(PointId, String)>>,
+    /// Computed points in dependency order.
+    computed: Arc<Vec<ComputedPoint>>,
+    integrators: Mutex<Vec<Integrator>>,
+    /// TTL deadlines of the configured points.
+    expiry: Arc<Mutex<Expiry>>,
+    /// Wakes the sweep when a deadline earlier than its next one is set.
+    expiry_changed: Arc<Notify>,
+    sweep: Option<JoinHandle<()>>,
+    diagnostics: Arc<DiagnosticsRecorder>,
+    /// Event bus for event-driven subscribers.
+    event_bus: EventBus,
+    event_handler: HandlerSlot,
 }
 
+/// Points refused in strict mode, with the reason.
+type Refused = Vec<(PointId, String)>;
+
+/// Event handler shared with the sweep, which may start before it is set.
+type HandlerSlot = Arc<Mutex<Option<Arc<dyn DataEventHandler>>>>;
+
 impl VirtualChannel {
     /// Create a new virtual channel.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the configured computed points are invalid. Use
+    /// [`try_new()`](Self::try_new) for configurations that contain them.
     pub fn new(config: VirtualChannelConfig) -> Self {
-        // Use broadcast channel for multiple subscribers
-        let (event_tx, _) = broadcast::channel(config.buffer_size);
+        match Self::try_new(config) {
+            Ok(channel) => channel,
+            Err(e) => panic!("invalid virtual channel config: {}", e),
+        }
+    }
 
-        Self {
+    /// Create a new virtual channel, validating computed points.
+    ///
+    /// Returns `GatewayError::Config` if an expression does not parse, the
+    /// computed points depend on each other in a cycle or an integrator is
+    /// invalid.
+    pub fn try_new(config: VirtualChannelConfig) -> Result<Self> {
+        let computed = compile_computed(&config.computed)?;
+        let integrators = compile_integrators(&config.integrators, &computed)?;
+        let event_bus = EventBus::new(config.buffer_size);
+        let expiry = Expiry::new(&config.points);
+        let derived_ids = computed
+            .iter()
+            .map(|p| p.id)
+            .chain(
+                config
+                    .integrators
+                    .iter()
+                    .flat_map(|i| std::iter::once(i.id).chain(i.reset_point)),
+            )
+            .collect();
+
+        Ok(Self {
+            point_index: index_points(&config.points),
+            derived_ids,
+            rejected: AtomicU64::new(0),
+            last_rejected: Mutex::new(None),
             config,
-            data_buffer: DashMap::new(),
-            diagnostics: Arc::new(RwLock::new(VirtualDiagnostics::default())),
-            event_tx,
-            event_handler: None,
-        }
+            data_buffer: Arc::new(DashMap::new()),
+            computed: Arc::new(computed),
+            integrators: Mutex::new(integrators),
+            expiry: Arc::new(Mutex::new(expiry)),
+            expiry_changed: Arc::new(Notify::new()),
+            sweep: None,
+            diagnostics: Arc::new(DiagnosticsRecorder::new()),
+            event_bus,
+            event_handler: Arc::new(Mutex::new(None)),
+        })
     }
 
     /// Get the channel name.
@@ -172,39 +502,248 @@ impl VirtualChannel {
         &self.config.name
     }
 
+    /// Replace the point configurations.
+    ///
+    /// Buffered values are kept; computed points are not affected. Changed
+    /// TTLs apply from the next write of a point.
+    pub fn set_points(&mut self, points: Vec<PointConfig>) {
+        lock(&self.expiry).set_points(&points);
+        self.point_index = index_points(&points);
+        self.config.points = points;
+    }
+
     /// Write a data batch directly to this channel.
     ///
     /// This is the primary method for feeding data into a virtual channel.
     /// Data is stored internally and can be retrieved via `poll_once()`.
-    pub async fn write(&self, batch: &DataBatch) -> Result<()> {
-        // Store to internal buffer
+    ///
+    /// Accepts an owned batch or a shared `Arc<DataBatch>`; a shared batch
+    /// is forwarded to subscribers without copying unless computed points
+    /// have to be added to it or the channel is strict.
+    ///
+    /// In [`StrictMode::Reject`], fails with `GatewayError::InvalidData`
+    /// and stores nothing if a point does not match its configuration.
+    pub async fn write(&self, batch: impl Into<Arc<DataBatch>>) -> Result<()> {
+        let (batch, _) = self.check(batch.into(), true)?;
+        self.store(batch).await
+    }
+
+    /// Write commands, checked like [`write()`](Self::write) but not
+    /// transformed.
+    ///
+    /// Commands dropped in [`StrictMode::Drop`] are reported as failures
+    /// with the reason they were refused.
+    async fn write_commands(&self, batch: DataBatch) -> Result<WriteResult> {
+        let (batch, refused) = self.check(Arc::new(batch), false)?;
+        let mut result = WriteResult::success(batch.len());
+        result.failures = refused;
+        self.store(batch).await?;
+        Ok(result)
+    }
+
+    /// Check `batch` against the configured points in strict mode and
+    /// apply their transforms if `transform` is set.
+    ///
+    /// Returns the points to store and the refused points with the reason.
+    fn check(&self, batch: Arc<DataBatch>, transform: bool) -> Result<(Arc<DataBatch>, Refused)> {
+        if self.config.strict == StrictMode::Off {
+            return Ok((batch, Vec::new()));
+        }
+
+        let mut checked = DataBatch::new();
+        let mut refused = Vec::new();
         for point in batch.iter() {
-            self.data_buffer.insert(point.id, point.clone());
+            match self.check_point(point, transform) {
+                Ok(point) => checked.add(point),
+                Err(reason) => {
+                    #[cfg(feature = "tracing-support")]
+                    tracing::warn!("Virtual channel '{}': {}", self.config.name, reason);
+                    self.rejected.fetch_add(1, Ordering::Relaxed);
+                    *lock(&self.last_rejected) = Some((point.id, reason.clone()));
+                    refused.push((point.id, reason));
+                }
+            }
+        }
+        match refused.first() {
+            Some((_, reason)) if self.config.strict == StrictMode::Reject => {
+                Err(GatewayError::invalid_data(format!(
+                    "virtual channel '{}': {}",
+                    self.config.name, reason
+                )))
+            }
+            _ => Ok((Arc::new(checked), refused)),
         }
+    }
 
-        // Emit event to all subscribers (broadcast is sync, not async)
-        let _ = self.event_tx.send(DataEvent::DataUpdate(batch.clone()));
+    /// The point as stored, or why it is refused.
+    fn check_point(
+        &self,
+        point: &DataPoint,
+        transform: bool,
+    ) -> std::result::Result<DataPoint, String> {
+        let Some(&index) = self.point_index.get(&point.id) else {
+            if self.derived_ids.contains(&point.id) {
+                return Ok(point.clone());
+            }
+            return Err(format!("point {} is not configured", point.id));
+        };
+        let config = &self.config.points[index];
 
-        // Update diagnostics
+        if let Some(expected) = config.data_type {
+            let kind = point.value.kind();
+            if kind != expected && (expected, kind) != (ValueKind::Float, ValueKind::Integer) {
+                return Err(format!(
+                    "point {} expects a {} value, got {}",
+                    point.id,
+                    expected.name(),
+                    kind.name()
+                ));
+            }
+        }
+        if !transform {
+            return Ok(point.clone());
+        }
+
+        // Quality already bad as written wins over the range check
+        let (value, range_quality) = config.transform.apply_value(point.value.clone());
+        let quality = if point.quality.is_good() {
+            range_quality
+        } else {
+            point.quality
+        };
+        Ok(DataPoint {
+            value,
+            quality,
+            ..point.clone()
+        })
+    }
+
+    /// Store a checked batch, add computed points and totals and emit it.
+    async fn store(&self, batch: Arc<DataBatch>) -> Result<()> {
+        // Store to internal buffer, under the expiry lock so that the sweep
+        // either degrades the old values or sees the restarted TTLs
         {
-            let mut diag = self.diagnostics.write().await;
-            diag.write_count += 1;
-            diag.points_stored = self.data_buffer.len();
+            let mut expiry = lock(&self.expiry);
+            for point in batch.iter() {
+                self.data_buffer.insert(point.id, point.clone());
+            }
+            self.restart_ttl(&mut expiry, &batch);
         }
 
+        let mut derived = self.recompute(&batch);
+        let totals = self.integrate(&batch, &derived);
+        derived.merge(totals);
+        let batch = if derived.is_empty() {
+            batch
+        } else {
+            self.restart_ttl(&mut lock(&self.expiry), &derived);
+            let mut merged = Arc::unwrap_or_clone(batch);
+            merged.merge(derived);
+            Arc::new(merged)
+        };
+
+        // Emit event to all subscribers (non-blocking)
+        let _ = self
+            .event_bus
+            .publish(DataEvent::DataUpdate(Arc::clone(&batch)));
+
+        self.diagnostics.record_write(1);
+
         // Call event handler if set
-        if let Some(handler) = &self.event_handler {
-            handler.on_data_update(batch.clone()).await;
+        let handler = lock(&self.event_handler).clone();
+        if let Some(handler) = handler {
+            handler.on_data_update(batch).await;
         }
 
         Ok(())
     }
 
+    /// Recompute computed points affected by `batch`.
+    fn recompute(&self, batch: &DataBatch) -> DataBatch {
+        recompute(&self.computed, &self.data_buffer, batch)
+    }
+
+    /// Restart the TTL of the points written in `batch`.
+    fn restart_ttl(&self, expiry: &mut Expiry, batch: &DataBatch) {
+        if expiry.is_empty() {
+            return;
+        }
+        let now = tokio::time::Instant::now();
+        let mut earlier = false;
+        for point in batch.iter() {
+            earlier |= expiry.written(point.id, now);
+        }
+        if earlier {
+            self.expiry_changed.notify_one();
+        }
+    }
+
+    /// Update totals from the input samples in `batch` and the computed
+    /// points derived from it, and apply reset commands.
+    fn integrate(&self, batch: &DataBatch, derived: &DataBatch) -> DataBatch {
+        let mut totals = DataBatch::new();
+        let mut integrators = self.integrators.lock().unwrap_or_else(|e| e.into_inner());
+        for integrator in integrators.iter_mut() {
+            for point in batch.iter().chain(derived.iter()) {
+                let total = if Some(point.id) == integrator.config.reset_point {
+                    point
+                        .value
+                        .as_bool()
+                        .unwrap_or(false)
+                        .then(|| integrator.reset())
+                } else if point.id == integrator.config.input {
+                    integrator.sample(point)
+                } else {
+                    None
+                };
+                if let Some(total) = total {
+                    self.data_buffer.insert(total.id, total.clone());
+                    totals.add(total);
+                }
+            }
+        }
+        totals
+    }
+
+    /// Pre-populate the buffer without emitting events.
+    ///
+    /// Intended for restoring last-known values from a
+    /// [`DataStore`](crate::store::DataStore) at startup; points already in
+    /// the buffer are kept. Restored totals are where integrators continue.
+    pub fn restore(&self, batch: &DataBatch) {
+        let mut integrators = self.integrators.lock().unwrap_or_else(|e| e.into_inner());
+        for point in batch.iter() {
+            if self.data_buffer.contains_key(&point.id) {
+                continue;
+            }
+            self.data_buffer.insert(point.id, point.clone());
+            if let Some(integrator) = integrators.iter_mut().find(|i| i.config.id == point.id) {
+                integrator.restore(point);
+            }
+        }
+    }
+
     /// Write a single data point.
     pub async fn write_point(&self, point: DataPoint) -> Result<()> {
         let mut batch = DataBatch::new();
         batch.add(point);
-        self.write(&batch).await
+        self.write(batch).await
+    }
+
+    /// Read the buffered values selected by `request`.
+    ///
+    /// Poll groups are those of the configured points; computed points and
+    /// integrator totals have none. Requested points that were never written
+    /// are left out.
+    pub fn read(&self, request: &ReadRequest) -> ReadResponse {
+        let groups = poll_group_map(&self.config.points);
+        let batch = self
+            .data_buffer
+            .iter()
+            .filter(|entry| request.matches(entry.value(), groups.get(entry.key()).copied()))
+            .map(|entry| entry.value().clone())
+            .collect();
+        ReadResponse::success(batch)
     }
 
     /// Get all points currently in the buffer.
@@ -217,6 +756,96 @@ impl VirtualChannel {
     }
 }
 
+/// Background task degrading points whose TTL ran out.
+struct Sweep {
+    expiry: Arc<Mutex<Expiry>>,
+    expiry_changed: Arc<Notify>,
+    data_buffer: Arc<DashMap<u32, DataPoint>>,
+    computed: Arc<Vec<ComputedPoint>>,
+    event_bus: EventBus,
+    event_handler: HandlerSlot,
+}
+
+impl Sweep {
+    async fn run(self) {
+        loop {
+            let next = lock(&self.expiry).next_deadline();
+            match next {
+                Some(deadline) => tokio::select! {
+                    _ = tokio::time::sleep_until(deadline) => {}
+                    _ = self.expiry_changed.notified() => continue,
+                },
+                None => {
+                    self.expiry_changed.notified().await;
+                    continue;
+                }
+            }
+
+            // Writes buffer their values under the same lock, so none lands
+            // between the expiry and the degradation
+            let batch = {
+                let mut expiry = lock(&self.expiry);
+                let expired = expiry.expire(tokio::time::Instant::now());
+                self.degrade(expired)
+            };
+            self.emit(batch).await;
+        }
+    }
+
+    /// Give the expired points their new quality and return the
+    /// transitions.
+    ///
+    /// A point already of worse quality, e.g. `CommFailure` as written,
+    /// keeps it.
+    fn degrade(&self, expired: Vec<(PointId, Quality)>) -> DataBatch {
+        let mut batch = DataBatch::new();
+        for (id, quality) in expired {
+            let Some(mut point) = self.data_buffer.get_mut(&id) else {
+                continue;
+            };
+            if point.quality == quality || point.quality.severity() > quality.severity() {
+                continue;
+            }
+            point.quality = quality;
+            batch.add(point.clone());
+        }
+        batch
+    }
+
+    /// Emit degraded points with the computed points derived from them.
+    async fn emit(&self, mut batch: DataBatch) {
+        if batch.is_empty() {
+            return;
+        }
+
+        #[cfg(feature = "tracing-support")]
+        tracing::debug!("{} virtual points degraded after their TTL", batch.len());
+
+        let derived = recompute(&self.computed, &self.data_buffer, &batch);
+        batch.merge(derived);
+        let batch = Arc::new(batch);
+        let _ = self
+            .event_bus
+            .publish(DataEvent::DataUpdate(Arc::clone(&batch)));
+        let handler = lock(&self.event_handler).clone();
+        if let Some(handler) = handler {
+            handler.on_data_update(batch).await;
+        }
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+impl Drop for VirtualChannel {
+    fn drop(&mut self) {
+        if let Some(task) = &self.sweep {
+            task.abort();
+        }
+    }
+}
+
 impl HasMetadata for VirtualChannel {
     fn metadata() -> DriverMetadata {
         DriverMetadata {
@@ -250,6 +879,27 @@ impl HasMetadata for VirtualChannel {
                     ParameterType::String,
                     serde_json::json!("aggregation"),
                 ),
+                ParameterMetadata::optional(
+                    "computed",
+                    "Computed Points",
+                    "Derived points: [{ id, expression }], e.g. \"sqrt(p1^2 + p2^2)\"",
+                    ParameterType::Array,
+                    serde_json::json!([]),
+                ),
+                ParameterMetadata::optional(
+                    "integrators",
+                    "Totalizers",
+                    "Running totals: [{ id, input, time_base_secs, max_gap_ms, reset_point }]",
+                    ParameterType::Array,
+                    serde_json::json!([]),
+                ),
+                ParameterMetadata::optional(
+                    "strict",
+                    "Strict Mode",
+                    "Checking of written points against the configured ones: 'off', 'drop' or 'reject'",
+                    ParameterType::String,
+                    serde_json::json!("off"),
+                ),
             ],
         }
     }
@@ -272,20 +922,19 @@ impl Protocol for VirtualChannel {
     }
 
     async fn diagnostics(&self) -> Result<Diagnostics> {
-        let diag = self.diagnostics.read().await;
-
-        Ok(Diagnostics {
-            protocol: "Virtual".to_string(),
-            connection_state: ConnectionState::Connected,
-            read_count: diag.read_count,
-            write_count: diag.write_count,
-            error_count: 0,
-            last_error: None,
-            extra: serde_json::json!({
-                "name": self.config.name,
-                "points_stored": diag.points_stored,
-            }),
-        })
+        let mut diag = self
+            .diagnostics
+            .to_diagnostics("Virtual", ConnectionState::Connected);
+        let last_rejected = lock(&self.last_rejected).as_ref().map(
+            |(point_id, reason)| serde_json::json!({ "point_id": point_id, "reason": reason }),
+        );
+        diag.extra = serde_json::json!({
+            "name": self.config.name,
+            "points_stored": self.data_buffer.len(),
+            "rejected_points": self.rejected.load(Ordering::Relaxed),
+            "last_rejected": last_rejected,
+        });
+        Ok(diag)
     }
 }
 
@@ -306,11 +955,10 @@ impl ProtocolClient for VirtualChannel {
     /// the accumulated data. The service layer should call this to get
     /// data that was pushed via `write()`.
     async fn poll_once(&mut self) -> PollResult {
+        let start = std::time::Instant::now();
         let batch = self.get_all_points();
-        {
-            let mut diag = self.diagnostics.write().await;
-            diag.read_count += 1;
-        }
+        self.diagnostics.record_read(1);
+        self.diagnostics.record_poll(start.elapsed());
         PollResult::success(batch)
     }
 
@@ -320,8 +968,7 @@ impl ProtocolClient for VirtualChannel {
         for cmd in commands {
             batch.add(DataPoint::new(cmd.id, cmd.value));
         }
-        self.write(&batch).await?;
-        Ok(WriteResult::success(commands.len()))
+        self.write_commands(batch).await
     }
 
     async fn write_adjustment(&mut self, adjustments: &[AdjustmentCommand]) -> Result<WriteResult> {
@@ -330,8 +977,7 @@ impl ProtocolClient for VirtualChannel {
         for adj in adjustments {
             batch.add(DataPoint::new(adj.id, adj.value));
         }
-        self.write(&batch).await?;
-        Ok(WriteResult::success(adjustments.len()))
+        self.write_commands(batch).await
     }
 }
 
@@ -339,20 +985,33 @@ impl EventDrivenProtocol for VirtualChannel {
     fn subscribe(&self) -> DataEventReceiver {
         // Broadcast channel supports multiple subscribers
         // Each call to subscribe() returns a new receiver that gets all future events
-        self.event_tx.subscribe()
+        self.event_bus.subscribe()
     }
 
     fn set_event_handler(&mut self, handler: Arc<dyn DataEventHandler>) {
-        self.event_handler = Some(handler);
+        *lock(&self.event_handler) = Some(handler);
     }
 
     async fn start(&mut self) -> Result<()> {
-        // Virtual channel is always "started" - no-op
+        // Virtual channel is always "started"; only the TTL sweep runs
+        if self.sweep.is_none() {
+            let sweep = Sweep {
+                expiry: Arc::clone(&self.expiry),
+                expiry_changed: Arc::clone(&self.expiry_changed),
+                data_buffer: Arc::clone(&self.data_buffer),
+                computed: Arc::clone(&self.computed),
+                event_bus: self.event_bus.clone(),
+                event_handler: Arc::clone(&self.event_handler),
+            };
+            self.sweep = Some(tokio::spawn(sweep.run()));
+        }
         Ok(())
     }
 
     async fn stop(&mut self) -> Result<()> {
-        // Virtual channel doesn't need explicit stop - no-op
+        if let Some(task) = self.sweep.take() {
+            task.abort();
+        }
         Ok(())
     }
 }
@@ -412,6 +1071,7 @@ mod tests {
         let diag = channel.diagnostics().await.unwrap();
         assert_eq!(diag.write_count, 2);
         assert_eq!(diag.protocol, "Virtual");
+        assert_eq!(diag.extra["points_stored"], 2);
     }
 
     #[tokio::test]
@@ -438,4 +1098,401 @@ mod tests {
             _ => panic!("Expected DataUpdate events"),
         }
     }
+
+    #[tokio::test]
+    async fn test_computed_point() {
+        let config = VirtualChannelConfig::new("computed").with_computed(vec![
+            ComputedPointConfig::new(3, "sqrt(p1^2 + p2^2)"),
+            ComputedPointConfig::new(4, "p3 * 2"),
+        ]);
+        let channel = VirtualChannel::try_new(config).unwrap();
+        let mut rx = channel.subscribe();
+
+        // Not computed until every input has a value
+        channel.write_point(DataPoint::new(1, 3.0)).await.unwrap();
+        assert!(channel.data_buffer.get(&3).is_none());
+
+        channel
+            .write_point(DataPoint::new(2, 4.0).with_quality(Quality::LastKnown))
+            .await
+            .unwrap();
+
+        let p3 = channel.data_buffer.get(&3).unwrap().clone();
+        assert_eq!(p3.value, Value::Float(5.0));
+        assert_eq!(p3.quality, Quality::LastKnown);
+        assert_eq!(
+            channel.data_buffer.get(&4).unwrap().value,
+            Value::Float(10.0)
+        );
+
+        // Inputs and derived points arrive in the same event
+        rx.recv().await.unwrap();
+        match rx.recv().await.unwrap() {
+            DataEvent::DataUpdate(batch) => {
+                let ids: Vec<_> = batch.iter().map(|p| p.id).collect();
+                assert_eq!(ids, vec![2, 3, 4]);
+            }
+            other => panic!("Expected DataUpdate, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_computed_dependency_order() {
+        let computed = compile_computed(&[
+            ComputedPointConfig::new(5, "p4 + 1"),
+            ComputedPointConfig::new(4, "p1 + 1"),
+        ])
+        .unwrap();
+        let order: Vec<_> = computed.iter().map(|p| p.id).collect();
+        assert_eq!(order, vec![4, 5]);
+    }
+
+    #[test]
+    fn test_computed_cycle_rejected() {
+        let config = VirtualChannelConfig::new("cycle").with_computed(vec![
+            ComputedPointConfig::new(10, "p11 + 1"),
+            ComputedPointConfig::new(11, "p12 * 2"),
+            ComputedPointConfig::new(12, "p10 - p1"),
+        ]);
+        match VirtualChannel::try_new(config) {
+            Err(GatewayError::Config(msg)) => {
+                assert!(msg.contains("10 -> 11 -> 12 -> 10"), "{}", msg)
+            }
+            other => panic!("expected cycle error, got {:?}", other.err()),
+        }
+
+        let config = VirtualChannelConfig::new("self")
+            .with_computed(vec![ComputedPointConfig::new(1, "p1 + 1")]);
+        assert!(VirtualChannel::try_new(config).is_err());
+    }
+
+    #[test]
+    fn test_params_config_computed() {
+        let params: VirtualChannelParamsConfig = serde_json::from_value(serde_json::json!({
+            "name": "hub",
+            "computed": [{ "id": 3, "expression": "p1 + p2" }]
+        }))
+        .unwrap();
+        assert_eq!(params.to_config().computed.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_integrator_total_and_reset() {
+        let params: VirtualChannelParamsConfig = serde_json::from_value(serde_json::json!({
+            "integrators": [{ "id": 20, "input": 1, "reset_point": 21 }]
+        }))
+        .unwrap();
+        let mut channel = VirtualChannel::try_new(params.to_config()).unwrap();
+        channel.restore(&DataBatch::from_points(vec![
+            DataPoint::new(20, 100.0).with_quality(Quality::LastKnown)
+        ]));
+
+        let start = chrono::Utc::now();
+        for (seconds, value) in [(0, 1.0), (10, 2.0)] {
+            let mut point = DataPoint::new(1, value);
+            point.timestamp = start + chrono::Duration::seconds(seconds);
+            channel.write_point(point).await.unwrap();
+        }
+        // 100 restored + (1 + 2) / 2 * 10 s
+        let total = channel.data_buffer.get(&20).unwrap().clone();
+        assert_eq!(
+            (total.value, total.quality),
+            (Value::Float(115.0), Quality::Good)
+        );
+
+        channel
+            .write_control(&[ControlCommand::latching(21, true)])
+            .await
+            .unwrap();
+        assert_eq!(
+            channel.data_buffer.get(&20).unwrap().value,
+            Value::Float(0.0)
+        );
+    }
+
+    #[test]
+    fn test_integrator_config_rejected() {
+        let invalid = [
+            vec![IntegratorConfig::new(3, 1)],
+            vec![IntegratorConfig::new(5, 5)],
+            vec![IntegratorConfig::new(5, 1).with_time_base(0.0)],
+            vec![IntegratorConfig::new(4, 1)],
+        ];
+        for integrators in invalid {
+            let config = VirtualChannelConfig::new("totals")
+                .with_computed(vec![ComputedPointConfig::new(3, "p4 + 1")])
+                .with_integrators(integrators);
+            assert!(VirtualChannel::try_new(config).is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_restore_last_known() {
+        let channel = VirtualChannel::new(VirtualChannelConfig::new("restore"));
+        let mut rx = channel.subscribe();
+        channel.write_point(DataPoint::new(1, 9.0)).await.unwrap();
+        rx.recv().await.unwrap();
+
+        let stored = DataBatch::from_points(vec![
+            DataPoint::new(1, 1.0).with_quality(Quality::LastKnown),
+            DataPoint::new(2, 2.0).with_quality(Quality::LastKnown),
+        ]);
+        channel.restore(&stored);
+
+        assert_eq!(
+            channel.data_buffer.get(&1).unwrap().value,
+            Value::Float(9.0)
+        );
+        assert_eq!(
+            channel.data_buffer.get(&2).unwrap().quality,
+            Quality::LastKnown
+        );
+        assert!(rx.try_recv().is_none());
+    }
+
+    fn strict_channel(strict: StrictMode) -> VirtualChannel {
+        use crate::core::point::{ProtocolAddress, TransformConfig};
+
+        let mut scaled = PointConfig::new(1, ProtocolAddress::Generic("1".into()))
+            .with_data_type(ValueKind::Float);
+        scaled.transform = TransformConfig {
+            scale: 0.1,
+            max_value: Some(50.0),
+            ..Default::default()
+        };
+        let points = vec![
+            scaled,
+            PointConfig::new(2, ProtocolAddress::Generic("2".into()))
+                .with_data_type(ValueKind::Bool),
+        ];
+        let config = VirtualChannelConfig::new("strict")
+            .with_points(points)
+            .with_computed(vec![ComputedPointConfig::new(3, "p1 * 2")])
+            .with_strict(strict);
+        VirtualChannel::try_new(config).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_strict_drop() {
+        let mut channel = strict_channel(StrictMode::Drop);
+        channel
+            .write(DataBatch::from_points(vec![
+                DataPoint::new(1, 250i64),
+                DataPoint::new(2, 1.0),
+                DataPoint::new(7, 1.0),
+            ]))
+            .await
+            .unwrap();
+
+        // Transformed; computed from the transformed value
+        let p1 = channel.data_buffer.get(&1).unwrap().clone();
+        assert_eq!((p1.value, p1.quality), (Value::Float(25.0), Quality::Good));
+        assert_eq!(
+            channel.data_buffer.get(&3).unwrap().value,
+            Value::Float(50.0)
+        );
+        assert!(channel.data_buffer.get(&2).is_none());
+        assert!(channel.data_buffer.get(&7).is_none());
+
+        // Range check of the transform; bad quality as written wins
+        channel.write_point(DataPoint::new(1, 600.0)).await.unwrap();
+        assert_eq!(
+            channel.data_buffer.get(&1).unwrap().quality,
+            Quality::Overflow
+        );
+        channel
+            .write_point(DataPoint::new(1, 600.0).with_quality(Quality::CommFailure))
+            .await
+            .unwrap();
+        assert_eq!(
+            channel.data_buffer.get(&1).unwrap().quality,
+            Quality::CommFailure
+        );
+
+        // Commands are checked but not transformed; dropped ones fail
+        let result = channel
+            .write_adjustment(&[
+                AdjustmentCommand::new(1, 12.0),
+                AdjustmentCommand::new(9, 1.0),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(result.success_count, 1);
+        assert_eq!(
+            result.failures,
+            [(9, "point 9 is not configured".to_string())]
+        );
+        assert_eq!(
+            channel.data_buffer.get(&1).unwrap().value,
+            Value::Float(12.0)
+        );
+
+        let diag = channel.diagnostics().await.unwrap();
+        assert_eq!(diag.extra["rejected_points"], 3);
+        assert_eq!(
+            diag.extra["last_rejected"],
+            serde_json::json!({ "point_id": 9, "reason": "point 9 is not configured" })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_strict_reject() {
+        let channel = strict_channel(StrictMode::Reject);
+        let mut rx = channel.subscribe();
+        let result = channel
+            .write(DataBatch::from_points(vec![
+                DataPoint::new(1, 1.0),
+                DataPoint::new(2, "on"),
+            ]))
+            .await;
+        match result {
+            Err(GatewayError::InvalidData(msg)) => assert_eq!(
+                msg,
+                "virtual channel 'strict': point 2 expects a bool value, got string"
+            ),
+            other => panic!("expected InvalidData, got {:?}", other),
+        }
+        assert_eq!(channel.data_buffer.len(), 0);
+        assert!(rx.try_recv().is_none());
+
+        channel.write_point(DataPoint::new(2, true)).await.unwrap();
+        let diag = channel.diagnostics().await.unwrap();
+        assert_eq!(diag.extra["rejected_points"], 1);
+
+        // Off by default
+        let channel = VirtualChannel::new(VirtualChannelConfig::new("lenient"));
+        channel.write_point(DataPoint::new(7, "x")).await.unwrap();
+        let diag = channel.diagnostics().await.unwrap();
+        assert_eq!(diag.extra["rejected_points"], 0);
+        assert!(diag.extra["last_rejected"].is_null());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_point_ttl() {
+        use crate::core::point::ProtocolAddress;
+        use std::time::Duration;
+
+        let points = vec![
+            PointConfig::new(1, ProtocolAddress::Generic("1".into()))
+                .with_ttl(Duration::from_secs(5))
+                .with_out_of_service_after(Duration::from_secs(60)),
+            PointConfig::new(2, ProtocolAddress::Generic("2".into())),
+        ];
+        let config = VirtualChannelConfig::new("ttl")
+            .with_points(points)
+            .with_computed(vec![ComputedPointConfig::new(3, "p1 + p2")]);
+        let mut channel = VirtualChannel::try_new(config).unwrap();
+        channel.start().await.unwrap();
+        let mut rx = channel.subscribe();
+        async fn qualities(rx: &mut DataEventReceiver) -> Vec<(PointId, Quality)> {
+            match rx.recv().await.unwrap() {
+                DataEvent::DataUpdate(batch) => batch.iter().map(|p| (p.id, p.quality)).collect(),
+                other => panic!("Expected DataUpdate, got {:?}", other),
+            }
+        }
+
+        channel
+            .write(DataBatch::from_points(vec![
+                DataPoint::new(1, 1.0),
+                DataPoint::new(2, 2.0),
+            ]))
+            .await
+            .unwrap();
+        assert_eq!(qualities(&mut rx).await.len(), 3);
+
+        // Writes restart the TTL
+        tokio::time::sleep(Duration::from_secs(4)).await;
+        channel.write_point(DataPoint::new(1, 1.5)).await.unwrap();
+        qualities(&mut rx).await;
+        tokio::time::sleep(Duration::from_secs(4)).await;
+        assert!(rx.try_recv().is_none());
+
+        // 5 s after the last write, with the computed point
+        assert_eq!(
+            qualities(&mut rx).await,
+            vec![(1, Quality::LastKnown), (3, Quality::LastKnown)]
+        );
+        let start = tokio::time::Instant::now();
+        assert_eq!(
+            qualities(&mut rx).await,
+            vec![(1, Quality::OutOfService), (3, Quality::OutOfService)]
+        );
+        assert_eq!(start.elapsed(), Duration::from_secs(55));
+        let p1 = channel.data_buffer.get(&1).unwrap().clone();
+        assert_eq!(p1.value, Value::Float(1.5));
+
+        // Point 2 has no TTL; the next write restores the quality
+        channel.write_point(DataPoint::new(1, 1.0)).await.unwrap();
+        assert_eq!(
+            qualities(&mut rx).await,
+            vec![(1, Quality::Good), (3, Quality::Good)]
+        );
+
+        channel.stop().await.unwrap();
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        assert!(rx.try_recv().is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_ttl_event_handler_set_after_start() {
+        use crate::core::point::ProtocolAddress;
+        use std::time::Duration;
+
+        #[derive(Default)]
+        struct Recorder(Mutex<Vec<(PointId, Quality)>>);
+
+        #[async_trait::async_trait]
+        impl DataEventHandler for Recorder {
+            async fn on_data_update(&self, batch: Arc<DataBatch>) {
+                lock(&self.0).extend(batch.iter().map(|p| (p.id, p.quality)));
+            }
+            async fn on_connection_changed(&self, _state: ConnectionState) {}
+            async fn on_error(&self, _error: &str) {}
+        }
+
+        let points = vec![PointConfig::new(1, ProtocolAddress::Generic("1".into()))
+            .with_ttl(Duration::from_secs(5))];
+        let mut channel = VirtualChannel::new(VirtualChannelConfig::new("ttl").with_points(points));
+        channel.start().await.unwrap();
+        let recorder = Arc::new(Recorder::default());
+        channel.set_event_handler(recorder.clone());
+
+        channel.write_point(DataPoint::new(1, 1.0)).await.unwrap();
+        tokio::time::sleep(Duration::from_secs(6)).await;
+        assert_eq!(
+            *lock(&recorder.0),
+            vec![(1, Quality::Good), (1, Quality::LastKnown)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_read_request_filters() {
+        use crate::core::point::ProtocolAddress;
+
+        let points = vec![
+            PointConfig::new(1, ProtocolAddress::Generic("1".into())).with_poll_group("fast"),
+            PointConfig::new(2, ProtocolAddress::Generic("2".into())).with_poll_group("slow"),
+            PointConfig::new(3, ProtocolAddress::Generic("3".into())).with_poll_group("fast"),
+        ];
+        let channel = VirtualChannel::new(VirtualChannelConfig::new("read").with_points(points));
+        channel
+            .write(DataBatch::from_points(vec![
+                DataPoint::new(1, 1.0),
+                DataPoint::new(2, 2.0),
+                DataPoint::new(3, 3.0).with_quality(Quality::CommFailure),
+                DataPoint::new(4, 4.0),
+            ]))
+            .await
+            .unwrap();
+
+        let ids = |request: ReadRequest| {
+            let mut ids: Vec<_> = channel.read(&request).data.iter().map(|p| p.id).collect();
+            ids.sort_unstable();
+            ids
+        };
+        assert_eq!(ids(ReadRequest::all()), vec![1, 2, 3, 4]);
+        assert_eq!(ids(ReadRequest::by_group("fast")), vec![1, 3]);
+        assert_eq!(ids(ReadRequest::bad_quality()), vec![3]);
+        assert_eq!(ids(ReadRequest::by_ids(vec![2, 4, 9])), vec![2, 4]);
+    }
 }
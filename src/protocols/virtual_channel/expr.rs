@@ -0,0 +1,451 @@
+//! Arithmetic expressions for computed virtual points.
+//!
+//! A deliberately small language so the virtual channel stays free of
+//! external dependencies:
+//!
+//! - numbers: `42`, `0.5`, `1e3`
+//! - point references: `p<id>`, e.g. `p101`
+//! - operators: `+ - * / %`, `^` (power, right-associative), unary `-`
+//! - functions: `sqrt abs min max floor ceil round`
+//!
+//! ```text
+//! sqrt(p1^2 + p2^2)
+//! ```
+
+use crate::core::data::PointId;
+use crate::core::error::{GatewayError, Result};
+
+/// A parsed expression.
+#[derive(Debug, Clone)]
+pub struct Expr {
+    root: Node,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Num(f64),
+    Point(PointId),
+    Neg(Box<Node>),
+    Binary(BinOp, Box<Node>, Box<Node>),
+    Call(Func, Vec<Node>),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Pow,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Func {
+    Sqrt,
+    Abs,
+    Min,
+    Max,
+    Floor,
+    Ceil,
+    Round,
+}
+
+impl Func {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "sqrt" => Self::Sqrt,
+            "abs" => Self::Abs,
+            "min" => Self::Min,
+            "max" => Self::Max,
+            "floor" => Self::Floor,
+            "ceil" => Self::Ceil,
+            "round" => Self::Round,
+            _ => return None,
+        })
+    }
+
+    /// Accepted argument count as (min, max).
+    fn arity(self) -> (usize, usize) {
+        match self {
+            Self::Min | Self::Max => (1, usize::MAX),
+            _ => (1, 1),
+        }
+    }
+}
+
+impl Expr {
+    /// Parse an expression.
+    pub fn parse(source: &str) -> Result<Self> {
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            pos: 0,
+        };
+        let root = parser.expr()?;
+        if let Some(token) = parser.peek() {
+            return Err(GatewayError::config(format!(
+                "unexpected {:?} in expression '{}'",
+                token, source
+            )));
+        }
+        Ok(Self { root })
+    }
+
+    /// Point ids referenced by the expression (sorted, deduplicated).
+    pub fn inputs(&self) -> Vec<PointId> {
+        let mut ids = Vec::new();
+        collect_inputs(&self.root, &mut ids);
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+
+    /// Evaluate the expression.
+    ///
+    /// Returns `None` if a referenced point has no value.
+    pub fn eval(&self, mut lookup: impl FnMut(PointId) -> Option<f64>) -> Option<f64> {
+        eval(&self.root, &mut lookup)
+    }
+}
+
+fn collect_inputs(node: &Node, ids: &mut Vec<PointId>) {
+    match node {
+        Node::Num(_) => {}
+        Node::Point(id) => ids.push(*id),
+        Node::Neg(inner) => collect_inputs(inner, ids),
+        Node::Binary(_, lhs, rhs) => {
+            collect_inputs(lhs, ids);
+            collect_inputs(rhs, ids);
+        }
+        Node::Call(_, args) => args.iter().for_each(|arg| collect_inputs(arg, ids)),
+    }
+}
+
+fn eval(node: &Node, lookup: &mut dyn FnMut(PointId) -> Option<f64>) -> Option<f64> {
+    Some(match node {
+        Node::Num(v) => *v,
+        Node::Point(id) => lookup(*id)?,
+        Node::Neg(inner) => -eval(inner, lookup)?,
+        Node::Binary(op, lhs, rhs) => {
+            let (a, b) = (eval(lhs, lookup)?, eval(rhs, lookup)?);
+            match op {
+                BinOp::Add => a + b,
+                BinOp::Sub => a - b,
+                BinOp::Mul => a * b,
+                BinOp::Div => a / b,
+                BinOp::Rem => a % b,
+                BinOp::Pow => a.powf(b),
+            }
+        }
+        Node::Call(func, args) => {
+            let args = args
+                .iter()
+                .map(|arg| eval(arg, lookup))
+                .collect::<Option<Vec<_>>>()?;
+            match func {
+                Func::Sqrt => args[0].sqrt(),
+                Func::Abs => args[0].abs(),
+                Func::Floor => args[0].floor(),
+                Func::Ceil => args[0].ceil(),
+                Func::Round => args[0].round(),
+                Func::Min => args.into_iter().fold(f64::INFINITY, f64::min),
+                Func::Max => args.into_iter().fold(f64::NEG_INFINITY, f64::max),
+            }
+        }
+    })
+}
+
+// ============================================================================
+// Tokenizer
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Num(f64),
+    Ident(String),
+    Op(char),
+    LParen,
+    RParen,
+    Comma,
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+
+    while let Some(&(start, c)) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '0'..='9' | '.' => {
+                let mut end = start;
+                let mut prev = c;
+                while let Some(&(i, c)) = chars.peek() {
+                    let exponent_sign = (c == '+' || c == '-') && (prev == 'e' || prev == 'E');
+                    if !(c.is_ascii_digit() || c == '.' || c == 'e' || c == 'E' || exponent_sign) {
+                        break;
+                    }
+                    end = i + c.len_utf8();
+                    prev = c;
+                    chars.next();
+                }
+                let text = &source[start..end];
+                let value = text.parse().map_err(|_| {
+                    GatewayError::config(format!(
+                        "invalid number '{}' in expression '{}'",
+                        text, source
+                    ))
+                })?;
+                tokens.push(Token::Num(value));
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut end = start;
+                while let Some(&(i, c)) = chars.peek() {
+                    if !(c.is_ascii_alphanumeric() || c == '_') {
+                        break;
+                    }
+                    end = i + c.len_utf8();
+                    chars.next();
+                }
+                tokens.push(Token::Ident(source[start..end].to_string()));
+            }
+            '+' | '-' | '*' | '/' | '%' | '^' => {
+                tokens.push(Token::Op(c));
+                chars.next();
+            }
+            '(' => {
+                tokens.push(Token::LParen);
+                chars.next();
+            }
+            ')' => {
+                tokens.push(Token::RParen);
+                chars.next();
+            }
+            ',' => {
+                tokens.push(Token::Comma);
+                chars.next();
+            }
+            other => {
+                return Err(GatewayError::config(format!(
+                    "unexpected character '{}' in expression '{}'",
+                    other, source
+                )));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+// ============================================================================
+// Parser
+// ============================================================================
+
+/// Recursive-descent parser.
+///
+/// ```text
+/// expr  := term (('+' | '-') term)*
+/// term  := unary (('*' | '/' | '%') unary)*
+/// unary := '-' unary | power
+/// power := atom ('^' unary)?
+/// atom  := number | point | func '(' expr (',' expr)* ')' | '(' expr ')'
+/// ```
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat_op(&mut self, ops: &[char]) -> Option<char> {
+        match self.peek() {
+            Some(Token::Op(op)) if ops.contains(op) => {
+                let op = *op;
+                self.pos += 1;
+                Some(op)
+            }
+            _ => None,
+        }
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<()> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => Err(GatewayError::config(format!(
+                "expected {:?}, found {:?}",
+                expected, token
+            ))),
+            None => Err(GatewayError::config(format!(
+                "expected {:?}, found end of expression",
+                expected
+            ))),
+        }
+    }
+
+    fn expr(&mut self) -> Result<Node> {
+        let mut node = self.term()?;
+        while let Some(op) = self.eat_op(&['+', '-']) {
+            let op = if op == '+' { BinOp::Add } else { BinOp::Sub };
+            node = Node::Binary(op, Box::new(node), Box::new(self.term()?));
+        }
+        Ok(node)
+    }
+
+    fn term(&mut self) -> Result<Node> {
+        let mut node = self.unary()?;
+        while let Some(op) = self.eat_op(&['*', '/', '%']) {
+            let op = match op {
+                '*' => BinOp::Mul,
+                '/' => BinOp::Div,
+                _ => BinOp::Rem,
+            };
+            node = Node::Binary(op, Box::new(node), Box::new(self.unary()?));
+        }
+        Ok(node)
+    }
+
+    fn unary(&mut self) -> Result<Node> {
+        if self.eat_op(&['-']).is_some() {
+            return Ok(Node::Neg(Box::new(self.unary()?)));
+        }
+        self.power()
+    }
+
+    fn power(&mut self) -> Result<Node> {
+        let base = self.atom()?;
+        if self.eat_op(&['^']).is_some() {
+            let exponent = self.unary()?;
+            return Ok(Node::Binary(BinOp::Pow, Box::new(base), Box::new(exponent)));
+        }
+        Ok(base)
+    }
+
+    fn atom(&mut self) -> Result<Node> {
+        match self.next() {
+            Some(Token::Num(v)) => Ok(Node::Num(v)),
+            Some(Token::LParen) => {
+                let node = self.expr()?;
+                self.expect(Token::RParen)?;
+                Ok(node)
+            }
+            Some(Token::Ident(name)) => {
+                if let Some(id) = parse_point_ref(&name) {
+                    return Ok(Node::Point(id));
+                }
+                let func = Func::from_name(&name).ok_or_else(|| {
+                    GatewayError::config(format!(
+                        "unknown identifier '{}' (points are written p<id>)",
+                        name
+                    ))
+                })?;
+                self.call(func, &name)
+            }
+            Some(token) => Err(GatewayError::config(format!(
+                "unexpected {:?} in expression",
+                token
+            ))),
+            None => Err(GatewayError::config("unexpected end of expression")),
+        }
+    }
+
+    fn call(&mut self, func: Func, name: &str) -> Result<Node> {
+        self.expect(Token::LParen)?;
+        let mut args = vec![self.expr()?];
+        while self.peek() == Some(&Token::Comma) {
+            self.pos += 1;
+            args.push(self.expr()?);
+        }
+        self.expect(Token::RParen)?;
+
+        let (min, max) = func.arity();
+        if args.len() < min || args.len() > max {
+            return Err(GatewayError::config(format!(
+                "{}() takes {} argument(s), got {}",
+                name,
+                if min == max {
+                    min.to_string()
+                } else {
+                    format!("at least {}", min)
+                },
+                args.len()
+            )));
+        }
+        Ok(Node::Call(func, args))
+    }
+}
+
+/// Parse `p<digits>` into a point id.
+fn parse_point_ref(name: &str) -> Option<PointId> {
+    let digits = name.strip_prefix('p')?;
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval_with(source: &str, values: &[(PointId, f64)]) -> Option<f64> {
+        Expr::parse(source).unwrap().eval(|id| {
+            values
+                .iter()
+                .find(|(point, _)| *point == id)
+                .map(|(_, value)| *value)
+        })
+    }
+
+    #[test]
+    fn test_apparent_power() {
+        let value = eval_with("sqrt(p1^2 + p2^2)", &[(1, 3.0), (2, 4.0)]);
+        assert_eq!(value, Some(5.0));
+        assert_eq!(
+            Expr::parse("sqrt(p1^2 + p2^2) + p1").unwrap().inputs(),
+            vec![1, 2]
+        );
+    }
+
+    #[test]
+    fn test_precedence() {
+        assert_eq!(eval_with("1 + 2 * 3", &[]), Some(7.0));
+        assert_eq!(eval_with("(1 + 2) * 3", &[]), Some(9.0));
+        assert_eq!(eval_with("-2^2", &[]), Some(-4.0));
+        assert_eq!(eval_with("2^3^2", &[]), Some(512.0));
+        assert_eq!(eval_with("10 - 4 - 3", &[]), Some(3.0));
+        assert_eq!(eval_with("max(1, p7, 3) + 1.5e1", &[(7, 9.0)]), Some(24.0));
+    }
+
+    #[test]
+    fn test_missing_input() {
+        assert_eq!(eval_with("p1 + p2", &[(1, 1.0)]), None);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for source in [
+            "",
+            "p1 +",
+            "(p1",
+            "foo(p1)",
+            "sqrt(1, 2)",
+            "p1 $ 2",
+            "p1 p2",
+        ] {
+            assert!(
+                matches!(Expr::parse(source), Err(GatewayError::Config(_))),
+                "{source:?} should not parse"
+            );
+        }
+    }
+}
@@ -0,0 +1,260 @@
+//! Totalizers for virtual channels.
+//!
+//! An integrator keeps a running total of another point of the channel,
+//! e.g. the volume (m³) flowed through a meter reporting m³/h:
+//!
+//! ```json
+//! { "id": 20, "input": 10, "time_base_secs": 3600, "max_gap_ms": 60000, "reset_point": 21 }
+//! ```
+//!
+//! - Each new input sample adds the trapezoid between it and the previous
+//!   sample, `(v0 + v1) / 2 * Δt / time_base_secs`, using the samples'
+//!   timestamps.
+//! - Samples that are not `Good` are skipped; the interval is then measured
+//!   from the last good sample to the next one.
+//! - An interval longer than `max_gap_ms` is not integrated, and the total
+//!   is marked `Quality::Uncertain` until it is reset.
+//! - Writing a control command to `reset_point` sets the total back to 0.
+//! - The total is an ordinary point of the channel, so the gateway stores
+//!   it and hands it back through
+//!   [`restore()`](super::VirtualChannel::restore) after a restart; the
+//!   integrator continues from there. A restart longer than `max_gap_ms`
+//!   counts as a gap.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::core::data::{DataPoint, PointId, Value};
+use crate::core::quality::Quality;
+
+/// A point holding the running total of another point.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntegratorConfig {
+    /// Id of the total.
+    pub id: PointId,
+
+    /// Id of the integrated point (a rate, e.g. flow or power).
+    pub input: PointId,
+
+    /// Seconds per time unit of the input's rate: 1 for per-second rates,
+    /// 3600 for per-hour rates such as m³/h or kW (giving kWh).
+    #[serde(default = "default_time_base")]
+    pub time_base_secs: f64,
+
+    /// Longest interval between good samples that is still integrated.
+    #[serde(default)]
+    pub max_gap_ms: Option<u64>,
+
+    /// Control point that resets the total to 0.
+    #[serde(default)]
+    pub reset_point: Option<PointId>,
+}
+
+fn default_time_base() -> f64 {
+    1.0
+}
+
+impl IntegratorConfig {
+    /// Integrate `input` into the total `id`, per second.
+    pub fn new(id: PointId, input: PointId) -> Self {
+        Self {
+            id,
+            input,
+            time_base_secs: default_time_base(),
+            max_gap_ms: None,
+            reset_point: None,
+        }
+    }
+
+    /// Set the seconds per time unit of the input's rate.
+    #[must_use]
+    pub fn with_time_base(mut self, seconds: f64) -> Self {
+        self.time_base_secs = seconds;
+        self
+    }
+
+    /// Set the longest interval that is still integrated.
+    #[must_use]
+    pub fn with_max_gap(mut self, max_gap: std::time::Duration) -> Self {
+        self.max_gap_ms = Some(max_gap.as_millis() as u64);
+        self
+    }
+
+    /// Set the control point that resets the total.
+    #[must_use]
+    pub fn with_reset_point(mut self, id: PointId) -> Self {
+        self.reset_point = Some(id);
+        self
+    }
+}
+
+/// Running state of one integrator.
+#[derive(Debug)]
+pub(super) struct Integrator {
+    pub(super) config: IntegratorConfig,
+    total: f64,
+    /// A gap was skipped since the last reset.
+    gap: bool,
+    /// Last good input sample.
+    last: Option<(DateTime<Utc>, f64)>,
+    /// Timestamp of the restored total, until the first sample arrives.
+    restored_at: Option<DateTime<Utc>>,
+}
+
+impl Integrator {
+    pub(super) fn new(config: IntegratorConfig) -> Self {
+        Self {
+            config,
+            total: 0.0,
+            gap: false,
+            last: None,
+            restored_at: None,
+        }
+    }
+
+    /// Take an input sample; returns the updated total if it moved on.
+    pub(super) fn sample(&mut self, point: &DataPoint) -> Option<DataPoint> {
+        if !point.quality.is_good() {
+            return None;
+        }
+        let value = point.value.as_f64().filter(|v| v.is_finite())?;
+        let at = point.timestamp;
+
+        match self.last {
+            Some((last_at, last_value)) => {
+                if at <= last_at {
+                    // Repeated or out-of-order sample
+                    return None;
+                }
+                if self.exceeds_gap(at - last_at) {
+                    self.gap = true;
+                } else {
+                    let seconds = (at - last_at).num_microseconds()? as f64 / 1e6;
+                    self.total += (last_value + value) / 2.0 * seconds / self.config.time_base_secs;
+                }
+            }
+            None => {
+                if let Some(restored_at) = self.restored_at.take() {
+                    if self.exceeds_gap(at - restored_at) {
+                        self.gap = true;
+                    }
+                }
+            }
+        }
+        self.last = Some((at, value));
+        Some(self.point(at))
+    }
+
+    /// Set the total back to 0.
+    pub(super) fn reset(&mut self) -> DataPoint {
+        self.total = 0.0;
+        self.gap = false;
+        self.restored_at = None;
+        self.point(Utc::now())
+    }
+
+    /// Continue from a stored total.
+    pub(super) fn restore(&mut self, point: &DataPoint) {
+        if let Some(total) = point.value.as_f64().filter(|v| v.is_finite()) {
+            self.total = total;
+            self.gap = point.quality == Quality::Uncertain;
+            self.restored_at = Some(point.timestamp);
+        }
+    }
+
+    fn exceeds_gap(&self, interval: chrono::Duration) -> bool {
+        self.config
+            .max_gap_ms
+            .is_some_and(|max| interval.num_milliseconds() > max as i64)
+    }
+
+    fn point(&self, at: DateTime<Utc>) -> DataPoint {
+        let quality = if self.gap {
+            Quality::Uncertain
+        } else {
+            Quality::Good
+        };
+        let mut point =
+            DataPoint::new(self.config.id, Value::Float(self.total)).with_quality(quality);
+        point.timestamp = at;
+        point
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(seconds: i64, value: f64) -> DataPoint {
+        let mut point = DataPoint::new(1, value);
+        point.timestamp = DateTime::<Utc>::UNIX_EPOCH + chrono::Duration::seconds(seconds);
+        point
+    }
+
+    fn total(point: Option<DataPoint>) -> (f64, Quality) {
+        let point = point.unwrap();
+        (point.value.as_f64().unwrap(), point.quality)
+    }
+
+    #[test]
+    fn test_trapezoidal_total() {
+        let mut integrator = Integrator::new(IntegratorConfig::new(2, 1).with_time_base(3600.0));
+        assert_eq!(total(integrator.sample(&at(0, 10.0))), (0.0, Quality::Good));
+        // 10 -> 20 m³/h over half an hour: 7.5 m³
+        assert_eq!(
+            total(integrator.sample(&at(1800, 20.0))),
+            (7.5, Quality::Good)
+        );
+        assert!(integrator.sample(&at(1800, 30.0)).is_none());
+    }
+
+    #[test]
+    fn test_bad_samples_and_gaps() {
+        let config = IntegratorConfig::new(2, 1).with_max_gap(std::time::Duration::from_secs(60));
+        let mut integrator = Integrator::new(config);
+        integrator.sample(&at(0, 1.0));
+        assert!(integrator
+            .sample(&at(10, 100.0).with_quality(Quality::CommFailure))
+            .is_none());
+        assert_eq!(
+            total(integrator.sample(&at(20, 1.0))),
+            (20.0, Quality::Good)
+        );
+
+        // Not integrated, but the total can no longer be trusted
+        assert_eq!(
+            total(integrator.sample(&at(200, 1.0))),
+            (20.0, Quality::Uncertain)
+        );
+        assert_eq!(
+            total(integrator.sample(&at(210, 1.0))),
+            (30.0, Quality::Uncertain)
+        );
+
+        assert_eq!(total(Some(integrator.reset())), (0.0, Quality::Good));
+        assert_eq!(
+            total(integrator.sample(&at(220, 1.0))),
+            (10.0, Quality::Good)
+        );
+    }
+
+    #[test]
+    fn test_restore_continues_total() {
+        let config = IntegratorConfig::new(2, 1).with_max_gap(std::time::Duration::from_secs(60));
+        let mut integrator = Integrator::new(config.clone());
+        integrator.restore(&at(100, 50.0).with_quality(Quality::LastKnown));
+        integrator.sample(&at(130, 1.0));
+        assert_eq!(
+            total(integrator.sample(&at(140, 1.0))),
+            (60.0, Quality::Good)
+        );
+
+        // Down for longer than max_gap
+        let mut integrator = Integrator::new(config);
+        integrator.restore(&at(100, 50.0).with_quality(Quality::LastKnown));
+        assert_eq!(
+            total(integrator.sample(&at(500, 1.0))),
+            (50.0, Quality::Uncertain)
+        );
+    }
+}
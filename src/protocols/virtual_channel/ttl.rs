@@ -0,0 +1,201 @@
+//! Expiry of point values that are no longer written.
+//!
+//! A point with a `ttl_ms` is marked `Quality::LastKnown` once it has not
+//! been written for that long, and `Quality::OutOfService` once it has not
+//! been written for its `out_of_service_ms`. Each point has at most one
+//! pending deadline, kept in a set ordered by deadline: a write moves the
+//! point's deadline, and [`Expiry::expire()`] only visits the points that
+//! are due, so the sweep costs nothing while values keep arriving.
+
+use std::collections::{BTreeSet, HashMap};
+
+use tokio::time::{Duration, Instant};
+
+use crate::core::data::PointId;
+use crate::core::point::PointConfig;
+use crate::core::quality::Quality;
+
+/// Degradation thresholds of a point, counted from its last write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Limits {
+    ttl: Duration,
+    out_of_service: Option<Duration>,
+}
+
+/// Pending degradation deadlines of the points with a TTL.
+#[derive(Debug, Default)]
+pub(super) struct Expiry {
+    limits: HashMap<PointId, Limits>,
+    /// Deadlines, earliest first.
+    queue: BTreeSet<(Instant, PointId)>,
+    /// Pending deadline of each point and the quality it then gets.
+    pending: HashMap<PointId, (Instant, Quality)>,
+}
+
+impl Expiry {
+    pub(super) fn new(points: &[PointConfig]) -> Self {
+        let mut expiry = Self::default();
+        expiry.set_points(points);
+        expiry
+    }
+
+    /// Replace the thresholds.
+    ///
+    /// Points that no longer have a TTL are not degraded any more; new
+    /// thresholds apply from the next write of a point.
+    pub(super) fn set_points(&mut self, points: &[PointConfig]) {
+        self.limits = points
+            .iter()
+            .filter(|p| p.enabled)
+            .filter_map(|p| {
+                let ttl = Duration::from_millis(p.ttl_ms.filter(|&ms| ms > 0)?);
+                let out_of_service = p.out_of_service_ms.map(Duration::from_millis);
+                Some((
+                    p.id,
+                    Limits {
+                        ttl,
+                        out_of_service,
+                    },
+                ))
+            })
+            .collect();
+        let removed: Vec<PointId> = self
+            .pending
+            .keys()
+            .copied()
+            .filter(|id| !self.limits.contains_key(id))
+            .collect();
+        for id in removed {
+            self.cancel(id);
+        }
+    }
+
+    /// Whether no point has a TTL.
+    pub(super) fn is_empty(&self) -> bool {
+        self.limits.is_empty()
+    }
+
+    /// Restart the TTL of a point written at `now`.
+    ///
+    /// Returns whether the new deadline is earlier than the earliest one so
+    /// far, i.e. the sweep has to wake up sooner than it planned.
+    pub(super) fn written(&mut self, id: PointId, now: Instant) -> bool {
+        let Some(limits) = self.limits.get(&id) else {
+            return false;
+        };
+        let deadline = now + limits.ttl;
+        let planned = self.next_deadline();
+        self.cancel(id);
+        self.schedule(id, deadline, Quality::LastKnown);
+        planned.is_none_or(|planned| deadline < planned)
+    }
+
+    /// The earliest pending deadline.
+    pub(super) fn next_deadline(&self) -> Option<Instant> {
+        self.queue.first().map(|&(deadline, _)| deadline)
+    }
+
+    /// Remove the deadlines due at `now` and return the quality each of
+    /// those points now has, in deadline order.
+    ///
+    /// A point past its `LastKnown` deadline is scheduled for
+    /// `OutOfService`, or goes there directly if that is due too.
+    pub(super) fn expire(&mut self, now: Instant) -> Vec<(PointId, Quality)> {
+        let mut expired = Vec::new();
+        while let Some(&(deadline, id)) = self.queue.first() {
+            if deadline > now {
+                break;
+            }
+            self.queue.pop_first();
+            let Some((_, quality)) = self.pending.remove(&id) else {
+                continue;
+            };
+            let out_of_service = self
+                .limits
+                .get(&id)
+                .and_then(|limits| {
+                    Some(deadline + limits.out_of_service?.saturating_sub(limits.ttl))
+                })
+                .filter(|_| quality == Quality::LastKnown);
+            match out_of_service {
+                Some(at) if at > now => {
+                    self.schedule(id, at, Quality::OutOfService);
+                    expired.push((id, quality));
+                }
+                Some(_) => expired.push((id, Quality::OutOfService)),
+                None => expired.push((id, quality)),
+            }
+        }
+        expired
+    }
+
+    fn schedule(&mut self, id: PointId, deadline: Instant, quality: Quality) {
+        self.queue.insert((deadline, id));
+        self.pending.insert(id, (deadline, quality));
+    }
+
+    fn cancel(&mut self, id: PointId) {
+        if let Some((deadline, _)) = self.pending.remove(&id) {
+            self.queue.remove(&(deadline, id));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::point::ProtocolAddress;
+
+    fn point(id: PointId, ttl_ms: u64, out_of_service_ms: Option<u64>) -> PointConfig {
+        let mut point = PointConfig::new(id, ProtocolAddress::Generic(id.to_string()))
+            .with_ttl(Duration::from_millis(ttl_ms));
+        point.out_of_service_ms = out_of_service_ms;
+        point
+    }
+
+    #[test]
+    fn test_deadlines() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut expiry = Expiry::new(&[point(1, 1000, Some(5000)), point(2, 500, None)]);
+
+        assert!(expiry.written(1, start));
+        assert!(expiry.written(2, start));
+        assert!(!expiry.written(3, start));
+        assert_eq!(expiry.next_deadline(), Some(at(500)));
+
+        // Writing a point moves its deadline
+        assert!(!expiry.written(2, at(400)));
+        assert_eq!(expiry.expire(at(899)), vec![]);
+        assert_eq!(
+            expiry.expire(at(1000)),
+            vec![(2, Quality::LastKnown), (1, Quality::LastKnown)]
+        );
+        assert_eq!(expiry.next_deadline(), Some(at(5000)));
+        assert_eq!(expiry.expire(at(5000)), vec![(1, Quality::OutOfService)]);
+        assert_eq!(expiry.next_deadline(), None);
+
+        // A late sweep skips straight to OutOfService
+        expiry.written(1, at(6000));
+        assert_eq!(expiry.expire(at(20_000)), vec![(1, Quality::OutOfService)]);
+        assert!(expiry.queue.is_empty() && expiry.pending.is_empty());
+    }
+
+    #[test]
+    fn test_set_points() {
+        let start = Instant::now();
+        let mut expiry = Expiry::new(&[point(1, 1000, None), point(2, 1000, None)]);
+        expiry.written(1, start);
+        expiry.written(2, start);
+
+        expiry.set_points(&[point(2, 1000, None)]);
+        assert_eq!(
+            expiry.expire(start + Duration::from_secs(1)),
+            vec![(2, Quality::LastKnown)]
+        );
+
+        expiry.set_points(&[]);
+        assert!(expiry.is_empty());
+        assert!(!expiry.written(2, start));
+    }
+}
@@ -0,0 +1,327 @@
+//! Data storage for gateway point values.
+//!
+//! [`DataStore`] is the object-safe interface the gateway uses to keep the
+//! latest value of every point, keyed by `(channel_id, point_id)`, together
+//! with the point configurations of each channel.
+//!
+//! Backends:
+//! - [`MemoryStore`]: in-process, lost on restart (always available)
+//! - [`SqliteStore`]: persisted to a SQLite file (feature `sqlite`)
+//!
+//! # Restart recovery
+//!
+//! After a restart, [`DataStore::last_known()`] returns the persisted values
+//! of a channel marked `Quality::LastKnown`, so channel caches can be
+//! pre-populated before the device answers again.
+//!
+//! # Point age
+//!
+//! [`DataStore::point_age()`] tells when a point was last written and when
+//! its value last changed, so flatlined sensors that keep reporting the same
+//! value can be told apart from live ones.
+//!
+//! # Snapshots
+//!
+//! [`DataStore::snapshot()`] returns the latest values of a channel as a
+//! shared [`Snapshot`] instead of a copied batch. Prefer it over
+//! [`DataStore::read_all()`] for readers that only look at the values, such
+//! as the HTTP API, on large channels.
+//!
+//! [`DataStore::export_snapshot()`] captures the whole store instead, point
+//! configurations included, as a versioned [`StoreSnapshot`] that
+//! [`DataStore::import_snapshot()`] loads into another store (see
+//! [`state`](self::state)).
+//!
+//! # Command audit
+//!
+//! [`DataStore::append_audit()`] keeps the gateway's command audit records,
+//! which [`DataStore::query_audit()`] returns by time range and channel
+//! (see [`audit`](self::audit)). Both backends support it; `MemoryStore`
+//! loses the records on restart.
+//!
+//! # Point overrides
+//!
+//! [`DataStore::save_override()`] keeps the points forced to a value during
+//! commissioning, so the runtime can restore them after a restart (see
+//! [`overrides`](crate::gateway::overrides)). Both backends support it;
+//! `MemoryStore` loses them on restart.
+
+pub mod age;
+pub mod audit;
+pub mod memory;
+pub mod snapshot;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod state;
+pub mod watch;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+use crate::core::data::{DataBatch, DataPoint, PointId};
+use crate::core::error::{GatewayError, Result};
+use crate::core::point::{poll_group_map, PointConfig, PointMeta};
+use crate::core::quality::Quality;
+use crate::core::traits::ReadRequest;
+use crate::gateway::audit::CommandAudit;
+use crate::gateway::overrides::PointOverride;
+
+pub use age::PointAge;
+pub use audit::AuditQuery;
+pub use memory::{HistoryConfig, MemoryStore};
+pub use snapshot::Snapshot;
+#[cfg(feature = "sqlite")]
+pub use sqlite::{SqliteStore, SqliteStoreConfig};
+pub use state::{ChannelSnapshot, StoreSnapshot, SNAPSHOT_FORMAT_VERSION};
+pub use watch::PointWatch;
+
+/// Storage for the latest point values of all channels.
+///
+/// Implementations must be safe to share between channel tasks
+/// (`Arc<dyn DataStore>`).
+#[async_trait]
+pub trait DataStore: Send + Sync {
+    /// Store a batch of values for a channel, replacing older values of the
+    /// same points.
+    async fn write_batch(&self, channel_id: u32, batch: &DataBatch) -> Result<()>;
+
+    /// Watch points of a channel for changes (all points if `point_ids` is
+    /// empty).
+    ///
+    /// The receiver fires only when a stored value or quality actually
+    /// changes, honouring the point's configured deadband; see
+    /// [`watch`](self::watch) for the exact rules. Backends without change
+    /// tracking return `GatewayError::Unsupported`.
+    fn watch(&self, _channel_id: u32, _point_ids: &[PointId]) -> Result<PointWatch> {
+        Err(GatewayError::Unsupported(
+            "watching points is not supported by this store".into(),
+        ))
+    }
+
+    /// When a point was last written and when its value last changed
+    /// (`None` if it was never written).
+    ///
+    /// Backends without age tracking return `GatewayError::Unsupported`.
+    fn point_age(&self, _channel_id: u32, _point_id: PointId) -> Result<Option<PointAge>> {
+        Err(GatewayError::Unsupported(
+            "point age is not supported by this store".into(),
+        ))
+    }
+
+    /// Read the latest value of a single point.
+    async fn read(&self, channel_id: u32, point_id: PointId) -> Result<Option<DataPoint>>;
+
+    /// Read the latest values of the given points (missing points are skipped).
+    async fn read_points(&self, channel_id: u32, point_ids: &[PointId]) -> Result<DataBatch>;
+
+    /// Read the latest values of all points of a channel.
+    async fn read_all(&self, channel_id: u32) -> Result<DataBatch>;
+
+    /// Shared view of the latest values of all points of a channel.
+    ///
+    /// Backends that keep their values in snapshots hand out a reference
+    /// without copying; the default builds one from
+    /// [`read_all()`](Self::read_all).
+    async fn snapshot(&self, channel_id: u32) -> Result<Snapshot> {
+        Ok(Snapshot::from(&self.read_all(channel_id).await?))
+    }
+
+    /// Read the latest values of a channel selected by a [`ReadRequest`].
+    ///
+    /// Poll groups are those of the channel's point configs. The default
+    /// filters [`read_points()`](Self::read_points) or
+    /// [`snapshot()`](Self::snapshot).
+    async fn read_request(&self, channel_id: u32, request: &ReadRequest) -> Result<DataBatch> {
+        let configs = match request.poll_group {
+            Some(_) => self.point_configs(channel_id).await?,
+            None => Vec::new(),
+        };
+        let groups = poll_group_map(&configs);
+        let selected = |point: &&DataPoint| request.matches(point, groups.get(&point.id).copied());
+        Ok(match &request.point_ids {
+            Some(ids) => self
+                .read_points(channel_id, ids)
+                .await?
+                .iter()
+                .filter(selected)
+                .cloned()
+                .collect(),
+            None => self
+                .snapshot(channel_id)
+                .await?
+                .iter()
+                .filter(selected)
+                .cloned()
+                .collect(),
+        })
+    }
+
+    /// Read historical samples of a point, oldest first.
+    ///
+    /// Returns at most `limit` of the most recent samples with a timestamp
+    /// at or after `since`. Backends without history support return
+    /// `GatewayError::Unsupported`.
+    async fn read_history(
+        &self,
+        _channel_id: u32,
+        _point_id: PointId,
+        _since: DateTime<Utc>,
+        _limit: usize,
+    ) -> Result<Vec<DataPoint>> {
+        Err(GatewayError::Unsupported(
+            "point history is not supported by this store".into(),
+        ))
+    }
+
+    /// Add past samples of a channel to the point history, e.g. backfilled
+    /// from the device after an outage.
+    ///
+    /// Samples are placed by timestamp; a sample of a point at a timestamp
+    /// already in its history is skipped. Neither the latest values nor
+    /// watchers and point ages are affected. Returns the number of samples
+    /// added. Backends without history support return
+    /// `GatewayError::Unsupported`.
+    async fn write_history(&self, _channel_id: u32, _samples: &DataBatch) -> Result<usize> {
+        Err(GatewayError::Unsupported(
+            "point history is not supported by this store".into(),
+        ))
+    }
+
+    /// Ids of all channels that have stored values or point configs.
+    async fn channels(&self) -> Result<Vec<u32>>;
+
+    /// Replace the point configurations of a channel.
+    async fn set_point_configs(&self, channel_id: u32, points: &[PointConfig]) -> Result<()>;
+
+    /// Point configurations of a channel.
+    async fn point_configs(&self, channel_id: u32) -> Result<Vec<PointConfig>>;
+
+    /// Descriptive metadata (name, unit) of a point.
+    async fn point_meta(&self, channel_id: u32, point_id: PointId) -> Result<Option<PointMeta>> {
+        Ok(self
+            .point_configs(channel_id)
+            .await?
+            .iter()
+            .find(|p| p.id == point_id)
+            .map(PointConfig::meta))
+    }
+
+    /// Stored values of a channel, marked `Quality::LastKnown`.
+    ///
+    /// Used to pre-populate channel caches on startup.
+    async fn last_known(&self, channel_id: u32) -> Result<DataBatch> {
+        let mut batch = self.read_all(channel_id).await?;
+        for point in batch.iter_mut() {
+            point.quality = Quality::LastKnown;
+        }
+        Ok(batch)
+    }
+
+    /// Point configurations and latest values of every channel.
+    async fn export_snapshot(&self) -> Result<StoreSnapshot> {
+        let mut channels = Vec::new();
+        for channel_id in self.channels().await? {
+            let mut values: Vec<DataPoint> = self.read_all(channel_id).await?.into_iter().collect();
+            values.sort_unstable_by_key(|p| p.id);
+            channels.push(ChannelSnapshot {
+                channel_id,
+                points: self.point_configs(channel_id).await?,
+                values,
+            });
+        }
+        Ok(StoreSnapshot::new(channels))
+    }
+
+    /// Load a snapshot, marking its values `Quality::LastKnown`.
+    ///
+    /// Stored values newer than the snapshot's are kept, as are the point
+    /// configurations of channels that already have some. Returns the
+    /// number of values written. Fails without writing anything if the
+    /// snapshot format is not supported.
+    async fn import_snapshot(&self, snapshot: &StoreSnapshot) -> Result<usize> {
+        snapshot.check_compatible()?;
+        let mut imported = 0;
+        for channel in &snapshot.channels {
+            let id = channel.channel_id;
+            if !channel.points.is_empty() && self.point_configs(id).await?.is_empty() {
+                self.set_point_configs(id, &channel.points).await?;
+            }
+            let ids: Vec<PointId> = channel.values.iter().map(|p| p.id).collect();
+            let stored = self.read_points(id, &ids).await?;
+            let batch: DataBatch = channel
+                .values
+                .iter()
+                .filter(|p| stored.get(p.id).is_none_or(|s| s.timestamp < p.timestamp))
+                .map(|p| DataPoint {
+                    quality: Quality::LastKnown,
+                    ..p.clone()
+                })
+                .collect();
+            if !batch.is_empty() {
+                self.write_batch(id, &batch).await?;
+                imported += batch.len();
+            }
+        }
+        Ok(imported)
+    }
+
+    /// Append command audit records.
+    ///
+    /// Backends without audit support return `GatewayError::Unsupported`.
+    async fn append_audit(&self, _records: &[CommandAudit]) -> Result<()> {
+        Err(GatewayError::Unsupported(
+            "command audit is not supported by this store".into(),
+        ))
+    }
+
+    /// Audit records selected by `query`, oldest first.
+    ///
+    /// Backends without audit support return `GatewayError::Unsupported`.
+    async fn query_audit(&self, _query: &AuditQuery) -> Result<Vec<CommandAudit>> {
+        Err(GatewayError::Unsupported(
+            "command audit is not supported by this store".into(),
+        ))
+    }
+
+    /// Delete audit records older than `before`, returning how many were
+    /// deleted.
+    ///
+    /// Backends without audit support return `GatewayError::Unsupported`.
+    async fn prune_audit(&self, _before: DateTime<Utc>) -> Result<usize> {
+        Err(GatewayError::Unsupported(
+            "command audit is not supported by this store".into(),
+        ))
+    }
+
+    /// Keep a point override, replacing one of the same point.
+    ///
+    /// Backends without override support return `GatewayError::Unsupported`.
+    async fn save_override(&self, _point_override: &PointOverride) -> Result<()> {
+        Err(GatewayError::Unsupported(
+            "point overrides are not supported by this store".into(),
+        ))
+    }
+
+    /// Delete the override of a point, if there is one.
+    ///
+    /// Backends without override support return `GatewayError::Unsupported`.
+    async fn delete_override(&self, _channel_id: u32, _point_id: PointId) -> Result<()> {
+        Err(GatewayError::Unsupported(
+            "point overrides are not supported by this store".into(),
+        ))
+    }
+
+    /// All kept overrides, by channel and point id.
+    ///
+    /// Backends without override support return `GatewayError::Unsupported`.
+    async fn load_overrides(&self) -> Result<Vec<PointOverride>> {
+        Err(GatewayError::Unsupported(
+            "point overrides are not supported by this store".into(),
+        ))
+    }
+
+    /// Persist any buffered writes.
+    async fn flush(&self) -> Result<()> {
+        Ok(())
+    }
+}
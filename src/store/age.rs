@@ -0,0 +1,121 @@
+//! Per-point update and change times.
+//!
+//! [`AgeTracker`] is embedded by store backends next to the change tracker.
+//! It remembers, for every stored point, when it was last written and when
+//! its value last changed. Re-writing the same value (a poll of a flatlined
+//! sensor) or only a new quality moves the update time but not the change
+//! time, so [`PointAge::age()`] grows until the value moves again.
+//!
+//! Values are compared exactly; deadbands only apply to watchers.
+
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::core::data::{DataBatch, PointId, Value};
+
+/// Update and change times of a stored point.
+///
+/// Returned by [`DataStore::point_age()`](super::DataStore::point_age).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PointAge {
+    /// Timestamp of the latest stored sample.
+    pub last_update: DateTime<Utc>,
+
+    /// Timestamp of the sample that last changed the value.
+    pub last_change: DateTime<Utc>,
+}
+
+impl PointAge {
+    /// Time since the value last changed.
+    pub fn age(&self) -> Duration {
+        self.age_at(Utc::now())
+    }
+
+    /// Time between the last value change and `now` (zero if `now` is
+    /// earlier).
+    pub fn age_at(&self, now: DateTime<Utc>) -> Duration {
+        (now - self.last_change).to_std().unwrap_or_default()
+    }
+}
+
+/// Per-store update and change times.
+#[derive(Debug, Default)]
+pub(crate) struct AgeTracker {
+    /// Last stored value and its age, keyed by `(channel_id, point_id)`.
+    points: DashMap<(u32, PointId), (Value, PointAge)>,
+}
+
+impl AgeTracker {
+    /// Take the update times of a written batch.
+    pub(crate) fn process(&self, channel_id: u32, batch: &DataBatch) {
+        for point in batch.iter() {
+            let mut entry = self
+                .points
+                .entry((channel_id, point.id))
+                .or_insert_with(|| {
+                    (
+                        point.value.clone(),
+                        PointAge {
+                            last_update: point.timestamp,
+                            last_change: point.timestamp,
+                        },
+                    )
+                });
+            let (value, age) = entry.value_mut();
+            age.last_update = point.timestamp;
+            if *value != point.value {
+                *value = point.value.clone();
+                age.last_change = point.timestamp;
+            }
+        }
+    }
+
+    /// Age of a point, if it was ever written.
+    pub(crate) fn get(&self, channel_id: u32, point_id: PointId) -> Option<PointAge> {
+        self.points
+            .get(&(channel_id, point_id))
+            .map(|entry| entry.1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::data::DataPoint;
+    use crate::core::quality::Quality;
+
+    fn at(seconds: i64, value: f64) -> DataPoint {
+        let mut point = DataPoint::new(1, value);
+        point.timestamp = DateTime::<Utc>::UNIX_EPOCH + chrono::Duration::seconds(seconds);
+        point
+    }
+
+    #[test]
+    fn test_only_value_changes_reset_the_age() {
+        let tracker = AgeTracker::default();
+        let write = |point: DataPoint| tracker.process(1, &DataBatch::from_points(vec![point]));
+        let seconds = |t: DateTime<Utc>| t.timestamp();
+
+        write(at(10, 1.0));
+        write(at(20, 1.0));
+        write(at(30, 1.0).with_quality(Quality::Uncertain));
+        let age = tracker.get(1, 1).unwrap();
+        assert_eq!(
+            (seconds(age.last_update), seconds(age.last_change)),
+            (30, 10)
+        );
+        assert_eq!(age.age_at(age.last_update), Duration::from_secs(20));
+
+        write(at(40, 2.0));
+        let age = tracker.get(1, 1).unwrap();
+        assert_eq!(
+            (seconds(age.last_update), seconds(age.last_change)),
+            (40, 40)
+        );
+        assert!(tracker.get(1, 2).is_none());
+        assert!(tracker.get(2, 1).is_none());
+    }
+}
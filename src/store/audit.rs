@@ -0,0 +1,64 @@
+//! Command audit history.
+//!
+//! Stores that support it keep the [`CommandAudit`] records of a gateway
+//! append-only: records are only ever added
+//! ([`DataStore::append_audit()`](super::DataStore::append_audit)) and
+//! removed once past their retention
+//! ([`DataStore::prune_audit()`](super::DataStore::prune_audit)).
+//! [`AuditQuery`] selects records by time range and channel.
+
+use chrono::{DateTime, Utc};
+
+use crate::gateway::audit::CommandAudit;
+
+/// Selection of audit records, oldest first.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AuditQuery {
+    /// Only records at or after this time.
+    pub from: Option<DateTime<Utc>>,
+
+    /// Only records before this time.
+    pub to: Option<DateTime<Utc>>,
+
+    /// Only records of this channel.
+    pub channel_id: Option<u32>,
+
+    /// At most this many records (the oldest matching ones).
+    pub limit: Option<usize>,
+}
+
+impl AuditQuery {
+    /// Query for all records.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only records in `[from, to)`.
+    #[must_use]
+    pub fn with_range(mut self, from: DateTime<Utc>, to: DateTime<Utc>) -> Self {
+        self.from = Some(from);
+        self.to = Some(to);
+        self
+    }
+
+    /// Only records of `channel_id`.
+    #[must_use]
+    pub fn with_channel(mut self, channel_id: u32) -> Self {
+        self.channel_id = Some(channel_id);
+        self
+    }
+
+    /// At most `limit` records.
+    #[must_use]
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Whether `record` is selected (the limit aside).
+    pub fn matches(&self, record: &CommandAudit) -> bool {
+        self.from.is_none_or(|from| record.timestamp >= from)
+            && self.to.is_none_or(|to| record.timestamp < to)
+            && self.channel_id.is_none_or(|id| record.channel_id == id)
+    }
+}
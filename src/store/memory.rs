@@ -0,0 +1,565 @@
+//! In-memory [`DataStore`] backend.
+//!
+//! Besides the latest values, [`MemoryStore`] can keep a bounded per-point
+//! history for trend queries. History is off by default; enable it for all
+//! channels with [`MemoryStore::with_history()`] or per channel with
+//! [`MemoryStore::set_channel_history()`]. Samples backfilled with
+//! [`DataStore::write_history()`] are kept only for channels with history.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+
+use crate::core::data::{DataBatch, DataPoint, PointId};
+use crate::core::error::Result;
+use crate::core::point::PointConfig;
+use crate::gateway::audit::CommandAudit;
+use crate::gateway::overrides::PointOverride;
+
+use super::age::{AgeTracker, PointAge};
+use super::audit::AuditQuery;
+use super::snapshot::Snapshot;
+use super::watch::{ChangeTracker, PointWatch};
+use super::DataStore;
+
+/// Per-point history settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HistoryConfig {
+    /// Maximum number of samples kept per point (at least 1).
+    pub capacity: usize,
+
+    /// Samples older than this are discarded on the next write.
+    pub retention: Option<Duration>,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 1000,
+            retention: None,
+        }
+    }
+}
+
+impl HistoryConfig {
+    /// Keep up to `capacity` samples per point.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            capacity,
+            retention: None,
+        }
+    }
+
+    /// Also discard samples older than `retention`.
+    #[must_use]
+    pub fn with_retention(mut self, retention: Duration) -> Self {
+        self.retention = Some(retention);
+        self
+    }
+}
+
+/// In-memory store (contents are lost on restart).
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    /// channel_id -> latest values
+    values: DashMap<u32, Snapshot>,
+    /// channel_id -> point configurations
+    configs: DashMap<u32, Vec<PointConfig>>,
+    /// History settings applied to channels without an override.
+    history: Option<HistoryConfig>,
+    /// Per-channel history overrides (`None` disables history).
+    channel_history: DashMap<u32, Option<HistoryConfig>>,
+    /// channel_id -> point_id -> samples, oldest first
+    samples: DashMap<u32, DashMap<PointId, VecDeque<DataPoint>>>,
+    changes: ChangeTracker,
+    ages: AgeTracker,
+    /// Command audit records, oldest first.
+    audit: Mutex<Vec<CommandAudit>>,
+    /// Point overrides by `(channel_id, point_id)`.
+    overrides: DashMap<(u32, PointId), PointOverride>,
+}
+
+impl MemoryStore {
+    /// Create an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enable history for all channels.
+    #[must_use]
+    pub fn with_history(mut self, config: HistoryConfig) -> Self {
+        self.history = Some(config);
+        self
+    }
+
+    /// Override history settings for one channel (`None` disables it).
+    ///
+    /// Disabling history drops the samples already kept for the channel.
+    pub fn set_channel_history(&self, channel_id: u32, config: Option<HistoryConfig>) {
+        if config.is_none() {
+            self.samples.remove(&channel_id);
+        }
+        self.channel_history.insert(channel_id, config);
+    }
+
+    fn history_config(&self, channel_id: u32) -> Option<HistoryConfig> {
+        match self.channel_history.get(&channel_id) {
+            Some(config) => *config,
+            None => self.history,
+        }
+    }
+
+    fn record_history(&self, channel_id: u32, batch: &DataBatch, config: HistoryConfig) {
+        let capacity = config.capacity.max(1);
+        let cutoff = retention_cutoff(config);
+
+        let channel = self.samples.entry(channel_id).or_default();
+        for point in batch.iter() {
+            let mut samples = channel.entry(point.id).or_default();
+            if samples.len() >= capacity {
+                samples.pop_front();
+            }
+            samples.push_back(point.clone());
+
+            if let Some(cutoff) = cutoff {
+                while samples.front().is_some_and(|p| p.timestamp < cutoff) {
+                    samples.pop_front();
+                }
+            }
+        }
+    }
+}
+
+/// Oldest timestamp kept under `config`'s retention.
+fn retention_cutoff(config: HistoryConfig) -> Option<DateTime<Utc>> {
+    config
+        .retention
+        .and_then(|r| chrono::Duration::from_std(r).ok())
+        .map(|r| Utc::now() - r)
+}
+
+#[async_trait]
+impl DataStore for MemoryStore {
+    async fn write_batch(&self, channel_id: u32, batch: &DataBatch) -> Result<()> {
+        self.values.entry(channel_id).or_default().apply(batch);
+
+        if let Some(config) = self.history_config(channel_id) {
+            self.record_history(channel_id, batch, config);
+        }
+        self.changes.process(channel_id, batch);
+        self.ages.process(channel_id, batch);
+        Ok(())
+    }
+
+    fn watch(&self, channel_id: u32, point_ids: &[PointId]) -> Result<PointWatch> {
+        Ok(self.changes.watch(channel_id, point_ids))
+    }
+
+    fn point_age(&self, channel_id: u32, point_id: PointId) -> Result<Option<PointAge>> {
+        Ok(self.ages.get(channel_id, point_id))
+    }
+
+    async fn read_history(
+        &self,
+        channel_id: u32,
+        point_id: PointId,
+        since: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<Vec<DataPoint>> {
+        let Some(channel) = self.samples.get(&channel_id) else {
+            return Ok(Vec::new());
+        };
+        let Some(samples) = channel.get(&point_id) else {
+            return Ok(Vec::new());
+        };
+
+        let mut points: Vec<DataPoint> = samples
+            .iter()
+            .rev()
+            .take_while(|p| p.timestamp >= since)
+            .take(limit)
+            .cloned()
+            .collect();
+        points.reverse();
+        Ok(points)
+    }
+
+    async fn write_history(&self, channel_id: u32, samples: &DataBatch) -> Result<usize> {
+        let Some(config) = self.history_config(channel_id) else {
+            return Ok(0);
+        };
+        let capacity = config.capacity.max(1);
+        let cutoff = retention_cutoff(config);
+
+        let channel = self.samples.entry(channel_id).or_default();
+        let mut added = 0;
+        for point in samples.iter() {
+            if cutoff.is_some_and(|cutoff| point.timestamp < cutoff) {
+                continue;
+            }
+            let mut history = channel.entry(point.id).or_default();
+            let at = history.partition_point(|p| p.timestamp < point.timestamp);
+            if history
+                .get(at)
+                .is_some_and(|p| p.timestamp == point.timestamp)
+            {
+                continue;
+            }
+            if history.len() >= capacity {
+                if at == 0 {
+                    // Older than everything a full history keeps
+                    continue;
+                }
+                history.pop_front();
+                history.insert(at - 1, point.clone());
+            } else {
+                history.insert(at, point.clone());
+            }
+            added += 1;
+        }
+        Ok(added)
+    }
+
+    async fn read(&self, channel_id: u32, point_id: PointId) -> Result<Option<DataPoint>> {
+        Ok(self
+            .values
+            .get(&channel_id)
+            .and_then(|channel| channel.get(point_id).cloned()))
+    }
+
+    async fn read_points(&self, channel_id: u32, point_ids: &[PointId]) -> Result<DataBatch> {
+        let Some(channel) = self.values.get(&channel_id) else {
+            return Ok(DataBatch::new());
+        };
+        Ok(point_ids
+            .iter()
+            .filter_map(|&id| channel.get(id).cloned())
+            .collect())
+    }
+
+    async fn read_all(&self, channel_id: u32) -> Result<DataBatch> {
+        Ok(self
+            .values
+            .get(&channel_id)
+            .map(|channel| channel.to_batch())
+            .unwrap_or_default())
+    }
+
+    async fn snapshot(&self, channel_id: u32) -> Result<Snapshot> {
+        Ok(self
+            .values
+            .get(&channel_id)
+            .map(|channel| channel.clone())
+            .unwrap_or_default())
+    }
+
+    async fn channels(&self) -> Result<Vec<u32>> {
+        let mut ids: Vec<u32> = self
+            .values
+            .iter()
+            .map(|e| *e.key())
+            .chain(self.configs.iter().map(|e| *e.key()))
+            .collect();
+        ids.sort_unstable();
+        ids.dedup();
+        Ok(ids)
+    }
+
+    async fn set_point_configs(&self, channel_id: u32, points: &[PointConfig]) -> Result<()> {
+        self.changes.set_point_configs(channel_id, points);
+        self.configs.insert(channel_id, points.to_vec());
+        Ok(())
+    }
+
+    async fn point_configs(&self, channel_id: u32) -> Result<Vec<PointConfig>> {
+        Ok(self
+            .configs
+            .get(&channel_id)
+            .map(|c| c.clone())
+            .unwrap_or_default())
+    }
+
+    async fn append_audit(&self, records: &[CommandAudit]) -> Result<()> {
+        let mut audit = self.audit.lock().unwrap_or_else(|e| e.into_inner());
+        audit.extend_from_slice(records);
+        Ok(())
+    }
+
+    async fn query_audit(&self, query: &AuditQuery) -> Result<Vec<CommandAudit>> {
+        let audit = self.audit.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(audit
+            .iter()
+            .filter(|record| query.matches(record))
+            .take(query.limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect())
+    }
+
+    async fn prune_audit(&self, before: DateTime<Utc>) -> Result<usize> {
+        let mut audit = self.audit.lock().unwrap_or_else(|e| e.into_inner());
+        let count = audit.len();
+        audit.retain(|record| record.timestamp >= before);
+        Ok(count - audit.len())
+    }
+
+    async fn save_override(&self, point_override: &PointOverride) -> Result<()> {
+        let key = (point_override.channel_id, point_override.point_id);
+        self.overrides.insert(key, point_override.clone());
+        Ok(())
+    }
+
+    async fn delete_override(&self, channel_id: u32, point_id: PointId) -> Result<()> {
+        self.overrides.remove(&(channel_id, point_id));
+        Ok(())
+    }
+
+    async fn load_overrides(&self) -> Result<Vec<PointOverride>> {
+        let mut overrides: Vec<PointOverride> =
+            self.overrides.iter().map(|o| o.value().clone()).collect();
+        overrides.sort_by_key(|o| (o.channel_id, o.point_id));
+        Ok(overrides)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::point::{ProtocolAddress, VirtualAddress};
+    use crate::core::quality::Quality;
+    use crate::core::traits::{QualityFilter, ReadRequest};
+
+    #[tokio::test]
+    async fn test_write_and_read() {
+        let store = MemoryStore::new();
+        let batch = DataBatch::from_points(vec![DataPoint::new(1, 1.0), DataPoint::new(2, 2.0)]);
+        store.write_batch(10, &batch).await.unwrap();
+        store
+            .write_batch(10, &DataBatch::from_points(vec![DataPoint::new(1, 5.0)]))
+            .await
+            .unwrap();
+
+        assert_eq!(store.read_all(10).await.unwrap().len(), 2);
+        let snapshot = store.snapshot(10).await.unwrap();
+        assert_eq!(snapshot.get(1).unwrap().value.as_f64(), Some(5.0));
+        assert!(store.snapshot(11).await.unwrap().is_empty());
+        assert_eq!(
+            store.read(10, 1).await.unwrap().unwrap().value.as_f64(),
+            Some(5.0)
+        );
+        assert!(store.read(11, 1).await.unwrap().is_none());
+        assert_eq!(store.read_points(10, &[2, 3]).await.unwrap().len(), 1);
+        assert_eq!(store.channels().await.unwrap(), vec![10]);
+
+        let age = store.point_age(10, 2).unwrap().unwrap();
+        assert_eq!(age.last_change, age.last_update);
+        assert!(store.point_age(11, 1).unwrap().is_none());
+
+        let last_known = store.last_known(10).await.unwrap();
+        assert!(last_known.iter().all(|p| p.quality == Quality::LastKnown));
+    }
+
+    #[tokio::test]
+    async fn test_point_meta() {
+        let store = MemoryStore::new();
+        let points = vec![
+            PointConfig::new(1, ProtocolAddress::Virtual(VirtualAddress::new("t")))
+                .with_unit("kPa"),
+        ];
+        store.set_point_configs(3, &points).await.unwrap();
+
+        let meta = store.point_meta(3, 1).await.unwrap().unwrap();
+        assert_eq!(meta.unit.as_deref(), Some("kPa"));
+        assert!(store.point_meta(3, 2).await.unwrap().is_none());
+        assert_eq!(store.channels().await.unwrap(), vec![3]);
+    }
+
+    #[tokio::test]
+    async fn test_read_request() {
+        let store = MemoryStore::new();
+        let address = || ProtocolAddress::Virtual(VirtualAddress::new("t"));
+        let points = vec![
+            PointConfig::new(1, address()).with_poll_group("fast"),
+            PointConfig::new(2, address()).with_poll_group("slow"),
+        ];
+        store.set_point_configs(4, &points).await.unwrap();
+        let batch = DataBatch::from_points(vec![
+            DataPoint::new(1, 1.0).with_quality(Quality::CommFailure),
+            DataPoint::new(2, 2.0),
+            DataPoint::new(3, 3.0),
+        ]);
+        store.write_batch(4, &batch).await.unwrap();
+
+        let cases = [
+            (ReadRequest::all(), vec![1, 2, 3]),
+            (ReadRequest::by_group("fast"), vec![1]),
+            (ReadRequest::bad_quality(), vec![1]),
+            (
+                ReadRequest::by_ids(vec![2, 3]).with_quality_filter(QualityFilter::Good),
+                vec![2, 3],
+            ),
+        ];
+        for (request, expected) in cases {
+            let batch = store.read_request(4, &request).await.unwrap();
+            let mut ids: Vec<_> = batch.iter().map(|p| p.id).collect();
+            ids.sort_unstable();
+            assert_eq!(ids, expected, "{:?}", request);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_history_disabled_by_default() {
+        let store = MemoryStore::new();
+        store
+            .write_batch(1, &DataBatch::from_points(vec![DataPoint::new(1, 1.0)]))
+            .await
+            .unwrap();
+        let history = store
+            .read_history(1, 1, DateTime::<Utc>::MIN_UTC, 10)
+            .await
+            .unwrap();
+        assert!(history.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_history_capacity_and_query() {
+        let store = MemoryStore::new().with_history(HistoryConfig::with_capacity(3));
+        let start = Utc::now();
+        for i in 0..5 {
+            let mut point = DataPoint::new(1, i as f64);
+            point.timestamp = start + chrono::Duration::seconds(i);
+            store
+                .write_batch(1, &DataBatch::from_points(vec![point]))
+                .await
+                .unwrap();
+        }
+
+        let values = |points: Vec<DataPoint>| -> Vec<f64> {
+            points.iter().filter_map(|p| p.value.as_f64()).collect()
+        };
+
+        // Only the newest 3 samples are kept, oldest first
+        let all = store
+            .read_history(1, 1, DateTime::<Utc>::MIN_UTC, 10)
+            .await
+            .unwrap();
+        assert_eq!(values(all), vec![2.0, 3.0, 4.0]);
+
+        let since = start + chrono::Duration::seconds(3);
+        let recent = store.read_history(1, 1, since, 10).await.unwrap();
+        assert_eq!(values(recent), vec![3.0, 4.0]);
+
+        let limited = store
+            .read_history(1, 1, DateTime::<Utc>::MIN_UTC, 1)
+            .await
+            .unwrap();
+        assert_eq!(values(limited), vec![4.0]);
+    }
+
+    #[tokio::test]
+    async fn test_history_retention_and_channel_override() {
+        let store = MemoryStore::new()
+            .with_history(HistoryConfig::default().with_retention(Duration::from_secs(60)));
+        store.set_channel_history(2, None);
+
+        let mut old = DataPoint::new(1, 1.0);
+        old.timestamp = Utc::now() - chrono::Duration::minutes(5);
+        let batch = DataBatch::from_points(vec![old, DataPoint::new(1, 2.0)]);
+        store.write_batch(1, &batch).await.unwrap();
+        store.write_batch(2, &batch).await.unwrap();
+
+        let history = store
+            .read_history(1, 1, DateTime::<Utc>::MIN_UTC, 10)
+            .await
+            .unwrap();
+        assert_eq!(history.len(), 1);
+        assert!(store
+            .read_history(2, 1, DateTime::<Utc>::MIN_UTC, 10)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_write_history() {
+        let store = MemoryStore::new().with_history(HistoryConfig::with_capacity(4));
+        let start = Utc::now() - chrono::Duration::minutes(10);
+        let sample = |value: f64, minutes: i64| {
+            let mut point = DataPoint::new(1, value).with_quality(Quality::Uncertain);
+            point.timestamp = start + chrono::Duration::minutes(minutes);
+            point
+        };
+        let mut watch = store.watch(1, &[]).unwrap();
+        store
+            .write_batch(
+                1,
+                &DataBatch::from_points(vec![sample(0.0, 0), sample(9.0, 9)]),
+            )
+            .await
+            .unwrap();
+        watch.recv().await.unwrap();
+
+        // Placed by timestamp, duplicates skipped
+        let backfill = DataBatch::from_points(vec![sample(3.0, 3), sample(0.5, 0), sample(6.0, 6)]);
+        assert_eq!(store.write_history(1, &backfill).await.unwrap(), 2);
+        let values = |points: Vec<DataPoint>| -> Vec<f64> {
+            points.iter().filter_map(|p| p.value.as_f64()).collect()
+        };
+        let history = store
+            .read_history(1, 1, DateTime::<Utc>::MIN_UTC, 10)
+            .await
+            .unwrap();
+        assert_eq!(values(history.clone()), vec![0.0, 3.0, 6.0, 9.0]);
+        assert_eq!(history[1].quality, Quality::Uncertain);
+
+        // A full history keeps its newest samples
+        let backfill = DataBatch::from_points(vec![sample(-1.0, -1), sample(7.0, 7)]);
+        assert_eq!(store.write_history(1, &backfill).await.unwrap(), 1);
+        let history = store
+            .read_history(1, 1, DateTime::<Utc>::MIN_UTC, 10)
+            .await
+            .unwrap();
+        assert_eq!(values(history), vec![3.0, 6.0, 7.0, 9.0]);
+
+        // The latest value is untouched and watchers are not notified
+        assert_eq!(
+            store.read(1, 1).await.unwrap().unwrap().value.as_f64(),
+            Some(9.0)
+        );
+        let notified = tokio::time::timeout(Duration::from_millis(20), watch.recv()).await;
+        assert!(notified.is_err());
+
+        // Channels without history keep nothing
+        store.set_channel_history(2, None);
+        assert_eq!(store.write_history(2, &backfill).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_watch() {
+        use crate::core::traits::DataEvent;
+
+        let store = MemoryStore::new();
+        let mut watch = store.watch(1, &[2]).unwrap();
+
+        let batch = DataBatch::from_points(vec![DataPoint::new(1, 1.0), DataPoint::new(2, false)]);
+        store.write_batch(1, &batch).await.unwrap();
+        store.write_batch(1, &batch).await.unwrap();
+        store
+            .write_batch(1, &DataBatch::from_points(vec![DataPoint::new(2, true)]))
+            .await
+            .unwrap();
+
+        for expected in [false, true] {
+            match watch.recv().await {
+                Some(DataEvent::DataUpdate(batch)) => {
+                    let point = batch.iter().next().unwrap();
+                    assert_eq!((point.id, point.value.as_bool()), (2, Some(expected)));
+                }
+                other => panic!("expected DataUpdate, got {:?}", other),
+            }
+        }
+    }
+}
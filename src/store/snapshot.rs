@@ -0,0 +1,129 @@
+//! Shared, immutable views of a channel's latest values.
+//!
+//! A [`Snapshot`] is what [`DataStore::snapshot()`](super::DataStore::snapshot)
+//! returns: cloning it is a reference-count bump, so any number of readers
+//! (HTTP API, diagnostics, monitors) can hold the same values without
+//! copying a single [`DataPoint`].
+//!
+//! The points are split into a fixed number of buckets by point id, each
+//! behind its own `Arc`. Runs of 64 consecutive ids share a bucket, so a
+//! poll of a register block touches few of them. A store write copies only
+//! the buckets it touches and only if a reader still holds them; untouched
+//! buckets stay shared between the old and the new snapshot.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::core::data::{DataBatch, DataPoint, PointId};
+
+/// Number of buckets a snapshot is split into.
+const BUCKETS: usize = 64;
+
+type Bucket = HashMap<PointId, DataPoint>;
+
+/// Latest values of one channel at a point in time.
+///
+/// Iteration order is unspecified.
+#[derive(Debug, Clone, Default)]
+pub struct Snapshot {
+    /// Empty until the first write, then `BUCKETS` long.
+    buckets: Arc<Vec<Arc<Bucket>>>,
+    len: usize,
+}
+
+impl Snapshot {
+    /// Latest value of a point.
+    pub fn get(&self, id: PointId) -> Option<&DataPoint> {
+        self.buckets.get(bucket_of(id))?.get(&id)
+    }
+
+    /// Number of points.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the snapshot holds no points.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Iterate over the points.
+    pub fn iter(&self) -> impl Iterator<Item = &DataPoint> + '_ {
+        self.buckets.iter().flat_map(|bucket| bucket.values())
+    }
+
+    /// Copy the points into a batch.
+    pub fn to_batch(&self) -> DataBatch {
+        self.iter().cloned().collect()
+    }
+
+    /// Store the points of a batch, copying only shared buckets they touch.
+    pub(crate) fn apply(&mut self, batch: &DataBatch) {
+        if batch.is_empty() {
+            return;
+        }
+        let buckets = Arc::make_mut(&mut self.buckets);
+        if buckets.is_empty() {
+            buckets.resize_with(BUCKETS, Default::default);
+        }
+        for point in batch.iter() {
+            let bucket = Arc::make_mut(&mut buckets[bucket_of(point.id)]);
+            if bucket.insert(point.id, point.clone()).is_none() {
+                self.len += 1;
+            }
+        }
+    }
+}
+
+impl From<&DataBatch> for Snapshot {
+    fn from(batch: &DataBatch) -> Self {
+        let mut snapshot = Self::default();
+        snapshot.apply(batch);
+        snapshot
+    }
+}
+
+fn bucket_of(id: PointId) -> usize {
+    (id as usize >> 6) % BUCKETS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn batch(ids: impl IntoIterator<Item = PointId>, value: f64) -> DataBatch {
+        ids.into_iter()
+            .map(|id| DataPoint::new(id, value))
+            .collect()
+    }
+
+    #[test]
+    fn test_readers_keep_their_view() {
+        let mut snapshot = Snapshot::from(&batch(0..200, 1.0));
+        let reader = snapshot.clone();
+
+        snapshot.apply(&batch([1, 500], 2.0));
+        assert_eq!((reader.len(), snapshot.len()), (200, 201));
+        assert_eq!(reader.get(1).unwrap().value.as_f64(), Some(1.0));
+        assert_eq!(snapshot.get(1).unwrap().value.as_f64(), Some(2.0));
+        assert!(reader.get(500).is_none());
+        assert_eq!(snapshot.iter().count(), 201);
+
+        // Only the touched buckets were copied
+        let shared = reader
+            .buckets
+            .iter()
+            .zip(snapshot.buckets.iter())
+            .filter(|(a, b)| Arc::ptr_eq(a, b))
+            .count();
+        assert_eq!(shared, BUCKETS - 2);
+    }
+
+    #[test]
+    fn test_empty_snapshot() {
+        let snapshot = Snapshot::default();
+        assert!(snapshot.is_empty());
+        assert!(snapshot.get(0).is_none());
+        assert!(snapshot.to_batch().is_empty());
+    }
+}
@@ -0,0 +1,809 @@
+//! SQLite-backed [`DataStore`] (feature `sqlite`).
+//!
+//! Latest values are kept in one row per `(channel_id, point_id)` and point
+//! configurations are stored as JSON, as are command audit records and
+//! point overrides, which are written straight to disk. Writes go to a small write-behind
+//! buffer that is flushed to disk in a single transaction once it reaches
+//! [`SqliteStoreConfig::max_pending`] entries or
+//! [`SqliteStoreConfig::flush_interval`] has elapsed since the last flush,
+//! so the poll hot path rarely touches the disk. Reads query the table and
+//! overlay any still-buffered values.
+//!
+//! Call [`DataStore::flush()`] before shutdown; dropping the store also
+//! flushes on a best-effort basis.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::core::data::{DataBatch, DataPoint, PointId, Value};
+use crate::core::error::{GatewayError, Result};
+use crate::core::point::PointConfig;
+use crate::core::quality::Quality;
+use crate::gateway::audit::CommandAudit;
+use crate::gateway::overrides::PointOverride;
+
+use super::age::{AgeTracker, PointAge};
+use super::audit::AuditQuery;
+use super::watch::{ChangeTracker, PointWatch};
+use super::DataStore;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS point_values (
+    channel_id       INTEGER NOT NULL,
+    point_id         INTEGER NOT NULL,
+    value            TEXT    NOT NULL,
+    quality          TEXT    NOT NULL,
+    timestamp_us     INTEGER NOT NULL,
+    source_timestamp_us INTEGER,
+    PRIMARY KEY (channel_id, point_id)
+);
+CREATE TABLE IF NOT EXISTS point_configs (
+    channel_id INTEGER NOT NULL,
+    point_id   INTEGER NOT NULL,
+    config     TEXT    NOT NULL,
+    PRIMARY KEY (channel_id, point_id)
+);
+CREATE TABLE IF NOT EXISTS command_audit (
+    id           INTEGER PRIMARY KEY AUTOINCREMENT,
+    timestamp_us INTEGER NOT NULL,
+    channel_id   INTEGER NOT NULL,
+    record       TEXT    NOT NULL
+);
+CREATE INDEX IF NOT EXISTS command_audit_time ON command_audit (timestamp_us);
+CREATE INDEX IF NOT EXISTS command_audit_channel ON command_audit (channel_id, timestamp_us);
+CREATE TABLE IF NOT EXISTS point_overrides (
+    channel_id INTEGER NOT NULL,
+    point_id   INTEGER NOT NULL,
+    record     TEXT    NOT NULL,
+    PRIMARY KEY (channel_id, point_id)
+);
+";
+
+/// Write-behind settings for [`SqliteStore`].
+#[derive(Debug, Clone)]
+pub struct SqliteStoreConfig {
+    /// Flush once this many point values are buffered.
+    pub max_pending: usize,
+
+    /// Flush on the next write once this much time has passed since the
+    /// last flush.
+    pub flush_interval: Duration,
+}
+
+impl Default for SqliteStoreConfig {
+    fn default() -> Self {
+        Self {
+            max_pending: 1024,
+            flush_interval: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Persistent store backed by a SQLite database file.
+#[derive(Debug, Clone)]
+pub struct SqliteStore {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    conn: Mutex<Connection>,
+    /// Buffered writes not yet on disk, latest value per point.
+    pending: Mutex<Pending>,
+    config: SqliteStoreConfig,
+    changes: ChangeTracker,
+    ages: AgeTracker,
+}
+
+#[derive(Debug)]
+struct Pending {
+    values: HashMap<(u32, PointId), DataPoint>,
+    last_flush: Instant,
+}
+
+impl SqliteStore {
+    /// Open (or create) a store at `path` with default write-behind settings.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::open_with_config(path, SqliteStoreConfig::default())
+    }
+
+    /// Open (or create) a store at `path`.
+    pub fn open_with_config(path: impl AsRef<Path>, config: SqliteStoreConfig) -> Result<Self> {
+        let conn = Connection::open(path).map_err(db_error)?;
+        Self::from_connection(conn, config)
+    }
+
+    /// Open a private in-memory database (mainly for tests).
+    pub fn open_in_memory() -> Result<Self> {
+        let conn = Connection::open_in_memory().map_err(db_error)?;
+        Self::from_connection(conn, SqliteStoreConfig::default())
+    }
+
+    fn from_connection(conn: Connection, config: SqliteStoreConfig) -> Result<Self> {
+        conn.pragma_update(None, "journal_mode", "WAL")
+            .map_err(db_error)?;
+        conn.pragma_update(None, "synchronous", "NORMAL")
+            .map_err(db_error)?;
+        conn.execute_batch(SCHEMA).map_err(db_error)?;
+
+        Ok(Self {
+            inner: Arc::new(Inner {
+                conn: Mutex::new(conn),
+                pending: Mutex::new(Pending {
+                    values: HashMap::new(),
+                    last_flush: Instant::now(),
+                }),
+                config,
+                changes: ChangeTracker::default(),
+                ages: AgeTracker::default(),
+            }),
+        })
+    }
+
+    /// Number of values waiting in the write-behind buffer.
+    pub fn pending_count(&self) -> usize {
+        self.inner.pending().values.len()
+    }
+
+    /// Run a blocking database operation off the async executor.
+    async fn blocking<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Inner) -> Result<T> + Send + 'static,
+    {
+        let inner = Arc::clone(&self.inner);
+        tokio::task::spawn_blocking(move || f(&inner))
+            .await
+            .map_err(|e| GatewayError::internal(format!("store task failed: {}", e)))?
+    }
+}
+
+impl Inner {
+    fn conn(&self) -> MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn pending(&self) -> MutexGuard<'_, Pending> {
+        self.pending.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Write all buffered values in one transaction.
+    fn flush(&self) -> Result<()> {
+        // Hold the connection first so concurrent flushes are ordered and a
+        // value taken from the buffer is always on disk before the next read.
+        let mut conn = self.conn();
+        let values = {
+            let mut pending = self.pending();
+            pending.last_flush = Instant::now();
+            std::mem::take(&mut pending.values)
+        };
+        if values.is_empty() {
+            return Ok(());
+        }
+
+        let result = write_values(&mut conn, &values);
+        if result.is_err() {
+            // Keep the values for the next attempt unless newer ones arrived.
+            let mut pending = self.pending();
+            for (key, point) in values {
+                pending.values.entry(key).or_insert(point);
+            }
+        }
+        result
+    }
+
+    /// Overlay buffered values of `channel_id` on rows read from disk.
+    fn overlay(
+        &self,
+        channel_id: u32,
+        point_ids: Option<&[PointId]>,
+        mut points: HashMap<PointId, DataPoint>,
+    ) -> DataBatch {
+        let pending = self.pending();
+        for ((channel, point_id), point) in &pending.values {
+            if *channel == channel_id && point_ids.is_none_or(|ids| ids.contains(point_id)) {
+                points.insert(*point_id, point.clone());
+            }
+        }
+        let mut points: Vec<DataPoint> = points.into_values().collect();
+        points.sort_by_key(|p| p.id);
+        DataBatch::from_points(points)
+    }
+
+    /// Read rows of a channel from disk and overlay buffered values.
+    ///
+    /// The connection lock is held throughout, so a concurrent flush cannot
+    /// move values from the buffer to disk between the two steps.
+    fn read_channel(&self, channel_id: u32, point_ids: Option<&[PointId]>) -> Result<DataBatch> {
+        let conn = self.conn();
+        let points = query_values(&conn, channel_id, point_ids)?;
+        Ok(self.overlay(channel_id, point_ids, points))
+    }
+}
+
+fn write_values(conn: &mut Connection, values: &HashMap<(u32, PointId), DataPoint>) -> Result<()> {
+    let tx = conn.transaction().map_err(db_error)?;
+    {
+        let mut stmt = tx
+            .prepare_cached(
+                "INSERT INTO point_values
+                     (channel_id, point_id, value, quality, timestamp_us, source_timestamp_us)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                 ON CONFLICT (channel_id, point_id) DO UPDATE SET
+                     value = excluded.value,
+                     quality = excluded.quality,
+                     timestamp_us = excluded.timestamp_us,
+                     source_timestamp_us = excluded.source_timestamp_us",
+            )
+            .map_err(db_error)?;
+        for ((channel_id, _), point) in values {
+            stmt.execute(params![
+                channel_id,
+                point.id,
+                serde_json::to_string(&point.value).map_err(json_error)?,
+                serde_json::to_string(&point.quality).map_err(json_error)?,
+                point.timestamp.timestamp_micros(),
+                point.source_timestamp.map(|ts| ts.timestamp_micros()),
+            ])
+            .map_err(db_error)?;
+        }
+    }
+    tx.commit().map_err(db_error)
+}
+
+fn query_values(
+    conn: &Connection,
+    channel_id: u32,
+    point_ids: Option<&[PointId]>,
+) -> Result<HashMap<PointId, DataPoint>> {
+    let mut stmt = conn
+        .prepare_cached(
+            "SELECT point_id, value, quality, timestamp_us, source_timestamp_us
+                 FROM point_values WHERE channel_id = ?1",
+        )
+        .map_err(db_error)?;
+    let rows = stmt
+        .query_map(params![channel_id], row_to_point)
+        .map_err(db_error)?;
+
+    let mut points = HashMap::new();
+    for row in rows {
+        let point = row.map_err(db_error)??;
+        if point_ids.is_none_or(|ids| ids.contains(&point.id)) {
+            points.insert(point.id, point);
+        }
+    }
+    Ok(points)
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        if let Err(_e) = self.flush() {
+            #[cfg(feature = "tracing-support")]
+            tracing::warn!("Failed to flush SQLite store on drop: {}", _e);
+        }
+    }
+}
+
+type RowResult = Result<DataPoint>;
+
+fn row_to_point(row: &rusqlite::Row<'_>) -> rusqlite::Result<RowResult> {
+    let point_id: PointId = row.get(0)?;
+    let value: String = row.get(1)?;
+    let quality: String = row.get(2)?;
+    let timestamp_us: i64 = row.get(3)?;
+    let source_timestamp_us: Option<i64> = row.get(4)?;
+
+    Ok((|| {
+        let value: Value = serde_json::from_str(&value).map_err(json_error)?;
+        let quality: Quality = serde_json::from_str(&quality).map_err(json_error)?;
+        Ok(DataPoint {
+            id: point_id,
+            value,
+            quality,
+            timestamp: from_micros(timestamp_us)?,
+            source_timestamp: source_timestamp_us.map(from_micros).transpose()?,
+        })
+    })())
+}
+
+fn from_micros(us: i64) -> Result<DateTime<Utc>> {
+    DateTime::from_timestamp_micros(us)
+        .ok_or_else(|| GatewayError::storage(format!("invalid stored timestamp {}", us)))
+}
+
+fn db_error(e: rusqlite::Error) -> GatewayError {
+    GatewayError::storage_with(format!("SQLite: {}", e), e)
+}
+
+fn json_error(e: serde_json::Error) -> GatewayError {
+    GatewayError::storage_with(format!("invalid stored JSON: {}", e), e)
+}
+
+#[async_trait]
+impl DataStore for SqliteStore {
+    async fn write_batch(&self, channel_id: u32, batch: &DataBatch) -> Result<()> {
+        let flush_due = {
+            let mut pending = self.inner.pending();
+            for point in batch.iter() {
+                pending.values.insert((channel_id, point.id), point.clone());
+            }
+            pending.values.len() >= self.inner.config.max_pending
+                || pending.last_flush.elapsed() >= self.inner.config.flush_interval
+        };
+        self.inner.changes.process(channel_id, batch);
+        self.inner.ages.process(channel_id, batch);
+
+        if flush_due {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    fn watch(&self, channel_id: u32, point_ids: &[PointId]) -> Result<PointWatch> {
+        Ok(self.inner.changes.watch(channel_id, point_ids))
+    }
+
+    fn point_age(&self, channel_id: u32, point_id: PointId) -> Result<Option<PointAge>> {
+        Ok(self.inner.ages.get(channel_id, point_id))
+    }
+
+    async fn read(&self, channel_id: u32, point_id: PointId) -> Result<Option<DataPoint>> {
+        if let Some(point) = self.inner.pending().values.get(&(channel_id, point_id)) {
+            return Ok(Some(point.clone()));
+        }
+
+        self.blocking(move |inner| {
+            let conn = inner.conn();
+            let row = conn
+                .query_row(
+                    "SELECT point_id, value, quality, timestamp_us, source_timestamp_us
+                     FROM point_values WHERE channel_id = ?1 AND point_id = ?2",
+                    params![channel_id, point_id],
+                    row_to_point,
+                )
+                .optional()
+                .map_err(db_error)?;
+            row.transpose()
+        })
+        .await
+    }
+
+    async fn read_points(&self, channel_id: u32, point_ids: &[PointId]) -> Result<DataBatch> {
+        let ids = point_ids.to_vec();
+        self.blocking(move |inner| inner.read_channel(channel_id, Some(&ids)))
+            .await
+    }
+
+    async fn read_all(&self, channel_id: u32) -> Result<DataBatch> {
+        self.blocking(move |inner| inner.read_channel(channel_id, None))
+            .await
+    }
+
+    async fn channels(&self) -> Result<Vec<u32>> {
+        self.blocking(|inner| {
+            let mut ids: Vec<u32> = inner
+                .pending()
+                .values
+                .keys()
+                .map(|(channel, _)| *channel)
+                .collect();
+
+            let conn = inner.conn();
+            let mut stmt = conn
+                .prepare_cached(
+                    "SELECT channel_id FROM point_values
+                     UNION SELECT channel_id FROM point_configs",
+                )
+                .map_err(db_error)?;
+            let rows = stmt.query_map([], |row| row.get(0)).map_err(db_error)?;
+            for row in rows {
+                ids.push(row.map_err(db_error)?);
+            }
+
+            ids.sort_unstable();
+            ids.dedup();
+            Ok(ids)
+        })
+        .await
+    }
+
+    async fn set_point_configs(&self, channel_id: u32, points: &[PointConfig]) -> Result<()> {
+        self.inner.changes.set_point_configs(channel_id, points);
+        let rows = points
+            .iter()
+            .map(|p| Ok((p.id, serde_json::to_string(p).map_err(json_error)?)))
+            .collect::<Result<Vec<_>>>()?;
+
+        self.blocking(move |inner| {
+            let mut conn = inner.conn();
+            let tx = conn.transaction().map_err(db_error)?;
+            tx.execute(
+                "DELETE FROM point_configs WHERE channel_id = ?1",
+                params![channel_id],
+            )
+            .map_err(db_error)?;
+            {
+                let mut stmt = tx
+                    .prepare_cached(
+                        "INSERT INTO point_configs (channel_id, point_id, config)
+                         VALUES (?1, ?2, ?3)",
+                    )
+                    .map_err(db_error)?;
+                for (point_id, config) in &rows {
+                    stmt.execute(params![channel_id, point_id, config])
+                        .map_err(db_error)?;
+                }
+            }
+            tx.commit().map_err(db_error)
+        })
+        .await
+    }
+
+    async fn point_configs(&self, channel_id: u32) -> Result<Vec<PointConfig>> {
+        self.blocking(move |inner| {
+            let conn = inner.conn();
+            let mut stmt = conn
+                .prepare_cached(
+                    "SELECT config FROM point_configs WHERE channel_id = ?1 ORDER BY point_id",
+                )
+                .map_err(db_error)?;
+            let rows = stmt
+                .query_map(params![channel_id], |row| row.get::<_, String>(0))
+                .map_err(db_error)?;
+
+            let mut configs = Vec::new();
+            for row in rows {
+                configs.push(serde_json::from_str(&row.map_err(db_error)?).map_err(json_error)?);
+            }
+            Ok(configs)
+        })
+        .await
+    }
+
+    async fn append_audit(&self, records: &[CommandAudit]) -> Result<()> {
+        let rows = records
+            .iter()
+            .map(|r| {
+                let record = serde_json::to_string(r).map_err(json_error)?;
+                Ok((r.timestamp.timestamp_micros(), r.channel_id, record))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        self.blocking(move |inner| {
+            let mut conn = inner.conn();
+            let tx = conn.transaction().map_err(db_error)?;
+            {
+                let mut stmt = tx
+                    .prepare_cached(
+                        "INSERT INTO command_audit (timestamp_us, channel_id, record)
+                         VALUES (?1, ?2, ?3)",
+                    )
+                    .map_err(db_error)?;
+                for (timestamp_us, channel_id, record) in &rows {
+                    stmt.execute(params![timestamp_us, channel_id, record])
+                        .map_err(db_error)?;
+                }
+            }
+            tx.commit().map_err(db_error)
+        })
+        .await
+    }
+
+    async fn query_audit(&self, query: &AuditQuery) -> Result<Vec<CommandAudit>> {
+        let from = query.from.map_or(i64::MIN, |t| t.timestamp_micros());
+        let to = query.to.map_or(i64::MAX, |t| t.timestamp_micros());
+        let channel_id = query.channel_id;
+        let limit = query
+            .limit
+            .map_or(-1, |l| i64::try_from(l).unwrap_or(i64::MAX));
+
+        self.blocking(move |inner| {
+            let conn = inner.conn();
+            let mut stmt = conn
+                .prepare_cached(
+                    "SELECT record FROM command_audit
+                     WHERE timestamp_us >= ?1 AND timestamp_us < ?2
+                       AND (?3 IS NULL OR channel_id = ?3)
+                     ORDER BY timestamp_us, id LIMIT ?4",
+                )
+                .map_err(db_error)?;
+            let rows = stmt
+                .query_map(params![from, to, channel_id, limit], |row| {
+                    row.get::<_, String>(0)
+                })
+                .map_err(db_error)?;
+
+            let mut records = Vec::new();
+            for row in rows {
+                records.push(serde_json::from_str(&row.map_err(db_error)?).map_err(json_error)?);
+            }
+            Ok(records)
+        })
+        .await
+    }
+
+    async fn prune_audit(&self, before: DateTime<Utc>) -> Result<usize> {
+        let before = before.timestamp_micros();
+        self.blocking(move |inner| {
+            inner
+                .conn()
+                .execute(
+                    "DELETE FROM command_audit WHERE timestamp_us < ?1",
+                    params![before],
+                )
+                .map_err(db_error)
+        })
+        .await
+    }
+
+    async fn save_override(&self, point_override: &PointOverride) -> Result<()> {
+        let (channel_id, point_id) = (point_override.channel_id, point_override.point_id);
+        let record = serde_json::to_string(point_override).map_err(json_error)?;
+        self.blocking(move |inner| {
+            inner
+                .conn()
+                .execute(
+                    "INSERT OR REPLACE INTO point_overrides (channel_id, point_id, record)
+                     VALUES (?1, ?2, ?3)",
+                    params![channel_id, point_id, record],
+                )
+                .map(|_| ())
+                .map_err(db_error)
+        })
+        .await
+    }
+
+    async fn delete_override(&self, channel_id: u32, point_id: PointId) -> Result<()> {
+        self.blocking(move |inner| {
+            inner
+                .conn()
+                .execute(
+                    "DELETE FROM point_overrides WHERE channel_id = ?1 AND point_id = ?2",
+                    params![channel_id, point_id],
+                )
+                .map(|_| ())
+                .map_err(db_error)
+        })
+        .await
+    }
+
+    async fn load_overrides(&self) -> Result<Vec<PointOverride>> {
+        self.blocking(|inner| {
+            let conn = inner.conn();
+            let mut stmt = conn
+                .prepare_cached("SELECT record FROM point_overrides ORDER BY channel_id, point_id")
+                .map_err(db_error)?;
+            let rows = stmt
+                .query_map([], |row| row.get::<_, String>(0))
+                .map_err(db_error)?;
+
+            let mut overrides = Vec::new();
+            for row in rows {
+                overrides.push(serde_json::from_str(&row.map_err(db_error)?).map_err(json_error)?);
+            }
+            Ok(overrides)
+        })
+        .await
+    }
+
+    async fn flush(&self) -> Result<()> {
+        self.blocking(|inner| inner.flush()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::point::{ProtocolAddress, VirtualAddress};
+
+    /// Unique database path in the system temp dir, removed on drop.
+    struct TempDb(std::path::PathBuf);
+
+    impl TempDb {
+        fn new(name: &str) -> Self {
+            let nanos = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos();
+            Self(std::env::temp_dir().join(format!(
+                "igw-{}-{}-{}.db",
+                name,
+                std::process::id(),
+                nanos
+            )))
+        }
+    }
+
+    impl Drop for TempDb {
+        fn drop(&mut self) {
+            for suffix in ["", "-wal", "-shm"] {
+                let _ = std::fs::remove_file(format!("{}{}", self.0.display(), suffix));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_write_behind_and_read() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        let batch = DataBatch::from_points(vec![
+            DataPoint::new(1, 1.5),
+            DataPoint::new(2, true).with_quality(Quality::Uncertain),
+        ]);
+        store.write_batch(7, &batch).await.unwrap();
+        assert_eq!(store.pending_count(), 2);
+
+        // Buffered values are visible before they hit the disk
+        assert_eq!(store.read_all(7).await.unwrap().len(), 2);
+
+        store.flush().await.unwrap();
+        assert_eq!(store.pending_count(), 0);
+
+        let point = store.read(7, 2).await.unwrap().unwrap();
+        assert_eq!(point.value, Value::Bool(true));
+        assert_eq!(point.quality, Quality::Uncertain);
+        assert_eq!(store.read_points(7, &[1, 9]).await.unwrap().len(), 1);
+        assert!(store.read(8, 1).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_restart_recovery() {
+        let db = TempDb::new("restart");
+        let points = vec![
+            PointConfig::new(1, ProtocolAddress::Virtual(VirtualAddress::new("a"))).with_unit("°C"),
+        ];
+
+        {
+            let store = SqliteStore::open(&db.0).unwrap();
+            store.set_point_configs(3, &points).await.unwrap();
+            store
+                .write_batch(3, &DataBatch::from_points(vec![DataPoint::new(1, 21.5)]))
+                .await
+                .unwrap();
+            store
+                .write_batch(3, &DataBatch::from_points(vec![DataPoint::new(1, 22.0)]))
+                .await
+                .unwrap();
+            // Dropped without an explicit flush
+        }
+
+        let store = SqliteStore::open(&db.0).unwrap();
+        assert_eq!(store.channels().await.unwrap(), vec![3]);
+
+        let last_known = store.last_known(3).await.unwrap();
+        let point = last_known.iter().next().unwrap();
+        assert_eq!(point.value, Value::Float(22.0));
+        assert_eq!(point.quality, Quality::LastKnown);
+
+        let meta = store.point_meta(3, 1).await.unwrap().unwrap();
+        assert_eq!(meta.unit.as_deref(), Some("°C"));
+    }
+
+    #[tokio::test]
+    async fn test_overrides_survive_reopen() {
+        use crate::gateway::{Caller, PermissionLevel, PointOverride};
+
+        let db = TempDb::new("overrides");
+        let caller = Caller::new("fat", PermissionLevel::Tune);
+        let now = chrono::Utc::now();
+        let kept = PointOverride::new(&caller, 3, 1, 42.0, now)
+            .with_expiry(now + chrono::Duration::hours(1));
+        {
+            let store = SqliteStore::open(&db.0).unwrap();
+            store
+                .save_override(&PointOverride::new(&caller, 3, 2, true, now))
+                .await
+                .unwrap();
+            store.save_override(&kept).await.unwrap();
+            store.delete_override(3, 2).await.unwrap();
+        }
+
+        let store = SqliteStore::open(&db.0).unwrap();
+        assert_eq!(store.load_overrides().await.unwrap(), vec![kept]);
+    }
+
+    #[tokio::test]
+    async fn test_command_audit() {
+        use crate::core::traits::WriteResult;
+        use crate::gateway::{Caller, CommandKind, PermissionLevel};
+
+        let db = TempDb::new("audit");
+        let caller = Caller::new("scada", PermissionLevel::Operate);
+        let now = Utc::now();
+        let records: Vec<CommandAudit> = [(1, 30), (2, 20), (1, 10)]
+            .into_iter()
+            .map(|(channel_id, days_ago)| {
+                let mut record = CommandAudit::new(
+                    &caller,
+                    channel_id,
+                    CommandKind::Control,
+                    &[(4, 1.0)],
+                    &Ok(WriteResult::success(1)),
+                );
+                record.timestamp = now - chrono::Duration::days(days_ago);
+                record
+            })
+            .collect();
+
+        {
+            let store = SqliteStore::open(&db.0).unwrap();
+            store.append_audit(&records).await.unwrap();
+        }
+
+        // Records survive a restart
+        let store = SqliteStore::open(&db.0).unwrap();
+        let all = store.query_audit(&AuditQuery::new()).await.unwrap();
+        assert_eq!(all, {
+            let mut sorted = records.clone();
+            sorted.sort_by_key(|r| r.timestamp);
+            sorted
+        });
+
+        let channel = store
+            .query_audit(&AuditQuery::new().with_channel(1))
+            .await
+            .unwrap();
+        assert_eq!(channel.len(), 2);
+        let range = AuditQuery::new().with_range(now - chrono::Duration::days(25), now);
+        let recent = store.query_audit(&range).await.unwrap();
+        assert_eq!(recent.len(), 2);
+        let first = store
+            .query_audit(&AuditQuery::new().with_limit(1))
+            .await
+            .unwrap();
+        assert_eq!(first[0].timestamp, records[0].timestamp);
+
+        let cutoff = now - chrono::Duration::days(15);
+        assert_eq!(store.prune_audit(cutoff).await.unwrap(), 2);
+        assert_eq!(
+            store.query_audit(&AuditQuery::new()).await.unwrap().len(),
+            1
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_channels() {
+        let db = TempDb::new("concurrent");
+        let store = SqliteStore::open_with_config(
+            &db.0,
+            SqliteStoreConfig {
+                max_pending: 16,
+                flush_interval: Duration::from_millis(5),
+            },
+        )
+        .unwrap();
+
+        let mut tasks = Vec::new();
+        for channel_id in 0..4u32 {
+            let store = store.clone();
+            tasks.push(tokio::spawn(async move {
+                for round in 0..50 {
+                    let batch: DataBatch = (0..20)
+                        .map(|id| DataPoint::new(id, (channel_id * 1000 + round) as f64))
+                        .collect();
+                    store.write_batch(channel_id, &batch).await.unwrap();
+                    if round % 10 == 0 {
+                        store.read_all(channel_id).await.unwrap();
+                    }
+                }
+            }));
+        }
+        for task in tasks {
+            task.await.unwrap();
+        }
+        store.flush().await.unwrap();
+
+        for channel_id in 0..4u32 {
+            let batch = store.read_all(channel_id).await.unwrap();
+            assert_eq!(batch.len(), 20);
+            let expected = (channel_id * 1000 + 49) as f64;
+            assert!(batch.iter().all(|p| p.value.as_f64() == Some(expected)));
+        }
+    }
+}
@@ -0,0 +1,233 @@
+//! Whole-store snapshots for diagnostics and cold starts.
+//!
+//! [`DataStore::export_snapshot()`](super::DataStore::export_snapshot)
+//! captures every channel of a store, with its point configurations and the
+//! latest value of each point, as a [`StoreSnapshot`] that serializes to
+//! JSON:
+//!
+//! ```text
+//! {
+//!   "format_version": 1,
+//!   "igw_version": "0.2.14",
+//!   "created_at": "2024-05-01T03:00:00Z",
+//!   "channels": [
+//!     { "channel_id": 1, "points": [{ "id": 1001, "address": "...", ... }],
+//!       "values": [{ "id": 1001, "value": 21.5, "quality": "good", "timestamp": "..." }] }
+//!   ]
+//! }
+//! ```
+//!
+//! [`DataStore::import_snapshot()`](super::DataStore::import_snapshot)
+//! loads a snapshot into another store, e.g. to pre-seed a replacement
+//! unit with the last values of the old one. Imported values are marked
+//! `Quality::LastKnown`.
+//!
+//! `format_version` is bumped whenever the layout changes incompatibly;
+//! snapshots written by a newer format are refused on import.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::core::data::DataPoint;
+use crate::core::error::{GatewayError, Result};
+use crate::core::point::PointConfig;
+
+/// Snapshot format written by this version of igw.
+pub const SNAPSHOT_FORMAT_VERSION: u32 = 1;
+
+/// Point configurations and latest values of every channel of a store.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoreSnapshot {
+    /// Layout version, see [`SNAPSHOT_FORMAT_VERSION`].
+    pub format_version: u32,
+
+    /// igw version that wrote the snapshot.
+    #[serde(default)]
+    pub igw_version: String,
+
+    /// When the snapshot was taken.
+    pub created_at: DateTime<Utc>,
+
+    /// Channels, by ascending id.
+    #[serde(default)]
+    pub channels: Vec<ChannelSnapshot>,
+}
+
+/// One channel of a [`StoreSnapshot`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChannelSnapshot {
+    /// Channel id.
+    pub channel_id: u32,
+
+    /// Point configurations.
+    #[serde(default)]
+    pub points: Vec<PointConfig>,
+
+    /// Latest values, by ascending point id.
+    #[serde(default)]
+    pub values: Vec<DataPoint>,
+}
+
+impl StoreSnapshot {
+    /// Snapshot of `channels` taken now, in the current format.
+    pub fn new(channels: Vec<ChannelSnapshot>) -> Self {
+        Self {
+            format_version: SNAPSHOT_FORMAT_VERSION,
+            igw_version: env!("CARGO_PKG_VERSION").to_string(),
+            created_at: Utc::now(),
+            channels,
+        }
+    }
+
+    /// Parse a JSON snapshot.
+    ///
+    /// The format version is checked before the rest of the document, so a
+    /// snapshot of a newer layout is reported as such rather than as a
+    /// parse error.
+    pub fn from_json(json: &str) -> Result<Self> {
+        /// Fields every format version has.
+        #[derive(Deserialize)]
+        struct Header {
+            format_version: u32,
+            #[serde(default)]
+            igw_version: String,
+        }
+
+        let invalid = |e: serde_json::Error| GatewayError::InvalidData(format!("snapshot: {}", e));
+        let header: Header = serde_json::from_str(json).map_err(invalid)?;
+        check_version(header.format_version, &header.igw_version)?;
+        serde_json::from_str(json).map_err(invalid)
+    }
+
+    /// Check that this version of igw can import the snapshot.
+    pub fn check_compatible(&self) -> Result<()> {
+        check_version(self.format_version, &self.igw_version)
+    }
+
+    /// Total number of point values.
+    pub fn value_count(&self) -> usize {
+        self.channels.iter().map(|c| c.values.len()).sum()
+    }
+}
+
+fn check_version(format_version: u32, igw_version: &str) -> Result<()> {
+    if (1..=SNAPSHOT_FORMAT_VERSION).contains(&format_version) {
+        return Ok(());
+    }
+    Err(GatewayError::Unsupported(format!(
+        "snapshot format version {} (written by igw {}) is not supported, expected 1..={}",
+        format_version,
+        if igw_version.is_empty() {
+            "unknown"
+        } else {
+            igw_version
+        },
+        SNAPSHOT_FORMAT_VERSION
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::data::{DataBatch, Value};
+    use crate::core::point::{ProtocolAddress, VirtualAddress};
+    use crate::core::quality::Quality;
+    use crate::store::{DataStore, MemoryStore};
+
+    fn address() -> ProtocolAddress {
+        ProtocolAddress::Virtual(VirtualAddress::new("t"))
+    }
+
+    #[tokio::test]
+    async fn test_export_import() {
+        let source = MemoryStore::new();
+        source
+            .set_point_configs(2, &[PointConfig::new(1, address()).with_name("voltage")])
+            .await
+            .unwrap();
+        source
+            .write_batch(
+                2,
+                &DataBatch::from_points(vec![DataPoint::new(2, 7i64), DataPoint::new(1, 230.5)]),
+            )
+            .await
+            .unwrap();
+        source
+            .write_batch(1, &DataBatch::from_points(vec![DataPoint::new(5, true)]))
+            .await
+            .unwrap();
+
+        let snapshot = source.export_snapshot().await.unwrap();
+        assert_eq!(snapshot.format_version, SNAPSHOT_FORMAT_VERSION);
+        let ids: Vec<u32> = snapshot.channels.iter().map(|c| c.channel_id).collect();
+        assert_eq!(ids, vec![1, 2]);
+        let points: Vec<u32> = snapshot.channels[1].values.iter().map(|p| p.id).collect();
+        assert_eq!(points, vec![1, 2]);
+        assert_eq!(snapshot.value_count(), 3);
+
+        // Round trip through JSON
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let snapshot: StoreSnapshot = serde_json::from_str(&json).unwrap();
+
+        // Newer values in the target store win over the snapshot.
+        let target = MemoryStore::new();
+        target
+            .write_batch(2, &DataBatch::from_points(vec![DataPoint::new(2, 8i64)]))
+            .await
+            .unwrap();
+        assert_eq!(target.import_snapshot(&snapshot).await.unwrap(), 2);
+
+        let voltage = target.read(2, 1).await.unwrap().unwrap();
+        assert_eq!(voltage.value, Value::Float(230.5));
+        assert_eq!(voltage.quality, Quality::LastKnown);
+        let live = target.read(2, 2).await.unwrap().unwrap();
+        assert_eq!(live.value, Value::Integer(8));
+        assert_eq!(live.quality, Quality::Good);
+        assert_eq!(
+            target.read(1, 5).await.unwrap().unwrap().value,
+            Value::Bool(true)
+        );
+        let configs = target.point_configs(2).await.unwrap();
+        assert_eq!(configs[0].name.as_deref(), Some("voltage"));
+
+        // Point configs already present are kept.
+        let configured = MemoryStore::new();
+        configured
+            .set_point_configs(2, &[PointConfig::new(9, address())])
+            .await
+            .unwrap();
+        configured.import_snapshot(&snapshot).await.unwrap();
+        let ids: Vec<u32> = configured
+            .point_configs(2)
+            .await
+            .unwrap()
+            .iter()
+            .map(|p| p.id)
+            .collect();
+        assert_eq!(ids, vec![9]);
+    }
+
+    #[tokio::test]
+    async fn test_incompatible_version() {
+        let mut snapshot = StoreSnapshot::new(Vec::new());
+        snapshot.format_version = SNAPSHOT_FORMAT_VERSION + 1;
+        snapshot.igw_version = "9.0.0".into();
+        let error = MemoryStore::new()
+            .import_snapshot(&snapshot)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, GatewayError::Unsupported(_)));
+        assert!(error.to_string().contains("written by igw 9.0.0"));
+
+        snapshot.format_version = 0;
+        assert!(snapshot.check_compatible().is_err());
+
+        // The version is checked before the layout.
+        let error =
+            StoreSnapshot::from_json(r#"{"format_version": 2, "channels": {}}"#).unwrap_err();
+        assert!(matches!(error, GatewayError::Unsupported(_)));
+        let error =
+            StoreSnapshot::from_json(r#"{"format_version": 1, "channels": {}}"#).unwrap_err();
+        assert!(matches!(error, GatewayError::InvalidData(_)));
+    }
+}
@@ -0,0 +1,220 @@
+//! Change notifications for stored points.
+//!
+//! [`ChangeTracker`] is embedded by store backends. On every write it
+//! compares incoming values with the last value reported to watchers and
+//! publishes only real changes on a per-channel [`EventBus`]:
+//!
+//! - a quality change is always a change;
+//! - numeric values change when they move more than the point's
+//!   [`TransformConfig::deadband`](crate::core::point::TransformConfig::deadband)
+//!   away from the last reported value (any difference without a deadband);
+//! - other values change when they are not equal.
+//!
+//! Nothing is tracked for channels nobody watches, so writes stay cheap.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use dashmap::DashMap;
+
+use crate::core::data::{DataBatch, DataPoint, PointId, Value};
+use crate::core::event::{DataEventReceiver, EventBus};
+use crate::core::point::PointConfig;
+use crate::core::quality::Quality;
+use crate::core::traits::DataEvent;
+
+/// Receiver for changes of watched points.
+///
+/// Created by [`DataStore::watch()`](super::DataStore::watch).
+#[derive(Debug)]
+pub struct PointWatch {
+    rx: DataEventReceiver,
+    /// Watched points; `None` watches the whole channel.
+    point_ids: Option<HashSet<PointId>>,
+}
+
+impl PointWatch {
+    /// Wait for the next change.
+    ///
+    /// Yields `DataEvent::DataUpdate` with the changed watched points, or
+    /// `DataEvent::Error` if this watcher lagged behind (see
+    /// [`EventBus`] for lag semantics). Returns `None` once the store is
+    /// dropped.
+    pub async fn recv(&mut self) -> Option<DataEvent> {
+        loop {
+            match self.rx.recv().await? {
+                DataEvent::DataUpdate(batch) => {
+                    if let Some(batch) = self.filter(batch) {
+                        return Some(DataEvent::DataUpdate(batch));
+                    }
+                }
+                other => return Some(other),
+            }
+        }
+    }
+
+    fn filter(&self, batch: Arc<DataBatch>) -> Option<Arc<DataBatch>> {
+        let batch = match &self.point_ids {
+            Some(ids) => Arc::new(
+                batch
+                    .iter()
+                    .filter(|p| ids.contains(&p.id))
+                    .cloned()
+                    .collect(),
+            ),
+            None => batch,
+        };
+        (!batch.is_empty()).then_some(batch)
+    }
+}
+
+/// Per-store change detection and watcher fan-out.
+#[derive(Debug, Default)]
+pub(crate) struct ChangeTracker {
+    buses: DashMap<u32, EventBus>,
+    /// Last value/quality reported to watchers.
+    reported: DashMap<(u32, PointId), (Value, Quality)>,
+    deadbands: DashMap<(u32, PointId), f64>,
+}
+
+impl ChangeTracker {
+    /// Watch `point_ids` of a channel (all points if empty).
+    pub(crate) fn watch(&self, channel_id: u32, point_ids: &[PointId]) -> PointWatch {
+        let rx = self.buses.entry(channel_id).or_default().subscribe();
+        PointWatch {
+            rx,
+            point_ids: (!point_ids.is_empty()).then(|| point_ids.iter().copied().collect()),
+        }
+    }
+
+    /// Take deadbands from the channel's point configurations.
+    pub(crate) fn set_point_configs(&self, channel_id: u32, points: &[PointConfig]) {
+        self.deadbands
+            .retain(|(channel, _), _| *channel != channel_id);
+        for point in points {
+            if let Some(deadband) = point.transform.deadband.filter(|d| *d > 0.0) {
+                self.deadbands.insert((channel_id, point.id), deadband);
+            }
+        }
+    }
+
+    /// Publish the changed points of a written batch.
+    pub(crate) fn process(&self, channel_id: u32, batch: &DataBatch) {
+        let Some(bus) = self.buses.get(&channel_id) else {
+            return;
+        };
+        if bus.subscriber_count() == 0 {
+            return;
+        }
+
+        let changed: DataBatch = batch
+            .iter()
+            .filter(|point| self.is_change(channel_id, point))
+            .cloned()
+            .collect();
+        if !changed.is_empty() {
+            bus.publish(DataEvent::DataUpdate(Arc::new(changed)));
+        }
+    }
+
+    fn is_change(&self, channel_id: u32, point: &DataPoint) -> bool {
+        let key = (channel_id, point.id);
+        let changed = match self.reported.get(&key) {
+            None => true,
+            Some(last) => {
+                let (value, quality) = last.value();
+                *quality != point.quality || self.value_changed(key, value, &point.value)
+            }
+        };
+        if changed {
+            self.reported
+                .insert(key, (point.value.clone(), point.quality));
+        }
+        changed
+    }
+
+    fn value_changed(&self, key: (u32, PointId), last: &Value, new: &Value) -> bool {
+        let numeric = !matches!(last, Value::Bool(_)) && !matches!(new, Value::Bool(_));
+        match (last.as_f64(), new.as_f64()) {
+            (Some(a), Some(b)) if numeric => match self.deadbands.get(&key) {
+                Some(deadband) => (b - a).abs() > *deadband,
+                None => a != b,
+            },
+            _ => last != new,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::point::{ProtocolAddress, TransformConfig, VirtualAddress};
+
+    fn write(tracker: &ChangeTracker, points: Vec<DataPoint>) {
+        tracker.process(1, &DataBatch::from_points(points));
+    }
+
+    fn next_ids(watch: &mut PointWatch) -> Vec<PointId> {
+        match watch.rx.try_recv() {
+            Some(DataEvent::DataUpdate(batch)) => watch
+                .filter(batch)
+                .map(|b| b.iter().map(|p| p.id).collect())
+                .unwrap_or_default(),
+            _ => Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_only_changes_are_reported() {
+        let tracker = ChangeTracker::default();
+        let mut watch = tracker.watch(1, &[1, 2]);
+
+        write(
+            &tracker,
+            vec![DataPoint::new(1, 1.0), DataPoint::new(3, 1.0)],
+        );
+        assert_eq!(next_ids(&mut watch), vec![1]);
+
+        // Identical poll write: nothing
+        write(&tracker, vec![DataPoint::new(1, 1.0)]);
+        assert!(watch.rx.try_recv().is_none());
+
+        // Quality change is a change
+        write(
+            &tracker,
+            vec![DataPoint::new(1, 1.0).with_quality(Quality::CommFailure)],
+        );
+        assert_eq!(next_ids(&mut watch), vec![1]);
+    }
+
+    #[test]
+    fn test_deadband() {
+        let tracker = ChangeTracker::default();
+        let point = PointConfig::new(1, ProtocolAddress::Virtual(VirtualAddress::new("a")))
+            .with_transform(TransformConfig {
+                deadband: Some(0.5),
+                ..Default::default()
+            });
+        tracker.set_point_configs(1, &[point]);
+        let mut watch = tracker.watch(1, &[]);
+
+        write(&tracker, vec![DataPoint::new(1, 10.0)]);
+        assert_eq!(next_ids(&mut watch), vec![1]);
+
+        // Jitter within the deadband of the last reported value
+        write(&tracker, vec![DataPoint::new(1, 10.3)]);
+        write(&tracker, vec![DataPoint::new(1, 9.6)]);
+        assert!(watch.rx.try_recv().is_none());
+
+        // Drift accumulates against the last reported value
+        write(&tracker, vec![DataPoint::new(1, 10.6)]);
+        assert_eq!(next_ids(&mut watch), vec![1]);
+    }
+
+    #[test]
+    fn test_no_tracking_without_watchers() {
+        let tracker = ChangeTracker::default();
+        write(&tracker, vec![DataPoint::new(1, 1.0)]);
+        assert!(tracker.reported.is_empty());
+    }
+}
@@ -0,0 +1,647 @@
+//! Test doubles for applications embedding igw.
+//!
+//! [`MockClient`] is a protocol client without a protocol: polls answer
+//! from a script, connects can be made to fail or stall, the connection
+//! can be dropped mid-run and every command written to it is recorded.
+//! It implements [`ProtocolClient`], [`EventDrivenProtocol`] and
+//! [`ChannelRuntime`], so it can stand in for a real channel anywhere,
+//! including inside a [`GatewayRuntime`](crate::gateway::GatewayRuntime).
+//!
+//! The client is usually moved into the code under test; a [`MockHandle`]
+//! taken beforehand keeps scripting and inspecting it.
+//!
+//! ```rust,ignore
+//! use igw::testing::MockClient;
+//!
+//! let client = MockClient::new().with_id(1);
+//! let mock = client.handle();
+//! mock.push_batch(DataBatch::from_points(vec![DataPoint::new(10, 1.5)]));
+//! mock.fail_connects(2);
+//!
+//! let mut channel: Box<dyn ChannelRuntime> = Box::new(client);
+//! // ... run the code under test ...
+//! assert_eq!(mock.connects(), 3);
+//! assert_eq!(mock.controls()[0].id, 10);
+//! ```
+//!
+//! Requires the `test-util` feature.
+
+use std::collections::{HashSet, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+use crate::core::data::{DataBatch, PointId};
+use crate::core::error::{GatewayError, Result};
+use crate::core::event::{DataEventReceiver, EventBus};
+use crate::core::traits::{
+    AdjustmentCommand, CommunicationMode, ConnectionState, ControlCommand, DataEvent,
+    DataEventHandler, Diagnostics, EventDrivenProtocol, PointFailure, PollResult, Protocol,
+    ProtocolCapabilities, ProtocolClient, WriteResult,
+};
+use crate::gateway::{ChannelCapabilities, ChannelRuntime};
+
+/// A call made on a [`MockClient`], in the order received.
+#[derive(Debug, Clone)]
+pub enum MockCall {
+    /// `connect()`, successful or not.
+    Connect,
+    /// `disconnect()`.
+    Disconnect,
+    /// `poll_once()`.
+    Poll,
+    /// `write_control()`.
+    Control(Vec<ControlCommand>),
+    /// `write_adjustment()`.
+    Adjustment(Vec<AdjustmentCommand>),
+    /// `EventDrivenProtocol::start()`.
+    Start,
+    /// `EventDrivenProtocol::stop()`.
+    Stop,
+    /// `ChannelRuntime::read_history()` of these points.
+    ReadHistory(Vec<PointId>),
+    /// `ChannelRuntime::refresh()`.
+    Refresh,
+}
+
+/// Scriptable protocol client, see the [module docs](self).
+pub struct MockClient {
+    id: u32,
+    name: String,
+    event_driven: bool,
+    shared: Arc<Shared>,
+}
+
+/// Handle for scripting and inspecting a [`MockClient`] after it was moved.
+#[derive(Clone)]
+pub struct MockHandle {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    state: Mutex<MockState>,
+    events: EventBus,
+}
+
+struct MockState {
+    /// Scripted poll results, consumed in order.
+    polls: VecDeque<PollResult>,
+    /// Answer once the script is exhausted.
+    steady: DataBatch,
+    /// Samples served by `read_history()`.
+    history: DataBatch,
+    connect_failures: usize,
+    reachable: bool,
+    connected: bool,
+    connect_latency: Duration,
+    poll_latency: Duration,
+    hang_polls: bool,
+    panic_polls: bool,
+    hang_disconnect: bool,
+    rejected: HashSet<PointId>,
+    calls: Vec<MockCall>,
+    handler: Option<Arc<dyn DataEventHandler>>,
+}
+
+impl MockClient {
+    /// A polling client with id 0 that connects and answers every poll
+    /// with an empty batch.
+    pub fn new() -> Self {
+        Self {
+            id: 0,
+            name: "mock".into(),
+            event_driven: false,
+            shared: Arc::new(Shared {
+                state: Mutex::new(MockState {
+                    polls: VecDeque::new(),
+                    steady: DataBatch::new(),
+                    history: DataBatch::new(),
+                    connect_failures: 0,
+                    reachable: true,
+                    connected: false,
+                    connect_latency: Duration::ZERO,
+                    poll_latency: Duration::ZERO,
+                    hang_polls: false,
+                    panic_polls: false,
+                    hang_disconnect: false,
+                    rejected: HashSet::new(),
+                    calls: Vec::new(),
+                    handler: None,
+                }),
+                events: EventBus::default(),
+            }),
+        }
+    }
+
+    /// Set the channel id reported through [`ChannelRuntime`].
+    pub fn with_id(mut self, id: u32) -> Self {
+        self.id = id;
+        self
+    }
+
+    /// Set the channel name reported through [`ChannelRuntime`].
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Report the channel as event-driven, so runtimes subscribe to it
+    /// instead of polling. Feed it with [`MockHandle::emit()`].
+    pub fn event_driven(mut self) -> Self {
+        self.event_driven = true;
+        self
+    }
+
+    /// Delay every `connect()` by `latency`.
+    pub fn with_connect_latency(self, latency: Duration) -> Self {
+        self.shared.lock().connect_latency = latency;
+        self
+    }
+
+    /// Delay every `poll_once()` by `latency`.
+    pub fn with_poll_latency(self, latency: Duration) -> Self {
+        self.shared.lock().poll_latency = latency;
+        self
+    }
+
+    /// Handle to script and inspect this client.
+    pub fn handle(&self) -> MockHandle {
+        MockHandle {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl Default for MockClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockHandle {
+    /// Queue the result of a future poll.
+    pub fn push_poll(&self, result: PollResult) {
+        self.shared.lock().polls.push_back(result);
+    }
+
+    /// Queue a successful poll returning `batch`.
+    pub fn push_batch(&self, batch: DataBatch) {
+        self.push_poll(PollResult::success(batch));
+    }
+
+    /// Answer polls with `batch` once the queued results are used up.
+    pub fn set_steady(&self, batch: DataBatch) {
+        self.shared.lock().steady = batch;
+    }
+
+    /// Serve `samples` from `read_history()`, filtered by point and time
+    /// range.
+    pub fn set_history(&self, samples: DataBatch) {
+        self.shared.lock().history = samples;
+    }
+
+    /// Make the next `count` connects fail with a connection error.
+    pub fn fail_connects(&self, count: usize) {
+        self.shared.lock().connect_failures = count;
+    }
+
+    /// Make the device unreachable (dropping the connection) or reachable
+    /// again. Connects fail while it is unreachable.
+    pub fn set_reachable(&self, reachable: bool) {
+        self.shared.lock().reachable = reachable;
+        if !reachable {
+            self.drop_connection();
+        }
+    }
+
+    /// Drop the connection now; the next connect succeeds again.
+    ///
+    /// Polls fail until then, and subscribers get
+    /// `DataEvent::ConnectionChanged(Disconnected)`.
+    pub fn drop_connection(&self) {
+        let was_connected = std::mem::replace(&mut self.shared.lock().connected, false);
+        if was_connected {
+            self.shared
+                .events
+                .publish(DataEvent::ConnectionChanged(ConnectionState::Disconnected));
+        }
+    }
+
+    /// Delay every later `connect()` by `latency`.
+    pub fn set_connect_latency(&self, latency: Duration) {
+        self.shared.lock().connect_latency = latency;
+    }
+
+    /// Delay every later `poll_once()` by `latency`.
+    pub fn set_poll_latency(&self, latency: Duration) {
+        self.shared.lock().poll_latency = latency;
+    }
+
+    /// Make `poll_once()` never return (e.g. to trip a watchdog).
+    pub fn hang_polls(&self, hang: bool) {
+        self.shared.lock().hang_polls = hang;
+    }
+
+    /// Make `poll_once()` panic, as a buggy decoder would.
+    pub fn panic_polls(&self, panic: bool) {
+        self.shared.lock().panic_polls = panic;
+    }
+
+    /// Make `disconnect()` never return.
+    pub fn hang_disconnect(&self, hang: bool) {
+        self.shared.lock().hang_disconnect = hang;
+    }
+
+    /// Fail writes to these points; other writes succeed.
+    pub fn reject_points(&self, ids: impl IntoIterator<Item = PointId>) {
+        self.shared.lock().rejected.extend(ids);
+    }
+
+    /// Push `batch` to subscribers and the event handler, as an
+    /// event-driven device would.
+    pub async fn emit(&self, batch: DataBatch) {
+        let batch = Arc::new(batch);
+        let handler = self.shared.lock().handler.clone();
+        self.shared
+            .events
+            .publish(DataEvent::DataUpdate(Arc::clone(&batch)));
+        if let Some(handler) = handler {
+            handler.on_data_update(batch).await;
+        }
+    }
+
+    /// Whether the client is currently connected.
+    pub fn is_connected(&self) -> bool {
+        self.shared.lock().connected
+    }
+
+    /// Every call made so far.
+    pub fn calls(&self) -> Vec<MockCall> {
+        self.shared.lock().calls.clone()
+    }
+
+    /// Number of `connect()` calls, failed ones included.
+    pub fn connects(&self) -> usize {
+        self.count(|call| matches!(call, MockCall::Connect))
+    }
+
+    /// Number of `poll_once()` calls.
+    pub fn polls(&self) -> usize {
+        self.count(|call| matches!(call, MockCall::Poll))
+    }
+
+    /// All control commands written, in order.
+    pub fn controls(&self) -> Vec<ControlCommand> {
+        let state = self.shared.lock();
+        let commands = state.calls.iter().filter_map(|call| match call {
+            MockCall::Control(commands) => Some(commands.iter().cloned()),
+            _ => None,
+        });
+        commands.flatten().collect()
+    }
+
+    /// All adjustment commands written, in order.
+    pub fn adjustments(&self) -> Vec<AdjustmentCommand> {
+        let state = self.shared.lock();
+        let commands = state.calls.iter().filter_map(|call| match call {
+            MockCall::Adjustment(commands) => Some(commands.iter().cloned()),
+            _ => None,
+        });
+        commands.flatten().collect()
+    }
+
+    fn count(&self, f: impl Fn(&MockCall) -> bool) -> usize {
+        self.shared
+            .lock()
+            .calls
+            .iter()
+            .filter(|call| f(call))
+            .count()
+    }
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn record(&self, call: MockCall) {
+        self.lock().calls.push(call);
+    }
+
+    fn write_result(&self, ids: impl Iterator<Item = PointId>) -> WriteResult {
+        let state = self.lock();
+        let mut result = WriteResult::success(0);
+        for id in ids {
+            if !state.connected {
+                result.failures.push((id, "not connected".into()));
+            } else if state.rejected.contains(&id) {
+                result.failures.push((id, "rejected by mock".into()));
+            } else {
+                result.success_count += 1;
+            }
+        }
+        result
+    }
+}
+
+impl ProtocolCapabilities for MockClient {
+    fn name(&self) -> &'static str {
+        "Mock"
+    }
+
+    fn supported_modes(&self) -> &[CommunicationMode] {
+        &[CommunicationMode::Polling, CommunicationMode::EventDriven]
+    }
+}
+
+impl Protocol for MockClient {
+    fn connection_state(&self) -> ConnectionState {
+        if self.shared.lock().connected {
+            ConnectionState::Connected
+        } else {
+            ConnectionState::Disconnected
+        }
+    }
+
+    async fn diagnostics(&self) -> Result<Diagnostics> {
+        let state = self.shared.lock();
+        let mut diagnostics = Diagnostics::new("mock");
+        diagnostics.connection_state = if state.connected {
+            ConnectionState::Connected
+        } else {
+            ConnectionState::Disconnected
+        };
+        diagnostics.read_count = state
+            .calls
+            .iter()
+            .filter(|call| matches!(call, MockCall::Poll))
+            .count() as u64;
+        Ok(diagnostics)
+    }
+}
+
+impl ProtocolClient for MockClient {
+    async fn connect(&mut self) -> Result<()> {
+        let latency = {
+            let mut state = self.shared.lock();
+            state.calls.push(MockCall::Connect);
+            state.connect_latency
+        };
+        tokio::time::sleep(latency).await;
+
+        let mut state = self.shared.lock();
+        if !state.reachable {
+            return Err(GatewayError::connection("mock device unreachable"));
+        }
+        if state.connect_failures > 0 {
+            state.connect_failures -= 1;
+            return Err(GatewayError::connection("mock connect failure"));
+        }
+        state.connected = true;
+        Ok(())
+    }
+
+    async fn disconnect(&mut self) -> Result<()> {
+        let hang = {
+            let mut state = self.shared.lock();
+            state.calls.push(MockCall::Disconnect);
+            state.hang_disconnect
+        };
+        if hang {
+            std::future::pending::<()>().await;
+        }
+        self.shared.lock().connected = false;
+        Ok(())
+    }
+
+    async fn poll_once(&mut self) -> PollResult {
+        let (latency, hang, panic) = {
+            let mut state = self.shared.lock();
+            state.calls.push(MockCall::Poll);
+            (state.poll_latency, state.hang_polls, state.panic_polls)
+        };
+        if panic {
+            panic!("mock poll panicked");
+        }
+        if hang {
+            std::future::pending::<()>().await;
+        }
+        tokio::time::sleep(latency).await;
+
+        let mut state = self.shared.lock();
+        if !state.connected {
+            // At least one failure, so callers check the connection state
+            let mut failures: Vec<_> = state
+                .steady
+                .iter()
+                .map(|p| PointFailure::new(p.id, "not connected"))
+                .collect();
+            if failures.is_empty() {
+                failures.push(PointFailure::new(0, "not connected"));
+            }
+            return PollResult::failed(failures);
+        }
+        match state.polls.pop_front() {
+            Some(result) => result,
+            None => PollResult::success(state.steady.clone()),
+        }
+    }
+
+    async fn write_control(&mut self, commands: &[ControlCommand]) -> Result<WriteResult> {
+        self.shared.record(MockCall::Control(commands.to_vec()));
+        Ok(self.shared.write_result(commands.iter().map(|c| c.id)))
+    }
+
+    async fn write_adjustment(&mut self, adjustments: &[AdjustmentCommand]) -> Result<WriteResult> {
+        self.shared
+            .record(MockCall::Adjustment(adjustments.to_vec()));
+        Ok(self.shared.write_result(adjustments.iter().map(|a| a.id)))
+    }
+}
+
+impl EventDrivenProtocol for MockClient {
+    fn subscribe(&self) -> DataEventReceiver {
+        self.shared.events.subscribe()
+    }
+
+    fn set_event_handler(&mut self, handler: Arc<dyn DataEventHandler>) {
+        self.shared.lock().handler = Some(handler);
+    }
+
+    async fn start(&mut self) -> Result<()> {
+        self.shared.record(MockCall::Start);
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> Result<()> {
+        self.shared.record(MockCall::Stop);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ChannelRuntime for MockClient {
+    fn id(&self) -> u32 {
+        self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn protocol(&self) -> &str {
+        "mock"
+    }
+
+    fn is_event_driven(&self) -> bool {
+        self.event_driven
+    }
+
+    fn capabilities(&self) -> ChannelCapabilities {
+        ChannelCapabilities::of(self)
+    }
+
+    async fn connect(&mut self) -> Result<()> {
+        ProtocolClient::connect(self).await
+    }
+
+    async fn disconnect(&mut self) -> Result<()> {
+        ProtocolClient::disconnect(self).await
+    }
+
+    async fn poll_once(&mut self) -> PollResult {
+        ProtocolClient::poll_once(self).await
+    }
+
+    async fn write_control(&mut self, commands: &[(u32, f64)]) -> Result<WriteResult> {
+        let commands: Vec<_> = commands
+            .iter()
+            .map(|(id, value)| ControlCommand::latching(*id, *value != 0.0))
+            .collect();
+        ProtocolClient::write_control(self, &commands).await
+    }
+
+    async fn write_adjustment(&mut self, adjustments: &[(u32, f64)]) -> Result<WriteResult> {
+        let adjustments: Vec<_> = adjustments
+            .iter()
+            .map(|(id, value)| AdjustmentCommand::new(*id, *value))
+            .collect();
+        ProtocolClient::write_adjustment(self, &adjustments).await
+    }
+
+    fn subscribe(&self) -> Option<DataEventReceiver> {
+        self.event_driven
+            .then(|| EventDrivenProtocol::subscribe(self))
+    }
+
+    async fn start_events(&mut self) -> Result<()> {
+        EventDrivenProtocol::start(self).await
+    }
+
+    async fn stop_events(&mut self) -> Result<()> {
+        EventDrivenProtocol::stop(self).await
+    }
+
+    async fn read_history(
+        &mut self,
+        points: &[PointId],
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<DataBatch> {
+        self.shared.record(MockCall::ReadHistory(points.to_vec()));
+        let state = self.shared.lock();
+        if !state.connected {
+            return Err(GatewayError::NotConnected);
+        }
+        let mut samples: DataBatch = state
+            .history
+            .iter()
+            .filter(|p| points.contains(&p.id))
+            .filter(|p| (start..=end).contains(&p.timestamp))
+            .cloned()
+            .collect();
+        samples.sort_by_timestamp();
+        Ok(samples)
+    }
+
+    async fn refresh(&mut self) -> Result<()> {
+        self.shared.record(MockCall::Refresh);
+        if !self.shared.lock().connected {
+            return Err(GatewayError::NotConnected);
+        }
+        Ok(())
+    }
+
+    async fn diagnostics(&self) -> Result<Diagnostics> {
+        Protocol::diagnostics(self).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::data::DataPoint;
+
+    #[tokio::test]
+    async fn test_scripted_polls_and_connection() {
+        let mut client = MockClient::new();
+        let mock = client.handle();
+        mock.push_batch(DataBatch::from_points(vec![DataPoint::new(1, 1.0)]));
+        mock.push_poll(PollResult::failed(vec![PointFailure::new(1, "timeout")]));
+        mock.set_steady(DataBatch::from_points(vec![DataPoint::new(1, 2.0)]));
+        mock.fail_connects(1);
+
+        assert!(ProtocolClient::connect(&mut client).await.is_err());
+        ProtocolClient::connect(&mut client).await.unwrap();
+        assert_eq!(ProtocolClient::poll_once(&mut client).await.data.len(), 1);
+        assert!(ProtocolClient::poll_once(&mut client).await.has_failures());
+        let steady = ProtocolClient::poll_once(&mut client).await;
+        assert_eq!(steady.data.get(1).unwrap().value.as_f64(), Some(2.0));
+
+        let mut events = EventDrivenProtocol::subscribe(&client);
+        mock.drop_connection();
+        assert!(matches!(
+            events.try_recv(),
+            Some(DataEvent::ConnectionChanged(ConnectionState::Disconnected))
+        ));
+        let failed = ProtocolClient::poll_once(&mut client).await;
+        assert_eq!(failed.failures[0].point_id, 1);
+
+        mock.set_reachable(false);
+        assert!(ProtocolClient::connect(&mut client).await.is_err());
+        mock.set_reachable(true);
+        ProtocolClient::connect(&mut client).await.unwrap();
+        assert_eq!((mock.connects(), mock.polls()), (4, 4));
+    }
+
+    #[tokio::test]
+    async fn test_records_writes() {
+        let mut client = MockClient::new();
+        let mock = client.handle();
+        mock.reject_points([2]);
+        ProtocolClient::connect(&mut client).await.unwrap();
+
+        let result = ProtocolClient::write_control(
+            &mut client,
+            &[
+                ControlCommand::latching(1, true),
+                ControlCommand::latching(2, false),
+            ],
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.success_count, 1);
+        assert_eq!(result.failures[0].0, 2);
+        ChannelRuntime::write_adjustment(&mut client, &[(3, 12.5)])
+            .await
+            .unwrap();
+
+        let controls = mock.controls();
+        assert_eq!((controls[0].id, controls[0].value), (1, true));
+        assert_eq!(controls.len(), 2);
+        assert_eq!(mock.adjustments()[0].value, 12.5);
+    }
+}